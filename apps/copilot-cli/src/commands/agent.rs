@@ -16,6 +16,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Read};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 /// Agent subcommands.
 #[derive(Subcommand)]
@@ -78,6 +81,10 @@ pub struct DecomposeArgs {
     #[arg(long, default_value = "true")]
     detect_boundaries: bool,
 
+    /// Seed for randomized heuristics (same seed, same output)
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
     /// Execution reference for tracing
     #[arg(long)]
     execution_ref: Option<String>,
@@ -89,6 +96,14 @@ pub struct DecomposeArgs {
     /// Complexity hint (low, medium, high, critical)
     #[arg(long)]
     complexity: Option<String>,
+
+    /// Maximum size of the input file or stdin, in bytes
+    #[arg(long, default_value = "10485760")]
+    max_input_bytes: usize,
+
+    /// Seconds to wait for the input to be read in full (0 waits forever)
+    #[arg(long, default_value = "30")]
+    read_timeout_secs: u64,
 }
 
 /// Agent registry entry for listing.
@@ -249,6 +264,8 @@ async fn run_decompose(args: DecomposeArgs, format: &str) -> Result<()> {
         max_tasks: args.max_tasks,
         detect_prerequisites: args.detect_prerequisites,
         detect_boundaries: args.detect_boundaries,
+        seed: args.seed,
+        tag_sample_size: None,
     };
 
     // Create agent (stateless)
@@ -280,15 +297,14 @@ async fn run_decompose(args: DecomposeArgs, format: &str) -> Result<()> {
 fn build_decomposer_input(args: &DecomposeArgs) -> Result<DecomposerInput> {
     // If input file is provided, read from it
     if let Some(ref input_path) = args.input {
+        let timeout = Duration::from_secs(args.read_timeout_secs);
         let content = if input_path == "-" {
-            // Read from stdin
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            buffer
+            read_bounded(io::stdin(), args.max_input_bytes, timeout)
+                .context("Failed to read from stdin")?
         } else {
-            fs::read_to_string(input_path)
+            let file = fs::File::open(input_path)
+                .with_context(|| format!("Failed to read input file: {}", input_path))?;
+            read_bounded(file, args.max_input_bytes, timeout)
                 .with_context(|| format!("Failed to read input file: {}", input_path))?
         };
 
@@ -343,6 +359,51 @@ fn build_decomposer_input(args: &DecomposeArgs) -> Result<DecomposerInput> {
     })
 }
 
+/// Read all of `reader` as UTF-8, failing if it exceeds `max_bytes` or
+/// takes longer than `timeout` (zero for no limit).
+///
+/// Input is never truncated: an oversized or slow input is an error, not a
+/// partial plan. On timeout the reading thread is left blocked; the command
+/// exits with the error shortly after.
+fn read_bounded<R>(reader: R, max_bytes: usize, timeout: Duration) -> Result<String>
+where
+    R: Read + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // One byte past the limit tells an oversized input from one that fits
+        let mut buffer = Vec::new();
+        let result = reader
+            .take(max_bytes as u64 + 1)
+            .read_to_end(&mut buffer)
+            .map(|_| buffer);
+        let _ = tx.send(result);
+    });
+
+    let buffer = if timeout.is_zero() {
+        rx.recv().context("Input reader stopped unexpectedly")?
+    } else {
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => anyhow::bail!(
+                "Timed out after {}s waiting for input (see --read-timeout-secs)",
+                timeout.as_secs_f64()
+            ),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("Input reader stopped unexpectedly")
+            }
+        }
+    }?;
+
+    if buffer.len() > max_bytes {
+        anyhow::bail!(
+            "Input exceeds the maximum size of {} bytes (see --max-input-bytes)",
+            max_bytes
+        );
+    }
+    String::from_utf8(buffer).context("Input is not valid UTF-8")
+}
+
 /// Print DecisionEvent in human-readable format.
 fn print_decision_event_human(event: &copilot_core::DecisionEvent) -> Result<()> {
     println!("{}", "╔══════════════════════════════════════════════════════════════╗".cyan());
@@ -503,3 +564,39 @@ fn format_confidence(confidence: f32) -> colored::ColoredString {
         pct.red()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A reader that never produces any data, like an idle pipe
+    struct Stalled;
+
+    impl Read for Stalled {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(60));
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_oversized_input_is_rejected() {
+        let input = Cursor::new(vec![b'{'; 2048]);
+        let err = read_bounded(input, 1024, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Input exceeds the maximum size of 1024 bytes (see --max-input-bytes)"
+        );
+
+        // An input of exactly the limit fits
+        let input = Cursor::new(b"{}".to_vec());
+        assert_eq!(read_bounded(input, 2, Duration::from_secs(5)).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_stalled_input_times_out() {
+        let err = read_bounded(Stalled, 1024, Duration::from_millis(50)).unwrap_err();
+        assert!(err.to_string().starts_with("Timed out after 0.05s waiting for input"));
+    }
+}
@@ -8,6 +8,7 @@ use copilot_core::CoPilotEngine;
 use copilot_conversation::ConversationManager;
 use copilot_nlp::NlpEngineImpl;
 use copilot_context::{ContextEngineImpl, ContextEngineConfig};
+use copilot_infra::CompositeHealthChecker;
 
 use crate::cli::Args;
 use crate::server::Server;
@@ -21,6 +22,8 @@ pub struct AppState {
     pub conversation_manager: Arc<ConversationManager>,
     /// JWT secret for authentication
     pub jwt_secret: String,
+    /// Health checks of the backing services, which drive load shedding
+    pub health_checker: Arc<CompositeHealthChecker>,
 }
 
 impl AppState {
@@ -48,10 +51,14 @@ impl AppState {
         let jwt_secret = std::env::var("JWT_SECRET")
             .unwrap_or_else(|_| "default-dev-secret-change-in-production".to_string());
 
+        // No backing services are wired in yet, so there is nothing to check
+        let health_checker = Arc::new(CompositeHealthChecker::new());
+
         Ok(Self {
             engine,
             conversation_manager,
             jwt_secret,
+            health_checker,
         })
     }
 }
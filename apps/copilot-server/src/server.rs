@@ -8,16 +8,20 @@ use axum::{
     http::StatusCode,
 };
 use serde_json::json;
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 use tower_http::trace::TraceLayer;
 use tracing::info;
 
 use copilot_api::create_router;
+use copilot_api::rest::shedding_middleware::spawn_health_updates;
 use copilot_api::AppState as ApiAppState;
 
 use crate::app::AppState;
 use crate::cli::Args;
 
+/// How often the health checks feeding load shedding run
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct Server {
     args: Args,
     state: AppState,
@@ -55,6 +59,13 @@ impl Server {
             self.state.jwt_secret.clone(),
         );
 
+        // Keep load shedding in step with the health checks
+        spawn_health_updates(
+            api_state.load_shedder.signal(),
+            self.state.health_checker.clone(),
+            HEALTH_CHECK_INTERVAL,
+        );
+
         // Create API router from copilot-api crate
         let api_router = create_router(api_state);
 
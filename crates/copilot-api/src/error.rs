@@ -5,6 +5,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use crate::error_codes::ErrorCode;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -35,6 +36,18 @@ pub enum ApiError {
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Request timed out: {0}")]
+    RequestTimeout(String),
+
+    #[error("Stream timed out: {0}")]
+    StreamTimeout(String),
+
+    #[error("Invalid resume token: {0}")]
+    InvalidResumeToken(String),
+
+    #[error("Stream backpressure: {0}")]
+    StreamBackpressure(String),
+
     #[error("WebSocket error: {0}")]
     WebSocketError(String),
 
@@ -62,6 +75,9 @@ impl ApiError {
             ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             ApiError::RateLimitExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::RequestTimeout(_) | ApiError::StreamTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::InvalidResumeToken(_) => StatusCode::BAD_REQUEST,
+            ApiError::StreamBackpressure(_) => StatusCode::SERVICE_UNAVAILABLE,
             ApiError::WebSocketError(_) => StatusCode::BAD_REQUEST,
             ApiError::GrpcError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::ConversationError(_) => StatusCode::BAD_REQUEST,
@@ -80,6 +96,10 @@ impl ApiError {
             ApiError::InternalError(_) => "INTERNAL_ERROR",
             ApiError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
             ApiError::RateLimitExceeded => "RATE_LIMIT_EXCEEDED",
+            ApiError::RequestTimeout(_) => "REQUEST_TIMEOUT",
+            ApiError::StreamTimeout(_) => "STREAM_TIMEOUT",
+            ApiError::InvalidResumeToken(_) => "INVALID_RESUME_TOKEN",
+            ApiError::StreamBackpressure(_) => "STREAM_BACKPRESSURE",
             ApiError::WebSocketError(_) => "WEBSOCKET_ERROR",
             ApiError::GrpcError(_) => "GRPC_ERROR",
             ApiError::ConversationError(_) => "CONVERSATION_ERROR",
@@ -87,6 +107,28 @@ impl ApiError {
             ApiError::ExecutionContextError(_) => "EXECUTION_CONTEXT_ERROR",
         }
     }
+
+    /// Get the entry of the error code catalog for this error
+    pub fn catalog_code(&self) -> ErrorCode {
+        match self {
+            ApiError::AuthenticationFailed(_) => ErrorCode::Unauthorized,
+            ApiError::AuthorizationFailed(_) => ErrorCode::Forbidden,
+            ApiError::InvalidInput(_) => ErrorCode::ValidationError,
+            ApiError::NotFound(_) => ErrorCode::NotFound,
+            ApiError::InternalError(_) => ErrorCode::InternalError,
+            ApiError::ServiceUnavailable(_) => ErrorCode::ServiceUnavailable,
+            ApiError::RateLimitExceeded => ErrorCode::RateLimitExceeded,
+            ApiError::RequestTimeout(_) => ErrorCode::RequestTimeout,
+            ApiError::StreamTimeout(_) => ErrorCode::StreamTimeout,
+            ApiError::InvalidResumeToken(_) => ErrorCode::InvalidResumeToken,
+            ApiError::StreamBackpressure(_) => ErrorCode::StreamBackpressure,
+            ApiError::WebSocketError(_) => ErrorCode::InvalidFormat,
+            ApiError::GrpcError(_) => ErrorCode::InternalError,
+            ApiError::ConversationError(_) => ErrorCode::InvalidState,
+            ApiError::WorkflowError(_) => ErrorCode::InvalidState,
+            ApiError::ExecutionContextError(_) => ErrorCode::InvalidWorkflowState,
+        }
+    }
 }
 
 /// Error response body
@@ -120,6 +162,21 @@ impl From<anyhow::Error> for ApiError {
     }
 }
 
+impl From<copilot_conversation::ConversationError> for ApiError {
+    fn from(err: copilot_conversation::ConversationError) -> Self {
+        use copilot_conversation::ConversationError;
+        match err {
+            ConversationError::InvalidResumeToken(token) => ApiError::InvalidResumeToken(token),
+            ConversationError::StreamBackpressure(reason) => ApiError::StreamBackpressure(reason),
+            err @ ConversationError::SessionNotFound(_) => ApiError::NotFound(err.to_string()),
+            err @ ConversationError::AccessDenied { .. } => {
+                ApiError::AuthorizationFailed(err.to_string())
+            }
+            err => ApiError::ConversationError(err.to_string()),
+        }
+    }
+}
+
 impl From<serde_json::Error> for ApiError {
     fn from(err: serde_json::Error) -> Self {
         ApiError::InvalidInput(err.to_string())
@@ -164,4 +221,54 @@ mod tests {
             "NOT_FOUND"
         );
     }
+
+    #[test]
+    fn test_invalid_resume_token_from_conversation() {
+        let error = ApiError::from(copilot_conversation::ConversationError::InvalidResumeToken(
+            "stream-1:4".into(),
+        ));
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.error_code(), "INVALID_RESUME_TOKEN");
+        assert_eq!(error.catalog_code().code(), 9003);
+    }
+
+    #[test]
+    fn test_stream_backpressure_from_conversation() {
+        let error = ApiError::from(copilot_conversation::ConversationError::StreamBackpressure(
+            "consumer fell 4 chunks behind".into(),
+        ));
+        assert_eq!(error.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.error_code(), "STREAM_BACKPRESSURE");
+        assert_eq!(error.catalog_code().code(), 9004);
+    }
+
+    #[test]
+    fn test_catalog_codes_agree_with_status() {
+        let errors = [
+            ApiError::AuthenticationFailed("test".into()),
+            ApiError::AuthorizationFailed("test".into()),
+            ApiError::InvalidInput("test".into()),
+            ApiError::NotFound("test".into()),
+            ApiError::InternalError("test".into()),
+            ApiError::ServiceUnavailable("test".into()),
+            ApiError::RateLimitExceeded,
+            ApiError::RequestTimeout("test".into()),
+            ApiError::StreamTimeout("test".into()),
+            ApiError::InvalidResumeToken("test".into()),
+            ApiError::StreamBackpressure("test".into()),
+            ApiError::WebSocketError("test".into()),
+            ApiError::GrpcError("test".into()),
+            ApiError::ConversationError("test".into()),
+            ApiError::WorkflowError("test".into()),
+            ApiError::ExecutionContextError("test".into()),
+        ];
+        for error in errors {
+            assert_eq!(
+                error.catalog_code().http_status(),
+                error.status_code().as_u16(),
+                "{:?}",
+                error
+            );
+        }
+    }
 }
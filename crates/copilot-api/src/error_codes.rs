@@ -1,5 +1,8 @@
-// Error Code Catalog and Definitions
-// Comprehensive error handling with localization support
+//! Error code catalog
+//!
+//! Numeric error codes with their HTTP status, default message and
+//! localization. [`crate::ApiError::catalog_code`] maps the API's errors
+//! onto this catalog.
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -102,6 +105,7 @@ pub enum ErrorCode {
     MessageQueueError,
     SerializationError,
     DeserializationError,
+    RequestTimeout,
 
     // ========== STREAMING ERRORS (9000-9999) ==========
     StreamError,
@@ -112,6 +116,109 @@ pub enum ErrorCode {
 }
 
 impl ErrorCode {
+    /// Every error code, in numeric order
+    pub const ALL: [ErrorCode; 82] = [
+        // Validation
+        ErrorCode::ValidationError,
+        ErrorCode::InvalidFormat,
+        ErrorCode::MissingRequiredField,
+        ErrorCode::InvalidFieldValue,
+        ErrorCode::FieldTooLong,
+        ErrorCode::FieldTooShort,
+        ErrorCode::InvalidPattern,
+        ErrorCode::InvalidEnum,
+        ErrorCode::InvalidUuid,
+        ErrorCode::InvalidTimestamp,
+        ErrorCode::InvalidTimeRange,
+        ErrorCode::InvalidJson,
+        ErrorCode::InvalidContentType,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::TooManyItems,
+        ErrorCode::DuplicateEntry,
+        ErrorCode::CircularDependency,
+        ErrorCode::InvalidDependency,
+        // Authentication
+        ErrorCode::Unauthorized,
+        ErrorCode::InvalidToken,
+        ErrorCode::ExpiredToken,
+        ErrorCode::MissingToken,
+        ErrorCode::InvalidCredentials,
+        ErrorCode::TokenRevoked,
+        ErrorCode::InvalidSignature,
+        ErrorCode::InvalidIssuer,
+        ErrorCode::InvalidAudience,
+        // Authorization
+        ErrorCode::Forbidden,
+        ErrorCode::InsufficientPermissions,
+        ErrorCode::ResourceAccessDenied,
+        ErrorCode::OperationNotAllowed,
+        ErrorCode::QuotaExceeded,
+        ErrorCode::FeatureNotEnabled,
+        // Resources
+        ErrorCode::NotFound,
+        ErrorCode::ResourceNotFound,
+        ErrorCode::SessionNotFound,
+        ErrorCode::WorkflowNotFound,
+        ErrorCode::IncidentNotFound,
+        ErrorCode::UserNotFound,
+        ErrorCode::AlreadyExists,
+        ErrorCode::ResourceConflict,
+        ErrorCode::ResourceLocked,
+        ErrorCode::ResourceExpired,
+        // Rate Limiting
+        ErrorCode::RateLimitExceeded,
+        ErrorCode::TooManyRequests,
+        ErrorCode::QuotaLimitExceeded,
+        ErrorCode::ConcurrencyLimitExceeded,
+        // Business Logic
+        ErrorCode::InvalidState,
+        ErrorCode::WorkflowExecutionFailed,
+        ErrorCode::WorkflowAlreadyRunning,
+        ErrorCode::WorkflowNotApproved,
+        ErrorCode::ApprovalRequired,
+        ErrorCode::CannotCancelWorkflow,
+        ErrorCode::TaskExecutionFailed,
+        ErrorCode::InvalidWorkflowState,
+        ErrorCode::IncidentAlreadyClosed,
+        ErrorCode::IncidentNotResolved,
+        // External Services
+        ErrorCode::ExternalServiceError,
+        ErrorCode::LlmApiError,
+        ErrorCode::LlmApiTimeout,
+        ErrorCode::LlmApiRateLimited,
+        ErrorCode::PrometheusError,
+        ErrorCode::LokiError,
+        ErrorCode::TempoError,
+        ErrorCode::DatabaseError,
+        ErrorCode::CacheError,
+        ErrorCode::VectorDbError,
+        // Internal
+        ErrorCode::InternalError,
+        ErrorCode::ConfigurationError,
+        ErrorCode::ServiceUnavailable,
+        ErrorCode::DependencyFailure,
+        ErrorCode::DatabaseConnectionError,
+        ErrorCode::CacheConnectionError,
+        ErrorCode::MessageQueueError,
+        ErrorCode::SerializationError,
+        ErrorCode::DeserializationError,
+        ErrorCode::RequestTimeout,
+        // Streaming
+        ErrorCode::StreamError,
+        ErrorCode::StreamClosed,
+        ErrorCode::StreamTimeout,
+        ErrorCode::InvalidResumeToken,
+        ErrorCode::StreamBackpressure,
+    ];
+
+    /// Wire name of the code, e.g. `VALIDATION_ERROR`
+    pub fn name(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("{:?}", self))
+    }
+
     /// Get numeric error code
     pub fn code(&self) -> u16 {
         match self {
@@ -206,6 +313,7 @@ impl ErrorCode {
             ErrorCode::MessageQueueError => 8006,
             ErrorCode::SerializationError => 8007,
             ErrorCode::DeserializationError => 8008,
+            ErrorCode::RequestTimeout => 8009,
 
             // Streaming
             ErrorCode::StreamError => 9000,
@@ -322,7 +430,7 @@ impl ErrorCode {
             | ErrorCode::StreamBackpressure => 503,
 
             // 504 Gateway Timeout
-            ErrorCode::LlmApiTimeout | ErrorCode::StreamTimeout => 504,
+            ErrorCode::LlmApiTimeout | ErrorCode::StreamTimeout | ErrorCode::RequestTimeout => 504,
 
             // 500 for streaming errors (default)
             ErrorCode::StreamError => 500,
@@ -415,6 +523,7 @@ impl ErrorCode {
             ErrorCode::MessageQueueError => "Message queue error",
             ErrorCode::SerializationError => "Serialization error",
             ErrorCode::DeserializationError => "Deserialization error",
+            ErrorCode::RequestTimeout => "Request timed out",
 
             ErrorCode::StreamError => "Stream error",
             ErrorCode::StreamClosed => "Stream closed",
@@ -625,6 +734,12 @@ impl ErrorLocalizer {
     }
 }
 
+impl Default for ErrorLocalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // ==================== ERROR CODE TABLE ====================
 
 /// Generate markdown table of all error codes
@@ -633,100 +748,7 @@ pub fn generate_error_code_table() -> String {
     table.push_str("| Code | HTTP | Name | Default Message |\n");
     table.push_str("|------|------|------|----------------|\n");
 
-    let all_codes = vec![
-        // Validation
-        ErrorCode::ValidationError,
-        ErrorCode::InvalidFormat,
-        ErrorCode::MissingRequiredField,
-        ErrorCode::InvalidFieldValue,
-        ErrorCode::FieldTooLong,
-        ErrorCode::FieldTooShort,
-        ErrorCode::InvalidPattern,
-        ErrorCode::InvalidEnum,
-        ErrorCode::InvalidUuid,
-        ErrorCode::InvalidTimestamp,
-        ErrorCode::InvalidTimeRange,
-        ErrorCode::InvalidJson,
-        ErrorCode::InvalidContentType,
-        ErrorCode::PayloadTooLarge,
-        ErrorCode::TooManyItems,
-        ErrorCode::DuplicateEntry,
-        ErrorCode::CircularDependency,
-        ErrorCode::InvalidDependency,
-        // Authentication
-        ErrorCode::Unauthorized,
-        ErrorCode::InvalidToken,
-        ErrorCode::ExpiredToken,
-        ErrorCode::MissingToken,
-        ErrorCode::InvalidCredentials,
-        ErrorCode::TokenRevoked,
-        ErrorCode::InvalidSignature,
-        ErrorCode::InvalidIssuer,
-        ErrorCode::InvalidAudience,
-        // Authorization
-        ErrorCode::Forbidden,
-        ErrorCode::InsufficientPermissions,
-        ErrorCode::ResourceAccessDenied,
-        ErrorCode::OperationNotAllowed,
-        ErrorCode::QuotaExceeded,
-        ErrorCode::FeatureNotEnabled,
-        // Resources
-        ErrorCode::NotFound,
-        ErrorCode::ResourceNotFound,
-        ErrorCode::SessionNotFound,
-        ErrorCode::WorkflowNotFound,
-        ErrorCode::IncidentNotFound,
-        ErrorCode::UserNotFound,
-        ErrorCode::AlreadyExists,
-        ErrorCode::ResourceConflict,
-        ErrorCode::ResourceLocked,
-        ErrorCode::ResourceExpired,
-        // Rate Limiting
-        ErrorCode::RateLimitExceeded,
-        ErrorCode::TooManyRequests,
-        ErrorCode::QuotaLimitExceeded,
-        ErrorCode::ConcurrencyLimitExceeded,
-        // Business Logic
-        ErrorCode::InvalidState,
-        ErrorCode::WorkflowExecutionFailed,
-        ErrorCode::WorkflowAlreadyRunning,
-        ErrorCode::WorkflowNotApproved,
-        ErrorCode::ApprovalRequired,
-        ErrorCode::CannotCancelWorkflow,
-        ErrorCode::TaskExecutionFailed,
-        ErrorCode::InvalidWorkflowState,
-        ErrorCode::IncidentAlreadyClosed,
-        ErrorCode::IncidentNotResolved,
-        // External Services
-        ErrorCode::ExternalServiceError,
-        ErrorCode::LlmApiError,
-        ErrorCode::LlmApiTimeout,
-        ErrorCode::LlmApiRateLimited,
-        ErrorCode::PrometheusError,
-        ErrorCode::LokiError,
-        ErrorCode::TempoError,
-        ErrorCode::DatabaseError,
-        ErrorCode::CacheError,
-        ErrorCode::VectorDbError,
-        // Internal
-        ErrorCode::InternalError,
-        ErrorCode::ConfigurationError,
-        ErrorCode::ServiceUnavailable,
-        ErrorCode::DependencyFailure,
-        ErrorCode::DatabaseConnectionError,
-        ErrorCode::CacheConnectionError,
-        ErrorCode::MessageQueueError,
-        ErrorCode::SerializationError,
-        ErrorCode::DeserializationError,
-        // Streaming
-        ErrorCode::StreamError,
-        ErrorCode::StreamClosed,
-        ErrorCode::StreamTimeout,
-        ErrorCode::InvalidResumeToken,
-        ErrorCode::StreamBackpressure,
-    ];
-
-    for code in all_codes {
+    for code in ErrorCode::ALL {
         table.push_str(&format!(
             "| {} | {} | {:?} | {} |\n",
             code.code(),
@@ -739,6 +761,82 @@ pub fn generate_error_code_table() -> String {
     table
 }
 
+// ==================== MACHINE-READABLE CATALOG ====================
+
+/// Version of the catalog document format
+pub const ERROR_CATALOG_VERSION: u32 = 1;
+
+/// One error code in the machine-readable catalog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorCatalogEntry {
+    pub code: u16,
+    pub http_status: u16,
+    pub name: String,
+    pub default_message: String,
+    pub expose_details: bool,
+}
+
+impl From<ErrorCode> for ErrorCatalogEntry {
+    fn from(code: ErrorCode) -> Self {
+        Self {
+            code: code.code(),
+            http_status: code.http_status(),
+            name: code.name(),
+            default_message: code.default_message().to_string(),
+            expose_details: code.expose_details(),
+        }
+    }
+}
+
+/// The machine-readable error catalog, for generating client SDKs and docs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorCatalog {
+    pub version: u32,
+    /// Entries ordered by numeric code
+    pub errors: Vec<ErrorCatalogEntry>,
+}
+
+/// Build the catalog of all error codes, ordered by numeric code
+pub fn error_code_catalog() -> ErrorCatalog {
+    let mut errors: Vec<ErrorCatalogEntry> =
+        ErrorCode::ALL.iter().copied().map(ErrorCatalogEntry::from).collect();
+    errors.sort_by_key(|entry| entry.code);
+    ErrorCatalog {
+        version: ERROR_CATALOG_VERSION,
+        errors,
+    }
+}
+
+/// Generate the error catalog as JSON
+///
+/// Entries are ordered by numeric code and fields are always emitted in
+/// the same order, so the output only changes when the catalog does.
+pub fn generate_error_code_catalog_json() -> String {
+    serde_json::to_string_pretty(&error_code_catalog())
+        .expect("error catalog is always serializable")
+}
+
+/// Generate an OpenAPI `components` fragment describing the error codes
+///
+/// Adds an `ErrorCode` string enum of the wire names, annotated with the
+/// full catalog under `x-error-catalog`.
+pub fn generate_error_code_openapi_components() -> serde_json::Value {
+    let catalog = error_code_catalog();
+    let names: Vec<&str> = catalog.errors.iter().map(|e| e.name.as_str()).collect();
+    serde_json::json!({
+        "components": {
+            "schemas": {
+                "ErrorCode": {
+                    "type": "string",
+                    "description": "Machine-readable error code",
+                    "enum": names,
+                    "x-error-catalog": catalog.errors,
+                }
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -791,4 +889,132 @@ mod tests {
         assert_eq!(sanitized.message, "Internal server error");
         assert!(sanitized.details.is_none());
     }
+
+    /// Fails to compile when a variant is added without updating the match,
+    /// which is the reminder to add it to `ErrorCode::ALL` as well
+    fn is_cataloged(code: ErrorCode) -> bool {
+        match code {
+            ErrorCode::ValidationError
+            | ErrorCode::InvalidFormat
+            | ErrorCode::MissingRequiredField
+            | ErrorCode::InvalidFieldValue
+            | ErrorCode::FieldTooLong
+            | ErrorCode::FieldTooShort
+            | ErrorCode::InvalidPattern
+            | ErrorCode::InvalidEnum
+            | ErrorCode::InvalidUuid
+            | ErrorCode::InvalidTimestamp
+            | ErrorCode::InvalidTimeRange
+            | ErrorCode::InvalidJson
+            | ErrorCode::InvalidContentType
+            | ErrorCode::PayloadTooLarge
+            | ErrorCode::TooManyItems
+            | ErrorCode::DuplicateEntry
+            | ErrorCode::CircularDependency
+            | ErrorCode::InvalidDependency
+            | ErrorCode::Unauthorized
+            | ErrorCode::InvalidToken
+            | ErrorCode::ExpiredToken
+            | ErrorCode::MissingToken
+            | ErrorCode::InvalidCredentials
+            | ErrorCode::TokenRevoked
+            | ErrorCode::InvalidSignature
+            | ErrorCode::InvalidIssuer
+            | ErrorCode::InvalidAudience
+            | ErrorCode::Forbidden
+            | ErrorCode::InsufficientPermissions
+            | ErrorCode::ResourceAccessDenied
+            | ErrorCode::OperationNotAllowed
+            | ErrorCode::QuotaExceeded
+            | ErrorCode::FeatureNotEnabled
+            | ErrorCode::NotFound
+            | ErrorCode::ResourceNotFound
+            | ErrorCode::SessionNotFound
+            | ErrorCode::WorkflowNotFound
+            | ErrorCode::IncidentNotFound
+            | ErrorCode::UserNotFound
+            | ErrorCode::AlreadyExists
+            | ErrorCode::ResourceConflict
+            | ErrorCode::ResourceLocked
+            | ErrorCode::ResourceExpired
+            | ErrorCode::RateLimitExceeded
+            | ErrorCode::TooManyRequests
+            | ErrorCode::QuotaLimitExceeded
+            | ErrorCode::ConcurrencyLimitExceeded
+            | ErrorCode::InvalidState
+            | ErrorCode::WorkflowExecutionFailed
+            | ErrorCode::WorkflowAlreadyRunning
+            | ErrorCode::WorkflowNotApproved
+            | ErrorCode::ApprovalRequired
+            | ErrorCode::CannotCancelWorkflow
+            | ErrorCode::TaskExecutionFailed
+            | ErrorCode::InvalidWorkflowState
+            | ErrorCode::IncidentAlreadyClosed
+            | ErrorCode::IncidentNotResolved
+            | ErrorCode::ExternalServiceError
+            | ErrorCode::LlmApiError
+            | ErrorCode::LlmApiTimeout
+            | ErrorCode::LlmApiRateLimited
+            | ErrorCode::PrometheusError
+            | ErrorCode::LokiError
+            | ErrorCode::TempoError
+            | ErrorCode::DatabaseError
+            | ErrorCode::CacheError
+            | ErrorCode::VectorDbError
+            | ErrorCode::InternalError
+            | ErrorCode::ConfigurationError
+            | ErrorCode::ServiceUnavailable
+            | ErrorCode::DependencyFailure
+            | ErrorCode::DatabaseConnectionError
+            | ErrorCode::CacheConnectionError
+            | ErrorCode::MessageQueueError
+            | ErrorCode::SerializationError
+            | ErrorCode::DeserializationError
+            | ErrorCode::RequestTimeout
+            | ErrorCode::StreamError
+            | ErrorCode::StreamClosed
+            | ErrorCode::StreamTimeout
+            | ErrorCode::InvalidResumeToken
+            | ErrorCode::StreamBackpressure => ErrorCode::ALL.contains(&code),
+        }
+    }
+
+    #[test]
+    fn test_catalog_covers_every_code() {
+        assert!(ErrorCode::ALL.iter().all(|code| is_cataloged(*code)));
+
+        let catalog = error_code_catalog();
+        assert_eq!(catalog.errors.len(), ErrorCode::ALL.len());
+        let mut codes: Vec<u16> = catalog.errors.iter().map(|e| e.code).collect();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorCode::ALL.len(), "numeric codes must be unique");
+        assert!(codes.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_catalog_json_contains_all_codes_with_statuses() {
+        let json = generate_error_code_catalog_json();
+        assert_eq!(json, generate_error_code_catalog_json());
+
+        let parsed: ErrorCatalog = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.version, ERROR_CATALOG_VERSION);
+        for code in ErrorCode::ALL {
+            let entry = parsed
+                .errors
+                .iter()
+                .find(|e| e.code == code.code())
+                .unwrap_or_else(|| panic!("{:?} missing from catalog", code));
+            assert_eq!(entry.http_status, code.http_status());
+            assert_eq!(entry.name, code.name());
+            assert_eq!(entry.expose_details, code.expose_details());
+        }
+
+        let not_found = parsed.errors.iter().find(|e| e.name == "NOT_FOUND").unwrap();
+        assert_eq!((not_found.code, not_found.http_status), (4000, 404));
+
+        let openapi = generate_error_code_openapi_components();
+        let names = &openapi["components"]["schemas"]["ErrorCode"]["enum"];
+        assert_eq!(names.as_array().unwrap().len(), ErrorCode::ALL.len());
+        assert_eq!(names[0], "VALIDATION_ERROR");
+    }
 }
@@ -57,15 +57,23 @@ impl CoPilotServiceImpl {
         match error {
             ApiError::AuthenticationFailed(msg) => Status::unauthenticated(msg),
             ApiError::AuthorizationFailed(msg) => Status::permission_denied(msg),
-            ApiError::InvalidInput(msg) => Status::invalid_argument(msg),
+            ApiError::InvalidInput(msg) | ApiError::InvalidResumeToken(msg) => {
+                Status::invalid_argument(msg)
+            }
             ApiError::NotFound(msg) => Status::not_found(msg),
             ApiError::InternalError(msg) => Status::internal(msg),
-            ApiError::ServiceUnavailable(msg) => Status::unavailable(msg),
+            ApiError::ServiceUnavailable(msg) | ApiError::StreamBackpressure(msg) => {
+                Status::unavailable(msg)
+            }
             ApiError::RateLimitExceeded => Status::resource_exhausted("Rate limit exceeded"),
+            ApiError::RequestTimeout(msg) | ApiError::StreamTimeout(msg) => {
+                Status::deadline_exceeded(msg)
+            }
             ApiError::WebSocketError(msg) => Status::internal(msg),
             ApiError::GrpcError(msg) => Status::internal(msg),
             ApiError::ConversationError(msg) => Status::failed_precondition(msg),
             ApiError::WorkflowError(msg) => Status::failed_precondition(msg),
+            ApiError::ExecutionContextError(msg) => Status::failed_precondition(msg),
         }
     }
 }
@@ -12,6 +12,7 @@
 //! - `grpc` - Enable gRPC services (enabled by default)
 
 pub mod error;
+pub mod error_codes;
 
 #[cfg(feature = "rest")]
 pub mod rest;
@@ -23,9 +24,11 @@ pub mod websocket;
 pub mod grpc;
 
 pub mod types;
+pub mod validation;
 
 // Re-export commonly used types
 pub use error::{ApiError, Result};
+pub use error_codes::ErrorCode;
 pub use types::*;
 
 #[cfg(feature = "rest")]
@@ -44,6 +47,11 @@ pub struct AppState {
     pub conversation_manager: Arc<ConversationManager>,
     /// JWT secret for authentication
     pub jwt_secret: String,
+    /// Per-route request timeouts
+    pub request_timeouts: RequestTimeouts,
+    /// Load shedding under overload
+    #[cfg(feature = "rest")]
+    pub load_shedder: Arc<rest::shedding_middleware::LoadShedder>,
 }
 
 impl AppState {
@@ -57,8 +65,25 @@ impl AppState {
             engine,
             conversation_manager,
             jwt_secret,
+            request_timeouts: RequestTimeouts::default(),
+            #[cfg(feature = "rest")]
+            load_shedder: Arc::default(),
         }
     }
+
+    /// Use custom per-route request timeouts
+    pub fn with_request_timeouts(mut self, timeouts: RequestTimeouts) -> Self {
+        self.request_timeouts = timeouts;
+        self
+    }
+
+    /// Shed load with a custom shedder, e.g. one whose signal is fed by
+    /// the server's health checks
+    #[cfg(feature = "rest")]
+    pub fn with_load_shedder(mut self, shedder: rest::shedding_middleware::LoadShedder) -> Self {
+        self.load_shedder = Arc::new(shedder);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -2,48 +2,104 @@
 
 use crate::{
     error::{ApiError, Result},
-    rest::execution_middleware::SharedExecutionGraph,
+    rest::{execution_middleware::SharedExecutionGraph, middleware::RequestId},
     types::*,
     AppState,
 };
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{request::Parts, StatusCode},
     Extension,
     Json,
 };
 use chrono::Utc;
 use copilot_core::agents::execution_graph::Artifact;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{convert::Infallible, sync::Arc, time::Instant};
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
+/// Per-request context used to build the response envelope
+///
+/// Extract this first so `took_ms` is measured from handler entry.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// Request ID assigned by the request ID middleware
+    pub request_id: String,
+    /// Rate limit state recorded by the rate limiting middleware
+    pub rate_limit: Option<RateLimitInfo>,
+    started: Instant,
+}
+
+impl RequestContext {
+    /// Build response metadata as of now
+    pub fn meta(&self) -> ResponseMeta {
+        ResponseMeta {
+            request_id: self.request_id.clone(),
+            took_ms: self.started.elapsed().as_millis() as u64,
+            rate_limit: self.rate_limit.clone(),
+        }
+    }
+
+    /// Wrap data in a successful envelope with populated metadata
+    pub fn respond<T>(&self, data: T) -> ApiResponse<T> {
+        ApiResponse::success(data).with_meta(self.meta())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let started = Instant::now();
+        let request_id = parts
+            .extensions
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        Ok(Self {
+            request_id,
+            rate_limit: parts.extensions.get::<RateLimitInfo>().cloned(),
+            started,
+        })
+    }
+}
+
 /// Health check handler
-pub async fn health_check() -> Result<Json<HealthResponse>> {
+pub async fn health_check(ctx: RequestContext) -> Result<Json<ApiResponse<HealthResponse>>> {
     debug!("Health check requested");
-    Ok(Json(HealthResponse {
+    Ok(Json(ctx.respond(HealthResponse {
         status: "healthy".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         uptime: 0, // TODO: Track actual uptime
-    }))
+    })))
 }
 
 /// Readiness check handler
-pub async fn readiness_check(State(state): State<Arc<AppState>>) -> Result<Json<HealthResponse>> {
+pub async fn readiness_check(
+    ctx: RequestContext,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<HealthResponse>>> {
     debug!("Readiness check requested");
 
     // TODO: Check if dependencies are ready (database, external services, etc.)
 
-    Ok(Json(HealthResponse {
+    Ok(Json(ctx.respond(HealthResponse {
         status: "ready".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         uptime: 0,
-    }))
+    })))
 }
 
 /// Create a new session
 pub async fn create_session(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
     Json(req): Json<CreateSessionRequest>,
 ) -> Result<(StatusCode, Json<ApiResponse<SessionResponse>>)> {
@@ -63,11 +119,12 @@ pub async fn create_session(
     };
 
     info!("Session created: {}", session_id);
-    Ok((StatusCode::CREATED, Json(ApiResponse::success(response))))
+    Ok((StatusCode::CREATED, Json(ctx.respond(response))))
 }
 
 /// Get session by ID
 pub async fn get_session(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<SessionResponse>>> {
@@ -84,23 +141,33 @@ pub async fn get_session(
         metadata: serde_json::json!({}),
     };
 
-    Ok(Json(ApiResponse::success(response)))
+    Ok(Json(ctx.respond(response)))
 }
 
 /// Delete session by ID
+///
+/// Deletes the session and its history on behalf of the authenticated
+/// user. Fails with `404` if the session does not exist.
 pub async fn delete_session(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
+    claims: Option<Extension<Claims>>,
     Path(id): Path<String>,
-) -> Result<StatusCode> {
+) -> Result<Json<ApiResponse<DeleteSessionResponse>>> {
     info!("Deleting session: {}", id);
 
-    // TODO: Delete session using conversation manager
+    let user_id = claims.as_ref().map(|Extension(claims)| claims.sub.as_str());
+    let session = state.conversation_manager.delete_session(&id, user_id).await?;
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok(Json(ctx.respond(DeleteSessionResponse {
+        id: session.id,
+        deleted: true,
+    })))
 }
 
 /// Send a message
 pub async fn send_message(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
     graph: Option<Extension<SharedExecutionGraph>>,
     Json(req): Json<SendMessageRequest>,
@@ -164,6 +231,7 @@ pub async fn send_message(
             api_response = api_response.with_execution_graph(json);
         }
     }
+    let api_response = api_response.with_meta(ctx.meta());
 
     info!("Message sent: {}", message_id);
     Ok((StatusCode::CREATED, Json(api_response)))
@@ -185,6 +253,7 @@ fn default_limit() -> usize {
 
 /// Get messages for a session
 pub async fn get_messages(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
     Query(query): Query<GetMessagesQuery>,
@@ -202,11 +271,12 @@ pub async fn get_messages(
         next_cursor: None,
     };
 
-    Ok(Json(ApiResponse::success(response)))
+    Ok(Json(ctx.respond(response)))
 }
 
 /// Create a new workflow
 pub async fn create_workflow(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
     graph: Option<Extension<SharedExecutionGraph>>,
     Json(req): Json<CreateWorkflowRequest>,
@@ -251,6 +321,7 @@ pub async fn create_workflow(
             api_response = api_response.with_execution_graph(json);
         }
     }
+    let api_response = api_response.with_meta(ctx.meta());
 
     info!("Workflow created: {}", workflow_id);
     Ok((StatusCode::CREATED, Json(api_response)))
@@ -258,6 +329,7 @@ pub async fn create_workflow(
 
 /// Get workflow status
 pub async fn get_workflow_status(
+    ctx: RequestContext,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<WorkflowResponse>>> {
@@ -276,19 +348,124 @@ pub async fn get_workflow_status(
         error: None,
     };
 
-    Ok(Json(ApiResponse::success(response)))
+    Ok(Json(ctx.respond(response)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn request_context() -> RequestContext {
+        RequestContext {
+            request_id: "req-1".to_string(),
+            rate_limit: None,
+            started: Instant::now(),
+        }
+    }
+
     #[tokio::test]
     async fn test_health_check() {
-        let result = health_check().await;
+        let result = health_check(request_context()).await;
         assert!(result.is_ok());
-        let health = result.unwrap().0;
-        assert_eq!(health.status, "healthy");
+        let response = result.unwrap().0;
+        assert!(response.success);
+        assert_eq!(response.data.unwrap().status, "healthy");
+        assert_eq!(response.meta.unwrap().request_id, "req-1");
+    }
+
+    async fn call_enveloped(uri: &str) -> (StatusCode, serde_json::Value) {
+        use crate::rest::middleware::{rate_limit_middleware, request_id_middleware};
+        use axum::{body::Body, http::Request, middleware, routing::get, Router};
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        async fn ok(ctx: RequestContext) -> Result<Json<ApiResponse<&'static str>>> {
+            Ok(Json(ctx.respond("pong")))
+        }
+
+        async fn fail(_ctx: RequestContext) -> Result<Json<ApiResponse<&'static str>>> {
+            Err(ApiError::NotFound("missing".to_string()))
+        }
+
+        let app = Router::new()
+            .route("/ok", get(ok))
+            .route("/fail", get(fail))
+            .layer(middleware::from_fn(rate_limit_middleware))
+            .layer(middleware::from_fn(request_id_middleware));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("x-request-id", "req-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_success_response_carries_envelope() {
+        let (status, json) = call_enveloped("/ok").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["data"], "pong");
+        assert_eq!(json["meta"]["request_id"], "req-123");
+        assert!(json["meta"]["took_ms"].is_u64());
+        assert_eq!(json["meta"]["rate_limit"]["limit"], 100);
+        assert!(json["meta"]["rate_limit"]["remaining"].as_u64().unwrap() < 100);
+    }
+
+    #[tokio::test]
+    async fn test_error_response_uses_error_body() {
+        let (status, json) = call_enveloped("/fail").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["code"], "NOT_FOUND");
+        assert!(json.get("meta").is_none());
+        assert!(json.get("data").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_removes_it() {
+        use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+        use copilot_conversation::ConversationManager;
+        use copilot_core::CoPilotEngine;
+        use copilot_nlp::NlpEngineImpl;
+
+        let context_engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let manager = Arc::new(ConversationManager::new(
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(context_engine),
+        ));
+        let state = Arc::new(AppState::new(
+            Arc::new(CoPilotEngine::new()),
+            Arc::clone(&manager),
+            "secret".to_string(),
+        ));
+        let (session, _) = manager.start_session(None, Default::default()).await.unwrap();
+
+        let response = delete_session(
+            request_context(),
+            State(Arc::clone(&state)),
+            None,
+            Path(session.id.clone()),
+        )
+        .await
+        .unwrap()
+        .0;
+        let data = response.data.unwrap();
+        assert_eq!(data.id, session.id);
+        assert!(data.deleted);
+        assert!(manager.get_session(&session.id, None).await.is_err());
+
+        // Deleting it again finds nothing to delete
+        let err = delete_session(request_context(), State(state), None, Path(session.id))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
     }
 
     #[test]
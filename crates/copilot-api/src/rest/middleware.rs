@@ -1,6 +1,10 @@
 //! Middleware for REST API
 
-use crate::{error::ApiError, types::Claims, AppState};
+use crate::{
+    error::ApiError,
+    types::{Claims, RateLimitInfo},
+    AppState,
+};
 use axum::{
     body::Body,
     extract::{Request, State},
@@ -8,8 +12,10 @@ use axum::{
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use chrono::Utc;
 use governor::{
     clock::DefaultClock,
+    middleware::{StateInformationMiddleware, StateSnapshot},
     state::{InMemoryState, NotKeyed},
     Quota, RateLimiter,
 };
@@ -66,20 +72,36 @@ fn validate_token(token: &str, secret: &str) -> Result<Claims, ApiError> {
 
 /// Rate limiting middleware
 ///
-/// Implements per-IP rate limiting using the governor crate
-pub async fn rate_limit_middleware(req: Request, next: Next) -> Result<Response, ApiError> {
+/// Implements per-IP rate limiting using the governor crate. Admitted
+/// requests carry a [`RateLimitInfo`] extension for handlers, and the
+/// response gets the matching `X-RateLimit-*` headers.
+pub async fn rate_limit_middleware(mut req: Request, next: Next) -> Result<Response, ApiError> {
     // Create a simple in-memory rate limiter
     // In production, you'd want to use a distributed rate limiter (Redis, etc.)
     lazy_static::lazy_static! {
-        static ref LIMITER: RateLimiter<NotKeyed, InMemoryState, DefaultClock> = {
+        static ref LIMITER: RateLimiter<NotKeyed, InMemoryState, DefaultClock, StateInformationMiddleware> = {
             // Allow 100 requests per minute
             let quota = Quota::per_minute(NonZeroU32::new(100).unwrap());
-            RateLimiter::direct(quota)
+            RateLimiter::direct(quota).with_middleware::<StateInformationMiddleware>()
         };
     }
 
     match LIMITER.check() {
-        Ok(_) => Ok(next.run(req).await),
+        Ok(snapshot) => {
+            let info = rate_limit_info(&snapshot);
+            req.extensions_mut().insert(info.clone());
+
+            let mut response = next.run(req).await;
+            for (name, value) in info.to_headers() {
+                if let (Ok(name), Ok(value)) = (
+                    header::HeaderName::try_from(name),
+                    header::HeaderValue::try_from(value),
+                ) {
+                    response.headers_mut().insert(name, value);
+                }
+            }
+            Ok(response)
+        }
         Err(_) => {
             warn!("Rate limit exceeded");
             Err(ApiError::RateLimitExceeded)
@@ -87,6 +109,21 @@ pub async fn rate_limit_middleware(req: Request, next: Next) -> Result<Response,
     }
 }
 
+/// Convert a limiter snapshot into the client-facing rate limit info
+fn rate_limit_info(snapshot: &StateSnapshot) -> RateLimitInfo {
+    let quota = snapshot.quota();
+    let limit = quota.burst_size().get();
+    let remaining = snapshot.remaining_burst_capacity().min(limit);
+    let until_full = quota.replenish_interval() * (limit - remaining);
+
+    RateLimitInfo {
+        limit,
+        remaining,
+        reset: Utc::now()
+            + chrono::Duration::from_std(until_full).unwrap_or_else(|_| chrono::Duration::zero()),
+    }
+}
+
 /// Request ID middleware
 ///
 /// Adds a unique request ID to each request for tracing purposes
@@ -122,6 +159,14 @@ pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
 #[derive(Debug, Clone)]
 pub struct RequestId(pub String);
 
+/// Whether a request asks for a `text/event-stream` response
+pub(crate) fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"))
+}
+
 /// Error handling middleware
 ///
 /// Converts errors into proper HTTP responses
@@ -174,6 +219,18 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_rate_limit_info_from_snapshot() {
+        let limiter = RateLimiter::direct(Quota::per_minute(NonZeroU32::new(10).unwrap()))
+            .with_middleware::<StateInformationMiddleware>();
+        let snapshot = limiter.check().unwrap();
+
+        let info = rate_limit_info(&snapshot);
+        assert_eq!(info.limit, 10);
+        assert_eq!(info.remaining, 9);
+        assert!(info.reset > Utc::now());
+    }
+
     #[test]
     fn test_request_id_type() {
         let request_id = RequestId("test-id".to_string());
@@ -6,6 +6,8 @@ pub mod execution_middleware;
 pub mod handlers;
 pub mod middleware;
 pub mod router;
+pub mod shedding_middleware;
+pub mod timeout_middleware;
 
 pub use handlers::*;
 pub use middleware::*;
@@ -1,7 +1,7 @@
 //! Axum router configuration
 
 use crate::{
-    rest::{handlers, middleware, execution_middleware},
+    rest::{handlers, middleware, execution_middleware, shedding_middleware, timeout_middleware},
     AppState,
 };
 use axum::{
@@ -49,7 +49,11 @@ pub fn create_router(state: AppState) -> Router {
                     middleware::auth_middleware,
                 ))
                 .layer(axum_middleware::from_fn(middleware::rate_limit_middleware))
-                .layer(axum_middleware::from_fn(middleware::request_id_middleware)),
+                .layer(axum_middleware::from_fn(middleware::request_id_middleware))
+                .layer(axum_middleware::from_fn_with_state(
+                    Arc::new(state.request_timeouts.clone()),
+                    timeout_middleware::timeout_middleware,
+                )),
         );
 
     // Health check routes (no authentication required)
@@ -57,10 +61,15 @@ pub fn create_router(state: AppState) -> Router {
         .route("/health", get(handlers::health_check))
         .route("/ready", get(handlers::readiness_check));
 
-    // Combine all routes
+    // Combine all routes; shedding sees every route so it can tell the
+    // critical ones apart
     Router::new()
         .nest("/api/v1", api_v1)
         .merge(health_routes)
+        .layer(axum_middleware::from_fn_with_state(
+            state.load_shedder.clone(),
+            shedding_middleware::shedding_middleware,
+        ))
         .layer(cors_layer())
         .layer(TraceLayer::new_for_http())
         .with_state(state)
@@ -0,0 +1,454 @@
+//! Health-aware load shedding middleware.
+//!
+//! Under overload it is better to turn some requests away quickly than to
+//! let every request slow down until the service collapses. The middleware
+//! reads a shared [`LoadSignal`]. It reports the requests in progress and
+//! their latency to the signal itself, and [`spawn_health_updates`] keeps
+//! the signal's health in step with a
+//! [`CompositeHealthChecker`](copilot_infra::CompositeHealthChecker). While
+//! the signal reports overload, sheddable requests get a fast `503` with a
+//! `Retry-After` header.
+//!
+//! Critical requests are never shed: health and readiness probes, and
+//! streams, whose clients hold on to the answer. A request is a stream
+//! when its route is one of the configured streaming routes and it asks
+//! for `text/event-stream`; the header alone cannot exempt a request.
+//! Streams already in flight are not affected at all, as shedding only
+//! happens when a request arrives.
+//!
+//! Shedding is never total. A configurable share of sheddable requests is
+//! still admitted, spread evenly over the arrivals, so every client keeps
+//! making some progress and the service can see load recover.
+
+use crate::{error::ApiError, rest::middleware::accepts_event_stream};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use copilot_infra::{CompositeHealthChecker, HealthStatus};
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Health of the service as reported by its health checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceHealth {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl From<&HealthStatus> for ServiceHealth {
+    fn from(status: &HealthStatus) -> Self {
+        match status {
+            HealthStatus::Healthy => ServiceHealth::Healthy,
+            HealthStatus::Degraded => ServiceHealth::Degraded,
+            HealthStatus::Unhealthy => ServiceHealth::Unhealthy,
+        }
+    }
+}
+
+impl ServiceHealth {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => ServiceHealth::Healthy,
+            1 => ServiceHealth::Degraded,
+            _ => ServiceHealth::Unhealthy,
+        }
+    }
+}
+
+/// Health and load metrics shared between the server and the middleware
+///
+/// Cheap to update from a background task: every field is atomic.
+#[derive(Debug)]
+pub struct LoadSignal {
+    health: AtomicU8,
+    queue_depth: AtomicUsize,
+    latency_ms: AtomicU64,
+}
+
+impl LoadSignal {
+    /// A signal reporting a healthy, idle service
+    pub fn new() -> Self {
+        Self {
+            health: AtomicU8::new(ServiceHealth::Healthy as u8),
+            queue_depth: AtomicUsize::new(0),
+            latency_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Report the overall health status
+    pub fn set_health(&self, health: ServiceHealth) {
+        self.health.store(health as u8, Ordering::Relaxed);
+    }
+
+    /// Report the number of requests in progress
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.store(depth, Ordering::Relaxed);
+    }
+
+    /// Report the recent request latency, e.g. a moving p95
+    pub fn set_latency(&self, latency: Duration) {
+        self.latency_ms.store(latency.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Count a request as in progress until the returned guard is dropped,
+    /// which also folds its latency into a moving average
+    fn track_request(&self) -> InProgress<'_> {
+        self.queue_depth.fetch_add(1, Ordering::Relaxed);
+        InProgress {
+            signal: self,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn health(&self) -> ServiceHealth {
+        ServiceHealth::from_u8(self.health.load(Ordering::Relaxed))
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.load(Ordering::Relaxed)
+    }
+
+    pub fn latency(&self) -> Duration {
+        Duration::from_millis(self.latency_ms.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for LoadSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A request counted by [`LoadSignal::track_request`]
+struct InProgress<'a> {
+    signal: &'a LoadSignal,
+    started: Instant,
+}
+
+impl Drop for InProgress<'_> {
+    fn drop(&mut self) {
+        self.signal.queue_depth.fetch_sub(1, Ordering::Relaxed);
+        // Exponentially weighted, each request counting for 1/8
+        let latency = self.started.elapsed().as_millis() as u64;
+        let mut average = self.signal.latency_ms.load(Ordering::Relaxed);
+        while let Err(current) = self.signal.latency_ms.compare_exchange_weak(
+            average,
+            (average * 7 + latency) / 8,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            average = current;
+        }
+    }
+}
+
+/// Keep a signal's health in step with the service's health checks
+///
+/// Runs the checks every `interval` and reports their overall status. A
+/// checker that fails to run counts as unhealthy.
+pub fn spawn_health_updates(
+    signal: Arc<LoadSignal>,
+    checker: Arc<CompositeHealthChecker>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            let health = match checker.check_overall().await {
+                Ok(result) => ServiceHealth::from(&result.status),
+                Err(e) => {
+                    warn!(error = %e, "Health checks failed to run");
+                    ServiceHealth::Unhealthy
+                }
+            };
+            signal.set_health(health);
+        }
+    })
+}
+
+/// When and how much load to shed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheddingConfig {
+    /// Queue depth above which the service counts as overloaded
+    pub max_queue_depth: usize,
+    /// Latency above which the service counts as overloaded
+    pub max_latency: Duration,
+    /// Share of sheddable requests admitted while degraded or overloaded
+    pub degraded_admit_ratio: f64,
+    /// Share of sheddable requests admitted while unhealthy
+    pub unhealthy_admit_ratio: f64,
+    /// Delay suggested to shed clients in `Retry-After`
+    pub retry_after: Duration,
+    /// Route patterns that are never shed
+    pub critical_routes: HashSet<String>,
+    /// Route patterns that may respond with a stream; requests to them
+    /// that ask for `text/event-stream` are never shed
+    pub streaming_routes: HashSet<String>,
+}
+
+impl Default for SheddingConfig {
+    fn default() -> Self {
+        Self {
+            max_queue_depth: 1_000,
+            max_latency: Duration::from_secs(5),
+            degraded_admit_ratio: 0.25,
+            unhealthy_admit_ratio: 0.05,
+            retry_after: Duration::from_secs(5),
+            critical_routes: ["/health", "/ready"].into_iter().map(String::from).collect(),
+            streaming_routes: ["/api/v1/messages"].into_iter().map(String::from).collect(),
+        }
+    }
+}
+
+impl SheddingConfig {
+    /// Never shed requests to a route pattern
+    pub fn with_critical_route(mut self, route: impl Into<String>) -> Self {
+        self.critical_routes.insert(route.into());
+        self
+    }
+
+    /// Treat event stream requests to a route pattern as streams
+    pub fn with_streaming_route(mut self, route: impl Into<String>) -> Self {
+        self.streaming_routes.insert(route.into());
+        self
+    }
+}
+
+/// Decides which requests to shed
+#[derive(Debug, Default)]
+pub struct LoadShedder {
+    config: SheddingConfig,
+    signal: Arc<LoadSignal>,
+    /// Sheddable requests seen while shedding, for spreading admissions
+    arrivals: AtomicU64,
+}
+
+impl LoadShedder {
+    pub fn new(config: SheddingConfig, signal: Arc<LoadSignal>) -> Self {
+        Self {
+            config,
+            signal,
+            arrivals: AtomicU64::new(0),
+        }
+    }
+
+    /// The signal the shedder reads, for the server to update
+    pub fn signal(&self) -> Arc<LoadSignal> {
+        Arc::clone(&self.signal)
+    }
+
+    pub fn config(&self) -> &SheddingConfig {
+        &self.config
+    }
+
+    /// Share of sheddable requests to admit now; 1.0 when not overloaded
+    pub fn admit_ratio(&self) -> f64 {
+        let overloaded = self.signal.queue_depth() > self.config.max_queue_depth
+            || self.signal.latency() > self.config.max_latency;
+        match self.signal.health() {
+            ServiceHealth::Unhealthy => self.config.unhealthy_admit_ratio,
+            ServiceHealth::Degraded => self.config.degraded_admit_ratio,
+            ServiceHealth::Healthy if overloaded => self.config.degraded_admit_ratio,
+            ServiceHealth::Healthy => 1.0,
+        }
+    }
+
+    /// Whether a request to `route` should be admitted
+    ///
+    /// `wants_stream` is whether the request asks for an event stream; it
+    /// only counts on streaming routes.
+    pub fn admit(&self, route: &str, wants_stream: bool) -> bool {
+        let streaming = wants_stream && self.config.streaming_routes.contains(route);
+        if streaming || self.config.critical_routes.contains(route) {
+            return true;
+        }
+        let ratio = self.admit_ratio().clamp(0.0, 1.0);
+        if ratio >= 1.0 {
+            return true;
+        }
+        // Admit the n-th arrival whenever n * ratio crosses an integer, so
+        // admissions are spread evenly rather than bunched
+        let n = self.arrivals.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * ratio).floor() > (n * ratio).floor()
+    }
+}
+
+/// Load shedding middleware
+///
+/// Rejects sheddable requests with `503 Service Unavailable` and a
+/// `Retry-After` header while the shared signal reports overload.
+pub async fn shedding_middleware(
+    State(shedder): State<Arc<LoadShedder>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+
+    if shedder.admit(&route, accepts_event_stream(req.headers())) {
+        let _in_progress = shedder.signal.track_request();
+        return next.run(req).await;
+    }
+
+    debug!(route = %route, health = ?shedder.signal.health(), "Shedding request");
+    let retry_after = shedder.config.retry_after.as_secs().max(1);
+    let mut response = ApiError::ServiceUnavailable(format!(
+        "{} is shedding load, retry in {}s",
+        route, retry_after
+    ))
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, middleware as axum_middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn router(shedder: Arc<LoadShedder>) -> Router {
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .route("/api/v1/sessions/:id", get(|| async { "session" }))
+            .route("/api/v1/messages", get(|| async { "stream" }))
+            .route("/api/v1/sessions", get(|| async { "sessions" }))
+            .layer(axum_middleware::from_fn_with_state(shedder, shedding_middleware))
+    }
+
+    async fn call(app: Router, uri: &str, accept: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(accept) = accept {
+            request = request.header(header::ACCEPT, accept);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_degraded_signal_sheds_only_sheddable_routes() {
+        let config = SheddingConfig {
+            degraded_admit_ratio: 0.0,
+            ..Default::default()
+        };
+        let shedder = Arc::new(LoadShedder::new(config, Arc::new(LoadSignal::new())));
+        let app = router(shedder.clone());
+
+        let response = call(app.clone(), "/api/v1/sessions/s1", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        shedder.signal().set_health(ServiceHealth::Degraded);
+        let response = call(app.clone(), "/api/v1/sessions/s1", None).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+
+        // Critical routes and streams still pass
+        let response = call(app.clone(), "/health", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = call(app.clone(), "/api/v1/messages", Some("text/event-stream")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Asking for a stream does not exempt routes that never stream
+        let response = call(app.clone(), "/api/v1/sessions", Some("text/event-stream")).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response = call(app.clone(), "/api/v1/messages", None).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Metrics over their thresholds shed a healthy service too
+        shedder.signal().set_health(ServiceHealth::Healthy);
+        shedder.signal().set_queue_depth(5_000);
+        let response = call(app, "/api/v1/sessions/s1", None).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_requests_report_queue_depth_and_latency() {
+        let shedder = Arc::new(LoadShedder::default());
+        let signal = shedder.signal();
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let released = Arc::new(tokio::sync::Mutex::new(Some(released)));
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(move || {
+                    let released = released.clone();
+                    async move {
+                        if let Some(released) = released.lock().await.take() {
+                            let _ = released.await;
+                        }
+                        "done"
+                    }
+                }),
+            )
+            .layer(axum_middleware::from_fn_with_state(shedder, shedding_middleware));
+
+        let pending = tokio::spawn(call(app, "/slow", None));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(signal.queue_depth(), 1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        release.send(()).unwrap();
+        assert_eq!(pending.await.unwrap().status(), StatusCode::OK);
+        assert_eq!(signal.queue_depth(), 0);
+        // A 200ms request moves the average an eighth of the way
+        assert!(signal.latency() >= Duration::from_millis(25));
+    }
+
+    #[tokio::test]
+    async fn test_health_checks_feed_the_signal() {
+        use async_trait::async_trait;
+        use copilot_infra::health::{HealthCheck, HealthCheckResult};
+
+        struct Failing;
+
+        #[async_trait]
+        impl HealthCheck for Failing {
+            async fn check(&self) -> copilot_infra::Result<HealthCheckResult> {
+                Ok(HealthCheckResult::unhealthy("database down"))
+            }
+
+            fn name(&self) -> &str {
+                "database"
+            }
+        }
+
+        let signal = Arc::new(LoadSignal::new());
+        let checker = Arc::new(CompositeHealthChecker::new().add_check(Box::new(Failing)));
+        let updates = spawn_health_updates(signal.clone(), checker, Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        updates.abort();
+
+        assert_eq!(signal.health(), ServiceHealth::Unhealthy);
+    }
+
+    #[test]
+    fn test_shedding_admits_a_fair_share() {
+        let shedder = LoadShedder::new(SheddingConfig::default(), Arc::new(LoadSignal::new()));
+        shedder.signal().set_latency(Duration::from_secs(30));
+
+        let admitted: Vec<bool> = (0..8).map(|_| shedder.admit("/api/v1/sessions/:id", false)).collect();
+        // One in four, evenly spread
+        assert_eq!(admitted, [false, false, false, true, false, false, false, true]);
+
+        shedder.signal().set_health(ServiceHealth::Unhealthy);
+        let admitted = (0..100).filter(|_| shedder.admit("/api/v1/sessions/:id", false)).count();
+        assert_eq!(admitted, 5);
+    }
+}
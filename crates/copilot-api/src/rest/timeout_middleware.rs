@@ -0,0 +1,230 @@
+//! Per-route request timeout middleware.
+//!
+//! Slow handlers (context retrieval, LLM calls) must not hold a connection
+//! indefinitely. Each request gets the timeout of its route from
+//! [`RequestTimeouts`]; when it is exceeded the handler future is dropped
+//! and the client receives a `504`.
+//!
+//! Streaming responses (`text/event-stream`) are bounded by time to first
+//! byte instead of total duration: the response is held back until the
+//! body yields its first frame, and a stream that stays silent past the
+//! deadline is answered with `STREAM_TIMEOUT`. Once the first frame is
+//! out, the stream runs for as long as it needs.
+//!
+//! Dropping the handler future cancels everything it awaits, but not
+//! tasks it spawned. Handlers that spawn work should use the
+//! [`RequestTasks`] request extension, whose tasks are aborted when the
+//! request times out.
+
+use crate::{error::ApiError, rest::middleware::accepts_event_stream, types::RequestTimeouts};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use futures::{stream, StreamExt};
+use http_body_util::{BodyExt, BodyStream, StreamBody};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::{
+    task::{AbortHandle, JoinHandle},
+    time::{timeout_at, Instant},
+};
+use tracing::warn;
+
+/// Tasks spawned on behalf of a request
+///
+/// Available to handlers as a request extension. Tasks spawned through it
+/// are aborted if the request times out.
+#[derive(Debug, Clone, Default)]
+pub struct RequestTasks {
+    handles: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl RequestTasks {
+    /// Spawn a task tied to the request
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = tokio::spawn(future);
+        self.handles.lock().unwrap_or_else(PoisonError::into_inner).push(handle.abort_handle());
+        handle
+    }
+
+    /// Abort every task spawned so far
+    fn abort_all(&self) {
+        for handle in self.handles.lock().unwrap_or_else(PoisonError::into_inner).drain(..) {
+            handle.abort();
+        }
+    }
+}
+
+/// Request timeout middleware
+///
+/// Applies the timeout of the matched route, measured to completion for
+/// unary responses and to the first byte for streaming ones.
+pub async fn timeout_middleware(
+    State(timeouts): State<Arc<RequestTimeouts>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let limit = timeouts.for_route(route.as_deref());
+    let route = route.unwrap_or_else(|| req.uri().path().to_string());
+    let streaming = accepts_event_stream(req.headers());
+    let deadline = Instant::now() + limit;
+
+    let tasks = RequestTasks::default();
+    req.extensions_mut().insert(tasks.clone());
+
+    let response = match timeout_at(deadline, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => return Err(timed_out(&tasks, &route, limit, streaming)),
+    };
+    if !is_event_stream(response.headers()) {
+        return Ok(response);
+    }
+
+    // Hold the response until the stream has produced its first frame
+    let (parts, mut body) = response.into_parts();
+    match timeout_at(deadline, body.frame()).await {
+        Ok(Some(first)) => {
+            let rest = BodyStream::new(body);
+            let body = Body::new(StreamBody::new(stream::once(async { first }).chain(rest)));
+            Ok(Response::from_parts(parts, body))
+        }
+        Ok(None) => Ok(Response::from_parts(parts, Body::empty())),
+        Err(_) => Err(timed_out(&tasks, &route, limit, true)),
+    }
+}
+
+/// Cancel a timed-out request's tasks and build its error
+fn timed_out(tasks: &RequestTasks, route: &str, limit: Duration, streaming: bool) -> ApiError {
+    tasks.abort_all();
+    warn!(route = %route, timeout_ms = limit.as_millis() as u64, streaming, "Request timed out");
+    if streaming {
+        ApiError::StreamTimeout(format!("{} sent no data within {:?}", route, limit))
+    } else {
+        ApiError::RequestTimeout(format!("{} did not respond within {:?}", route, limit))
+    }
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::StatusCode,
+        middleware as axum_middleware,
+        response::IntoResponse,
+        routing::get,
+        Extension, Router,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tower::ServiceExt;
+
+    fn router(timeouts: RequestTimeouts, finished: Arc<AtomicBool>) -> Router {
+        Router::new()
+            .route(
+                "/slow",
+                get(move |Extension(tasks): Extension<RequestTasks>| async move {
+                    tasks.spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        finished.store(true, Ordering::SeqCst);
+                    });
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    "done"
+                }),
+            )
+            .route("/fast", get(|| async { "done" }))
+            .route("/stream/late", get(|| async { event_stream(Duration::from_millis(200)) }))
+            .route("/stream/long", get(|| async { event_stream(Duration::ZERO) }))
+            .layer(axum_middleware::from_fn_with_state(
+                Arc::new(timeouts),
+                timeout_middleware,
+            ))
+    }
+
+    /// An event stream whose first event is delayed, followed by events
+    /// spread over 150ms
+    fn event_stream(first_delay: Duration) -> Response {
+        let events = stream::iter(0..4).then(move |i| async move {
+            let delay = if i == 0 { first_delay } else { Duration::from_millis(50) };
+            tokio::time::sleep(delay).await;
+            Ok::<_, std::convert::Infallible>(format!("data: {}\n\n", i))
+        });
+        (
+            [(header::CONTENT_TYPE, "text/event-stream")],
+            Body::from_stream(events),
+        )
+            .into_response()
+    }
+
+    async fn call(app: Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_unary_handler_exceeding_timeout() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let timeouts = RequestTimeouts::new(Duration::from_secs(5))
+            .with_route("/slow", Duration::from_millis(50));
+
+        let (status, body) = call(router(timeouts.clone(), finished.clone()), "/slow").await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert!(body.contains("REQUEST_TIMEOUT"));
+
+        // The spawned task was aborted with the handler
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+
+        let (status, body) = call(router(timeouts, finished), "/fast").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "done");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_timeout_applies_to_first_byte() {
+        let timeouts = RequestTimeouts::new(Duration::from_millis(100));
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let (status, body) = call(router(timeouts.clone(), finished.clone()), "/stream/late").await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert!(body.contains("STREAM_TIMEOUT"));
+
+        // A prompt first byte lets the stream outlive the timeout
+        let (status, body) = call(router(timeouts, finished), "/stream/long").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "data: 0\n\ndata: 1\n\ndata: 2\n\ndata: 3\n\n");
+    }
+
+    #[test]
+    fn test_route_overrides() {
+        let timeouts = RequestTimeouts::default().with_route("/api/v1/messages", Duration::from_secs(90));
+        assert_eq!(timeouts.for_route(Some("/api/v1/messages")), Duration::from_secs(90));
+        assert_eq!(timeouts.for_route(Some("/api/v1/sessions")), timeouts.default);
+        assert_eq!(timeouts.for_route(None), timeouts.default);
+    }
+}
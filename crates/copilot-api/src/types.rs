@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
 use uuid::Uuid;
 
 /// Session creation request
@@ -31,6 +32,15 @@ pub struct SessionResponse {
     pub metadata: serde_json::Value,
 }
 
+/// Session deletion response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteSessionResponse {
+    /// ID of the deleted session
+    pub id: String,
+    /// Whether the session was deleted
+    pub deleted: bool,
+}
+
 /// Message send request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendMessageRequest {
@@ -159,7 +169,90 @@ pub struct HealthResponse {
     pub uptime: u64,
 }
 
+/// Rate limit state at the time a request was admitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    /// Maximum number of requests in the window
+    pub limit: u32,
+    /// Requests remaining in the current window
+    pub remaining: u32,
+    /// When the window is fully replenished
+    pub reset: DateTime<Utc>,
+}
+
+impl RateLimitInfo {
+    /// Render as `X-RateLimit-*` response headers
+    pub fn to_headers(&self) -> Vec<(String, String)> {
+        vec![
+            ("X-RateLimit-Limit".to_string(), self.limit.to_string()),
+            ("X-RateLimit-Remaining".to_string(), self.remaining.to_string()),
+            ("X-RateLimit-Reset".to_string(), self.reset.timestamp().to_string()),
+        ]
+    }
+}
+
+/// Timeout applied to routes without their own
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long each route may take to respond
+///
+/// Unary routes must complete within their timeout. Streaming routes
+/// (`text/event-stream`) must produce their first byte within it and may
+/// then stream for as long as they need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    /// Timeout of routes without an override
+    pub default: Duration,
+    /// Overrides keyed by route pattern, e.g. `/api/v1/messages`
+    pub routes: HashMap<String, Duration>,
+}
+
+impl RequestTimeouts {
+    /// Apply `default` to every route
+    pub fn new(default: Duration) -> Self {
+        Self {
+            default,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Override the timeout of one route pattern
+    pub fn with_route(mut self, route: impl Into<String>, timeout: Duration) -> Self {
+        self.routes.insert(route.into(), timeout);
+        self
+    }
+
+    /// Timeout of a route pattern
+    pub fn for_route(&self, route: Option<&str>) -> Duration {
+        route
+            .and_then(|route| self.routes.get(route))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUEST_TIMEOUT)
+    }
+}
+
+/// Metadata attached to every successful response envelope
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseMeta {
+    /// Request ID (mirrors the `x-request-id` header)
+    pub request_id: String,
+    /// Milliseconds spent in the handler
+    pub took_ms: u64,
+    /// Rate limit state, when the route is rate limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitInfo>,
+}
+
 /// API response wrapper
+///
+/// Successful responses carry `data` and `meta`. Failures are returned as
+/// [`ErrorResponse`](crate::error::ErrorResponse) bodies instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     /// Success flag
@@ -173,6 +266,9 @@ pub struct ApiResponse<T> {
     /// Execution graph (present when execution tracking is active)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub execution_graph: Option<serde_json::Value>,
+    /// Response metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ResponseMeta>,
 }
 
 impl<T> ApiResponse<T> {
@@ -183,6 +279,7 @@ impl<T> ApiResponse<T> {
             data: Some(data),
             error: None,
             execution_graph: None,
+            meta: None,
         }
     }
 
@@ -193,6 +290,7 @@ impl<T> ApiResponse<T> {
             data: None,
             error: Some(error),
             execution_graph: None,
+            meta: None,
         }
     }
 
@@ -201,6 +299,12 @@ impl<T> ApiResponse<T> {
         self.execution_graph = Some(graph);
         self
     }
+
+    /// Attach response metadata
+    pub fn with_meta(mut self, meta: ResponseMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
 }
 
 /// JWT claims
@@ -237,6 +341,21 @@ mod tests {
         assert_eq!(response.error, Some("test error".to_string()));
     }
 
+    #[test]
+    fn test_api_response_meta_serialization() {
+        let meta = ResponseMeta {
+            request_id: "req-1".to_string(),
+            took_ms: 12,
+            rate_limit: None,
+        };
+        let json = serde_json::to_value(ApiResponse::success(1).with_meta(meta)).unwrap();
+        assert_eq!(json["data"], 1);
+        assert_eq!(json["meta"]["request_id"], "req-1");
+        assert_eq!(json["meta"]["took_ms"], 12);
+        assert!(json["meta"].get("rate_limit").is_none());
+        assert!(json.get("error").is_none());
+    }
+
     #[test]
     fn test_message_role_serialization() {
         let role = MessageRole::User;
@@ -1,12 +1,16 @@
-// API Validation Module
-// Comprehensive request validation using validator crate
+//! Request validation
+//!
+//! Derive-based field validation with the `validator` crate plus business
+//! rules, reported as catalog [`ApiError`]s with per-field errors.
 
 use serde::{Deserialize, Serialize};
-use validator::{Validate, ValidationError};
+use validator::{Validate, ValidationError, ValidationErrorsKind};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use regex::Regex;
 use chrono::{DateTime, Utc, Duration};
-use uuid::Uuid;
+
+use crate::error_codes::{self, ApiError, ErrorCode};
 
 // ==================== VALIDATION TRAITS ====================
 
@@ -16,7 +20,7 @@ pub trait BusinessValidation {
 }
 
 /// Validation errors with detailed field information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ValidationErrors {
     pub errors: Vec<FieldError>,
 }
@@ -30,7 +34,7 @@ pub struct FieldError {
 
 impl ValidationErrors {
     pub fn new() -> Self {
-        Self { errors: Vec::new() }
+        Self::default()
     }
 
     pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
@@ -57,6 +61,136 @@ impl ValidationErrors {
     pub fn is_empty(&self) -> bool {
         self.errors.is_empty()
     }
+
+    /// Append the errors of another validation pass
+    pub fn extend(&mut self, other: ValidationErrors) {
+        self.errors.extend(other.errors);
+    }
+}
+
+// ==================== ERROR CONVERSION ====================
+
+/// Run derive-based and business-rule validation, collecting the errors of both
+pub fn validate_request<T: Validate + BusinessValidation>(request: &T) -> Result<(), ValidationErrors> {
+    let mut errors = match request.validate() {
+        Ok(()) => ValidationErrors::new(),
+        Err(e) => ValidationErrors::from(e),
+    };
+    if let Err(e) = request.validate_business_rules() {
+        errors.extend(e);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+impl From<validator::ValidationErrors> for ValidationErrors {
+    /// Flatten nested validator errors into field paths such as
+    /// `tasks[2].retry_policy.max_attempts`
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let mut flattened = ValidationErrors::new();
+        flatten_validator_errors("", &errors, &mut flattened);
+        flattened
+    }
+}
+
+fn flatten_validator_errors(prefix: &str, errors: &validator::ValidationErrors, out: &mut ValidationErrors) {
+    // Sort fields so the output order is stable; list items are already ordered
+    let mut fields: Vec<_> = errors.errors().iter().collect();
+    fields.sort_by_key(|(field, _)| **field);
+
+    for (field, kind) in fields {
+        let path = match (prefix.is_empty(), *field) {
+            // Struct-level errors belong to the enclosing path
+            (_, "__all__") => prefix.to_string(),
+            (true, field) => field.to_string(),
+            (false, field) => format!("{}.{}", prefix, field),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                for error in field_errors {
+                    let message = error
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| format!("Failed '{}' validation", error.code));
+                    out.add_with_constraint(path.clone(), message, constraint_of(error));
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => flatten_validator_errors(&path, nested, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_validator_errors(&format!("{}[{}]", path, index), nested, out);
+                }
+            }
+        }
+    }
+}
+
+/// Describe the violated constraint, e.g. `length(max=200, min=1)`
+fn constraint_of(error: &ValidationError) -> String {
+    let mut params: Vec<String> = error
+        .params
+        .iter()
+        .filter(|(name, _)| name.as_ref() != "value")
+        .map(|(name, value)| format!("{}={}", name, format_param(value)))
+        .collect();
+
+    if params.is_empty() {
+        return error.code.to_string();
+    }
+    params.sort();
+    format!("{}({})", error.code, params.join(", "))
+}
+
+fn format_param(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        // Range bounds are stored as floats; print whole numbers without ".0"
+        serde_json::Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < 1e15 => format!("{}", f as i64),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+impl From<FieldError> for error_codes::FieldError {
+    fn from(error: FieldError) -> Self {
+        Self {
+            field: error.field,
+            message: error.message,
+            constraint: error.constraint,
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let count = errors.errors.len();
+        ApiError::new(
+            ErrorCode::ValidationError,
+            format!("Request validation failed with {} error(s)", count),
+        )
+        .with_field_errors(errors.errors.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<validator::ValidationErrors> for ApiError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        ValidationErrors::from(errors).into()
+    }
+}
+
+/// Build a validation error with a message
+fn invalid(code: &'static str, message: Cow<'static, str>) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(message);
+    error
 }
 
 // ==================== SESSION VALIDATION ====================
@@ -92,18 +226,15 @@ lazy_static::lazy_static! {
 
 fn validate_metadata(metadata: &HashMap<String, String>) -> Result<(), ValidationError> {
     if metadata.len() > 20 {
-        return Err(ValidationError::new("metadata_max_properties")
-            .with_message("Maximum 20 metadata properties allowed".into()));
+        return Err(invalid("metadata_max_properties", "Maximum 20 metadata properties allowed".into()));
     }
 
     for (key, value) in metadata {
         if key.len() > 100 {
-            return Err(ValidationError::new("metadata_key_length")
-                .with_message("Metadata key too long".into()));
+            return Err(invalid("metadata_key_length", "Metadata key too long".into()));
         }
         if value.len() > 1000 {
-            return Err(ValidationError::new("metadata_value_length")
-                .with_message("Metadata value too long".into()));
+            return Err(invalid("metadata_value_length", "Metadata value too long".into()));
         }
     }
 
@@ -129,8 +260,10 @@ fn validate_language(language: &str) -> Result<(), ValidationError> {
     const SUPPORTED_LANGUAGES: &[&str] = &["en", "es", "fr", "de", "ja", "zh"];
 
     if !SUPPORTED_LANGUAGES.contains(&language) {
-        return Err(ValidationError::new("unsupported_language")
-            .with_message(format!("Language must be one of: {}", SUPPORTED_LANGUAGES.join(", ")).into()));
+        return Err(invalid(
+            "unsupported_language",
+            format!("Language must be one of: {}", SUPPORTED_LANGUAGES.join(", ")).into(),
+        ));
     }
     Ok(())
 }
@@ -155,14 +288,12 @@ pub struct SendMessageRequest {
 fn validate_message_content(content: &str) -> Result<(), ValidationError> {
     // Check for suspicious patterns
     if content.trim().is_empty() {
-        return Err(ValidationError::new("content_empty")
-            .with_message("Content cannot be only whitespace".into()));
+        return Err(invalid("content_empty", "Content cannot be only whitespace".into()));
     }
 
     // Check for potential injection attacks
     if content.contains("<?php") || content.contains("<script") {
-        return Err(ValidationError::new("content_suspicious")
-            .with_message("Content contains potentially malicious code".into()));
+        return Err(invalid("content_suspicious", "Content contains potentially malicious code".into()));
     }
 
     Ok(())
@@ -194,8 +325,10 @@ fn validate_attachment_type(attachment_type: &str) -> Result<(), ValidationError
     const VALID_TYPES: &[&str] = &["file", "url", "image", "code"];
 
     if !VALID_TYPES.contains(&attachment_type) {
-        return Err(ValidationError::new("invalid_attachment_type")
-            .with_message(format!("Type must be one of: {}", VALID_TYPES.join(", ")).into()));
+        return Err(invalid(
+            "invalid_attachment_type",
+            format!("Type must be one of: {}", VALID_TYPES.join(", ")).into(),
+        ));
     }
     Ok(())
 }
@@ -246,6 +379,7 @@ pub struct CreateWorkflowRequest {
     #[validate(length(max = 100))]
     pub template: Option<String>,
 
+    #[validate]
     #[validate(length(min = 1, max = 100, message = "Must have 1-100 tasks"))]
     pub tasks: Vec<WorkflowTask>,
 
@@ -294,8 +428,10 @@ fn validate_task_type(task_type: &str) -> Result<(), ValidationError> {
     ];
 
     if !VALID_TYPES.contains(&task_type) {
-        return Err(ValidationError::new("invalid_task_type")
-            .with_message(format!("Type must be one of: {}", VALID_TYPES.join(", ")).into()));
+        return Err(invalid(
+            "invalid_task_type",
+            format!("Type must be one of: {}", VALID_TYPES.join(", ")).into(),
+        ));
     }
     Ok(())
 }
@@ -319,8 +455,10 @@ fn validate_backoff_strategy(strategy: &str) -> Result<(), ValidationError> {
     const VALID_STRATEGIES: &[&str] = &["exponential", "linear", "constant"];
 
     if !VALID_STRATEGIES.contains(&strategy) {
-        return Err(ValidationError::new("invalid_backoff_strategy")
-            .with_message(format!("Strategy must be one of: {}", VALID_STRATEGIES.join(", ")).into()));
+        return Err(invalid(
+            "invalid_backoff_strategy",
+            format!("Strategy must be one of: {}", VALID_STRATEGIES.join(", ")).into(),
+        ));
     }
     Ok(())
 }
@@ -331,18 +469,19 @@ impl BusinessValidation for CreateWorkflowRequest {
 
         // Validate task dependencies form a DAG (no cycles)
         if let Err(e) = validate_dag(&self.tasks) {
-            errors.add("tasks", e);
+            errors.add_with_constraint("tasks", e, "acyclic");
         }
 
         // Validate all dependency references exist
         let task_ids: Vec<&str> = self.tasks.iter().map(|t| t.id.as_str()).collect();
-        for task in &self.tasks {
+        for (index, task) in self.tasks.iter().enumerate() {
             if let Some(deps) = &task.depends_on {
                 for dep in deps {
                     if !task_ids.contains(&dep.as_str()) {
-                        errors.add(
-                            format!("tasks[{}].depends_on", task.id),
+                        errors.add_with_constraint(
+                            format!("tasks[{}].depends_on", index),
                             format!("Dependency '{}' not found", dep),
+                            "existing_task",
                         );
                     }
                 }
@@ -351,9 +490,13 @@ impl BusinessValidation for CreateWorkflowRequest {
 
         // Validate unique task IDs
         let mut seen_ids = std::collections::HashSet::new();
-        for task in &self.tasks {
+        for (index, task) in self.tasks.iter().enumerate() {
             if !seen_ids.insert(&task.id) {
-                errors.add("tasks", format!("Duplicate task ID: {}", task.id));
+                errors.add_with_constraint(
+                    format!("tasks[{}].id", index),
+                    format!("Duplicate task ID: {}", task.id),
+                    "unique",
+                );
             }
         }
 
@@ -371,12 +514,15 @@ fn validate_dag(tasks: &[WorkflowTask]) -> Result<(), String> {
     let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
     let mut in_degree: HashMap<&str, usize> = HashMap::new();
 
-    // Build adjacency list and in-degree map
+    let task_ids: HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+
+    // Build adjacency list and in-degree map; unknown dependencies are
+    // reported separately and cannot form a cycle
     for task in tasks {
         in_degree.entry(&task.id).or_insert(0);
 
         if let Some(deps) = &task.depends_on {
-            for dep in deps {
+            for dep in deps.iter().filter(|d| task_ids.contains(d.as_str())) {
                 graph.entry(dep.as_str()).or_default().push(&task.id);
                 *in_degree.entry(&task.id).or_insert(0) += 1;
             }
@@ -448,10 +594,9 @@ fn validate_promql_safety(query: &str) -> Result<(), ValidationError> {
     }
 
     // Limit number of operators to prevent resource exhaustion
-    let operator_count = query.matches(|c: char| c == '+' || c == '-' || c == '*' || c == '/').count();
+    let operator_count = query.matches(['+', '-', '*', '/']).count();
     if operator_count > 50 {
-        return Err(ValidationError::new("query_too_complex")
-            .with_message("Query contains too many operators".into()));
+        return Err(invalid("query_too_complex", "Query contains too many operators".into()));
     }
 
     Ok(())
@@ -561,22 +706,22 @@ fn validate_severity(severity: &str) -> Result<(), ValidationError> {
     const VALID_SEVERITIES: &[&str] = &["critical", "high", "medium", "low"];
 
     if !VALID_SEVERITIES.contains(&severity) {
-        return Err(ValidationError::new("invalid_severity")
-            .with_message(format!("Severity must be one of: {}", VALID_SEVERITIES.join(", ")).into()));
+        return Err(invalid(
+            "invalid_severity",
+            format!("Severity must be one of: {}", VALID_SEVERITIES.join(", ")).into(),
+        ));
     }
     Ok(())
 }
 
 fn validate_services_list(services: &[String]) -> Result<(), ValidationError> {
     if services.len() > 50 {
-        return Err(ValidationError::new("too_many_services")
-            .with_message("Maximum 50 affected services allowed".into()));
+        return Err(invalid("too_many_services", "Maximum 50 affected services allowed".into()));
     }
 
     for service in services {
         if service.len() > 100 {
-            return Err(ValidationError::new("service_name_too_long")
-                .with_message("Service name too long (max 100 characters)".into()));
+            return Err(invalid("service_name_too_long", "Service name too long (max 100 characters)".into()));
         }
     }
 
@@ -660,9 +805,7 @@ impl RateLimitInfo {
 
 /// Validation middleware for Axum
 pub mod middleware {
-    use super::*;
     use axum::{
-        body::Body,
         extract::Request,
         http::StatusCode,
         middleware::Next,
@@ -674,14 +817,14 @@ pub mod middleware {
     pub async fn validate_content_length(
         request: Request,
         next: Next,
-    ) -> Result<Response, Response> {
+    ) -> Response {
         const MAX_CONTENT_LENGTH: u64 = 10 * 1024 * 1024; // 10MB
 
         if let Some(content_length) = request.headers().get("content-length") {
             if let Ok(length_str) = content_length.to_str() {
                 if let Ok(length) = length_str.parse::<u64>() {
                     if length > MAX_CONTENT_LENGTH {
-                        return Err((
+                        return (
                             StatusCode::PAYLOAD_TOO_LARGE,
                             Json(json!({
                                 "error": {
@@ -689,25 +832,25 @@ pub mod middleware {
                                     "message": format!("Request body too large. Maximum size: {} bytes", MAX_CONTENT_LENGTH)
                                 }
                             }))
-                        ).into_response());
+                        ).into_response();
                     }
                 }
             }
         }
 
-        Ok(next.run(request).await)
+        next.run(request).await
     }
 
     pub async fn validate_content_type(
         request: Request,
         next: Next,
-    ) -> Result<Response, Response> {
+    ) -> Response {
         if request.method() == "POST" || request.method() == "PUT" || request.method() == "PATCH" {
             if let Some(content_type) = request.headers().get("content-type") {
                 let content_type = content_type.to_str().unwrap_or("");
 
                 if !content_type.starts_with("application/json") {
-                    return Err((
+                    return (
                         StatusCode::UNSUPPORTED_MEDIA_TYPE,
                         Json(json!({
                             "error": {
@@ -715,10 +858,10 @@ pub mod middleware {
                                 "message": "Content-Type must be application/json"
                             }
                         }))
-                    ).into_response());
+                    ).into_response();
                 }
             } else {
-                return Err((
+                return (
                     StatusCode::BAD_REQUEST,
                     Json(json!({
                         "error": {
@@ -726,11 +869,11 @@ pub mod middleware {
                             "message": "Content-Type header required"
                         }
                     }))
-                ).into_response());
+                ).into_response();
             }
         }
 
-        Ok(next.run(request).await)
+        next.run(request).await
     }
 }
 
@@ -872,4 +1015,67 @@ mod tests {
             "test\\%value\\_"
         );
     }
+
+    fn task(id: &str, depends_on: &[&str], timeout_seconds: Option<u32>) -> WorkflowTask {
+        WorkflowTask {
+            id: id.to_string(),
+            task_type: "command".to_string(),
+            name: None,
+            depends_on: Some(depends_on.iter().map(|d| d.to_string()).collect()),
+            config: HashMap::new(),
+            retry_policy: None,
+            timeout_seconds,
+        }
+    }
+
+    #[test]
+    fn test_multi_field_failure_produces_complete_field_errors() {
+        let request = CreateWorkflowRequest {
+            name: String::new(),
+            description: None,
+            template: None,
+            tasks: vec![
+                task("task1", &[], None),
+                task("task2", &["task1"], None),
+                task("task3", &["missing"], Some(0)),
+            ],
+            auto_execute: None,
+            approval_required: None,
+            rollback_on_failure: None,
+        };
+
+        let api_error: ApiError = validate_request(&request).unwrap_err().into();
+        assert_eq!(api_error.code, ErrorCode::ValidationError);
+
+        let fields: Vec<(&str, Option<&str>)> = api_error
+            .field_errors
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|e| (e.field.as_str(), e.constraint.as_deref()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("name", Some("length(max=200, min=1)")),
+                ("tasks[2].timeout_seconds", Some("range(max=3600, min=1)")),
+                ("tasks[2].depends_on", Some("existing_task")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_valid_request_has_no_field_errors() {
+        let request = CreateWorkflowRequest {
+            name: "Deploy".to_string(),
+            description: None,
+            template: None,
+            tasks: vec![task("task1", &[], Some(30))],
+            auto_execute: None,
+            approval_required: None,
+            rollback_on_failure: None,
+        };
+
+        assert!(validate_request(&request).is_ok());
+    }
 }
@@ -4,8 +4,10 @@
 //! including summarization, truncation, and intelligent content reduction.
 
 use crate::{ContextError, MemoryItem, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Compression strategy enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -50,6 +52,14 @@ pub struct CompressionConfig {
 
     /// Enable aggressive compression when needed
     pub allow_aggressive: bool,
+
+    /// Don't compress items created less than this many seconds ago
+    #[serde(default)]
+    pub skip_newer_than_secs: Option<u64>,
+
+    /// Don't compress items accessed less than this many seconds ago
+    #[serde(default)]
+    pub skip_accessed_within_secs: Option<u64>,
 }
 
 impl Default for CompressionConfig {
@@ -61,11 +71,47 @@ impl Default for CompressionConfig {
             max_tokens_per_item: 2000,
             preserve_important: true,
             allow_aggressive: false,
+            skip_newer_than_secs: None,
+            skip_accessed_within_secs: None,
         }
     }
 }
 
 impl CompressionConfig {
+    /// Keep items created within `age` verbatim
+    pub fn skip_newer_than(mut self, age: Duration) -> Self {
+        self.skip_newer_than_secs = Some(age.as_secs());
+        self
+    }
+
+    /// Keep items accessed within `idle` verbatim
+    pub fn skip_accessed_within(mut self, idle: Duration) -> Self {
+        self.skip_accessed_within_secs = Some(idle.as_secs());
+        self
+    }
+
+    /// A stronger variant of this configuration, compressing to
+    /// `target_ratio` of the original size
+    pub fn aggressive(&self, target_ratio: f64) -> Self {
+        Self {
+            target_ratio,
+            allow_aggressive: true,
+            ..self.clone()
+        }
+    }
+
+    /// Whether an item is too fresh to compress
+    ///
+    /// Recent and recently read items are likely to be read again soon, so
+    /// they are kept verbatim while stale items are compressed.
+    pub fn is_recent(&self, item: &MemoryItem, now: DateTime<Utc>) -> bool {
+        let within = |time: DateTime<Utc>, secs: Option<u64>| {
+            secs.is_some_and(|secs| (now - time).num_seconds() < secs as i64)
+        };
+        within(item.created_at, self.skip_newer_than_secs)
+            || within(item.last_accessed, self.skip_accessed_within_secs)
+    }
+
     pub fn validate(&self) -> Result<()> {
         if self.target_ratio <= 0.0 || self.target_ratio > 1.0 {
             return Err(ContextError::CompressionFailed(
@@ -414,11 +460,35 @@ impl CompressionMetrics {
     }
 }
 
+/// Tokens set aside with [`TokenBudgetManager::reserve`]
+///
+/// Hand the handle back to [`TokenBudgetManager::release`] once the
+/// reserved space is no longer needed, e.g. when the completion it was
+/// earmarked for has arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReservationHandle {
+    id: u64,
+    tokens: usize,
+}
+
+impl ReservationHandle {
+    /// Number of tokens reserved
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+}
+
 /// Token budget manager
+///
+/// Tracks the tokens of stored items, plus reservations: headroom set
+/// aside, e.g. for a model's response, that counts against the budget
+/// without belonging to any item.
 pub struct TokenBudgetManager {
     max_tokens: usize,
     target_utilization: f64,
     current_tokens: usize,
+    reservations: HashMap<u64, usize>,
+    next_reservation: u64,
 }
 
 impl TokenBudgetManager {
@@ -427,6 +497,8 @@ impl TokenBudgetManager {
             max_tokens,
             target_utilization,
             current_tokens: 0,
+            reservations: HashMap::new(),
+            next_reservation: 0,
         }
     }
 
@@ -435,30 +507,41 @@ impl TokenBudgetManager {
         (self.max_tokens as f64 * self.target_utilization) as usize
     }
 
+    /// Tokens attributed to stored items
+    pub fn current_tokens(&self) -> usize {
+        self.current_tokens
+    }
+
+    /// Tokens held by reservations
+    pub fn reserved_tokens(&self) -> usize {
+        self.reservations.values().sum()
+    }
+
+    /// Tokens counting against the budget: stored and reserved
+    fn used_tokens(&self) -> usize {
+        self.current_tokens + self.reserved_tokens()
+    }
+
     /// Check if we need compression
     pub fn needs_compression(&self) -> bool {
-        self.current_tokens > self.target_budget()
+        self.used_tokens() > self.target_budget()
     }
 
     /// Calculate how many tokens need to be freed
     pub fn tokens_to_free(&self) -> usize {
-        if self.current_tokens > self.target_budget() {
-            self.current_tokens - self.target_budget()
-        } else {
-            0
-        }
+        self.used_tokens().saturating_sub(self.target_budget())
     }
 
     /// Add tokens to current count
     pub fn add_tokens(&mut self, tokens: usize) -> Result<()> {
-        let new_total = self.current_tokens + tokens;
+        let new_total = self.used_tokens() + tokens;
         if new_total > self.max_tokens {
             return Err(ContextError::TokenLimitExceeded {
                 current: new_total,
                 limit: self.max_tokens,
             });
         }
-        self.current_tokens = new_total;
+        self.current_tokens += tokens;
         Ok(())
     }
 
@@ -467,14 +550,48 @@ impl TokenBudgetManager {
         self.current_tokens = self.current_tokens.saturating_sub(tokens);
     }
 
+    /// Forget the tokens of all stored items, keeping reservations
+    pub fn reset_usage(&mut self) {
+        self.current_tokens = 0;
+    }
+
+    /// Reserve headroom that stored items cannot use
+    ///
+    /// Fails with [`ContextError::TokenLimitExceeded`] if the reservation
+    /// does not fit next to what is already stored and reserved.
+    pub fn reserve(&mut self, tokens: usize) -> Result<ReservationHandle> {
+        let new_total = self.used_tokens() + tokens;
+        if new_total > self.max_tokens {
+            return Err(ContextError::TokenLimitExceeded {
+                current: new_total,
+                limit: self.max_tokens,
+            });
+        }
+        let id = self.next_reservation;
+        self.next_reservation += 1;
+        self.reservations.insert(id, tokens);
+        Ok(ReservationHandle { id, tokens })
+    }
+
+    /// Return reserved tokens to the budget
+    ///
+    /// Fails with [`ContextError::ReservationNotFound`] if the handle was
+    /// already released.
+    pub fn release(&mut self, handle: ReservationHandle) -> Result<()> {
+        self.reservations
+            .remove(&handle.id)
+            .map(|_| ())
+            .ok_or(ContextError::ReservationNotFound(handle.id))
+    }
+
     /// Get current utilization (0.0 - 1.0)
     pub fn utilization(&self) -> f64 {
-        self.current_tokens as f64 / self.max_tokens as f64
+        self.used_tokens() as f64 / self.max_tokens as f64
     }
 
     /// Check if within budget
     pub fn is_within_budget(&self) -> bool {
-        self.current_tokens <= self.target_budget()
+        self.used_tokens() <= self.target_budget()
     }
 }
 
@@ -502,6 +619,27 @@ mod tests {
         assert!(invalid_config.validate().is_err());
     }
 
+    #[test]
+    fn test_recent_items() {
+        let config = CompressionConfig::default()
+            .skip_newer_than(Duration::from_secs(3600))
+            .skip_accessed_within(Duration::from_secs(600));
+        let now = Utc::now();
+
+        let mut item = create_test_item("content", 100);
+        assert!(config.is_recent(&item, now));
+
+        // Old but recently read
+        item.created_at = now - chrono::Duration::hours(2);
+        assert!(config.is_recent(&item, now));
+
+        item.last_accessed = now - chrono::Duration::minutes(30);
+        assert!(!config.is_recent(&item, now));
+
+        // No rule: nothing is protected
+        assert!(!CompressionConfig::default().is_recent(&create_test_item("content", 100), now));
+    }
+
     #[test]
     fn test_truncation() {
         let config = CompressionConfig {
@@ -591,4 +729,35 @@ More text here.
         assert!(extracted.contains("Code:"));
         assert!(extracted.contains("fn main"));
     }
+
+    #[test]
+    fn test_reservations_count_against_the_budget() {
+        let mut budget = TokenBudgetManager::new(1_000, 0.8);
+        budget.add_tokens(500).unwrap();
+
+        let handle = budget.reserve(400).unwrap();
+        assert_eq!(handle.tokens(), 400);
+        assert_eq!(budget.reserved_tokens(), 400);
+        assert_eq!(budget.current_tokens(), 500);
+        assert!(!budget.is_within_budget());
+        assert_eq!(budget.tokens_to_free(), 100);
+
+        // Stored items cannot use the reserved headroom
+        assert!(budget.add_tokens(200).is_err());
+        budget.add_tokens(100).unwrap();
+
+        // Over-reservation fails and leaves the budget unchanged
+        assert!(matches!(
+            budget.reserve(1),
+            Err(ContextError::TokenLimitExceeded { current: 1_001, limit: 1_000 })
+        ));
+        assert_eq!(budget.reserved_tokens(), 400);
+
+        budget.release(handle).unwrap();
+        assert_eq!(budget.reserved_tokens(), 0);
+        assert!(matches!(budget.release(handle), Err(ContextError::ReservationNotFound(_))));
+
+        budget.reset_usage();
+        assert_eq!(budget.utilization(), 0.0);
+    }
 }
@@ -0,0 +1,225 @@
+//! Disk-backed memory store
+//!
+//! [`DiskStore`] keeps each item as a JSON file, `<id>.json`, in a
+//! directory, so a tier survives process restarts. Items are also held in
+//! memory: reads never touch the disk, and writes go to the file before
+//! the in-memory copy changes. A file is written to a temporary name and
+//! renamed into place, so a crash mid-write leaves the previous version.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    memory::{MemoryItem, MemoryStore, MemoryTier},
+    ContextError, Result,
+};
+
+/// Memory store persisting one JSON file per item
+pub struct DiskStore {
+    dir: PathBuf,
+    items: HashMap<Uuid, MemoryItem>,
+    tier: MemoryTier,
+}
+
+impl DiskStore {
+    /// Open the store in `dir`, creating the directory if needed and
+    /// loading the items already in it
+    ///
+    /// Files that cannot be read as an item are skipped with a warning.
+    pub fn open(dir: impl Into<PathBuf>, tier: MemoryTier) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| storage_error("create", &dir, e))?;
+
+        let mut items = HashMap::new();
+        let entries = std::fs::read_dir(&dir).map_err(|e| storage_error("read", &dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| storage_error("read", &dir, e))?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let item = std::fs::read(&path)
+                .map_err(|e| storage_error("read", &path, e))
+                .and_then(|bytes| Ok(serde_json::from_slice::<MemoryItem>(&bytes)?));
+            match item {
+                Ok(mut item) => {
+                    item.tier = tier;
+                    items.insert(item.metadata.id, item);
+                }
+                Err(e) => warn!(path = %path.display(), error = %e, "Skipping unreadable context item"),
+            }
+        }
+
+        debug!("Loaded {} context items from {}", items.len(), dir.display());
+        Ok(Self { dir, items, tier })
+    }
+
+    /// Directory the items are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Tier the store holds
+    pub fn tier(&self) -> MemoryTier {
+        self.tier
+    }
+
+    /// Items currently stored
+    pub fn items(&self) -> impl Iterator<Item = &MemoryItem> {
+        self.items.values()
+    }
+
+    fn item_path(&self, id: &Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    async fn write_item(&self, item: &MemoryItem) -> Result<()> {
+        let path = self.item_path(&item.metadata.id);
+        let temp = path.with_extension("json.tmp");
+        let bytes = serde_json::to_vec(item)?;
+        tokio::fs::write(&temp, bytes)
+            .await
+            .map_err(|e| storage_error("write", &temp, e))?;
+        tokio::fs::rename(&temp, &path)
+            .await
+            .map_err(|e| storage_error("write", &path, e))
+    }
+
+    async fn delete_item(&self, id: &Uuid) -> Result<()> {
+        let path = self.item_path(id);
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(storage_error("remove", &path, e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl MemoryStore for DiskStore {
+    async fn store(&mut self, mut item: MemoryItem) -> Result<()> {
+        item.tier = self.tier;
+        self.write_item(&item).await?;
+        self.items.insert(item.metadata.id, item);
+        Ok(())
+    }
+
+    async fn retrieve(&self, id: &Uuid) -> Result<Option<MemoryItem>> {
+        Ok(self.items.get(id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<MemoryItem>> {
+        Ok(self.items.values().cloned().collect())
+    }
+
+    async fn remove(&mut self, id: &Uuid) -> Result<()> {
+        self.delete_item(id).await?;
+        self.items.remove(id);
+        Ok(())
+    }
+
+    async fn update(&mut self, item: MemoryItem) -> Result<()> {
+        self.store(item).await
+    }
+
+    async fn total_tokens(&self) -> Result<usize> {
+        Ok(self.items.values().map(|item| item.token_count).sum())
+    }
+
+    async fn clear(&mut self) -> Result<()> {
+        let ids: Vec<Uuid> = self.items.keys().copied().collect();
+        for id in ids {
+            self.remove(&id).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_by_tier(&self, tier: MemoryTier) -> Result<Vec<MemoryItem>> {
+        Ok(self
+            .items
+            .values()
+            .filter(|item| item.tier == tier)
+            .cloned()
+            .collect())
+    }
+
+    async fn evict(&mut self, target_tokens: usize) -> Result<Vec<MemoryItem>> {
+        let mut current_tokens = self.total_tokens().await?;
+        if current_tokens <= target_tokens {
+            return Ok(Vec::new());
+        }
+
+        let mut items: Vec<_> = self.items.values().cloned().collect();
+        items.sort_by(|a, b| a.current_importance().total_cmp(&b.current_importance()));
+
+        let mut evicted = Vec::new();
+        for item in items {
+            if current_tokens <= target_tokens {
+                break;
+            }
+            self.remove(&item.metadata.id).await?;
+            current_tokens -= item.token_count;
+            evicted.push(item);
+        }
+        Ok(evicted)
+    }
+}
+
+fn storage_error(action: &str, path: &Path, error: std::io::Error) -> ContextError {
+    ContextError::StorageError(format!("Failed to {} {}: {}", action, path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryMetadata;
+
+    fn item(content: &str, importance: f64, tokens: usize) -> MemoryItem {
+        MemoryItem::new(content.to_string(), MemoryMetadata::new("test", "test"), importance, tokens)
+    }
+
+    #[tokio::test]
+    async fn test_items_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = item("kept", 0.9, 10);
+        let removed = item("removed", 0.9, 20);
+        {
+            let mut store = DiskStore::open(dir.path(), MemoryTier::LongTerm).unwrap();
+            store.store(kept.clone()).await.unwrap();
+            store.store(removed.clone()).await.unwrap();
+            store.remove(&removed.metadata.id).await.unwrap();
+
+            let mut updated = kept.clone();
+            updated.access_count = 3;
+            store.update(updated).await.unwrap();
+        }
+        // Stray files are ignored
+        std::fs::write(dir.path().join("notes.txt"), "not an item").unwrap();
+        std::fs::write(dir.path().join("broken.json"), "{").unwrap();
+
+        let store = DiskStore::open(dir.path(), MemoryTier::LongTerm).unwrap();
+        let items = store.list().await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].metadata.id, kept.metadata.id);
+        assert_eq!(items[0].access_count, 3);
+        assert_eq!(store.total_tokens().await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_evict_removes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = DiskStore::open(dir.path(), MemoryTier::LongTerm).unwrap();
+        store.store(item("minor", 0.7, 30)).await.unwrap();
+        let major = item("major", 1.0, 30);
+        store.store(major.clone()).await.unwrap();
+
+        let evicted = store.evict(30).await.unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].content, "minor");
+
+        let reopened = DiskStore::open(dir.path(), MemoryTier::LongTerm).unwrap();
+        let ids: Vec<Uuid> = reopened.list().await.unwrap().iter().map(|i| i.metadata.id).collect();
+        assert_eq!(ids, [major.metadata.id]);
+    }
+}
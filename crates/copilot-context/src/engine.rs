@@ -4,15 +4,29 @@
 
 use async_trait::async_trait;
 use dashmap::DashMap;
+use futures::stream::{self, BoxStream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::BinaryHeap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tiktoken_rs::{get_bpe_from_model, CoreBPE};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
 use crate::{
-    compression::{CompressionConfig, Compressor, TokenBudgetManager},
-    memory::{ImportanceScorer, InMemoryStore, MemoryItem, MemoryMetadata, MemoryStore, MemoryTier},
-    retrieval::{ContextWindow, RetrievalConfig, RetrievalResult},
+    compression::{CompressionConfig, Compressor, ReservationHandle, TokenBudgetManager},
+    disk::DiskStore,
+    expansion::ExpandedQuery,
+    export::VectorRecord,
+    hooks::{run_hook, ContextHooks},
+    hybrid_search::EmbeddingProvider,
+    importance::{resolve_importance, DefaultImportanceInferer, ImportanceInferer, IMPORTANCE_KEY, INFERRED_KEY},
+    memory::{
+        eviction_candidates, ImportanceScorer, InMemoryStore, MemoryFilter, MemoryItem,
+        MemoryMetadata, MemoryStore, MemoryTier,
+    },
+    retrieval::{ContextWindow, RetrievalConfig, RetrievalResult, ScoredItem},
+    tokenizer::{Tokenizer, TokenizerConfig},
     ContextError, Result,
 };
 
@@ -39,6 +53,28 @@ pub struct ContextEngineConfig {
 
     /// Model for token counting (e.g., "gpt-4", "gpt-3.5-turbo")
     pub tokenizer_model: String,
+
+    /// How tokens are counted; models tiktoken does not know are
+    /// approximated
+    #[serde(default)]
+    pub tokenizer: TokenizerConfig,
+
+    /// Directory persisting the long-term tier, which is otherwise kept in
+    /// memory only
+    #[serde(default)]
+    pub long_term_path: Option<PathBuf>,
+
+    /// Maximum tokens in the short-term tier, on top of `max_tokens`
+    #[serde(default)]
+    pub short_term_max_tokens: Option<usize>,
+
+    /// Maximum tokens in the medium-term tier, on top of `max_tokens`
+    #[serde(default)]
+    pub medium_term_max_tokens: Option<usize>,
+
+    /// Maximum tokens in the long-term tier, on top of `max_tokens`
+    #[serde(default)]
+    pub long_term_max_tokens: Option<usize>,
 }
 
 impl Default for ContextEngineConfig {
@@ -51,6 +87,22 @@ impl Default for ContextEngineConfig {
             auto_tier_management: true,
             auto_compress_threshold: 0.85,
             tokenizer_model: "gpt-4".to_string(),
+            tokenizer: TokenizerConfig::default(),
+            long_term_path: None,
+            short_term_max_tokens: None,
+            medium_term_max_tokens: None,
+            long_term_max_tokens: None,
+        }
+    }
+}
+
+impl ContextEngineConfig {
+    /// Token cap of a tier, if it has one
+    pub fn tier_max_tokens(&self, tier: MemoryTier) -> Option<usize> {
+        match tier {
+            MemoryTier::ShortTerm => self.short_term_max_tokens,
+            MemoryTier::MediumTerm => self.medium_term_max_tokens,
+            MemoryTier::LongTerm => self.long_term_max_tokens,
         }
     }
 }
@@ -66,15 +118,52 @@ pub trait ContextEngine: Send + Sync {
         importance: f64,
     ) -> Result<Uuid>;
 
+    /// Store context with an importance inferred from its content and
+    /// metadata
+    ///
+    /// An `importance` entry in the metadata's custom fields overrides the
+    /// inferred value. Engines without their own inferer use
+    /// [`DefaultImportanceInferer`].
+    async fn store_inferred(&self, content: String, metadata: MemoryMetadata) -> Result<Uuid> {
+        let importance = resolve_importance(
+            &DefaultImportanceInferer::default(),
+            &content,
+            &metadata,
+            chrono::Utc::now(),
+        );
+        self.store(content, metadata, importance).await
+    }
+
+    /// Store context with an automatically scored importance, as
+    /// [`store_inferred`](Self::store_inferred) does
+    async fn store_auto(&self, content: String, metadata: MemoryMetadata) -> Result<Uuid> {
+        self.store_inferred(content, metadata).await
+    }
+
     /// Retrieve relevant context within token budget
     async fn retrieve(&self, query: &str) -> Result<RetrievalResult>;
 
+    /// Retrieve relevant context for a query with expanded terms
+    ///
+    /// Engines that do not support expansion retrieve with the original query.
+    async fn retrieve_expanded(&self, query: &ExpandedQuery) -> Result<RetrievalResult> {
+        self.retrieve(&query.original).await
+    }
+
     /// Compress context when approaching limits
     async fn compress(&self) -> Result<CompressionStats>;
 
     /// Get current statistics
     async fn stats(&self) -> Result<EngineStats>;
 
+    /// Token capacity of a tier
+    ///
+    /// Engines with configured tier caps report them; others report the
+    /// tier's nominal [`MemoryTier::token_capacity`].
+    fn tier_capacity(&self, tier: MemoryTier) -> usize {
+        tier.token_capacity()
+    }
+
     /// Manually promote item to higher tier
     async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()>;
 
@@ -89,31 +178,82 @@ pub trait ContextEngine: Send + Sync {
 
     /// Run maintenance (tier management, compression, eviction)
     async fn maintenance(&self) -> Result<MaintenanceReport>;
+
+    /// Stream every stored item as a record for a vector database
+    ///
+    /// Engines that cannot export yield a single error.
+    fn export_vectors(&self) -> BoxStream<'_, Result<VectorRecord>> {
+        stream::once(async {
+            Err(ContextError::StorageError(
+                "this context engine does not support vector export".to_string(),
+            ))
+        })
+        .boxed()
+    }
 }
 
+/// Maintenance events buffered per subscriber before the oldest are dropped
+const MAINTENANCE_EVENT_CAPACITY: usize = 256;
+
+/// A tier's store, shared between the engine and its tasks
+type SharedStore = Arc<tokio::sync::RwLock<dyn MemoryStore>>;
+
 /// Implementation of the context engine
 pub struct ContextEngineImpl {
     config: ContextEngineConfig,
     short_term: Arc<tokio::sync::RwLock<InMemoryStore>>,
     medium_term: Arc<tokio::sync::RwLock<InMemoryStore>>,
-    long_term: Arc<tokio::sync::RwLock<InMemoryStore>>,
+    long_term: SharedStore,
     budget_manager: Arc<tokio::sync::RwLock<TokenBudgetManager>>,
     compressor: Compressor,
     context_window: ContextWindow,
-    tokenizer: CoreBPE,
+    tokenizer: Box<dyn Tokenizer>,
     item_index: Arc<DashMap<Uuid, MemoryTier>>, // Quick lookup for item location
+    inferer: Arc<dyn ImportanceInferer>,
+    embedder: Option<Arc<dyn EmbeddingProvider>>,
+    hooks: Option<Arc<dyn ContextHooks>>,
+    maintenance_events: broadcast::Sender<MaintenanceEvent>,
 }
 
 impl ContextEngineImpl {
     /// Create a new context engine
+    ///
+    /// With [`ContextEngineConfig::long_term_path`] set, the long-term tier
+    /// is a [`DiskStore`] in that directory, and the items it already holds
+    /// are loaded and count against the token budget.
     pub fn new(config: ContextEngineConfig) -> Result<Self> {
-        let tokenizer = get_bpe_from_model(&config.tokenizer_model)
-            .map_err(|e| ContextError::CoreError(format!("Failed to load tokenizer: {}", e)))?;
+        let tokenizer = config.tokenizer.build(&config.tokenizer_model);
 
-        let budget_manager = TokenBudgetManager::new(config.max_tokens, config.target_utilization);
+        let mut budget_manager = TokenBudgetManager::new(config.max_tokens, config.target_utilization);
         let compressor = Compressor::new(config.compression.clone())?;
         let context_window = ContextWindow::new(config.retrieval.clone())?;
 
+        let item_index = Arc::new(DashMap::new());
+        let long_term: SharedStore = match &config.long_term_path {
+            Some(path) => {
+                let store = DiskStore::open(path, MemoryTier::LongTerm)?;
+                let (mut recovered, mut over_budget) = (0, 0);
+                for item in store.items() {
+                    item_index.insert(item.metadata.id, MemoryTier::LongTerm);
+                    let tokens = item
+                        .compressed_content
+                        .as_deref()
+                        .map(|compressed| tokenizer.count(compressed))
+                        .unwrap_or(item.token_count);
+                    if budget_manager.add_tokens(tokens).is_err() {
+                        over_budget += 1;
+                    }
+                    recovered += 1;
+                }
+                if over_budget > 0 {
+                    warn!(over_budget, "Recovered long-term context exceeds the token budget");
+                }
+                debug!("Recovered {} long-term items from {}", recovered, path.display());
+                Arc::new(tokio::sync::RwLock::new(store))
+            }
+            None => Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(MemoryTier::LongTerm))),
+        };
+
         Ok(Self {
             config,
             short_term: Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(
@@ -122,24 +262,306 @@ impl ContextEngineImpl {
             medium_term: Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(
                 MemoryTier::MediumTerm,
             ))),
-            long_term: Arc::new(tokio::sync::RwLock::new(InMemoryStore::new(
-                MemoryTier::LongTerm,
-            ))),
+            long_term,
             budget_manager: Arc::new(tokio::sync::RwLock::new(budget_manager)),
             compressor,
             context_window,
             tokenizer,
-            item_index: Arc::new(DashMap::new()),
+            item_index,
+            inferer: Arc::new(DefaultImportanceInferer::default()),
+            embedder: None,
+            hooks: None,
+            maintenance_events: broadcast::channel(MAINTENANCE_EVENT_CAPACITY).0,
         })
     }
 
+    /// Use a custom importance inferer for `store_inferred`
+    pub fn with_importance_inferer(mut self, inferer: Arc<dyn ImportanceInferer>) -> Self {
+        self.inferer = inferer;
+        self
+    }
+
+    /// Use a custom importance scorer for `store_auto`
+    pub fn with_scorer(self, scorer: Arc<dyn crate::importance::ImportanceScorer>) -> Self {
+        self.with_importance_inferer(scorer)
+    }
+
+    /// Embed content as it is stored, for vector export
+    pub fn with_embedding_provider(mut self, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Call hooks before items are evicted or compressed, e.g. to archive
+    /// them
+    pub fn with_hooks(mut self, hooks: Arc<dyn ContextHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Subscribe to the items promoted, demoted, compressed or evicted
+    ///
+    /// Events are published as each action happens, whether during
+    /// [`maintenance`](ContextEngine::maintenance) or when storing needs
+    /// room. A subscriber that falls more than 256 events behind misses
+    /// the oldest ones.
+    pub fn subscribe_maintenance(&self) -> broadcast::Receiver<MaintenanceEvent> {
+        self.maintenance_events.subscribe()
+    }
+
+    /// Publish a maintenance event to any subscribers
+    fn publish(&self, event: MaintenanceEvent) {
+        // Without subscribers there is nobody to tell
+        let _ = self.maintenance_events.send(event);
+    }
+
+    /// Find the stored items matching a filter, in every tier
+    ///
+    /// An exact lookup for debugging and auditing: unlike retrieval, it
+    /// does not rank items, apply the token budget or count as an access.
+    pub async fn find(&self, filter: MemoryFilter) -> Result<Vec<MemoryItem>> {
+        let mut items = self.collect_all_items().await?;
+        items.retain(|item| filter.matches(item));
+        Ok(items)
+    }
+
+    /// Reserve tokens, e.g. for a model's response, that stored context
+    /// cannot use
+    ///
+    /// Fails with [`ContextError::TokenLimitExceeded`] when the reservation
+    /// does not fit next to the stored context.
+    pub async fn reserve(&self, tokens: usize) -> Result<ReservationHandle> {
+        self.budget_manager.write().await.reserve(tokens)
+    }
+
+    /// Release a reservation made with [`reserve`](Self::reserve)
+    pub async fn release(&self, handle: ReservationHandle) -> Result<()> {
+        self.budget_manager.write().await.release(handle)
+    }
+
+    /// Retrieve relevant context, leaving room for a reservation
+    ///
+    /// The retrieval budget shrinks so that the selected context plus the
+    /// reserved tokens fit the retrieval window, keeping the space
+    /// earmarked for the completion free.
+    pub async fn retrieve_with_reservation(
+        &self,
+        query: &str,
+        reservation: Option<&ReservationHandle>,
+    ) -> Result<RetrievalResult> {
+        let retrieval = &self.config.retrieval;
+        let reserved = reservation.map_or(0, ReservationHandle::tokens);
+        let budget = retrieval
+            .target_tokens()
+            .min(retrieval.max_tokens.saturating_sub(reserved));
+        self.retrieve_within(&ExpandedQuery::new(query), budget).await
+    }
+
+    /// Retrieve context for an expanded query within a token budget
+    async fn retrieve_within(&self, query: &ExpandedQuery, budget: usize) -> Result<RetrievalResult> {
+        // Collect all items
+        let all_items = self.collect_all_items().await?;
+
+        // Use context window to retrieve relevant items
+        let result = self.context_window.retrieve_within(query, all_items, budget)?;
+
+        if let Some(effect) = &result.expansion {
+            debug!(
+                added_terms = ?effect.added_terms,
+                recovered_items = effect.recovered_items,
+                "Query expansion applied"
+            );
+        }
+
+        // Update access statistics for retrieved items
+        for scored in &result.selected {
+            self.record_access(&scored.item.metadata.id).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Stream relevant context in descending relevance within the token
+    /// budget
+    ///
+    /// Items are scored up front but ranked lazily, so the first items can
+    /// be sent on before the rest are ordered. Like [`retrieve`], an item
+    /// that no longer fits the remaining budget is skipped in favour of
+    /// smaller, less relevant ones; the stream ends once the budget is used
+    /// up or no items remain. Each item's access statistics are updated as
+    /// it is emitted.
+    ///
+    /// [`retrieve`]: ContextEngine::retrieve
+    pub fn retrieve_stream(&self, query: &str) -> BoxStream<'_, Result<ScoredItem>> {
+        let query = ExpandedQuery::new(query);
+        let budget = self.config.retrieval.target_tokens();
+
+        stream::once(self.collect_all_items())
+            .flat_map(move |items| match items {
+                Ok(items) => {
+                    let ranked: BinaryHeap<ScoredItem> = self
+                        .context_window
+                        .scorer()
+                        .filter_relevant_expanded(&query, items)
+                        .into_iter()
+                        .collect();
+                    stream::unfold((ranked, budget), move |(mut ranked, mut remaining)| async move {
+                        loop {
+                            if remaining == 0 {
+                                return None;
+                            }
+                            let scored = ranked.pop()?;
+                            if scored.item.token_count > remaining {
+                                continue;
+                            }
+                            remaining -= scored.item.token_count;
+                            let emitted = self.record_access(&scored.item.metadata.id).await.map(|()| scored);
+                            return Some((emitted, (ranked, remaining)));
+                        }
+                    })
+                    .boxed()
+                }
+                Err(e) => stream::once(async { Err(e) }).boxed(),
+            })
+            .boxed()
+    }
+
+    /// Compress harder, including items that are already compressed
+    ///
+    /// Re-runs the compressor on every item's original content with the
+    /// configured compression at `target_ratio`, e.g. 0.2 for a fifth of
+    /// the original size, and keeps the result only where it is smaller
+    /// than the item's current compressed form. Recency rules still apply.
+    /// The returned stats count the tokens saved by this pass alone.
+    pub async fn compress_aggressive(&self, target_ratio: f64) -> Result<CompressionStats> {
+        let compressor = Compressor::new(self.config.compression.aggressive(target_ratio))?;
+        self.compress_with(&compressor, true).await
+    }
+
+    /// Compress items with `compressor`, already compressed ones too if
+    /// `recompress` is set
+    async fn compress_with(&self, compressor: &Compressor, recompress: bool) -> Result<CompressionStats> {
+        let mut stats = CompressionStats::default();
+        let now = chrono::Utc::now();
+
+        // Compress items in each tier
+        for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm, MemoryTier::LongTerm] {
+            let store = self.get_store(tier);
+            let items = store.read().await.list().await?;
+
+            let mut compressible = Vec::new();
+            for item in items {
+                if item.compressed_content.is_some() && !recompress {
+                    continue; // Already compressed
+                }
+
+                // Fresh items stay verbatim; if nothing else can be
+                // compressed, callers fall back to eviction
+                if self.config.compression.is_recent(&item, now) {
+                    stats.items_skipped += 1;
+                    continue;
+                }
+
+                let compressed = compressor.compress_item(&item)?;
+                let compressed_tokens = self.count_tokens(&compressed);
+                let current_tokens = self.budgeted_tokens(&item);
+                if compressed_tokens < current_tokens {
+                    compressible.push((item, compressed, current_tokens - compressed_tokens));
+                }
+            }
+            if compressible.is_empty() {
+                continue;
+            }
+
+            // Hooks see items with their full content before they are
+            // first compressed
+            if let Some(hooks) = &self.hooks {
+                let originals: Vec<_> = compressible
+                    .iter()
+                    .filter(|(item, ..)| item.compressed_content.is_none())
+                    .map(|(item, ..)| item.clone())
+                    .collect();
+                if !originals.is_empty() {
+                    run_hook("compress", &originals, hooks.on_compress(&originals)).await;
+                }
+            }
+
+            for (mut item, compressed, saved) in compressible {
+                let mut store = store.write().await;
+                // Removed while the hooks ran
+                if store.retrieve(&item.metadata.id).await?.is_none() {
+                    continue;
+                }
+                let item_id = item.metadata.id;
+                item.compressed_content = Some(compressed);
+                store.update(item).await?;
+                drop(store);
+
+                self.publish(MaintenanceEvent {
+                    item_id,
+                    action: MaintenanceAction::Compressed,
+                    from_tier: tier,
+                    to_tier: None,
+                    tokens_delta: -(saved as i64),
+                });
+
+                stats.items_compressed += 1;
+                stats.tokens_saved += saved;
+
+                // Update budget
+                self.budget_manager.write().await.remove_tokens(saved);
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Record a retrieval of an item, if it is still stored
+    async fn record_access(&self, id: &Uuid) -> Result<()> {
+        let Some(tier) = self.item_index.get(id).map(|tier| *tier) else {
+            return Ok(());
+        };
+        let store = self.get_store(tier);
+        let mut store = store.write().await;
+        if let Some(mut item) = store.retrieve(id).await? {
+            item.record_access();
+            if item.metadata.custom.contains_key(INFERRED_KEY) {
+                item.importance = self.inferer.rescore(&item, chrono::Utc::now());
+            }
+            store.update(item).await?;
+        }
+        Ok(())
+    }
+
     /// Count tokens in text
     fn count_tokens(&self, text: &str) -> usize {
-        self.tokenizer.encode_with_special_tokens(text).len()
+        self.tokenizer.count(text)
+    }
+
+    /// Tokens an item counts for in the budget: the compressed size once
+    /// compressed
+    fn budgeted_tokens(&self, item: &MemoryItem) -> usize {
+        item.compressed_content
+            .as_deref()
+            .map(|compressed| self.count_tokens(compressed))
+            .unwrap_or(item.token_count)
+    }
+
+    /// Tokens a tier's items count for in the budget, which its cap
+    /// applies to
+    async fn tier_tokens(&self, tier: MemoryTier) -> Result<usize> {
+        let items = self.get_store(tier).read().await.list().await?;
+        Ok(items.iter().map(|item| self.budgeted_tokens(item)).sum())
+    }
+
+    /// Tokens an item in `tier` counts for in the budget
+    async fn item_tokens(&self, id: &Uuid, tier: MemoryTier) -> Result<usize> {
+        let item = self.get_store(tier).read().await.retrieve(id).await?;
+        Ok(item.map_or(0, |item| self.budgeted_tokens(&item)))
     }
 
     /// Get the appropriate store for a tier
-    fn get_store(&self, tier: MemoryTier) -> Arc<tokio::sync::RwLock<InMemoryStore>> {
+    fn get_store(&self, tier: MemoryTier) -> SharedStore {
         match tier {
             MemoryTier::ShortTerm => self.short_term.clone(),
             MemoryTier::MediumTerm => self.medium_term.clone(),
@@ -147,6 +569,15 @@ impl ContextEngineImpl {
         }
     }
 
+    /// The tier above `tier`, which spill-over is promoted to
+    fn higher_tier(tier: MemoryTier) -> Option<MemoryTier> {
+        match tier {
+            MemoryTier::ShortTerm => Some(MemoryTier::MediumTerm),
+            MemoryTier::MediumTerm => Some(MemoryTier::LongTerm),
+            MemoryTier::LongTerm => None,
+        }
+    }
+
     /// Select tier based on importance
     fn select_tier(&self, importance: f64) -> MemoryTier {
         if importance >= MemoryTier::LongTerm.importance_threshold() {
@@ -183,13 +614,38 @@ impl ContextEngineImpl {
             let items = store.read().await.list().await?;
 
             for item in items {
-                if let Some(new_tier) = item.should_promote() {
-                    self.move_item(&item.metadata.id, item.tier, new_tier).await?;
-                    stats.promotions += 1;
+                // Spill-over for an earlier move may have moved or evicted it
+                if self.item_index.get(&item.metadata.id).map(|t| *t) != Some(item.tier) {
+                    continue;
+                }
+                let (new_tier, action) = if let Some(new_tier) = item.should_promote() {
+                    (new_tier, MaintenanceAction::Promoted)
                 } else if let Some(new_tier) = item.should_demote() {
-                    self.move_item(&item.metadata.id, item.tier, new_tier).await?;
-                    stats.demotions += 1;
+                    (new_tier, MaintenanceAction::Demoted)
+                } else {
+                    continue;
+                };
+                let moved = match action {
+                    MaintenanceAction::Promoted => self.promote(&item.metadata.id, new_tier).await,
+                    _ => self.demote(&item.metadata.id, new_tier).await,
+                };
+                match moved {
+                    Ok(()) => {}
+                    // The item stays where it is until the tier has room
+                    Err(ContextError::TokenLimitExceeded { .. }) => continue,
+                    Err(e) => return Err(e),
                 }
+                match action {
+                    MaintenanceAction::Promoted => stats.promotions += 1,
+                    _ => stats.demotions += 1,
+                }
+                self.publish(MaintenanceEvent {
+                    item_id: item.metadata.id,
+                    action,
+                    from_tier: item.tier,
+                    to_tier: Some(new_tier),
+                    tokens_delta: 0,
+                });
             }
         }
 
@@ -222,34 +678,132 @@ impl ContextEngineImpl {
         Ok(())
     }
 
+    /// Make room for `tokens` more in a tier with a token cap
+    ///
+    /// While the tier is over its cap, its most important items are
+    /// promoted to the tier above as far as that tier has room, and the
+    /// least important of the rest are evicted. Fails with
+    /// [`ContextError::TokenLimitExceeded`] if `tokens` alone exceed the
+    /// cap.
+    async fn make_room_in_tier(&self, tier: MemoryTier, tokens: usize) -> Result<()> {
+        let Some(limit) = self.config.tier_max_tokens(tier) else {
+            return Ok(());
+        };
+        if tokens > limit {
+            return Err(ContextError::TokenLimitExceeded { current: tokens, limit });
+        }
+
+        let store = self.get_store(tier);
+        let used = self.tier_tokens(tier).await?;
+        let mut needed = (used + tokens).saturating_sub(limit);
+        if needed == 0 {
+            return Ok(());
+        }
+
+        // Spill over into the tier above while it has room
+        if let Some(higher) = Self::higher_tier(tier) {
+            let mut room = match self.config.tier_max_tokens(higher) {
+                Some(higher_limit) => higher_limit.saturating_sub(self.tier_tokens(higher).await?),
+                None => usize::MAX,
+            };
+
+            let mut items = store.read().await.list().await?;
+            items.sort_by(|a, b| b.current_importance().total_cmp(&a.current_importance()));
+            for item in items {
+                if needed == 0 {
+                    break;
+                }
+                let item_tokens = self.budgeted_tokens(&item);
+                if item_tokens > room {
+                    continue;
+                }
+                self.move_item(&item.metadata.id, tier, higher).await?;
+                self.publish(MaintenanceEvent {
+                    item_id: item.metadata.id,
+                    action: MaintenanceAction::Promoted,
+                    from_tier: tier,
+                    to_tier: Some(higher),
+                    tokens_delta: 0,
+                });
+                room -= item_tokens;
+                needed = needed.saturating_sub(item_tokens);
+            }
+        }
+
+        if needed > 0 {
+            debug!(?tier, needed, "Evicting from a tier over its token cap");
+            self.evict_from_tier(tier, needed).await?;
+        }
+        Ok(())
+    }
+
     /// Evict items to free up space
+    ///
+    /// Evicts from the short-term tier until `tokens_needed` are freed or
+    /// the tier is empty, then falls through to the medium-term tier.
+    /// Returns the tokens freed, which may fall short of `tokens_needed`
+    /// when both tiers together hold less.
     async fn evict_items(&self, tokens_needed: usize) -> Result<usize> {
         let mut tokens_freed = 0;
 
-        // Evict from short-term first
-        if tokens_freed < tokens_needed {
-            let freed = self.short_term
-                .write()
-                .await
-                .evict(self.short_term.read().await.total_tokens().await? - tokens_needed)
-                .await?
-                .iter()
-                .map(|item| item.token_count)
-                .sum::<usize>();
-            tokens_freed += freed;
-        }
-
-        // Then medium-term if needed
-        if tokens_freed < tokens_needed {
-            let freed = self.medium_term
-                .write()
-                .await
-                .evict(self.medium_term.read().await.total_tokens().await? - (tokens_needed - tokens_freed))
-                .await?
-                .iter()
-                .map(|item| item.token_count)
-                .sum::<usize>();
-            tokens_freed += freed;
+        // Evict from short-term first, then medium-term if needed
+        for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm] {
+            if tokens_freed >= tokens_needed {
+                break;
+            }
+            tokens_freed += self.evict_from_tier(tier, tokens_needed - tokens_freed).await?;
+        }
+
+        Ok(tokens_freed)
+    }
+
+    /// Evict the least important items of a tier until `tokens_needed` are
+    /// freed or the tier is empty, returning the tokens freed
+    async fn evict_from_tier(&self, tier: MemoryTier, tokens_needed: usize) -> Result<usize> {
+        let store = self.get_store(tier);
+        let candidates = {
+            let store = store.read().await;
+            let items = store.list().await?;
+            // A tier holding less than what is needed is emptied
+            let used: usize = items.iter().map(|item| self.budgeted_tokens(item)).sum();
+            eviction_candidates(items, used.saturating_sub(tokens_needed), |item| self.budgeted_tokens(item))
+        };
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        // Hooks see the items while they are still stored
+        if let Some(hooks) = &self.hooks {
+            run_hook("evict", &candidates, hooks.on_evict(&candidates)).await;
+        }
+
+        // Items removed while the hooks ran are already gone
+        let mut tokens_freed = 0;
+        let mut evicted = Vec::new();
+        {
+            let mut store = store.write().await;
+            for candidate in candidates {
+                if let Some(item) = store.retrieve(&candidate.metadata.id).await? {
+                    store.remove(&item.metadata.id).await?;
+                    tokens_freed += self.budgeted_tokens(&item);
+                    evicted.push(item);
+                }
+            }
+        }
+
+        // Evicted items no longer count against the budget
+        let mut budget = self.budget_manager.write().await;
+        for item in &evicted {
+            self.item_index.remove(&item.metadata.id);
+            let tokens = self.budgeted_tokens(item);
+            budget.remove_tokens(tokens);
+            self.publish(MaintenanceEvent {
+                item_id: item.metadata.id,
+                action: MaintenanceAction::Evicted,
+                from_tier: tier,
+                to_tier: None,
+                tokens_delta: -(tokens as i64),
+            });
         }
 
         Ok(tokens_freed)
@@ -267,6 +821,12 @@ impl ContextEngine for ContextEngineImpl {
         // Count tokens
         let token_count = self.count_tokens(&content);
 
+        // Select tier
+        let tier = self.select_tier(importance);
+
+        // A capped tier makes room within itself first
+        self.make_room_in_tier(tier, token_count).await?;
+
         // Check if we need to make space
         let mut budget = self.budget_manager.write().await;
         if budget.add_tokens(token_count).is_err() {
@@ -287,13 +847,18 @@ impl ContextEngine for ContextEngineImpl {
             }
         }
 
-        // Select tier
-        let tier = self.select_tier(importance);
-
         // Create memory item
-        let item = MemoryItem::new(content, metadata, importance, token_count);
+        let mut item = MemoryItem::new(content, metadata, importance, token_count);
         let id = item.metadata.id;
 
+        // An item without an embedding is still worth storing
+        if let Some(embedder) = &self.embedder {
+            match embedder.embed(&item.content).await {
+                Ok(embedding) => item.embedding = Some(embedding),
+                Err(e) => warn!(item_id = %id, error = %e, "Failed to embed context item"),
+            }
+        }
+
         // Store in appropriate tier
         let store = self.get_store(tier);
         store.write().await.store(item).await?;
@@ -304,64 +869,26 @@ impl ContextEngine for ContextEngineImpl {
         Ok(id)
     }
 
-    async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
-        // Collect all items
-        let all_items = self.collect_all_items().await?;
-
-        // Use context window to retrieve relevant items
-        let result = self.context_window.retrieve_optimized(query, all_items)?;
-
-        // Update access statistics for retrieved items
-        for scored in &result.selected {
-            if let Some(tier) = self.item_index.get(&scored.item.metadata.id) {
-                let store = self.get_store(*tier);
-                let mut store_write = store.write().await;
-
-                if let Some(mut item) = store_write.retrieve(&scored.item.metadata.id).await? {
-                    item.record_access();
-                    store_write.update(item).await?;
-                }
-            }
+    async fn store_inferred(&self, content: String, mut metadata: MemoryMetadata) -> Result<Uuid> {
+        let importance =
+            resolve_importance(self.inferer.as_ref(), &content, &metadata, chrono::Utc::now());
+        if !metadata.custom.contains_key(IMPORTANCE_KEY) {
+            metadata.add_custom(INFERRED_KEY.to_string(), serde_json::json!(true));
         }
-
-        Ok(result)
+        debug!("Inferred importance {:.3} for {} chars", importance, content.len());
+        self.store(content, metadata, importance).await
     }
 
-    async fn compress(&self) -> Result<CompressionStats> {
-        let mut stats = CompressionStats::default();
-
-        // Compress items in each tier
-        for tier in [MemoryTier::ShortTerm, MemoryTier::MediumTerm, MemoryTier::LongTerm] {
-            let store = self.get_store(tier);
-            let items = store.read().await.list().await?;
-
-            for item in items {
-                if item.compressed_content.is_some() {
-                    continue; // Already compressed
-                }
-
-                let compressed = self.compressor.compress_item(&item)?;
-                let compressed_tokens = self.count_tokens(&compressed);
-
-                if compressed_tokens < item.token_count {
-                    let mut updated_item = item.clone();
-                    updated_item.compressed_content = Some(compressed);
-
-                    store.write().await.update(updated_item).await?;
-
-                    stats.items_compressed += 1;
-                    stats.tokens_saved += item.token_count - compressed_tokens;
+    async fn retrieve(&self, query: &str) -> Result<RetrievalResult> {
+        self.retrieve_expanded(&ExpandedQuery::new(query)).await
+    }
 
-                    // Update budget
-                    self.budget_manager
-                        .write()
-                        .await
-                        .remove_tokens(item.token_count - compressed_tokens);
-                }
-            }
-        }
+    async fn retrieve_expanded(&self, query: &ExpandedQuery) -> Result<RetrievalResult> {
+        self.retrieve_within(query, self.config.retrieval.target_tokens()).await
+    }
 
-        Ok(stats)
+    async fn compress(&self) -> Result<CompressionStats> {
+        self.compress_with(&self.compressor, false).await
     }
 
     async fn stats(&self) -> Result<EngineStats> {
@@ -390,19 +917,56 @@ impl ContextEngine for ContextEngineImpl {
         })
     }
 
+    fn tier_capacity(&self, tier: MemoryTier) -> usize {
+        self.config
+            .tier_max_tokens(tier)
+            .unwrap_or_else(|| tier.token_capacity())
+    }
+
+    /// Move an item to another tier
+    ///
+    /// Fails with [`ContextError::TokenLimitExceeded`] if the item does not
+    /// fit in the target tier's token cap; nothing is evicted to make room.
     async fn promote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
-        if let Some(current_tier) = self.item_index.get(id) {
-            if *current_tier != tier {
-                self.move_item(id, *current_tier, tier).await?;
+        let current_tier = match self.item_index.get(id) {
+            Some(current_tier) => *current_tier,
+            None => return Err(ContextError::ItemNotFound(id.to_string())),
+        };
+        if current_tier == tier {
+            return Ok(());
+        }
+
+        if let Some(limit) = self.config.tier_max_tokens(tier) {
+            let tokens = self.item_tokens(id, current_tier).await?;
+            let used = self.tier_tokens(tier).await?;
+            if used + tokens > limit {
+                return Err(ContextError::TokenLimitExceeded {
+                    current: used + tokens,
+                    limit,
+                });
             }
-            Ok(())
-        } else {
-            Err(ContextError::ItemNotFound(id.to_string()))
         }
+        self.move_item(id, current_tier, tier).await
     }
 
+    /// Move an item to another tier, making room in it as storing does
+    ///
+    /// A target tier at its token cap spills its most important items over
+    /// into the tier above and evicts the least important of the rest.
+    /// Fails with [`ContextError::TokenLimitExceeded`] only if the item
+    /// alone exceeds the cap.
     async fn demote(&self, id: &Uuid, tier: MemoryTier) -> Result<()> {
-        self.promote(id, tier).await
+        let current_tier = match self.item_index.get(id) {
+            Some(current_tier) => *current_tier,
+            None => return Err(ContextError::ItemNotFound(id.to_string())),
+        };
+        if current_tier == tier {
+            return Ok(());
+        }
+
+        let tokens = self.item_tokens(id, current_tier).await?;
+        self.make_room_in_tier(tier, tokens).await?;
+        self.move_item(id, current_tier, tier).await
     }
 
     async fn remove(&self, id: &Uuid) -> Result<()> {
@@ -427,8 +991,8 @@ impl ContextEngine for ContextEngineImpl {
         self.long_term.write().await.clear().await?;
         self.item_index.clear();
 
-        let mut budget = self.budget_manager.write().await;
-        *budget = TokenBudgetManager::new(self.config.max_tokens, self.config.target_utilization);
+        // Reservations are not context and outlive it
+        self.budget_manager.write().await.reset_usage();
 
         Ok(())
     }
@@ -463,6 +1027,23 @@ impl ContextEngine for ContextEngineImpl {
 
         Ok(report)
     }
+    fn export_vectors(&self) -> BoxStream<'_, Result<VectorRecord>> {
+        // Only ids are snapshotted; each item is read as the stream reaches it
+        let mut ids: Vec<Uuid> = self.item_index.iter().map(|entry| *entry.key()).collect();
+        ids.sort();
+
+        stream::iter(ids)
+            .filter_map(move |id| async move {
+                let tier = *self.item_index.get(&id)?;
+                match self.get_store(tier).read().await.retrieve(&id).await {
+                    Ok(Some(item)) => Some(Ok(VectorRecord::from(&item))),
+                    // Removed since the snapshot
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            })
+            .boxed()
+    }
 }
 
 /// Statistics about the context engine
@@ -485,6 +1066,8 @@ pub struct EngineStats {
 pub struct CompressionStats {
     pub items_compressed: usize,
     pub tokens_saved: usize,
+    /// Items left uncompressed because they are recent
+    pub items_skipped: usize,
 }
 
 /// Tier management statistics
@@ -494,6 +1077,33 @@ struct TierManagementStats {
     demotions: usize,
 }
 
+/// What maintenance did to an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaintenanceAction {
+    /// Moved to a more important tier
+    Promoted,
+    /// Moved to a less important tier
+    Demoted,
+    /// Compressed in place
+    Compressed,
+    /// Dropped from the engine
+    Evicted,
+}
+
+/// A maintenance action on one item, see
+/// [`ContextEngineImpl::subscribe_maintenance`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceEvent {
+    pub item_id: Uuid,
+    pub action: MaintenanceAction,
+    /// Tier the item was in
+    pub from_tier: MemoryTier,
+    /// Tier the item moved to, for promotions and demotions
+    pub to_tier: Option<MemoryTier>,
+    /// Change in the tokens the item counts for in the budget
+    pub tokens_delta: i64,
+}
+
 /// Maintenance report
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct MaintenanceReport {
@@ -596,4 +1206,740 @@ mod tests {
         assert_eq!(stats_after.total_items, 0);
         assert_eq!(stats_after.total_tokens, 0);
     }
+
+    fn report(lines: usize) -> String {
+        (0..lines)
+            .map(|i| format!("Service {} reported normal latency during the nightly batch window. ", i))
+            .collect()
+    }
+
+    fn skip_recent_config(max_tokens: usize) -> ContextEngineConfig {
+        ContextEngineConfig {
+            max_tokens,
+            compression: CompressionConfig::default()
+                .skip_newer_than(std::time::Duration::from_secs(3600)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compress_skips_recent_items() {
+        let engine = ContextEngineImpl::new(skip_recent_config(200_000)).unwrap();
+        let fresh = engine
+            .store(report(30), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        let stale = engine
+            .store(report(30), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        // Age the stale item past the rule
+        {
+            let mut store = engine.short_term.write().await;
+            let mut item = store.retrieve(&stale).await.unwrap().unwrap();
+            item.created_at = chrono::Utc::now() - chrono::Duration::hours(2);
+            store.update(item).await.unwrap();
+        }
+
+        let stats = engine.compress().await.unwrap();
+        assert_eq!(stats.items_compressed, 1);
+        assert_eq!(stats.items_skipped, 1);
+
+        let store = engine.short_term.read().await;
+        assert!(store.retrieve(&fresh).await.unwrap().unwrap().compressed_content.is_none());
+        assert!(store.retrieve(&stale).await.unwrap().unwrap().compressed_content.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_store_evicts_when_only_recent_items_remain() {
+        let engine = ContextEngineImpl::new(skip_recent_config(800)).unwrap();
+        let first = engine
+            .store(report(40), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        let first_tokens = engine.stats().await.unwrap().total_tokens;
+        assert!(first_tokens > 400 && first_tokens < 800);
+
+        // Over the limit with nothing eligible for compression: the fresh
+        // item is evicted rather than compressed
+        let second = engine
+            .store(report(40), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        assert!(!engine.item_index.contains_key(&first));
+        assert!(engine.item_index.contains_key(&second));
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.total_items, 1);
+        assert_eq!(stats.total_tokens, first_tokens);
+    }
+
+    fn stream_config(budget: usize) -> ContextEngineConfig {
+        ContextEngineConfig {
+            retrieval: RetrievalConfig {
+                max_tokens: budget,
+                target_utilization: 1.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_stream_skips_items_over_budget() {
+        let engine = ContextEngineImpl::new(stream_config(40)).unwrap();
+        let store = |content: String, importance| {
+            let engine = &engine;
+            async move {
+                engine
+                    .store(content, MemoryMetadata::new("log", "test"), importance)
+                    .await
+                    .unwrap()
+            }
+        };
+        // The most relevant item alone is over the budget
+        let large = store("latency ".repeat(200), 0.9).await;
+        let checkout = store("checkout latency spiked after the deploy".to_string(), 0.8).await;
+        let payments = store("latency alerts for payments".to_string(), 0.3).await;
+        store("unrelated disk usage report".to_string(), 0.9).await;
+
+        let streamed: Vec<ScoredItem> = engine
+            .retrieve_stream("latency")
+            .map(|scored| scored.unwrap())
+            .collect()
+            .await;
+        let ids: Vec<Uuid> = streamed.iter().map(|s| s.item.metadata.id).collect();
+        assert_eq!(ids, [checkout, payments]);
+        assert!(streamed[0].score >= streamed[1].score);
+        assert!(streamed.iter().map(|s| s.item.token_count).sum::<usize>() <= 40);
+
+        // Only emitted items count as accessed
+        let long_term = engine.long_term.read().await;
+        assert_eq!(long_term.retrieve(&large).await.unwrap().unwrap().access_count, 0);
+        assert_eq!(long_term.retrieve(&checkout).await.unwrap().unwrap().access_count, 1);
+
+        // A budget smaller than every item yields nothing
+        let engine = ContextEngineImpl::new(stream_config(2)).unwrap();
+        engine
+            .store("checkout latency spiked".to_string(), MemoryMetadata::new("log", "test"), 0.8)
+            .await
+            .unwrap();
+        assert_eq!(engine.retrieve_stream("latency").count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_store_evicts_across_tiers_when_one_holds_too_little() {
+        let engine = ContextEngineImpl::new(skip_recent_config(800)).unwrap();
+        let long = engine
+            .store(report(20), MemoryMetadata::new("log", "test"), 0.9)
+            .await
+            .unwrap();
+        let medium = engine
+            .store(report(20), MemoryMetadata::new("log", "test"), 0.6)
+            .await
+            .unwrap();
+        let short = engine
+            .store("a short note".to_string(), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        // Needs more than the short-term tier holds: it is emptied and
+        // eviction carries on in the medium-term tier
+        let large = engine
+            .store(report(30), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        assert!(!engine.item_index.contains_key(&short));
+        assert!(!engine.item_index.contains_key(&medium));
+        assert!(engine.item_index.contains_key(&long));
+        assert!(engine.item_index.contains_key(&large));
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.total_items, 2);
+        assert!(stats.total_tokens <= 800);
+    }
+
+    #[tokio::test]
+    async fn test_long_term_tier_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ContextEngineConfig {
+            long_term_path: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let (kept, forgotten) = {
+            let engine = ContextEngineImpl::new(config.clone()).unwrap();
+            let kept = engine
+                .store("Checkout latency SLO is 300ms".to_string(), MemoryMetadata::new("fact", "test"), 0.9)
+                .await
+                .unwrap();
+            let forgotten = engine
+                .store("Checkout latency looked fine today".to_string(), MemoryMetadata::new("log", "test"), 0.2)
+                .await
+                .unwrap();
+            (kept, forgotten)
+        };
+
+        let engine = ContextEngineImpl::new(config).unwrap();
+        assert!(engine.item_index.contains_key(&kept));
+        assert!(!engine.item_index.contains_key(&forgotten));
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.long_term_items, 1);
+        assert_eq!(stats.short_term_items, 0);
+        assert!(stats.utilization > 0.0);
+
+        let result = engine.retrieve("checkout latency").await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert_eq!(result.selected[0].item.metadata.id, kept);
+
+        // Removal is persisted too
+        engine.remove(&kept).await.unwrap();
+        drop(engine);
+        let engine = ContextEngineImpl::new(ContextEngineConfig {
+            long_term_path: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(engine.stats().await.unwrap().total_items, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retrieval_leaves_reserved_room() {
+        let engine = ContextEngineImpl::new(stream_config(60)).unwrap();
+        for service in ["checkout", "payments", "search", "auth"] {
+            engine
+                .store(
+                    format!("{} latency rose after the deploy this morning", service),
+                    MemoryMetadata::new("log", "test"),
+                    0.5,
+                )
+                .await
+                .unwrap();
+        }
+        let unreserved = engine.retrieve("latency").await.unwrap();
+        assert_eq!(unreserved.selected.len(), 4);
+
+        let handle = engine.reserve(30).await.unwrap();
+        let reserved = engine.retrieve_with_reservation("latency", Some(&handle)).await.unwrap();
+        assert!(reserved.total_tokens + handle.tokens() <= 60);
+        assert!(reserved.selected.len() < unreserved.selected.len());
+        assert_eq!(
+            engine.retrieve_with_reservation("latency", None).await.unwrap().total_tokens,
+            unreserved.total_tokens
+        );
+
+        // Reservations survive clearing the context, and release once
+        engine.clear().await.unwrap();
+        assert!(engine.stats().await.unwrap().utilization > 0.0);
+        engine.release(handle).await.unwrap();
+        assert!(matches!(
+            engine.release(handle).await,
+            Err(ContextError::ReservationNotFound(_))
+        ));
+        assert!(engine.reserve(usize::MAX / 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_aggressive_pass_recompresses_compressed_items() {
+        let config = ContextEngineConfig {
+            compression: CompressionConfig {
+                target_ratio: 0.8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let engine = ContextEngineImpl::new(config).unwrap();
+        let document: Vec<String> = (0..60)
+            .map(|i| format!("Request {} to checkout-service completed with status 200 in {}ms.", i, 100 + i))
+            .collect();
+        let id = engine
+            .store(document.join(" "), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        let normal = engine.compress().await.unwrap();
+        assert_eq!(normal.items_compressed, 1);
+        let after_normal = engine.stats().await.unwrap().utilization;
+
+        let aggressive = engine.compress_aggressive(0.2).await.unwrap();
+        assert_eq!(aggressive.items_compressed, 1);
+        assert!(
+            aggressive.tokens_saved > normal.tokens_saved,
+            "aggressive {} vs normal {}",
+            aggressive.tokens_saved,
+            normal.tokens_saved
+        );
+        assert!(engine.stats().await.unwrap().utilization < after_normal);
+
+        // A weaker pass leaves the smaller version in place
+        let weaker = engine.compress_aggressive(0.9).await.unwrap();
+        assert_eq!(weaker.items_compressed, 0);
+        let item = engine.short_term.read().await.retrieve(&id).await.unwrap().unwrap();
+        assert!(item.compressed_content.unwrap().len() < item.content.len() / 2);
+
+        assert!(engine.compress_aggressive(0.0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_forced_eviction_emits_event() {
+        let engine = ContextEngineImpl::new(skip_recent_config(800)).unwrap();
+        let mut events = engine.subscribe_maintenance();
+        let first = engine
+            .store(report(40), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        let first_tokens = engine.stats().await.unwrap().total_tokens;
+
+        engine
+            .store(report(40), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(
+            event,
+            MaintenanceEvent {
+                item_id: first,
+                action: MaintenanceAction::Evicted,
+                from_tier: MemoryTier::ShortTerm,
+                to_tier: None,
+                tokens_delta: -(first_tokens as i64),
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    fn capped_config(short_term: usize, medium_term: usize) -> ContextEngineConfig {
+        ContextEngineConfig {
+            short_term_max_tokens: Some(short_term),
+            medium_term_max_tokens: Some(medium_term),
+            auto_compress_threshold: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_low_importance_spam_never_evicts_long_term() {
+        let engine = ContextEngineImpl::new(capped_config(300, 300)).unwrap();
+        let keep = engine
+            .store(report(10), MemoryMetadata::new("runbook", "test"), 0.9)
+            .await
+            .unwrap();
+        let mut events = engine.subscribe_maintenance();
+
+        for _ in 0..50 {
+            engine
+                .store(report(5), MemoryMetadata::new("log", "test"), 0.1)
+                .await
+                .unwrap();
+        }
+
+        // The spam was confined by the tier caps long before the global
+        // budget came into play
+        let stats = engine.stats().await.unwrap();
+        assert!(stats.short_term_tokens <= 300);
+        assert!(stats.medium_term_tokens <= 300);
+        assert!(stats.total_tokens < engine.config.max_tokens / 2);
+        assert_eq!(stats.long_term_items, 1);
+        let kept = engine.long_term.read().await.retrieve(&keep).await.unwrap();
+        assert!(kept.is_some());
+
+        // Short-term spilled over into medium-term until it was full, then
+        // evicted its own items; long-term was never involved
+        let mut spilled = 0;
+        let mut evicted = 0;
+        while let Ok(event) = events.try_recv() {
+            assert_ne!(event.item_id, keep);
+            assert_eq!(event.from_tier, MemoryTier::ShortTerm);
+            match event.action {
+                MaintenanceAction::Promoted => {
+                    assert_eq!(event.to_tier, Some(MemoryTier::MediumTerm));
+                    spilled += 1;
+                }
+                MaintenanceAction::Evicted => evicted += 1,
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(spilled, stats.medium_term_items);
+        assert!(evicted > 0);
+        assert_eq!(stats.total_items, 51 - evicted);
+    }
+
+    #[tokio::test]
+    async fn test_full_tier_spills_over_by_importance() {
+        let engine = ContextEngineImpl::new(capped_config(150, 1000)).unwrap();
+        let mut events = engine.subscribe_maintenance();
+        let minor = engine
+            .store(report(5), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        let major = engine
+            .store(report(5), MemoryMetadata::new("log", "test"), 0.4)
+            .await
+            .unwrap();
+        let item_tokens = engine.stats().await.unwrap().short_term_tokens / 2;
+        assert!(item_tokens * 3 > 150);
+
+        engine
+            .store(report(5), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        // The more important item moved up instead of anything being evicted
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.total_items, 3);
+        assert_eq!(*engine.item_index.get(&major).unwrap(), MemoryTier::MediumTerm);
+        assert_eq!(*engine.item_index.get(&minor).unwrap(), MemoryTier::ShortTerm);
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.item_id, major);
+        assert_eq!(event.action, MaintenanceAction::Promoted);
+        assert_eq!(event.to_tier, Some(MemoryTier::MediumTerm));
+    }
+
+    #[tokio::test]
+    async fn test_demotion_into_full_tier_spills_over() {
+        let engine = ContextEngineImpl::new(capped_config(100, 1000)).unwrap();
+        let minor = engine
+            .store(report(3), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        let major = engine
+            .store(report(3), MemoryMetadata::new("log", "test"), 0.4)
+            .await
+            .unwrap();
+        let cold = engine
+            .store(report(3), MemoryMetadata::new("log", "test"), 0.6)
+            .await
+            .unwrap();
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.short_term_items, 2);
+        assert!(stats.short_term_tokens / 2 * 3 > 100);
+        let mut events = engine.subscribe_maintenance();
+
+        engine.demote(&cold, MemoryTier::ShortTerm).await.unwrap();
+
+        // The demoted item got in by moving the more important one up
+        let stats = engine.stats().await.unwrap();
+        assert_eq!(stats.total_items, 3);
+        assert!(stats.short_term_tokens <= 100);
+        assert_eq!(*engine.item_index.get(&cold).unwrap(), MemoryTier::ShortTerm);
+        assert_eq!(*engine.item_index.get(&minor).unwrap(), MemoryTier::ShortTerm);
+        assert_eq!(*engine.item_index.get(&major).unwrap(), MemoryTier::MediumTerm);
+        let event = events.try_recv().unwrap();
+        assert_eq!((event.item_id, event.action), (major, MaintenanceAction::Promoted));
+    }
+
+    #[tokio::test]
+    async fn test_promotion_respects_tier_cap() {
+        let engine = ContextEngineImpl::new(capped_config(100, 1000)).unwrap();
+        assert_eq!(engine.tier_capacity(MemoryTier::ShortTerm), 100);
+        assert_eq!(
+            engine.tier_capacity(MemoryTier::LongTerm),
+            MemoryTier::LongTerm.token_capacity()
+        );
+
+        let cold = engine
+            .store(report(5), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        engine.demote(&cold, MemoryTier::MediumTerm).await.unwrap();
+        let warm = engine
+            .store(report(5), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+        let item_tokens = engine.stats().await.unwrap().short_term_tokens;
+        assert!(item_tokens * 2 > 100);
+
+        let err = engine.promote(&cold, MemoryTier::ShortTerm).await.unwrap_err();
+        assert!(matches!(err, ContextError::TokenLimitExceeded { limit: 100, .. }));
+        assert_eq!(*engine.item_index.get(&cold).unwrap(), MemoryTier::MediumTerm);
+        assert_eq!(*engine.item_index.get(&warm).unwrap(), MemoryTier::ShortTerm);
+    }
+
+    #[tokio::test]
+    async fn test_item_over_tier_cap_is_rejected() {
+        let engine = ContextEngineImpl::new(capped_config(20, 1000)).unwrap();
+        let err = engine
+            .store(report(5), MemoryMetadata::new("log", "test"), 0.1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ContextError::TokenLimitExceeded { limit: 20, .. }));
+        assert_eq!(engine.stats().await.unwrap().total_items, 0);
+    }
+
+    #[tokio::test]
+    async fn test_find_combines_filters() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let start = chrono::Utc::now();
+        let wanted = engine
+            .store("Deploy failed".to_string(), MemoryMetadata::new("log", "ci"), 0.6)
+            .await
+            .unwrap();
+        engine
+            .store("Deploy passed".to_string(), MemoryMetadata::new("log", "ci"), 0.2)
+            .await
+            .unwrap();
+        engine
+            .store("Disk full".to_string(), MemoryMetadata::new("log", "alerts"), 0.6)
+            .await
+            .unwrap();
+
+        let filter = MemoryFilter::new().with_source("ci").with_importance(0.5, 1.0);
+        let found = engine.find(filter.clone()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].metadata.id, wanted);
+        // Finding is not an access
+        assert_eq!(found[0].access_count, 0);
+        assert_eq!(engine.find(filter.clone()).await.unwrap()[0].access_count, 0);
+
+        let filter = filter.with_tier(MemoryTier::MediumTerm).created_between(start, chrono::Utc::now());
+        assert_eq!(engine.find(filter.clone()).await.unwrap().len(), 1);
+        let filter = filter.with_tier(MemoryTier::LongTerm);
+        assert!(engine.find(filter.clone()).await.unwrap().is_empty());
+        assert_eq!(engine.find(MemoryFilter::new()).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_engine_builds_with_unknown_tokenizer_model() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig {
+            tokenizer_model: "llama-3-70b".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        engine
+            .store("abcdefghijkl".to_string(), MemoryMetadata::new("log", "test"), 0.5)
+            .await
+            .unwrap();
+        assert_eq!(engine.stats().await.unwrap().total_tokens, 3);
+
+        let engine = ContextEngineImpl::new(ContextEngineConfig {
+            tokenizer: TokenizerConfig::Approximate { chars_per_token: 3 },
+            ..Default::default()
+        })
+        .unwrap();
+        engine
+            .store("abcdefghijkl".to_string(), MemoryMetadata::new("log", "test"), 0.5)
+            .await
+            .unwrap();
+        assert_eq!(engine.stats().await.unwrap().total_tokens, 4);
+    }
+
+    /// Records hooked items, and whether evicted ones were still stored
+    #[derive(Default)]
+    struct ArchivingHooks {
+        engine: std::sync::OnceLock<std::sync::Weak<ContextEngineImpl>>,
+        evicted: std::sync::Mutex<Vec<(Uuid, bool)>>,
+        compressed: std::sync::Mutex<Vec<MemoryItem>>,
+        fail: bool,
+    }
+
+    impl ArchivingHooks {
+        fn outcome(&self) -> Result<()> {
+            if self.fail {
+                return Err(ContextError::StorageError("archive unavailable".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl ContextHooks for ArchivingHooks {
+        async fn on_evict(&self, items: &[MemoryItem]) -> Result<()> {
+            let engine = self.engine.get().and_then(|e| e.upgrade()).unwrap();
+            for item in items {
+                let store = engine.get_store(item.tier);
+                let stored = store.read().await.retrieve(&item.metadata.id).await?.is_some();
+                self.evicted.lock().unwrap().push((item.metadata.id, stored));
+            }
+            self.outcome()
+        }
+
+        async fn on_compress(&self, items: &[MemoryItem]) -> Result<()> {
+            self.compressed.lock().unwrap().extend_from_slice(items);
+            self.outcome()
+        }
+    }
+
+    fn hooked_engine(config: ContextEngineConfig, fail: bool) -> (Arc<ContextEngineImpl>, Arc<ArchivingHooks>) {
+        let hooks = Arc::new(ArchivingHooks {
+            fail,
+            ..Default::default()
+        });
+        let engine = Arc::new(ContextEngineImpl::new(config).unwrap().with_hooks(hooks.clone()));
+        hooks.engine.set(Arc::downgrade(&engine)).ok();
+        (engine, hooks)
+    }
+
+    #[tokio::test]
+    async fn test_evict_hook_sees_items_before_removal() {
+        for fail in [false, true] {
+            let (engine, hooks) = hooked_engine(skip_recent_config(800), fail);
+            let first = engine
+                .store(report(40), MemoryMetadata::new("log", "test"), 0.2)
+                .await
+                .unwrap();
+            let first_tokens = engine.stats().await.unwrap().total_tokens;
+            let second = engine
+                .store(report(40), MemoryMetadata::new("log", "test"), 0.2)
+                .await
+                .unwrap();
+
+            assert_eq!(*hooks.evicted.lock().unwrap(), [(first, true)]);
+
+            // A failing hook does not stop eviction or skew the budget
+            assert!(!engine.item_index.contains_key(&first));
+            assert!(engine.item_index.contains_key(&second));
+            let stats = engine.stats().await.unwrap();
+            assert_eq!(stats.total_items, 1);
+            assert_eq!(stats.total_tokens, first_tokens);
+            assert!(stats.within_budget);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compress_hook_sees_full_content() {
+        let (engine, hooks) = hooked_engine(ContextEngineConfig::default(), true);
+        let id = engine
+            .store(report(30), MemoryMetadata::new("log", "test"), 0.2)
+            .await
+            .unwrap();
+
+        let stats = engine.compress().await.unwrap();
+        assert_eq!(stats.items_compressed, 1);
+
+        let compressed = hooks.compressed.lock().unwrap().clone();
+        assert_eq!(compressed.len(), 1);
+        assert_eq!(compressed[0].metadata.id, id);
+        assert_eq!(compressed[0].content, report(30));
+        assert!(compressed[0].compressed_content.is_none());
+
+        let store = engine.short_term.read().await;
+        assert!(store.retrieve(&id).await.unwrap().unwrap().compressed_content.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_compressed_item_keeps_provenance() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let metadata = MemoryMetadata::new("log", "kb")
+            .with_provenance(crate::Provenance::document("runbook-42"));
+        engine.store(report(30), metadata, 0.2).await.unwrap();
+
+        let stats = engine.compress().await.unwrap();
+        assert_eq!(stats.items_compressed, 1);
+
+        let result = engine.retrieve("Service reported latency").await.unwrap();
+        let item = &result.selected[0].item;
+        assert!(item.compressed_content.is_some());
+        assert_eq!(item.provenance(), crate::Provenance::document("runbook-42"));
+    }
+
+    #[tokio::test]
+    async fn test_store_inferred_places_dense_content_in_long_term() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let mut metadata = MemoryMetadata::new("conversation", "user_input");
+        metadata.add_custom(crate::importance::ROLE_KEY.to_string(), serde_json::json!("user"));
+
+        let dense = engine
+            .store_inferred(
+                "checkout-service p99 hit 850ms on db-1.prod with 503s since 14:05".to_string(),
+                metadata.clone(),
+            )
+            .await
+            .unwrap();
+        let chatter = engine
+            .store_inferred("ok thanks".to_string(), MemoryMetadata::new("conversation", "llm_output"))
+            .await
+            .unwrap();
+
+        assert_eq!(*engine.item_index.get(&dense).unwrap(), MemoryTier::LongTerm);
+        assert_ne!(*engine.item_index.get(&chatter).unwrap(), MemoryTier::LongTerm);
+    }
+
+    #[tokio::test]
+    async fn test_store_auto_rescores_on_access() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default())
+            .unwrap()
+            .with_scorer(Arc::new(DefaultImportanceInferer::default()));
+        let id = engine
+            .store_auto("please restart the gateway".to_string(), MemoryMetadata::new("conversation", "user_input"))
+            .await
+            .unwrap();
+
+        let importance = |engine: &ContextEngineImpl| {
+            let tier = *engine.item_index.get(&id).unwrap();
+            let store = engine.get_store(tier);
+            async move { store.read().await.retrieve(&id).await.unwrap().unwrap().importance }
+        };
+        let stored = importance(&engine).await;
+        for _ in 0..3 {
+            engine.record_access(&id).await.unwrap();
+        }
+        assert!(importance(&engine).await > stored);
+    }
+
+    #[tokio::test]
+    async fn test_store_inferred_honors_explicit_importance() {
+        let engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+
+        let inferred = engine
+            .store_inferred(
+                "checkout-service returned 503 for 12% of requests".to_string(),
+                MemoryMetadata::new("conversation", "user_input"),
+            )
+            .await
+            .unwrap();
+        let mut metadata = MemoryMetadata::new("conversation", "user_input");
+        metadata.add_custom("importance".to_string(), serde_json::json!(0.1));
+        let explicit = engine
+            .store_inferred("checkout-service returned 503".to_string(), metadata)
+            .await
+            .unwrap();
+
+        let engine = &engine;
+        let importance = |id: Uuid| async move {
+            let tier = *engine.item_index.get(&id).unwrap();
+            let store = engine.get_store(tier);
+            let item = store.read().await.retrieve(&id).await.unwrap().unwrap();
+            item.importance
+        };
+        let inferred = importance(inferred).await;
+        assert!(inferred > 0.5 && inferred <= 1.0);
+        assert_eq!(importance(explicit).await, 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_export_vectors_marks_missing_embeddings() {
+        let embedded = ContextEngineImpl::new(ContextEngineConfig::default())
+            .unwrap()
+            .with_embedding_provider(Arc::new(crate::MockEmbeddingProvider::new(8)));
+        let metadata = MemoryMetadata::new("document", "runbooks")
+            .with_provenance(crate::Provenance::document("runbook-7"));
+        let id = embedded
+            .store("Drain the node before rebooting it".to_string(), metadata, 0.6)
+            .await
+            .unwrap();
+
+        let records: Vec<_> = embedded.export_vectors().collect().await;
+        assert_eq!(records.len(), 1);
+        let record = records[0].as_ref().unwrap();
+        assert_eq!(record.id, id);
+        assert_eq!(record.content, "Drain the node before rebooting it");
+        assert!(!record.compressed);
+        assert_eq!(record.importance, 0.6);
+        assert_eq!(record.metadata.provenance, Some(crate::Provenance::document("runbook-7")));
+        assert_eq!(record.embedding.as_ref().map(Vec::len), Some(8));
+
+        let plain = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        plain
+            .store("No vector for this one".to_string(), MemoryMetadata::new("note", "test"), 0.3)
+            .await
+            .unwrap();
+        let records: Vec<_> = plain.export_vectors().collect().await;
+        let record = records[0].as_ref().unwrap();
+        assert!(!record.has_embedding());
+        let line: serde_json::Value = serde_json::from_str(&record.to_json_line().unwrap()).unwrap();
+        assert!(line["embedding"].is_null());
+        assert_eq!(line["content"], "No vector for this one");
+    }
 }
@@ -0,0 +1,298 @@
+//! Query expansion for context retrieval
+//!
+//! Augments a retrieval query with normalized entity values and dictionary
+//! synonyms so items phrased differently from the query (e.g. "memory" for a
+//! question about "RAM") still score as relevant. Expansions are attached to
+//! the query term they stand in for rather than appended to the query, so
+//! they never dilute coverage of the original terms.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Configuration for query expansion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryExpansionConfig {
+    /// Whether queries are expanded at all
+    pub enabled: bool,
+
+    /// Maximum number of terms added to a single query
+    pub max_added_terms: usize,
+}
+
+impl Default for QueryExpansionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_added_terms: 6,
+        }
+    }
+}
+
+/// Groups of interchangeable terms
+#[derive(Debug, Clone)]
+pub struct SynonymDictionary {
+    groups: Vec<Vec<String>>,
+    index: HashMap<String, Vec<usize>>,
+}
+
+impl Default for SynonymDictionary {
+    fn default() -> Self {
+        let mut dictionary = Self::empty();
+        for group in [
+            &["ram", "memory", "mem"][..],
+            &["cpu", "processor", "compute"],
+            &["disk", "storage", "volume"],
+            &["latency", "response time", "delay"],
+            &["error", "failure", "exception"],
+            &["db", "database"],
+            &["k8s", "kubernetes"],
+            &["oom", "out of memory"],
+            &["throughput", "requests per second", "rps"],
+            &["deploy", "deployment", "release"],
+        ] {
+            dictionary.add_group(group.iter().copied());
+        }
+        dictionary
+    }
+}
+
+impl SynonymDictionary {
+    /// Create a dictionary with no synonym groups
+    pub fn empty() -> Self {
+        Self {
+            groups: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Add a group of interchangeable terms
+    pub fn add_group<I, S>(&mut self, terms: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let group: Vec<String> = terms.into_iter().map(|t| t.into().to_lowercase()).collect();
+        let idx = self.groups.len();
+        for term in &group {
+            self.index.entry(term.clone()).or_default().push(idx);
+        }
+        self.groups.push(group);
+    }
+
+    /// Synonyms of a term, excluding the term itself
+    pub fn synonyms(&self, term: &str) -> Vec<&str> {
+        let term = term.to_lowercase();
+        let mut synonyms: Vec<&str> = Vec::new();
+        for &idx in self.index.get(&term).into_iter().flatten() {
+            for candidate in &self.groups[idx] {
+                if *candidate != term && !synonyms.contains(&candidate.as_str()) {
+                    synonyms.push(candidate);
+                }
+            }
+        }
+        synonyms
+    }
+}
+
+/// An entity recognized in the query, as input to expansion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTerm {
+    /// Text of the entity as it appears in the query
+    pub original_text: String,
+    /// Canonical value of the entity
+    pub normalized_value: String,
+}
+
+impl EntityTerm {
+    pub fn new(original_text: impl Into<String>, normalized_value: impl Into<String>) -> Self {
+        Self {
+            original_text: original_text.into(),
+            normalized_value: normalized_value.into(),
+        }
+    }
+
+    /// Whether a lowercase query word is part of this entity's text
+    fn covers(&self, word: &str) -> bool {
+        let word = clean(word);
+        !word.is_empty()
+            && self
+                .original_text
+                .split_whitespace()
+                .any(|w| clean(w).eq_ignore_ascii_case(word))
+    }
+}
+
+/// A retrieval query with alternative terms for its words
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpandedQuery {
+    /// The query as given
+    pub original: String,
+
+    /// Alternatives keyed by lowercase query word
+    pub alternatives: HashMap<String, Vec<String>>,
+}
+
+impl ExpandedQuery {
+    /// Wrap a query without expansions
+    pub fn new(original: impl Into<String>) -> Self {
+        Self {
+            original: original.into(),
+            alternatives: HashMap::new(),
+        }
+    }
+
+    /// Whether any terms were added
+    pub fn is_expanded(&self) -> bool {
+        self.alternatives.values().any(|alts| !alts.is_empty())
+    }
+
+    /// All added terms
+    pub fn added_terms(&self) -> Vec<&str> {
+        let mut terms: Vec<&str> = self
+            .alternatives
+            .values()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        terms
+    }
+
+    /// Alternatives for a lowercase query word
+    pub fn alternatives_for(&self, word: &str) -> &[String] {
+        self.alternatives.get(word).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Expands retrieval queries from entities and a synonym dictionary
+#[derive(Debug, Clone, Default)]
+pub struct QueryExpander {
+    config: QueryExpansionConfig,
+    dictionary: SynonymDictionary,
+}
+
+impl QueryExpander {
+    pub fn new(config: QueryExpansionConfig, dictionary: SynonymDictionary) -> Self {
+        Self { config, dictionary }
+    }
+
+    pub fn config(&self) -> &QueryExpansionConfig {
+        &self.config
+    }
+
+    /// Expand a query
+    ///
+    /// Entity values take precedence over dictionary synonyms when the
+    /// `max_added_terms` cap is reached. Terms already in the query are
+    /// never added.
+    pub fn expand(&self, query: &str, entities: &[EntityTerm]) -> ExpandedQuery {
+        let mut expanded = ExpandedQuery::new(query);
+        if !self.config.enabled || self.config.max_added_terms == 0 {
+            return expanded;
+        }
+
+        let query_lower = query.to_lowercase();
+        let words: Vec<&str> = query_lower.split_whitespace().collect();
+        let mut added: Vec<String> = Vec::new();
+
+        // Candidate alternatives per word, in priority order
+        let mut candidates: Vec<(&str, String)> = Vec::new();
+        for entity in entities {
+            let normalized = entity.normalized_value.to_lowercase();
+            for word in words.iter().filter(|w| entity.covers(w)) {
+                candidates.push((word, normalized.clone()));
+            }
+        }
+        for entity in entities {
+            for word in words.iter().filter(|w| entity.covers(w)) {
+                for synonym in self.dictionary.synonyms(&entity.normalized_value) {
+                    candidates.push((word, synonym.to_string()));
+                }
+            }
+        }
+        for word in &words {
+            for synonym in self.dictionary.synonyms(clean(word)) {
+                candidates.push((word, synonym.to_string()));
+            }
+        }
+
+        // The cap counts distinct terms; a term may stand in for several words
+        for (word, term) in candidates {
+            if term.is_empty() || words.iter().any(|w| clean(w) == term) {
+                continue;
+            }
+            if !added.contains(&term) {
+                if added.len() >= self.config.max_added_terms {
+                    continue;
+                }
+                added.push(term.clone());
+            }
+
+            let alternatives = expanded.alternatives.entry(word.to_string()).or_default();
+            if !alternatives.contains(&term) {
+                alternatives.push(term);
+            }
+        }
+
+        expanded
+    }
+}
+
+/// Strip surrounding punctuation from a query word
+fn clean(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+/// How expansion changed a retrieval
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExpansionEffect {
+    /// Terms added to the query
+    pub added_terms: Vec<String>,
+
+    /// Selected items that only passed the relevance threshold because of
+    /// the added terms
+    pub recovered_items: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(max_added_terms: usize) -> QueryExpander {
+        QueryExpander::new(
+            QueryExpansionConfig {
+                enabled: true,
+                max_added_terms,
+            },
+            SynonymDictionary::default(),
+        )
+    }
+
+    #[test]
+    fn test_disabled_expander_is_a_no_op() {
+        let expanded = QueryExpander::default().expand("RAM usage", &[]);
+        assert!(!expanded.is_expanded());
+    }
+
+    #[test]
+    fn test_synonyms_attached_to_query_word() {
+        let expanded = enabled(6).expand("RAM usage", &[]);
+        assert_eq!(expanded.alternatives_for("ram"), ["memory", "mem"]);
+        assert!(expanded.alternatives_for("usage").is_empty());
+    }
+
+    #[test]
+    fn test_entity_values_take_priority_under_cap() {
+        let entities = [EntityTerm::new("last hour", "1h")];
+        let expanded = enabled(1).expand("errors in the last hour", &entities);
+        assert_eq!(expanded.added_terms(), ["1h"]);
+    }
+
+    #[test]
+    fn test_terms_already_in_query_not_added() {
+        let expanded = enabled(6).expand("ram memory", &[]);
+        assert!(!expanded.added_terms().contains(&"memory"));
+        assert!(!expanded.added_terms().contains(&"ram"));
+    }
+}
@@ -0,0 +1,69 @@
+//! Export of stored context to vector stores
+//!
+//! [`ContextEngine::export_vectors`](crate::ContextEngine::export_vectors)
+//! streams one [`VectorRecord`] per stored item, in a shape most vector
+//! databases load directly: an id, the text, a vector and a flat payload.
+//! Items are read one at a time, so exporting a large store never holds
+//! all of it in memory.
+//!
+//! Items stored before an embedding provider was configured, or whose
+//! embedding failed, have no vector. Their records carry `embedding: null`
+//! so an importer can skip them or embed them itself.
+
+use crate::hybrid_search::Embedding;
+use crate::memory::{MemoryItem, MemoryMetadata, MemoryTier};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One stored item, ready to load into a vector database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorRecord {
+    /// Item identifier
+    pub id: Uuid,
+    /// Text to index: the compressed content if the item was compressed
+    pub content: String,
+    /// Whether `content` is the compressed version
+    pub compressed: bool,
+    /// The item's embedding, `None` if it has none
+    pub embedding: Option<Embedding>,
+    /// Item metadata, including provenance
+    pub metadata: MemoryMetadata,
+    /// Importance score (0.0 - 1.0)
+    pub importance: f64,
+    /// Tier the item was stored in
+    pub tier: MemoryTier,
+    /// Tokens of `content`'s original
+    pub token_count: usize,
+    /// When the item was stored
+    pub created_at: DateTime<Utc>,
+}
+
+impl VectorRecord {
+    /// Whether the record carries an embedding
+    pub fn has_embedding(&self) -> bool {
+        self.embedding.is_some()
+    }
+
+    /// Serialize the record as one line of JSON Lines
+    pub fn to_json_line(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl From<&MemoryItem> for VectorRecord {
+    fn from(item: &MemoryItem) -> Self {
+        Self {
+            id: item.metadata.id,
+            content: item.get_content().to_string(),
+            compressed: item.compressed_content.is_some(),
+            embedding: item.embedding.clone(),
+            metadata: item.metadata.clone(),
+            importance: item.importance,
+            tier: item.tier,
+            token_count: item.token_count,
+            created_at: item.created_at,
+        }
+    }
+}
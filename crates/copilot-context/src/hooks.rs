@@ -0,0 +1,43 @@
+//! Eviction and compression hooks
+//!
+//! Eviction drops items for good, and compression replaces what a prompt
+//! sees of an item with a summary. Callers that want to keep the original
+//! context somewhere, e.g. in cold storage, register [`ContextHooks`] with
+//! [`ContextEngineImpl::with_hooks`]. The engine calls them with the
+//! affected items before it changes the store, and without holding a store
+//! lock, so a hook may read from the engine.
+//!
+//! A hook cannot stop eviction or compression: an error or panic is logged
+//! and the engine carries on, keeping its budget consistent.
+//!
+//! [`ContextEngineImpl::with_hooks`]: crate::ContextEngineImpl::with_hooks
+
+use crate::{memory::MemoryItem, Result};
+use async_trait::async_trait;
+use futures::FutureExt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use tracing::warn;
+
+/// Callbacks for context about to be evicted or compressed
+#[async_trait]
+pub trait ContextHooks: Send + Sync {
+    /// Items about to be evicted, still in the store
+    async fn on_evict(&self, _items: &[MemoryItem]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Items about to be compressed, with their full content
+    async fn on_compress(&self, _items: &[MemoryItem]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Run a hook, logging rather than propagating its failure
+pub(crate) async fn run_hook(event: &str, items: &[MemoryItem], hook: impl Future<Output = Result<()>>) {
+    match AssertUnwindSafe(hook).catch_unwind().await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!(event, items = items.len(), error = %e, "Context hook failed"),
+        Err(_) => warn!(event, items = items.len(), "Context hook panicked"),
+    }
+}
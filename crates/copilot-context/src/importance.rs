@@ -0,0 +1,308 @@
+//! Importance inference for stored context
+//!
+//! [`ContextEngine::store`](crate::ContextEngine::store) takes an explicit
+//! importance, which callers storing conversation turns rarely know. An
+//! [`ImportanceInferer`] derives one from the content and its metadata
+//! instead. Inference is a pure function of its inputs, including the
+//! reference time, so the same message always scores the same.
+//!
+//! The default inferer combines five signals:
+//! - role: user messages outrank assistant and system ones
+//! - entity density: share of words that look like services, hosts,
+//!   metrics, status codes or quantities
+//! - length: longer content scores higher, with diminishing returns
+//! - recency: decays with the age of the content's `timestamp`
+//! - access frequency: how often a stored item has been retrieved
+//!
+//! Content is never accessed before it is stored, so access frequency only
+//! counts when a stored item is rescored: the engine rescores items stored
+//! with an inferred importance each time retrieval records an access.
+
+use crate::memory::{MemoryItem, MemoryMetadata};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Custom metadata key holding an explicit importance, which overrides
+/// inference
+pub const IMPORTANCE_KEY: &str = "importance";
+
+/// Custom metadata key holding the speaker role, e.g. "user"
+pub const ROLE_KEY: &str = "role";
+
+/// Custom metadata key holding the number of entities found in the
+/// content, e.g. by an NLP engine
+pub const ENTITY_COUNT_KEY: &str = "entity_count";
+
+/// Custom metadata key holding when the content was created (RFC 3339)
+pub const TIMESTAMP_KEY: &str = "timestamp";
+
+/// Custom metadata key marking an item whose importance was inferred, and
+/// so is rescored as it is accessed
+pub const INFERRED_KEY: &str = "importance_inferred";
+
+/// Derives an importance for content stored without one
+pub trait ImportanceInferer: Send + Sync {
+    /// Importance in `0.0..=1.0` of content with metadata, as of `now`
+    fn infer(&self, content: &str, metadata: &MemoryMetadata, now: DateTime<Utc>) -> f64;
+
+    /// Importance in `0.0..=1.0` of a stored item, as of `now`
+    ///
+    /// Unlike [`infer`](Self::infer) this sees the item's access
+    /// statistics. By default it ignores them.
+    fn rescore(&self, item: &MemoryItem, now: DateTime<Utc>) -> f64 {
+        self.infer(&item.content, &item.metadata, now)
+    }
+}
+
+/// The importance inferer under the name the engine's
+/// [`with_scorer`](crate::ContextEngineImpl::with_scorer) uses
+pub use self::ImportanceInferer as ImportanceScorer;
+
+/// Importance of stored content: the explicit [`IMPORTANCE_KEY`] metadata
+/// if set, otherwise the inferer's estimate, clamped to `0.0..=1.0`
+pub fn resolve_importance(
+    inferer: &dyn ImportanceInferer,
+    content: &str,
+    metadata: &MemoryMetadata,
+    now: DateTime<Utc>,
+) -> f64 {
+    let importance = metadata
+        .custom
+        .get(IMPORTANCE_KEY)
+        .and_then(serde_json::Value::as_f64)
+        .unwrap_or_else(|| inferer.infer(content, metadata, now));
+    if importance.is_nan() {
+        0.0
+    } else {
+        importance.clamp(0.0, 1.0)
+    }
+}
+
+/// Weights of the default inferer's signals
+///
+/// The weights sum to at most one, so the score stays in range without
+/// clamping.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportanceWeights {
+    /// Score of user messages; other roles score a fraction of it
+    pub role: f64,
+    /// Weight of entity density
+    pub entities: f64,
+    /// Weight of content length
+    pub length: f64,
+    /// Weight of recency
+    pub recency: f64,
+    /// Weight of access frequency
+    #[serde(default)]
+    pub access: f64,
+    /// Age at which the recency signal halves, in seconds
+    pub recency_half_life_secs: f64,
+}
+
+impl Default for ImportanceWeights {
+    fn default() -> Self {
+        Self {
+            role: 0.35,
+            entities: 0.3,
+            length: 0.1,
+            recency: 0.15,
+            access: 0.1,
+            recency_half_life_secs: 3600.0,
+        }
+    }
+}
+
+/// Default inferer from role, entity density, length, recency and access
+/// frequency
+#[derive(Debug, Clone, Default)]
+pub struct DefaultImportanceInferer {
+    weights: ImportanceWeights,
+}
+
+impl DefaultImportanceInferer {
+    /// Create an inferer with custom weights
+    pub fn new(weights: ImportanceWeights) -> Self {
+        Self { weights }
+    }
+
+    /// Share of the role weight a role gets
+    fn role_factor(metadata: &MemoryMetadata) -> f64 {
+        let role = metadata
+            .custom
+            .get(ROLE_KEY)
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or(metadata.source.as_str());
+        match role {
+            "user" | "user_input" => 1.0,
+            "system" => 0.8,
+            "assistant" | "llm_output" | "llm_response" => 0.7,
+            _ => 0.5,
+        }
+    }
+
+    /// Entities per word, saturating at one entity every three words
+    fn entity_factor(content: &str, metadata: &MemoryMetadata) -> f64 {
+        let words = content.split_whitespace().count();
+        if words == 0 {
+            return 0.0;
+        }
+        let entities = metadata
+            .custom
+            .get(ENTITY_COUNT_KEY)
+            .and_then(serde_json::Value::as_u64)
+            .map(|count| count as usize)
+            .unwrap_or_else(|| content.split_whitespace().filter(|w| looks_like_entity(w)).count());
+        (entities as f64 * 3.0 / words as f64).min(1.0)
+    }
+
+    /// Length on a log scale, saturating around 2000 characters
+    fn length_factor(content: &str) -> f64 {
+        ((content.chars().count() as f64).ln_1p() / 2000f64.ln_1p()).min(1.0)
+    }
+
+    fn recency_factor(&self, metadata: &MemoryMetadata, now: DateTime<Utc>) -> f64 {
+        let created = metadata
+            .custom
+            .get(TIMESTAMP_KEY)
+            .and_then(serde_json::Value::as_str)
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok());
+        let Some(created) = created else {
+            // Content stored as it is created is as recent as it gets
+            return 1.0;
+        };
+        let age = (now - created.with_timezone(&Utc)).num_seconds().max(0) as f64;
+        0.5f64.powf(age / self.weights.recency_half_life_secs.max(1.0))
+    }
+
+    /// Accesses on a log scale, saturating at ten
+    fn access_factor(accesses: u64) -> f64 {
+        ((accesses as f64).ln_1p() / 10f64.ln_1p()).min(1.0)
+    }
+
+    fn score(&self, content: &str, metadata: &MemoryMetadata, accesses: u64, now: DateTime<Utc>) -> f64 {
+        let w = &self.weights;
+        let score = w.role * Self::role_factor(metadata)
+            + w.entities * Self::entity_factor(content, metadata)
+            + w.length * Self::length_factor(content)
+            + w.recency * self.recency_factor(metadata, now)
+            + w.access * Self::access_factor(accesses);
+        score.clamp(0.0, 1.0)
+    }
+}
+
+impl ImportanceInferer for DefaultImportanceInferer {
+    fn infer(&self, content: &str, metadata: &MemoryMetadata, now: DateTime<Utc>) -> f64 {
+        self.score(content, metadata, 0, now)
+    }
+
+    fn rescore(&self, item: &MemoryItem, now: DateTime<Utc>) -> f64 {
+        self.score(&item.content, &item.metadata, item.access_count, now)
+    }
+}
+
+/// Whether a word looks like an observability entity
+///
+/// Service and host names (`checkout-service`, `db-1.prod`), metric names
+/// (`http_requests_total`), status codes, and quantities such as `5m`,
+/// `99%` or `250ms`.
+fn looks_like_entity(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '%');
+    if word.len() < 2 {
+        return false;
+    }
+    let has_digit = word.chars().any(|c| c.is_ascii_digit());
+    let has_alpha = word.chars().any(|c| c.is_alphabetic());
+    let separated = word.contains(['-', '_', '.', ':', '/']);
+
+    (separated && has_alpha) || (has_digit && (has_alpha || word.len() == 3)) || word.ends_with('%')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_message() -> MemoryMetadata {
+        let mut metadata = MemoryMetadata::new("conversation", "user_input");
+        metadata.add_custom(ROLE_KEY.to_string(), serde_json::json!("user"));
+        metadata
+    }
+
+    #[test]
+    fn test_entity_dense_message_outranks_trivial_one() {
+        let inferer = DefaultImportanceInferer::default();
+        let now = Utc::now();
+
+        let trivial = inferer.infer("ok thanks", &user_message(), now);
+        let dense = inferer.infer(
+            "checkout-service p99 latency hit 850ms on db-1.prod with 503 errors since 14:05",
+            &user_message(),
+            now,
+        );
+
+        assert!(dense > trivial + 0.2, "dense {} vs trivial {}", dense, trivial);
+        assert!((0.0..=1.0).contains(&dense) && (0.0..=1.0).contains(&trivial));
+    }
+
+    #[test]
+    fn test_inference_is_deterministic_and_decays() {
+        let inferer = DefaultImportanceInferer::default();
+        let now = Utc::now();
+        let mut metadata = user_message();
+        metadata.add_custom(
+            TIMESTAMP_KEY.to_string(),
+            serde_json::json!((now - chrono::Duration::hours(2)).to_rfc3339()),
+        );
+
+        let first = inferer.infer("restart api-gateway", &metadata, now);
+        assert_eq!(first, inferer.infer("restart api-gateway", &metadata, now));
+        assert!(first < inferer.infer("restart api-gateway", &user_message(), now));
+
+        let assistant = MemoryMetadata::new("conversation", "llm_output");
+        assert!(
+            inferer.infer("restart api-gateway", &assistant, now)
+                < inferer.infer("restart api-gateway", &user_message(), now)
+        );
+    }
+
+    #[test]
+    fn test_recency_access_and_length_each_move_the_score() {
+        let inferer = DefaultImportanceInferer::default();
+        let now = Utc::now();
+        let content = "please restart the gateway";
+        let item = MemoryItem::new(content.to_string(), user_message(), 0.5, 5);
+        let base = inferer.rescore(&item, now);
+        assert_eq!(base, inferer.infer(content, &user_message(), now));
+
+        let mut stale = item.clone();
+        stale.metadata.add_custom(
+            TIMESTAMP_KEY.to_string(),
+            serde_json::json!((now - chrono::Duration::hours(2)).to_rfc3339()),
+        );
+        assert!(inferer.rescore(&stale, now) < base);
+
+        let mut accessed = item.clone();
+        for _ in 0..5 {
+            accessed.record_access();
+        }
+        assert!(inferer.rescore(&accessed, now) > base);
+
+        let longer = MemoryItem::new(
+            format!("{} once the connection pool drains and traffic has shifted away", content),
+            user_message(),
+            0.5,
+            16,
+        );
+        assert!(inferer.rescore(&longer, now) > base);
+    }
+
+    #[test]
+    fn test_explicit_importance_overrides_and_is_clamped() {
+        let inferer = DefaultImportanceInferer::default();
+        let mut metadata = user_message();
+        metadata.add_custom(IMPORTANCE_KEY.to_string(), serde_json::json!(0.05));
+        assert_eq!(resolve_importance(&inferer, "checkout-service 503", &metadata, Utc::now()), 0.05);
+
+        metadata.add_custom(IMPORTANCE_KEY.to_string(), serde_json::json!(7.0));
+        assert_eq!(resolve_importance(&inferer, "x", &metadata, Utc::now()), 1.0);
+    }
+}
@@ -4,17 +4,38 @@
 //! compression, and token budget management for LLM interactions.
 
 pub mod compression;
+pub mod disk;
 pub mod engine;
+pub mod expansion;
+pub mod export;
+pub mod hooks;
 pub mod hybrid_search;
+pub mod importance;
 pub mod memory;
+pub mod merge;
 pub mod reranking;
 pub mod retrieval;
+pub mod tokenizer;
 
 // Re-exports
-pub use engine::{ContextEngine, ContextEngineImpl, ContextEngineConfig};
-pub use memory::{MemoryTier, MemoryItem, MemoryStore, MemoryMetadata};
-pub use retrieval::{RelevanceScorer, ContextWindow, RetrievalConfig};
-pub use compression::{CompressionStrategy, CompressionConfig, Compressor};
+pub use disk::DiskStore;
+pub use engine::{
+    ContextEngine, ContextEngineImpl, ContextEngineConfig, MaintenanceAction, MaintenanceEvent,
+};
+pub use export::VectorRecord;
+pub use hooks::ContextHooks;
+pub use importance::{DefaultImportanceInferer, ImportanceInferer, ImportanceWeights};
+pub use memory::{MemoryTier, MemoryItem, MemoryStore, MemoryMetadata, MemoryFilter, Provenance};
+pub use merge::{ContextMerger, ContextSource, MergeConflict, MergePolicy, MergeResult};
+pub use expansion::{
+    EntityTerm, ExpandedQuery, ExpansionEffect, QueryExpander, QueryExpansionConfig,
+    SynonymDictionary,
+};
+pub use retrieval::{
+    RelevanceScorer, ContextWindow, RetrievalConfig, RecencyDecayConfig, RecencyReference,
+};
+pub use tokenizer::{ApproximateTokenizer, TiktokenTokenizer, Tokenizer, TokenizerConfig};
+pub use compression::{CompressionStrategy, CompressionConfig, Compressor, ReservationHandle};
 pub use hybrid_search::{
     HybridSearchEngine, HybridSearchConfig, HybridSearchResult,
     EmbeddingProvider, BM25Scorer, SimilarityMetric, Embedding,
@@ -40,6 +61,9 @@ pub enum ContextError {
     #[error("Compression failed: {0}")]
     CompressionFailed(String),
 
+    #[error("Token reservation not found: {0}")]
+    ReservationNotFound(u64),
+
     #[error("Retrieval failed: {0}")]
     RetrievalFailed(String),
 
@@ -59,6 +59,85 @@ impl MemoryTier {
     }
 }
 
+/// Where a memory item's content originally came from
+///
+/// Carried through compression so prompts built from compressed items can
+/// still cite their sources.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Provenance {
+    /// A conversation message
+    Message {
+        /// Message ID
+        message_id: String,
+        /// Session the message belongs to
+        session_id: Option<String>,
+    },
+    /// A knowledge-base document
+    Document {
+        /// Document ID
+        document_id: String,
+        /// Document title, if known
+        title: Option<String>,
+    },
+    /// A summary of earlier conversation or context
+    Summary {
+        /// ID of the summary
+        summary_id: String,
+    },
+    /// Any other source, identified by the item's `source` metadata
+    Other {
+        /// Free-form source, e.g. "user_input"
+        source: String,
+    },
+}
+
+impl Provenance {
+    /// A conversation message
+    pub fn message(message_id: impl Into<String>) -> Self {
+        Self::Message {
+            message_id: message_id.into(),
+            session_id: None,
+        }
+    }
+
+    /// A knowledge-base document
+    pub fn document(document_id: impl Into<String>) -> Self {
+        Self::Document {
+            document_id: document_id.into(),
+            title: None,
+        }
+    }
+
+    /// A summary
+    pub fn summary(summary_id: impl Into<String>) -> Self {
+        Self::Summary {
+            summary_id: summary_id.into(),
+        }
+    }
+
+    /// Short name of the kind of source, e.g. for analytics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Message { .. } => "message",
+            Self::Document { .. } => "document",
+            Self::Summary { .. } => "summary",
+            Self::Other { .. } => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Message { message_id, .. } => write!(f, "message:{}", message_id),
+            Self::Document { document_id, .. } => write!(f, "document:{}", document_id),
+            Self::Summary { summary_id } => write!(f, "summary:{}", summary_id),
+            Self::Other { source } => write!(f, "other:{}", source),
+        }
+    }
+}
+
 /// Metadata associated with memory items
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryMetadata {
@@ -74,6 +153,10 @@ pub struct MemoryMetadata {
     /// Tags for categorization
     pub tags: Vec<String>,
 
+    /// Typed origin of the content, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+
     /// Custom metadata fields
     #[serde(flatten)]
     pub custom: HashMap<String, serde_json::Value>,
@@ -86,6 +169,7 @@ impl MemoryMetadata {
             content_type: content_type.into(),
             source: source.into(),
             tags: Vec::new(),
+            provenance: None,
             custom: HashMap::new(),
         }
     }
@@ -95,11 +179,90 @@ impl MemoryMetadata {
         self
     }
 
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
     pub fn add_custom(&mut self, key: String, value: serde_json::Value) {
         self.custom.insert(key, value);
     }
 }
 
+/// Exact-match criteria for looking up stored items
+///
+/// Every criterion that is set must match; an empty filter matches every
+/// item. Ranges include their bounds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryFilter {
+    /// Source the item came from
+    pub source: Option<String>,
+
+    /// Tag the item carries
+    pub tag: Option<String>,
+
+    /// Tier the item is in
+    pub tier: Option<MemoryTier>,
+
+    /// Lowest base importance
+    pub min_importance: Option<f64>,
+
+    /// Highest base importance
+    pub max_importance: Option<f64>,
+
+    /// Earliest creation time
+    pub created_after: Option<DateTime<Utc>>,
+
+    /// Latest creation time
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl MemoryFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    pub fn with_tier(mut self, tier: MemoryTier) -> Self {
+        self.tier = Some(tier);
+        self
+    }
+
+    /// Match items whose importance lies in `min..=max`
+    pub fn with_importance(mut self, min: f64, max: f64) -> Self {
+        self.min_importance = Some(min);
+        self.max_importance = Some(max);
+        self
+    }
+
+    /// Match items created in `after..=before`
+    pub fn created_between(mut self, after: DateTime<Utc>, before: DateTime<Utc>) -> Self {
+        self.created_after = Some(after);
+        self.created_before = Some(before);
+        self
+    }
+
+    /// Whether an item matches every criterion that is set
+    pub fn matches(&self, item: &MemoryItem) -> bool {
+        self.source.as_ref().map_or(true, |source| &item.metadata.source == source)
+            && self.tag.as_ref().map_or(true, |tag| item.metadata.tags.contains(tag))
+            && self.tier.map_or(true, |tier| item.tier == tier)
+            && self.min_importance.map_or(true, |min| item.importance >= min)
+            && self.max_importance.map_or(true, |max| item.importance <= max)
+            && self.created_after.map_or(true, |after| item.created_at >= after)
+            && self.created_before.map_or(true, |before| item.created_at <= before)
+    }
+}
+
 /// A memory item stored in the context engine
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryItem {
@@ -129,6 +292,10 @@ pub struct MemoryItem {
 
     /// Compressed version (if available)
     pub compressed_content: Option<String>,
+
+    /// Embedding of the content (if an embedding provider is configured)
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
 }
 
 impl MemoryItem {
@@ -151,6 +318,7 @@ impl MemoryItem {
             access_count: 0,
             token_count,
             compressed_content: None,
+            embedding: None,
         }
     }
 
@@ -235,6 +403,19 @@ impl MemoryItem {
     pub fn get_content(&self) -> &str {
         self.compressed_content.as_deref().unwrap_or(&self.content)
     }
+
+    /// Where the content came from
+    ///
+    /// Falls back to the free-form `source` metadata for items stored
+    /// without typed provenance.
+    pub fn provenance(&self) -> Provenance {
+        self.metadata
+            .provenance
+            .clone()
+            .unwrap_or_else(|| Provenance::Other {
+                source: self.metadata.source.clone(),
+            })
+    }
 }
 
 /// Importance scoring algorithm
@@ -411,34 +592,71 @@ impl MemoryStore for InMemoryStore {
             return Ok(Vec::new());
         }
 
-        let mut items: Vec<_> = self.items.values().cloned().collect();
-        items.sort_by(|a, b| {
-            a.current_importance()
-                .partial_cmp(&b.current_importance())
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        let mut evicted = Vec::new();
-        let mut freed_tokens = 0;
-        let tokens_to_free = current_tokens - target_tokens;
-
-        for item in items {
-            if freed_tokens >= tokens_to_free {
-                break;
-            }
-            freed_tokens += item.token_count;
+        let evicted = self.eviction_candidates(target_tokens);
+        for item in &evicted {
             self.items.remove(&item.metadata.id);
-            evicted.push(item);
         }
 
         Ok(evicted)
     }
 }
 
+impl InMemoryStore {
+    /// Items [`MemoryStore::evict`] would remove to get down to
+    /// `target_tokens`, least important first, without removing them
+    pub fn eviction_candidates(&self, target_tokens: usize) -> Vec<MemoryItem> {
+        eviction_candidates(self.items.values().cloned().collect(), target_tokens, |item| item.token_count)
+    }
+}
+
+/// The least important of `items` that must go to get their tokens, as
+/// measured by `tokens`, down to `target_tokens`
+pub(crate) fn eviction_candidates(
+    mut items: Vec<MemoryItem>,
+    target_tokens: usize,
+    tokens: impl Fn(&MemoryItem) -> usize,
+) -> Vec<MemoryItem> {
+    let current_tokens: usize = items.iter().map(&tokens).sum();
+    if current_tokens <= target_tokens {
+        return Vec::new();
+    }
+
+    items.sort_by(|a, b| {
+        a.current_importance()
+            .partial_cmp(&b.current_importance())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut candidates = Vec::new();
+    let mut freed_tokens = 0;
+    let tokens_to_free = current_tokens - target_tokens;
+
+    for item in items {
+        if freed_tokens >= tokens_to_free {
+            break;
+        }
+        freed_tokens += tokens(&item);
+        candidates.push(item);
+    }
+
+    candidates
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_memory_filter_matches_tags() {
+        let metadata = MemoryMetadata::new("log", "ci").with_tags(vec!["deploy".to_string()]);
+        let item = MemoryItem::new("Deploy failed".to_string(), metadata, 0.4, 3);
+
+        assert!(MemoryFilter::new().matches(&item));
+        assert!(MemoryFilter::new().with_tag("deploy").with_source("ci").matches(&item));
+        assert!(!MemoryFilter::new().with_tag("rollback").matches(&item));
+        assert!(!MemoryFilter::new().with_importance(0.5, 1.0).matches(&item));
+    }
+
     #[test]
     fn test_memory_tier_capacity() {
         assert_eq!(MemoryTier::ShortTerm.token_capacity(), 10_000);
@@ -0,0 +1,324 @@
+//! Merging context from multiple sources
+//!
+//! A prompt is often grounded in several sources at once: conversation
+//! history, the knowledge base and a shared team memory. They overlap, and
+//! sometimes disagree ("checkout runs 3 replicas" in an old runbook, "5
+//! replicas" in this morning's conversation). [`ContextMerger`] combines
+//! them into one set: it drops duplicates, keeps one item per fact under a
+//! [`MergePolicy`], and fills the token budget in policy order.
+//!
+//! Two items state the same fact when they share a [`FACT_KEY`] metadata
+//! value, or, without one, when their content is the same after
+//! normalizing case and whitespace. Items of the same fact with different
+//! content conflict; the kept item lists the provenance of the ones it
+//! superseded under [`CONFLICTS_KEY`], so a prompt can cite the
+//! disagreement instead of hiding it.
+//!
+//! Merging is deterministic: it depends only on the sources, their order
+//! and the items' stored importance and timestamps, never on the clock.
+
+use crate::memory::{MemoryItem, Provenance};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Metadata key naming the fact an item states
+pub const FACT_KEY: &str = "fact_key";
+
+/// Metadata key listing the provenance of conflicting items a kept item
+/// superseded
+pub const CONFLICTS_KEY: &str = "merge_conflicts";
+
+/// Which item to keep when several state the same fact
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// The most recently created item
+    #[default]
+    MostRecent,
+    /// The item with the highest stored importance
+    HighestImportance,
+    /// The item from the source listed first
+    SourcePriority,
+}
+
+/// Items from one source of context
+#[derive(Debug, Clone)]
+pub struct ContextSource {
+    /// Source name, e.g. "history" or "knowledge_base"
+    pub name: String,
+    /// The source's items
+    pub items: Vec<MemoryItem>,
+}
+
+impl ContextSource {
+    pub fn new(name: impl Into<String>, items: Vec<MemoryItem>) -> Self {
+        Self {
+            name: name.into(),
+            items,
+        }
+    }
+}
+
+/// Items that stated the same fact differently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    /// The fact the items state
+    pub fact: String,
+    /// Provenance of the kept item
+    pub kept: Provenance,
+    /// Provenance of the superseded items, in preference order
+    pub superseded: Vec<Provenance>,
+}
+
+/// Result of a merge
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    /// Merged items within the budget, in policy order
+    pub items: Vec<MemoryItem>,
+    /// Kept items that did not fit the budget
+    pub over_budget: Vec<MemoryItem>,
+    /// Facts stated differently by several items
+    pub conflicts: Vec<MergeConflict>,
+    /// Number of items dropped as exact duplicates
+    pub duplicates_removed: usize,
+    /// Tokens of the merged items
+    pub total_tokens: usize,
+}
+
+/// Merges context from several sources
+#[derive(Debug, Clone, Default)]
+pub struct ContextMerger {
+    policy: MergePolicy,
+}
+
+/// An item with its position among the sources, for tie-breaking
+struct Candidate {
+    source: usize,
+    position: usize,
+    content: String,
+    item: MemoryItem,
+}
+
+impl ContextMerger {
+    pub fn new(policy: MergePolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> MergePolicy {
+        self.policy
+    }
+
+    /// Merge sources into a deduplicated set within `token_budget`
+    ///
+    /// Sources are listed in priority order, which
+    /// [`MergePolicy::SourcePriority`] ranks by and every policy falls back
+    /// on for ties.
+    pub fn merge(&self, sources: Vec<ContextSource>, token_budget: usize) -> MergeResult {
+        // Group items by fact, in first-seen order
+        let mut facts: Vec<(String, Vec<Candidate>)> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (source, context) in sources.into_iter().enumerate() {
+            for (position, item) in context.items.into_iter().enumerate() {
+                let content = normalize(item.get_content());
+                let fact = item
+                    .metadata
+                    .custom
+                    .get(FACT_KEY)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| content.clone());
+                let slot = *index.entry(fact.clone()).or_insert_with(|| {
+                    facts.push((fact, Vec::new()));
+                    facts.len() - 1
+                });
+                facts[slot].1.push(Candidate {
+                    source,
+                    position,
+                    content,
+                    item,
+                });
+            }
+        }
+
+        let mut kept = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut duplicates_removed = 0;
+        for (fact, mut candidates) in facts {
+            candidates.sort_by(|a, b| self.compare(a, b));
+            let mut candidates = candidates.into_iter();
+            let Some(mut winner) = candidates.next() else {
+                continue;
+            };
+
+            let mut superseded: Vec<Provenance> = Vec::new();
+            for other in candidates {
+                if other.content == winner.content {
+                    duplicates_removed += 1;
+                    continue;
+                }
+                let provenance = other.item.provenance();
+                if !superseded.contains(&provenance) {
+                    superseded.push(provenance);
+                }
+            }
+            if !superseded.is_empty() {
+                winner.item.metadata.add_custom(
+                    CONFLICTS_KEY.to_string(),
+                    superseded.iter().map(|p| p.to_string()).collect(),
+                );
+                conflicts.push(MergeConflict {
+                    fact,
+                    kept: winner.item.provenance(),
+                    superseded,
+                });
+            }
+            kept.push(winner);
+        }
+
+        // Fill the budget in policy order
+        kept.sort_by(|a, b| self.compare(a, b));
+        let mut items = Vec::new();
+        let mut over_budget = Vec::new();
+        let mut total_tokens = 0;
+        for candidate in kept {
+            if total_tokens + candidate.item.token_count <= token_budget {
+                total_tokens += candidate.item.token_count;
+                items.push(candidate.item);
+            } else {
+                over_budget.push(candidate.item);
+            }
+        }
+
+        MergeResult {
+            items,
+            over_budget,
+            conflicts,
+            duplicates_removed,
+            total_tokens,
+        }
+    }
+
+    /// Order candidates from most to least preferred
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        let recency = || b.item.created_at.cmp(&a.item.created_at);
+        let importance = || b.item.importance.total_cmp(&a.item.importance);
+        let priority = || a.source.cmp(&b.source);
+
+        let preferred = match self.policy {
+            MergePolicy::MostRecent => recency().then_with(importance),
+            MergePolicy::HighestImportance => importance().then_with(recency),
+            MergePolicy::SourcePriority => priority().then_with(recency),
+        };
+        preferred
+            .then_with(priority)
+            .then_with(|| a.position.cmp(&b.position))
+            .then_with(|| a.item.metadata.id.cmp(&b.item.metadata.id))
+    }
+}
+
+/// Content with case and whitespace normalized
+fn normalize(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryMetadata;
+    use chrono::{Duration, Utc};
+
+    fn item(content: &str, fact: Option<&str>, provenance: Provenance, importance: f64, age_hours: i64) -> MemoryItem {
+        let mut metadata = MemoryMetadata::new("fact", "test").with_provenance(provenance);
+        if let Some(fact) = fact {
+            metadata.add_custom(FACT_KEY.to_string(), fact.into());
+        }
+        let mut item = MemoryItem::new(content.to_string(), metadata, importance, 10);
+        item.created_at = Utc::now() - Duration::hours(age_hours);
+        item
+    }
+
+    /// History and a knowledge base that share one fact verbatim and
+    /// disagree on another
+    fn sources() -> Vec<ContextSource> {
+        vec![
+            ContextSource::new(
+                "history",
+                vec![
+                    item("checkout runs 5 replicas", Some("checkout.replicas"), Provenance::message("m-2"), 0.4, 1),
+                    item("Deploys freeze on Fridays", None, Provenance::message("m-1"), 0.5, 2),
+                ],
+            ),
+            ContextSource::new(
+                "knowledge_base",
+                vec![
+                    item("checkout runs 3 replicas", Some("checkout.replicas"), Provenance::document("runbook"), 0.9, 48),
+                    item("deploys  freeze on fridays", None, Provenance::document("policy"), 0.8, 72),
+                ],
+            ),
+        ]
+    }
+
+    fn replicas(result: &MergeResult) -> &str {
+        &result
+            .items
+            .iter()
+            .find(|i| i.content.contains("replicas"))
+            .unwrap()
+            .content
+    }
+
+    #[test]
+    fn test_merge_under_each_policy() {
+        let merge = |policy| ContextMerger::new(policy).merge(sources(), 1_000);
+
+        let recent = merge(MergePolicy::MostRecent);
+        assert_eq!(replicas(&recent), "checkout runs 5 replicas");
+        assert_eq!(recent.items.len(), 2);
+        assert_eq!(recent.duplicates_removed, 1);
+
+        let important = merge(MergePolicy::HighestImportance);
+        assert_eq!(replicas(&important), "checkout runs 3 replicas");
+        // Ordered by importance: the kept duplicate is the policy document
+        assert_eq!(important.items[1].provenance(), Provenance::document("policy"));
+
+        let priority = merge(MergePolicy::SourcePriority);
+        assert_eq!(replicas(&priority), "checkout runs 5 replicas");
+        assert_eq!(priority.items[1].provenance(), Provenance::message("m-1"));
+
+        // The same input always merges the same way
+        let again = merge(MergePolicy::HighestImportance);
+        let ids = |r: &MergeResult| r.items.iter().map(|i| i.content.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&important), ids(&again));
+    }
+
+    #[test]
+    fn test_conflicts_are_flagged_in_provenance() {
+        let result = ContextMerger::new(MergePolicy::HighestImportance).merge(sources(), 1_000);
+
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.fact, "checkout.replicas");
+        assert_eq!(conflict.kept, Provenance::document("runbook"));
+        assert_eq!(conflict.superseded, vec![Provenance::message("m-2")]);
+
+        let kept = result.items.iter().find(|i| i.content.contains("replicas")).unwrap();
+        assert_eq!(kept.metadata.custom[CONFLICTS_KEY], serde_json::json!(["message:m-2"]));
+        // Duplicates are not conflicts
+        let policy = result.items.iter().find(|i| i.content.contains("freeze")).unwrap();
+        assert!(!policy.metadata.custom.contains_key(CONFLICTS_KEY));
+    }
+
+    #[test]
+    fn test_merge_respects_token_budget() {
+        let result = ContextMerger::new(MergePolicy::MostRecent).merge(sources(), 15);
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].content, "checkout runs 5 replicas");
+        assert_eq!(result.over_budget.len(), 1);
+        assert_eq!(result.total_tokens, 10);
+    }
+}
@@ -3,9 +3,10 @@
 //! Provides intelligent retrieval of context items based on relevance,
 //! importance, and recency with token budget management.
 
+use crate::expansion::{ExpandedQuery, ExpansionEffect};
 use crate::{ContextError, MemoryItem, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::cmp::Ordering;
 
 /// Configuration for context retrieval
@@ -31,6 +32,10 @@ pub struct RetrievalConfig {
 
     /// Include compressed content
     pub allow_compressed: bool,
+
+    /// Recency decay applied to composite scores
+    #[serde(default)]
+    pub recency_decay: RecencyDecayConfig,
 }
 
 impl Default for RetrievalConfig {
@@ -43,6 +48,7 @@ impl Default for RetrievalConfig {
             recency_weight: 0.2,
             min_relevance: 0.3,
             allow_compressed: true,
+            recency_decay: RecencyDecayConfig::default(),
         }
     }
 }
@@ -71,6 +77,87 @@ impl RetrievalConfig {
     }
 }
 
+/// Timestamp an item's age is measured from for recency decay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecencyReference {
+    /// Age since the item was last retrieved
+    LastAccessed,
+    /// Age since the item was stored
+    CreatedAt,
+}
+
+/// Exponential recency decay applied at retrieval time
+///
+/// An item's recency signal is `0.5^(age / half_life)`. The item's
+/// importance offsets part of the decay, so a highly important item keeps
+/// most of its recency even when old. This is the only place age lowers a
+/// composite score: the importance signal is taken as stored, undecayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecencyDecayConfig {
+    /// Decay the recency signal with age; when disabled every item counts
+    /// as fully recent
+    pub enabled: bool,
+
+    /// Default half-life in seconds
+    pub half_life_secs: f64,
+
+    /// Per-namespace half-lives in seconds, keyed by content type
+    pub namespace_half_lives: HashMap<String, f64>,
+
+    /// How much importance offsets decay (0.0 - 1.0). With 1.0 an item of
+    /// importance 1.0 does not decay at all.
+    pub importance_offset: f64,
+
+    /// Timestamp used to compute an item's age
+    pub reference: RecencyReference,
+}
+
+impl Default for RecencyDecayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            half_life_secs: 24.0 * 3600.0,
+            namespace_half_lives: HashMap::new(),
+            importance_offset: 0.5,
+            reference: RecencyReference::LastAccessed,
+        }
+    }
+}
+
+impl RecencyDecayConfig {
+    /// Set the half-life for a namespace
+    pub fn with_namespace_half_life(mut self, namespace: impl Into<String>, half_life_secs: f64) -> Self {
+        self.namespace_half_lives.insert(namespace.into(), half_life_secs);
+        self
+    }
+
+    /// Get the half-life for a namespace, falling back to the default
+    pub fn half_life_for(&self, namespace: &str) -> f64 {
+        self.namespace_half_lives
+            .get(namespace)
+            .copied()
+            .unwrap_or(self.half_life_secs)
+    }
+
+    /// Calculate the decay (0.0 - 1.0) for an item of the given age
+    pub fn decay(&self, namespace: &str, age_seconds: f64) -> f64 {
+        let half_life = self.half_life_for(namespace);
+        if half_life <= 0.0 {
+            return 1.0;
+        }
+
+        0.5_f64.powf(age_seconds.max(0.0) / half_life)
+    }
+
+    /// Calculate the score multiplier, with importance offsetting decay
+    pub fn multiplier(&self, namespace: &str, age_seconds: f64, importance: f64) -> f64 {
+        let decay = self.decay(namespace, age_seconds);
+        let offset = (importance.clamp(0.0, 1.0) * self.importance_offset.clamp(0.0, 1.0)).min(1.0);
+
+        decay + (1.0 - decay) * offset
+    }
+}
+
 /// Relevance scorer for context items
 pub struct RelevanceScorer {
     config: RetrievalConfig,
@@ -83,8 +170,17 @@ impl RelevanceScorer {
 
     /// Calculate relevance score between query and content
     pub fn calculate_relevance(&self, query: &str, content: &str) -> f64 {
+        self.calculate_expanded_relevance(&ExpandedQuery::new(query), content)
+    }
+
+    /// Calculate relevance score, counting a query word as matched when it
+    /// or any of its alternatives appears in the content
+    ///
+    /// Alternatives only match as whole words, so a short synonym such as
+    /// "mem" does not match inside "member".
+    pub fn calculate_expanded_relevance(&self, query: &ExpandedQuery, content: &str) -> f64 {
         // Simple keyword-based relevance (in production, use embeddings)
-        let query_lower = query.to_lowercase();
+        let query_lower = query.original.to_lowercase();
         let content_lower = content.to_lowercase();
 
         let query_words: Vec<&str> = query_lower.split_whitespace().collect();
@@ -100,7 +196,13 @@ impl RelevanceScorer {
                 continue; // Skip short words
             }
 
-            let count = content_lower.matches(word).count();
+            let count = query
+                .alternatives_for(word)
+                .iter()
+                .map(|term| count_whole_words(&content_lower, term))
+                .chain(std::iter::once(content_lower.matches(word).count()))
+                .max()
+                .unwrap_or(0);
             if count > 0 {
                 matches += 1;
                 // Earlier words in query are more important
@@ -115,19 +217,41 @@ impl RelevanceScorer {
         (coverage * 0.7 + density * 0.3).min(1.0)
     }
 
+    /// Age of an item in seconds, measured from the configured reference
+    fn item_age_seconds(&self, item: &MemoryItem) -> f64 {
+        let reference = match self.config.recency_decay.reference {
+            RecencyReference::LastAccessed => item.last_accessed,
+            RecencyReference::CreatedAt => item.created_at,
+        };
+
+        (chrono::Utc::now() - reference).num_milliseconds() as f64 / 1000.0
+    }
+
     /// Calculate recency score (0.0 - 1.0)
     pub fn calculate_recency(&self, item: &MemoryItem) -> f64 {
-        let age_seconds = (chrono::Utc::now() - item.last_accessed).num_seconds() as f64;
-        let half_life_hours = 24.0; // Score halves every 24 hours
-
-        let decay = (-age_seconds / (half_life_hours * 3600.0) * 0.693).exp();
-        decay
+        let decay = &self.config.recency_decay;
+        if !decay.enabled {
+            return 1.0;
+        }
+        decay.multiplier(&item.metadata.content_type, self.item_age_seconds(item), item.importance)
     }
 
     /// Calculate composite score for retrieval prioritization
     pub fn calculate_score(&self, query: &str, item: &MemoryItem) -> f64 {
         let relevance = self.calculate_relevance(query, item.get_content());
-        let importance = item.current_importance();
+        self.score_with_relevance(relevance, item)
+    }
+
+    /// Calculate composite score for an expanded query
+    pub fn calculate_expanded_score(&self, query: &ExpandedQuery, item: &MemoryItem) -> f64 {
+        let relevance = self.calculate_expanded_relevance(query, item.get_content());
+        self.score_with_relevance(relevance, item)
+    }
+
+    fn score_with_relevance(&self, relevance: f64, item: &MemoryItem) -> f64 {
+        // Age only counts through the recency signal, so the stored
+        // importance is used rather than the time-decayed one
+        let importance = item.importance;
         let recency = self.calculate_recency(item);
 
         self.config.relevance_weight * relevance
@@ -137,12 +261,21 @@ impl RelevanceScorer {
 
     /// Filter items by minimum relevance
     pub fn filter_relevant(&self, query: &str, items: Vec<MemoryItem>) -> Vec<ScoredItem> {
+        self.filter_relevant_expanded(&ExpandedQuery::new(query), items)
+    }
+
+    /// Filter items by minimum relevance to an expanded query
+    pub fn filter_relevant_expanded(
+        &self,
+        query: &ExpandedQuery,
+        items: Vec<MemoryItem>,
+    ) -> Vec<ScoredItem> {
         items
             .into_iter()
             .filter_map(|item| {
-                let relevance = self.calculate_relevance(query, item.get_content());
+                let relevance = self.calculate_expanded_relevance(query, item.get_content());
                 if relevance >= self.config.min_relevance {
-                    let score = self.calculate_score(query, &item);
+                    let score = self.score_with_relevance(relevance, &item);
                     Some(ScoredItem { item, score })
                 } else {
                     None
@@ -152,6 +285,22 @@ impl RelevanceScorer {
     }
 }
 
+/// Count occurrences of `term` in `content` bounded by non-alphanumeric
+/// characters or the ends of the content
+fn count_whole_words(content: &str, term: &str) -> usize {
+    if term.is_empty() {
+        return 0;
+    }
+    content
+        .match_indices(term)
+        .filter(|(start, _)| {
+            let before = content[..*start].chars().next_back();
+            let after = content[start + term.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+        .count()
+}
+
 /// Memory item with retrieval score
 #[derive(Debug, Clone)]
 pub struct ScoredItem {
@@ -195,6 +344,11 @@ impl ContextWindow {
         Ok(Self { config, scorer })
     }
 
+    /// The scorer items are ranked with
+    pub fn scorer(&self) -> &RelevanceScorer {
+        &self.scorer
+    }
+
     /// Retrieve and prioritize items within token budget
     pub fn retrieve(&self, query: &str, items: Vec<MemoryItem>) -> Result<RetrievalResult> {
         let target_tokens = self.config.target_tokens();
@@ -227,15 +381,38 @@ impl ContextWindow {
             total_tokens: current_tokens,
             target_tokens,
             max_tokens: self.config.max_tokens,
+            expansion: None,
         })
     }
 
     /// Retrieve with advanced prioritization (knapsack-like optimization)
     pub fn retrieve_optimized(&self, query: &str, items: Vec<MemoryItem>) -> Result<RetrievalResult> {
-        let target_tokens = self.config.target_tokens();
+        self.retrieve_expanded(&ExpandedQuery::new(query), items)
+    }
+
+    /// Optimized retrieval for an expanded query
+    ///
+    /// When the query carries expansions, the result records how many
+    /// selected items were recovered by them.
+    pub fn retrieve_expanded(
+        &self,
+        query: &ExpandedQuery,
+        items: Vec<MemoryItem>,
+    ) -> Result<RetrievalResult> {
+        self.retrieve_within(query, items, self.config.target_tokens())
+    }
+
+    /// Optimized retrieval for an expanded query within an explicit token
+    /// budget, e.g. the target less space reserved for a completion
+    pub fn retrieve_within(
+        &self,
+        query: &ExpandedQuery,
+        items: Vec<MemoryItem>,
+        target_tokens: usize,
+    ) -> Result<RetrievalResult> {
 
         // Score and filter items
-        let scored_items = self.scorer.filter_relevant(query, items);
+        let scored_items = self.scorer.filter_relevant_expanded(query, items);
 
         // Use priority queue for better selection
         let mut heap: BinaryHeap<ScoredItem> = scored_items.into_iter().collect();
@@ -261,12 +438,24 @@ impl ContextWindow {
 
         let total_tokens = selected.iter().map(|s| s.item.token_count).sum();
 
+        let expansion = query.is_expanded().then(|| ExpansionEffect {
+            added_terms: query.added_terms().into_iter().map(String::from).collect(),
+            recovered_items: selected
+                .iter()
+                .filter(|s| {
+                    self.scorer.calculate_relevance(&query.original, s.item.get_content())
+                        < self.config.min_relevance
+                })
+                .count(),
+        });
+
         Ok(RetrievalResult {
             selected,
             rejected,
             total_tokens,
             target_tokens,
             max_tokens: self.config.max_tokens,
+            expansion,
         })
     }
 
@@ -372,6 +561,9 @@ pub struct RetrievalResult {
 
     /// Maximum allowed tokens
     pub max_tokens: usize,
+
+    /// Effect of query expansion, when the query was expanded
+    pub expansion: Option<ExpansionEffect>,
 }
 
 impl RetrievalResult {
@@ -487,4 +679,127 @@ mod tests {
         assert!(recency > 0.99); // Very recent
         assert!(recency <= 1.0);
     }
+
+    fn aged_item(content: &str, importance: f64, hours: i64) -> MemoryItem {
+        let mut item = create_test_item(content, importance, 100);
+        item.created_at = chrono::Utc::now() - chrono::Duration::hours(hours);
+        item.last_accessed = item.created_at;
+        item
+    }
+
+    #[test]
+    fn test_recent_item_outranks_old_item_under_decay() {
+        let scorer = RelevanceScorer::new(RetrievalConfig::default());
+
+        let recent = aged_item("database connection pool exhausted", 0.6, 0);
+        let old = aged_item("database connection pool exhausted", 0.6, 72);
+
+        let query = "database connection pool";
+        assert!(scorer.calculate_score(query, &recent) > scorer.calculate_score(query, &old));
+    }
+
+    #[test]
+    fn test_decay_is_applied_once() {
+        let scorer = RelevanceScorer::new(RetrievalConfig {
+            relevance_weight: 0.0,
+            importance_weight: 0.5,
+            recency_weight: 0.5,
+            recency_decay: RecencyDecayConfig {
+                importance_offset: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        // One half-life old: importance is undecayed, recency halved
+        let item = aged_item("database connection pool exhausted", 0.8, 24);
+        let score = scorer.calculate_score("database", &item);
+        assert!((score - (0.5 * 0.8 + 0.5 * 0.5)).abs() < 1e-3, "score {}", score);
+
+        let no_decay = RelevanceScorer::new(RetrievalConfig {
+            recency_decay: RecencyDecayConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(no_decay.calculate_recency(&item), 1.0);
+    }
+
+    #[test]
+    fn test_importance_offsets_decay() {
+        let decay = RecencyDecayConfig {
+            importance_offset: 1.0,
+            ..Default::default()
+        };
+
+        let age = 7.0 * 24.0 * 3600.0;
+        assert!(decay.multiplier("conversation", age, 0.2) < 0.3);
+        assert!(decay.multiplier("conversation", age, 1.0) > 0.99);
+    }
+
+    #[test]
+    fn test_namespace_half_life() {
+        let decay = RecencyDecayConfig::default().with_namespace_half_life("incident", 3600.0);
+
+        assert_eq!(decay.half_life_for("incident"), 3600.0);
+        assert_eq!(decay.half_life_for("conversation"), 24.0 * 3600.0);
+        assert!((decay.decay("incident", 3600.0) - 0.5).abs() < 1e-9);
+        assert!(decay.decay("conversation", 3600.0) > 0.9);
+    }
+
+    #[test]
+    fn test_expanded_query_recovers_synonym_match() {
+        use crate::expansion::{QueryExpander, QueryExpansionConfig, SynonymDictionary};
+
+        let window = ContextWindow::new(RetrievalConfig::default()).unwrap();
+        let items = vec![create_test_item("Node memory pressure is high", 0.5, 10)];
+
+        let plain = window.retrieve_optimized("RAM usage", items.clone()).unwrap();
+        assert!(plain.selected.is_empty());
+        assert!(plain.expansion.is_none());
+
+        let expander = QueryExpander::new(
+            QueryExpansionConfig {
+                enabled: true,
+                ..Default::default()
+            },
+            SynonymDictionary::default(),
+        );
+        let expanded = window
+            .retrieve_expanded(&expander.expand("RAM usage", &[]), items)
+            .unwrap();
+        assert_eq!(expanded.selected.len(), 1);
+        assert_eq!(expanded.expansion.unwrap().recovered_items, 1);
+    }
+
+    #[test]
+    fn test_synonyms_match_whole_words_only() {
+        use crate::expansion::{QueryExpander, QueryExpansionConfig, SynonymDictionary};
+
+        let scorer = RelevanceScorer::new(RetrievalConfig::default());
+        let expander = QueryExpander::new(
+            QueryExpansionConfig {
+                enabled: true,
+                ..Default::default()
+            },
+            SynonymDictionary::default(),
+        );
+
+        // "compute" and "mem" occur only inside longer words
+        let query = expander.expand("cpu", &[]);
+        let unrelated = "Recomputed the member list";
+        assert_eq!(
+            scorer.calculate_expanded_relevance(&query, unrelated),
+            scorer.calculate_relevance("cpu", unrelated)
+        );
+
+        let related = "The processor is saturated";
+        assert!(
+            scorer.calculate_expanded_relevance(&query, related)
+                > scorer.calculate_relevance("cpu", related)
+        );
+        assert_eq!(count_whole_words("out of memory, again", "out of memory"), 1);
+        assert_eq!(count_whole_words("memory-bound", "mem"), 0);
+    }
 }
@@ -0,0 +1,132 @@
+//! Token counting
+//!
+//! Budgets are only as accurate as the token counts behind them. The
+//! [`Tokenizer`] trait counts tokens for the model the context is built
+//! for: [`TiktokenTokenizer`] uses the exact BPE of OpenAI models, and
+//! [`ApproximateTokenizer`] estimates a fixed number of characters per
+//! token for models without a local tokenizer, such as Claude or Llama.
+
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::{get_bpe_from_model, CoreBPE};
+use tracing::warn;
+
+use crate::{ContextError, Result};
+
+/// Counts the tokens text takes up for a model
+pub trait Tokenizer: Send + Sync {
+    /// Token count of text
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Exact token counts from a tiktoken BPE
+pub struct TiktokenTokenizer {
+    bpe: CoreBPE,
+}
+
+impl TiktokenTokenizer {
+    /// Load the BPE of a model, e.g. "gpt-4"
+    pub fn for_model(model: &str) -> Result<Self> {
+        let bpe = get_bpe_from_model(model)
+            .map_err(|e| ContextError::CoreError(format!("Failed to load tokenizer: {}", e)))?;
+        Ok(Self { bpe })
+    }
+}
+
+impl Tokenizer for TiktokenTokenizer {
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Estimated token counts of a fixed number of characters per token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApproximateTokenizer {
+    chars_per_token: usize,
+}
+
+impl ApproximateTokenizer {
+    /// Count one token per `chars_per_token` characters, rounding up
+    pub fn new(chars_per_token: usize) -> Self {
+        Self {
+            chars_per_token: chars_per_token.max(1),
+        }
+    }
+
+    pub fn chars_per_token(&self) -> usize {
+        self.chars_per_token
+    }
+}
+
+impl Default for ApproximateTokenizer {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl Tokenizer for ApproximateTokenizer {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(self.chars_per_token)
+    }
+}
+
+/// Which tokenizer the context engine counts with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenizerConfig {
+    /// The tiktoken BPE of the engine's `tokenizer_model`
+    #[default]
+    Tiktoken,
+    /// A fixed number of characters per token
+    Approximate {
+        /// Characters counted as one token
+        chars_per_token: usize,
+    },
+}
+
+impl TokenizerConfig {
+    /// Build the configured tokenizer
+    ///
+    /// A tiktoken model that cannot be loaded falls back to four characters
+    /// per token, so an unknown model degrades accuracy rather than
+    /// failing.
+    pub fn build(&self, model: &str) -> Box<dyn Tokenizer> {
+        match self {
+            TokenizerConfig::Tiktoken => match TiktokenTokenizer::for_model(model) {
+                Ok(tokenizer) => Box::new(tokenizer),
+                Err(e) => {
+                    warn!(model = %model, error = %e, "Unknown tokenizer model, approximating token counts");
+                    Box::new(ApproximateTokenizer::default())
+                }
+            },
+            TokenizerConfig::Approximate { chars_per_token } => {
+                Box::new(ApproximateTokenizer::new(*chars_per_token))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approximate_counts_are_deterministic() {
+        let tokenizer = ApproximateTokenizer::default();
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(tokenizer.count("abc"), 1);
+        assert_eq!(tokenizer.count("abcdefghi"), 3);
+        // Characters, not bytes
+        assert_eq!(tokenizer.count("ééééé"), 2);
+        assert_eq!(tokenizer.count("abcdefghi"), tokenizer.count("abcdefghi"));
+        assert_eq!(ApproximateTokenizer::new(0).chars_per_token(), 1);
+    }
+
+    #[test]
+    fn test_unknown_model_falls_back_to_approximation() {
+        let tokenizer = TokenizerConfig::Tiktoken.build("claude-3-opus");
+        assert_eq!(tokenizer.count("abcdefgh"), 2);
+
+        let tokenizer = TokenizerConfig::Tiktoken.build("gpt-4");
+        assert_eq!(tokenizer.count("hello world"), 2);
+    }
+}
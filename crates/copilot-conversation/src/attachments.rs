@@ -0,0 +1,724 @@
+//! Content-type-aware attachment processing
+//!
+//! Turns message attachments into text that can be fed into context and
+//! retrieval. Code attachments become fenced code blocks, text files are
+//! passed through, and URL attachments are fetched through a sandboxed
+//! [`UrlFetcher`] that enforces a scheme allowlist, refuses hosts that
+//! resolve to private addresses, caps the number of bytes read and gives
+//! up on slow servers.
+
+use crate::{ConversationError, Result};
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Kind of message attachment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentType {
+    /// Uploaded file contents
+    File,
+    /// Link to a remote document
+    Url,
+    /// Image data
+    Image,
+    /// Source code snippet
+    Code,
+}
+
+/// An attachment sent with a user message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageAttachment {
+    /// Kind of attachment
+    pub attachment_type: AttachmentType,
+    /// Inline content, or the URL for `Url` attachments
+    pub content: String,
+    /// Original filename
+    #[serde(default)]
+    pub filename: Option<String>,
+    /// MIME type of the content
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+impl MessageAttachment {
+    /// Create an attachment
+    pub fn new(attachment_type: AttachmentType, content: impl Into<String>) -> Self {
+        Self {
+            attachment_type,
+            content: content.into(),
+            filename: None,
+            mime_type: None,
+        }
+    }
+
+    /// Set the filename
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Set the MIME type
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
+/// Text extracted from an attachment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessedAttachment {
+    /// Kind of attachment the text came from
+    pub attachment_type: AttachmentType,
+    /// Original filename or URL, if known
+    pub source: Option<String>,
+    /// Extracted text
+    pub text: String,
+    /// Whether the content was cut off at a size limit
+    pub truncated: bool,
+}
+
+/// Configuration for attachment processing
+#[derive(Debug, Clone)]
+pub struct AttachmentConfig {
+    /// Maximum bytes read from a URL
+    pub max_fetch_bytes: usize,
+    /// Time allowed for a URL fetch, including reading the body
+    pub fetch_timeout: Duration,
+    /// URL schemes that may be fetched
+    pub allowed_schemes: Vec<String>,
+    /// Whether loopback, private and link-local hosts may be fetched
+    pub allow_private_hosts: bool,
+    /// Maximum characters of text kept per attachment
+    pub max_text_chars: usize,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_fetch_bytes: 512 * 1024,
+            fetch_timeout: Duration::from_secs(5),
+            allowed_schemes: vec!["https".to_string(), "http".to_string()],
+            allow_private_hosts: false,
+            max_text_chars: 20_000,
+        }
+    }
+}
+
+/// Body of a fetched URL
+#[derive(Debug, Clone, Default)]
+pub struct FetchedContent {
+    /// Bytes read, at most the requested maximum
+    pub body: Vec<u8>,
+    /// Content type reported by the server
+    pub content_type: Option<String>,
+    /// Whether the body was longer than the requested maximum
+    pub truncated: bool,
+}
+
+/// Resolves host names for URL attachments
+#[async_trait]
+pub trait HostResolver: Send + Sync {
+    /// Addresses `host` resolves to, each with `port`
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>>;
+}
+
+/// Resolver that uses the system's DNS configuration
+pub struct SystemResolver;
+
+#[async_trait]
+impl HostResolver for SystemResolver {
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let addrs = tokio::net::lookup_host((host, port)).await.map_err(|e| {
+            ConversationError::AttachmentError(format!("Could not resolve {}: {}", host, e))
+        })?;
+        Ok(addrs.collect())
+    }
+}
+
+/// Fetches remote documents for URL attachments
+#[async_trait]
+pub trait UrlFetcher: Send + Sync {
+    /// Fetch a URL from one of `addrs`, the checked addresses of its host,
+    /// reading at most `max_bytes` of the body
+    async fn fetch(
+        &self,
+        url: &Url,
+        addrs: &[SocketAddr],
+        max_bytes: usize,
+    ) -> Result<FetchedContent>;
+}
+
+/// HTTP fetcher that stops reading once the size cap is reached
+pub struct HttpFetcher;
+
+impl HttpFetcher {
+    /// Create a fetcher that does not follow redirects, so a redirect
+    /// cannot bypass the host checks
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl UrlFetcher for HttpFetcher {
+    async fn fetch(
+        &self,
+        url: &Url,
+        addrs: &[SocketAddr],
+        max_bytes: usize,
+    ) -> Result<FetchedContent> {
+        // Connect only to the checked addresses; resolving the name again
+        // could return a different, private, address
+        let mut builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = url.domain() {
+            builder = builder.resolve_to_addrs(domain, addrs);
+        }
+        let client = builder.build().map_err(|e| {
+            ConversationError::AttachmentError(format!("Fetch of {} failed: {}", url, e))
+        })?;
+
+        let mut response = client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ConversationError::AttachmentError(format!("Fetch of {} failed: {}", url, e)))?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        let mut content = FetchedContent {
+            content_type,
+            ..Default::default()
+        };
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ConversationError::AttachmentError(format!("Reading {} failed: {}", url, e)))?
+        {
+            let room = max_bytes - content.body.len();
+            if chunk.len() > room {
+                content.body.extend_from_slice(&chunk[..room]);
+                content.truncated = true;
+                break;
+            }
+            content.body.extend_from_slice(&chunk);
+        }
+
+        Ok(content)
+    }
+}
+
+/// Extracts text from attachments according to their type
+#[derive(Clone)]
+pub struct AttachmentProcessor {
+    config: AttachmentConfig,
+    fetcher: Arc<dyn UrlFetcher>,
+    resolver: Arc<dyn HostResolver>,
+}
+
+impl Default for AttachmentProcessor {
+    fn default() -> Self {
+        Self::new(AttachmentConfig::default())
+    }
+}
+
+impl AttachmentProcessor {
+    /// Create a processor that fetches URLs over HTTP
+    pub fn new(config: AttachmentConfig) -> Self {
+        Self {
+            config,
+            fetcher: Arc::new(HttpFetcher::new()),
+            resolver: Arc::new(SystemResolver),
+        }
+    }
+
+    /// Use a custom URL fetcher
+    pub fn with_fetcher(mut self, fetcher: Arc<dyn UrlFetcher>) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Use a custom host resolver
+    pub fn with_resolver(mut self, resolver: Arc<dyn HostResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &AttachmentConfig {
+        &self.config
+    }
+
+    /// Extract text from an attachment
+    pub async fn process(&self, attachment: &MessageAttachment) -> Result<ProcessedAttachment> {
+        debug!(
+            "Processing {:?} attachment ({} bytes)",
+            attachment.attachment_type,
+            attachment.content.len()
+        );
+
+        match attachment.attachment_type {
+            AttachmentType::Code => Ok(self.process_code(attachment)),
+            AttachmentType::File => self.process_file(attachment),
+            AttachmentType::Url => self.process_url(attachment).await,
+            AttachmentType::Image => Ok(ProcessedAttachment {
+                attachment_type: AttachmentType::Image,
+                source: attachment.filename.clone(),
+                text: format!(
+                    "[image: {}]",
+                    attachment.filename.as_deref().unwrap_or("unnamed")
+                ),
+                truncated: false,
+            }),
+        }
+    }
+
+    /// Process several attachments, skipping any that fail
+    pub async fn process_all(&self, attachments: &[MessageAttachment]) -> Vec<ProcessedAttachment> {
+        let results = futures::future::join_all(attachments.iter().map(|a| self.process(a))).await;
+        results
+            .into_iter()
+            .filter_map(|result| match result {
+                Ok(processed) => Some(processed),
+                Err(e) => {
+                    warn!("Skipping attachment: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn process_code(&self, attachment: &MessageAttachment) -> ProcessedAttachment {
+        let (code, truncated) = self.limit(&attachment.content);
+        let language = code_language(attachment).unwrap_or_default();
+
+        // Use a fence longer than any backtick run in the code
+        let longest_run = code
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+
+        ProcessedAttachment {
+            attachment_type: AttachmentType::Code,
+            source: attachment.filename.clone(),
+            text: format!("{}{}\n{}\n{}", fence, language, code.trim_end_matches('\n'), fence),
+            truncated,
+        }
+    }
+
+    fn process_file(&self, attachment: &MessageAttachment) -> Result<ProcessedAttachment> {
+        if let Some(mime) = &attachment.mime_type {
+            if !is_text_mime(mime) {
+                return Err(ConversationError::AttachmentError(format!(
+                    "Unsupported file type: {}",
+                    mime
+                )));
+            }
+        }
+
+        let (text, truncated) = self.limit(&attachment.content);
+        Ok(ProcessedAttachment {
+            attachment_type: AttachmentType::File,
+            source: attachment.filename.clone(),
+            text,
+            truncated,
+        })
+    }
+
+    async fn process_url(&self, attachment: &MessageAttachment) -> Result<ProcessedAttachment> {
+        let (url, addrs) = self.check_url(attachment.content.trim()).await?;
+
+        let fetched = tokio::time::timeout(
+            self.config.fetch_timeout,
+            self.fetcher.fetch(&url, &addrs, self.config.max_fetch_bytes),
+        )
+        .await
+        .map_err(|_| {
+            ConversationError::AttachmentError(format!(
+                "Fetch of {} timed out after {:?}",
+                url, self.config.fetch_timeout
+            ))
+        })??;
+
+        let content_type = fetched.content_type.as_deref().unwrap_or("text/plain");
+        if !is_text_mime(content_type) {
+            return Err(ConversationError::AttachmentError(format!(
+                "Unsupported content type {} at {}",
+                content_type, url
+            )));
+        }
+
+        // A cap may split a multi-byte character; decode lossily
+        let mut body = fetched.body;
+        body.truncate(self.config.max_fetch_bytes);
+        let raw = String::from_utf8_lossy(&body);
+        let text = if content_type.starts_with("text/html") {
+            strip_html(&raw)
+        } else {
+            raw.into_owned()
+        };
+        let (text, limited) = self.limit(&text);
+
+        Ok(ProcessedAttachment {
+            attachment_type: AttachmentType::Url,
+            source: Some(url.to_string()),
+            text,
+            truncated: fetched.truncated || limited,
+        })
+    }
+
+    /// Parse a URL, resolve its host and check both against the sandbox
+    /// rules, returning the addresses that may be connected to
+    async fn check_url(&self, raw: &str) -> Result<(Url, Vec<SocketAddr>)> {
+        let url = Url::parse(raw)
+            .map_err(|e| ConversationError::AttachmentError(format!("Invalid URL {}: {}", raw, e)))?;
+
+        if !self.config.allowed_schemes.iter().any(|s| s == url.scheme()) {
+            return Err(ConversationError::AttachmentError(format!(
+                "URL scheme not allowed: {}",
+                url.scheme()
+            )));
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| ConversationError::AttachmentError(format!("URL has no host: {}", raw)))?;
+
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| ConversationError::AttachmentError(format!("URL has no port: {}", raw)))?;
+
+        if !self.config.allow_private_hosts && is_local_name(host) {
+            return Err(ConversationError::AttachmentError(format!(
+                "URL host not allowed: {}",
+                host
+            )));
+        }
+
+        let addrs = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => self.resolver.resolve(host, port).await?,
+        };
+        if addrs.is_empty() {
+            return Err(ConversationError::AttachmentError(format!(
+                "URL host has no addresses: {}",
+                host
+            )));
+        }
+
+        // Every address must be public, since the connection may use any
+        if !self.config.allow_private_hosts {
+            if let Some(addr) = addrs.iter().find(|addr| is_private_ip(addr.ip())) {
+                return Err(ConversationError::AttachmentError(format!(
+                    "URL host not allowed: {} resolves to {}",
+                    host,
+                    addr.ip()
+                )));
+            }
+        }
+
+        Ok((url, addrs))
+    }
+
+    /// Cut text to the configured character limit
+    fn limit(&self, text: &str) -> (String, bool) {
+        match text.char_indices().nth(self.config.max_text_chars) {
+            Some((end, _)) => (text[..end].to_string(), true),
+            None => (text.to_string(), false),
+        }
+    }
+}
+
+/// Whether a host name always refers to the local machine
+fn is_local_name(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    host == "localhost" || host.ends_with(".localhost")
+}
+
+/// Whether an address is on the local machine or a private network
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(is_private_ipv4)
+        }
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        // "This network" (0.0.0.0/8) and carrier-grade NAT (100.64.0.0/10)
+        || first == 0
+        || (first == 100 && (second & 0xc0) == 64)
+}
+
+fn is_text_mime(mime: &str) -> bool {
+    let mime = mime.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    mime.starts_with("text/")
+        || matches!(
+            mime.as_str(),
+            "application/json" | "application/xml" | "application/yaml" | "application/x-yaml" | "application/toml"
+        )
+}
+
+/// Language tag for a code block, from the MIME type or file extension
+fn code_language(attachment: &MessageAttachment) -> Option<String> {
+    if let Some(mime) = &attachment.mime_type {
+        let subtype = mime.rsplit('/').next().unwrap_or("");
+        let subtype = subtype.trim_start_matches("x-");
+        if !subtype.is_empty() && subtype != "plain" {
+            return Some(subtype.to_string());
+        }
+    }
+
+    let extension = Path::new(attachment.filename.as_deref()?).extension()?.to_str()?;
+    let language = match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "go" => "go",
+        "sh" => "bash",
+        "yml" | "yaml" => "yaml",
+        "sql" => "sql",
+        other => other,
+    };
+    Some(language.to_string())
+}
+
+/// Crude HTML to text conversion: drops tags, scripts and styles
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let lower = tag.get(..7).unwrap_or(tag).to_ascii_lowercase();
+        let skip_to = if lower.starts_with("<script") {
+            find_ci(tag, "</script>").map(|i| i + "</script>".len())
+        } else if lower.starts_with("<style") {
+            find_ci(tag, "</style>").map(|i| i + "</style>".len())
+        } else {
+            tag.find('>').map(|i| i + 1)
+        };
+
+        match skip_to {
+            Some(end) => {
+                text.push(' ');
+                rest = &tag[end..];
+            }
+            None => {
+                rest = "";
+            }
+        }
+    }
+    text.push_str(rest);
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn find_ci(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one HTTP response with a body of `len` bytes
+    async fn serve_once(len: usize, content_type: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let header = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\n\r\n",
+                content_type, len
+            );
+            let _ = socket.write_all(header.as_bytes()).await;
+            let chunk = vec![b'a'; 8192];
+            let mut sent = 0;
+            while sent < len {
+                let n = chunk.len().min(len - sent);
+                if socket.write_all(&chunk[..n]).await.is_err() {
+                    break;
+                }
+                sent += n;
+            }
+        });
+        format!("http://{}/doc.txt", addr)
+    }
+
+    fn local_processor(config: AttachmentConfig) -> AttachmentProcessor {
+        AttachmentProcessor::new(AttachmentConfig {
+            allow_private_hosts: true,
+            ..config
+        })
+    }
+
+    #[tokio::test]
+    async fn test_code_attachment_becomes_code_block() {
+        let attachment = MessageAttachment::new(AttachmentType::Code, "fn main() {}\n")
+            .with_filename("main.rs");
+
+        let processed = AttachmentProcessor::default().process(&attachment).await.unwrap();
+        assert_eq!(processed.text, "```rust\nfn main() {}\n```");
+        assert_eq!(processed.source.as_deref(), Some("main.rs"));
+        assert!(!processed.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_code_fence_outlasts_backticks_in_code() {
+        let attachment = MessageAttachment::new(AttachmentType::Code, "let s = \"```\";")
+            .with_mime_type("text/x-python");
+
+        let processed = AttachmentProcessor::default().process(&attachment).await.unwrap();
+        assert!(processed.text.starts_with("````python\n"));
+        assert!(processed.text.ends_with("\n````"));
+    }
+
+    #[tokio::test]
+    async fn test_url_fetch_respects_size_cap() {
+        let url = serve_once(200_000, "text/plain").await;
+        let processor = local_processor(AttachmentConfig {
+            max_fetch_bytes: 1000,
+            ..Default::default()
+        });
+
+        let processed = processor
+            .process(&MessageAttachment::new(AttachmentType::Url, url))
+            .await
+            .unwrap();
+        assert_eq!(processed.text.len(), 1000);
+        assert!(processed.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_slow_fetch_times_out() {
+        // Accepts the connection but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+
+        let processor = local_processor(AttachmentConfig {
+            fetch_timeout: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let err = processor
+            .process(&MessageAttachment::new(AttachmentType::Url, url))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_url_sandbox() {
+        let processor = AttachmentProcessor::default();
+        for url in [
+            "http://127.0.0.1/",
+            "http://localhost:8080/",
+            "http://10.0.0.5/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://100.100.100.200/",
+            "http://0.0.0.0:8080/",
+            "file:///etc/passwd",
+        ] {
+            let result = processor
+                .process(&MessageAttachment::new(AttachmentType::Url, url))
+                .await;
+            assert!(result.is_err(), "{} should be refused", url);
+        }
+    }
+
+    /// Resolves every name to fixed addresses
+    struct StaticResolver(Vec<IpAddr>);
+
+    #[async_trait]
+    impl HostResolver for StaticResolver {
+        async fn resolve(&self, _host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+            Ok(self.0.iter().map(|ip| SocketAddr::new(*ip, port)).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_name_resolving_to_private_address_refused() {
+        for addrs in [
+            vec!["10.0.0.5"],
+            vec!["100.64.1.1"],
+            vec!["0.0.0.0"],
+            // One private address among public ones is enough
+            vec!["93.184.216.34", "127.0.0.1"],
+        ] {
+            let ips = addrs.iter().map(|a| a.parse().unwrap()).collect();
+            let processor =
+                AttachmentProcessor::default().with_resolver(Arc::new(StaticResolver(ips)));
+            let err = processor
+                .process(&MessageAttachment::new(AttachmentType::Url, "http://internal.example.com/"))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("not allowed"), "{:?}: {}", addrs, err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_connects_to_checked_address() {
+        // The name does not exist in DNS, so the fetch can only succeed by
+        // connecting to the address the resolver returned
+        let served = serve_once(5, "text/plain").await;
+        let port = Url::parse(&served).unwrap().port().unwrap();
+        let processor = local_processor(AttachmentConfig::default())
+            .with_resolver(Arc::new(StaticResolver(vec![IpAddr::from([127, 0, 0, 1])])));
+
+        let processed = processor
+            .process(&MessageAttachment::new(
+                AttachmentType::Url,
+                format!("http://docs.invalid:{}/doc.txt", port),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(processed.text, "aaaaa");
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let html = "<html><style>p{}</style><p>Disk <b>full</b></p><script>x()</script></html>";
+        assert_eq!(strip_html(html), "Disk full");
+    }
+}
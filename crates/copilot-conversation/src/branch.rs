@@ -0,0 +1,211 @@
+//! Branch trees of forked conversations
+//!
+//! Forking a session creates a branch that records its parent and the
+//! message it branched at. A [`BranchTree`] arranges the sessions forked
+//! from one root into a tree for display. It is built from lightweight
+//! [`BranchSummary`] records, so message content is never loaded.
+
+use crate::session::{BranchOrigin, Session};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Lightweight description of a session in a fork tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchSummary {
+    /// Session identifier
+    pub session_id: String,
+    /// Where the session branched off, `None` for the root
+    pub origin: Option<BranchOrigin>,
+    /// When the session was created
+    pub created_at: DateTime<Utc>,
+    /// Number of messages in the session
+    pub message_count: usize,
+}
+
+impl BranchSummary {
+    /// Summarize a session with the given message count
+    pub fn from_session(session: &Session, message_count: usize) -> Self {
+        Self {
+            session_id: session.id.clone(),
+            origin: session.branch_origin.clone(),
+            created_at: session.created_at,
+            message_count,
+        }
+    }
+
+    fn parent_id(&self) -> Option<&str> {
+        self.origin.as_ref().map(|o| o.parent_session_id.as_str())
+    }
+}
+
+/// A session and the branches forked from it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchNode {
+    /// Session identifier
+    pub session_id: String,
+    /// Parent message this branch was forked at
+    pub branch_point: Option<String>,
+    /// When the session was created
+    pub created_at: DateTime<Utc>,
+    /// Number of messages in the session
+    pub message_count: usize,
+    /// Branches forked from this session, oldest first
+    pub children: Vec<BranchNode>,
+}
+
+impl BranchNode {
+    /// Find a node by session ID in this subtree
+    pub fn find(&self, session_id: &str) -> Option<&BranchNode> {
+        if self.session_id == session_id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|c| c.find(session_id))
+    }
+
+    /// Number of sessions in this subtree
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(BranchNode::size).sum::<usize>()
+    }
+}
+
+/// Fork tree rooted at a session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchTree {
+    /// The root session and its reachable branches
+    pub root: BranchNode,
+    /// Branches of this tree whose parent session no longer exists
+    pub orphans: Vec<BranchNode>,
+}
+
+impl BranchTree {
+    /// Build a tree from session summaries
+    ///
+    /// Summaries other than the root are attached under their parent.
+    /// Branches whose parent is missing become orphan subtrees. Returns
+    /// `None` if the root is not among the summaries.
+    pub fn build(root_id: &str, summaries: Vec<BranchSummary>) -> Option<Self> {
+        let ids: HashSet<String> = summaries.iter().map(|s| s.session_id.clone()).collect();
+        let mut children: HashMap<String, Vec<BranchSummary>> = HashMap::new();
+        let mut root = None;
+        let mut orphans = Vec::new();
+
+        for summary in summaries {
+            if summary.session_id == root_id {
+                root = Some(summary);
+                continue;
+            }
+            match summary.parent_id() {
+                Some(parent) if ids.contains(parent) => {
+                    children.entry(parent.to_string()).or_default().push(summary);
+                }
+                _ => orphans.push(summary),
+            }
+        }
+
+        let root = Self::node(root?, &mut children);
+        let mut orphans: Vec<BranchNode> = sorted(orphans)
+            .into_iter()
+            .map(|summary| Self::node(summary, &mut children))
+            .collect();
+
+        // Anything left over is part of a parent cycle unreachable from the
+        // root; surface it rather than dropping it
+        while let Some(key) = children.keys().next().cloned() {
+            for summary in sorted(children.remove(&key).unwrap_or_default()) {
+                orphans.push(Self::node(summary, &mut children));
+            }
+        }
+
+        Some(Self { root, orphans })
+    }
+
+    /// Find a node by session ID, including orphaned branches
+    pub fn find(&self, session_id: &str) -> Option<&BranchNode> {
+        self.root
+            .find(session_id)
+            .or_else(|| self.orphans.iter().find_map(|o| o.find(session_id)))
+    }
+
+    fn node(summary: BranchSummary, children: &mut HashMap<String, Vec<BranchSummary>>) -> BranchNode {
+        let kids = sorted(children.remove(&summary.session_id).unwrap_or_default());
+        BranchNode {
+            branch_point: summary.origin.and_then(|o| o.message_id),
+            children: kids.into_iter().map(|k| Self::node(k, children)).collect(),
+            session_id: summary.session_id,
+            created_at: summary.created_at,
+            message_count: summary.message_count,
+        }
+    }
+}
+
+fn sorted(mut summaries: Vec<BranchSummary>) -> Vec<BranchSummary> {
+    summaries.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn summary(id: &str, parent: Option<&str>, minute: i64) -> BranchSummary {
+        BranchSummary {
+            session_id: id.to_string(),
+            origin: parent.map(|p| BranchOrigin {
+                parent_session_id: p.to_string(),
+                root_session_id: "root".to_string(),
+                message_id: Some(format!("{}-m", p)),
+            }),
+            created_at: DateTime::<Utc>::UNIX_EPOCH + Duration::minutes(minute),
+            message_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_children_ordered_by_creation() {
+        let tree = BranchTree::build(
+            "root",
+            vec![
+                summary("b", Some("root"), 2),
+                summary("root", None, 0),
+                summary("a", Some("root"), 1),
+                summary("a1", Some("a"), 3),
+            ],
+        )
+        .unwrap();
+
+        let ids: Vec<_> = tree.root.children.iter().map(|c| c.session_id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(tree.root.size(), 4);
+        assert_eq!(tree.find("a1").unwrap().branch_point.as_deref(), Some("a-m"));
+        assert!(tree.orphans.is_empty());
+    }
+
+    #[test]
+    fn test_branches_of_deleted_parent_are_orphans() {
+        let tree = BranchTree::build(
+            "root",
+            vec![
+                summary("root", None, 0),
+                summary("gone-child", Some("gone"), 1),
+                summary("grandchild", Some("gone-child"), 2),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(tree.root.size(), 1);
+        assert_eq!(tree.orphans.len(), 1);
+        assert_eq!(tree.orphans[0].session_id, "gone-child");
+        assert_eq!(tree.orphans[0].children[0].session_id, "grandchild");
+    }
+
+    #[test]
+    fn test_missing_root() {
+        assert!(BranchTree::build("root", vec![summary("a", Some("root"), 1)]).is_none());
+    }
+}
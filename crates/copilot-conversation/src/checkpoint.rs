@@ -0,0 +1,179 @@
+//! Conversation checkpoints
+//!
+//! A checkpoint bookmarks a session's state under a label so the
+//! conversation can later be rewound to it, like a named git tag. Each
+//! checkpoint stores a full copy of the messages and session metadata it
+//! covers, so it stays restorable after the live history has been edited
+//! or pruned. Storage is bounded per session; the oldest checkpoints are
+//! dropped first.
+
+use crate::history::ConversationMessage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+/// Default number of checkpoints kept per session
+pub const DEFAULT_MAX_CHECKPOINTS: usize = 20;
+
+/// Identifier of a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CheckpointId(String);
+
+impl CheckpointId {
+    fn new() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    /// The identifier as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for CheckpointId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for CheckpointId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+/// Snapshot of a session at a point in the conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Checkpoint identifier
+    pub id: CheckpointId,
+    /// User-supplied label
+    pub label: String,
+    /// Session the checkpoint belongs to
+    pub session_id: String,
+    /// When the checkpoint was taken
+    pub created_at: DateTime<Utc>,
+    /// Messages at the time of the checkpoint
+    pub messages: Vec<ConversationMessage>,
+    /// Session metadata at the time of the checkpoint
+    pub metadata: HashMap<String, String>,
+}
+
+impl Checkpoint {
+    /// Create a checkpoint from a session's current messages and metadata
+    pub fn new(
+        session_id: &str,
+        label: impl Into<String>,
+        messages: Vec<ConversationMessage>,
+        metadata: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            id: CheckpointId::new(),
+            label: label.into(),
+            session_id: session_id.to_string(),
+            created_at: Utc::now(),
+            messages,
+            metadata,
+        }
+    }
+
+    /// ID of the last message covered by the checkpoint
+    pub fn last_message_id(&self) -> Option<&str> {
+        self.messages.last().map(|msg| msg.id.as_str())
+    }
+}
+
+/// Bounded per-session checkpoint storage
+#[derive(Debug)]
+pub struct CheckpointStore {
+    checkpoints: HashMap<String, VecDeque<Checkpoint>>,
+    max_per_session: usize,
+}
+
+impl Default for CheckpointStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CHECKPOINTS)
+    }
+}
+
+impl CheckpointStore {
+    /// Create a store keeping at most `max_per_session` checkpoints per session
+    pub fn new(max_per_session: usize) -> Self {
+        Self {
+            checkpoints: HashMap::new(),
+            max_per_session: max_per_session.max(1),
+        }
+    }
+
+    /// Store a checkpoint, dropping the session's oldest if it is full
+    ///
+    /// Returns the dropped checkpoint, if any.
+    pub fn insert(&mut self, checkpoint: Checkpoint) -> Option<Checkpoint> {
+        let entries = self
+            .checkpoints
+            .entry(checkpoint.session_id.clone())
+            .or_default();
+        let evicted = if entries.len() >= self.max_per_session {
+            entries.pop_front()
+        } else {
+            None
+        };
+        entries.push_back(checkpoint);
+        evicted
+    }
+
+    /// Get a checkpoint of a session
+    pub fn get(&self, session_id: &str, id: &CheckpointId) -> Option<&Checkpoint> {
+        self.checkpoints
+            .get(session_id)
+            .and_then(|entries| entries.iter().find(|c| &c.id == id))
+    }
+
+    /// List a session's checkpoints, oldest first
+    pub fn list(&self, session_id: &str) -> Vec<&Checkpoint> {
+        self.checkpoints
+            .get(session_id)
+            .map(|entries| entries.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Delete a checkpoint
+    pub fn remove(&mut self, session_id: &str, id: &CheckpointId) -> Option<Checkpoint> {
+        let entries = self.checkpoints.get_mut(session_id)?;
+        let index = entries.iter().position(|c| &c.id == id)?;
+        entries.remove(index)
+    }
+
+    /// Delete all checkpoints of a session
+    pub fn clear_session(&mut self, session_id: &str) -> usize {
+        self.checkpoints
+            .remove(session_id)
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_is_bounded_per_session() {
+        let mut store = CheckpointStore::new(2);
+        let first = Checkpoint::new("s1", "a", Vec::new(), HashMap::new());
+        let first_id = first.id.clone();
+
+        assert!(store.insert(first).is_none());
+        store.insert(Checkpoint::new("s1", "b", Vec::new(), HashMap::new()));
+        store.insert(Checkpoint::new("s2", "other", Vec::new(), HashMap::new()));
+        let evicted = store.insert(Checkpoint::new("s1", "c", Vec::new(), HashMap::new()));
+
+        assert_eq!(evicted.unwrap().id, first_id);
+        assert!(store.get("s1", &first_id).is_none());
+        let labels: Vec<_> = store.list("s1").iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["b", "c"]);
+        assert_eq!(store.list("s2").len(), 1);
+    }
+}
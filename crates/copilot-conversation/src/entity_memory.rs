@@ -0,0 +1,295 @@
+//! Per-session entity memory
+//!
+//! Once a conversation has established which service, host or threshold
+//! it is about, later messages refer to it loosely: "restart the service",
+//! "is the threshold still breached?". [`EntityMemory`] records the
+//! canonical value of each entity type as it is mentioned and resolves
+//! such under-specified mentions to it, so "the service" keeps meaning the
+//! one established earlier. It complements reference resolution, which
+//! handles pronouns.
+//!
+//! A session remembers one value per entity type. Mentioning a different
+//! value of the same type replaces it, as the conversation has moved on.
+//! A value is remembered for [`EntityMemoryConfig::stale_after_turns`]
+//! turns after its last mention and then forgotten, so a service from the
+//! start of a long session is not silently reused an hour later.
+
+use copilot_nlp::{Entity, EntityType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Phrases that refer to an entity of a type without naming it
+const VAGUE_MENTIONS: &[(&str, EntityType)] = &[
+    ("the service", EntityType::Service),
+    ("the app", EntityType::Service),
+    ("the host", EntityType::Host),
+    ("the instance", EntityType::Host),
+    ("the endpoint", EntityType::Endpoint),
+    ("the namespace", EntityType::Namespace),
+    ("the cluster", EntityType::Namespace),
+    ("the pod", EntityType::Pod),
+    ("the deployment", EntityType::Deployment),
+    ("the container", EntityType::Container),
+    ("the environment", EntityType::Environment),
+    ("the metric", EntityType::Metric),
+    ("the threshold", EntityType::Threshold),
+    ("the limit", EntityType::Threshold),
+];
+
+/// Confidence of a resolution from entity memory
+const RESOLUTION_CONFIDENCE: f64 = 0.9;
+
+/// How entity memory remembers and forgets
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EntityMemoryConfig {
+    /// Turns an entity is remembered for after its last mention
+    pub stale_after_turns: usize,
+    /// Minimum extraction confidence of an entity to be remembered
+    pub min_confidence: f64,
+}
+
+impl Default for EntityMemoryConfig {
+    fn default() -> Self {
+        Self {
+            stale_after_turns: 10,
+            min_confidence: 0.6,
+        }
+    }
+}
+
+/// An entity value a session has established
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RememberedEntity {
+    /// Type of the entity
+    pub entity_type: EntityType,
+    /// Canonical value
+    pub value: String,
+    /// Number of turns that mentioned the value
+    pub mentions: usize,
+    /// Turn the value was last mentioned in, starting at 1
+    pub last_turn: usize,
+}
+
+/// An under-specified mention resolved from entity memory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityResolution {
+    /// The mention as written in the message, e.g. "the service"
+    pub phrase: String,
+    /// The remembered entity it refers to
+    pub entity: RememberedEntity,
+}
+
+impl EntityResolution {
+    /// The resolution as an extracted entity
+    pub fn to_entity(&self) -> Entity {
+        Entity::new(
+            self.entity.entity_type.clone(),
+            self.entity.value.clone(),
+            self.entity.value.clone(),
+            self.phrase.clone(),
+            RESOLUTION_CONFIDENCE,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct SessionEntities {
+    turn: usize,
+    entities: HashMap<EntityType, RememberedEntity>,
+}
+
+/// Entity values established by each session
+#[derive(Debug, Default)]
+pub struct EntityMemory {
+    config: EntityMemoryConfig,
+    sessions: HashMap<String, SessionEntities>,
+}
+
+impl EntityMemory {
+    /// Create an empty entity memory
+    pub fn new(config: EntityMemoryConfig) -> Self {
+        Self {
+            config,
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Record the entities mentioned in a session's turn
+    ///
+    /// Starts a new turn, remembers the mentioned values and forgets stale
+    /// ones. Returns the values that were replaced by a different value of
+    /// the same type.
+    pub fn record(&mut self, session_id: &str, mentioned: &[Entity]) -> Vec<RememberedEntity> {
+        let session = self.sessions.entry(session_id.to_string()).or_default();
+        session.turn += 1;
+        let turn = session.turn;
+
+        let mut replaced = Vec::new();
+        for entity in mentioned {
+            if entity.confidence < self.config.min_confidence {
+                continue;
+            }
+            let value = &entity.normalized_value;
+            match session.entities.get_mut(&entity.entity_type) {
+                Some(remembered) if remembered.value == *value => {
+                    // Several mentions in one turn count once
+                    if remembered.last_turn != turn {
+                        remembered.mentions += 1;
+                        remembered.last_turn = turn;
+                    }
+                }
+                existing => {
+                    let new = RememberedEntity {
+                        entity_type: entity.entity_type.clone(),
+                        value: value.clone(),
+                        mentions: 1,
+                        last_turn: turn,
+                    };
+                    if let Some(previous) = existing {
+                        // Within one turn the first mention wins
+                        if previous.last_turn == turn {
+                            continue;
+                        }
+                        replaced.push(std::mem::replace(previous, new));
+                    } else {
+                        session.entities.insert(entity.entity_type.clone(), new);
+                    }
+                }
+            }
+        }
+
+        let stale_after = self.config.stale_after_turns;
+        session
+            .entities
+            .retain(|_, remembered| turn - remembered.last_turn < stale_after);
+        replaced
+    }
+
+    /// The value a session has established for an entity type
+    pub fn get(&self, session_id: &str, entity_type: &EntityType) -> Option<&RememberedEntity> {
+        self.sessions.get(session_id)?.entities.get(entity_type)
+    }
+
+    /// All values a session has established, most recently mentioned first
+    pub fn entities(&self, session_id: &str) -> Vec<RememberedEntity> {
+        let mut entities: Vec<_> = self
+            .sessions
+            .get(session_id)
+            .map(|session| session.entities.values().cloned().collect())
+            .unwrap_or_default();
+        entities.sort_by(|a, b| b.last_turn.cmp(&a.last_turn).then_with(|| a.value.cmp(&b.value)));
+        entities
+    }
+
+    /// Resolve under-specified mentions in a message
+    ///
+    /// A mention such as "the service" resolves to the session's
+    /// established service, unless the message also names a service
+    /// itself (`mentioned`).
+    pub fn resolve(&self, session_id: &str, message: &str, mentioned: &[Entity]) -> Vec<EntityResolution> {
+        let Some(session) = self.sessions.get(session_id) else {
+            return Vec::new();
+        };
+        let lower = message.to_lowercase();
+
+        let mut resolutions: Vec<EntityResolution> = Vec::new();
+        for (phrase, entity_type) in VAGUE_MENTIONS {
+            if mentioned.iter().any(|e| e.entity_type == *entity_type)
+                || resolutions.iter().any(|r| r.entity.entity_type == *entity_type)
+            {
+                continue;
+            }
+            let (Some(start), Some(remembered)) =
+                (find_phrase(&lower, phrase), session.entities.get(entity_type))
+            else {
+                continue;
+            };
+            // Lowercasing can shift byte offsets; fall back to the phrase
+            let written = message
+                .get(start..start + phrase.len())
+                .filter(|written| written.eq_ignore_ascii_case(phrase))
+                .unwrap_or(phrase);
+            resolutions.push(EntityResolution {
+                phrase: written.to_string(),
+                entity: remembered.clone(),
+            });
+        }
+        resolutions
+    }
+
+    /// Forget everything a session has established
+    pub fn forget(&mut self, session_id: &str) {
+        self.sessions.remove(session_id);
+    }
+}
+
+/// Byte offset of `phrase` in `text` as whole words
+fn find_phrase(text: &str, phrase: &str) -> Option<usize> {
+    find_phrases(text, phrase).next()
+}
+
+/// Byte offsets of every whole-word occurrence of `phrase` in `text`
+pub(crate) fn find_phrases<'a>(text: &'a str, phrase: &'a str) -> impl Iterator<Item = usize> + 'a {
+    let is_word = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    text.match_indices(phrase).map(|(start, _)| start).filter(move |&start| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str) -> Entity {
+        Entity::new(EntityType::Service, name.to_string(), name.to_string(), name.to_string(), 0.9)
+    }
+
+    #[test]
+    fn test_new_mention_replaces_established_value() {
+        let mut memory = EntityMemory::default();
+        assert!(memory.record("s1", &[service("checkout-service")]).is_empty());
+        memory.record("s1", &[service("checkout-service")]);
+        assert_eq!(memory.get("s1", &EntityType::Service).unwrap().mentions, 2);
+
+        let replaced = memory.record("s1", &[service("payment-service")]);
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].value, "checkout-service");
+        assert_eq!(memory.get("s1", &EntityType::Service).unwrap().value, "payment-service");
+
+        // Sessions are independent
+        assert!(memory.get("s2", &EntityType::Service).is_none());
+    }
+
+    #[test]
+    fn test_stale_entities_are_forgotten() {
+        let mut memory = EntityMemory::new(EntityMemoryConfig {
+            stale_after_turns: 2,
+            ..Default::default()
+        });
+        memory.record("s1", &[service("checkout-service")]);
+        memory.record("s1", &[]);
+        assert!(memory.get("s1", &EntityType::Service).is_some());
+        memory.record("s1", &[]);
+        assert!(memory.get("s1", &EntityType::Service).is_none());
+    }
+
+    #[test]
+    fn test_resolves_vague_mentions_as_whole_words() {
+        let mut memory = EntityMemory::default();
+        memory.record("s1", &[service("checkout-service")]);
+
+        let resolved = memory.resolve("s1", "Restart The Service now", &[]);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].phrase, "The Service");
+        assert_eq!(resolved[0].entity.value, "checkout-service");
+
+        assert!(memory.resolve("s1", "list the services", &[]).is_empty());
+        assert!(memory.resolve("s1", "restart the service-mesh", &[]).is_empty());
+        // An explicitly named service takes precedence
+        assert!(memory
+            .resolve("s1", "restart the service auth-service", &[service("auth-service")])
+            .is_empty());
+    }
+}
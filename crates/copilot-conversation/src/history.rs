@@ -1,10 +1,11 @@
 //! Conversation history management with search and export capabilities
 
-use crate::{Result, ConversationError};
+use crate::{prompt::ContextSource, Result, ConversationError};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tracing::{debug, info};
+use uuid::Uuid;
 
 /// Message role in a conversation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -20,6 +21,9 @@ pub enum MessageRole {
 /// A single message in conversation history
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationMessage {
+    /// Unique message identifier
+    #[serde(default = "new_message_id")]
+    pub id: String,
     /// Role of the message sender
     pub role: MessageRole,
     /// Message content
@@ -31,6 +35,74 @@ pub struct ConversationMessage {
     /// Optional metadata
     #[serde(default)]
     pub metadata: HashMap<String, String>,
+    /// Pinned messages are kept through pruning and always included in
+    /// the prompt
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Generate a new message identifier
+pub fn new_message_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Kind of feedback an annotation gives on a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnnotationKind {
+    /// The message was helpful
+    ThumbsUp,
+    /// The message was not helpful
+    ThumbsDown,
+    /// The message needs review, e.g. for dataset curation
+    Flag { reason: String },
+    /// A free-form note
+    Note { text: String },
+}
+
+/// Feedback on a message, kept apart from the message itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// What the annotation says
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+    /// Who annotated the message
+    pub author: String,
+    /// When the message was annotated
+    pub created_at: DateTime<Utc>,
+}
+
+impl Annotation {
+    /// Create an annotation by `author`
+    pub fn new(kind: AnnotationKind, author: impl Into<String>) -> Self {
+        Self {
+            kind,
+            author: author.into(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl std::fmt::Display for Annotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            AnnotationKind::ThumbsUp => write!(f, "thumbs up by {}", self.author),
+            AnnotationKind::ThumbsDown => write!(f, "thumbs down by {}", self.author),
+            AnnotationKind::Flag { reason } => write!(f, "flagged by {}: {}", self.author, reason),
+            AnnotationKind::Note { text } => write!(f, "note by {}: {}", self.author, text),
+        }
+    }
+}
+
+/// A message with its annotations, as exported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotatedMessage {
+    /// The message, unchanged
+    #[serde(flatten)]
+    pub message: ConversationMessage,
+    /// Its annotations, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
 }
 
 /// Search query for conversation history
@@ -46,6 +118,9 @@ pub struct SearchQuery {
     pub end_date: Option<DateTime<Utc>>,
     /// Maximum number of results
     pub limit: Option<usize>,
+    /// Rank pinned messages above unpinned ones of similar relevance
+    #[serde(default)]
+    pub boost_pinned: bool,
 }
 
 /// Result of a history search
@@ -59,6 +134,20 @@ pub struct SearchResult {
     pub snippets: Vec<String>,
 }
 
+/// Number of messages on each side of a hit returned as its context
+pub const SEARCH_CONTEXT_MESSAGES: usize = 1;
+
+/// A search hit from a multi-session search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchHit {
+    /// Session the message belongs to
+    pub session_id: String,
+    /// The matching message and its score
+    pub result: SearchResult,
+    /// Messages immediately before and after the hit, in order
+    pub context: Vec<ConversationMessage>,
+}
+
 /// Export format for conversation history
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExportFormat {
@@ -72,6 +161,21 @@ pub enum ExportFormat {
     Csv,
 }
 
+/// Options for exporting conversation history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Show when each message was sent in Markdown and text exports
+    pub include_timestamps: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            include_timestamps: true,
+        }
+    }
+}
+
 /// Manages conversation history for sessions
 pub struct HistoryManager {
     /// History storage: session_id -> messages
@@ -80,8 +184,17 @@ pub struct HistoryManager {
     max_messages_per_session: usize,
     /// Whether to enable search indexing
     enable_search_index: bool,
+    /// Maximum total tokens of pinned messages per session
+    max_pinned_tokens: usize,
+    /// Annotations: session_id -> message_id -> annotations, oldest first
+    annotations: HashMap<String, HashMap<String, Vec<Annotation>>>,
+    /// Context responses were generated from: session_id -> message_id -> sources
+    sources: HashMap<String, HashMap<String, Vec<ContextSource>>>,
 }
 
+/// Default cap on the tokens of pinned messages per session
+pub const DEFAULT_MAX_PINNED_TOKENS: usize = 2048;
+
 impl HistoryManager {
     /// Create a new history manager
     pub fn new() -> Self {
@@ -89,6 +202,9 @@ impl HistoryManager {
             history: HashMap::new(),
             max_messages_per_session: 1000,
             enable_search_index: true,
+            max_pinned_tokens: DEFAULT_MAX_PINNED_TOKENS,
+            annotations: HashMap::new(),
+            sources: HashMap::new(),
         }
     }
 
@@ -98,9 +214,21 @@ impl HistoryManager {
             history: HashMap::new(),
             max_messages_per_session: max_messages,
             enable_search_index: enable_search,
+            max_pinned_tokens: DEFAULT_MAX_PINNED_TOKENS,
+            annotations: HashMap::new(),
+            sources: HashMap::new(),
         }
     }
 
+    /// Set the cap on the total tokens of pinned messages per session
+    ///
+    /// Pinned messages are always part of the prompt, so the cap keeps
+    /// them from crowding out the rest of the context window.
+    pub fn with_pinned_token_budget(mut self, max_tokens: usize) -> Self {
+        self.max_pinned_tokens = max_tokens;
+        self
+    }
+
     /// Append a message to conversation history
     ///
     /// # Arguments
@@ -123,9 +251,17 @@ impl HistoryManager {
 
         // Enforce max messages limit
         if messages.len() >= self.max_messages_per_session {
-            // Remove oldest message
-            messages.remove(0);
-            debug!("Removed oldest message due to limit");
+            // Remove oldest unpinned message
+            if let Some(oldest) = messages.iter().position(|msg| !msg.pinned) {
+                let removed = messages.remove(oldest);
+                if let Some(annotations) = self.annotations.get_mut(session_id) {
+                    annotations.remove(&removed.id);
+                }
+                if let Some(sources) = self.sources.get_mut(session_id) {
+                    sources.remove(&removed.id);
+                }
+                debug!("Removed oldest message due to limit");
+            }
         }
 
         messages.push(message);
@@ -178,6 +314,258 @@ impl HistoryManager {
         self.history.get(session_id).map(|msgs| msgs.len()).unwrap_or(0)
     }
 
+    /// Get the ID of the most recent message in a session
+    pub fn last_message_id(&self, session_id: &str) -> Option<String> {
+        self.history
+            .get(session_id)
+            .and_then(|msgs| msgs.last())
+            .map(|msg| msg.id.clone())
+    }
+
+    /// Pin a message so it survives pruning and is always in the prompt
+    ///
+    /// Fails if the message does not exist or pinning it would exceed the
+    /// session's pinned token budget. Pinning a pinned message is a no-op.
+    pub fn pin(&mut self, session_id: &str, message_id: &str) -> Result<()> {
+        let max_pinned_tokens = self.max_pinned_tokens;
+        let messages = self.history.get_mut(session_id).map(Vec::as_mut_slice).unwrap_or_default();
+        let pinned_tokens: usize = messages
+            .iter()
+            .filter(|msg| msg.pinned)
+            .map(|msg| msg.token_count)
+            .sum();
+
+        let message = messages
+            .iter_mut()
+            .find(|msg| msg.id == message_id)
+            .ok_or_else(|| {
+                ConversationError::HistoryError(format!(
+                    "Message {} not found in session {}",
+                    message_id, session_id
+                ))
+            })?;
+        if message.pinned {
+            return Ok(());
+        }
+
+        if pinned_tokens + message.token_count > max_pinned_tokens {
+            return Err(ConversationError::HistoryError(format!(
+                "Pinning message {} would use {} of {} pinned tokens",
+                message_id,
+                pinned_tokens + message.token_count,
+                max_pinned_tokens
+            )));
+        }
+
+        message.pinned = true;
+        debug!("Pinned message {} in session {}", message_id, session_id);
+        Ok(())
+    }
+
+    /// Unpin a message
+    ///
+    /// Returns whether the message was pinned.
+    pub fn unpin(&mut self, session_id: &str, message_id: &str) -> Result<bool> {
+        let message = self
+            .history
+            .get_mut(session_id)
+            .and_then(|msgs| msgs.iter_mut().find(|msg| msg.id == message_id))
+            .ok_or_else(|| {
+                ConversationError::HistoryError(format!(
+                    "Message {} not found in session {}",
+                    message_id, session_id
+                ))
+            })?;
+
+        let was_pinned = std::mem::replace(&mut message.pinned, false);
+        Ok(was_pinned)
+    }
+
+    /// Get a session's pinned messages in conversation order
+    pub fn pinned_messages(&self, session_id: &str) -> Vec<ConversationMessage> {
+        self.history
+            .get(session_id)
+            .map(|msgs| msgs.iter().filter(|msg| msg.pinned).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Annotate a message
+    ///
+    /// Annotations are stored alongside the message and never change it;
+    /// a message may have any number of them.
+    pub fn annotate(&mut self, session_id: &str, message_id: &str, annotation: Annotation) -> Result<()> {
+        let known = self
+            .history
+            .get(session_id)
+            .is_some_and(|msgs| msgs.iter().any(|msg| msg.id == message_id));
+        if !known {
+            return Err(ConversationError::HistoryError(format!(
+                "Message {} not found in session {}",
+                message_id, session_id
+            )));
+        }
+
+        debug!("Annotated message {} in session {}: {}", message_id, session_id, annotation);
+        self.annotations
+            .entry(session_id.to_string())
+            .or_default()
+            .entry(message_id.to_string())
+            .or_default()
+            .push(annotation);
+        Ok(())
+    }
+
+    /// Get a message's annotations, oldest first
+    pub fn annotations(&self, session_id: &str, message_id: &str) -> Vec<Annotation> {
+        self.annotations
+            .get(session_id)
+            .and_then(|annotations| annotations.get(message_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Get all messages for a session with their annotations
+    pub fn annotated_messages(&self, session_id: &str) -> Vec<AnnotatedMessage> {
+        let annotations = self.annotations.get(session_id);
+        self.history
+            .get(session_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|msg| AnnotatedMessage {
+                message: msg.clone(),
+                annotations: annotations
+                    .and_then(|a| a.get(&msg.id))
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Record the context a response was generated from
+    ///
+    /// Kept alongside the message, like annotations, and counted in the
+    /// session's [`HistoryStatistics`].
+    pub fn record_sources(
+        &mut self,
+        session_id: &str,
+        message_id: &str,
+        sources: Vec<ContextSource>,
+    ) -> Result<()> {
+        let known = self
+            .history
+            .get(session_id)
+            .is_some_and(|msgs| msgs.iter().any(|msg| msg.id == message_id));
+        if !known {
+            return Err(ConversationError::HistoryError(format!(
+                "Message {} not found in session {}",
+                message_id, session_id
+            )));
+        }
+
+        self.sources
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(message_id.to_string(), sources);
+        Ok(())
+    }
+
+    /// Get the context a response was generated from
+    pub fn sources(&self, session_id: &str, message_id: &str) -> Vec<ContextSource> {
+        self.sources
+            .get(session_id)
+            .and_then(|sources| sources.get(message_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Drop the annotations and sources of messages no longer in a
+    /// session's history
+    fn prune_annotations(&mut self, session_id: &str) {
+        let messages = self.history.get(session_id).map(Vec::as_slice).unwrap_or_default();
+        let is_known = |id: &String| messages.iter().any(|msg| msg.id == *id);
+        if let Some(annotations) = self.annotations.get_mut(session_id) {
+            annotations.retain(|id, _| is_known(id));
+            if annotations.is_empty() {
+                self.annotations.remove(session_id);
+            }
+        }
+        if let Some(sources) = self.sources.get_mut(session_id) {
+            sources.retain(|id, _| is_known(id));
+            if sources.is_empty() {
+                self.sources.remove(session_id);
+            }
+        }
+    }
+
+    /// Copy a session's history into a new branch
+    ///
+    /// Copies every message up to and including `through_message_id` from
+    /// `source_id` to `target_id`, replacing any existing history of the
+    /// target. Copied messages keep their IDs so the branch point can be
+    /// located in both sessions.
+    ///
+    /// Returns the number of messages copied.
+    pub fn fork_history(
+        &mut self,
+        source_id: &str,
+        target_id: &str,
+        through_message_id: &str,
+    ) -> Result<usize> {
+        let source = self.history.get(source_id).map(Vec::as_slice).unwrap_or_default();
+        let end = source
+            .iter()
+            .position(|msg| msg.id == through_message_id)
+            .ok_or_else(|| {
+                ConversationError::HistoryError(format!(
+                    "Message {} not found in session {}",
+                    through_message_id, source_id
+                ))
+            })?;
+
+        let branch = source[..=end].to_vec();
+        let count = branch.len();
+        self.history.insert(target_id.to_string(), branch);
+        debug!(
+            "Forked {} messages from session {} into {}",
+            count, source_id, target_id
+        );
+        Ok(count)
+    }
+
+    /// Replace a session's history with the given messages
+    ///
+    /// Used to rewind a session to a checkpoint. Returns the number of
+    /// messages that were replaced.
+    pub fn replace_history(&mut self, session_id: &str, messages: Vec<ConversationMessage>) -> usize {
+        let replaced = self
+            .history
+            .insert(session_id.to_string(), messages)
+            .map(|old| old.len())
+            .unwrap_or(0);
+        self.prune_annotations(session_id);
+        debug!("Replaced {} messages in session {}", replaced, session_id);
+        replaced
+    }
+
+    /// Recount the tokens of a session's messages
+    ///
+    /// Returns the token totals of the session's messages before and
+    /// after recounting.
+    pub fn recount_tokens(&mut self, session_id: &str, count: impl Fn(&str) -> usize) -> (usize, usize) {
+        let Some(messages) = self.history.get_mut(session_id) else {
+            return (0, 0);
+        };
+        let mut before = 0;
+        let mut after = 0;
+        for message in messages.iter_mut() {
+            before += message.token_count;
+            message.token_count = count(&message.content);
+            after += message.token_count;
+        }
+        (before, after)
+    }
+
     /// Search conversation history
     ///
     /// # Arguments
@@ -191,10 +579,84 @@ impl HistoryManager {
     ) -> Result<Vec<SearchResult>> {
         info!("Searching history for session {}: {}", session_id, query.query);
 
-        let messages = self.history.get(session_id).cloned().unwrap_or_default();
-        let mut results = Vec::new();
+        let messages = self.history.get(session_id).map(Vec::as_slice).unwrap_or_default();
+        let mut matches = self.match_messages(messages, &query);
+
+        // Sort by relevance score
+        matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        // Apply limit
+        if let Some(limit) = query.limit {
+            matches.truncate(limit);
+        }
+
+        let results: Vec<_> = matches
+            .into_iter()
+            .map(|(idx, score)| self.search_result(&messages[idx], score, &query))
+            .collect();
+
+        debug!("Found {} matching messages", results.len());
+
+        Ok(results)
+    }
+
+    /// Search the history of several sessions at once
+    ///
+    /// Hits are ranked together by relevance across all sessions, and the
+    /// query limit applies to the combined results. Only the returned hits
+    /// and their surrounding messages are copied.
+    pub fn search_sessions<'a>(
+        &self,
+        session_ids: impl IntoIterator<Item = &'a str>,
+        query: &SearchQuery,
+    ) -> Vec<SessionSearchHit> {
+        let mut matches: Vec<(&str, &[ConversationMessage], usize, f64)> = Vec::new();
+        for session_id in session_ids {
+            let Some(messages) = self.history.get(session_id) else {
+                continue;
+            };
+            matches.extend(
+                self.match_messages(messages, query)
+                    .into_iter()
+                    .map(|(idx, score)| (session_id, messages.as_slice(), idx, score)),
+            );
+        }
 
-        for message in messages {
+        // Rank globally, newest first among equal scores
+        matches.sort_by(|a, b| {
+            b.3.partial_cmp(&a.3)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.1[b.2].timestamp.cmp(&a.1[a.2].timestamp))
+        });
+        if let Some(limit) = query.limit {
+            matches.truncate(limit);
+        }
+
+        matches
+            .into_iter()
+            .map(|(session_id, messages, idx, score)| {
+                let start = idx.saturating_sub(SEARCH_CONTEXT_MESSAGES);
+                let end = (idx + SEARCH_CONTEXT_MESSAGES + 1).min(messages.len());
+                SessionSearchHit {
+                    session_id: session_id.to_string(),
+                    result: self.search_result(&messages[idx], score, query),
+                    context: messages[start..end]
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| start + i != idx)
+                        .map(|(_, msg)| msg.clone())
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Indices and scores of the messages matching a query
+    fn match_messages(&self, messages: &[ConversationMessage], query: &SearchQuery) -> Vec<(usize, f64)> {
+        let query_lower = query.query.to_lowercase();
+        let mut matches = Vec::new();
+
+        for (idx, message) in messages.iter().enumerate() {
             // Apply role filter
             if let Some(role) = query.role {
                 if message.role != role {
@@ -216,39 +678,34 @@ impl HistoryManager {
             }
 
             // Simple text search (in production, use proper search engine)
-            let query_lower = query.query.to_lowercase();
-            let content_lower = message.content.to_lowercase();
-
-            if content_lower.contains(&query_lower) {
+            if message.content.to_lowercase().contains(&query_lower) {
                 // Calculate simple relevance score
-                let score = self.calculate_relevance(&message.content, &query.query);
-
-                // Extract snippets
-                let snippets = self.extract_snippets(&message.content, &query.query, 3);
-
-                results.push(SearchResult {
-                    message,
-                    score,
-                    snippets,
-                });
+                let mut score = self.calculate_relevance(&message.content, &query.query);
+                if query.boost_pinned && message.pinned {
+                    // Move halfway to a perfect score, keeping pinned
+                    // results ordered by relevance among themselves
+                    score += (1.0 - score) * 0.5;
+                }
+                matches.push((idx, score));
             }
         }
 
-        // Sort by relevance score
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        matches
+    }
 
-        // Apply limit
-        if let Some(limit) = query.limit {
-            results.truncate(limit);
+    fn search_result(&self, message: &ConversationMessage, score: f64, query: &SearchQuery) -> SearchResult {
+        SearchResult {
+            message: message.clone(),
+            score,
+            snippets: self.extract_snippets(&message.content, &query.query, 3),
         }
-
-        debug!("Found {} matching messages", results.len());
-
-        Ok(results)
     }
 
     /// Export conversation history
     ///
+    /// JSON, Markdown and text exports include each message's
+    /// annotations; CSV keeps one row of fixed columns per message.
+    ///
     /// # Arguments
     ///
     /// * `session_id` - The session identifier
@@ -257,15 +714,30 @@ impl HistoryManager {
         &self,
         session_id: &str,
         format: ExportFormat,
+    ) -> Result<String> {
+        self.export_history_with(session_id, format, ExportOptions::default()).await
+    }
+
+    /// Export conversation history with options
+    ///
+    /// Markdown gives each message a header naming its role and keeps its
+    /// content verbatim, closing a code block the message leaves open so
+    /// it does not swallow the messages after it. Text gives each message
+    /// a `Role: content` line. JSON and CSV always carry timestamps.
+    pub async fn export_history_with(
+        &self,
+        session_id: &str,
+        format: ExportFormat,
+        options: ExportOptions,
     ) -> Result<String> {
         info!("Exporting history for session {} as {:?}", session_id, format);
 
-        let messages = self.history.get(session_id).cloned().unwrap_or_default();
+        let messages = self.annotated_messages(session_id);
 
         let output = match format {
             ExportFormat::Json => self.export_as_json(&messages)?,
-            ExportFormat::Markdown => self.export_as_markdown(&messages),
-            ExportFormat::Text => self.export_as_text(&messages),
+            ExportFormat::Markdown => self.export_as_markdown(&messages, options),
+            ExportFormat::Text => self.export_as_text(&messages, options),
             ExportFormat::Csv => self.export_as_csv(&messages),
         };
 
@@ -276,11 +748,15 @@ impl HistoryManager {
     pub fn clear_history(&mut self, session_id: &str) -> usize {
         let count = self.message_count(session_id);
         self.history.remove(session_id);
+        self.annotations.remove(session_id);
+        self.sources.remove(session_id);
         info!("Cleared {} messages for session {}", count, session_id);
         count
     }
 
     /// Delete old messages before a certain date
+    ///
+    /// Pinned messages are kept.
     pub async fn delete_before(
         &mut self,
         session_id: &str,
@@ -290,8 +766,9 @@ impl HistoryManager {
 
         if let Some(msgs) = messages {
             let before_count = msgs.len();
-            msgs.retain(|msg| msg.timestamp >= before);
+            msgs.retain(|msg| msg.pinned || msg.timestamp >= before);
             let deleted = before_count - msgs.len();
+            self.prune_annotations(session_id);
             info!("Deleted {} messages before {} for session {}", deleted, before, session_id);
             Ok(deleted)
         } else {
@@ -310,10 +787,40 @@ impl HistoryManager {
             system_messages: 0,
             total_tokens: 0,
             average_message_length: 0.0,
+            thumbs_up: 0,
+            thumbs_down: 0,
+            flagged_messages: 0,
+            satisfaction_rate: None,
+            context_sources: BTreeMap::new(),
+            compressed_context_sources: 0,
         };
 
         let mut total_length = 0;
 
+        for annotations in self.annotations.get(session_id).into_iter().flat_map(HashMap::values) {
+            let mut flagged = false;
+            for annotation in annotations {
+                match annotation.kind {
+                    AnnotationKind::ThumbsUp => stats.thumbs_up += 1,
+                    AnnotationKind::ThumbsDown => stats.thumbs_down += 1,
+                    AnnotationKind::Flag { .. } => flagged = true,
+                    AnnotationKind::Note { .. } => {}
+                }
+            }
+            stats.flagged_messages += usize::from(flagged);
+        }
+        for source in self.sources.get(session_id).into_iter().flat_map(HashMap::values).flatten() {
+            *stats
+                .context_sources
+                .entry(source.provenance.kind().to_string())
+                .or_default() += 1;
+            stats.compressed_context_sources += usize::from(source.compressed);
+        }
+        let ratings = stats.thumbs_up + stats.thumbs_down;
+        if ratings > 0 {
+            stats.satisfaction_rate = Some(stats.thumbs_up as f64 / ratings as f64);
+        }
+
         for msg in &messages {
             match msg.role {
                 MessageRole::User => stats.user_messages += 1,
@@ -373,60 +880,85 @@ impl HistoryManager {
         snippets
     }
 
-    fn export_as_json(&self, messages: &[ConversationMessage]) -> Result<String> {
+    fn export_as_json(&self, messages: &[AnnotatedMessage]) -> Result<String> {
         serde_json::to_string_pretty(messages)
             .map_err(|e| ConversationError::SerializationError(e))
     }
 
-    fn export_as_markdown(&self, messages: &[ConversationMessage]) -> String {
+    fn export_as_markdown(&self, messages: &[AnnotatedMessage], options: ExportOptions) -> String {
         let mut output = String::from("# Conversation History\n\n");
 
-        for msg in messages {
+        for AnnotatedMessage { message: msg, annotations } in messages {
             let role = match msg.role {
                 MessageRole::User => "User",
                 MessageRole::Assistant => "Assistant",
                 MessageRole::System => "System",
             };
 
-            output.push_str(&format!(
-                "## {} - {}\n\n{}\n\n---\n\n",
-                role,
-                msg.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
-                msg.content
-            ));
+            output.push_str(&format!("## {}", role));
+            if options.include_timestamps {
+                output.push_str(&format!(" - {}", msg.timestamp.format("%Y-%m-%d %H:%M:%S UTC")));
+            }
+            if msg.pinned {
+                output.push_str(" (pinned)");
+            }
+            output.push_str(&format!("\n\n{}\n", msg.content));
+            // An unbalanced fence would turn the rest of the export into code
+            let fences = msg
+                .content
+                .lines()
+                .filter(|line| line.trim_start().starts_with("```"))
+                .count();
+            if fences % 2 == 1 {
+                output.push_str("```\n");
+            }
+            output.push('\n');
+            for annotation in annotations {
+                output.push_str(&format!("> {}\n", annotation));
+            }
+            if !annotations.is_empty() {
+                output.push('\n');
+            }
+            output.push_str("---\n\n");
         }
 
         output
     }
 
-    fn export_as_text(&self, messages: &[ConversationMessage]) -> String {
+    fn export_as_text(&self, messages: &[AnnotatedMessage], options: ExportOptions) -> String {
         let mut output = String::from("Conversation History\n");
         output.push_str(&"=".repeat(50));
         output.push_str("\n\n");
 
-        for msg in messages {
+        for AnnotatedMessage { message: msg, annotations } in messages {
             let role = match msg.role {
-                MessageRole::User => "USER",
-                MessageRole::Assistant => "ASSISTANT",
-                MessageRole::System => "SYSTEM",
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+                MessageRole::System => "System",
             };
 
+            if options.include_timestamps {
+                output.push_str(&format!("[{}] ", msg.timestamp.format("%Y-%m-%d %H:%M:%S")));
+            }
             output.push_str(&format!(
-                "[{}] {} ({})\n{}\n\n",
-                msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                "{}{}: {}\n",
                 role,
-                msg.token_count,
+                if msg.pinned { " (pinned)" } else { "" },
                 msg.content
             ));
+            for annotation in annotations {
+                output.push_str(&format!("  * {}\n", annotation));
+            }
+            output.push('\n');
         }
 
         output
     }
 
-    fn export_as_csv(&self, messages: &[ConversationMessage]) -> String {
-        let mut output = String::from("timestamp,role,content,token_count\n");
+    fn export_as_csv(&self, messages: &[AnnotatedMessage]) -> String {
+        let mut output = String::from("timestamp,role,content,token_count,pinned\n");
 
-        for msg in messages {
+        for AnnotatedMessage { message: msg, .. } in messages {
             let role = match msg.role {
                 MessageRole::User => "user",
                 MessageRole::Assistant => "assistant",
@@ -437,11 +969,12 @@ impl HistoryManager {
             let content = msg.content.replace('"', "\"\"");
 
             output.push_str(&format!(
-                "{},{},\"{}\",{}\n",
+                "{},{},\"{}\",{},{}\n",
                 msg.timestamp.to_rfc3339(),
                 role,
                 content,
-                msg.token_count
+                msg.token_count,
+                msg.pinned
             ));
         }
 
@@ -464,6 +997,26 @@ pub struct HistoryStatistics {
     pub system_messages: usize,
     pub total_tokens: usize,
     pub average_message_length: f64,
+    /// Thumbs-up annotations
+    #[serde(default)]
+    pub thumbs_up: usize,
+    /// Thumbs-down annotations
+    #[serde(default)]
+    pub thumbs_down: usize,
+    /// Messages flagged at least once
+    #[serde(default)]
+    pub flagged_messages: usize,
+    /// Share of thumbs-up among thumbs-up and thumbs-down annotations,
+    /// if there are any
+    #[serde(default)]
+    pub satisfaction_rate: Option<f64>,
+    /// Pieces of context responses were generated from, by kind of source
+    /// (`message`, `document`, `summary` or `other`)
+    #[serde(default)]
+    pub context_sources: BTreeMap<String, usize>,
+    /// Pieces of context included in compressed form
+    #[serde(default)]
+    pub compressed_context_sources: usize,
 }
 
 #[cfg(test)]
@@ -476,11 +1029,13 @@ mod tests {
         let session_id = "test-session";
 
         let message = ConversationMessage {
+            id: new_message_id(),
             role: MessageRole::User,
             content: "Hello, world!".to_string(),
             timestamp: Utc::now(),
             token_count: 3,
             metadata: HashMap::new(),
+            pinned: false,
         };
 
         manager.append_message(session_id, message).await.unwrap();
@@ -498,11 +1053,13 @@ mod tests {
         manager.append_message(
             session_id,
             ConversationMessage {
+                id: new_message_id(),
                 role: MessageRole::User,
                 content: "Tell me about Rust programming".to_string(),
                 timestamp: Utc::now(),
                 token_count: 5,
                 metadata: HashMap::new(),
+                pinned: false,
             },
         ).await.unwrap();
 
@@ -512,6 +1069,7 @@ mod tests {
             start_date: None,
             end_date: None,
             limit: Some(10),
+            boost_pinned: false,
         };
 
         let results = manager.search_history(session_id, query).await.unwrap();
@@ -527,11 +1085,13 @@ mod tests {
         manager.append_message(
             session_id,
             ConversationMessage {
+                id: new_message_id(),
                 role: MessageRole::User,
                 content: "Test message".to_string(),
                 timestamp: Utc::now(),
                 token_count: 2,
                 metadata: HashMap::new(),
+                pinned: false,
             },
         ).await.unwrap();
 
@@ -551,4 +1111,235 @@ mod tests {
         let csv = manager.export_history(session_id, ExportFormat::Csv).await.unwrap();
         assert!(csv.contains("timestamp,role,content,token_count"));
     }
+
+    #[tokio::test]
+    async fn test_export_empty_conversation() {
+        let manager = HistoryManager::new();
+        let options = ExportOptions::default();
+
+        let md = manager.export_history_with("empty", ExportFormat::Markdown, options).await.unwrap();
+        assert_eq!(md, "# Conversation History\n\n");
+        let text = manager.export_history_with("empty", ExportFormat::Text, options).await.unwrap();
+        assert_eq!(text, format!("Conversation History\n{}\n\n", "=".repeat(50)));
+        let json = manager.export_history_with("empty", ExportFormat::Json, options).await.unwrap();
+        assert_eq!(json, "[]");
+    }
+
+    #[tokio::test]
+    async fn test_export_preserves_backticks() {
+        let mut manager = HistoryManager::new();
+        let session_id = "test-session";
+        manager
+            .append_message(session_id, message("Run `kubectl get pods`:\n```sh\nkubectl get pods\n```", 8))
+            .await
+            .unwrap();
+        manager
+            .append_message(session_id, message("Unclosed:\n```\nlet x = 1;", 5))
+            .await
+            .unwrap();
+        manager.append_message(session_id, message("Thanks", 1)).await.unwrap();
+        let options = ExportOptions { include_timestamps: false };
+
+        let md = manager.export_history_with(session_id, ExportFormat::Markdown, options).await.unwrap();
+        assert!(md.contains("## User\n\nRun `kubectl get pods`:\n```sh\nkubectl get pods\n```\n\n---"));
+        // The open block is closed before the next message
+        assert!(md.contains("let x = 1;\n```\n\n---\n\n## User\n\nThanks\n"));
+        assert_eq!(md.matches("```").count(), 4);
+
+        let text = manager.export_history_with(session_id, ExportFormat::Text, options).await.unwrap();
+        assert!(text.contains("User: Run `kubectl get pods`:\n```sh\n"));
+        assert!(text.contains("\nUser: Thanks\n"));
+        assert!(!text.contains('['));
+
+        let stamped = manager.export_history(session_id, ExportFormat::Text).await.unwrap();
+        assert!(stamped.contains("] User: Thanks\n"));
+    }
+
+    fn message(content: &str, token_count: usize) -> ConversationMessage {
+        ConversationMessage {
+            id: new_message_id(),
+            role: MessageRole::User,
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            token_count,
+            metadata: HashMap::new(),
+            pinned: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_annotations_are_kept_apart_from_messages() {
+        let mut manager = HistoryManager::new();
+        let session_id = "test-session";
+
+        let answer = message("Restart the checkout pods", 5);
+        let answer_id = answer.id.clone();
+        manager.append_message(session_id, answer).await.unwrap();
+
+        manager.annotate(session_id, &answer_id, Annotation::new(AnnotationKind::ThumbsUp, "alice")).unwrap();
+        let note = AnnotationKind::Note { text: "worked after a retry".to_string() };
+        manager.annotate(session_id, &answer_id, Annotation::new(note, "bob")).unwrap();
+        assert!(manager
+            .annotate(session_id, "missing", Annotation::new(AnnotationKind::ThumbsDown, "bob"))
+            .is_err());
+
+        let annotations = manager.annotations(session_id, &answer_id);
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[1].to_string(), "note by bob: worked after a retry");
+        // The message itself is untouched
+        let stored = &manager.get_all_messages(session_id).await.unwrap()[0];
+        assert_eq!(stored.content, "Restart the checkout pods");
+        assert!(stored.metadata.is_empty());
+
+        let json = manager.export_history(session_id, ExportFormat::Json).await.unwrap();
+        let exported: Vec<AnnotatedMessage> = serde_json::from_str(&json).unwrap();
+        assert_eq!(exported[0].annotations, annotations);
+        let md = manager.export_history(session_id, ExportFormat::Markdown).await.unwrap();
+        assert!(md.contains("> thumbs up by alice\n"));
+
+        // Annotations go with their message
+        manager.clear_history(session_id);
+        assert!(manager.annotations(session_id, &answer_id).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_satisfaction_rate_from_annotations() {
+        let mut manager = HistoryManager::new();
+        let session_id = "test-session";
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let msg = message(&format!("answer {}", i), 2);
+            ids.push(msg.id.clone());
+            manager.append_message(session_id, msg).await.unwrap();
+        }
+        assert_eq!(manager.statistics(session_id).satisfaction_rate, None);
+
+        let rate = |manager: &mut HistoryManager, id: &str, kind: AnnotationKind, author: &str| {
+            manager.annotate(session_id, id, Annotation::new(kind, author)).unwrap();
+        };
+        rate(&mut manager, &ids[0], AnnotationKind::ThumbsUp, "alice");
+        rate(&mut manager, &ids[0], AnnotationKind::ThumbsUp, "bob");
+        rate(&mut manager, &ids[1], AnnotationKind::ThumbsUp, "alice");
+        rate(&mut manager, &ids[2], AnnotationKind::ThumbsDown, "alice");
+        let flag = || AnnotationKind::Flag { reason: "wrong namespace".to_string() };
+        rate(&mut manager, &ids[2], flag(), "reviewer");
+        rate(&mut manager, &ids[2], flag(), "reviewer-2");
+
+        let stats = manager.statistics(session_id);
+        assert_eq!(stats.thumbs_up, 3);
+        assert_eq!(stats.thumbs_down, 1);
+        assert_eq!(stats.flagged_messages, 1);
+        assert_eq!(stats.satisfaction_rate, Some(0.75));
+    }
+
+    #[tokio::test]
+    async fn test_context_sources_in_statistics() {
+        use crate::prompt::PromptSegment;
+        use copilot_context::Provenance;
+
+        let mut manager = HistoryManager::with_config(2, true);
+        let session_id = "test-session";
+        let first = message("first answer", 2);
+        let first_id = first.id.clone();
+        manager.append_message(session_id, first).await.unwrap();
+        let summary = ContextSource {
+            compressed: true,
+            ..ContextSource::new(PromptSegment::Retrieved, Provenance::Summary { summary_id: "s1".into() })
+        };
+        manager
+            .record_sources(session_id, &first_id, vec![
+                ContextSource::new(PromptSegment::Retrieved, Provenance::document("runbook-7")),
+                summary,
+            ])
+            .unwrap();
+        assert!(manager.record_sources(session_id, "unknown", Vec::new()).is_err());
+
+        let stats = manager.statistics(session_id);
+        assert_eq!(stats.context_sources.get("document"), Some(&1));
+        assert_eq!(stats.context_sources.get("summary"), Some(&1));
+        assert_eq!(stats.compressed_context_sources, 1);
+
+        // Sources go with their message
+        manager.append_message(session_id, message("second", 1)).await.unwrap();
+        manager.append_message(session_id, message("third", 1)).await.unwrap();
+        assert!(manager.sources(session_id, &first_id).is_empty());
+        assert!(manager.statistics(session_id).context_sources.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_messages_survive_pruning() {
+        let mut manager = HistoryManager::with_config(3, true);
+        let session_id = "test-session";
+
+        let first = message("The database password rotates on Fridays", 8);
+        let first_id = first.id.clone();
+        manager.append_message(session_id, first).await.unwrap();
+        manager.pin(session_id, &first_id).unwrap();
+
+        for i in 0..5 {
+            manager.append_message(session_id, message(&format!("msg {}", i), 2)).await.unwrap();
+        }
+
+        let history = manager.get_all_messages(session_id).await.unwrap();
+        let contents: Vec<_> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["The database password rotates on Fridays", "msg 3", "msg 4"]);
+
+        let removed = manager.delete_before(session_id, Utc::now()).await.unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(manager.pinned_messages(session_id).len(), 1);
+
+        assert!(manager.unpin(session_id, &first_id).unwrap());
+        assert!(manager.pinned_messages(session_id).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_token_budget() {
+        let mut manager = HistoryManager::new().with_pinned_token_budget(10);
+        let session_id = "test-session";
+
+        let small = message("small", 6);
+        let large = message("large", 5);
+        let (small_id, large_id) = (small.id.clone(), large.id.clone());
+        manager.append_message(session_id, small).await.unwrap();
+        manager.append_message(session_id, large).await.unwrap();
+
+        manager.pin(session_id, &small_id).unwrap();
+        assert!(manager.pin(session_id, &large_id).is_err());
+        assert!(manager.pin(session_id, "missing").is_err());
+
+        manager.unpin(session_id, &small_id).unwrap();
+        manager.pin(session_id, &large_id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pinned_messages_marked_in_exports_and_boosted_in_search() {
+        let mut manager = HistoryManager::new();
+        let session_id = "test-session";
+
+        manager.append_message(session_id, message("check the deploy deploy status later", 5)).await.unwrap();
+        let pinned = message("rollback plan for the deploy of api", 8);
+        let pinned_id = pinned.id.clone();
+        manager.append_message(session_id, pinned).await.unwrap();
+        manager.pin(session_id, &pinned_id).unwrap();
+
+        let md = manager.export_history(session_id, ExportFormat::Markdown).await.unwrap();
+        assert_eq!(md.matches("(pinned)").count(), 1);
+        let csv = manager.export_history(session_id, ExportFormat::Csv).await.unwrap();
+        assert!(csv.lines().nth(2).unwrap().ends_with(",true"));
+
+        let mut query = SearchQuery {
+            query: "deploy".to_string(),
+            role: None,
+            start_date: None,
+            end_date: None,
+            limit: None,
+            boost_pinned: false,
+        };
+        let results = manager.search_history(session_id, query.clone()).await.unwrap();
+        assert_ne!(results[0].message.id, pinned_id);
+
+        query.boost_pinned = true;
+        let results = manager.search_history(session_id, query).await.unwrap();
+        assert_eq!(results[0].message.id, pinned_id);
+    }
 }
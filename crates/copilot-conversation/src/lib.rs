@@ -3,19 +3,75 @@
 //! This crate provides conversation management capabilities including:
 //! - Multi-turn dialogue with context retention
 //! - Session management with token tracking
+//! - Per-session models with their own tokenizers, context windows and pricing
 //! - Response streaming with SSE support
+//! - Resumable streams with replicated chunk buffers
 //! - Conversation history with search and export
+//! - Session forking with branch trees
 //! - Reference resolution for natural dialogue
+//! - Per-session entity memory for consistent answers
+//! - Content-type-aware attachment processing
+//! - Message content normalization before tokenization and storage
+//! - Pluggable safety screening of incoming messages
+//! - Deterministic replay of recorded conversations for regression tests
 
 pub mod manager;
+pub mod entity_memory;
+pub mod model;
 pub mod session;
 pub mod streaming;
+pub mod resumable;
 pub mod history;
+pub mod normalize;
+pub mod branch;
+pub mod attachments;
+pub mod checkpoint;
+pub mod prompt;
+pub mod refinement;
+pub mod replay;
+pub mod safety;
 
-pub use manager::ConversationManager;
-pub use session::{Session, SessionManager, SessionState};
-pub use streaming::{StreamingResponse, StreamChunk};
-pub use history::{HistoryManager, ConversationMessage, MessageRole};
+pub use manager::{
+    ContextFailurePolicy, ConversationManager, ResponseGenerator, Summarizer, WarmupReport,
+    DEFAULT_SUMMARY_KEEP_TURNS, SUMMARIZED_MESSAGES_KEY,
+};
+pub use session::{
+    BranchOrigin, BudgetWarning, BudgetWarningHook, BudgetWarningPolicy, PreviousOwnerAccess,
+    QuotaKind, ResourceQuota, ResourceUsage, Session, SessionConfig, SessionEvent,
+    SessionEventKind, SessionManager, SessionState, SessionTimeline, SessionTransfer, TokenWarning,
+};
+pub use streaming::{
+    bounded_stream, sse_stream, BackpressurePolicy, ChunkType, SseConfig, StreamChunk, StreamChunkBuilder, StreamEmitter, StreamUsage,
+    ResumeToken, StreamConfig, StreamingResponse, DEFAULT_STREAM_BUFFER_SIZE, SSE_KEEPALIVE,
+};
+pub use resumable::{
+    InMemoryStreamStore, ResumableStreamConfig, ResumableStreamManager, StateUpdate, StreamState,
+    StreamStateStore,
+};
+#[cfg(feature = "redis")]
+pub use resumable::RedisStreamStore;
+pub use history::{
+    AnnotatedMessage, Annotation, AnnotationKind, HistoryManager, ConversationMessage, ExportFormat,
+    ExportOptions, MessageRole, SearchQuery, SessionSearchHit,
+};
+pub use branch::{BranchNode, BranchSummary, BranchTree};
+pub use entity_memory::{EntityMemory, EntityMemoryConfig, EntityResolution, RememberedEntity};
+pub use model::{ModelPricing, ModelProfile, ModelRegistry, ModelTokenizer, TokenizerSpec};
+pub use normalize::NormalizationConfig;
+pub use checkpoint::{Checkpoint, CheckpointId, CheckpointStore};
+pub use prompt::{ContextSource, PromptContext, PromptEstimate, PromptSegment};
+pub use refinement::{QueryState, RefinedQuery, RefinementKind};
+pub use replay::{
+    ConversationReplay, RecordedConversation, RecordedDocument, RecordedTurn, ReplayTranscript,
+    ReplayedTurn, ScriptedModel,
+};
+pub use safety::{
+    AllowAllClassifier, SafetyClassifier, SafetyFailurePolicy, SafetyVerdict, SAFETY_FLAG_KEY,
+};
+pub use attachments::{
+    AttachmentConfig, AttachmentProcessor, AttachmentType, HostResolver, HttpFetcher,
+    MessageAttachment, ProcessedAttachment, SystemResolver, UrlFetcher,
+};
 
 use thiserror::Error;
 
@@ -31,6 +87,15 @@ pub enum ConversationError {
     #[error("Session expired: {0}")]
     SessionExpired(String),
 
+    #[error("Session {id} already exists with a different configuration: {reason}")]
+    SessionConflict { id: String, reason: String },
+
+    #[error("Access denied to session {session_id} for {user}")]
+    AccessDenied { session_id: String, user: String },
+
+    #[error("Message blocked: {reason}")]
+    MessageBlocked { reason: String },
+
     #[error("Invalid message format: {0}")]
     InvalidMessage(String),
 
@@ -40,15 +105,37 @@ pub enum ConversationError {
     #[error("Streaming error: {0}")]
     StreamingError(String),
 
+    #[error("Invalid resume token: {0}")]
+    InvalidResumeToken(String),
+
+    #[error("Stream backpressure: {0}")]
+    StreamBackpressure(String),
+
+    #[error("Checkpoint not found: {0}")]
+    CheckpointNotFound(String),
+
+    #[error("Attachment error: {0}")]
+    AttachmentError(String),
+
     #[error("Context error: {0}")]
     ContextError(String),
 
     #[error("NLP processing error: {0}")]
     NlpError(String),
 
+    #[error("Response generation failed: {0}")]
+    GenerationError(String),
+
     #[error("Token limit exceeded: used {used}, limit {limit}")]
     TokenLimitExceeded { used: usize, limit: usize },
 
+    #[error("Quota exceeded for {quota}: requested {requested}, limit {limit}")]
+    QuotaExceeded {
+        quota: session::QuotaKind,
+        requested: usize,
+        limit: usize,
+    },
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -1,17 +1,47 @@
 //! Conversation manager for handling multi-turn dialogue
 
 use crate::{
-    history::{ConversationMessage, HistoryManager, MessageRole},
-    session::{SessionManager, SessionState},
-    streaming::StreamingResponse,
+    attachments::{AttachmentProcessor, MessageAttachment, ProcessedAttachment},
+    branch::{BranchSummary, BranchTree},
+    checkpoint::{Checkpoint, CheckpointId, CheckpointStore},
+    entity_memory::{find_phrases, EntityMemory, EntityMemoryConfig, RememberedEntity},
+    history::{
+        new_message_id, ConversationMessage, ExportFormat, HistoryManager, MessageRole, SearchQuery,
+        SessionSearchHit,
+    },
+    model::{ModelProfile, ModelRegistry, ModelTokenizer, DEFAULT_MODEL},
+    normalize::NormalizationConfig,
+    prompt::{
+        ContextSource, PromptContext, PromptEstimate, PromptSegment, DEFAULT_CONTEXT_WINDOW,
+        DEFAULT_SYSTEM_PROMPT,
+    },
+    refinement::{self, QueryState, RefinedQuery, RefinementKind},
+    safety::{
+        AllowAllClassifier, SafetyClassifier, SafetyFailurePolicy, SafetyVerdict, SAFETY_FLAG_KEY,
+    },
+    session::{
+        BudgetWarningPolicy, Session, SessionEventKind, SessionManager, SessionState,
+        SessionTransfer,
+    },
+    resumable::ResumableStreamManager,
+    streaming::{ResumeToken, StreamChunk, StreamingResponse},
     Result, ConversationError,
 };
 use async_trait::async_trait;
-use copilot_context::ContextEngine;
-use copilot_nlp::NlpEngine;
+use copilot_context::{
+    importance::{ENTITY_COUNT_KEY, ROLE_KEY, TIMESTAMP_KEY},
+    retrieval::RetrievalResult, ContextEngine, EntityTerm, ExpandedQuery, MemoryMetadata,
+    MemoryTier, Provenance, QueryExpander,
+};
+use copilot_nlp::{Entity, EntityType, NlpEngine, QueryLanguage};
 use serde::{Deserialize, Serialize};
+use futures::Stream;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedMutexGuard, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
 /// Request for processing a user message
@@ -24,6 +54,56 @@ pub struct MessageRequest {
     /// Optional metadata
     #[serde(default)]
     pub metadata: std::collections::HashMap<String, String>,
+    /// Attachments sent with the message
+    #[serde(default)]
+    pub attachments: Vec<MessageAttachment>,
+    /// User sending the message, who must be allowed to write to an
+    /// owned session
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+/// What a turn does when context retrieval fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextFailurePolicy {
+    /// Continue with history-only context and flag the turn as degraded
+    #[default]
+    Degrade,
+    /// Fail the turn
+    Fail,
+}
+
+/// Produces the assistant reply to an assembled prompt
+///
+/// Without a generator the manager answers from the message's intent
+/// alone.
+#[async_trait]
+pub trait ResponseGenerator: Send + Sync {
+    /// Generate the reply to a prompt
+    async fn generate(&self, prompt: &PromptContext) -> Result<String>;
+}
+
+/// Condenses a session's earlier turns, see
+/// [`ConversationManager::enable_auto_summarize`]
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    /// Summarize messages, oldest first, into the content of one system
+    /// message
+    async fn summarize(&self, messages: &[ConversationMessage]) -> Result<String>;
+}
+
+/// Turns kept verbatim by default when a session is summarized
+pub const DEFAULT_SUMMARY_KEEP_TURNS: usize = 2;
+
+/// Metadata key of a summary message recording how many messages it
+/// replaced
+pub const SUMMARIZED_MESSAGES_KEY: &str = "summarized_messages";
+
+/// When and how sessions are summarized
+struct AutoSummarize {
+    threshold: f64,
+    summarizer: Arc<dyn Summarizer>,
 }
 
 /// Response containing the assistant's reply
@@ -40,6 +120,40 @@ pub struct MessageResponse {
     pub tokens_used: usize,
     /// Total tokens used in session
     pub total_tokens: usize,
+    /// Attachments whose text was used for this response
+    #[serde(default)]
+    pub attachments: Vec<ProcessedAttachment>,
+    /// Sources of the context the response was generated from
+    #[serde(default)]
+    pub provenance: Vec<ContextSource>,
+    /// Whether context retrieval failed and the response was generated
+    /// from conversation history only
+    #[serde(default)]
+    pub context_degraded: bool,
+    /// Model the response was generated for
+    #[serde(default)]
+    pub model: String,
+    /// Cost of the turn at the model's pricing
+    #[serde(default)]
+    pub cost: f64,
+}
+
+/// What warming up a session's context did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmupReport {
+    /// Query the context was prefetched for; empty if the session had
+    /// nothing to go on
+    pub query: String,
+    /// Items moved into the short-term tier
+    pub items_promoted: usize,
+    /// Tokens moved into the short-term tier
+    pub tokens_promoted: usize,
+    /// Relevant items already in the short-term tier
+    pub items_warm: usize,
+    /// Relevant items left in place because the short-term tier was full
+    pub items_skipped: usize,
+    /// Why warmup stopped early, if it did
+    pub error: Option<String>,
 }
 
 /// A resolved reference from the conversation
@@ -59,8 +173,79 @@ pub struct ConversationManager {
     context_engine: Arc<dyn ContextEngine>,
     session_manager: Arc<RwLock<SessionManager>>,
     history_manager: Arc<RwLock<HistoryManager>>,
+    query_expander: Option<QueryExpander>,
+    attachment_processor: AttachmentProcessor,
+    checkpoints: RwLock<CheckpointStore>,
+    resumable_streams: Arc<ResumableStreamManager>,
+    query_states: RwLock<HashMap<String, QueryState>>,
+    entity_memory: RwLock<EntityMemory>,
+    normalization: NormalizationConfig,
+    context_failure_policy: ContextFailurePolicy,
+    response_generator: Option<Arc<dyn ResponseGenerator>>,
+    safety_classifier: Arc<dyn SafetyClassifier>,
+    safety_failure_policy: SafetyFailurePolicy,
+    models: ModelRegistry,
+    /// Number of prompts built without retrieved context because retrieval failed
+    degraded_prompts: AtomicU64,
+    system_prompt: String,
+    context_window: usize,
+    auto_summarize: Option<AutoSummarize>,
+    summary_keep_turns: usize,
+}
+
+/// Promote the context retrieved for a warmup query into the short-term
+/// tier, within the capacity the engine reports for it
+async fn prefetch(
+    context_engine: &dyn ContextEngine,
+    report: &mut WarmupReport,
+) -> copilot_context::Result<()> {
+    let retrieved = context_engine.retrieve(&report.query).await?;
+    let stats = context_engine.stats().await?;
+    let mut headroom = context_engine
+        .tier_capacity(MemoryTier::ShortTerm)
+        .saturating_sub(stats.short_term_tokens);
+
+    for scored in retrieved.selected {
+        let item = scored.item;
+        if item.tier == MemoryTier::ShortTerm {
+            report.items_warm += 1;
+        } else if item.token_count > headroom {
+            report.items_skipped += 1;
+        } else {
+            context_engine.promote(&item.metadata.id, MemoryTier::ShortTerm).await?;
+            headroom -= item.token_count;
+            report.items_promoted += 1;
+            report.tokens_promoted += item.token_count;
+        }
+    }
+    Ok(())
 }
 
+/// Number of history messages included in a prompt
+const PROMPT_HISTORY_MESSAGES: usize = 10;
+
+/// Number of recent history messages searched for the antecedent of a
+/// reference
+const REFERENCE_HISTORY_MESSAGES: usize = 6;
+
+/// Phrases that refer back to an earlier entity, longest first, and the
+/// type of entity they refer to; `None` refers to any service or resource
+const REFERENCE_PHRASES: &[(&str, Option<EntityType>)] = &[
+    ("the same service", Some(EntityType::Service)),
+    ("that deployment", Some(EntityType::Deployment)),
+    ("that endpoint", Some(EntityType::Endpoint)),
+    ("that service", Some(EntityType::Service)),
+    ("this service", Some(EntityType::Service)),
+    ("that metric", Some(EntityType::Metric)),
+    ("that host", Some(EntityType::Host)),
+    ("that pod", Some(EntityType::Pod)),
+    ("its", None),
+    ("it", None),
+];
+
+/// Phrase that refers to the user's previous message as a query
+const PREVIOUS_QUERY_PHRASE: &str = "the previous query";
+
 impl ConversationManager {
     /// Create a new conversation manager
     ///
@@ -74,82 +259,272 @@ impl ConversationManager {
             context_engine,
             session_manager: Arc::new(RwLock::new(SessionManager::new())),
             history_manager: Arc::new(RwLock::new(HistoryManager::new())),
+            query_expander: None,
+            attachment_processor: AttachmentProcessor::default(),
+            checkpoints: RwLock::new(CheckpointStore::default()),
+            resumable_streams: Arc::new(ResumableStreamManager::in_memory()),
+            query_states: RwLock::new(HashMap::new()),
+            entity_memory: RwLock::new(EntityMemory::default()),
+            normalization: NormalizationConfig::default(),
+            context_failure_policy: ContextFailurePolicy::default(),
+            response_generator: None,
+            safety_classifier: Arc::new(AllowAllClassifier),
+            safety_failure_policy: SafetyFailurePolicy::default(),
+            models: ModelRegistry::default(),
+            degraded_prompts: AtomicU64::new(0),
+            system_prompt: DEFAULT_SYSTEM_PROMPT.to_string(),
+            context_window: DEFAULT_CONTEXT_WINDOW,
+            auto_summarize: None,
+            summary_keep_turns: DEFAULT_SUMMARY_KEEP_TURNS,
         }
     }
 
+    /// Use a custom system prompt
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<String>) -> Self {
+        self.system_prompt = system_prompt.into();
+        self
+    }
+
+    /// Set the context window in tokens of sessions on the default model
+    pub fn with_context_window(mut self, tokens: usize) -> Self {
+        self.context_window = tokens;
+        self
+    }
+
+    /// Keep at most `max_per_session` checkpoints per session
+    pub fn with_checkpoint_limit(mut self, max_per_session: usize) -> Self {
+        self.checkpoints = RwLock::new(CheckpointStore::new(max_per_session));
+        self
+    }
+
+    /// Buffer the chunks of every stream in a shared manager, e.g. one
+    /// backed by Redis so a client can resume on another replica
+    pub fn with_resumable_streams(mut self, streams: Arc<ResumableStreamManager>) -> Self {
+        self.resumable_streams = streams;
+        self
+    }
+
+    /// Use a custom attachment processor
+    pub fn with_attachment_processor(mut self, processor: AttachmentProcessor) -> Self {
+        self.attachment_processor = processor;
+        self
+    }
+
+    /// Set how long sessions remember the entities they mention
+    pub fn with_entity_memory(mut self, config: EntityMemoryConfig) -> Self {
+        self.entity_memory = RwLock::new(EntityMemory::new(config));
+        self
+    }
+
+    /// Warn before sessions reach their token limit
+    ///
+    /// By default sessions warn at 70%, 85% and 95% of their budget.
+    pub fn with_budget_warnings(mut self, policy: BudgetWarningPolicy) -> Self {
+        self.session_manager = Arc::new(RwLock::new(
+            SessionManager::new().with_budget_warning_policy(policy),
+        ));
+        self
+    }
+
+    /// Set how message content is normalized before it is stored
+    pub fn with_normalization(mut self, config: NormalizationConfig) -> Self {
+        self.normalization = config;
+        self
+    }
+
+    /// Set what a turn does when context retrieval fails
+    ///
+    /// By default the turn continues with conversation history only.
+    pub fn with_context_failure_policy(mut self, policy: ContextFailurePolicy) -> Self {
+        self.context_failure_policy = policy;
+        self
+    }
+
+    /// Use a registry of the models sessions may switch to
+    pub fn with_model_registry(mut self, registry: ModelRegistry) -> Self {
+        self.models = registry;
+        self
+    }
+
+    /// Generate replies with a model instead of from the message intent
+    pub fn with_response_generator(mut self, generator: Arc<dyn ResponseGenerator>) -> Self {
+        self.response_generator = Some(generator);
+        self
+    }
+
+    /// Summarize a session's oldest turns instead of failing a turn that
+    /// takes it close to its token limit
+    ///
+    /// Once a turn would bring a session's usage to `threshold` of its
+    /// `max_tokens`, e.g. 0.8, the turns before the most recent ones are
+    /// replaced with one system message written by `summarizer`, and the
+    /// session's usage is recounted from the remaining history. Pinned
+    /// messages are never summarized.
+    pub fn enable_auto_summarize(mut self, threshold: f64, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.auto_summarize = Some(AutoSummarize { threshold, summarizer });
+        self
+    }
+
+    /// Keep the last `turns` turns verbatim when summarizing a session
+    ///
+    /// A turn runs from a user message to the next one.
+    pub fn with_summary_keep_turns(mut self, turns: usize) -> Self {
+        self.summary_keep_turns = turns;
+        self
+    }
+
+    /// Screen incoming messages with a safety classifier
+    ///
+    /// By default every message is allowed.
+    pub fn with_safety_classifier(mut self, classifier: Arc<dyn SafetyClassifier>) -> Self {
+        self.safety_classifier = classifier;
+        self
+    }
+
+    /// Set what happens to a message when its safety classifier fails
+    ///
+    /// By default the message is blocked.
+    pub fn with_safety_failure_policy(mut self, policy: SafetyFailurePolicy) -> Self {
+        self.safety_failure_policy = policy;
+        self
+    }
+
+    /// Number of prompts built without retrieved context because
+    /// retrieval failed
+    pub fn degraded_prompts(&self) -> u64 {
+        self.degraded_prompts.load(Ordering::Relaxed)
+    }
+
+    /// Expand retrieval queries with entity values and synonyms
+    pub fn with_query_expander(mut self, expander: QueryExpander) -> Self {
+        self.query_expander = Some(expander);
+        self
+    }
+
     /// Process a user message
     ///
     /// This is the main entry point for handling user messages. It:
-    /// 1. Validates the session
-    /// 2. Resolves references
-    /// 3. Updates context
-    /// 4. Generates response
-    /// 5. Updates history
+    /// 1. Validates the session, normalizes the message content, extracts
+    ///    text from attachments and screens both with the safety classifier
+    /// 2. Resolves references, including under-specified entity mentions
+    ///    such as "the service"
+    /// 3. Generates response
+    /// 4. Updates history and session usage
+    ///
+    /// Response generation runs without holding the session lock. The turn
+    /// is then committed under the lock, so concurrent turns on one session
+    /// never interleave their messages or miscount tokens, while turns on
+    /// different sessions proceed in parallel.
     ///
     /// # Arguments
     ///
     /// * `request` - The message request to process
-    pub async fn process_message(&self, request: MessageRequest) -> Result<MessageResponse> {
+    pub async fn process_message(&self, mut request: MessageRequest) -> Result<MessageResponse> {
         info!("Processing message for session: {}", request.session_id);
+        request.message = self.normalization.normalize(&request.message);
 
-        // Get or create session
-        let mut session_mgr = self.session_manager.write().await;
-        let session = session_mgr
-            .get_session(&request.session_id)
-            .ok_or_else(|| ConversationError::SessionNotFound(request.session_id.clone()))?;
+        let attachment_bytes: usize = request.attachments.iter().map(|a| a.content.len()).sum();
 
-        // Check if session is expired
-        if session.state == SessionState::Expired {
-            return Err(ConversationError::SessionExpired(request.session_id));
-        }
+        // Fail fast before generating: the user message and the reply must fit
+        let model = {
+            let mut session_mgr = self.session_manager.write().await;
+            self.check_turn(&mut session_mgr, &request, 0, attachment_bytes)?;
+            session_mgr.get_session(&request.session_id).and_then(|s| s.model.clone())
+        };
 
-        drop(session_mgr);
+        // Extract text from attachments; ones that cannot be processed are skipped
+        let attachments = self.attachment_processor.process_all(&request.attachments).await;
+        self.screen_turn(&mut request, &attachments).await?;
+        let (profile, tokenizer, context_window) = self.model_limits(model.as_deref());
 
         // Resolve references in the message
-        let resolved_refs = self.resolve_references(&request.session_id, &request.message).await?;
+        let mut resolved_refs = self.detect_references(&request.session_id, &request.message).await?;
+        let mentioned = self.mentioned_entities(&request.message).await;
+        resolved_refs.extend(
+            self.entity_memory
+                .read()
+                .await
+                .resolve(&request.session_id, &request.message, &mentioned)
+                .into_iter()
+                .map(|resolution| ResolvedReference {
+                    reference: resolution.phrase,
+                    refers_to: resolution.entity.value,
+                    confidence: 0.9,
+                }),
+        );
         debug!("Resolved {} references", resolved_refs.len());
 
-        // Build enhanced message with resolved references
-        let enhanced_message = self.enhance_message_with_references(&request.message, &resolved_refs);
+        // Build enhanced message with resolved references and attachment text
+        let mut enhanced_message = self.enhance_message_with_references(&request.message, &resolved_refs);
+        for attachment in &attachments {
+            enhanced_message.push_str("\n\n");
+            enhanced_message.push_str(&attachment.text);
+        }
+
+        // Generate response
+        let (response, prompt) = self.generate(&request.session_id, &enhanced_message).await?;
+        let response = self.normalization.normalize(&response);
+        let message_tokens = tokenizer.count(&request.message)
+            + attachments.iter().map(|a| tokenizer.count(&a.text)).sum::<usize>();
+        let response_tokens = tokenizer.count(&response);
+        let total_tokens = message_tokens + response_tokens;
+        let prompt_tokens = prompt.estimate_with(&tokenizer, context_window).total_tokens;
+        let cost = profile.pricing.cost(prompt_tokens, response_tokens);
+
+        // Commit the turn
+        let _guard = self.lock_session(&request.session_id).await?;
+
+        // Make room rather than fail the re-check
+        self.summarize_if_needed(&request.session_id, total_tokens, &tokenizer).await?;
+
+        // Re-check: another turn may have committed while this one generated
+        let mut session_mgr = self.session_manager.write().await;
+        self.check_turn(&mut session_mgr, &request, total_tokens, attachment_bytes)?;
+
+        let mut response_metadata = HashMap::new();
+        if prompt.context_degraded {
+            response_metadata.insert("context_degraded".to_string(), "true".to_string());
+        }
 
-        // Add user message to history
         let mut history_mgr = self.history_manager.write().await;
         history_mgr.append_message(
             &request.session_id,
             ConversationMessage {
+                id: new_message_id(),
                 role: MessageRole::User,
                 content: request.message.clone(),
                 timestamp: chrono::Utc::now(),
-                token_count: self.estimate_tokens(&request.message),
+                token_count: message_tokens,
                 metadata: request.metadata.clone(),
+                pinned: false,
             },
         ).await?;
-        drop(history_mgr);
-
-        // Generate response
-        let response = self.generate_response(&request.session_id, &enhanced_message).await?;
-        let response_tokens = self.estimate_tokens(&response);
-
-        // Add assistant message to history
-        let mut history_mgr = self.history_manager.write().await;
+        let response_id = new_message_id();
         history_mgr.append_message(
             &request.session_id,
             ConversationMessage {
+                id: response_id.clone(),
                 role: MessageRole::Assistant,
                 content: response.clone(),
                 timestamp: chrono::Utc::now(),
                 token_count: response_tokens,
-                metadata: std::collections::HashMap::new(),
+                metadata: response_metadata,
+                pinned: false,
             },
         ).await?;
+        history_mgr.record_sources(&request.session_id, &response_id, prompt.sources.clone())?;
         drop(history_mgr);
 
         // Update session token count
-        let message_tokens = self.estimate_tokens(&request.message);
-        let total_tokens = message_tokens + response_tokens;
-
-        let mut session_mgr = self.session_manager.write().await;
         session_mgr.update_session(&request.session_id, total_tokens).await?;
+        session_mgr.record_messages(&request.session_id, 2, attachment_bytes)?;
+
+        for replaced in self.entity_memory.write().await.record(&request.session_id, &mentioned) {
+            debug!(
+                "Session {} moved on from {:?} {}",
+                request.session_id, replaced.entity_type, replaced.value
+            );
+        }
         let session = session_mgr.get_session(&request.session_id).unwrap();
         let session_total_tokens = session.total_tokens;
 
@@ -159,9 +534,199 @@ impl ConversationManager {
             resolved_references: resolved_refs,
             tokens_used: total_tokens,
             total_tokens: session_total_tokens,
+            attachments,
+            provenance: prompt.sources,
+            context_degraded: prompt.context_degraded,
+            model: profile.name,
+            cost,
         })
     }
 
+    /// Interpret a message as a query, refining the session's previous one
+    ///
+    /// Follow-ups such as "now only production" are merged into the
+    /// session's accumulated query (see [`refinement`]) and the result is
+    /// translated again; other messages start a new query. A reset phrase
+    /// such as "start over" clears the accumulated query. Under-specified
+    /// mentions such as "the service" resolve to the entity the session's
+    /// messages established.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session identifier
+    /// * `message` - The user message
+    /// * `language` - Language to translate the query to
+    pub async fn refine_query(
+        &self,
+        session_id: &str,
+        message: &str,
+        language: QueryLanguage,
+    ) -> Result<RefinedQuery> {
+        if self.session_manager.write().await.get_session(session_id).is_none() {
+            return Err(ConversationError::SessionNotFound(session_id.to_string()));
+        }
+
+        let intent = self
+            .nlp_engine
+            .classify_intent(message)
+            .await
+            .map_err(|e| ConversationError::NlpError(e.to_string()))?;
+
+        let mut states = self.query_states.write().await;
+        let kind = refinement::classify(message, &intent, states.get(session_id));
+        debug!("Query turn for session {}: {:?}", session_id, kind);
+
+        if kind == RefinementKind::Reset {
+            states.remove(session_id);
+            return Ok(RefinedQuery {
+                kind,
+                state: None,
+                language,
+                query: None,
+            });
+        }
+
+        let mut entities = self
+            .nlp_engine
+            .extract_entities(message)
+            .await
+            .map_err(|e| ConversationError::NlpError(e.to_string()))?;
+        let resolutions = self.entity_memory.read().await.resolve(session_id, message, &entities);
+        entities.extend(resolutions.iter().map(|resolution| resolution.to_entity()));
+
+        let state = match (kind, states.get_mut(session_id)) {
+            (RefinementKind::Refine, Some(state)) => {
+                state.refine(entities, refinement::is_exclusive(message));
+                state.clone()
+            }
+            _ => {
+                let state = QueryState::new(intent, entities);
+                states.insert(session_id.to_string(), state.clone());
+                state
+            }
+        };
+        drop(states);
+
+        let query = self
+            .nlp_engine
+            .translate_query(message, &state.intent, &state.entities, language)
+            .await
+            .map_err(|e| ConversationError::NlpError(e.to_string()))?;
+
+        Ok(RefinedQuery {
+            kind,
+            state: Some(state),
+            language,
+            query: Some(query),
+        })
+    }
+
+    /// Summarize a session's older turns if `incoming` more tokens would
+    /// take it past the auto-summarize threshold
+    ///
+    /// Call with the session lock held. A failing summarizer is logged and
+    /// leaves the history as it is. Returns whether the session was
+    /// summarized.
+    async fn summarize_if_needed(
+        &self,
+        session_id: &str,
+        incoming: usize,
+        tokenizer: &ModelTokenizer,
+    ) -> Result<bool> {
+        let Some(auto) = &self.auto_summarize else {
+            return Ok(false);
+        };
+        let (used, limit) = {
+            let mut session_mgr = self.session_manager.write().await;
+            let session = session_mgr
+                .get_session(session_id)
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+            (session.total_tokens, session.max_tokens)
+        };
+        if ((used + incoming) as f64) < limit as f64 * auto.threshold {
+            return Ok(false);
+        }
+
+        let messages = self.history_manager.read().await.get_all_messages(session_id).await?;
+        let keep_from = recent_turns_start(&messages, self.summary_keep_turns);
+        let (older, recent) = messages.split_at(keep_from);
+        let (pinned, summarized): (Vec<_>, Vec<_>) = older.iter().cloned().partition(|msg| msg.pinned);
+        if summarized.is_empty() {
+            return Ok(false);
+        }
+
+        let summary = match auto.summarizer.summarize(&summarized).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("Failed to summarize session {}: {}", session_id, e);
+                return Ok(false);
+            }
+        };
+        let mut history = vec![ConversationMessage {
+            id: new_message_id(),
+            role: MessageRole::System,
+            token_count: tokenizer.count(&summary),
+            content: summary,
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::from([(
+                SUMMARIZED_MESSAGES_KEY.to_string(),
+                summarized.len().to_string(),
+            )]),
+            pinned: false,
+        }];
+        history.extend(pinned);
+        history.extend_from_slice(recent);
+        let tokens_after = history.iter().map(|msg| msg.token_count).sum();
+
+        self.history_manager.write().await.replace_history(session_id, history);
+        self.session_manager.write().await.record_compression(session_id, tokens_after)?;
+        info!(
+            "Summarized {} messages of session {}: {} -> {} tokens",
+            summarized.len(),
+            session_id,
+            used,
+            tokens_after
+        );
+        Ok(true)
+    }
+
+    /// Check that a session can accept a turn of two messages, `tokens` and
+    /// `attachment_bytes`
+    fn check_turn(
+        &self,
+        session_mgr: &mut SessionManager,
+        request: &MessageRequest,
+        tokens: usize,
+        attachment_bytes: usize,
+    ) -> Result<()> {
+        let session_id = &request.session_id;
+        let session = session_mgr
+            .get_session(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        session.check_write(request.user_id.as_deref())?;
+
+        if session.state == SessionState::Expired {
+            return Err(ConversationError::SessionExpired(session_id.to_string()));
+        }
+
+        session.check_message_quota(2, attachment_bytes)?;
+        session.check_tokens(tokens)
+    }
+
+    /// Acquire the mutation lock for a session
+    ///
+    /// Hold the guard only while mutating session state or history, never
+    /// across response generation.
+    pub async fn lock_session(&self, session_id: &str) -> Result<OwnedMutexGuard<()>> {
+        let lock = self
+            .session_manager
+            .write()
+            .await
+            .session_lock(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        Ok(lock.lock_owned().await)
+    }
+
     /// Generate an assistant response
     ///
     /// # Arguments
@@ -169,15 +734,25 @@ impl ConversationManager {
     /// * `session_id` - The session identifier
     /// * `message` - The enhanced message with resolved references
     pub async fn generate_response(&self, session_id: &str, message: &str) -> Result<String> {
+        Ok(self.generate(session_id, message).await?.0)
+    }
+
+    /// Generate an assistant response, returning it with the prompt used
+    async fn generate(&self, session_id: &str, message: &str) -> Result<(String, PromptContext)> {
         debug!("Generating response for session: {}", session_id);
 
-        // Get conversation history for context
-        let history_mgr = self.history_manager.read().await;
-        let history = history_mgr.get_history(session_id, 0, 10).await?;
-        drop(history_mgr);
+        // Assemble the prompt from history and retrieved context
+        let prompt = self.build_prompt_context(session_id, message).await?;
+        debug!(
+            "Built prompt of {} tokens from {} sources",
+            prompt.token_count(),
+            prompt.sources.len()
+        );
 
-        // Build context from history
-        let context = self.build_context_from_history(&history);
+        if let Some(generator) = &self.response_generator {
+            let response = generator.generate(&prompt).await?;
+            return Ok((response, prompt));
+        }
 
         // Use NLP engine to analyze intent
         let intent = self.nlp_engine
@@ -187,96 +762,940 @@ impl ConversationManager {
 
         debug!("Detected intent: {:?}", intent);
 
-        // Use context engine to enhance understanding
-        let context_data = self.context_engine
-            .retrieve(session_id)
-            .await
-            .map_err(|e| ConversationError::ContextError(e.to_string()))?;
-
         // Generate response based on intent and context
-        // In a real implementation, this would call an LLM
+        // In a real implementation, this would send the prompt to an LLM
         let response = format!(
             "I understand you're asking about: {:?}. Based on our conversation context, I can help with that.",
             intent
         );
 
-        Ok(response)
+        Ok((response, prompt))
+    }
+
+    /// Assemble the prompt for a new message
+    ///
+    /// Combines the system prompt, recent history and context retrieved
+    /// for the message. Pinned messages are always included, ahead of the
+    /// recent history. This is the prompt [`generate_response`] uses.
+    ///
+    /// The prompt lists the source of every included history message and
+    /// retrieved item; compressed items are included in compressed form
+    /// and keep the provenance of their original content.
+    ///
+    /// If retrieval fails, the prompt is built from history alone and
+    /// flagged as degraded, unless the manager was configured with
+    /// [`ContextFailurePolicy::Fail`].
+    ///
+    /// [`generate_response`]: Self::generate_response
+    pub async fn build_prompt_context(&self, session_id: &str, message: &str) -> Result<PromptContext> {
+        let history = {
+            let history_mgr = self.history_manager.read().await;
+            let skip = history_mgr.message_count(session_id).saturating_sub(PROMPT_HISTORY_MESSAGES);
+            let recent = history_mgr.get_history(session_id, skip, PROMPT_HISTORY_MESSAGES).await?;
+
+            // Pinned messages outside the recent window come first
+            let mut history: Vec<_> = history_mgr
+                .pinned_messages(session_id)
+                .into_iter()
+                .filter(|pinned| !recent.iter().any(|msg| msg.id == pinned.id))
+                .collect();
+            history.extend(recent);
+            history
+        };
+
+        let (selected, context_degraded) = match self.retrieve_context(message).await {
+            Ok(retrieval) => (retrieval.selected, false),
+            Err(e) if self.context_failure_policy == ContextFailurePolicy::Degrade => {
+                let degraded = self.degraded_prompts.fetch_add(1, Ordering::Relaxed) + 1;
+                warn!(
+                    session_id = %session_id,
+                    error = %e,
+                    degraded_prompts = degraded,
+                    "Context retrieval failed, continuing with history only"
+                );
+                (Vec::new(), true)
+            }
+            Err(e) => return Err(e),
+        };
+        let retrieved = selected
+            .iter()
+            .map(|scored| scored.item.get_content())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut sources: Vec<ContextSource> = history
+            .iter()
+            .map(|msg| {
+                ContextSource::new(
+                    PromptSegment::History,
+                    Provenance::Message {
+                        message_id: msg.id.clone(),
+                        session_id: Some(session_id.to_string()),
+                    },
+                )
+            })
+            .collect();
+        sources.extend(selected.iter().map(|scored| ContextSource {
+            compressed: scored.item.compressed_content.is_some(),
+            ..ContextSource::new(PromptSegment::Retrieved, scored.item.provenance())
+        }));
+
+        Ok(PromptContext {
+            system: self.system_prompt.clone(),
+            history: self.build_context_from_history(&history),
+            retrieved,
+            message: message.to_string(),
+            sources,
+            context_degraded,
+        })
+    }
+
+    /// Estimate whether a new message fits the model's context window
+    ///
+    /// Tokens are counted with the session's model. Returns per-segment
+    /// token counts of the prompt
+    /// [`build_prompt_context`](Self::build_prompt_context) would produce,
+    /// e.g. to decide whether to compress history before sending.
+    pub async fn estimate_prompt(&self, session_id: &str, new_message: &str) -> Result<PromptEstimate> {
+        let model = {
+            let mut session_mgr = self.session_manager.write().await;
+            session_mgr
+                .get_session(session_id)
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?
+                .model
+                .clone()
+        };
+        let (_, tokenizer, context_window) = self.model_limits(model.as_deref());
+
+        let prompt = self.build_prompt_context(session_id, new_message).await?;
+        Ok(prompt.estimate_with(&tokenizer, context_window))
+    }
+
+    /// Retrieve context relevant to a query
+    ///
+    /// When a query expander is configured, the query is augmented with the
+    /// normalized values of its entities and their synonyms before scoring.
+    pub async fn retrieve_context(&self, query: &str) -> Result<RetrievalResult> {
+        let expanded = match &self.query_expander {
+            Some(expander) if expander.config().enabled => {
+                let entities = self
+                    .nlp_engine
+                    .extract_entities(query)
+                    .await
+                    .map_err(|e| ConversationError::NlpError(e.to_string()))?
+                    .into_iter()
+                    .map(|e| EntityTerm::new(e.original_text, e.normalized_value))
+                    .collect::<Vec<_>>();
+                expander.expand(query, &entities)
+            }
+            _ => ExpandedQuery::new(query),
+        };
+
+        self.context_engine
+            .retrieve_expanded(&expanded)
+            .await
+            .map_err(|e| ConversationError::ContextError(e.to_string()))
+    }
+
+    /// Prefetch context likely to be relevant to a session
+    ///
+    /// Retrieves context for the session's metadata values and first user
+    /// message, and promotes it into the engine's short-term tier so the
+    /// session's first retrieval finds it warm. Retrieval stays within the
+    /// engine's token budget, and promotion stops at the short-term tier's
+    /// capacity as the engine reports it, configured caps included.
+    /// [`start_session`](Self::start_session) runs it automatically.
+    ///
+    /// Warmup runs in the background and is best-effort: failures are
+    /// logged and reported, never raised, and the session can be used
+    /// while it runs. Only an unknown session is an error.
+    pub async fn warmup(&self, session_id: &str) -> Result<JoinHandle<WarmupReport>> {
+        let metadata = {
+            let mut session_mgr = self.session_manager.write().await;
+            let session = session_mgr
+                .get_session(session_id)
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+            session.metadata.clone()
+        };
+        let first_message = self
+            .history_manager
+            .read()
+            .await
+            .get_all_messages(session_id)
+            .await?
+            .into_iter()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| m.content);
+
+        // Metadata values in key order keep the query stable
+        let mut terms: Vec<(String, String)> = metadata.into_iter().collect();
+        terms.sort();
+        let query = terms
+            .into_iter()
+            .map(|(_, value)| value)
+            .chain(first_message)
+            .filter(|term| !term.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let context_engine = Arc::clone(&self.context_engine);
+        let session_id = session_id.to_string();
+        Ok(tokio::spawn(async move {
+            let mut report = WarmupReport {
+                query,
+                ..Default::default()
+            };
+            if report.query.is_empty() {
+                return report;
+            }
+            if let Err(e) = prefetch(context_engine.as_ref(), &mut report).await {
+                warn!(session_id = %session_id, error = %e, "Context warmup failed");
+                report.error = Some(e.to_string());
+            }
+            debug!(
+                session_id = %session_id,
+                promoted = report.items_promoted,
+                warm = report.items_warm,
+                skipped = report.items_skipped,
+                "Context warmup finished"
+            );
+            report
+        }))
+    }
+
+    /// Screen a turn's message and attachment text with the safety
+    /// classifier
+    ///
+    /// The reasons a flagged turn was flagged are recorded in the request's
+    /// metadata under [`SAFETY_FLAG_KEY`]. Fails with
+    /// [`ConversationError::MessageBlocked`] if any part was blocked.
+    async fn screen_turn(
+        &self,
+        request: &mut MessageRequest,
+        attachments: &[ProcessedAttachment],
+    ) -> Result<()> {
+        let texts = std::iter::once(request.message.as_str())
+            .chain(attachments.iter().map(|a| a.text.as_str()));
+        let mut reasons: Vec<String> = Vec::new();
+        for text in texts {
+            if let Some(reason) = self.screen_message(text).await? {
+                if !reasons.contains(&reason) {
+                    reasons.push(reason);
+                }
+            }
+        }
+        if !reasons.is_empty() {
+            request.metadata.insert(SAFETY_FLAG_KEY.to_string(), reasons.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Screen a message with the safety classifier
+    ///
+    /// Returns the reason the message was flagged, if it was, and
+    /// [`ConversationError::MessageBlocked`] if it was blocked.
+    async fn screen_message(&self, message: &str) -> Result<Option<String>> {
+        let verdict = match self.safety_classifier.classify(message).await {
+            Ok(verdict) => verdict,
+            Err(e) => {
+                warn!("Safety classification failed: {}", e);
+                match self.safety_failure_policy {
+                    SafetyFailurePolicy::Block => SafetyVerdict::Block {
+                        reason: format!("safety classification failed: {}", e),
+                    },
+                    SafetyFailurePolicy::Allow => SafetyVerdict::Allow,
+                }
+            }
+        };
+
+        match verdict {
+            SafetyVerdict::Allow => Ok(None),
+            SafetyVerdict::Flag { reason } => {
+                info!("Message flagged: {}", reason);
+                Ok(Some(reason))
+            }
+            SafetyVerdict::Block { reason } => {
+                info!("Message blocked: {}", reason);
+                Err(ConversationError::MessageBlocked { reason })
+            }
+        }
     }
 
     /// Create a streaming response
     ///
+    /// The message and the text of its attachments are screened as in
+    /// [`process_message`](Self::process_message). If the request was
+    /// flagged, the stream's final chunk carries the reason under
+    /// [`SAFETY_FLAG_KEY`]. With auto-summarize enabled, a session the
+    /// message takes past the threshold is summarized before the stream
+    /// starts.
+    ///
     /// # Arguments
     ///
     /// * `request` - The message request to process
     pub async fn create_streaming_response(
         &self,
-        request: MessageRequest,
+        mut request: MessageRequest,
     ) -> Result<StreamingResponse> {
         info!("Creating streaming response for session: {}", request.session_id);
+        request.message = self.normalization.normalize(&request.message);
 
-        // Validate session exists
-        {
+        // Validate session exists and the user may write to it
+        let model = {
             let mut session_mgr = self.session_manager.write().await;
-            let _session = session_mgr
+            let session = session_mgr
                 .get_session(&request.session_id)
                 .ok_or_else(|| ConversationError::SessionNotFound(request.session_id.clone()))?;
+            session.check_write(request.user_id.as_deref())?;
+            session.model.clone()
+        };
+        let attachments = self.attachment_processor.process_all(&request.attachments).await;
+        self.screen_turn(&mut request, &attachments).await?;
+
+        // Make room for the message before the stream starts
+        {
+            let (_, tokenizer, _) = self.model_limits(model.as_deref());
+            let _guard = self.lock_session(&request.session_id).await?;
+            self.summarize_if_needed(&request.session_id, tokenizer.count(&request.message), &tokenizer)
+                .await?;
         }
 
         // Create streaming response
-        let streaming_response = StreamingResponse::new(
+        let mut streaming_response = StreamingResponse::new(
             request.session_id.clone(),
             Arc::clone(&self.nlp_engine),
             Arc::clone(&self.context_engine),
             Arc::clone(&self.history_manager),
-        );
+        )
+        .with_resumable(Arc::clone(&self.resumable_streams));
+        if let Some(reason) = request.metadata.get(SAFETY_FLAG_KEY) {
+            streaming_response = streaming_response.with_metadata(SAFETY_FLAG_KEY, reason.clone());
+        }
 
         Ok(streaming_response)
     }
 
-    /// Resolve references in a message
+    /// Replay a stream after the last chunk a reconnecting client received
     ///
-    /// Handles pronouns and references like "it", "that service", "the previous one"
-    async fn resolve_references(
+    /// Works for any stream created by
+    /// [`create_streaming_response`](Self::create_streaming_response),
+    /// found by the stream ID in the token. Yields what the stream has
+    /// emitted so far. Fails with [`ConversationError::InvalidResumeToken`]
+    /// if the stream has expired or chunks after the token were already
+    /// dropped from its buffer.
+    pub async fn resume_stream(
         &self,
-        session_id: &str,
-        message: &str,
-    ) -> Result<Vec<ResolvedReference>> {
-        let mut resolved = Vec::new();
+        token: ResumeToken,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
+        let chunks = self.resumable_streams.resume_after(&token).await?;
+        debug!("Replaying {} chunks of stream {}", chunks.len(), token.stream_id);
+        Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))))
+    }
 
-        // Get recent history for reference resolution
+    /// Export a session's history, optionally followed by its timeline
+    ///
+    /// JSON exports become an object with `messages` and `timeline` fields
+    /// when the timeline is included; other formats append a timeline
+    /// section.
+    pub async fn export_session(
+        &self,
+        session_id: &str,
+        format: ExportFormat,
+        include_timeline: bool,
+    ) -> Result<String> {
+        let timeline = {
+            let session_mgr = self.session_manager.read().await;
+            session_mgr
+                .timeline(session_id)
+                .cloned()
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?
+        };
         let history_mgr = self.history_manager.read().await;
-        let history = history_mgr.get_history(session_id, 0, 5).await?;
-        drop(history_mgr);
-
-        // Simple reference patterns (in production, use NLP engine)
-        let reference_patterns = vec![
-            ("it", "the previously mentioned item"),
-            ("that", "the referenced item"),
-            ("this", "the current item"),
-            ("they", "the mentioned items"),
-            ("that service", "the service discussed earlier"),
-            ("the previous", "the previous item"),
-        ];
 
-        for (pattern, default_ref) in reference_patterns {
-            if message.to_lowercase().contains(pattern) {
-                // Try to find actual reference in history
-                let refers_to = self.find_reference_in_history(&history, pattern)
-                    .unwrap_or_else(|| default_ref.to_string());
+        if !include_timeline {
+            return history_mgr.export_history(session_id, format).await;
+        }
 
-                resolved.push(ResolvedReference {
-                    reference: pattern.to_string(),
-                    refers_to,
-                    confidence: 0.75, // Would be calculated by NLP engine
+        match format {
+            ExportFormat::Json => {
+                let export = serde_json::json!({
+                    "messages": history_mgr.annotated_messages(session_id),
+                    "timeline": timeline.events().collect::<Vec<_>>(),
                 });
+                Ok(serde_json::to_string_pretty(&export)?)
+            }
+            _ => {
+                let history = history_mgr.export_history(session_id, format).await?;
+                Ok(format!("{}\n{}", history, timeline.export(format)?))
             }
         }
-
-        Ok(resolved)
     }
 
-    /// Enhance message with resolved references
-    fn enhance_message_with_references(
+    /// Start a session and warm up its context
+    ///
+    /// Creates the session, owned by `user_id` and counted against their
+    /// conversation quota if given, sets its metadata and starts
+    /// [`warmup`](Self::warmup) in the background. The session can be used
+    /// straight away; the returned handle reports what warmup did.
+    pub async fn start_session(
+        &self,
+        user_id: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<(Session, JoinHandle<WarmupReport>)> {
+        let session = {
+            let mut session_mgr = self.session_manager.write().await;
+            let session = match user_id {
+                Some(user_id) => session_mgr.create_user_session(user_id, None)?,
+                None => session_mgr.create_session(None),
+            };
+            for (key, value) in &metadata {
+                session_mgr.set_metadata(&session.id, key, value)?;
+            }
+            session_mgr.get_session(&session.id).cloned().unwrap_or(session)
+        };
+        let warmup = self.warmup(&session.id).await?;
+        Ok((session, warmup))
+    }
+
+    /// Get a session the user may read
+    pub async fn get_session(&self, session_id: &str, user_id: Option<&str>) -> Result<Session> {
+        let mut session_mgr = self.session_manager.write().await;
+        let session = session_mgr
+            .get_session(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        session.check_read(user_id)?;
+        Ok(session.clone())
+    }
+
+    /// Fail unless the session exists and the user may read and write it
+    fn check_writable(
+        session_mgr: &mut SessionManager,
+        session_id: &str,
+        user_id: Option<&str>,
+    ) -> Result<()> {
+        let session = session_mgr
+            .get_session(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        session.check_read(user_id)?;
+        session.check_write(user_id)
+    }
+
+    /// Delete a session and its history
+    ///
+    /// Only a user who may write to the session may delete it.
+    pub async fn delete_session(&self, session_id: &str, user_id: Option<&str>) -> Result<Session> {
+        let _guard = self.lock_session(session_id).await?;
+
+        let session = {
+            let mut session_mgr = self.session_manager.write().await;
+            Self::check_writable(&mut session_mgr, session_id, user_id)?;
+            session_mgr
+                .delete_session(session_id)
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?
+        };
+        self.forget_session(session_id).await;
+        info!("Deleted session {}", session_id);
+        Ok(session)
+    }
+
+    /// Remove expired sessions along with their history, checkpoints, query
+    /// state and remembered entities
+    ///
+    /// Returns the number of sessions removed.
+    pub async fn cleanup_expired(&self) -> usize {
+        let expired = self.session_manager.write().await.remove_expired();
+        for session_id in &expired {
+            self.forget_session(session_id).await;
+        }
+        expired.len()
+    }
+
+    /// Drop everything kept for a session that no longer exists
+    async fn forget_session(&self, session_id: &str) {
+        self.history_manager.write().await.clear_history(session_id);
+        self.checkpoints.write().await.clear_session(session_id);
+        self.query_states.write().await.remove(session_id);
+        self.entity_memory.write().await.forget(session_id);
+    }
+
+    /// Fork a session into a new branch
+    ///
+    /// The branch starts with the parent's history up to and including
+    /// `at_message_id`, or the whole history if `None`, and continues
+    /// independently of the parent from there.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session to fork
+    /// * `at_message_id` - The last message carried into the branch
+    /// * `user_id` - The user forking, who must be allowed to write to the
+    ///   session
+    pub async fn fork_session(
+        &self,
+        session_id: &str,
+        at_message_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> Result<Session> {
+        let _guard = self.lock_session(session_id).await?;
+        let mut session_mgr = self.session_manager.write().await;
+        Self::check_writable(&mut session_mgr, session_id, user_id)?;
+
+        let message_id = {
+            let history_mgr = self.history_manager.read().await;
+            match at_message_id {
+                Some(id) => {
+                    let known = history_mgr
+                        .get_all_messages(session_id)
+                        .await?
+                        .iter()
+                        .any(|msg| msg.id == id);
+                    if !known {
+                        return Err(ConversationError::HistoryError(format!(
+                            "Message {} not found in session {}",
+                            id, session_id
+                        )));
+                    }
+                    Some(id.to_string())
+                }
+                None => history_mgr.last_message_id(session_id),
+            }
+        };
+
+        let branch = session_mgr.fork_session(session_id, message_id.clone())?;
+
+        if let Some(message_id) = &message_id {
+            let mut history_mgr = self.history_manager.write().await;
+            let copied = history_mgr.fork_history(session_id, &branch.id, message_id)?;
+            let tokens = history_mgr.statistics(&branch.id).total_tokens;
+            drop(history_mgr);
+
+            session_mgr.update_session(&branch.id, tokens).await?;
+            session_mgr.record_messages(&branch.id, copied, 0)?;
+        }
+
+        info!("Forked session {} into branch {}", session_id, branch.id);
+        session_mgr
+            .get_session(&branch.id)
+            .cloned()
+            .ok_or_else(|| ConversationError::SessionNotFound(branch.id.clone()))
+    }
+
+    /// Bookmark the current state of a session
+    ///
+    /// Snapshots the session's messages and metadata under `label`. If the
+    /// session already has the maximum number of checkpoints, its oldest is
+    /// dropped.
+    pub async fn checkpoint(&self, session_id: &str, label: &str) -> Result<CheckpointId> {
+        let _guard = self.lock_session(session_id).await?;
+
+        let metadata = {
+            let mut session_mgr = self.session_manager.write().await;
+            session_mgr
+                .get_session(session_id)
+                .map(|s| s.metadata.clone())
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?
+        };
+        let messages = self
+            .history_manager
+            .read()
+            .await
+            .get_all_messages(session_id)
+            .await?;
+
+        let checkpoint = Checkpoint::new(session_id, label, messages, metadata);
+        let id = checkpoint.id.clone();
+        if let Some(evicted) = self.checkpoints.write().await.insert(checkpoint) {
+            debug!("Dropped oldest checkpoint {} of session {}", evicted.id, session_id);
+        }
+
+        info!("Created checkpoint {} ('{}') for session {}", id, label, session_id);
+        Ok(id)
+    }
+
+    /// Rewind a session to a checkpoint
+    ///
+    /// The session's messages and metadata are replaced by the checkpoint's
+    /// snapshot, discarding everything since. The checkpoint holds its own
+    /// copy of the messages, so it can be restored even after the history
+    /// was pruned or edited. Tokens and quota already consumed are not
+    /// refunded. The checkpoint remains available for later restores.
+    ///
+    /// Returns the number of messages in the restored history.
+    pub async fn restore_checkpoint(
+        &self,
+        session_id: &str,
+        checkpoint_id: &CheckpointId,
+    ) -> Result<usize> {
+        let _guard = self.lock_session(session_id).await?;
+
+        let checkpoint = self
+            .checkpoints
+            .read()
+            .await
+            .get(session_id, checkpoint_id)
+            .cloned()
+            .ok_or_else(|| ConversationError::CheckpointNotFound(checkpoint_id.to_string()))?;
+
+        let mut session_mgr = self.session_manager.write().await;
+        let session = session_mgr
+            .get_session_mut(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+        session.metadata = checkpoint.metadata;
+        session.total_tokens = checkpoint.messages.iter().map(|m| m.token_count).sum();
+        session.touch();
+        session_mgr.record_event(
+            session_id,
+            SessionEventKind::CheckpointRestored {
+                checkpoint_id: checkpoint_id.to_string(),
+                label: checkpoint.label.clone(),
+            },
+        )?;
+
+        let restored = checkpoint.messages.len();
+        let discarded = self
+            .history_manager
+            .write()
+            .await
+            .replace_history(session_id, checkpoint.messages);
+
+        info!(
+            "Restored session {} to checkpoint '{}' ({} messages, {} before restore)",
+            session_id, checkpoint.label, restored, discarded
+        );
+        Ok(restored)
+    }
+
+    /// List a session's checkpoints, oldest first
+    pub async fn list_checkpoints(&self, session_id: &str) -> Vec<Checkpoint> {
+        self.checkpoints
+            .read()
+            .await
+            .list(session_id)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Store a message of a session in the context engine
+    ///
+    /// The importance is inferred by the context engine from the message's
+    /// role, age, length and the number of entities the NLP engine finds in
+    /// it, so callers need not score messages themselves. The stored item
+    /// cites the message as its provenance.
+    pub async fn remember_message(&self, session_id: &str, message_id: &str) -> Result<uuid::Uuid> {
+        let message = self
+            .history_manager
+            .read()
+            .await
+            .get_all_messages(session_id)
+            .await?
+            .into_iter()
+            .find(|msg| msg.id == message_id)
+            .ok_or_else(|| {
+                ConversationError::HistoryError(format!("Message not found: {}", message_id))
+            })?;
+
+        let entities = self
+            .nlp_engine
+            .extract_entities(&message.content)
+            .await
+            .map_err(|e| ConversationError::NlpError(e.to_string()))?;
+
+        let (role, source) = match message.role {
+            MessageRole::User => ("user", "user_input"),
+            MessageRole::Assistant => ("assistant", "llm_output"),
+            MessageRole::System => ("system", "system"),
+        };
+        let mut metadata = MemoryMetadata::new("conversation", source).with_provenance(
+            Provenance::Message {
+                message_id: message.id.clone(),
+                session_id: Some(session_id.to_string()),
+            },
+        );
+        metadata.add_custom(ROLE_KEY.to_string(), serde_json::json!(role));
+        metadata.add_custom(ENTITY_COUNT_KEY.to_string(), serde_json::json!(entities.len()));
+        metadata.add_custom(
+            TIMESTAMP_KEY.to_string(),
+            serde_json::json!(message.timestamp.to_rfc3339()),
+        );
+
+        self.context_engine
+            .store_inferred(message.content, metadata)
+            .await
+            .map_err(|e| ConversationError::ContextError(e.to_string()))
+    }
+
+    /// Search all of a user's sessions
+    ///
+    /// Hits from every session the user owns or may read (e.g. after
+    /// handing it over to someone else) are ranked together by
+    /// relevance; the query limit applies to the combined results. Each hit
+    /// carries its session ID and the surrounding messages.
+    pub async fn search_all(&self, user_id: &str, query: SearchQuery) -> Result<Vec<SessionSearchHit>> {
+        info!("Searching all sessions of user {}: {}", user_id, query.query);
+
+        let session_ids: Vec<String> = self
+            .session_manager
+            .read()
+            .await
+            .readable_sessions(user_id)
+            .into_iter()
+            .map(|session| session.id.clone())
+            .collect();
+
+        let hits = self
+            .history_manager
+            .read()
+            .await
+            .search_sessions(session_ids.iter().map(String::as_str), &query);
+
+        debug!("Found {} hits across {} sessions", hits.len(), session_ids.len());
+        Ok(hits)
+    }
+
+    /// Hand a session over to another user, e.g. from the bot to a human
+    /// agent
+    ///
+    /// See [`SessionManager::transfer`] for ownership and access. With
+    /// `summarize`, a system message summarizing the conversation so far is
+    /// added to the history for the new owner, charged to the session's
+    /// token budget.
+    pub async fn transfer_session(
+        &self,
+        session_id: &str,
+        new_owner: &str,
+        summarize: bool,
+    ) -> Result<SessionTransfer> {
+        let _guard = self.lock_session(session_id).await?;
+
+        let summary = if summarize {
+            let session = self
+                .session_manager
+                .write()
+                .await
+                .get_session(session_id)
+                .cloned()
+                .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?;
+            let summary = self.handoff_summary(&session, new_owner).await?;
+            let (_, tokenizer, _) = self.model_limits(session.model.as_deref());
+            let tokens = tokenizer.count(&summary);
+            session.check_tokens(tokens)?;
+            Some((summary, tokens))
+        } else {
+            None
+        };
+
+        let mut session_mgr = self.session_manager.write().await;
+        let transfer = session_mgr.transfer(session_id, new_owner)?;
+
+        if let Some((summary, tokens)) = summary {
+            self.history_manager.write().await.append_message(
+                session_id,
+                ConversationMessage {
+                    id: new_message_id(),
+                    role: MessageRole::System,
+                    content: summary,
+                    timestamp: chrono::Utc::now(),
+                    token_count: tokens,
+                    metadata: HashMap::from([("handoff".to_string(), new_owner.to_string())]),
+                    pinned: false,
+                },
+            ).await?;
+            session_mgr.update_session(session_id, tokens).await?;
+            session_mgr.record_messages(session_id, 1, 0)?;
+        }
+        Ok(transfer)
+    }
+
+    /// Summary of a session for the user taking it over
+    async fn handoff_summary(&self, session: &Session, new_owner: &str) -> Result<String> {
+        let messages = self
+            .history_manager
+            .read()
+            .await
+            .get_all_messages(&session.id)
+            .await?;
+
+        let mut summary = format!(
+            "Conversation handed over from {} to {}.",
+            session.user_id.as_deref().unwrap_or("an unassigned session"),
+            new_owner
+        );
+        summary.push_str(&format!(
+            " {} message(s) so far.",
+            messages.iter().filter(|m| m.role != MessageRole::System).count()
+        ));
+
+        let entities = self.entity_memory.read().await.entities(&session.id);
+        if !entities.is_empty() {
+            let established: Vec<String> = entities
+                .iter()
+                .map(|e| format!("{} {}", format!("{:?}", e.entity_type).to_lowercase(), e.value))
+                .collect();
+            summary.push_str(&format!(" Established: {}.", established.join(", ")));
+        }
+        if let Some(last) = messages.iter().rev().find(|m| m.role == MessageRole::User) {
+            summary.push_str(&format!(" Last user message: \"{}\"", last.content));
+        }
+        Ok(summary)
+    }
+
+    /// Build the fork tree rooted at a session
+    ///
+    /// Only session metadata and message counts are read, never message
+    /// content. Branches whose parent was deleted are reported as orphans.
+    pub async fn branch_tree(&self, root_session_id: &str) -> Result<BranchTree> {
+        let session_mgr = self.session_manager.read().await;
+        let history_mgr = self.history_manager.read().await;
+
+        let summaries = session_mgr
+            .branch_sessions(root_session_id)
+            .into_iter()
+            .map(|s| BranchSummary::from_session(s, history_mgr.message_count(&s.id)))
+            .collect();
+
+        BranchTree::build(root_session_id, summaries)
+            .ok_or_else(|| ConversationError::SessionNotFound(root_session_id.to_string()))
+    }
+
+    /// Detect references in a message
+    ///
+    /// Handles pronouns and references like "it", "that service", "the previous one"
+    async fn detect_references(
+        &self,
+        session_id: &str,
+        message: &str,
+    ) -> Result<Vec<ResolvedReference>> {
+        let mut resolved = Vec::new();
+
+        // Get recent history for reference resolution
+        let history_mgr = self.history_manager.read().await;
+        let history = history_mgr.get_history(session_id, 0, 5).await?;
+        drop(history_mgr);
+
+        // Simple reference patterns (in production, use NLP engine)
+        let reference_patterns = vec![
+            ("it", "the previously mentioned item"),
+            ("that", "the referenced item"),
+            ("this", "the current item"),
+            ("they", "the mentioned items"),
+            ("that service", "the service discussed earlier"),
+            ("the previous", "the previous item"),
+        ];
+
+        for (pattern, default_ref) in reference_patterns {
+            if message.to_lowercase().contains(pattern) {
+                // Try to find actual reference in history
+                let refers_to = self.find_reference_in_history(&history, pattern)
+                    .unwrap_or_else(|| default_ref.to_string());
+
+                resolved.push(ResolvedReference {
+                    reference: pattern.to_string(),
+                    refers_to,
+                    confidence: 0.75, // Would be calculated by NLP engine
+                });
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Entities mentioned in a message; extraction failures are logged
+    /// and treated as no mentions
+    async fn mentioned_entities(&self, message: &str) -> Vec<Entity> {
+        self.nlp_engine.extract_entities(message).await.unwrap_or_else(|e| {
+            warn!("Entity extraction failed: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Rewrite references in text with what they refer to
+    ///
+    /// Pronouns such as "it" and "its" and phrases such as "that service"
+    /// are replaced with the most recently mentioned matching entity in the
+    /// session's last few user and assistant messages, so "show its errors"
+    /// after a turn about auth-service becomes "show auth-service's
+    /// errors". "The previous query" is replaced with the user's previous
+    /// message, quoted. A typed phrase is left alone when the text itself
+    /// names an entity of that type, and any reference without an
+    /// antecedent is kept as written.
+    pub async fn resolve_references(&self, session_id: &str, text: &str) -> Result<String> {
+        let recent: Vec<ConversationMessage> = {
+            let history_mgr = self.history_manager.read().await;
+            let offset = history_mgr
+                .message_count(session_id)
+                .saturating_sub(REFERENCE_HISTORY_MESSAGES);
+            history_mgr.get_history(session_id, offset, REFERENCE_HISTORY_MESSAGES).await?
+        };
+        let recent: Vec<&ConversationMessage> = recent
+            .iter()
+            .rev()
+            .filter(|msg| matches!(msg.role, MessageRole::User | MessageRole::Assistant))
+            .collect();
+
+        // Entities of each message, most recent message first, and within a
+        // message the last mention first
+        let mut antecedents: Vec<Entity> = Vec::new();
+        for msg in &recent {
+            let mut entities = self.mentioned_entities(&msg.content).await;
+            entities.retain(|e| is_referent(&e.entity_type));
+            entities.sort_by_key(|e| std::cmp::Reverse(e.span.map(|(start, _)| start)));
+            antecedents.extend(entities);
+        }
+        let mentioned = self.mentioned_entities(text).await;
+
+        // Lowercasing ASCII only keeps byte offsets aligned with the text
+        let lower = text.to_ascii_lowercase();
+        let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+        let overlaps = |replacements: &[(usize, usize, String)], start: usize, end: usize| {
+            replacements.iter().any(|(s, e, _)| start < *e && *s < end)
+        };
+
+        if let Some(previous) = recent.iter().find(|msg| msg.role == MessageRole::User) {
+            for start in find_phrases(&lower, PREVIOUS_QUERY_PHRASE) {
+                let end = start + PREVIOUS_QUERY_PHRASE.len();
+                replacements.push((start, end, format!("\"{}\"", previous.content)));
+            }
+        }
+
+        for (phrase, entity_type) in REFERENCE_PHRASES {
+            let antecedent = match entity_type {
+                Some(entity_type) if mentioned.iter().any(|e| e.entity_type == *entity_type) => None,
+                Some(entity_type) => antecedents.iter().find(|e| e.entity_type == *entity_type),
+                None => antecedents.iter().find(|e| is_resource(&e.entity_type)),
+            };
+            let Some(antecedent) = antecedent else {
+                continue;
+            };
+            let replacement = if *phrase == "its" {
+                format!("{}'s", antecedent.value)
+            } else {
+                antecedent.value.clone()
+            };
+            for start in find_phrases(&lower, phrase) {
+                let end = start + phrase.len();
+                if !overlaps(&replacements, start, end) {
+                    replacements.push((start, end, replacement.clone()));
+                }
+            }
+        }
+
+        replacements.sort_by_key(|(start, ..)| std::cmp::Reverse(*start));
+        let mut resolved = text.to_string();
+        for (start, end, replacement) in replacements {
+            resolved.replace_range(start..end, &replacement);
+        }
+        Ok(resolved)
+    }
+
+    /// Entity values a session has established, most recently mentioned
+    /// first
+    pub async fn remembered_entities(&self, session_id: &str) -> Vec<RememberedEntity> {
+        self.entity_memory.read().await.entities(session_id)
+    }
+
+    /// Enhance message with resolved references
+    fn enhance_message_with_references(
         &self,
         message: &str,
         references: &[ResolvedReference],
@@ -317,16 +1736,67 @@ impl ConversationManager {
     fn build_context_from_history(&self, history: &[ConversationMessage]) -> String {
         history
             .iter()
-            .map(|msg| format!("{:?}: {}", msg.role, msg.content))
+            .map(|msg| {
+                let pin = if msg.pinned { "[pinned] " } else { "" };
+                format!("{}{:?}: {}", pin, msg.role, msg.content)
+            })
             .collect::<Vec<_>>()
             .join("\n")
     }
 
-    /// Estimate token count for a message
-    fn estimate_tokens(&self, text: &str) -> usize {
-        // Simple estimation: ~4 characters per token
-        // In production, use proper tokenizer
-        (text.len() / 4).max(1)
+    /// Profile, tokenizer and context window of a session's model
+    ///
+    /// Sessions on the default model use the manager's context window.
+    fn model_limits(&self, model: Option<&str>) -> (ModelProfile, ModelTokenizer, usize) {
+        let profile = self.models.profile(model).clone();
+        let tokenizer = self.models.tokenizer(Some(&profile.name));
+        let context_window = if profile.name == DEFAULT_MODEL {
+            self.context_window
+        } else {
+            profile.context_window
+        };
+        (profile, tokenizer, context_window)
+    }
+
+    /// Switch the model a session talks to
+    ///
+    /// The session's history is recounted with the new model's tokenizer
+    /// and its token usage adjusted by the difference; messages already
+    /// trimmed from history keep the count they were charged at. Unknown
+    /// models fall back to the default model. Returns the profile of the
+    /// model the session now uses.
+    pub async fn set_session_model(&self, session_id: &str, model: &str) -> Result<ModelProfile> {
+        let _guard = self.lock_session(session_id).await?;
+        if !self.models.contains(model) {
+            warn!(session_id = %session_id, model = %model, "Unknown model, using the default model");
+        }
+        let (profile, tokenizer, _) = self.model_limits(Some(model));
+
+        let (before, after) = self
+            .history_manager
+            .write()
+            .await
+            .recount_tokens(session_id, |text| tokenizer.count(text));
+
+        let mut session_mgr = self.session_manager.write().await;
+        let total_tokens = session_mgr
+            .get_session(session_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(session_id.to_string()))?
+            .total_tokens
+            .saturating_sub(before)
+            + after;
+        session_mgr.set_model(session_id, &profile.name, total_tokens)?;
+        Ok(profile)
+    }
+
+    /// Get context engine
+    pub fn context_engine(&self) -> Arc<dyn ContextEngine> {
+        Arc::clone(&self.context_engine)
+    }
+
+    /// Model context window in tokens
+    pub fn context_window(&self) -> usize {
+        self.context_window
     }
 
     /// Get session manager
@@ -340,17 +1810,1413 @@ impl ConversationManager {
     }
 }
 
+/// Index of the first message of the last `turns` turns
+///
+/// A turn starts at a user message and runs until the next one, so turns
+/// are counted by role rather than assuming user and assistant messages
+/// alternate. Returns 0 if the messages hold no more than `turns` turns.
+fn recent_turns_start(messages: &[ConversationMessage], turns: usize) -> usize {
+    if turns == 0 {
+        return messages.len();
+    }
+    messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, msg)| msg.role == MessageRole::User)
+        .nth(turns - 1)
+        .map_or(0, |(idx, _)| idx)
+}
+
+/// Whether references such as "that metric" or "it" can refer to
+/// entities of a type
+fn is_referent(entity_type: &EntityType) -> bool {
+    is_resource(entity_type) || *entity_type == EntityType::Metric
+}
+
+/// Whether entities of a type are services or resources, the only
+/// antecedents of pronouns such as "it"
+fn is_resource(entity_type: &EntityType) -> bool {
+    matches!(
+        entity_type,
+        EntityType::Service
+            | EntityType::Host
+            | EntityType::Pod
+            | EntityType::Deployment
+            | EntityType::Container
+            | EntityType::Endpoint
+            | EntityType::Namespace
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::{ModelPricing, ModelProfile, ModelRegistry};
+    use crate::prompt::estimate_tokens;
+    use crate::session::{QuotaKind, ResourceQuota, SessionConfig};
+    use copilot_context::engine::{CompressionStats, EngineStats, MaintenanceReport};
+    use copilot_context::retrieval::ScoredItem;
+    use futures::StreamExt;
+    use copilot_context::{
+        ContextEngineConfig, ContextEngineImpl, ContextError, MemoryItem, MemoryMetadata, MemoryTier,
+        QueryExpansionConfig, SynonymDictionary,
+    };
+    use uuid::Uuid;
+    use copilot_nlp::NlpEngineImpl;
+
+    fn create_test_manager() -> ConversationManager {
+        let context_engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        ConversationManager::new(Arc::new(NlpEngineImpl::default()), Arc::new(context_engine))
+    }
+
+    fn create_request(session_id: &str, message: &str) -> MessageRequest {
+        MessageRequest {
+            session_id: session_id.to_string(),
+            message: message.to_string(),
+            metadata: std::collections::HashMap::new(),
+            attachments: Vec::new(),
+            user_id: None,
+        }
+    }
 
     #[tokio::test]
     async fn test_reference_resolution() {
-        // Test would go here
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        // Nothing to refer back to yet
+        let resolved = manager.resolve_references(&id, "show its errors").await.unwrap();
+        assert_eq!(resolved, "show its errors");
+
+        manager
+            .process_message(create_request(&id, "Why is auth-service slow?"))
+            .await
+            .unwrap();
+        let resolved = manager.resolve_references(&id, "show its errors").await.unwrap();
+        assert_eq!(resolved, "show auth-service's errors");
+        let resolved = manager
+            .resolve_references(&id, "Is that service behind the previous query?")
+            .await
+            .unwrap();
+        assert_eq!(resolved, "Is auth-service behind \"Why is auth-service slow?\"?");
+
+        // A text naming its own service keeps "that service" as written
+        let resolved = manager
+            .resolve_references(&id, "compare payment-service with that service")
+            .await
+            .unwrap();
+        assert_eq!(resolved, "compare payment-service with that service");
+    }
+
+    #[tokio::test]
+    async fn test_pronouns_skip_metric_antecedents() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        manager
+            .process_message(create_request(&id, "Why is auth-service slow?"))
+            .await
+            .unwrap();
+        manager
+            .process_message(create_request(&id, "Show the error rate"))
+            .await
+            .unwrap();
+
+        // The metric is the most recent entity, but "it" means the service
+        let resolved = manager.resolve_references(&id, "why is it failing").await.unwrap();
+        assert_eq!(resolved, "why is auth-service failing");
+        let resolved = manager.resolve_references(&id, "plot that metric").await.unwrap();
+        assert_eq!(resolved, "plot error rate");
+    }
+
+    /// Summarizes by counting the messages it was given
+    struct CountingSummarizer;
+
+    #[async_trait]
+    impl Summarizer for CountingSummarizer {
+        async fn summarize(&self, messages: &[ConversationMessage]) -> Result<String> {
+            Ok(format!("Summary of {} earlier messages", messages.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auto_summarize_keeps_recent_turns() {
+        let manager = create_test_manager()
+            .enable_auto_summarize(0.5, Arc::new(CountingSummarizer))
+            .with_summary_keep_turns(1);
+        let id = manager.session_manager.write().await.create_session(Some(400)).id;
+
+        let mut summaries = Vec::new();
+        for i in 0..12 {
+            let message = format!("Turn {} asks about checkout-service latency", i);
+            manager.process_message(create_request(&id, &message)).await.unwrap();
+
+            let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+            let session = manager.session_manager.write().await.get_session(&id).unwrap().clone();
+            assert!(session.total_tokens < 200, "turn {}: {} tokens", i, session.total_tokens);
+            assert_eq!(session.total_tokens, history.iter().map(|m| m.token_count).sum::<usize>());
+
+            // Summarized during this turn
+            if history[0].role == MessageRole::System && !summaries.contains(&history[0].id) {
+                summaries.push(history[0].id.clone());
+                assert!(history[0].content.starts_with("Summary of"));
+                // The kept turn and the new one are verbatim
+                assert_eq!(history.len(), 5);
+                assert_eq!(history[1].content, format!("Turn {} asks about checkout-service latency", i - 1));
+                assert_eq!(history[3].content, message);
+            }
+        }
+        assert!(summaries.len() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_turn_summarizes_by_role() {
+        let manager = create_test_manager()
+            .enable_auto_summarize(0.5, Arc::new(CountingSummarizer))
+            .with_summary_keep_turns(1);
+        let id = manager.session_manager.write().await.create_session(Some(400)).id;
+
+        // Turns do not alternate: a repeated question, then a reply in two parts
+        let roles = [
+            MessageRole::User,
+            MessageRole::User,
+            MessageRole::Assistant,
+            MessageRole::User,
+            MessageRole::Assistant,
+            MessageRole::Assistant,
+        ];
+        for (i, role) in roles.into_iter().enumerate() {
+            manager
+                .history_manager
+                .write()
+                .await
+                .append_message(
+                    &id,
+                    ConversationMessage {
+                        id: new_message_id(),
+                        role,
+                        content: format!("message {}", i),
+                        timestamp: chrono::Utc::now(),
+                        token_count: 40,
+                        metadata: HashMap::new(),
+                        pinned: false,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        manager.session_manager.write().await.update_session(&id, 240).await.unwrap();
+
+        manager.create_streaming_response(create_request(&id, "and now?")).await.unwrap();
+
+        // The last turn is kept whole, from its question to both replies
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        let contents: Vec<&str> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            ["Summary of 3 earlier messages", "message 3", "message 4", "message 5"]
+        );
+        let session = manager.session_manager.write().await.get_session(&id).unwrap().clone();
+        assert_eq!(session.total_tokens, history.iter().map(|m| m.token_count).sum::<usize>());
     }
 
     #[tokio::test]
     async fn test_message_processing() {
         // Test would go here
     }
+
+    #[tokio::test]
+    async fn test_message_quota_checked_before_mutation() {
+        let manager = create_test_manager();
+        let session_id = {
+            let mut session_mgr = manager.session_manager.write().await;
+            let id = session_mgr.create_session(None).id;
+            session_mgr.get_session_mut(&id).unwrap().quota =
+                ResourceQuota::default().with_max_messages(3);
+            id
+        };
+
+        manager
+            .process_message(create_request(&session_id, "Show CPU usage for api"))
+            .await
+            .unwrap();
+
+        let err = manager
+            .process_message(create_request(&session_id, "And memory usage?"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ConversationError::QuotaExceeded { quota: QuotaKind::Messages, .. }
+        ));
+
+        // The rejected turn left no trace in history
+        assert_eq!(manager.history_manager.read().await.message_count(&session_id), 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_expansion_retrieves_synonym() {
+        let context_engine =
+            Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        context_engine
+            .store(
+                "Node memory pressure is high".to_string(),
+                MemoryMetadata::new("monitoring", "observation"),
+                0.5,
+            )
+            .await
+            .unwrap();
+
+        let nlp_engine = Arc::new(NlpEngineImpl::default());
+        let plain = ConversationManager::new(nlp_engine.clone(), context_engine.clone());
+        assert!(plain.retrieve_context("RAM usage").await.unwrap().selected.is_empty());
+
+        let expanding = ConversationManager::new(nlp_engine, context_engine)
+            .with_query_expander(QueryExpander::new(
+                QueryExpansionConfig {
+                    enabled: true,
+                    ..Default::default()
+                },
+                SynonymDictionary::default(),
+            ));
+        let result = expanding.retrieve_context("RAM usage").await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert!(result.selected[0].item.get_content().contains("memory"));
+        assert_eq!(result.expansion.unwrap().recovered_items, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_turns_on_one_session_are_serialized() {
+        let manager = Arc::new(create_test_manager());
+        let session_id = manager.session_manager.write().await.create_session(None).id;
+
+        let turns = (0..16).map(|i| {
+            let manager = Arc::clone(&manager);
+            let request = create_request(&session_id, &format!("Show CPU usage for service {}", i));
+            tokio::spawn(async move { manager.process_message(request).await })
+        });
+        let responses: Vec<MessageResponse> = futures::future::join_all(turns)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap().unwrap())
+            .collect();
+
+        let history = manager
+            .history_manager
+            .read()
+            .await
+            .get_history(&session_id, 0, 100)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 32);
+        for pair in history.chunks(2) {
+            assert_eq!(pair[0].role, MessageRole::User);
+            assert_eq!(pair[1].role, MessageRole::Assistant);
+        }
+
+        let mut session_mgr = manager.session_manager.write().await;
+        let session = session_mgr.get_session(&session_id).unwrap();
+        let tokens: usize = responses.iter().map(|r| r.tokens_used).sum();
+        let history_tokens: usize = history.iter().map(|m| m.token_count).sum();
+        assert_eq!(session.total_tokens, tokens);
+        assert_eq!(session.total_tokens, history_tokens);
+        assert_eq!(session.usage.messages, 32);
+    }
+
+    #[tokio::test]
+    async fn test_session_lock_does_not_block_other_sessions() {
+        let manager = create_test_manager();
+        let (locked, other) = {
+            let mut session_mgr = manager.session_manager.write().await;
+            (session_mgr.create_session(None).id, session_mgr.create_session(None).id)
+        };
+
+        let _guard = manager.lock_session(&locked).await.unwrap();
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            manager.process_message(create_request(&other, "Show memory usage")),
+        )
+        .await
+        .expect("turn on another session was blocked");
+        assert!(response.is_ok());
+    }
+
+    async fn message_ids(manager: &ConversationManager, session_id: &str) -> Vec<String> {
+        manager
+            .history_manager
+            .read()
+            .await
+            .get_all_messages(session_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_branch_tree_of_forked_sessions() {
+        let manager = create_test_manager();
+        let root = manager.session_manager.write().await.create_session(None).id;
+        for message in ["Show CPU usage", "Show memory usage"] {
+            manager.process_message(create_request(&root, message)).await.unwrap();
+        }
+        let root_ids = message_ids(&manager, &root).await;
+
+        // Branch after the first turn, and again from the end
+        let early = manager.fork_session(&root, Some(&root_ids[1]), None).await.unwrap();
+        let late = manager.fork_session(&root, None, None).await.unwrap();
+        manager.process_message(create_request(&early.id, "Show disk usage")).await.unwrap();
+        let nested = manager.fork_session(&early.id, None, None).await.unwrap();
+
+        let early_ids = message_ids(&manager, &early.id).await;
+        assert_eq!(early_ids.len(), 4);
+        assert_eq!(early_ids[..2], root_ids[..2]);
+        let root_history = manager.history_manager.read().await.get_all_messages(&root).await.unwrap();
+        let forked_tokens: usize = root_history[..2].iter().map(|m| m.token_count).sum();
+        assert_eq!(early.total_tokens, forked_tokens);
+        assert_eq!(early.usage.messages, 2);
+
+        let tree = manager.branch_tree(&root).await.unwrap();
+        assert_eq!(tree.root.session_id, root);
+        assert_eq!(tree.root.branch_point, None);
+        assert_eq!(tree.root.message_count, 4);
+        assert_eq!(tree.root.size(), 4);
+        assert!(tree.orphans.is_empty());
+
+        let children: Vec<_> = tree.root.children.iter().map(|c| c.session_id.clone()).collect();
+        assert_eq!(children, [early.id.clone(), late.id.clone()]);
+        assert_eq!(tree.root.children[0].branch_point.as_ref(), Some(&root_ids[1]));
+        assert_eq!(tree.root.children[1].branch_point.as_ref(), Some(&root_ids[3]));
+
+        let nested_node = tree.find(&nested.id).unwrap();
+        assert_eq!(nested_node.branch_point.as_ref(), Some(&early_ids[3]));
+        assert_eq!(nested_node.message_count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_branch_tree_reports_orphans() {
+        let manager = create_test_manager();
+        let root = manager.session_manager.write().await.create_session(None).id;
+        manager.process_message(create_request(&root, "Show CPU usage")).await.unwrap();
+        let child = manager.fork_session(&root, None, None).await.unwrap();
+        let grandchild = manager.fork_session(&child.id, None, None).await.unwrap();
+        assert_eq!(
+            grandchild.branch_origin.as_ref().unwrap().root_session_id,
+            root
+        );
+
+        manager.session_manager.write().await.delete_session(&child.id);
+
+        let tree = manager.branch_tree(&root).await.unwrap();
+        assert_eq!(tree.root.size(), 1);
+        assert_eq!(tree.orphans.len(), 1);
+        assert_eq!(tree.orphans[0].session_id, grandchild.id);
+    }
+
+    #[tokio::test]
+    async fn test_fork_at_unknown_message_fails() {
+        let manager = create_test_manager();
+        let root = manager.session_manager.write().await.create_session(None).id;
+
+        assert!(manager.fork_session(&root, Some("missing"), None).await.is_err());
+        assert_eq!(manager.session_manager.read().await.session_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_session_with_timeline() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+        manager.process_message(create_request(&id, "Show CPU usage")).await.unwrap();
+
+        let plain = manager.export_session(&id, ExportFormat::Markdown, false).await.unwrap();
+        assert!(!plain.contains("Session Timeline"));
+
+        let markdown = manager.export_session(&id, ExportFormat::Markdown, true).await.unwrap();
+        assert!(markdown.contains("# Session Timeline"));
+        assert!(markdown.contains("2 message(s) added"));
+
+        let json = manager.export_session(&id, ExportFormat::Json, true).await.unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(value["timeline"][0]["type"], "created");
+        assert_eq!(value["timeline"][1]["type"], "messages_added");
+    }
+
+    #[tokio::test]
+    async fn test_code_attachment_included_in_turn() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let mut request = create_request(&id, "Why does this panic?");
+        request.attachments.push(
+            MessageAttachment::new(crate::attachments::AttachmentType::Code, "let v: Vec<u8> = vec![];\nv[0];")
+                .with_filename("main.rs"),
+        );
+        // Unprocessable attachments are skipped rather than failing the turn
+        request.attachments.push(MessageAttachment::new(
+            crate::attachments::AttachmentType::Url,
+            "http://127.0.0.1/internal",
+        ));
+
+        let attachment_bytes: usize = request.attachments.iter().map(|a| a.content.len()).sum();
+
+        let response = manager.process_message(request).await.unwrap();
+        assert_eq!(response.attachments.len(), 1);
+        assert!(response.attachments[0].text.starts_with("```rust\n"));
+
+        let mut session_mgr = manager.session_manager.write().await;
+        let usage = &session_mgr.get_session(&id).unwrap().usage;
+        assert_eq!(usage.attachment_bytes, attachment_bytes);
+    }
+
+    #[tokio::test]
+    async fn test_messages_normalized_before_storage() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let response = manager
+            .process_message(create_request(&id, "Show CPU usage  \r\n\r\n\r\nfor api\r\n"))
+            .await
+            .unwrap();
+
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        assert_eq!(history[0].content, "Show CPU usage\n\nfor api");
+        assert_eq!(history[0].token_count, estimate_tokens("Show CPU usage\n\nfor api"));
+        assert_eq!(history[1].content, response.response);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_context_reports_provenance() {
+        let context_engine =
+            Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        context_engine
+            .store(
+                "Checkout latency runbook: scale the payment pool first".to_string(),
+                MemoryMetadata::new("document", "kb")
+                    .with_provenance(Provenance::document("runbook-7")),
+                0.5,
+            )
+            .await
+            .unwrap();
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let response = manager
+            .process_message(create_request(&id, "Where is the checkout latency runbook?"))
+            .await
+            .unwrap();
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+
+        let prompt = manager.build_prompt_context(&id, "checkout latency runbook").await.unwrap();
+        let sources: Vec<_> = prompt
+            .provenance()
+            .iter()
+            .map(|s| (s.segment, s.provenance.to_string()))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                (PromptSegment::History, format!("message:{}", history[0].id)),
+                (PromptSegment::History, format!("message:{}", history[1].id)),
+                (PromptSegment::Retrieved, "document:runbook-7".to_string()),
+            ]
+        );
+
+        // The turn reports the sources its response was generated from
+        assert_eq!(response.provenance.len(), 1);
+        assert_eq!(response.provenance[0].provenance, Provenance::document("runbook-7"));
+
+        // ...which are kept with the response and counted in the session's statistics
+        let history_mgr = manager.history_manager.read().await;
+        assert_eq!(history_mgr.sources(&id, &history[1].id), response.provenance);
+        let stats = history_mgr.statistics(&id);
+        assert_eq!(stats.context_sources.get("document"), Some(&1));
+        assert_eq!(stats.context_sources.get("message"), None);
+        assert_eq!(stats.compressed_context_sources, 0);
+    }
+
+    /// Context engine whose store is down
+    struct UnavailableContextEngine;
+
+    #[async_trait]
+    impl ContextEngine for UnavailableContextEngine {
+        async fn store(
+            &self,
+            _content: String,
+            _metadata: MemoryMetadata,
+            _importance: f64,
+        ) -> copilot_context::Result<Uuid> {
+            Err(unavailable())
+        }
+        async fn retrieve(&self, _query: &str) -> copilot_context::Result<RetrievalResult> {
+            Err(unavailable())
+        }
+        async fn compress(&self) -> copilot_context::Result<CompressionStats> {
+            Err(unavailable())
+        }
+        async fn stats(&self) -> copilot_context::Result<EngineStats> {
+            Err(unavailable())
+        }
+        async fn promote(&self, _id: &Uuid, _tier: MemoryTier) -> copilot_context::Result<()> {
+            Err(unavailable())
+        }
+        async fn demote(&self, _id: &Uuid, _tier: MemoryTier) -> copilot_context::Result<()> {
+            Err(unavailable())
+        }
+        async fn remove(&self, _id: &Uuid) -> copilot_context::Result<()> {
+            Err(unavailable())
+        }
+        async fn clear(&self) -> copilot_context::Result<()> {
+            Err(unavailable())
+        }
+        async fn maintenance(&self) -> copilot_context::Result<MaintenanceReport> {
+            Err(unavailable())
+        }
+    }
+
+    fn unavailable() -> ContextError {
+        ContextError::StorageError("connection refused".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_turn_completes_in_degraded_mode_without_context() {
+        let manager = ConversationManager::new(
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(UnavailableContextEngine),
+        );
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let first = manager
+            .process_message(create_request(&id, "Show CPU usage for api"))
+            .await
+            .unwrap();
+        let second = manager
+            .process_message(create_request(&id, "And memory?"))
+            .await
+            .unwrap();
+
+        assert!(first.context_degraded && second.context_degraded);
+        assert_eq!(manager.degraded_prompts(), 2);
+
+        // The second turn still saw the first one through history
+        assert_eq!(second.provenance.len(), 2);
+        assert!(second.provenance.iter().all(|s| s.segment == PromptSegment::History));
+
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[1].metadata.get("context_degraded").map(String::as_str), Some("true"));
+    }
+
+    #[tokio::test]
+    async fn test_context_failure_fails_turn_when_opted_in() {
+        let manager = ConversationManager::new(
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(UnavailableContextEngine),
+        )
+        .with_context_failure_policy(ContextFailurePolicy::Fail);
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let err = manager
+            .process_message(create_request(&id, "Show CPU usage for api"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ConversationError::ContextError(_)));
+        assert_eq!(manager.degraded_prompts(), 0);
+        assert_eq!(manager.history_manager.read().await.message_count(&id), 0);
+    }
+
+    #[tokio::test]
+    async fn test_remember_message_infers_importance() {
+        let context_engine =
+            Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        let manager =
+            ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine.clone());
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let message = "Show p99 latency for checkout-service in production over the last 1h";
+        manager.process_message(create_request(&id, message)).await.unwrap();
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        let message_id = history[0].id.clone();
+
+        manager.remember_message(&id, &message_id).await.unwrap();
+
+        let result = context_engine.retrieve("checkout-service latency").await.unwrap();
+        let item = &result.selected[0].item;
+        assert!(item.importance > 0.5 && item.importance <= 1.0);
+        assert_eq!(
+            item.provenance(),
+            Provenance::Message {
+                message_id,
+                session_id: Some(id.clone()),
+            }
+        );
+        assert!(manager.remember_message(&id, "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_checkpoint_after_diverging() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        manager.process_message(create_request(&id, "Show CPU usage")).await.unwrap();
+        manager.session_manager.write().await.set_metadata(&id, "env", "prod").unwrap();
+        let checkpoint = manager.checkpoint(&id, "before-experiment").await.unwrap();
+        let snapshot = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+
+        manager.process_message(create_request(&id, "Now try memory")).await.unwrap();
+        manager.session_manager.write().await.set_metadata(&id, "env", "staging").unwrap();
+        assert_eq!(manager.history_manager.read().await.message_count(&id), 4);
+        let diverged_tokens = manager.session_manager.write().await.get_session(&id).unwrap().total_tokens;
+
+        let restored = manager.restore_checkpoint(&id, &checkpoint).await.unwrap();
+        assert_eq!(restored, 2);
+
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        let ids: Vec<_> = history.iter().map(|m| m.id.as_str()).collect();
+        let expected: Vec<_> = snapshot.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, expected);
+
+        let mut session_mgr = manager.session_manager.write().await;
+        let session = session_mgr.get_session(&id).unwrap();
+        assert_eq!(session.metadata.get("env").map(String::as_str), Some("prod"));
+        assert_eq!(session.total_tokens, snapshot.iter().map(|m| m.token_count).sum::<usize>());
+        assert!(session.total_tokens < diverged_tokens);
+        assert!(matches!(
+            session.timeline.events().last().unwrap().kind,
+            SessionEventKind::CheckpointRestored { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_restore_checkpoint_after_pruning() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        manager.process_message(create_request(&id, "Show CPU usage")).await.unwrap();
+        let checkpoint = manager.checkpoint(&id, "start").await.unwrap();
+
+        manager.history_manager.write().await.clear_history(&id);
+        assert_eq!(manager.restore_checkpoint(&id, &checkpoint).await.unwrap(), 2);
+        assert_eq!(manager.history_manager.read().await.message_count(&id), 2);
+
+        // Restoring is repeatable
+        manager.process_message(create_request(&id, "And memory?")).await.unwrap();
+        assert_eq!(manager.restore_checkpoint(&id, &checkpoint).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_are_bounded() {
+        let manager = create_test_manager().with_checkpoint_limit(2);
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let first = manager.checkpoint(&id, "one").await.unwrap();
+        manager.checkpoint(&id, "two").await.unwrap();
+        manager.checkpoint(&id, "three").await.unwrap();
+
+        let labels: Vec<_> = manager
+            .list_checkpoints(&id)
+            .await
+            .into_iter()
+            .map(|c| c.label)
+            .collect();
+        assert_eq!(labels, ["two", "three"]);
+        assert!(matches!(
+            manager.restore_checkpoint(&id, &first).await,
+            Err(ConversationError::CheckpointNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_removed_with_their_session() {
+        let manager = create_test_manager();
+        *manager.session_manager.write().await = SessionManager::with_config(SessionConfig {
+            timeout_seconds: 0,
+            ..Default::default()
+        });
+        let deleted = manager.session_manager.write().await.create_session(None).id;
+        let expired = manager.session_manager.write().await.create_session(None).id;
+        manager.checkpoint(&deleted, "before").await.unwrap();
+        manager.checkpoint(&expired, "before").await.unwrap();
+
+        manager.delete_session(&deleted, None).await.unwrap();
+        assert!(manager.list_checkpoints(&deleted).await.is_empty());
+        assert_eq!(manager.list_checkpoints(&expired).await.len(), 1);
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert_eq!(manager.cleanup_expired().await, 1);
+        assert!(manager.list_checkpoints(&expired).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_prompt_matches_built_prompt() {
+        let context_engine =
+            Arc::new(ContextEngineImpl::new(ContextEngineConfig::default()).unwrap());
+        context_engine
+            .store(
+                "CPU usage on api spiked after the deploy".to_string(),
+                MemoryMetadata::new("monitoring", "observation"),
+                0.9,
+            )
+            .await
+            .unwrap();
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), context_engine);
+        let id = manager.session_manager.write().await.create_session(None).id;
+        manager.process_message(create_request(&id, "Show CPU usage for api")).await.unwrap();
+
+        let message = "Why did CPU usage spike?";
+        let estimate = manager.estimate_prompt(&id, message).await.unwrap();
+        let prompt = manager.build_prompt_context(&id, message).await.unwrap();
+
+        assert_eq!(estimate.total_tokens, prompt.token_count());
+        assert!(estimate.system_tokens > 0);
+        assert!(estimate.history_tokens > 0);
+        assert!(estimate.retrieved_tokens > 0);
+        // 24 characters at four per token
+        assert_eq!(estimate.message_tokens, 6);
+        assert!(estimate.fits);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_prompt_reports_overflow() {
+        let manager = create_test_manager().with_context_window(16);
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let estimate = manager.estimate_prompt(&id, &"word ".repeat(40)).await.unwrap();
+        assert!(!estimate.fits);
+        assert_eq!(estimate.overflow_tokens(), estimate.total_tokens - 16);
+
+        assert!(matches!(
+            manager.estimate_prompt("missing", "hi").await,
+            Err(ConversationError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_message_always_in_prompt_context() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+        for i in 0..6 {
+            let text = format!("Show CPU usage for service-{}", i);
+            manager.process_message(create_request(&id, &text)).await.unwrap();
+        }
+
+        // The recent window holds the last ten messages, so the first
+        // user message is only included once pinned
+        let (pinned, latest) = {
+            let history_mgr = manager.history_manager.read().await;
+            let messages = history_mgr.get_all_messages(&id).await.unwrap();
+            assert_eq!(messages.len(), 12);
+            (messages[0].clone(), messages[10].clone())
+        };
+        let prompt = manager.build_prompt_context(&id, "hi").await.unwrap();
+        assert!(!prompt.history.contains(&pinned.content));
+        assert!(prompt.history.contains(&latest.content));
+
+        manager.history_manager.write().await.pin(&id, &pinned.id).unwrap();
+        let prompt = manager.build_prompt_context(&id, "hi").await.unwrap();
+        assert!(prompt
+            .history
+            .starts_with(&format!("[pinned] User: {}", pinned.content)));
+        assert_eq!(prompt.history.matches("[pinned]").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refine_metrics_query_across_turns() {
+        use copilot_nlp::EntityType;
+
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+        let refine = |message: &'static str| manager.refine_query(&id, message, QueryLanguage::PromQL);
+
+        let first = refine("Show CPU usage for checkout-service in the last 1 hour").await.unwrap();
+        assert_eq!(first.kind, RefinementKind::NewQuery);
+        assert!(first.query.as_deref().unwrap().contains("checkout-service"));
+
+        let second = refine("now only production").await.unwrap();
+        assert_eq!(second.kind, RefinementKind::Refine);
+        let state = second.state.unwrap();
+        assert_eq!(state.turns, 2);
+        assert_eq!(state.entities_of(EntityType::Environment).count(), 1);
+        assert_eq!(state.entities_of(EntityType::Service).count(), 1);
+
+        let third = refine("make it p99 for the last 15 minutes, also payment-service").await.unwrap();
+        assert_eq!(third.kind, RefinementKind::Refine);
+        let state = third.state.unwrap();
+        let values = |entity_type| {
+            state
+                .entities_of(entity_type)
+                .map(|e| e.normalized_value.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(EntityType::TimeRange), ["15m"]);
+        assert_eq!(values(EntityType::Service), ["checkout-service", "payment-service"]);
+        assert_eq!(values(EntityType::Aggregation), ["percentile"]);
+        assert_eq!(values(EntityType::Metric), ["cpu"]);
+        let query = third.query.unwrap();
+        assert!(query.contains("payment-service") && query.contains("[15m]"), "{}", query);
+
+        let reset = refine("let's start over").await.unwrap();
+        assert_eq!(reset.kind, RefinementKind::Reset);
+        assert!(reset.state.is_none());
+        let fresh = refine("now only production").await.unwrap();
+        assert_eq!(fresh.kind, RefinementKind::NewQuery);
+        assert_eq!(fresh.state.unwrap().entities_of(EntityType::Service).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_query_state_removed_with_its_session() {
+        let manager = create_test_manager();
+        *manager.session_manager.write().await = SessionManager::with_config(SessionConfig {
+            timeout_seconds: 0,
+            ..Default::default()
+        });
+        let deleted = manager.session_manager.write().await.create_session(None).id;
+        let expired = manager.session_manager.write().await.create_session(None).id;
+        for id in [&deleted, &expired] {
+            manager
+                .refine_query(id, "Show CPU usage for checkout-service", QueryLanguage::PromQL)
+                .await
+                .unwrap();
+        }
+        assert_eq!(manager.query_states.read().await.len(), 2);
+
+        manager.delete_session(&deleted, None).await.unwrap();
+        assert!(!manager.query_states.read().await.contains_key(&deleted));
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        manager.cleanup_expired().await;
+        assert!(manager.query_states.read().await.is_empty());
+    }
+
+    fn multi_model_manager() -> ConversationManager {
+        let registry = ModelRegistry::new()
+            .register(
+                ModelProfile::new("gpt-4", 8192)
+                    .with_tiktoken("gpt-4")
+                    .with_pricing(ModelPricing::new(0.03, 0.06)),
+            )
+            .register(ModelProfile::new("compact", 2048).with_chars_per_token(2));
+        create_test_manager().with_model_registry(registry)
+    }
+
+    #[tokio::test]
+    async fn test_sessions_on_different_models_count_tokens_differently() {
+        let manager = multi_model_manager();
+        let (gpt, compact) = {
+            let mut session_mgr = manager.session_manager.write().await;
+            (session_mgr.create_session(None).id, session_mgr.create_session(None).id)
+        };
+        manager.set_session_model(&gpt, "gpt-4").await.unwrap();
+        manager.set_session_model(&compact, "compact").await.unwrap();
+
+        let message = "Show p99 latency for checkout-service over the last 15 minutes";
+        let gpt_turn = manager.process_message(create_request(&gpt, message)).await.unwrap();
+        let compact_turn = manager.process_message(create_request(&compact, message)).await.unwrap();
+
+        let gpt_history = manager.history_manager.read().await.get_all_messages(&gpt).await.unwrap();
+        let compact_history =
+            manager.history_manager.read().await.get_all_messages(&compact).await.unwrap();
+        assert_eq!(compact_history[0].token_count, message.len() / 2);
+        assert_ne!(gpt_history[0].token_count, compact_history[0].token_count);
+
+        assert_eq!(gpt_turn.model, "gpt-4");
+        assert!(gpt_turn.cost > 0.0);
+        assert_eq!(compact_turn.model, "compact");
+        assert_eq!(compact_turn.cost, 0.0);
+
+        // Prompts are sized against each model's window
+        let estimate = manager.estimate_prompt(&compact, message).await.unwrap();
+        assert_eq!(estimate.context_window, 2048);
+        assert_eq!(manager.estimate_prompt(&gpt, message).await.unwrap().context_window, 8192);
+    }
+
+    #[tokio::test]
+    async fn test_switching_models_recounts_usage() {
+        let manager = multi_model_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+        manager
+            .process_message(create_request(&id, "Why is checkout-service returning 503s?"))
+            .await
+            .unwrap();
+        let default_total = manager.session_manager.write().await.get_session(&id).unwrap().total_tokens;
+
+        let profile = manager.set_session_model(&id, "compact").await.unwrap();
+        assert_eq!(profile.name, "compact");
+
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        let recounted: usize = history.iter().map(|m| m.content.chars().count().div_ceil(2)).sum();
+        let session = manager.session_manager.write().await.get_session(&id).unwrap().clone();
+        assert_eq!(session.model.as_deref(), Some("compact"));
+        assert_eq!(session.total_tokens, recounted);
+        assert!(session.total_tokens > default_total);
+
+        // An unknown model falls back to the default one
+        let profile = manager.set_session_model(&id, "no-such-model").await.unwrap();
+        assert_eq!(profile.name, crate::model::DEFAULT_MODEL);
+        let session = manager.session_manager.write().await.get_session(&id).unwrap().clone();
+        assert_eq!(session.total_tokens, default_total);
+    }
+
+    /// Blocks messages containing "drop table", flags ones containing
+    /// "password", and fails on ones mentioning "classifier"
+    struct StubClassifier;
+
+    #[async_trait]
+    impl SafetyClassifier for StubClassifier {
+        async fn classify(&self, message: &str) -> Result<SafetyVerdict> {
+            let lower = message.to_lowercase();
+            if lower.contains("classifier") {
+                Err(ConversationError::GenerationError("classifier unavailable".to_string()))
+            } else if lower.contains("drop table") {
+                Ok(SafetyVerdict::Block {
+                    reason: "destructive sql".to_string(),
+                })
+            } else if lower.contains("password") {
+                Ok(SafetyVerdict::Flag {
+                    reason: "credentials".to_string(),
+                })
+            } else {
+                Ok(SafetyVerdict::Allow)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_safety_classifier_blocks_and_flags() {
+        let manager = create_test_manager().with_safety_classifier(Arc::new(StubClassifier));
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let blocked = manager
+            .process_message(create_request(&id, "DROP TABLE users; then show errors"))
+            .await;
+        assert!(matches!(
+            blocked,
+            Err(ConversationError::MessageBlocked { ref reason }) if reason == "destructive sql"
+        ));
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        assert!(history.is_empty());
+        assert_eq!(manager.session_manager.write().await.get_session(&id).unwrap().total_tokens, 0);
+
+        manager
+            .process_message(create_request(&id, "The db password rotated, is auth-service failing?"))
+            .await
+            .unwrap();
+        manager
+            .process_message(create_request(&id, "Show auth-service errors"))
+            .await
+            .unwrap();
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(
+            history[0].metadata.get(SAFETY_FLAG_KEY).map(String::as_str),
+            Some("credentials")
+        );
+        assert!(!history[2].metadata.contains_key(SAFETY_FLAG_KEY));
+    }
+
+    #[tokio::test]
+    async fn test_safety_classifier_failure_follows_policy() {
+        let id = |manager: &ConversationManager| {
+            let sessions = manager.session_manager();
+            async move { sessions.write().await.create_session(None).id }
+        };
+
+        let fail_closed = create_test_manager().with_safety_classifier(Arc::new(StubClassifier));
+        let session = id(&fail_closed).await;
+        assert!(matches!(
+            fail_closed.process_message(create_request(&session, "Is the classifier up?")).await,
+            Err(ConversationError::MessageBlocked { .. })
+        ));
+
+        let fail_open = create_test_manager()
+            .with_safety_classifier(Arc::new(StubClassifier))
+            .with_safety_failure_policy(SafetyFailurePolicy::Allow);
+        let session = id(&fail_open).await;
+        assert!(fail_open.process_message(create_request(&session, "Is the classifier up?")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_attachments_screened_on_both_paths() {
+        use crate::attachments::AttachmentType;
+
+        let manager = create_test_manager().with_safety_classifier(Arc::new(StubClassifier));
+        let id = manager.session_manager.write().await.create_session(None).id;
+        let with_code = |code: &str| {
+            let mut request = create_request(&id, "Can you review this?");
+            request.attachments.push(MessageAttachment::new(AttachmentType::Code, code));
+            request
+        };
+        let blocked = |result: Result<()>| matches!(result, Err(ConversationError::MessageBlocked { .. }));
+
+        let destructive = with_code("DROP TABLE users;");
+        assert!(blocked(manager.process_message(destructive.clone()).await.map(|_| ())));
+        assert!(blocked(manager.create_streaming_response(destructive).await.map(|_| ())));
+
+        let credentials = with_code("password = \"hunter2\"");
+        manager.process_message(credentials.clone()).await.unwrap();
+        let history = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        assert_eq!(
+            history[0].metadata.get(SAFETY_FLAG_KEY).map(String::as_str),
+            Some("credentials")
+        );
+
+        let mut streaming = manager.create_streaming_response(credentials).await.unwrap();
+        let chunks: Vec<_> = streaming
+            .stream("Can you review this?".to_string())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let last = chunks.last().unwrap().as_ref().unwrap();
+        assert!(last.is_final);
+        assert_eq!(last.metadata.get(SAFETY_FLAG_KEY).map(String::as_str), Some("credentials"));
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|c| !c.as_ref().unwrap().metadata.contains_key(SAFETY_FLAG_KEY)));
+    }
+
+    #[tokio::test]
+    async fn test_resume_stream_by_token() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        let mut streaming = manager.create_streaming_response(create_request(&id, "hi")).await.unwrap();
+        let chunks: Vec<StreamChunk> = streaming
+            .stream("hi".to_string())
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let token = streaming.resume_token(&chunks[3]);
+        drop(streaming);
+
+        // The response is gone, but its chunks are still buffered
+        let replayed: Vec<usize> = manager
+            .resume_stream(token)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap().sequence)
+            .collect()
+            .await;
+        assert_eq!(replayed, (4..chunks.len()).collect::<Vec<_>>());
+
+        let unknown = ResumeToken {
+            stream_id: "unknown".to_string(),
+            sequence: 0,
+        };
+        assert!(matches!(
+            manager.resume_stream(unknown).await,
+            Err(ConversationError::InvalidResumeToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_under_specified_query_reuses_established_service() {
+        let manager = create_test_manager();
+        let id = manager.session_manager.write().await.create_session(None).id;
+
+        manager
+            .process_message(create_request(&id, "Why is checkout-service returning 503s?"))
+            .await
+            .unwrap();
+        let response = manager
+            .process_message(create_request(&id, "How many replicas does the service run?"))
+            .await
+            .unwrap();
+        let reference = response
+            .resolved_references
+            .iter()
+            .find(|r| r.reference == "the service")
+            .unwrap();
+        assert_eq!(reference.refers_to, "checkout-service");
+
+        // Queries translated for the session use the established service
+        let refined = manager
+            .refine_query(&id, "Show error rate for the service", QueryLanguage::PromQL)
+            .await
+            .unwrap();
+        assert!(refined.query.as_deref().unwrap().contains("checkout-service"));
+
+        // Mentioning another service moves the conversation on
+        manager
+            .process_message(create_request(&id, "Is payment-service healthy?"))
+            .await
+            .unwrap();
+        let response = manager
+            .process_message(create_request(&id, "Restart the service"))
+            .await
+            .unwrap();
+        assert!(response
+            .resolved_references
+            .iter()
+            .any(|r| r.reference == "the service" && r.refers_to == "payment-service"));
+        let remembered = manager.remembered_entities(&id).await;
+        assert_eq!(remembered[0].value, "payment-service");
+        assert_eq!(remembered.iter().filter(|e| e.value == "checkout-service").count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_entities_forgotten_with_their_session() {
+        let manager = create_test_manager();
+        *manager.session_manager.write().await = SessionManager::with_config(SessionConfig {
+            timeout_seconds: 1,
+            ..Default::default()
+        });
+        let deleted = manager.session_manager.write().await.create_session(None).id;
+        let expired = manager.session_manager.write().await.create_session(None).id;
+        for id in [&deleted, &expired] {
+            manager
+                .process_message(create_request(id, "Is checkout-service healthy?"))
+                .await
+                .unwrap();
+        }
+        assert!(!manager.remembered_entities(&expired).await.is_empty());
+
+        manager.delete_session(&deleted, None).await.unwrap();
+        assert!(manager.remembered_entities(&deleted).await.is_empty());
+        assert!(!manager.remembered_entities(&expired).await.is_empty());
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        manager.cleanup_expired().await;
+        assert!(manager.remembered_entities(&expired).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_non_owner_is_denied_session_access() {
+        let manager = create_test_manager();
+        let id = manager
+            .session_manager
+            .write()
+            .await
+            .create_user_session("alice", None)
+            .unwrap()
+            .id;
+        let denied = |result: Result<_>| matches!(result, Err(ConversationError::AccessDenied { .. }));
+
+        let mut request = create_request(&id, "Show me the errors");
+        request.user_id = Some("mallory".to_string());
+        assert!(denied(manager.process_message(request.clone()).await.map(|_| ())));
+        assert!(denied(manager.create_streaming_response(request.clone()).await.map(|_| ())));
+        request.user_id = None;
+        assert!(denied(manager.process_message(request.clone()).await.map(|_| ())));
+
+        assert!(denied(manager.get_session(&id, Some("mallory")).await.map(|_| ())));
+        assert!(denied(manager.fork_session(&id, None, Some("mallory")).await.map(|_| ())));
+        assert!(denied(manager.delete_session(&id, Some("mallory")).await.map(|_| ())));
+        assert_eq!(manager.session_manager.read().await.session_count(), 1);
+
+        request.user_id = Some("alice".to_string());
+        manager.process_message(request).await.unwrap();
+        assert!(manager.get_session(&id, Some("alice")).await.is_ok());
+        manager.delete_session(&id, Some("alice")).await.unwrap();
+        assert!(manager.history_manager.read().await.get_all_messages(&id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_session_injects_handoff_summary() {
+        let manager = create_test_manager();
+        let id = manager
+            .session_manager
+            .write()
+            .await
+            .create_user_session("bot", None)
+            .unwrap()
+            .id;
+        let mut request = create_request(&id, "Why is checkout-service slow?");
+        request.user_id = Some("bot".to_string());
+        manager.process_message(request).await.unwrap();
+        let tokens_before = manager.session_manager.write().await.get_session(&id).unwrap().total_tokens;
+
+        let transfer = manager.transfer_session(&id, "agent-7", true).await.unwrap();
+        assert_eq!(transfer.from.as_deref(), Some("bot"));
+        assert_eq!(transfer.to, "agent-7");
+
+        let messages = manager.history_manager.read().await.get_all_messages(&id).await.unwrap();
+        let summary = messages.last().unwrap();
+        assert_eq!(summary.role, MessageRole::System);
+        assert!(summary.content.starts_with("Conversation handed over from bot to agent-7. 2 message(s)"));
+        assert!(summary.content.contains("service checkout-service"));
+        assert!(summary.content.contains("Last user message: \"Why is checkout-service slow?\""));
+
+        let mut session_mgr = manager.session_manager.write().await;
+        let session = session_mgr.get_session(&id).unwrap();
+        assert_eq!(session.user_id.as_deref(), Some("agent-7"));
+        assert_eq!(session.total_tokens, tokens_before + summary.token_count);
+    }
+
+    /// Short-term capacity configured for [`TieredContextEngine`]
+    const TIERED_SHORT_TERM_CAPACITY: usize = 1_000;
+
+    /// Context engine where only short-term items are warm; retrieving
+    /// anything else counts as a cold read
+    #[derive(Default)]
+    struct TieredContextEngine {
+        items: std::sync::Mutex<Vec<MemoryItem>>,
+        cold_reads: std::sync::atomic::AtomicUsize,
+    }
+
+    impl TieredContextEngine {
+        fn add(&self, content: &str, tier: MemoryTier, token_count: usize) {
+            let mut item = MemoryItem::new(content.to_string(), MemoryMetadata::new("fact", "test"), 0.5, token_count);
+            item.tier = tier;
+            self.items.lock().unwrap().push(item);
+        }
+    }
+
+    #[async_trait]
+    impl ContextEngine for TieredContextEngine {
+        async fn store(
+            &self,
+            _content: String,
+            _metadata: MemoryMetadata,
+            _importance: f64,
+        ) -> copilot_context::Result<Uuid> {
+            Err(unavailable())
+        }
+        async fn retrieve(&self, query: &str) -> copilot_context::Result<RetrievalResult> {
+            let query = query.to_lowercase();
+            let selected: Vec<ScoredItem> = self
+                .items
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|item| query.split_whitespace().any(|word| item.content.contains(word)))
+                .map(|item| ScoredItem { item: item.clone(), score: 1.0 })
+                .collect();
+            let cold = selected.iter().filter(|s| s.item.tier != MemoryTier::ShortTerm).count();
+            self.cold_reads.fetch_add(cold, Ordering::SeqCst);
+            Ok(RetrievalResult {
+                total_tokens: selected.iter().map(|s| s.item.token_count).sum(),
+                selected,
+                rejected: Vec::new(),
+                target_tokens: 100_000,
+                max_tokens: 100_000,
+                expansion: None,
+            })
+        }
+        async fn compress(&self) -> copilot_context::Result<CompressionStats> {
+            Ok(CompressionStats::default())
+        }
+        async fn stats(&self) -> copilot_context::Result<EngineStats> {
+            let items = self.items.lock().unwrap();
+            let short_term: Vec<_> = items.iter().filter(|i| i.tier == MemoryTier::ShortTerm).collect();
+            Ok(EngineStats {
+                total_items: items.len(),
+                total_tokens: items.iter().map(|i| i.token_count).sum(),
+                short_term_tokens: short_term.iter().map(|i| i.token_count).sum(),
+                medium_term_tokens: 0,
+                long_term_tokens: 0,
+                short_term_items: short_term.len(),
+                medium_term_items: 0,
+                long_term_items: 0,
+                utilization: 0.0,
+                within_budget: true,
+            })
+        }
+        fn tier_capacity(&self, tier: MemoryTier) -> usize {
+            match tier {
+                MemoryTier::ShortTerm => TIERED_SHORT_TERM_CAPACITY,
+                _ => tier.token_capacity(),
+            }
+        }
+        async fn promote(&self, id: &Uuid, tier: MemoryTier) -> copilot_context::Result<()> {
+            let mut items = self.items.lock().unwrap();
+            let item = items
+                .iter_mut()
+                .find(|i| i.metadata.id == *id)
+                .ok_or_else(|| ContextError::ItemNotFound(id.to_string()))?;
+            item.tier = tier;
+            Ok(())
+        }
+        async fn demote(&self, id: &Uuid, tier: MemoryTier) -> copilot_context::Result<()> {
+            self.promote(id, tier).await
+        }
+        async fn remove(&self, _id: &Uuid) -> copilot_context::Result<()> {
+            Ok(())
+        }
+        async fn clear(&self) -> copilot_context::Result<()> {
+            Ok(())
+        }
+        async fn maintenance(&self) -> copilot_context::Result<MaintenanceReport> {
+            Ok(MaintenanceReport::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warmup_prefetches_into_short_term_tier() {
+        let engine = Arc::new(TieredContextEngine::default());
+        engine.add("checkout latency runbook", MemoryTier::LongTerm, 100);
+        engine.add("checkout deploy history", MemoryTier::ShortTerm, 100);
+        engine.add("checkout capacity plan", MemoryTier::MediumTerm, TIERED_SHORT_TERM_CAPACITY);
+        engine.add("billing outage notes", MemoryTier::LongTerm, 100);
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), engine.clone());
+        let id = manager.session_manager.write().await.create_session(None).id;
+        manager.session_manager.write().await.set_metadata(&id, "service", "checkout").unwrap();
+
+        let report = manager.warmup(&id).await.unwrap().await.unwrap();
+        assert_eq!(report.query, "checkout");
+        assert_eq!(report.items_promoted, 1);
+        assert_eq!(report.tokens_promoted, 100);
+        assert_eq!(report.items_warm, 1);
+        // The capacity plan does not fit the configured capacity next to
+        // what is already warm, though it would fit the nominal one
+        assert!(TIERED_SHORT_TERM_CAPACITY < MemoryTier::ShortTerm.token_capacity());
+        assert_eq!(report.items_skipped, 1);
+        assert!(report.error.is_none());
+
+        // The first retrieval reads the runbook without a cold read
+        engine.cold_reads.store(0, Ordering::SeqCst);
+        let result = engine.retrieve("latency").await.unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert_eq!(result.selected[0].item.content, "checkout latency runbook");
+        assert_eq!(engine.cold_reads.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_start_session_warms_up_context() {
+        let engine = Arc::new(TieredContextEngine::default());
+        engine.add("checkout latency runbook", MemoryTier::LongTerm, 100);
+        engine.add("billing outage notes", MemoryTier::LongTerm, 100);
+        let manager = ConversationManager::new(Arc::new(NlpEngineImpl::default()), engine.clone());
+
+        let metadata = HashMap::from([("service".to_string(), "checkout".to_string())]);
+        let (session, warmup) = manager.start_session(Some("alice"), metadata).await.unwrap();
+        assert_eq!(session.user_id.as_deref(), Some("alice"));
+        assert_eq!(session.metadata.get("service").map(String::as_str), Some("checkout"));
+
+        let report = warmup.await.unwrap();
+        assert_eq!(report.query, "checkout");
+        assert_eq!(report.items_promoted, 1);
+        let items = engine.items.lock().unwrap();
+        let runbook = items.iter().find(|i| i.content.contains("runbook")).unwrap();
+        assert_eq!(runbook.tier, MemoryTier::ShortTerm);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_failure_does_not_block_session() {
+        let manager = ConversationManager::new(
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(UnavailableContextEngine),
+        );
+        let id = manager.session_manager.write().await.create_session(None).id;
+        manager.session_manager.write().await.set_metadata(&id, "service", "api").unwrap();
+
+        let warmup = manager.warmup(&id).await.unwrap();
+        manager
+            .process_message(create_request(&id, "Show CPU usage for api"))
+            .await
+            .unwrap();
+        let report = warmup.await.unwrap();
+        assert_eq!(report.error.as_deref(), Some("Storage error: connection refused"));
+        assert_eq!(report.items_promoted, 0);
+
+        assert!(matches!(
+            manager.warmup("missing").await,
+            Err(ConversationError::SessionNotFound(_))
+        ));
+    }
 }
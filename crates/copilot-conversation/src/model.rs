@@ -0,0 +1,265 @@
+//! Per-session models
+//!
+//! Sessions may talk to different models at the same time. Each model has
+//! a [`ModelProfile`] describing how its tokens are counted, how large its
+//! context window is and what its tokens cost. A [`ModelRegistry`] holds
+//! the known profiles and resolves a session's model to one.
+//!
+//! Models the registry does not know resolve to the default profile, whose
+//! tokenizer approximates four characters per token, so a misspelled or
+//! new model name degrades token accuracy instead of failing the turn.
+
+use crate::prompt::DEFAULT_CONTEXT_WINDOW;
+use copilot_context::{ApproximateTokenizer, Tokenizer, TokenizerConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Name of the profile used for sessions without a model
+pub const DEFAULT_MODEL: &str = "default";
+
+/// How a model's tokens are counted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenizerSpec {
+    /// The tiktoken BPE of an OpenAI model, e.g. "gpt-4" or "gpt-4o"
+    Tiktoken {
+        /// Model name tiktoken resolves the encoding from
+        model: String,
+    },
+    /// A fixed number of characters per token
+    Approximate {
+        /// Characters counted as one token
+        chars_per_token: usize,
+    },
+}
+
+impl Default for TokenizerSpec {
+    fn default() -> Self {
+        Self::Approximate { chars_per_token: 4 }
+    }
+}
+
+/// Counts tokens for one model
+///
+/// Wraps the context engine's [`Tokenizer`]s, so a conversation and the
+/// context built for it agree on how many tokens the same text takes.
+#[derive(Clone)]
+pub struct ModelTokenizer(Arc<dyn Tokenizer>);
+
+impl std::fmt::Debug for ModelTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ModelTokenizer")
+    }
+}
+
+impl ModelTokenizer {
+    /// Load the tokenizer a spec describes
+    ///
+    /// A tiktoken model that cannot be loaded falls back to the four
+    /// characters per token approximation.
+    pub fn load(spec: &TokenizerSpec) -> Self {
+        match spec {
+            TokenizerSpec::Tiktoken { model } => Self(TokenizerConfig::Tiktoken.build(model).into()),
+            TokenizerSpec::Approximate { chars_per_token } => {
+                Self(Arc::new(ApproximateTokenizer::new(*chars_per_token)))
+            }
+        }
+    }
+
+    /// Token count of text; empty text costs nothing
+    pub fn count(&self, text: &str) -> usize {
+        self.0.count(text)
+    }
+}
+
+impl Default for ModelTokenizer {
+    fn default() -> Self {
+        Self(Arc::new(ApproximateTokenizer::default()))
+    }
+}
+
+/// Price of a model's tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Cost of 1000 prompt tokens
+    pub input_per_1k: f64,
+    /// Cost of 1000 completion tokens
+    pub output_per_1k: f64,
+}
+
+impl ModelPricing {
+    /// Create pricing from per-1000-token costs
+    pub fn new(input_per_1k: f64, output_per_1k: f64) -> Self {
+        Self {
+            input_per_1k,
+            output_per_1k,
+        }
+    }
+
+    /// Cost of a call with the given prompt and completion tokens
+    pub fn cost(&self, input_tokens: usize, output_tokens: usize) -> f64 {
+        (input_tokens as f64 * self.input_per_1k + output_tokens as f64 * self.output_per_1k)
+            / 1000.0
+    }
+}
+
+/// What the conversation layer needs to know about a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelProfile {
+    /// Model name sessions refer to it by
+    pub name: String,
+    /// Context window in tokens
+    pub context_window: usize,
+    /// How tokens are counted
+    #[serde(default)]
+    pub tokenizer: TokenizerSpec,
+    /// Token prices
+    #[serde(default)]
+    pub pricing: ModelPricing,
+}
+
+impl ModelProfile {
+    /// Create a profile with the approximate tokenizer and no pricing
+    pub fn new(name: impl Into<String>, context_window: usize) -> Self {
+        Self {
+            name: name.into(),
+            context_window,
+            tokenizer: TokenizerSpec::default(),
+            pricing: ModelPricing::default(),
+        }
+    }
+
+    /// Count tokens with a tiktoken BPE
+    pub fn with_tiktoken(mut self, model: impl Into<String>) -> Self {
+        self.tokenizer = TokenizerSpec::Tiktoken {
+            model: model.into(),
+        };
+        self
+    }
+
+    /// Count tokens as a fixed number of characters per token
+    pub fn with_chars_per_token(mut self, chars_per_token: usize) -> Self {
+        self.tokenizer = TokenizerSpec::Approximate { chars_per_token };
+        self
+    }
+
+    /// Set the token prices
+    pub fn with_pricing(mut self, pricing: ModelPricing) -> Self {
+        self.pricing = pricing;
+        self
+    }
+}
+
+/// Known models and their tokenizers
+///
+/// Tokenizers are loaded on first use and shared by all sessions on the
+/// model.
+#[derive(Debug)]
+pub struct ModelRegistry {
+    profiles: HashMap<String, ModelProfile>,
+    default_model: String,
+    tokenizers: RwLock<HashMap<String, ModelTokenizer>>,
+}
+
+impl ModelRegistry {
+    /// Create a registry holding only the default profile
+    pub fn new() -> Self {
+        let default = ModelProfile::new(DEFAULT_MODEL, DEFAULT_CONTEXT_WINDOW);
+        Self {
+            profiles: HashMap::from([(default.name.clone(), default)]),
+            default_model: DEFAULT_MODEL.to_string(),
+            tokenizers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Add or replace a profile
+    pub fn register(mut self, profile: ModelProfile) -> Self {
+        self.tokenizers.get_mut().unwrap().remove(&profile.name);
+        self.profiles.insert(profile.name.clone(), profile);
+        self
+    }
+
+    /// Use a registered model for sessions without one
+    ///
+    /// Unregistered names are ignored.
+    pub fn with_default_model(mut self, name: &str) -> Self {
+        if self.profiles.contains_key(name) {
+            self.default_model = name.to_string();
+        } else {
+            warn!(model = %name, "Default model is not registered, keeping {}", self.default_model);
+        }
+        self
+    }
+
+    /// Whether a model is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    /// Profile of a model, or the default profile for unknown models
+    pub fn profile(&self, name: Option<&str>) -> &ModelProfile {
+        name.and_then(|name| self.profiles.get(name))
+            .unwrap_or_else(|| &self.profiles[&self.default_model])
+    }
+
+    /// Tokenizer of a model, or of the default profile for unknown models
+    pub fn tokenizer(&self, name: Option<&str>) -> ModelTokenizer {
+        let profile = self.profile(name);
+        if let Some(tokenizer) = self.tokenizers.read().unwrap().get(&profile.name) {
+            return tokenizer.clone();
+        }
+        let tokenizer = ModelTokenizer::load(&profile.tokenizer);
+        self.tokenizers
+            .write()
+            .unwrap()
+            .insert(profile.name.clone(), tokenizer.clone());
+        tokenizer
+    }
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_models_fall_back() {
+        let registry = ModelRegistry::new()
+            .register(ModelProfile::new("broken", 4096).with_tiktoken("no-such-model"));
+
+        assert_eq!(registry.profile(Some("nope")).name, DEFAULT_MODEL);
+        assert_eq!(registry.profile(None).context_window, DEFAULT_CONTEXT_WINDOW);
+
+        // A registered model whose tokenizer cannot load approximates
+        let text = "How many 5xx errors did checkout return?";
+        assert_eq!(registry.tokenizer(Some("broken")).count(text), ModelTokenizer::default().count(text));
+        assert_eq!(registry.tokenizer(Some("nope")).count(""), 0);
+    }
+
+    #[test]
+    fn test_approximation_counts_characters() {
+        let tokenizer = ModelTokenizer::load(&TokenizerSpec::Approximate { chars_per_token: 4 });
+        // 16 characters, 32 bytes
+        assert_eq!(tokenizer.count("привет, как дела"), 4);
+        assert_eq!(tokenizer.count("How many errors?"), 4);
+
+        // Same counts as the context engine
+        let context = ApproximateTokenizer::default();
+        for text in ["", "abc", "привет, как дела", "How many 5xx errors did checkout return?"] {
+            assert_eq!(tokenizer.count(text), context.count(text));
+        }
+    }
+
+    #[test]
+    fn test_pricing() {
+        let pricing = ModelPricing::new(0.01, 0.03);
+        assert!((pricing.cost(2000, 1000) - 0.05).abs() < 1e-9);
+    }
+}
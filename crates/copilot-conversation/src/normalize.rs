@@ -0,0 +1,168 @@
+//! Message content normalization
+//!
+//! The same message pasted from different clients arrives with CRLF or LF
+//! line endings, trailing spaces and runs of blank lines, which makes token
+//! counts drift and stored history noisy to diff. Messages are normalized
+//! once, before they are tokenized and stored.
+//!
+//! Whitespace inside fenced code blocks (```` ``` ```` or `~~~`) can be
+//! significant, e.g. in Markdown, YAML or diffs, so fenced lines only have
+//! their line endings normalized.
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Which normalizations are applied to message content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizationConfig {
+    /// Convert CRLF and CR line endings to LF
+    pub line_endings: bool,
+    /// Strip trailing whitespace from lines outside code blocks
+    pub trailing_whitespace: bool,
+    /// Collapse runs of blank lines outside code blocks into one, and drop
+    /// leading and trailing blank lines
+    pub blank_lines: bool,
+    /// Apply Unicode NFC normalization
+    pub unicode_nfc: bool,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            line_endings: true,
+            trailing_whitespace: true,
+            blank_lines: true,
+            unicode_nfc: false,
+        }
+    }
+}
+
+impl NormalizationConfig {
+    /// A configuration that leaves content unchanged
+    pub fn disabled() -> Self {
+        Self {
+            line_endings: false,
+            trailing_whitespace: false,
+            blank_lines: false,
+            unicode_nfc: false,
+        }
+    }
+
+    /// Enable or disable Unicode NFC normalization
+    pub fn with_unicode_nfc(mut self, enabled: bool) -> Self {
+        self.unicode_nfc = enabled;
+        self
+    }
+
+    /// Normalize message content
+    pub fn normalize(&self, content: &str) -> String {
+        let content = if self.unicode_nfc {
+            content.nfc().collect::<String>()
+        } else {
+            content.to_string()
+        };
+        let content = if self.line_endings {
+            content.replace("\r\n", "\n").replace('\r', "\n")
+        } else {
+            content
+        };
+        if !self.trailing_whitespace && !self.blank_lines {
+            return content;
+        }
+
+        let mut lines: Vec<&str> = Vec::new();
+        let mut fence: Option<&str> = None;
+        for line in content.split('\n') {
+            let marker = fence_marker(line);
+            let in_fence = fence.is_some();
+            match (fence, marker) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if marker == open => fence = None,
+                _ => {}
+            }
+
+            // Lines inside a fence, including its closing marker, are kept
+            // verbatim
+            if in_fence {
+                lines.push(line);
+                continue;
+            }
+
+            let line = if self.trailing_whitespace {
+                line.trim_end()
+            } else {
+                line
+            };
+            let blank = line.trim().is_empty();
+            if self.blank_lines && blank && lines.last().map_or(true, |l| l.trim().is_empty()) {
+                continue;
+            }
+            lines.push(line);
+        }
+
+        // A trailing blank line can only come from outside a fence
+        if self.blank_lines && fence.is_none() {
+            while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                lines.pop();
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// The fence marker a line opens or closes a code block with, if any
+fn fence_marker(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    ["```", "~~~"]
+        .into_iter()
+        .find(|marker| line.starts_with(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::estimate_tokens;
+
+    #[test]
+    fn test_normalization_stabilizes_token_counts() {
+        let config = NormalizationConfig::default();
+        let variants = [
+            "Why is checkout slow?\n\nCPU looks fine.",
+            "Why is checkout slow?  \r\n\r\n\r\nCPU looks fine.\r\n",
+            "\nWhy is checkout slow?\t\r\r   \rCPU looks fine.   \n\n",
+        ];
+
+        let normalized: Vec<String> = variants.iter().map(|v| config.normalize(v)).collect();
+        assert!(normalized.iter().all(|n| n == variants[0]));
+
+        let counts: Vec<usize> = normalized.iter().map(|n| estimate_tokens(n)).collect();
+        assert!(counts.iter().all(|&c| c == counts[0]));
+        assert_ne!(estimate_tokens(variants[1]), counts[0]);
+    }
+
+    #[test]
+    fn test_code_block_whitespace_is_preserved() {
+        let content = "Config:  \r\n```yaml\r\nserver:  \r\n  port: 80\r\n\r\n\r\n  tls: true\t\r\n```\r\nDone.  ";
+        let normalized = NormalizationConfig::default().normalize(content);
+
+        assert_eq!(
+            normalized,
+            "Config:\n```yaml\nserver:  \n  port: 80\n\n\n  tls: true\t\n```\nDone."
+        );
+    }
+
+    #[test]
+    fn test_unicode_nfc_is_optional() {
+        // "é" as "e" followed by a combining acute accent
+        let decomposed = "caf\u{0065}\u{0301}";
+
+        assert_eq!(NormalizationConfig::default().normalize(decomposed), decomposed);
+        assert_eq!(
+            NormalizationConfig::default()
+                .with_unicode_nfc(true)
+                .normalize(decomposed),
+            "caf\u{00e9}"
+        );
+        assert_eq!(NormalizationConfig::disabled().normalize(" a \r\n"), " a \r\n");
+    }
+}
@@ -0,0 +1,200 @@
+//! Prompt assembly and size estimation
+//!
+//! A prompt is made of four segments: the system prompt, recent history,
+//! context retrieved for the message, and the new message itself. Each
+//! segment is counted separately, the way chat providers count messages,
+//! so the per-segment breakdown of a [`PromptEstimate`] always adds up to
+//! the token count of the [`PromptContext`] it describes.
+//!
+//! A prompt also records where each included piece of history and
+//! retrieved context came from, so responses can cite their sources.
+
+use crate::model::ModelTokenizer;
+use copilot_context::{ApproximateTokenizer, Provenance, Tokenizer};
+use serde::{Deserialize, Serialize};
+
+/// Default system prompt
+pub const DEFAULT_SYSTEM_PROMPT: &str =
+    "You are an observability assistant. Answer questions about metrics, logs and traces.";
+
+/// Default model context window in tokens
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
+/// Estimate the token count of a prompt segment
+///
+/// Uses the context engine's four characters per token approximation.
+/// Empty segments cost nothing.
+pub fn estimate_tokens(text: &str) -> usize {
+    ApproximateTokenizer::default().count(text)
+}
+
+/// Prompt segment a piece of context was included in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSegment {
+    /// Conversation history
+    History,
+    /// Retrieved context
+    Retrieved,
+}
+
+/// One piece of context included in a prompt and where it came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextSource {
+    /// Segment the piece was included in
+    pub segment: PromptSegment,
+    /// Where the piece came from
+    pub provenance: Provenance,
+    /// Whether a compressed version of the content was included
+    #[serde(default)]
+    pub compressed: bool,
+}
+
+impl ContextSource {
+    /// A piece of context in a segment
+    pub fn new(segment: PromptSegment, provenance: Provenance) -> Self {
+        Self {
+            segment,
+            provenance,
+            compressed: false,
+        }
+    }
+}
+
+/// The assembled context for one turn
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptContext {
+    /// System prompt
+    pub system: String,
+    /// Recent conversation history
+    pub history: String,
+    /// Context retrieved for the message
+    pub retrieved: String,
+    /// The new message
+    pub message: String,
+    /// Sources of the history and retrieved segments, in prompt order
+    #[serde(default)]
+    pub sources: Vec<ContextSource>,
+    /// Whether retrieval failed and the retrieved segment is missing
+    #[serde(default)]
+    pub context_degraded: bool,
+}
+
+impl PromptContext {
+    /// Render the prompt as a single string, skipping empty segments
+    pub fn render(&self) -> String {
+        [&self.system, &self.history, &self.retrieved, &self.message]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// What history and retrieved context the prompt includes and from where
+    pub fn provenance(&self) -> &[ContextSource] {
+        &self.sources
+    }
+
+    /// Token count of the prompt
+    pub fn token_count(&self) -> usize {
+        estimate_tokens(&self.system)
+            + estimate_tokens(&self.history)
+            + estimate_tokens(&self.retrieved)
+            + estimate_tokens(&self.message)
+    }
+
+    /// Size the prompt against a context window
+    pub fn estimate(&self, context_window: usize) -> PromptEstimate {
+        self.estimate_with(&ModelTokenizer::default(), context_window)
+    }
+
+    /// Size the prompt against a model's tokenizer and context window
+    pub fn estimate_with(&self, tokenizer: &ModelTokenizer, context_window: usize) -> PromptEstimate {
+        let system_tokens = tokenizer.count(&self.system);
+        let history_tokens = tokenizer.count(&self.history);
+        let retrieved_tokens = tokenizer.count(&self.retrieved);
+        let message_tokens = tokenizer.count(&self.message);
+        let total_tokens = system_tokens + history_tokens + retrieved_tokens + message_tokens;
+
+        PromptEstimate {
+            system_tokens,
+            history_tokens,
+            retrieved_tokens,
+            message_tokens,
+            total_tokens,
+            context_window,
+            fits: total_tokens <= context_window,
+        }
+    }
+}
+
+/// Per-segment token counts of a prompt and whether it fits the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptEstimate {
+    /// Tokens in the system prompt
+    pub system_tokens: usize,
+    /// Tokens in the conversation history
+    pub history_tokens: usize,
+    /// Tokens in retrieved context
+    pub retrieved_tokens: usize,
+    /// Tokens in the new message
+    pub message_tokens: usize,
+    /// Sum of all segments
+    pub total_tokens: usize,
+    /// Context window the prompt was sized against
+    pub context_window: usize,
+    /// Whether the prompt fits within the context window
+    pub fits: bool,
+}
+
+impl PromptEstimate {
+    /// Tokens left in the context window, e.g. for the response
+    pub fn remaining_tokens(&self) -> usize {
+        self.context_window.saturating_sub(self.total_tokens)
+    }
+
+    /// Tokens over the context window, zero if the prompt fits
+    pub fn overflow_tokens(&self) -> usize {
+        self.total_tokens.saturating_sub(self.context_window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_sums_to_total() {
+        let prompt = PromptContext {
+            system: "s".repeat(40),
+            history: "h".repeat(22),
+            retrieved: String::new(),
+            message: "m".repeat(3),
+            ..Default::default()
+        };
+
+        let estimate = prompt.estimate(20);
+        assert_eq!(estimate.system_tokens, 10);
+        assert_eq!(estimate.history_tokens, 6);
+        assert_eq!(estimate.retrieved_tokens, 0);
+        assert_eq!(estimate.message_tokens, 1);
+        assert_eq!(estimate.total_tokens, prompt.token_count());
+        assert!(estimate.fits);
+        assert_eq!(estimate.remaining_tokens(), 3);
+
+        let estimate = prompt.estimate(10);
+        assert!(!estimate.fits);
+        assert_eq!(estimate.overflow_tokens(), 7);
+    }
+
+    #[test]
+    fn test_render_skips_empty_segments() {
+        let prompt = PromptContext {
+            system: "system".to_string(),
+            message: "hello".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(prompt.render(), "system\n\nhello");
+    }
+}
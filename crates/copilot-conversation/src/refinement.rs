@@ -0,0 +1,245 @@
+//! Multi-turn query refinement
+//!
+//! Operators narrow a query over several turns: "show CPU for
+//! checkout-service", then "now only production", then "make it p99". A
+//! follow-up like that is a refinement: its entities are merged into the
+//! session's accumulated query instead of starting a new one. How an entity
+//! merges depends on its type. Single-valued constraints such as the time
+//! range or aggregation replace the previous value; scoping entities such
+//! as services accumulate, unless the message says "only". A reset phrase
+//! ("start over") clears the accumulated query.
+
+use copilot_nlp::{Entity, EntityType, Intent, IntentType, QueryLanguage};
+use serde::{Deserialize, Serialize};
+
+/// Phrases that clear the accumulated query
+const RESET_PHRASES: &[&str] = &["start over", "start again", "reset", "new query", "forget that"];
+
+/// Leading words that mark a message as a follow-up to the previous query
+const REFINEMENT_CUES: &[&str] = &[
+    "now", "only", "just", "also", "and", "but", "instead", "make it", "what about", "same",
+    "then", "with", "without", "for",
+];
+
+/// How a message relates to the session's accumulated query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefinementKind {
+    /// A new query, replacing any accumulated one
+    NewQuery,
+    /// Constraints merged into the accumulated query
+    Refine,
+    /// The accumulated query was cleared
+    Reset,
+}
+
+/// How entities of one type merge into an accumulated query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// A new value replaces the previous ones
+    Replace,
+    /// New values are added to the previous ones
+    Add,
+}
+
+impl MergePolicy {
+    /// The merge policy of an entity type
+    pub fn for_type(entity_type: &EntityType) -> Self {
+        match entity_type {
+            EntityType::Service
+            | EntityType::Host
+            | EntityType::Endpoint
+            | EntityType::HttpStatus
+            | EntityType::Namespace
+            | EntityType::Pod
+            | EntityType::Deployment
+            | EntityType::Container => MergePolicy::Add,
+            EntityType::TimeRange
+            | EntityType::Metric
+            | EntityType::Severity
+            | EntityType::Environment
+            | EntityType::Threshold
+            | EntityType::Aggregation => MergePolicy::Replace,
+        }
+    }
+}
+
+/// The query a session has built up over its turns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryState {
+    /// Intent of the query
+    pub intent: Intent,
+    /// Accumulated entities
+    pub entities: Vec<Entity>,
+    /// Number of turns that contributed to the query
+    pub turns: usize,
+}
+
+impl QueryState {
+    /// Start a query from a single turn
+    pub fn new(intent: Intent, entities: Vec<Entity>) -> Self {
+        let mut state = Self {
+            intent,
+            entities: Vec::new(),
+            turns: 1,
+        };
+        state.merge(entities, false);
+        state
+    }
+
+    /// Merge a follow-up turn's entities into the query
+    ///
+    /// With `only`, entities of additive types replace the previous ones
+    /// too, e.g. "only payment-service" narrows rather than widens.
+    pub fn refine(&mut self, entities: Vec<Entity>, only: bool) {
+        self.merge(entities, only);
+        self.turns += 1;
+    }
+
+    /// Entities of a type
+    pub fn entities_of(&self, entity_type: EntityType) -> impl Iterator<Item = &Entity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.entity_type == entity_type)
+    }
+
+    fn merge(&mut self, entities: Vec<Entity>, only: bool) {
+        // Types replaced by this turn are cleared once, before any of the
+        // turn's own values are added
+        let mut replaced: Vec<EntityType> = Vec::new();
+        for entity in entities {
+            let replace = only || MergePolicy::for_type(&entity.entity_type) == MergePolicy::Replace;
+            if replace && !replaced.contains(&entity.entity_type) {
+                self.entities.retain(|e| e.entity_type != entity.entity_type);
+                replaced.push(entity.entity_type.clone());
+            }
+
+            let duplicate = self.entities.iter().any(|e| {
+                e.entity_type == entity.entity_type && e.normalized_value == entity.normalized_value
+            });
+            if !duplicate {
+                self.entities.push(entity);
+            }
+        }
+    }
+}
+
+/// Result of interpreting a message as a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefinedQuery {
+    /// How the message related to the previous query
+    pub kind: RefinementKind,
+    /// The accumulated query after the message; `None` after a reset
+    pub state: Option<QueryState>,
+    /// Language of the translated query
+    pub language: QueryLanguage,
+    /// The accumulated query translated; `None` after a reset
+    pub query: Option<String>,
+}
+
+/// Whether a message asks to clear the accumulated query
+pub fn is_reset(message: &str) -> bool {
+    let message = format!(" {} ", normalize(message));
+    RESET_PHRASES
+        .iter()
+        .any(|phrase| message.contains(&format!(" {} ", phrase)))
+}
+
+/// Whether a message asks to narrow additive constraints to its own values
+pub fn is_exclusive(message: &str) -> bool {
+    normalize(message).split_whitespace().any(|word| word == "only" || word == "just")
+}
+
+/// Classify a message against the session's accumulated query
+///
+/// A message is a refinement if there is a query to refine and it either
+/// opens with a follow-up cue or has no clear intent of its own.
+pub fn classify(message: &str, intent: &Intent, previous: Option<&QueryState>) -> RefinementKind {
+    if is_reset(message) {
+        return RefinementKind::Reset;
+    }
+    if previous.is_none() {
+        return RefinementKind::NewQuery;
+    }
+
+    let message = normalize(message);
+    let cued = REFINEMENT_CUES.iter().any(|cue| {
+        message
+            .strip_prefix(cue)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+    });
+    let vague = matches!(intent.intent_type, IntentType::GeneralQuery | IntentType::Unknown);
+
+    if cued || vague {
+        RefinementKind::Refine
+    } else {
+        RefinementKind::NewQuery
+    }
+}
+
+fn normalize(message: &str) -> String {
+    message
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(entity_type: EntityType, value: &str) -> Entity {
+        Entity::new(entity_type, value.to_string(), value.to_string(), value.to_string(), 0.9)
+    }
+
+    fn values(state: &QueryState, entity_type: EntityType) -> Vec<&str> {
+        state
+            .entities_of(entity_type)
+            .map(|e| e.normalized_value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_replaces_or_adds_by_type() {
+        let mut state = QueryState::new(
+            Intent::new(IntentType::QueryMetrics, 0.9),
+            vec![
+                entity(EntityType::Service, "checkout-service"),
+                entity(EntityType::TimeRange, "1h"),
+            ],
+        );
+
+        state.refine(
+            vec![
+                entity(EntityType::Service, "payment-service"),
+                entity(EntityType::TimeRange, "15m"),
+            ],
+            false,
+        );
+        assert_eq!(values(&state, EntityType::Service), ["checkout-service", "payment-service"]);
+        assert_eq!(values(&state, EntityType::TimeRange), ["15m"]);
+
+        state.refine(vec![entity(EntityType::Service, "payment-service")], true);
+        assert_eq!(values(&state, EntityType::Service), ["payment-service"]);
+        assert_eq!(state.turns, 3);
+    }
+
+    #[test]
+    fn test_classify() {
+        let state = QueryState::new(Intent::new(IntentType::QueryMetrics, 0.9), Vec::new());
+        let metrics = Intent::new(IntentType::QueryMetrics, 0.9);
+        let vague = Intent::new(IntentType::GeneralQuery, 0.3);
+
+        assert_eq!(classify("Now only production", &metrics, Some(&state)), RefinementKind::Refine);
+        assert_eq!(classify("make it p99", &vague, Some(&state)), RefinementKind::Refine);
+        assert_eq!(classify("Show memory for api-service", &metrics, Some(&state)), RefinementKind::NewQuery);
+        assert_eq!(classify("nowhere is healthy", &metrics, Some(&state)), RefinementKind::NewQuery);
+        assert_eq!(classify("now only production", &metrics, None), RefinementKind::NewQuery);
+        assert_eq!(classify("OK, start over.", &metrics, Some(&state)), RefinementKind::Reset);
+        assert_eq!(classify("show the preset dashboards", &metrics, Some(&state)), RefinementKind::NewQuery);
+    }
+}
@@ -0,0 +1,375 @@
+//! Replay of recorded conversations
+//!
+//! A [`RecordedConversation`] is a list of user messages, the replies a
+//! model gave to them, and the documents the context engine held at the
+//! time. [`ConversationReplay`] drives the turns through
+//! [`ConversationManager::process_message`] with a [`ScriptedModel`] in
+//! place of a real one, and captures the prompt assembled for each turn:
+//! its segments, per-segment token counts and the sources of its history
+//! and retrieved context.
+//!
+//! The replay is deterministic. The session has a fixed id, message ids
+//! are replaced by their position in the conversation (`msg-1`, `msg-2`,
+//! ...), and token counts come from the same estimator the manager uses,
+//! so [`ReplayTranscript::render`] can be compared against a golden
+//! transcript to catch regressions in prompt assembly and context
+//! selection.
+
+use crate::{
+    manager::{ConversationManager, MessageRequest, ResponseGenerator},
+    prompt::{PromptContext, PromptEstimate},
+    ConversationError, Result,
+};
+use async_trait::async_trait;
+use copilot_context::{MemoryMetadata, Provenance};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+/// Session id replays run under
+pub const REPLAY_SESSION_ID: &str = "replay";
+
+/// Importance of documents seeded into the context engine
+const DOCUMENT_IMPORTANCE: f64 = 0.5;
+
+/// A document available to retrieval during a recorded conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedDocument {
+    /// Document identifier, reported as the document's provenance
+    pub id: String,
+    /// Document content
+    pub content: String,
+}
+
+/// One recorded exchange
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedTurn {
+    /// The user message
+    pub user: String,
+    /// The model's reply
+    pub assistant: String,
+}
+
+/// A conversation recorded for replay
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedConversation {
+    /// Documents stored in the context engine before the first turn
+    #[serde(default)]
+    pub documents: Vec<RecordedDocument>,
+    /// Exchanges in order
+    pub turns: Vec<RecordedTurn>,
+}
+
+impl RecordedConversation {
+    /// Create an empty recording
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a document available to retrieval
+    pub fn with_document(mut self, id: impl Into<String>, content: impl Into<String>) -> Self {
+        self.documents.push(RecordedDocument {
+            id: id.into(),
+            content: content.into(),
+        });
+        self
+    }
+
+    /// Add an exchange
+    pub fn with_turn(mut self, user: impl Into<String>, assistant: impl Into<String>) -> Self {
+        self.turns.push(RecordedTurn {
+            user: user.into(),
+            assistant: assistant.into(),
+        });
+        self
+    }
+
+    /// Parse a recording from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// A model that replies with a fixed script and records its prompts
+#[derive(Debug, Default)]
+pub struct ScriptedModel {
+    replies: Mutex<VecDeque<String>>,
+    prompts: Mutex<Vec<PromptContext>>,
+}
+
+impl ScriptedModel {
+    /// Create a model that gives `replies` in order
+    pub fn new(replies: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            replies: Mutex::new(replies.into_iter().map(Into::into).collect()),
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Prompts the model was given, in order
+    pub fn prompts(&self) -> Vec<PromptContext> {
+        self.prompts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl ResponseGenerator for ScriptedModel {
+    async fn generate(&self, prompt: &PromptContext) -> Result<String> {
+        let reply = self.replies.lock().unwrap().pop_front().ok_or_else(|| {
+            ConversationError::GenerationError("scripted model has no replies left".to_string())
+        })?;
+        self.prompts.lock().unwrap().push(prompt.clone());
+        Ok(reply)
+    }
+}
+
+/// What happened in one replayed turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayedTurn {
+    /// Turn number, starting at 1
+    pub turn: usize,
+    /// The user message
+    pub user: String,
+    /// The prompt the model was given
+    pub prompt: PromptContext,
+    /// Per-segment token counts of the prompt
+    pub estimate: PromptEstimate,
+    /// The reply
+    pub response: String,
+    /// Tokens the turn added to the session
+    pub tokens_used: usize,
+    /// Session tokens after the turn
+    pub total_tokens: usize,
+}
+
+/// The result of a replay
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayTranscript {
+    /// Replayed turns in order
+    pub turns: Vec<ReplayedTurn>,
+}
+
+impl ReplayTranscript {
+    /// Render the transcript as diff-able plain text
+    ///
+    /// Each turn lists its token counts, the sources of its context and
+    /// the full prompt, one line per prompt line.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for turn in &self.turns {
+            let e = &turn.estimate;
+            let _ = writeln!(out, "## turn {}", turn.turn);
+            let _ = writeln!(out, "user: {}", turn.user);
+            let _ = writeln!(
+                out,
+                "tokens: system={} history={} retrieved={} message={} total={}/{}",
+                e.system_tokens,
+                e.history_tokens,
+                e.retrieved_tokens,
+                e.message_tokens,
+                e.total_tokens,
+                e.context_window
+            );
+            out.push_str("sources:\n");
+            for source in &turn.prompt.sources {
+                let compressed = if source.compressed { " (compressed)" } else { "" };
+                let segment = serde_json::to_value(source.segment)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                let _ = writeln!(out, "  {} {}{}", segment, source.provenance, compressed);
+            }
+            if turn.prompt.context_degraded {
+                out.push_str("  (retrieval failed)\n");
+            }
+            out.push_str("prompt:\n");
+            for line in turn.prompt.render().lines() {
+                let _ = writeln!(out, "  |{}{}", if line.is_empty() { "" } else { " " }, line);
+            }
+            let _ = writeln!(out, "assistant: {}", turn.response);
+            let _ = writeln!(out, "usage: turn={} session={}", turn.tokens_used, turn.total_tokens);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Replays a recorded conversation through a conversation manager
+#[derive(Debug, Clone)]
+pub struct ConversationReplay {
+    conversation: RecordedConversation,
+}
+
+impl ConversationReplay {
+    /// Create a replay of a recording
+    pub fn new(conversation: RecordedConversation) -> Self {
+        Self { conversation }
+    }
+
+    /// Replay the recording through `manager`
+    ///
+    /// The manager's context engine is seeded with the recording's
+    /// documents and its response generator is replaced by a
+    /// [`ScriptedModel`] giving the recorded replies. Use a fresh manager
+    /// and context engine per replay, as anything they already hold can
+    /// end up in the prompts.
+    pub async fn run(&self, manager: ConversationManager) -> Result<ReplayTranscript> {
+        let model = Arc::new(ScriptedModel::new(
+            self.conversation.turns.iter().map(|t| t.assistant.clone()),
+        ));
+        let manager = manager.with_response_generator(model.clone());
+
+        for document in &self.conversation.documents {
+            manager
+                .context_engine()
+                .store(
+                    document.content.clone(),
+                    MemoryMetadata::new("document", "replay")
+                        .with_provenance(Provenance::document(document.id.clone())),
+                    DOCUMENT_IMPORTANCE,
+                )
+                .await
+                .map_err(|e| ConversationError::ContextError(e.to_string()))?;
+        }
+        manager
+            .session_manager()
+            .write()
+            .await
+            .create_session_with_id(REPLAY_SESSION_ID.to_string(), None)?;
+
+        let mut turns = Vec::with_capacity(self.conversation.turns.len());
+        for (index, recorded) in self.conversation.turns.iter().enumerate() {
+            let response = manager
+                .process_message(MessageRequest {
+                    session_id: REPLAY_SESSION_ID.to_string(),
+                    message: recorded.user.clone(),
+                    metadata: HashMap::new(),
+                    attachments: Vec::new(),
+                    user_id: None,
+                })
+                .await?;
+            let prompt = model.prompts().pop().ok_or_else(|| {
+                ConversationError::GenerationError("scripted model was not called".to_string())
+            })?;
+
+            turns.push(ReplayedTurn {
+                turn: index + 1,
+                user: recorded.user.clone(),
+                estimate: prompt.estimate(manager.context_window()),
+                prompt,
+                response: response.response,
+                tokens_used: response.tokens_used,
+                total_tokens: response.total_tokens,
+            });
+        }
+
+        // Replace generated message ids by their position in the session
+        let labels: HashMap<String, String> = manager
+            .history_manager()
+            .read()
+            .await
+            .get_all_messages(REPLAY_SESSION_ID)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(i, msg)| (msg.id, format!("msg-{}", i + 1)))
+            .collect();
+        for turn in &mut turns {
+            for source in &mut turn.prompt.sources {
+                if let Provenance::Message { message_id, .. } = &mut source.provenance {
+                    if let Some(label) = labels.get(message_id) {
+                        *message_id = label.clone();
+                    }
+                }
+            }
+        }
+
+        Ok(ReplayTranscript { turns })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+    use copilot_nlp::NlpEngineImpl;
+
+    fn manager() -> ConversationManager {
+        let context_engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        ConversationManager::new(Arc::new(NlpEngineImpl::default()), Arc::new(context_engine))
+            .with_system_prompt("You are an SRE assistant.")
+    }
+
+    fn recording() -> RecordedConversation {
+        RecordedConversation::new()
+            .with_document(
+                "runbook-checkout",
+                "Checkout latency runbook: scale the payment pool before restarting pods.",
+            )
+            .with_turn(
+                "Why is checkout latency high?",
+                "Payment pool saturation. Scale the payment pool.",
+            )
+            .with_turn("Scaled it, what next?", "Watch p99 for ten minutes.")
+    }
+
+    const GOLDEN: &str = "\
+## turn 1
+user: Why is checkout latency high?
+tokens: system=7 history=0 retrieved=18 message=8 total=33/8192
+sources:
+  retrieved document:runbook-checkout
+prompt:
+  | You are an SRE assistant.
+  |
+  | Checkout latency runbook: scale the payment pool before restarting pods.
+  |
+  | Why is checkout latency high?
+assistant: Payment pool saturation. Scale the payment pool.
+usage: turn=20 session=20
+
+## turn 2
+user: Scaled it, what next?
+tokens: system=7 history=24 retrieved=0 message=9 total=40/8192
+sources:
+  history message:msg-1
+  history message:msg-2
+prompt:
+  | You are an SRE assistant.
+  |
+  | User: Why is checkout latency high?
+  | Assistant: Payment pool saturation. Scale the payment pool.
+  |
+  | Scaled it (Payment pool), what next?
+assistant: Watch p99 for ten minutes.
+usage: turn=13 session=33
+
+";
+
+    #[tokio::test]
+    async fn test_replay_matches_golden_transcript() {
+        let replay = ConversationReplay::new(recording());
+        let transcript = replay.run(manager()).await.unwrap();
+
+        assert_eq!(transcript.render(), GOLDEN);
+
+        // A second replay on a fresh manager is identical
+        assert_eq!(replay.run(manager()).await.unwrap(), transcript);
+    }
+
+    #[tokio::test]
+    async fn test_recording_round_trips_and_script_exhaustion_fails() {
+        let json = serde_json::to_string(&recording()).unwrap();
+        assert_eq!(RecordedConversation::from_json(&json).unwrap(), recording());
+
+        let model = ScriptedModel::new(["only reply"]);
+        let prompt = PromptContext::default();
+        assert_eq!(model.generate(&prompt).await.unwrap(), "only reply");
+        assert!(matches!(
+            model.generate(&prompt).await,
+            Err(ConversationError::GenerationError(_))
+        ));
+        assert_eq!(model.prompts().len(), 1);
+    }
+}
@@ -0,0 +1,425 @@
+//! Resumable streaming with pluggable state persistence
+//!
+//! Each stream keeps a bounded buffer of its most recent chunks so a client
+//! that reconnects can replay what it missed. The buffer lives in a
+//! [`StreamStateStore`]; backing it with a shared store (Redis, behind the
+//! `redis` feature) lets a reconnect that lands on another replica resume
+//! the stream after a failover.
+
+use crate::{
+    streaming::{ResumeToken, StreamChunk},
+    ConversationError, Result,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// Configuration for resumable streams
+#[derive(Debug, Clone)]
+pub struct ResumableStreamConfig {
+    /// Maximum number of chunks kept per stream
+    pub buffer_size: usize,
+    /// Time-to-live of an in-flight stream's state, refreshed on every chunk
+    pub ttl: Duration,
+    /// Time-to-live of a completed stream's state, so late reconnects can
+    /// still replay the tail
+    pub completed_ttl: Duration,
+}
+
+impl Default for ResumableStreamConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 256,
+            ttl: Duration::from_secs(300),
+            completed_ttl: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Persisted state of a single stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamState {
+    /// Stream identifier
+    pub stream_id: String,
+    /// Sequence number expected for the next chunk
+    pub next_sequence: usize,
+    /// Most recent chunks, oldest first
+    pub chunks: VecDeque<StreamChunk>,
+    /// Whether the final chunk has been recorded
+    pub completed: bool,
+}
+
+impl StreamState {
+    fn new(stream_id: &str) -> Self {
+        Self {
+            stream_id: stream_id.to_string(),
+            next_sequence: 0,
+            chunks: VecDeque::new(),
+            completed: false,
+        }
+    }
+
+    /// Sequence number of the oldest chunk still buffered
+    pub fn first_buffered_sequence(&self) -> Option<usize> {
+        self.chunks.front().map(|c| c.sequence)
+    }
+}
+
+/// Change to a stream's state: given the current state, if any, returns
+/// the new state and the TTL to persist it with
+pub type StateUpdate = Box<dyn FnOnce(Option<StreamState>) -> Result<(StreamState, Duration)> + Send>;
+
+/// Storage backend for stream state
+#[async_trait]
+pub trait StreamStateStore: Send + Sync {
+    /// Load the state of a stream, if present
+    async fn load(&self, stream_id: &str) -> Result<Option<StreamState>>;
+
+    /// Persist the state of a stream, expiring it after `ttl`
+    async fn save(&self, state: &StreamState, ttl: Duration) -> Result<()>;
+
+    /// Load, change and persist the state of a stream as one step
+    ///
+    /// Concurrent updates of a stream must not overwrite each other's
+    /// changes. An update that fails leaves the state untouched.
+    async fn update(&self, stream_id: &str, update: StateUpdate) -> Result<()>;
+
+    /// Remove the state of a stream
+    async fn remove(&self, stream_id: &str) -> Result<()>;
+}
+
+/// Process-local stream state store
+///
+/// State does not survive a restart and is not shared between replicas.
+#[derive(Default)]
+pub struct InMemoryStreamStore {
+    entries: RwLock<HashMap<String, (StreamState, Instant)>>,
+}
+
+impl InMemoryStreamStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StreamStateStore for InMemoryStreamStore {
+    async fn load(&self, stream_id: &str) -> Result<Option<StreamState>> {
+        let mut entries = self.entries.write().await;
+        match entries.get(stream_id) {
+            Some((_, expires_at)) if Instant::now() >= *expires_at => {
+                entries.remove(stream_id);
+                Ok(None)
+            }
+            Some((state, _)) => Ok(Some(state.clone())),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, state: &StreamState, ttl: Duration) -> Result<()> {
+        self.entries
+            .write()
+            .await
+            .insert(state.stream_id.clone(), (state.clone(), Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn update(&self, stream_id: &str, update: StateUpdate) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        let current = match entries.get(stream_id) {
+            Some((_, expires_at)) if Instant::now() >= *expires_at => None,
+            Some((state, _)) => Some(state.clone()),
+            None => None,
+        };
+        let (state, ttl) = update(current)?;
+        entries.insert(state.stream_id.clone(), (state, Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn remove(&self, stream_id: &str) -> Result<()> {
+        self.entries.write().await.remove(stream_id);
+        Ok(())
+    }
+}
+
+/// Redis-backed stream state store, shared across replicas
+///
+/// Updates are serialized within the process. A stream is produced by one
+/// replica at a time; others only read it to resume.
+#[cfg(feature = "redis")]
+pub struct RedisStreamStore {
+    cache: copilot_infra::RedisCache,
+    update_lock: tokio::sync::Mutex<()>,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStreamStore {
+    /// Create a store on top of an existing Redis cache
+    pub fn new(cache: copilot_infra::RedisCache) -> Self {
+        Self {
+            cache,
+            update_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn key(stream_id: &str) -> String {
+        format!("stream:{}", stream_id)
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl StreamStateStore for RedisStreamStore {
+    async fn load(&self, stream_id: &str) -> Result<Option<StreamState>> {
+        use copilot_core::cache::Cache;
+
+        self.cache
+            .get(&Self::key(stream_id))
+            .await
+            .map_err(|e| ConversationError::StreamingError(e.to_string()))
+    }
+
+    async fn save(&self, state: &StreamState, ttl: Duration) -> Result<()> {
+        self.cache
+            .set_with_ttl(&Self::key(&state.stream_id), state, ttl)
+            .await
+            .map_err(|e| ConversationError::StreamingError(e.to_string()))
+    }
+
+    async fn update(&self, stream_id: &str, update: StateUpdate) -> Result<()> {
+        let _guard = self.update_lock.lock().await;
+        let (state, ttl) = update(self.load(stream_id).await?)?;
+        self.save(&state, ttl).await
+    }
+
+    async fn remove(&self, stream_id: &str) -> Result<()> {
+        use copilot_core::cache::Cache;
+
+        self.cache
+            .delete(&Self::key(stream_id))
+            .await
+            .map_err(|e| ConversationError::StreamingError(e.to_string()))
+    }
+}
+
+/// Records stream chunks and replays them to reconnecting clients
+#[derive(Clone)]
+pub struct ResumableStreamManager {
+    store: Arc<dyn StreamStateStore>,
+    config: ResumableStreamConfig,
+}
+
+impl ResumableStreamManager {
+    /// Create a manager over the given store
+    pub fn new(store: Arc<dyn StreamStateStore>, config: ResumableStreamConfig) -> Self {
+        Self { store, config }
+    }
+
+    /// Create a manager over a process-local store
+    pub fn in_memory() -> Self {
+        Self::new(
+            Arc::new(InMemoryStreamStore::new()),
+            ResumableStreamConfig::default(),
+        )
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &ResumableStreamConfig {
+        &self.config
+    }
+
+    /// Record a chunk that has been sent to the client
+    ///
+    /// The buffer keeps at most `buffer_size` chunks. Recording the final
+    /// chunk marks the stream complete and shortens its TTL to
+    /// `completed_ttl`.
+    pub async fn record(&self, stream_id: &str, chunk: StreamChunk) -> Result<()> {
+        let config = self.config.clone();
+        let id = stream_id.to_string();
+        let update: StateUpdate = Box::new(move |state| {
+            let mut state = state.unwrap_or_else(|| StreamState::new(&id));
+            if state.completed {
+                return Err(ConversationError::StreamingError(format!(
+                    "Stream {} is already complete",
+                    id
+                )));
+            }
+
+            state.next_sequence = state.next_sequence.max(chunk.sequence + 1);
+            state.completed = chunk.is_final;
+            state.chunks.push_back(chunk);
+            while state.chunks.len() > config.buffer_size.max(1) {
+                state.chunks.pop_front();
+            }
+
+            let ttl = if state.completed {
+                config.completed_ttl
+            } else {
+                config.ttl
+            };
+            Ok((state, ttl))
+        });
+        self.store.update(stream_id, update).await
+    }
+
+    /// Replay the chunks after `last_sequence`
+    ///
+    /// Pass `None` to replay the whole buffer. Returns `Ok(None)` if the
+    /// stream is unknown or has expired, and an error if chunks the client
+    /// has not seen were already evicted from the buffer.
+    pub async fn resume(
+        &self,
+        stream_id: &str,
+        last_sequence: Option<usize>,
+    ) -> Result<Option<Vec<StreamChunk>>> {
+        let state = match self.store.load(stream_id).await? {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+
+        let from = last_sequence.map(|s| s + 1).unwrap_or(0);
+        if let Some(first) = state.first_buffered_sequence() {
+            if from < first {
+                return Err(ConversationError::StreamingError(format!(
+                    "Stream {} cannot resume from sequence {}: oldest buffered chunk is {}",
+                    stream_id, from, first
+                )));
+            }
+        }
+
+        debug!("Resuming stream {} from sequence {}", stream_id, from);
+        Ok(Some(
+            state
+                .chunks
+                .into_iter()
+                .filter(|c| c.sequence >= from)
+                .collect(),
+        ))
+    }
+
+    /// Replay the chunks after the one a resume token points to
+    ///
+    /// Fails with [`ConversationError::InvalidResumeToken`] if the stream
+    /// is unknown or has expired, or chunks after the token were already
+    /// evicted from the buffer.
+    pub async fn resume_after(&self, token: &ResumeToken) -> Result<Vec<StreamChunk>> {
+        self.resume(&token.stream_id, Some(token.sequence))
+            .await
+            .map_err(|e| match e {
+                ConversationError::StreamingError(reason) => ConversationError::InvalidResumeToken(reason),
+                e => e,
+            })?
+            .ok_or_else(|| {
+                ConversationError::InvalidResumeToken(format!("stream {} has expired", token.stream_id))
+            })
+    }
+
+    /// Get the current state of a stream
+    pub async fn state(&self, stream_id: &str) -> Result<Option<StreamState>> {
+        self.store.load(stream_id).await
+    }
+
+    /// Drop a stream's state, e.g. once the client acknowledged completion
+    pub async fn discard(&self, stream_id: &str) -> Result<()> {
+        self.store.remove(stream_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::StreamUsage;
+
+    fn chunk(sequence: usize, is_final: bool) -> StreamChunk {
+        let chunk = if is_final {
+            StreamChunk::done(StreamUsage::default()).sequence(sequence).build()
+        } else {
+            StreamChunk::token(format!("t{}", sequence)).sequence(sequence).build()
+        };
+        chunk.unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_records_are_not_lost() {
+        let store: Arc<dyn StreamStateStore> = Arc::new(InMemoryStreamStore::new());
+        let managers: Vec<_> = (0..2)
+            .map(|_| ResumableStreamManager::new(store.clone(), ResumableStreamConfig::default()))
+            .collect();
+
+        let tasks: Vec<_> = (0..64)
+            .map(|seq| {
+                let manager = managers[seq % 2].clone();
+                tokio::spawn(async move { manager.record("s1", chunk(seq, false)).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let state = managers[0].state("s1").await.unwrap().unwrap();
+        assert_eq!(state.chunks.len(), 64);
+        assert_eq!(state.next_sequence, 64);
+    }
+
+    #[tokio::test]
+    async fn test_resume_across_managers_sharing_store() {
+        let store: Arc<dyn StreamStateStore> = Arc::new(InMemoryStreamStore::new());
+        let primary = ResumableStreamManager::new(store.clone(), ResumableStreamConfig::default());
+        for seq in 0..4 {
+            primary.record("s1", chunk(seq, false)).await.unwrap();
+        }
+
+        // Client saw chunk 1, then reconnects to a different replica
+        let replica = ResumableStreamManager::new(store, ResumableStreamConfig::default());
+        let replay = replica.resume("s1", Some(1)).await.unwrap().unwrap();
+        let sequences: Vec<_> = replay.iter().map(|c| c.sequence).collect();
+        assert_eq!(sequences, vec![2, 3]);
+
+        replica.record("s1", chunk(4, true)).await.unwrap();
+        let state = primary.state("s1").await.unwrap().unwrap();
+        assert!(state.completed);
+        assert_eq!(state.next_sequence, 5);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_is_bounded() {
+        let manager = ResumableStreamManager::new(
+            Arc::new(InMemoryStreamStore::new()),
+            ResumableStreamConfig {
+                buffer_size: 3,
+                ..Default::default()
+            },
+        );
+        for seq in 0..10 {
+            manager.record("s1", chunk(seq, false)).await.unwrap();
+        }
+
+        let state = manager.state("s1").await.unwrap().unwrap();
+        assert_eq!(state.chunks.len(), 3);
+        assert_eq!(state.first_buffered_sequence(), Some(7));
+        assert!(manager.resume("s1", Some(2)).await.is_err());
+        assert_eq!(manager.resume("s1", Some(6)).await.unwrap().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_completed_stream_expires() {
+        let manager = ResumableStreamManager::new(
+            Arc::new(InMemoryStreamStore::new()),
+            ResumableStreamConfig {
+                completed_ttl: Duration::from_millis(20),
+                ..Default::default()
+            },
+        );
+        manager.record("s1", chunk(0, false)).await.unwrap();
+        manager.record("s1", chunk(1, true)).await.unwrap();
+        assert!(manager.record("s1", chunk(2, false)).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(manager.resume("s1", None).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,73 @@
+//! Safety classification of incoming messages
+//!
+//! Some deployments must keep unsafe input (profanity, abuse, prompt
+//! injection, data they may not process) from reaching the model or the
+//! stored history. A [`SafetyClassifier`] screens each incoming message,
+//! and the text extracted from its attachments, before the turn does any
+//! work and returns a [`SafetyVerdict`]:
+//!
+//! - [`SafetyVerdict::Allow`]: the message is processed as usual
+//! - [`SafetyVerdict::Flag`]: the message is processed and stored with its
+//!   reason under the [`SAFETY_FLAG_KEY`] metadata key, for later review;
+//!   a streamed response carries the reason on its final chunk
+//! - [`SafetyVerdict::Block`]: the turn fails with
+//!   [`ConversationError::MessageBlocked`](crate::ConversationError::MessageBlocked)
+//!   and nothing is stored
+//!
+//! Classifiers may call external services, so they are async and may fail.
+//! What happens to a message whose classification failed is set by a
+//! [`SafetyFailurePolicy`].
+
+use crate::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Message metadata key holding the reason a message was flagged
+pub const SAFETY_FLAG_KEY: &str = "safety_flag";
+
+/// Outcome of screening a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum SafetyVerdict {
+    /// Process the message
+    Allow,
+    /// Process the message and mark it for review
+    Flag {
+        /// Why the message was flagged
+        reason: String,
+    },
+    /// Reject the message
+    Block {
+        /// Why the message was blocked
+        reason: String,
+    },
+}
+
+/// Screens incoming messages before they are processed
+#[async_trait]
+pub trait SafetyClassifier: Send + Sync {
+    /// Classify a normalized user message
+    async fn classify(&self, message: &str) -> Result<SafetyVerdict>;
+}
+
+/// Classifier that allows every message
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllowAllClassifier;
+
+#[async_trait]
+impl SafetyClassifier for AllowAllClassifier {
+    async fn classify(&self, _message: &str) -> Result<SafetyVerdict> {
+        Ok(SafetyVerdict::Allow)
+    }
+}
+
+/// What happens to a message when its classifier fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyFailurePolicy {
+    /// Block the message
+    #[default]
+    Block,
+    /// Allow the message
+    Allow,
+}
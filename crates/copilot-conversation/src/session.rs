@@ -1,9 +1,11 @@
 //! Session management for conversation tracking
 
-use crate::{Result, ConversationError};
+use crate::{history::ExportFormat, Result, ConversationError};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
@@ -18,6 +20,383 @@ pub enum SessionState {
     Expired,
 }
 
+/// A resource dimension limited by a [`ResourceQuota`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuotaKind {
+    /// Number of messages in a session
+    Messages,
+    /// Total attachment bytes in a session
+    AttachmentBytes,
+    /// Number of conversations owned by a user
+    Conversations,
+}
+
+impl std::fmt::Display for QuotaKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaKind::Messages => write!(f, "messages"),
+            QuotaKind::AttachmentBytes => write!(f, "attachment_bytes"),
+            QuotaKind::Conversations => write!(f, "conversations"),
+        }
+    }
+}
+
+/// Resource limits for a session beyond its token budget
+///
+/// A `None` limit is unbounded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceQuota {
+    /// Maximum number of messages in the session
+    pub max_messages: Option<usize>,
+    /// Maximum total size of message attachments in bytes
+    pub max_attachment_bytes: Option<usize>,
+    /// Maximum number of conversations the session's user may own
+    pub max_conversations: Option<usize>,
+}
+
+impl ResourceQuota {
+    /// Set the maximum number of messages
+    pub fn with_max_messages(mut self, max: usize) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    /// Set the maximum total attachment bytes
+    pub fn with_max_attachment_bytes(mut self, max: usize) -> Self {
+        self.max_attachment_bytes = Some(max);
+        self
+    }
+
+    /// Set the maximum number of conversations per user
+    pub fn with_max_conversations(mut self, max: usize) -> Self {
+        self.max_conversations = Some(max);
+        self
+    }
+
+    fn check(kind: QuotaKind, limit: Option<usize>, requested: usize) -> Result<()> {
+        match limit {
+            Some(limit) if requested > limit => Err(ConversationError::QuotaExceeded {
+                quota: kind,
+                requested,
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resources consumed by a session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Number of messages recorded
+    pub messages: usize,
+    /// Total attachment bytes recorded
+    pub attachment_bytes: usize,
+}
+
+/// Kind of activity recorded in a session's timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// The session was created
+    Created,
+    /// Messages were added to the session
+    MessagesAdded { count: usize },
+    /// A metadata entry (e.g. a preference) was set
+    MetadataChanged { key: String },
+    /// The session's context was compressed
+    Compressed { tokens_before: usize, tokens_after: usize },
+    /// A branch was forked from the session
+    Forked { branch_session_id: String },
+    /// The session was rewound to a checkpoint
+    CheckpointRestored { checkpoint_id: String, label: String },
+    /// The session switched models and its token usage was recounted
+    ModelChanged { model: String, tokens_before: usize, tokens_after: usize },
+    /// Ownership of the session was handed to another user
+    Transferred { from: Option<String>, to: String },
+    /// The session expired
+    Expired,
+}
+
+impl std::fmt::Display for SessionEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionEventKind::Created => write!(f, "created"),
+            SessionEventKind::MessagesAdded { count } => write!(f, "{} message(s) added", count),
+            SessionEventKind::MetadataChanged { key } => write!(f, "metadata '{}' changed", key),
+            SessionEventKind::Compressed { tokens_before, tokens_after } => {
+                write!(f, "compressed from {} to {} tokens", tokens_before, tokens_after)
+            }
+            SessionEventKind::Forked { branch_session_id } => {
+                write!(f, "forked into {}", branch_session_id)
+            }
+            SessionEventKind::CheckpointRestored { label, .. } => {
+                write!(f, "restored to checkpoint '{}'", label)
+            }
+            SessionEventKind::ModelChanged { model, .. } => write!(f, "switched to model {}", model),
+            SessionEventKind::Transferred { from: Some(from), to } => {
+                write!(f, "transferred from {} to {}", from, to)
+            }
+            SessionEventKind::Transferred { from: None, to } => write!(f, "assigned to {}", to),
+            SessionEventKind::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// An entry in a session's timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// When the event happened
+    pub timestamp: DateTime<Utc>,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+}
+
+/// Bounded, chronological log of session activity
+///
+/// Once `capacity` events are recorded, each new event evicts the oldest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTimeline {
+    events: VecDeque<SessionEvent>,
+    capacity: usize,
+    /// Number of events evicted to stay within capacity
+    #[serde(default)]
+    dropped: usize,
+}
+
+impl Default for SessionTimeline {
+    fn default() -> Self {
+        Self::with_capacity(100)
+    }
+}
+
+impl SessionTimeline {
+    /// Create an empty timeline holding at most `capacity` events
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity.min(16)),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    /// Record an event
+    pub fn record(&mut self, kind: SessionEventKind) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(SessionEvent {
+            timestamp: Utc::now(),
+            kind,
+        });
+    }
+
+    /// Recorded events, oldest first
+    pub fn events(&self) -> impl Iterator<Item = &SessionEvent> {
+        self.events.iter()
+    }
+
+    /// Number of events currently held
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether the timeline holds no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Number of events evicted to stay within capacity
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Render the timeline in an export format
+    pub fn export(&self, format: ExportFormat) -> Result<String> {
+        let output = match format {
+            ExportFormat::Json => serde_json::to_string_pretty(&self.events)?,
+            ExportFormat::Markdown => {
+                let mut output = String::from("# Session Timeline\n\n");
+                for event in &self.events {
+                    output.push_str(&format!(
+                        "- {} - {}\n",
+                        event.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                        event.kind
+                    ));
+                }
+                output
+            }
+            ExportFormat::Text => {
+                let mut output = String::from("Session Timeline\n");
+                output.push_str(&"=".repeat(50));
+                output.push_str("\n\n");
+                for event in &self.events {
+                    output.push_str(&format!(
+                        "[{}] {}\n",
+                        event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                        event.kind
+                    ));
+                }
+                output
+            }
+            ExportFormat::Csv => {
+                let mut output = String::from("timestamp,event\n");
+                for event in &self.events {
+                    output.push_str(&format!(
+                        "{},\"{}\"\n",
+                        event.timestamp.to_rfc3339(),
+                        event.kind.to_string().replace('"', "\"\"")
+                    ));
+                }
+                output
+            }
+        };
+        Ok(output)
+    }
+}
+
+/// What a session's previous owner may do after a transfer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviousOwnerAccess {
+    /// The previous owner loses access
+    #[default]
+    Revoke,
+    /// The previous owner keeps read access to the history
+    ReadOnly,
+}
+
+/// Audit record of a session changing owner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTransfer {
+    /// Owner before the transfer, `None` if the session had none
+    pub from: Option<String>,
+    /// Owner after the transfer
+    pub to: String,
+    /// When the transfer happened
+    pub transferred_at: DateTime<Utc>,
+    /// Access the previous owner kept
+    pub previous_owner_access: PreviousOwnerAccess,
+}
+
+/// A session crossing a token budget warning threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetWarning {
+    /// Session that crossed the threshold
+    pub session_id: String,
+    /// Crossed threshold as a fraction of the budget, e.g. 0.85
+    pub threshold: f64,
+    /// Tokens used when the threshold was crossed
+    pub used: usize,
+    /// The session's token budget
+    pub limit: usize,
+}
+
+/// Default fraction of its token budget at which a session reports a
+/// [`TokenWarning`]
+pub const DEFAULT_SOFT_THRESHOLD: f64 = 0.9;
+
+fn default_soft_threshold() -> f64 {
+    DEFAULT_SOFT_THRESHOLD
+}
+
+/// A session's usage at or above its soft token threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenWarning {
+    /// Soft threshold as a fraction of the budget, e.g. 0.9
+    pub threshold: f64,
+    /// Tokens used
+    pub used: usize,
+    /// The session's token budget
+    pub limit: usize,
+    /// Tokens left before the limit
+    pub remaining: usize,
+}
+
+/// Callback invoked for each budget warning
+pub type BudgetWarningHook = Arc<dyn Fn(&BudgetWarning) + Send + Sync>;
+
+/// Tiered warnings before a session reaches its token limit
+///
+/// Each threshold fires once when usage crosses it. A threshold re-arms
+/// when usage drops back below it, e.g. after the context was compressed,
+/// and fires again on the next crossing.
+#[derive(Clone)]
+pub struct BudgetWarningPolicy {
+    thresholds: Vec<f64>,
+    hook: Option<BudgetWarningHook>,
+}
+
+impl BudgetWarningPolicy {
+    /// Create a policy warning at the given fractions of the budget
+    ///
+    /// Thresholds outside `(0, 1]` are ignored.
+    pub fn new(thresholds: impl IntoIterator<Item = f64>) -> Self {
+        let mut thresholds: Vec<f64> = thresholds
+            .into_iter()
+            .filter(|t| *t > 0.0 && *t <= 1.0)
+            .collect();
+        thresholds.sort_by(f64::total_cmp);
+        thresholds.dedup();
+        Self {
+            thresholds,
+            hook: None,
+        }
+    }
+
+    /// Set the callback receiving warnings
+    pub fn with_hook(mut self, hook: impl Fn(&BudgetWarning) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Warning thresholds in ascending order
+    pub fn thresholds(&self) -> &[f64] {
+        &self.thresholds
+    }
+
+    /// Number of thresholds a usage level has crossed
+    fn level(&self, used: usize, limit: usize) -> usize {
+        if limit == 0 {
+            return self.thresholds.len();
+        }
+        let fraction = used as f64 / limit as f64;
+        self.thresholds.iter().take_while(|t| fraction >= **t).count()
+    }
+}
+
+impl Default for BudgetWarningPolicy {
+    fn default() -> Self {
+        Self::new([0.70, 0.85, 0.95])
+    }
+}
+
+impl std::fmt::Debug for BudgetWarningPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BudgetWarningPolicy")
+            .field("thresholds", &self.thresholds)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+/// Where a forked session branched off its parent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchOrigin {
+    /// Session the branch was forked from
+    pub parent_session_id: String,
+    /// Root session of the fork tree
+    pub root_session_id: String,
+    /// Last parent message carried into the branch, if the parent had any
+    pub message_id: Option<String>,
+}
+
 /// A conversation session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -36,6 +415,37 @@ pub struct Session {
     /// Session metadata
     #[serde(default)]
     pub metadata: HashMap<String, String>,
+    /// User that owns this session
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Resource limits for this session
+    #[serde(default)]
+    pub quota: ResourceQuota,
+    /// Resources consumed against the quota
+    #[serde(default)]
+    pub usage: ResourceUsage,
+    /// Origin of this session if it was forked from another
+    #[serde(default)]
+    pub branch_origin: Option<BranchOrigin>,
+    /// Recent activity on this session
+    #[serde(default)]
+    pub timeline: SessionTimeline,
+    /// Model the session talks to; `None` uses the default model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Number of budget warning thresholds the session's usage has crossed
+    #[serde(default)]
+    pub budget_warning_level: usize,
+    /// Fraction of the token budget from which [`Session::token_warning`]
+    /// reports the remaining headroom
+    #[serde(default = "default_soft_threshold")]
+    pub soft_threshold: f64,
+    /// Users other than the owner who may read the session's history
+    #[serde(default)]
+    pub readers: Vec<String>,
+    /// Ownership transfers, oldest first; unlike the timeline, never evicted
+    #[serde(default)]
+    pub transfers: Vec<SessionTransfer>,
 }
 
 impl Session {
@@ -50,6 +460,16 @@ impl Session {
             total_tokens: 0,
             max_tokens,
             metadata: HashMap::new(),
+            user_id: None,
+            quota: ResourceQuota::default(),
+            usage: ResourceUsage::default(),
+            branch_origin: None,
+            timeline: SessionTimeline::default(),
+            model: None,
+            budget_warning_level: 0,
+            soft_threshold: DEFAULT_SOFT_THRESHOLD,
+            readers: Vec::new(),
+            transfers: Vec::new(),
         }
     }
 
@@ -64,6 +484,16 @@ impl Session {
             total_tokens: 0,
             max_tokens,
             metadata: HashMap::new(),
+            user_id: None,
+            quota: ResourceQuota::default(),
+            usage: ResourceUsage::default(),
+            branch_origin: None,
+            timeline: SessionTimeline::default(),
+            model: None,
+            budget_warning_level: 0,
+            soft_threshold: DEFAULT_SOFT_THRESHOLD,
+            readers: Vec::new(),
+            transfers: Vec::new(),
         }
     }
 
@@ -80,8 +510,8 @@ impl Session {
         }
     }
 
-    /// Add tokens to the session count
-    pub fn add_tokens(&mut self, count: usize) -> Result<()> {
+    /// Check whether tokens fit within the session's limit without adding them
+    pub fn check_tokens(&self, count: usize) -> Result<()> {
         let new_total = self.total_tokens + count;
         if new_total > self.max_tokens {
             return Err(ConversationError::TokenLimitExceeded {
@@ -89,14 +519,107 @@ impl Session {
                 limit: self.max_tokens,
             });
         }
-        self.total_tokens = new_total;
         Ok(())
     }
 
+    /// Add tokens to the session count
+    pub fn add_tokens(&mut self, count: usize) -> Result<()> {
+        self.check_tokens(count)?;
+        self.total_tokens += count;
+        Ok(())
+    }
+
+    /// Whether a user may read the session's history
+    ///
+    /// Sessions without an owner are readable by anyone.
+    pub fn can_read(&self, user_id: &str) -> bool {
+        self.can_write(user_id) || self.readers.iter().any(|r| r == user_id)
+    }
+
+    /// Whether a user may add to the session
+    ///
+    /// Only the owner may, or anyone if the session has no owner.
+    pub fn can_write(&self, user_id: &str) -> bool {
+        self.user_id.as_deref().map_or(true, |owner| owner == user_id)
+    }
+
+    /// Fail with `AccessDenied` unless the user may read the session
+    ///
+    /// Owned sessions refuse callers that do not name a user.
+    pub fn check_read(&self, user_id: Option<&str>) -> Result<()> {
+        self.check_access(user_id, Self::can_read)
+    }
+
+    /// Fail with `AccessDenied` unless the user may add to the session
+    ///
+    /// Owned sessions refuse callers that do not name a user.
+    pub fn check_write(&self, user_id: Option<&str>) -> Result<()> {
+        self.check_access(user_id, Self::can_write)
+    }
+
+    fn check_access(&self, user_id: Option<&str>, allowed: fn(&Self, &str) -> bool) -> Result<()> {
+        let permitted = match user_id {
+            Some(user_id) => allowed(self, user_id),
+            None => self.user_id.is_none(),
+        };
+        if permitted {
+            Ok(())
+        } else {
+            Err(ConversationError::AccessDenied {
+                session_id: self.id.clone(),
+                user: user_id.unwrap_or("anonymous").to_string(),
+            })
+        }
+    }
+
     /// Get remaining tokens
     pub fn remaining_tokens(&self) -> usize {
         self.max_tokens.saturating_sub(self.total_tokens)
     }
+
+    /// Headroom left once usage reaches the soft token threshold
+    ///
+    /// Unlike the one-off [`BudgetWarning`]s of a [`BudgetWarningPolicy`],
+    /// this reflects the current usage: it is reported for as long as
+    /// usage stays at or above the threshold, and clears once the context
+    /// is trimmed below it. Callers can poll it to summarize before
+    /// [`ConversationError::TokenLimitExceeded`] fails a turn.
+    pub fn token_warning(&self) -> Option<TokenWarning> {
+        let threshold = self.soft_threshold;
+        let crossed = self.max_tokens == 0
+            || self.total_tokens as f64 >= self.max_tokens as f64 * threshold;
+        crossed.then(|| TokenWarning {
+            threshold,
+            used: self.total_tokens,
+            limit: self.max_tokens,
+            remaining: self.remaining_tokens(),
+        })
+    }
+
+    /// Check whether messages and attachment bytes fit within the quota
+    /// without recording them
+    pub fn check_message_quota(&self, messages: usize, attachment_bytes: usize) -> Result<()> {
+        ResourceQuota::check(
+            QuotaKind::Messages,
+            self.quota.max_messages,
+            self.usage.messages + messages,
+        )?;
+        ResourceQuota::check(
+            QuotaKind::AttachmentBytes,
+            self.quota.max_attachment_bytes,
+            self.usage.attachment_bytes + attachment_bytes,
+        )
+    }
+
+    /// Record messages and attachment bytes against the quota
+    ///
+    /// Nothing is recorded if either limit would be exceeded.
+    pub fn record_messages(&mut self, messages: usize, attachment_bytes: usize) -> Result<()> {
+        self.check_message_quota(messages, attachment_bytes)?;
+        self.usage.messages += messages;
+        self.usage.attachment_bytes += attachment_bytes;
+        Ok(())
+    }
 }
 
 /// Configuration for session management
@@ -110,6 +633,23 @@ pub struct SessionConfig {
     pub default_max_tokens: usize,
     /// Cleanup interval (in seconds)
     pub cleanup_interval_seconds: u64,
+    /// Default resource quota for new sessions
+    #[serde(default)]
+    pub default_quota: ResourceQuota,
+    /// Maximum number of events kept in each session's timeline
+    #[serde(default = "default_timeline_capacity")]
+    pub timeline_capacity: usize,
+    /// Access a session's previous owner keeps after a transfer
+    #[serde(default)]
+    pub previous_owner_access: PreviousOwnerAccess,
+    /// Fraction of the token budget from which new sessions report a
+    /// [`TokenWarning`]
+    #[serde(default = "default_soft_threshold")]
+    pub soft_threshold: f64,
+}
+
+fn default_timeline_capacity() -> usize {
+    100
 }
 
 impl Default for SessionConfig {
@@ -119,6 +659,10 @@ impl Default for SessionConfig {
             idle_timeout_seconds: 300,  // 5 minutes
             default_max_tokens: 100_000, // 100k tokens
             cleanup_interval_seconds: 300, // 5 minutes
+            default_quota: ResourceQuota::default(),
+            timeline_capacity: default_timeline_capacity(),
+            previous_owner_access: PreviousOwnerAccess::default(),
+            soft_threshold: DEFAULT_SOFT_THRESHOLD,
         }
     }
 }
@@ -127,6 +671,9 @@ impl Default for SessionConfig {
 pub struct SessionManager {
     sessions: HashMap<String, Session>,
     config: SessionConfig,
+    /// Per-session mutation locks, created on first use
+    locks: HashMap<String, Arc<Mutex<()>>>,
+    budget_warnings: BudgetWarningPolicy,
 }
 
 impl SessionManager {
@@ -140,16 +687,25 @@ impl SessionManager {
         Self {
             sessions: HashMap::new(),
             config,
+            locks: HashMap::new(),
+            budget_warnings: BudgetWarningPolicy::default(),
         }
     }
 
+    /// Set the token budget warning policy
+    pub fn with_budget_warning_policy(mut self, policy: BudgetWarningPolicy) -> Self {
+        self.budget_warnings = policy;
+        self
+    }
+
     /// Create a new session
     ///
     /// # Arguments
     ///
     /// * `max_tokens` - Optional maximum tokens for this session (uses default if None)
     pub fn create_session(&mut self, max_tokens: Option<usize>) -> Session {
-        let session = Session::new(max_tokens.unwrap_or(self.config.default_max_tokens));
+        let mut session = Session::new(max_tokens.unwrap_or(self.config.default_max_tokens));
+        self.prepare(&mut session);
         info!("Created new session: {}", session.id);
         self.sessions.insert(session.id.clone(), session.clone());
         session
@@ -157,23 +713,162 @@ impl SessionManager {
 
     /// Create a session with a specific ID
     ///
+    /// Creation is idempotent so clients can retry it: if a session with
+    /// the ID already exists with the same configuration, it is returned
+    /// unchanged. If it exists with a different token limit, or has
+    /// expired, creation fails with `SessionConflict`.
+    ///
     /// # Arguments
     ///
     /// * `id` - The session ID to use
     /// * `max_tokens` - Optional maximum tokens for this session
     pub fn create_session_with_id(&mut self, id: String, max_tokens: Option<usize>) -> Result<Session> {
-        if self.sessions.contains_key(&id) {
-            return Err(ConversationError::InvalidMessage(
-                format!("Session {} already exists", id)
-            ));
+        let max_tokens = max_tokens.unwrap_or(self.config.default_max_tokens);
+
+        if let Some(existing) = self.get_session(&id) {
+            let conflict = |reason: String| ConversationError::SessionConflict {
+                id: id.clone(),
+                reason,
+            };
+            if existing.state == SessionState::Expired {
+                return Err(conflict("the session has expired".to_string()));
+            }
+            if existing.max_tokens != max_tokens {
+                return Err(conflict(format!(
+                    "max_tokens is {}, requested {}",
+                    existing.max_tokens, max_tokens
+                )));
+            }
+            debug!("Session {} already exists, returning it", id);
+            return Ok(existing.clone());
         }
 
-        let session = Session::with_id(id.clone(), max_tokens.unwrap_or(self.config.default_max_tokens));
+        let mut session = Session::with_id(id.clone(), max_tokens);
+        self.prepare(&mut session);
         info!("Created new session with ID: {}", session.id);
         self.sessions.insert(id, session.clone());
         Ok(session)
     }
 
+    /// Create a new session owned by a user
+    ///
+    /// Fails with `QuotaExceeded` if the user already owns the maximum number
+    /// of conversations allowed by the default quota.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The owning user
+    /// * `max_tokens` - Optional maximum tokens for this session
+    pub fn create_user_session(&mut self, user_id: &str, max_tokens: Option<usize>) -> Result<Session> {
+        self.check_conversation_quota(user_id)?;
+
+        let mut session = Session::new(max_tokens.unwrap_or(self.config.default_max_tokens));
+        self.prepare(&mut session);
+        session.user_id = Some(user_id.to_string());
+        info!("Created new session {} for user {}", session.id, user_id);
+        self.sessions.insert(session.id.clone(), session.clone());
+        Ok(session)
+    }
+
+    /// Fail with `QuotaExceeded` if the user cannot own another conversation
+    fn check_conversation_quota(&self, user_id: &str) -> Result<()> {
+        ResourceQuota::check(
+            QuotaKind::Conversations,
+            self.config.default_quota.max_conversations,
+            self.user_session_count(user_id) + 1,
+        )
+    }
+
+    /// Apply the configured defaults to a new session and record its creation
+    fn prepare(&self, session: &mut Session) {
+        session.quota = self.config.default_quota.clone();
+        session.soft_threshold = self.config.soft_threshold;
+        session.timeline = SessionTimeline::with_capacity(self.config.timeline_capacity);
+        session.timeline.record(SessionEventKind::Created);
+    }
+
+    /// Fork a session into a new branch
+    ///
+    /// The branch inherits the parent's owner, limits and metadata, and
+    /// records where it branched off. It counts towards the owner's
+    /// conversation quota like any other session they own. History is
+    /// copied separately by the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent_id` - The session to fork
+    /// * `message_id` - The last parent message carried into the branch
+    pub fn fork_session(&mut self, parent_id: &str, message_id: Option<String>) -> Result<Session> {
+        let parent = self.sessions
+            .get(parent_id)
+            .ok_or_else(|| ConversationError::SessionNotFound(parent_id.to_string()))?;
+        if let Some(owner) = &parent.user_id {
+            self.check_conversation_quota(owner)?;
+        }
+
+        let root_session_id = parent
+            .branch_origin
+            .as_ref()
+            .map(|origin| origin.root_session_id.clone())
+            .unwrap_or_else(|| parent.id.clone());
+
+        let mut session = Session::new(parent.max_tokens);
+        self.prepare(&mut session);
+        session.quota = parent.quota.clone();
+        session.soft_threshold = parent.soft_threshold;
+        session.user_id = parent.user_id.clone();
+        session.metadata = parent.metadata.clone();
+        session.model = parent.model.clone();
+        session.branch_origin = Some(BranchOrigin {
+            parent_session_id: parent.id.clone(),
+            root_session_id,
+            message_id,
+        });
+
+        info!("Forked session {} from {}", session.id, parent_id);
+        self.sessions.insert(session.id.clone(), session.clone());
+        self.record_event(
+            parent_id,
+            SessionEventKind::Forked { branch_session_id: session.id.clone() },
+        )?;
+        Ok(session)
+    }
+
+    /// Get the root session and every branch forked from it, directly or not
+    pub fn branch_sessions(&self, root_id: &str) -> Vec<&Session> {
+        self.sessions
+            .values()
+            .filter(|s| {
+                s.id == root_id
+                    || s.branch_origin
+                        .as_ref()
+                        .is_some_and(|origin| origin.root_session_id == root_id)
+            })
+            .collect()
+    }
+
+    /// Get the sessions owned by a user
+    pub fn user_sessions(&self, user_id: &str) -> Vec<&Session> {
+        self.sessions
+            .values()
+            .filter(|s| s.user_id.as_deref() == Some(user_id))
+            .collect()
+    }
+
+    /// Get the sessions a user may read: those they own and those they
+    /// were given read access to
+    pub fn readable_sessions(&self, user_id: &str) -> Vec<&Session> {
+        self.sessions
+            .values()
+            .filter(|s| s.user_id.is_some() && s.can_read(user_id))
+            .collect()
+    }
+
+    /// Get the number of sessions owned by a user
+    pub fn user_session_count(&self, user_id: &str) -> usize {
+        self.user_sessions(user_id).len()
+    }
+
     /// Get an existing session
     ///
     /// # Arguments
@@ -186,6 +881,9 @@ impl SessionManager {
             let expire_duration = Duration::seconds(self.config.timeout_seconds);
 
             if session.is_expired(expire_duration) {
+                if session.state != SessionState::Expired {
+                    session.timeline.record(SessionEventKind::Expired);
+                }
                 session.state = SessionState::Expired;
                 debug!("Session {} marked as expired", id);
             } else if session.is_expired(idle_duration) && session.state == SessionState::Active {
@@ -224,9 +922,202 @@ impl SessionManager {
             session.remaining_tokens()
         );
 
+        self.check_budget(id);
+        Ok(())
+    }
+
+    /// Record that a session's context was compressed to `tokens_after`
+    /// tokens
+    pub fn record_compression(&mut self, id: &str, tokens_after: usize) -> Result<()> {
+        let session = self.sessions
+            .get_mut(id)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))?;
+
+        let tokens_before = session.total_tokens;
+        session.total_tokens = tokens_after;
+        session.timeline.record(SessionEventKind::Compressed { tokens_before, tokens_after });
+        info!("Session {} compressed: {} -> {} tokens", id, tokens_before, tokens_after);
+
+        self.check_budget(id);
         Ok(())
     }
 
+    /// A session's token warning, if its usage is at or above its soft
+    /// threshold
+    pub fn token_warning(&self, id: &str) -> Result<Option<TokenWarning>> {
+        self.sessions
+            .get(id)
+            .map(Session::token_warning)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))
+    }
+
+    /// Fire warnings for budget thresholds a session newly crossed, and
+    /// re-arm those its usage dropped below
+    fn check_budget(&mut self, id: &str) {
+        let Some(session) = self.sessions.get_mut(id) else {
+            return;
+        };
+        let level = self.budget_warnings.level(session.total_tokens, session.max_tokens);
+        let previous = std::mem::replace(&mut session.budget_warning_level, level);
+
+        for &threshold in self.budget_warnings.thresholds.get(previous..level).unwrap_or_default() {
+            let warning = BudgetWarning {
+                session_id: id.to_string(),
+                threshold,
+                used: session.total_tokens,
+                limit: session.max_tokens,
+            };
+            warn!(
+                "Session {} crossed {:.0}% of its token budget: {} / {} tokens",
+                id,
+                threshold * 100.0,
+                warning.used,
+                warning.limit
+            );
+            if let Some(hook) = &self.budget_warnings.hook {
+                hook(&warning);
+            }
+        }
+    }
+
+    /// Hand a session over to a new owner
+    ///
+    /// The new owner gets full access. The previous owner keeps read
+    /// access or loses it, as set by
+    /// [`SessionConfig::previous_owner_access`]. The transfer is recorded
+    /// in the session's timeline and in its transfer log.
+    ///
+    /// Fails with `QuotaExceeded` if the new owner already owns the
+    /// maximum number of conversations, and with `SessionConflict` if the
+    /// session has expired or already belongs to the new owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The session ID
+    /// * `new_owner` - The user taking over the session
+    pub fn transfer(&mut self, id: &str, new_owner: &str) -> Result<SessionTransfer> {
+        let session = self
+            .get_session(id)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))?;
+        let conflict = |reason: String| ConversationError::SessionConflict {
+            id: id.to_string(),
+            reason,
+        };
+        if session.state == SessionState::Expired {
+            return Err(conflict("the session has expired".to_string()));
+        }
+        if session.user_id.as_deref() == Some(new_owner) {
+            return Err(conflict(format!("the session already belongs to {}", new_owner)));
+        }
+        ResourceQuota::check(
+            QuotaKind::Conversations,
+            self.config.default_quota.max_conversations,
+            self.user_session_count(new_owner) + 1,
+        )?;
+
+        let access = self.config.previous_owner_access;
+        let session = self.sessions.get_mut(id).expect("session exists");
+        let from = session.user_id.replace(new_owner.to_string());
+        session.readers.retain(|reader| reader != new_owner);
+        if let (Some(from), PreviousOwnerAccess::ReadOnly) = (&from, access) {
+            if !session.readers.contains(from) {
+                session.readers.push(from.clone());
+            }
+        }
+
+        let transfer = SessionTransfer {
+            from: from.clone(),
+            to: new_owner.to_string(),
+            transferred_at: Utc::now(),
+            previous_owner_access: access,
+        };
+        session.transfers.push(transfer.clone());
+        session.timeline.record(SessionEventKind::Transferred {
+            from: from.clone(),
+            to: new_owner.to_string(),
+        });
+        session.touch();
+        info!(
+            "Session {} transferred from {} to {} (previous owner access: {:?})",
+            id,
+            from.as_deref().unwrap_or("<none>"),
+            new_owner,
+            access
+        );
+        Ok(transfer)
+    }
+
+    /// Record messages and attachment bytes against a session's quota
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The session ID
+    /// * `messages` - Number of messages being added
+    /// * `attachment_bytes` - Total size of the messages' attachments
+    pub fn record_messages(&mut self, id: &str, messages: usize, attachment_bytes: usize) -> Result<()> {
+        let session = self.sessions
+            .get_mut(id)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))?;
+
+        session.record_messages(messages, attachment_bytes)?;
+        if messages > 0 {
+            session.timeline.record(SessionEventKind::MessagesAdded { count: messages });
+        }
+        Ok(())
+    }
+
+    /// Set a metadata entry on a session, e.g. a user preference
+    pub fn set_metadata(&mut self, id: &str, key: &str, value: &str) -> Result<()> {
+        let session = self.sessions
+            .get_mut(id)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))?;
+
+        session.metadata.insert(key.to_string(), value.to_string());
+        session.timeline.record(SessionEventKind::MetadataChanged { key: key.to_string() });
+        Ok(())
+    }
+
+    /// Switch a session's model, replacing its token usage with the
+    /// usage recounted for the new model
+    pub fn set_model(&mut self, id: &str, model: &str, total_tokens: usize) -> Result<()> {
+        let session = self.sessions
+            .get_mut(id)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))?;
+
+        let tokens_before = session.total_tokens;
+        session.model = Some(model.to_string());
+        session.total_tokens = total_tokens;
+        session.timeline.record(SessionEventKind::ModelChanged {
+            model: model.to_string(),
+            tokens_before,
+            tokens_after: total_tokens,
+        });
+        info!(
+            "Session {} switched to model {}: {} -> {} tokens",
+            id, model, tokens_before, total_tokens
+        );
+        self.check_budget(id);
+        Ok(())
+    }
+
+    /// Record an event on a session's timeline
+    ///
+    /// Used for activity that happens outside the session manager, such as
+    /// context compression.
+    pub fn record_event(&mut self, id: &str, kind: SessionEventKind) -> Result<()> {
+        let session = self.sessions
+            .get_mut(id)
+            .ok_or_else(|| ConversationError::SessionNotFound(id.to_string()))?;
+
+        session.timeline.record(kind);
+        Ok(())
+    }
+
+    /// Get a session's activity timeline
+    pub fn timeline(&self, id: &str) -> Option<&SessionTimeline> {
+        self.sessions.get(id).map(|s| &s.timeline)
+    }
+
     /// Delete a session
     ///
     /// # Arguments
@@ -234,27 +1125,48 @@ impl SessionManager {
     /// * `id` - The session ID to delete
     pub fn delete_session(&mut self, id: &str) -> Option<Session> {
         info!("Deleting session: {}", id);
+        self.locks.remove(id);
         self.sessions.remove(id)
     }
 
+    /// Get the mutation lock for a session
+    ///
+    /// Holders of the lock have exclusive rights to mutate the session and
+    /// its history. The lock is fair: waiters acquire it in FIFO order.
+    /// Returns `None` if the session does not exist.
+    pub fn session_lock(&mut self, id: &str) -> Option<Arc<Mutex<()>>> {
+        if !self.sessions.contains_key(id) {
+            return None;
+        }
+        Some(Arc::clone(self.locks.entry(id.to_string()).or_default()))
+    }
+
     /// Clean up expired sessions
     ///
     /// Returns the number of sessions removed
     pub fn cleanup_expired(&mut self) -> usize {
+        self.remove_expired().len()
+    }
+
+    /// Remove expired sessions, returning their IDs
+    pub fn remove_expired(&mut self) -> Vec<String> {
         let expire_duration = Duration::seconds(self.config.timeout_seconds);
-        let before_count = self.sessions.len();
+        let mut removed = Vec::new();
 
         self.sessions.retain(|id, session| {
             let expired = session.is_expired(expire_duration);
             if expired {
                 info!("Removing expired session: {}", id);
+                removed.push(id.clone());
             }
             !expired
         });
 
-        let removed = before_count - self.sessions.len();
-        if removed > 0 {
-            info!("Cleaned up {} expired sessions", removed);
+        let sessions = &self.sessions;
+        self.locks.retain(|id, _| sessions.contains_key(id));
+
+        if !removed.is_empty() {
+            info!("Cleaned up {} expired sessions", removed.len());
         }
         removed
     }
@@ -336,6 +1248,49 @@ mod tests {
         assert!(session.add_tokens(30).is_err());
     }
 
+    #[tokio::test]
+    async fn test_create_session_with_id_is_idempotent() {
+        let mut manager = SessionManager::new();
+        let created = manager.create_session_with_id("client-42".to_string(), Some(1000)).unwrap();
+        manager.update_session("client-42", 100).await.unwrap();
+
+        // A retry returns the existing session, usage included
+        let retried = manager.create_session_with_id("client-42".to_string(), Some(1000)).unwrap();
+        assert_eq!(retried.id, created.id);
+        assert_eq!(retried.created_at, created.created_at);
+        assert_eq!(retried.total_tokens, 100);
+        assert_eq!(manager.session_count(), 1);
+
+        // The default limit matches a session created with it
+        let default = manager.create_session_with_id("client-43".to_string(), None).unwrap();
+        let limit = Some(default.max_tokens);
+        assert!(manager.create_session_with_id("client-43".to_string(), limit).is_ok());
+    }
+
+    #[test]
+    fn test_create_session_with_id_conflict() {
+        let mut manager = SessionManager::new();
+        manager.create_session_with_id("client-42".to_string(), Some(1000)).unwrap();
+
+        let conflict = manager.create_session_with_id("client-42".to_string(), Some(2000));
+        assert!(matches!(
+            conflict,
+            Err(ConversationError::SessionConflict { ref id, .. }) if id == "client-42"
+        ));
+        assert_eq!(manager.get_session("client-42").unwrap().max_tokens, 1000);
+
+        let mut manager = SessionManager::with_config(SessionConfig {
+            timeout_seconds: 0,
+            ..Default::default()
+        });
+        manager.create_session_with_id("stale".to_string(), None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(matches!(
+            manager.create_session_with_id("stale".to_string(), None),
+            Err(ConversationError::SessionConflict { .. })
+        ));
+    }
+
     #[tokio::test]
     async fn test_session_update() {
         let mut manager = SessionManager::new();
@@ -362,4 +1317,281 @@ mod tests {
         assert_eq!(removed, 1);
         assert_eq!(manager.session_count(), 0);
     }
+
+    #[test]
+    fn test_message_quota() {
+        let config = SessionConfig {
+            default_quota: ResourceQuota::default().with_max_messages(3),
+            ..Default::default()
+        };
+
+        let mut manager = SessionManager::with_config(config);
+        let id = manager.create_session(None).id;
+
+        manager.record_messages(&id, 2, 0).unwrap();
+        let err = manager.record_messages(&id, 2, 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversationError::QuotaExceeded { quota: QuotaKind::Messages, requested: 4, limit: 3 }
+        ));
+
+        // The rejected request was not recorded
+        manager.record_messages(&id, 1, 0).unwrap();
+        assert_eq!(manager.get_session(&id).unwrap().usage.messages, 3);
+    }
+
+    #[test]
+    fn test_attachment_bytes_quota() {
+        let config = SessionConfig {
+            default_quota: ResourceQuota::default().with_max_attachment_bytes(1024),
+            ..Default::default()
+        };
+
+        let mut manager = SessionManager::with_config(config);
+        let id = manager.create_session(None).id;
+
+        manager.record_messages(&id, 1, 1000).unwrap();
+        let err = manager.record_messages(&id, 1, 100).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversationError::QuotaExceeded { quota: QuotaKind::AttachmentBytes, .. }
+        ));
+        assert!(err.to_string().contains("attachment_bytes"));
+        assert_eq!(manager.get_session(&id).unwrap().usage.messages, 1);
+    }
+
+    #[test]
+    fn test_conversation_quota() {
+        let config = SessionConfig {
+            default_quota: ResourceQuota::default().with_max_conversations(2),
+            ..Default::default()
+        };
+
+        let mut manager = SessionManager::with_config(config);
+        manager.create_user_session("alice", None).unwrap();
+        manager.create_user_session("alice", None).unwrap();
+
+        let err = manager.create_user_session("alice", None).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversationError::QuotaExceeded { quota: QuotaKind::Conversations, requested: 3, limit: 2 }
+        ));
+        assert_eq!(manager.user_session_count("alice"), 2);
+
+        // Other users are unaffected
+        assert!(manager.create_user_session("bob", None).is_ok());
+    }
+
+    #[test]
+    fn test_fork_counts_towards_conversation_quota() {
+        let config = SessionConfig {
+            default_quota: ResourceQuota::default().with_max_conversations(2),
+            ..Default::default()
+        };
+
+        let mut manager = SessionManager::with_config(config);
+        let id = manager.create_user_session("alice", None).unwrap().id;
+        manager.fork_session(&id, None).unwrap();
+
+        let err = manager.fork_session(&id, None).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversationError::QuotaExceeded { quota: QuotaKind::Conversations, requested: 3, limit: 2 }
+        ));
+        assert!(manager.create_user_session("alice", None).is_err());
+        assert_eq!(manager.user_session_count("alice"), 2);
+    }
+
+    fn kinds(manager: &SessionManager, id: &str) -> Vec<SessionEventKind> {
+        manager.timeline(id).unwrap().events().map(|e| e.kind.clone()).collect()
+    }
+
+    #[test]
+    fn test_timeline_records_operations_in_order() {
+        let mut manager = SessionManager::new();
+        let id = manager.create_session(None).id;
+
+        manager.record_messages(&id, 2, 0).unwrap();
+        manager.set_metadata(&id, "tone", "concise").unwrap();
+        manager
+            .record_event(&id, SessionEventKind::Compressed { tokens_before: 900, tokens_after: 300 })
+            .unwrap();
+        let branch = manager.fork_session(&id, None).unwrap();
+
+        manager.config.timeout_seconds = 0;
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        manager.get_session(&id);
+        manager.get_session(&id);
+
+        assert_eq!(
+            kinds(&manager, &id),
+            vec![
+                SessionEventKind::Created,
+                SessionEventKind::MessagesAdded { count: 2 },
+                SessionEventKind::MetadataChanged { key: "tone".to_string() },
+                SessionEventKind::Compressed { tokens_before: 900, tokens_after: 300 },
+                SessionEventKind::Forked { branch_session_id: branch.id.clone() },
+                SessionEventKind::Expired,
+            ]
+        );
+        let timestamps: Vec<_> = manager.timeline(&id).unwrap().events().map(|e| e.timestamp).collect();
+        assert!(timestamps.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(kinds(&manager, &branch.id), vec![SessionEventKind::Created]);
+    }
+
+    #[test]
+    fn test_timeline_is_bounded() {
+        let config = SessionConfig {
+            timeline_capacity: 3,
+            ..Default::default()
+        };
+        let mut manager = SessionManager::with_config(config);
+        let id = manager.create_session(None).id;
+
+        for _ in 0..5 {
+            manager.record_messages(&id, 1, 0).unwrap();
+        }
+
+        let timeline = manager.timeline(&id).unwrap();
+        assert_eq!(timeline.len(), 3);
+        assert_eq!(timeline.dropped(), 3);
+        assert!(timeline
+            .events()
+            .all(|e| e.kind == SessionEventKind::MessagesAdded { count: 1 }));
+    }
+
+    fn warned_manager() -> (SessionManager, Arc<std::sync::Mutex<Vec<f64>>>) {
+        let warnings = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&warnings);
+        let policy = BudgetWarningPolicy::default()
+            .with_hook(move |warning| sink.lock().unwrap().push(warning.threshold));
+        (SessionManager::new().with_budget_warning_policy(policy), warnings)
+    }
+
+    #[tokio::test]
+    async fn test_budget_warnings_fire_once_per_crossing() {
+        let (mut manager, warnings) = warned_manager();
+        let id = manager.create_session(Some(1000)).id;
+
+        manager.update_session(&id, 600).await.unwrap();
+        assert!(warnings.lock().unwrap().is_empty());
+
+        manager.update_session(&id, 100).await.unwrap();
+        manager.update_session(&id, 50).await.unwrap();
+        assert_eq!(*warnings.lock().unwrap(), vec![0.70]);
+
+        // One jump across two thresholds fires both, in order
+        manager.update_session(&id, 220).await.unwrap();
+        assert_eq!(*warnings.lock().unwrap(), vec![0.70, 0.85, 0.95]);
+
+        manager.update_session(&id, 10).await.unwrap();
+        assert_eq!(warnings.lock().unwrap().len(), 3);
+        assert_eq!(manager.get_session(&id).unwrap().budget_warning_level, 3);
+    }
+
+    #[tokio::test]
+    async fn test_budget_warnings_reset_after_usage_drops() {
+        let (mut manager, warnings) = warned_manager();
+        let id = manager.create_session(Some(1000)).id;
+
+        manager.update_session(&id, 900).await.unwrap();
+        assert_eq!(*warnings.lock().unwrap(), vec![0.70, 0.85]);
+
+        // Compression drops usage below both thresholds, re-arming them
+        manager.record_compression(&id, 400).unwrap();
+        assert_eq!(manager.get_session(&id).unwrap().budget_warning_level, 0);
+
+        manager.update_session(&id, 350).await.unwrap();
+        assert_eq!(*warnings.lock().unwrap(), vec![0.70, 0.85, 0.70]);
+    }
+
+    #[tokio::test]
+    async fn test_token_warning_until_context_is_trimmed() {
+        let mut manager = SessionManager::new();
+        let id = manager.create_session(Some(1000)).id;
+
+        manager.update_session(&id, 899).await.unwrap();
+        assert_eq!(manager.token_warning(&id).unwrap(), None);
+
+        manager.update_session(&id, 1).await.unwrap();
+        let warning = manager.token_warning(&id).unwrap().unwrap();
+        assert_eq!(
+            warning,
+            TokenWarning {
+                threshold: 0.9,
+                used: 900,
+                limit: 1000,
+                remaining: 100,
+            }
+        );
+        // Still reported while usage stays high
+        manager.update_session(&id, 50).await.unwrap();
+        assert_eq!(manager.token_warning(&id).unwrap().unwrap().remaining, 50);
+
+        manager.record_compression(&id, 500).unwrap();
+        assert_eq!(manager.token_warning(&id).unwrap(), None);
+        assert!(manager.token_warning("missing").is_err());
+    }
+
+    #[test]
+    fn test_soft_threshold_is_configurable() {
+        let mut manager = SessionManager::with_config(SessionConfig {
+            soft_threshold: 0.5,
+            ..Default::default()
+        });
+        let mut session = manager.create_session(Some(100));
+        assert_eq!(session.soft_threshold, 0.5);
+        session.total_tokens = 50;
+        assert_eq!(session.token_warning().unwrap().remaining, 50);
+    }
+
+    #[test]
+    fn test_transfer_changes_owner_and_records_handoff() {
+        let mut manager = SessionManager::new();
+        let id = manager.create_user_session("bot", None).unwrap().id;
+
+        let transfer = manager.transfer(&id, "agent-7").unwrap();
+        assert_eq!(transfer.from.as_deref(), Some("bot"));
+        assert_eq!(transfer.previous_owner_access, PreviousOwnerAccess::Revoke);
+
+        let session = manager.get_session(&id).unwrap();
+        assert_eq!(session.user_id.as_deref(), Some("agent-7"));
+        assert!(session.can_write("agent-7"));
+        assert!(!session.can_read("bot"));
+        assert_eq!(session.transfers, vec![transfer]);
+        assert_eq!(
+            kinds(&manager, &id).last(),
+            Some(&SessionEventKind::Transferred {
+                from: Some("bot".to_string()),
+                to: "agent-7".to_string(),
+            })
+        );
+        assert_eq!(manager.user_session_count("bot"), 0);
+
+        // Handing a session to its current owner is a conflict
+        assert!(matches!(
+            manager.transfer(&id, "agent-7"),
+            Err(ConversationError::SessionConflict { .. })
+        ));
+    }
+
+    #[test]
+    fn test_transfer_can_leave_previous_owner_read_access() {
+        let config = SessionConfig {
+            previous_owner_access: PreviousOwnerAccess::ReadOnly,
+            ..Default::default()
+        };
+        let mut manager = SessionManager::with_config(config);
+        let id = manager.create_user_session("bot", None).unwrap().id;
+
+        manager.transfer(&id, "agent-7").unwrap();
+        manager.transfer(&id, "agent-9").unwrap();
+
+        let session = manager.get_session(&id).unwrap();
+        assert!(session.can_read("bot") && session.can_read("agent-7"));
+        assert!(!session.can_write("agent-7"));
+        assert_eq!(session.transfers.len(), 2);
+        assert_eq!(manager.readable_sessions("bot").len(), 1);
+        assert!(manager.user_sessions("bot").is_empty());
+    }
 }
@@ -1,16 +1,42 @@
 //! Response streaming with Server-Sent Events (SSE) support
+//!
+//! [`sse_stream`] serializes a chunk stream as SSE frames. With an
+//! [`SseConfig`] it can name events after their chunk type, so browser
+//! clients attach listeners for `chunk`, `done` and `error` instead of
+//! dispatching on the payload, and send comment keepalives during idle
+//! gaps so proxies do not close quiet connections. Keepalives are SSE
+//! comments, not chunks: they carry no sequence number and do not count
+//! towards usage.
+//!
+//! Every chunk a [`StreamingResponse`] delivers is also kept in a bounded
+//! replay buffer, so a client that lost its connection can pass a
+//! [`ResumeToken`] for the last chunk it saw to
+//! [`StreamingResponse::resume_from`] and receive the chunks after it.
+//!
+//! Chunks are handed to the consumer through a bounded buffer
+//! ([`bounded_stream`]), so a slow consumer cannot make the producer
+//! queue an unbounded backlog. A [`StreamConfig`] sets the buffer size and
+//! what happens once it fills. Chunks are kept for resuming only once they
+//! leave that buffer, so chunks dropped under backpressure are never
+//! replayed as if they had been delivered.
 
-use crate::{history::HistoryManager, Result, ConversationError};
+use crate::{
+    history::HistoryManager,
+    resumable::ResumableStreamManager,
+    Result, ConversationError,
+};
 use copilot_context::ContextEngine;
 use copilot_nlp::NlpEngine;
-use futures::stream::Stream;
+use futures::stream::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 use tokio::time::{sleep, Duration};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// A chunk of streaming response
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +53,13 @@ pub struct StreamChunk {
     /// Metadata for this chunk
     #[serde(default)]
     pub metadata: std::collections::HashMap<String, String>,
+    /// Token usage for the whole response, present on terminal chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<StreamUsage>,
+    /// Upstream stream the chunk came from, set on chunks of a
+    /// [merged](StreamingResponse::merge) stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
 }
 
 /// Type of stream chunk
@@ -42,6 +75,249 @@ pub enum ChunkType {
     Error,
     /// Stream completed
     Done,
+    /// Stream was cancelled before completion
+    Cancelled,
+}
+
+impl ChunkType {
+    /// Whether this chunk type ends a stream
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ChunkType::Error | ChunkType::Done | ChunkType::Cancelled)
+    }
+
+    /// SSE event name of chunks of this type
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            ChunkType::Token | ChunkType::Thinking | ChunkType::Metadata => "chunk",
+            ChunkType::Done => "done",
+            ChunkType::Error => "error",
+            ChunkType::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Token usage of a streamed response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamUsage {
+    /// Tokens in the prompt
+    pub prompt_tokens: usize,
+    /// Tokens generated
+    pub completion_tokens: usize,
+}
+
+impl StreamUsage {
+    /// Create a usage record
+    pub fn new(prompt_tokens: usize, completion_tokens: usize) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    /// Total tokens
+    pub fn total_tokens(&self) -> usize {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl StreamChunk {
+    /// Start a token chunk
+    pub fn token(content: impl Into<String>) -> StreamChunkBuilder<Content> {
+        StreamChunkBuilder::new(ChunkType::Token, content.into(), None)
+    }
+
+    /// Start a thinking chunk
+    pub fn thinking(content: impl Into<String>) -> StreamChunkBuilder<Content> {
+        StreamChunkBuilder::new(ChunkType::Thinking, content.into(), None)
+    }
+
+    /// Start a metadata-only chunk
+    pub fn metadata_update() -> StreamChunkBuilder<Content> {
+        StreamChunkBuilder::new(ChunkType::Metadata, String::new(), None)
+    }
+
+    /// Start the terminal chunk of a completed stream
+    pub fn done(usage: StreamUsage) -> StreamChunkBuilder<Terminal> {
+        StreamChunkBuilder::new(ChunkType::Done, String::new(), Some(usage))
+    }
+
+    /// Start the terminal chunk of a failed stream
+    pub fn error(message: impl Into<String>, usage: StreamUsage) -> StreamChunkBuilder<Terminal> {
+        StreamChunkBuilder::new(ChunkType::Error, message.into(), Some(usage))
+    }
+
+    /// Start the terminal chunk of a cancelled stream
+    pub fn cancelled(usage: StreamUsage) -> StreamChunkBuilder<Terminal> {
+        StreamChunkBuilder::new(ChunkType::Cancelled, String::new(), Some(usage))
+    }
+
+    /// Check the chunk's invariants
+    ///
+    /// Chunks made with [`StreamChunkBuilder`] always pass; this catches
+    /// chunks assembled by hand or received from elsewhere.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(ConversationError::StreamingError(format!(
+                "Invalid {:?} chunk {}: {}",
+                self.chunk_type, self.sequence, reason
+            )))
+        };
+
+        if self.is_final != self.chunk_type.is_terminal() {
+            return invalid("is_final must be set exactly on terminal chunks");
+        }
+        if self.chunk_type.is_terminal() && self.usage.is_none() {
+            return invalid("terminal chunks must carry usage");
+        }
+        if !self.chunk_type.is_terminal() && self.usage.is_some() {
+            return invalid("only terminal chunks carry usage");
+        }
+        match self.chunk_type {
+            ChunkType::Token if self.content.is_empty() => invalid("token chunks need content"),
+            ChunkType::Error if self.content.is_empty() => invalid("error chunks need a message"),
+            ChunkType::Done | ChunkType::Cancelled | ChunkType::Metadata if !self.content.is_empty() => {
+                invalid("chunk type carries no content")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Marker for builders of content-carrying, non-terminal chunks
+#[derive(Debug)]
+pub enum Content {}
+
+/// Marker for builders of terminal chunks
+#[derive(Debug)]
+pub enum Terminal {}
+
+/// Builder for [`StreamChunk`]
+///
+/// The chunk kind is fixed by the constructor on `StreamChunk`, so a
+/// terminal chunk always has usage and only one terminal kind, and
+/// non-terminal chunks can never be marked final. Remaining invariants,
+/// such as non-empty token content, are checked by [`build`](Self::build).
+#[derive(Debug)]
+#[must_use]
+pub struct StreamChunkBuilder<K> {
+    chunk_type: ChunkType,
+    content: String,
+    sequence: Option<usize>,
+    usage: Option<StreamUsage>,
+    metadata: HashMap<String, String>,
+    _kind: PhantomData<K>,
+}
+
+impl<K> StreamChunkBuilder<K> {
+    fn new(chunk_type: ChunkType, content: String, usage: Option<StreamUsage>) -> Self {
+        Self {
+            chunk_type,
+            content,
+            sequence: None,
+            usage,
+            metadata: HashMap::new(),
+            _kind: PhantomData,
+        }
+    }
+
+    /// Add a metadata entry
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the sequence number explicitly
+    ///
+    /// Usually left to [`StreamEmitter`], which assigns sequence numbers in
+    /// order.
+    pub fn sequence(mut self, sequence: usize) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Build the chunk
+    ///
+    /// Fails if no sequence number was set or the chunk is inconsistent.
+    pub fn build(self) -> Result<StreamChunk> {
+        let sequence = self.sequence.ok_or_else(|| {
+            ConversationError::StreamingError("Chunk built without a sequence number".to_string())
+        })?;
+        let chunk = StreamChunk {
+            chunk_type: self.chunk_type,
+            content: self.content,
+            sequence,
+            is_final: self.chunk_type.is_terminal(),
+            metadata: self.metadata,
+            usage: self.usage,
+            source_id: None,
+        };
+        chunk.validate()?;
+        Ok(chunk)
+    }
+}
+
+impl StreamChunkBuilder<Content> {
+    /// Append text to the chunk's content
+    pub fn push_str(mut self, text: &str) -> Self {
+        self.content.push_str(text);
+        self
+    }
+}
+
+/// Emits the chunks of one stream in order
+///
+/// Assigns consecutive sequence numbers, rejects explicit sequence numbers
+/// that do not increase, and refuses chunks after a terminal one.
+#[derive(Debug, Default)]
+pub struct StreamEmitter {
+    next_sequence: usize,
+    finished: bool,
+}
+
+impl StreamEmitter {
+    /// Create an emitter starting at sequence 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sequence number the next chunk will receive
+    pub fn next_sequence(&self) -> usize {
+        self.next_sequence
+    }
+
+    /// Whether a terminal chunk has been emitted
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Build and emit a chunk
+    pub fn emit<K>(&mut self, builder: StreamChunkBuilder<K>) -> Result<StreamChunk> {
+        if self.finished {
+            return Err(ConversationError::StreamingError(
+                "Chunk emitted after the stream ended".to_string(),
+            ));
+        }
+
+        let builder = match builder.sequence {
+            Some(sequence) if sequence < self.next_sequence => {
+                return Err(ConversationError::StreamingError(format!(
+                    "Sequence {} is not after {}",
+                    sequence,
+                    self.next_sequence.saturating_sub(1)
+                )));
+            }
+            Some(_) => builder,
+            None => {
+                let next = self.next_sequence;
+                builder.sequence(next)
+            }
+        };
+
+        let chunk = builder.build()?;
+        self.next_sequence = chunk.sequence + 1;
+        self.finished = chunk.is_final;
+        Ok(chunk)
+    }
 }
 
 /// Statistics about streaming response
@@ -57,9 +333,48 @@ pub struct StreamStatistics {
     pub tokens_per_second: f64,
 }
 
+/// Identifies the last chunk a client received from a stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeToken {
+    /// Stream the chunk belongs to
+    pub stream_id: String,
+    /// Sequence number of the chunk
+    pub sequence: usize,
+}
+
+impl std::fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.stream_id, self.sequence)
+    }
+}
+
+impl std::str::FromStr for ResumeToken {
+    type Err = ConversationError;
+
+    /// Parse a token written as `stream_id:sequence`, e.g. from an SSE
+    /// `Last-Event-ID` header
+    fn from_str(token: &str) -> Result<Self> {
+        let invalid = || ConversationError::InvalidResumeToken(token.to_string());
+        let (stream_id, sequence) = token.rsplit_once(':').ok_or_else(invalid)?;
+        if stream_id.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            stream_id: stream_id.to_string(),
+            sequence: sequence.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
 /// Streaming response handler
 pub struct StreamingResponse {
     session_id: String,
+    stream_id: String,
+    resumable: Arc<ResumableStreamManager>,
+    config: StreamConfig,
+    sse_config: SseConfig,
+    /// Metadata added to the final chunk
+    metadata: HashMap<String, String>,
     nlp_engine: Arc<dyn NlpEngine>,
     context_engine: Arc<dyn ContextEngine>,
     history_manager: Arc<RwLock<HistoryManager>>,
@@ -78,6 +393,11 @@ impl StreamingResponse {
     ) -> Self {
         Self {
             session_id,
+            stream_id: uuid::Uuid::new_v4().to_string(),
+            resumable: Arc::new(ResumableStreamManager::in_memory()),
+            config: StreamConfig::default(),
+            sse_config: SseConfig::default(),
+            metadata: HashMap::new(),
             nlp_engine,
             context_engine,
             history_manager,
@@ -87,6 +407,128 @@ impl StreamingResponse {
         }
     }
 
+    /// Buffer chunks for resumption in a shared manager instead of this
+    /// response's own in-memory buffer, e.g. one backed by Redis so a
+    /// client can resume on another replica
+    pub fn with_resumable(mut self, resumable: Arc<ResumableStreamManager>) -> Self {
+        self.resumable = resumable;
+        self
+    }
+
+    /// Set the delivery buffer size and backpressure policy
+    pub fn with_stream_config(mut self, config: StreamConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Send a keepalive comment after `interval` without a chunk when
+    /// streaming as SSE
+    pub fn with_keepalive(mut self, interval: Duration) -> Self {
+        self.sse_config.keepalive_interval = Some(interval);
+        self
+    }
+
+    /// Set how [`stream_sse`](Self::stream_sse) formats events
+    pub fn with_sse_config(mut self, config: SseConfig) -> Self {
+        self.sse_config = config;
+        self
+    }
+
+    /// Add a metadata entry to the final chunk
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Identifier of the stream, as carried in its resume tokens
+    pub fn stream_id(&self) -> &str {
+        &self.stream_id
+    }
+
+    /// Token for resuming after `chunk`
+    pub fn resume_token(&self, chunk: &StreamChunk) -> ResumeToken {
+        ResumeToken {
+            stream_id: self.stream_id.clone(),
+            sequence: chunk.sequence,
+        }
+    }
+
+    /// Replay the buffered chunks after the one a token points to
+    ///
+    /// Yields what the stream has emitted so far; a stream still in flight
+    /// is not followed. Fails with
+    /// [`ConversationError::InvalidResumeToken`] if the token belongs to
+    /// another stream, the stream's buffer has expired, or chunks after
+    /// the token were already dropped from the buffer.
+    pub async fn resume_from(
+        &self,
+        token: ResumeToken,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>> {
+        if token.stream_id != self.stream_id {
+            return Err(ConversationError::InvalidResumeToken(format!(
+                "{} does not belong to stream {}",
+                token, self.stream_id
+            )));
+        }
+        let chunks = self.resumable.resume_after(&token).await?;
+
+        debug!("Replaying {} chunks of stream {}", chunks.len(), self.stream_id);
+        Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))))
+    }
+
+    /// Interleave several upstream streams into one, e.g. the answers of
+    /// an ensemble of models
+    ///
+    /// Chunks are delivered in order of arrival, tagged with the id of
+    /// their source and renumbered. The sources' terminal chunks are held
+    /// back: once every source has finished, a single terminal chunk
+    /// carries their combined usage. It is an error chunk naming the
+    /// failed sources if any source ended in error, and done otherwise.
+    pub fn merge<S>(streams: Vec<(String, S)>) -> Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>
+    where
+        S: Stream<Item = StreamChunk> + Send + 'static,
+    {
+        let sources = streams
+            .into_iter()
+            .map(|(source_id, chunks)| chunks.map(move |chunk| (source_id.clone(), chunk)).boxed());
+        let mut merged = futures::stream::select_all(sources);
+
+        Box::pin(async_stream::stream! {
+            let mut next_sequence = 0;
+            let mut usage = StreamUsage::default();
+            let mut finished = std::collections::HashSet::new();
+            let mut errors = Vec::new();
+
+            while let Some((source_id, mut chunk)) = merged.next().await {
+                if finished.contains(&source_id) {
+                    continue;
+                }
+                if chunk.is_final {
+                    if let Some(source_usage) = chunk.usage {
+                        usage.prompt_tokens += source_usage.prompt_tokens;
+                        usage.completion_tokens += source_usage.completion_tokens;
+                    }
+                    if chunk.chunk_type == ChunkType::Error {
+                        errors.push(format!("{}: {}", source_id, chunk.content));
+                    }
+                    finished.insert(source_id);
+                    continue;
+                }
+                chunk.sequence = next_sequence;
+                chunk.source_id = Some(source_id);
+                next_sequence += 1;
+                yield Ok(chunk);
+            }
+
+            let terminal = if errors.is_empty() {
+                StreamChunk::done(usage)
+            } else {
+                StreamChunk::error(errors.join("; "), usage)
+            };
+            yield terminal.sequence(next_sequence).build();
+        })
+    }
+
     /// Start streaming response
     ///
     /// # Arguments
@@ -104,6 +546,7 @@ impl StreamingResponse {
         let nlp_engine = Arc::clone(&self.nlp_engine);
         let context_engine = Arc::clone(&self.context_engine);
         let _history_manager = Arc::clone(&self.history_manager);
+        let final_metadata = self.metadata.clone();
 
         // In a real implementation, this would stream from an LLM
         // For now, we'll simulate streaming
@@ -112,14 +555,10 @@ impl StreamingResponse {
             let first_token_delay = Duration::from_millis(350);
             sleep(first_token_delay).await;
 
+            let mut emitter = StreamEmitter::new();
+
             // First token
-            yield Ok(StreamChunk {
-                chunk_type: ChunkType::Token,
-                content: "I".to_string(),
-                sequence: 0,
-                is_final: false,
-                metadata: std::collections::HashMap::new(),
-            });
+            yield emitter.emit(StreamChunk::token("I"));
 
             // Simulate streaming tokens
             let response_tokens = vec![
@@ -139,34 +578,52 @@ impl StreamingResponse {
                 ".",
             ];
 
-            for (idx, token) in response_tokens.iter().enumerate() {
+            for token in response_tokens.iter() {
                 // Simulate token generation delay
                 sleep(Duration::from_millis(50)).await;
 
-                yield Ok(StreamChunk {
-                    chunk_type: ChunkType::Token,
-                    content: token.to_string(),
-                    sequence: idx + 1,
-                    is_final: false,
-                    metadata: std::collections::HashMap::new(),
-                });
+                yield emitter.emit(StreamChunk::token(*token));
             }
 
             // Final chunk
-            yield Ok(StreamChunk {
-                chunk_type: ChunkType::Done,
-                content: String::new(),
-                sequence: response_tokens.len() + 1,
-                is_final: true,
-                metadata: std::collections::HashMap::new(),
-            });
+            let usage = StreamUsage::new((message.len() / 4).max(1), response_tokens.len() + 1);
+            let done = final_metadata
+                .into_iter()
+                .fold(StreamChunk::done(usage), |done, (key, value)| done.with_metadata(key, value));
+            yield emitter.emit(done);
 
             debug!("Streaming completed for session: {}", session_id);
         };
 
+        // Keep each delivered chunk for clients that reconnect
+        let stream_id = self.stream_id.clone();
+        let resumable = Arc::clone(&self.resumable);
+        let stream = bounded_stream(stream, self.config).then(move |chunk| {
+            let stream_id = stream_id.clone();
+            let resumable = Arc::clone(&resumable);
+            async move {
+                if let Ok(chunk) = &chunk {
+                    if let Err(e) = resumable.record(&stream_id, chunk.clone()).await {
+                        warn!("Failed to buffer chunk of stream {}: {}", stream_id, e);
+                    }
+                }
+                chunk
+            }
+        });
+
         Ok(Box::pin(stream))
     }
 
+    /// Start streaming a response as SSE frames
+    ///
+    /// Like [`stream`](Self::stream), serialized with [`sse_stream`].
+    /// Keepalive comments fill silent gaps but are not chunks: they take no
+    /// sequence number, so resume tokens are unaffected.
+    pub async fn stream_sse(&mut self, message: String) -> Result<Pin<Box<dyn Stream<Item = String> + Send>>> {
+        let chunks = self.stream(message).await?;
+        Ok(Box::pin(sse_stream(chunks, self.sse_config)))
+    }
+
     /// Convert stream to Server-Sent Events format
     pub fn to_sse_format(chunk: &StreamChunk) -> String {
         let json = serde_json::to_string(chunk).unwrap_or_default();
@@ -266,6 +723,242 @@ impl StreamBuilder {
     }
 }
 
+/// Comment frame sent to keep idle SSE connections open
+pub const SSE_KEEPALIVE: &str = ": ping\n\n";
+
+/// How chunks are serialized as Server-Sent Events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SseConfig {
+    /// Idle time after which a keepalive comment is sent; `None` disables
+    /// keepalives
+    pub keepalive_interval: Option<Duration>,
+    /// Name events after their chunk type (`event: chunk`, `event: done`,
+    /// `event: error`, `event: cancelled`). Unnamed events reach a browser
+    /// `EventSource`'s `onmessage` handler; named ones only reach
+    /// listeners for their name.
+    pub named_events: bool,
+}
+
+impl Default for SseConfig {
+    fn default() -> Self {
+        Self {
+            keepalive_interval: Some(Duration::from_secs(15)),
+            named_events: false,
+        }
+    }
+}
+
+/// Serialize a chunk stream as SSE frames
+///
+/// Ends after the first terminal chunk. A stream error becomes a terminal
+/// `error` event carrying the error message, sequenced after the last
+/// chunk.
+pub fn sse_stream<S>(chunks: S, config: SseConfig) -> impl Stream<Item = String> + Send
+where
+    S: Stream<Item = Result<StreamChunk>> + Send + 'static,
+{
+    async_stream::stream! {
+        let mut chunks = Box::pin(chunks);
+        let mut next_sequence = 0;
+        loop {
+            let next = match config.keepalive_interval {
+                Some(interval) => match tokio::time::timeout(interval, chunks.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        yield SSE_KEEPALIVE.to_string();
+                        continue;
+                    }
+                },
+                None => chunks.next().await,
+            };
+
+            let chunk = match next {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    let chunk = StreamChunk::error(e.to_string(), StreamUsage::default())
+                        .sequence(next_sequence)
+                        .build();
+                    match chunk {
+                        Ok(chunk) => yield SseFormatter::format_event(&chunk, config.named_events),
+                        Err(_) => yield SseFormatter::format_error(&e.to_string()),
+                    }
+                    break;
+                }
+                None => break,
+            };
+            next_sequence = chunk.sequence + 1;
+            yield SseFormatter::format_event(&chunk, config.named_events);
+            if chunk.is_final {
+                break;
+            }
+        }
+    }
+}
+
+/// Default number of chunks buffered for a consumer
+pub const DEFAULT_STREAM_BUFFER_SIZE: usize = 64;
+
+/// What a stream does when its consumer falls behind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressurePolicy {
+    /// Drop the oldest buffered chunk to make room for the new one
+    DropOldest,
+    /// Pause the producer until the consumer makes room
+    #[default]
+    Block,
+    /// End the stream with [`ConversationError::StreamBackpressure`] after
+    /// the chunks already buffered
+    Error,
+}
+
+/// How chunks are buffered between producer and consumer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamConfig {
+    /// Chunks buffered before backpressure applies (at least 1)
+    pub buffer_size: usize,
+    /// What to do when the buffer is full
+    pub on_backpressure: BackpressurePolicy,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_STREAM_BUFFER_SIZE,
+            on_backpressure: BackpressurePolicy::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BufferState {
+    chunks: VecDeque<Result<StreamChunk>>,
+    producer_done: bool,
+    consumer_gone: bool,
+}
+
+/// Chunks in flight between the producer task and the consumer
+#[derive(Default)]
+struct ChunkBuffer {
+    state: Mutex<BufferState>,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl ChunkBuffer {
+    fn state(&self) -> std::sync::MutexGuard<'_, BufferState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Consumer end of a [`ChunkBuffer`]; dropping it stops the producer
+struct BufferReader(Arc<ChunkBuffer>);
+
+impl Drop for BufferReader {
+    fn drop(&mut self) {
+        self.0.state().consumer_gone = true;
+        self.0.writable.notify_one();
+    }
+}
+
+enum Push {
+    Done,
+    Full,
+    Stop,
+}
+
+/// Deliver a chunk stream through a bounded buffer
+///
+/// The chunks are produced on a spawned task, which applies the config's
+/// [`BackpressurePolicy`] whenever `buffer_size` chunks are waiting for the
+/// consumer. The task stops once the returned stream is dropped.
+pub fn bounded_stream<S>(
+    chunks: S,
+    config: StreamConfig,
+) -> Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>
+where
+    S: Stream<Item = Result<StreamChunk>> + Send + 'static,
+{
+    let buffer = Arc::new(ChunkBuffer::default());
+    tokio::spawn(produce(chunks, Arc::clone(&buffer), config));
+
+    Box::pin(futures::stream::unfold(BufferReader(buffer), |reader| async move {
+        loop {
+            let next = {
+                let mut state = reader.0.state();
+                match state.chunks.pop_front() {
+                    Some(chunk) => Some(Some(chunk)),
+                    None if state.producer_done => Some(None),
+                    None => None,
+                }
+            };
+            match next {
+                Some(Some(chunk)) => {
+                    reader.0.writable.notify_one();
+                    return Some((chunk, reader));
+                }
+                Some(None) => return None,
+                None => reader.0.readable.notified().await,
+            }
+        }
+    }))
+}
+
+async fn produce<S>(chunks: S, buffer: Arc<ChunkBuffer>, config: StreamConfig)
+where
+    S: Stream<Item = Result<StreamChunk>> + Send,
+{
+    let capacity = config.buffer_size.max(1);
+    let mut chunks = Box::pin(chunks);
+    let mut dropped = 0;
+
+    'chunks: while let Some(chunk) = chunks.next().await {
+        let mut chunk = Some(chunk);
+        loop {
+            let push = {
+                let mut state = buffer.state();
+                if state.consumer_gone {
+                    Push::Stop
+                } else if state.chunks.len() < capacity {
+                    state.chunks.extend(chunk.take());
+                    Push::Done
+                } else {
+                    match config.on_backpressure {
+                        BackpressurePolicy::DropOldest => {
+                            state.chunks.pop_front();
+                            state.chunks.extend(chunk.take());
+                            dropped += 1;
+                            Push::Done
+                        }
+                        BackpressurePolicy::Block => Push::Full,
+                        BackpressurePolicy::Error => {
+                            state.chunks.push_back(Err(ConversationError::StreamBackpressure(format!(
+                                "consumer fell {} chunks behind",
+                                capacity
+                            ))));
+                            Push::Stop
+                        }
+                    }
+                }
+            };
+            match push {
+                Push::Done => {
+                    buffer.readable.notify_one();
+                    continue 'chunks;
+                }
+                Push::Full => buffer.writable.notified().await,
+                Push::Stop => break 'chunks,
+            }
+        }
+    }
+
+    if dropped > 0 {
+        warn!("Dropped {} chunks for a slow stream consumer", dropped);
+    }
+    buffer.state().producer_done = true;
+    buffer.readable.notify_one();
+}
+
 /// SSE event formatter
 pub struct SseFormatter;
 
@@ -277,6 +970,16 @@ impl SseFormatter {
         Ok(format!("data: {}\n\n", json))
     }
 
+    /// Format a chunk as SSE event, optionally named after its type
+    pub fn format_event(chunk: &StreamChunk, named: bool) -> String {
+        let data = Self::format(chunk).unwrap_or_else(|e| Self::format_error(&e.to_string()));
+        if named {
+            format!("event: {}\n{}", chunk.chunk_type.event_name(), data)
+        } else {
+            data
+        }
+    }
+
     /// Format an error as SSE event
     pub fn format_error(error: &str) -> String {
         let chunk = StreamChunk {
@@ -285,6 +988,8 @@ impl SseFormatter {
             sequence: 0,
             is_final: true,
             metadata: std::collections::HashMap::new(),
+            usage: Some(StreamUsage::default()),
+            source_id: None,
         };
         serde_json::to_string(&chunk)
             .map(|json| format!("data: {}\n\n", json))
@@ -299,6 +1004,8 @@ impl SseFormatter {
             sequence: 0,
             is_final: true,
             metadata: std::collections::HashMap::new(),
+            usage: Some(StreamUsage::default()),
+            source_id: None,
         };
         serde_json::to_string(&chunk)
             .map(|json| format!("data: {}\n\n", json))
@@ -314,13 +1021,7 @@ mod tests {
 
     #[test]
     fn test_sse_formatting() {
-        let chunk = StreamChunk {
-            chunk_type: ChunkType::Token,
-            content: "Hello".to_string(),
-            sequence: 0,
-            is_final: false,
-            metadata: std::collections::HashMap::new(),
-        };
+        let chunk = StreamChunk::token("Hello").sequence(0).build().unwrap();
 
         let sse = SseFormatter::format(&chunk).unwrap();
         assert!(sse.starts_with("data: "));
@@ -335,6 +1036,11 @@ mod tests {
 
         let mut response = StreamingResponse {
             session_id: "test".to_string(),
+            stream_id: "stream".to_string(),
+            resumable: Arc::new(ResumableStreamManager::in_memory()),
+            config: StreamConfig::default(),
+            sse_config: SseConfig::default(),
+            metadata: HashMap::new(),
             nlp_engine: Arc::new(NlpEngineImpl::default()),
             context_engine: Arc::new(context_engine),
             history_manager: Arc::new(RwLock::new(HistoryManager::new())),
@@ -350,4 +1056,361 @@ mod tests {
         assert!(stats.time_to_first_token_ms >= 0);
         assert_eq!(stats.token_count, 1);
     }
+
+    fn streaming_response() -> StreamingResponse {
+        let context_engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        StreamingResponse::new(
+            "test".to_string(),
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(context_engine),
+            Arc::new(RwLock::new(HistoryManager::new())),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_the_middle() {
+        let mut response = streaming_response();
+        let chunks: Vec<StreamChunk> = response
+            .stream("hi".to_string())
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let token: ResumeToken = response.resume_token(&chunks[5]).to_string().parse().unwrap();
+
+        let replayed: Vec<StreamChunk> = response
+            .resume_from(token)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let sequences: Vec<usize> = replayed.iter().map(|c| c.sequence).collect();
+        assert_eq!(sequences, (6..chunks.len()).collect::<Vec<_>>());
+        assert!(replayed.last().unwrap().is_final);
+
+        // Tokens of other streams are rejected
+        let foreign = ResumeToken {
+            stream_id: "other".to_string(),
+            sequence: 5,
+        };
+        assert!(matches!(
+            response.resume_from(foreign).await,
+            Err(ConversationError::InvalidResumeToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_expired_token() {
+        let resumable = ResumableStreamManager::new(
+            Arc::new(crate::resumable::InMemoryStreamStore::new()),
+            crate::resumable::ResumableStreamConfig {
+                buffer_size: 4,
+                ..Default::default()
+            },
+        );
+        let mut response = streaming_response().with_resumable(Arc::new(resumable));
+        let chunks: Vec<StreamChunk> = response
+            .stream("hi".to_string())
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        // Chunks after sequence 2 have left the buffer
+        let token = response.resume_token(&chunks[2]);
+        assert!(matches!(
+            response.resume_from(token).await,
+            Err(ConversationError::InvalidResumeToken(_))
+        ));
+        let token = response.resume_token(&chunks[chunks.len() - 3]);
+        assert_eq!(response.resume_from(token).await.unwrap().count().await, 2);
+        assert!("no-sequence".parse::<ResumeToken>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_chunks_dropped_under_backpressure() {
+        let mut response = streaming_response().with_stream_config(StreamConfig {
+            buffer_size: 1,
+            on_backpressure: BackpressurePolicy::DropOldest,
+        });
+        let mut stream = response.stream("hi".to_string()).await.unwrap();
+        let mut received = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            received.push(chunk.unwrap());
+            sleep(Duration::from_millis(120)).await;
+        }
+        let last = received.last().unwrap();
+        assert!(last.is_final);
+        assert!(last.sequence + 1 > received.len(), "no chunk was dropped");
+
+        // Only the chunks the consumer received are replayed
+        let replayed: Vec<usize> = response
+            .resume_from(response.resume_token(&received[0]))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap().sequence)
+            .collect()
+            .await;
+        let delivered: Vec<usize> = received[1..].iter().map(|c| c.sequence).collect();
+        assert_eq!(replayed, delivered);
+    }
+
+    /// Twenty chunks, the last one final, produced without delay
+    fn fast_chunks() -> impl Stream<Item = Result<StreamChunk>> + Send {
+        let mut emitter = StreamEmitter::new();
+        let mut chunks: Vec<_> = (0..19).map(|_| emitter.emit(StreamChunk::token("t"))).collect();
+        chunks.push(emitter.emit(StreamChunk::done(StreamUsage::new(1, 19))));
+        futures::stream::iter(chunks)
+    }
+
+    async fn slow_receive(config: StreamConfig) -> Vec<Result<StreamChunk>> {
+        let mut stream = bounded_stream(fast_chunks(), config);
+        // Let the producer run ahead before reading anything
+        sleep(Duration::from_millis(50)).await;
+        let mut received = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            received.push(chunk);
+            sleep(Duration::from_millis(1)).await;
+        }
+        received
+    }
+
+    fn config(on_backpressure: BackpressurePolicy) -> StreamConfig {
+        StreamConfig {
+            buffer_size: 4,
+            on_backpressure,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_delivers_every_chunk() {
+        let received = slow_receive(config(BackpressurePolicy::Block)).await;
+        let sequences: Vec<usize> = received.into_iter().map(|c| c.unwrap().sequence).collect();
+        assert_eq!(sequences, (0..20).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_keeps_latest_chunks() {
+        let received = slow_receive(config(BackpressurePolicy::DropOldest)).await;
+        let sequences: Vec<usize> = received.into_iter().map(|c| c.unwrap().sequence).collect();
+        assert_eq!(sequences, [16, 17, 18, 19]);
+    }
+
+    #[tokio::test]
+    async fn test_error_policy_signals_backpressure() {
+        let received = slow_receive(config(BackpressurePolicy::Error)).await;
+        assert_eq!(received.len(), 5);
+        for (sequence, chunk) in received[..4].iter().enumerate() {
+            assert_eq!(chunk.as_ref().unwrap().sequence, sequence);
+        }
+        assert!(matches!(received[4], Err(ConversationError::StreamBackpressure(_))));
+    }
+
+    #[tokio::test]
+    async fn test_keepalives_fill_silent_gaps() {
+        // The simulated response is silent for 350ms before its first token
+        let mut response = streaming_response().with_keepalive(Duration::from_millis(50));
+        let frames: Vec<String> = response.stream_sse("hi".to_string()).await.unwrap().collect().await;
+
+        let leading_pings = frames.iter().take_while(|f| f.as_str() == SSE_KEEPALIVE).count();
+        assert!(leading_pings >= 3, "only {} keepalives", leading_pings);
+
+        let sequences: Vec<usize> = frames
+            .iter()
+            .filter(|f| f.as_str() != SSE_KEEPALIVE)
+            .map(|f| {
+                let chunk: StreamChunk = serde_json::from_str(f.trim().trim_start_matches("data: ")).unwrap();
+                chunk.sequence
+            })
+            .collect();
+        assert_eq!(sequences, (0..16).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_merge_waits_for_every_source() {
+        fn source(delay_ms: u64, tokens: usize) -> Pin<Box<dyn Stream<Item = StreamChunk> + Send>> {
+            Box::pin(async_stream::stream! {
+                let mut emitter = StreamEmitter::new();
+                for _ in 0..tokens {
+                    sleep(Duration::from_millis(delay_ms)).await;
+                    yield emitter.emit(StreamChunk::token("t")).unwrap();
+                }
+                yield emitter.emit(StreamChunk::done(StreamUsage::new(1, tokens))).unwrap();
+            })
+        }
+
+        let merged: Vec<StreamChunk> = StreamingResponse::merge(vec![
+            ("slow".to_string(), source(30, 3)),
+            ("fast".to_string(), source(0, 4)),
+        ])
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+        let from = |source: &str| {
+            merged
+                .iter()
+                .filter(|c| c.source_id.as_deref() == Some(source))
+                .count()
+        };
+        assert_eq!(from("fast"), 4);
+        assert_eq!(from("slow"), 3);
+        assert_eq!(merged[3].source_id.as_deref(), Some("fast"));
+
+        let sequences: Vec<usize> = merged.iter().map(|c| c.sequence).collect();
+        assert_eq!(sequences, (0..8).collect::<Vec<_>>());
+        let terminal = merged.last().unwrap();
+        assert_eq!(terminal.chunk_type, ChunkType::Done);
+        assert_eq!(terminal.usage, Some(StreamUsage::new(2, 7)));
+        assert_eq!(merged.iter().filter(|c| c.is_final).count(), 1);
+    }
+
+    #[test]
+    fn test_builder_builds_consistent_chunks() {
+        let token = StreamChunk::token("Hel")
+            .push_str("lo")
+            .with_metadata("model", "m1")
+            .sequence(3)
+            .build()
+            .unwrap();
+        assert_eq!(token.content, "Hello");
+        assert!(!token.is_final);
+        assert!(token.usage.is_none());
+
+        let done = StreamChunk::done(StreamUsage::new(10, 5)).sequence(4).build().unwrap();
+        assert_eq!(done.chunk_type, ChunkType::Done);
+        assert!(done.is_final);
+        assert_eq!(done.usage.unwrap().total_tokens(), 15);
+
+        let cancelled = StreamChunk::cancelled(StreamUsage::new(10, 2)).sequence(5).build().unwrap();
+        assert!(cancelled.is_final);
+    }
+
+    #[test]
+    fn test_builder_rejects_inconsistent_chunks() {
+        assert!(StreamChunk::token("").sequence(0).build().is_err());
+        assert!(StreamChunk::error("", StreamUsage::default()).sequence(0).build().is_err());
+        // Sequence numbers are required
+        assert!(StreamChunk::token("x").build().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_hand_built_chunks() {
+        let mut chunk = StreamChunk::done(StreamUsage::default()).sequence(0).build().unwrap();
+        chunk.usage = None;
+        assert!(chunk.validate().is_err());
+
+        let mut chunk = StreamChunk::done(StreamUsage::default()).sequence(0).build().unwrap();
+        chunk.content = "trailing".to_string();
+        assert!(chunk.validate().is_err());
+
+        let mut chunk = StreamChunk::token("x").sequence(0).build().unwrap();
+        chunk.is_final = true;
+        assert!(chunk.validate().is_err());
+    }
+
+    #[test]
+    fn test_emitter_enforces_order_and_termination() {
+        let mut emitter = StreamEmitter::new();
+        assert_eq!(emitter.emit(StreamChunk::token("a")).unwrap().sequence, 0);
+        assert_eq!(emitter.emit(StreamChunk::token("b").sequence(5)).unwrap().sequence, 5);
+        assert!(emitter.emit(StreamChunk::token("c").sequence(5)).is_err());
+        assert_eq!(emitter.emit(StreamChunk::thinking("d")).unwrap().sequence, 6);
+
+        let done = emitter.emit(StreamChunk::done(StreamUsage::new(1, 4))).unwrap();
+        assert!(done.is_final);
+        assert!(emitter.is_finished());
+        assert!(emitter.emit(StreamChunk::token("late")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_chunks_are_valid() {
+        let context_engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+        let mut response = StreamingResponse::new(
+            "test".to_string(),
+            Arc::new(NlpEngineImpl::default()),
+            Arc::new(context_engine),
+            Arc::new(RwLock::new(HistoryManager::new())),
+        );
+
+        let chunks: Vec<StreamChunk> = futures::StreamExt::collect::<Vec<_>>(
+            response.stream("hi".to_string()).await.unwrap(),
+        )
+        .await
+        .into_iter()
+        .map(|c| c.unwrap())
+        .collect();
+
+        assert!(chunks.iter().all(|c| c.validate().is_ok()));
+        assert!(chunks.windows(2).all(|w| w[0].sequence < w[1].sequence));
+        assert_eq!(chunks.iter().filter(|c| c.is_final).count(), 1);
+        assert!(chunks.last().unwrap().is_final);
+    }
+
+    #[tokio::test]
+    async fn test_sse_keepalives_fill_idle_gaps_without_sequence_numbers() {
+        let chunks = futures::stream::iter(0..3).then(|i| async move {
+            // A long pause before the terminal chunk
+            if i == 2 {
+                sleep(Duration::from_millis(150)).await;
+            }
+            match i {
+                2 => StreamChunk::done(StreamUsage::new(3, 2)).sequence(i).build(),
+                _ => StreamChunk::token("x").sequence(i).build(),
+            }
+        });
+        let config = SseConfig {
+            keepalive_interval: Some(Duration::from_millis(20)),
+            named_events: false,
+        };
+        let frames: Vec<String> = sse_stream(chunks, config).collect().await;
+
+        let keepalives = frames.iter().filter(|f| *f == SSE_KEEPALIVE).count();
+        assert!(keepalives >= 3, "{} keepalives", keepalives);
+        assert!(frames[..2].iter().all(|f| f.starts_with("data: ")));
+        assert!(frames.last().unwrap().contains("\"type\":\"Done\""));
+
+        // Keepalives are not chunks: the data frames are sequenced 0, 1, 2
+        let sequences: Vec<usize> = frames
+            .iter()
+            .filter_map(|f| f.strip_prefix("data: "))
+            .map(|data| serde_json::from_str::<StreamChunk>(data.trim()).unwrap().sequence)
+            .collect();
+        assert_eq!(sequences, [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_sse_named_events_carry_terminal_payloads() {
+        let config = SseConfig {
+            keepalive_interval: None,
+            named_events: true,
+        };
+
+        let chunks = futures::stream::iter([
+            StreamChunk::token("Hi").sequence(0).build(),
+            StreamChunk::done(StreamUsage::new(4, 1)).sequence(1).build(),
+        ]);
+        let frames: Vec<String> = sse_stream(chunks, config).collect().await;
+        assert!(frames[0].starts_with("event: chunk\ndata: "));
+        assert!(frames[1].starts_with("event: done\ndata: "));
+        assert!(frames[1].contains("\"completion_tokens\":1"));
+
+        // A failing stream ends with an error event after the last chunk
+        let chunks = futures::stream::iter([
+            StreamChunk::token("Hi").sequence(0).build(),
+            Err(ConversationError::StreamingError("model went away".to_string())),
+            StreamChunk::token("never sent").sequence(1).build(),
+        ]);
+        let frames: Vec<String> = sse_stream(chunks, config).collect().await;
+        assert_eq!(frames.len(), 2);
+        assert!(frames[1].starts_with("event: error\ndata: "));
+        let error: StreamChunk =
+            serde_json::from_str(frames[1].split_once("data: ").unwrap().1.trim()).unwrap();
+        assert_eq!(error.sequence, 1);
+        assert!(error.is_final);
+        assert!(error.content.contains("model went away"));
+    }
 }
@@ -0,0 +1,105 @@
+//! Cross-session history search.
+
+use chrono::Utc;
+use copilot_context::{ContextEngineConfig, ContextEngineImpl};
+use copilot_conversation::{
+    ConversationManager, ConversationMessage, MessageRole, SearchQuery,
+};
+use copilot_nlp::NlpEngineImpl;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn manager() -> ConversationManager {
+    let context_engine = ContextEngineImpl::new(ContextEngineConfig::default()).unwrap();
+    ConversationManager::new(Arc::new(NlpEngineImpl::default()), Arc::new(context_engine))
+}
+
+async fn conversation(manager: &ConversationManager, user_id: &str, messages: &[&str]) -> String {
+    let session_id = manager
+        .session_manager()
+        .write()
+        .await
+        .create_user_session(user_id, None)
+        .unwrap()
+        .id;
+
+    let history = manager.history_manager();
+    let mut history = history.write().await;
+    for (i, content) in messages.iter().enumerate() {
+        let message = ConversationMessage {
+            id: format!("{}-{}", session_id, i),
+            role: if i % 2 == 0 { MessageRole::User } else { MessageRole::Assistant },
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            token_count: content.len() / 4,
+            metadata: HashMap::new(),
+            pinned: false,
+        };
+        history.append_message(&session_id, message).await.unwrap();
+    }
+    session_id
+}
+
+fn query(text: &str) -> SearchQuery {
+    SearchQuery {
+        query: text.to_string(),
+        role: None,
+        start_date: None,
+        end_date: None,
+        limit: None,
+        boost_pinned: false,
+    }
+}
+
+#[tokio::test]
+async fn test_search_all_ranks_hits_across_sessions() {
+    let manager = manager();
+    let checkout = conversation(
+        &manager,
+        "alice",
+        &["Show errors for checkout", "p99 latency on checkout", "Thanks"],
+    )
+    .await;
+    let spikes = conversation(
+        &manager,
+        "alice",
+        &["latency latency spikes", "The database explains why latency rose today"],
+    )
+    .await;
+    conversation(&manager, "bob", &["latency on api"]).await;
+
+    let hits = manager.search_all("alice", query("latency")).await.unwrap();
+
+    let ranked: Vec<_> = hits
+        .iter()
+        .map(|hit| (hit.session_id.as_str(), hit.result.message.content.as_str()))
+        .collect();
+    assert_eq!(
+        ranked,
+        [
+            (spikes.as_str(), "latency latency spikes"),
+            (checkout.as_str(), "p99 latency on checkout"),
+            (spikes.as_str(), "The database explains why latency rose today"),
+        ]
+    );
+    assert!(hits.windows(2).all(|w| w[0].result.score >= w[1].result.score));
+
+    // Context is the neighbouring messages of the same session
+    let context: Vec<_> = hits[1].context.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(context, ["Show errors for checkout", "Thanks"]);
+}
+
+#[tokio::test]
+async fn test_search_all_limit_applies_globally() {
+    let manager = manager();
+    conversation(&manager, "alice", &["deploy failed", "rollback the deploy"]).await;
+    conversation(&manager, "alice", &["deploy"]).await;
+
+    let mut q = query("deploy");
+    q.limit = Some(2);
+    let hits = manager.search_all("alice", q).await.unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].result.message.content, "deploy");
+
+    assert!(manager.search_all("nobody", query("deploy")).await.unwrap().is_empty());
+}
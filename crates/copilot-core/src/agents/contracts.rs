@@ -6,7 +6,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Decision types for the Agentics platform.
@@ -50,6 +50,10 @@ impl std::fmt::Display for DecisionType {
 /// - constraints_applied
 /// - execution_ref
 /// - timestamp (UTC)
+///
+/// Serialization is deterministic: constraints are kept sorted and
+/// telemetry labels are ordered by key, so an event always serializes to
+/// the same bytes and can be hashed or signed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecisionEvent {
     /// Unique identifier for this decision event
@@ -73,7 +77,7 @@ pub struct DecisionEvent {
     /// Confidence score (0.0 - 1.0)
     pub confidence: f32,
 
-    /// List of constraints that were applied
+    /// List of constraints that were applied, sorted
     pub constraints_applied: Vec<String>,
 
     /// Reference to the execution context
@@ -115,12 +119,14 @@ impl DecisionEvent {
     /// Add a constraint that was applied during decision making.
     pub fn with_constraint(mut self, constraint: impl Into<String>) -> Self {
         self.constraints_applied.push(constraint.into());
+        self.constraints_applied.sort();
         self
     }
 
     /// Add multiple constraints.
     pub fn with_constraints(mut self, constraints: Vec<String>) -> Self {
         self.constraints_applied.extend(constraints);
+        self.constraints_applied.sort();
         self
     }
 
@@ -184,9 +190,9 @@ pub struct TelemetryMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<u64>,
 
-    /// Additional labels for metrics
+    /// Additional labels for metrics, ordered by key
     #[serde(default)]
-    pub labels: HashMap<String, String>,
+    pub labels: BTreeMap<String, String>,
 }
 
 impl TelemetryMetadata {
@@ -315,4 +321,56 @@ mod tests {
         assert_eq!(telemetry.trace_id, Some("trace-123".to_string()));
         assert_eq!(telemetry.duration_ms, Some(150));
     }
+
+    #[test]
+    fn test_serialization_is_byte_stable() {
+        let event = |labels: &[(&str, &str)], constraints: &[&str]| {
+            let telemetry = labels
+                .iter()
+                .fold(TelemetryMetadata::new().with_trace("trace-1"), |t, (k, v)| {
+                    t.with_label(*k, *v)
+                });
+            let mut event = DecisionEvent::new(
+                "decomposer",
+                "1.0.0",
+                DecisionType::TaskDecomposition,
+                "abc123",
+                serde_json::json!({"tasks": 3, "depth": 2}),
+                0.9,
+            )
+            .with_constraints(constraints.iter().map(|c| c.to_string()).collect())
+            .with_execution_ref("exec-1")
+            .with_telemetry(telemetry);
+            event.id = Uuid::nil();
+            event.timestamp = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc);
+            event
+        };
+
+        let labels = [("plan_id", "p1"), ("task_count", "3"), ("agent", "d"), ("zone", "eu")];
+        let constraints = ["stateless:true", "max_depth:5", "read_only:true"];
+        let first = event(&labels, &constraints);
+        let json = serde_json::to_string(&first).unwrap();
+        assert_eq!(json, serde_json::to_string(&first).unwrap());
+
+        // Insertion order does not matter
+        let mut reversed_labels = labels;
+        reversed_labels.reverse();
+        let mut reversed_constraints = constraints;
+        reversed_constraints.reverse();
+        let second = event(&reversed_labels, &reversed_constraints);
+        assert_eq!(json, serde_json::to_string(&second).unwrap());
+
+        let parsed: DecisionEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&parsed).unwrap());
+        assert_eq!(compute_inputs_hash(&parsed), compute_inputs_hash(&first));
+
+        // Events written while labels were unordered still deserialize
+        let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+        legacy["telemetry"]["labels"] = serde_json::json!({"zone": "eu", "agent": "d"});
+        let parsed: DecisionEvent = serde_json::from_value(legacy).unwrap();
+        let keys: Vec<_> = parsed.telemetry.labels.keys().cloned().collect();
+        assert_eq!(keys, ["agent", "zone"]);
+    }
 }
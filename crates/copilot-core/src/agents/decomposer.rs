@@ -19,7 +19,9 @@
 //!
 //! # Agentics Global Agent Constitution Compliance
 //! - Stateless at runtime
-//! - Emits exactly ONE DecisionEvent per invocation
+//! - Emits exactly ONE DecisionEvent per invocation (or, with
+//!   `decompose_per_objective`, one per objective under a shared execution
+//!   reference)
 //! - Persists ONLY via ruvector-service (external)
 //! - NEVER connects directly to Google SQL
 //! - NEVER executes SQL
@@ -31,9 +33,11 @@
 use crate::agents::contracts::{
     compute_inputs_hash, DecisionEvent, DecisionEventError, DecisionType, TelemetryMetadata,
 };
+use crate::agents::telemetry::{DecisionMetrics, DecisionMetricsSink};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
 use std::time::Instant;
 use uuid::Uuid;
 
@@ -41,6 +45,13 @@ use uuid::Uuid;
 pub const DECOMPOSER_AGENT_ID: &str = "decomposer-agent";
 pub const DECOMPOSER_AGENT_VERSION: &str = "1.0.0";
 
+/// Terms that mark an objective as technically demanding by default.
+pub const DEFAULT_COMPLEXITY_KEYWORDS: &[&str] =
+    &["integrate", "migrate", "refactor", "security", "performance"];
+
+/// Default word counts past which an objective is detailed and lengthy.
+pub const DEFAULT_WORD_COUNT_THRESHOLDS: (usize, usize) = (10, 20);
+
 /// Decomposer Agent - Analyzes and decomposes plans into atomic tasks.
 ///
 /// This agent is STATELESS and produces deterministic outputs for identical inputs.
@@ -49,6 +60,8 @@ pub const DECOMPOSER_AGENT_VERSION: &str = "1.0.0";
 pub struct DecomposerAgent {
     /// Configuration for decomposition behavior
     config: DecomposerConfig,
+    /// Where emitted decisions are recorded as metrics
+    metrics: DecisionMetrics,
 }
 
 /// Configuration for the Decomposer Agent.
@@ -64,6 +77,62 @@ pub struct DecomposerConfig {
     pub detect_prerequisites: bool,
     /// Enable boundary detection
     pub detect_boundaries: bool,
+    /// Seed for randomized heuristics.
+    ///
+    /// Part of the inputs hash while a randomized heuristic is enabled, so
+    /// identical inputs and seed always produce identical outputs. Without
+    /// one the seed has no effect and the hash covers the input alone.
+    #[serde(default)]
+    pub seed: u64,
+    /// Keep at most this many tags per task, sampled using `seed`
+    #[serde(default)]
+    pub tag_sample_size: Option<usize>,
+    /// Terms that mark an objective as technically demanding, matched as
+    /// case-sensitive substrings
+    #[serde(default = "default_complexity_keywords")]
+    pub complexity_keywords: Vec<String>,
+    /// Word counts `(detailed, lengthy)` past which an objective counts as
+    /// detailed and lengthy. Objectives without keywords or multiple parts
+    /// become medium complexity halfway between the two.
+    #[serde(default = "default_word_count_thresholds")]
+    pub word_count_thresholds: (usize, usize),
+    /// Prerequisite relations less confident than this are dropped
+    #[serde(default)]
+    pub min_prerequisite_confidence: f32,
+    /// How output and input names are compared to detect data dependencies
+    #[serde(default)]
+    pub prerequisite_match: PrereqMatch,
+}
+
+/// How output and input names must match to infer a data dependency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrereqMatch {
+    /// Names are equal, ignoring case
+    Exact,
+    /// Either name contains the other, ignoring case
+    #[default]
+    Substring,
+}
+
+impl PrereqMatch {
+    /// Whether an output of one task feeds an input of another.
+    fn matches(&self, output: &str, input: &str) -> bool {
+        let output = output.to_lowercase();
+        let input = input.to_lowercase();
+        match self {
+            PrereqMatch::Exact => output == input,
+            PrereqMatch::Substring => output.contains(&input) || input.contains(&output),
+        }
+    }
+}
+
+fn default_complexity_keywords() -> Vec<String> {
+    DEFAULT_COMPLEXITY_KEYWORDS.iter().map(|k| k.to_string()).collect()
+}
+
+fn default_word_count_thresholds() -> (usize, usize) {
+    DEFAULT_WORD_COUNT_THRESHOLDS
 }
 
 impl Default for DecomposerConfig {
@@ -74,10 +143,72 @@ impl Default for DecomposerConfig {
             max_tasks: 100,
             detect_prerequisites: true,
             detect_boundaries: true,
+            seed: 0,
+            tag_sample_size: None,
+            complexity_keywords: default_complexity_keywords(),
+            word_count_thresholds: DEFAULT_WORD_COUNT_THRESHOLDS,
+            min_prerequisite_confidence: 0.0,
+            prerequisite_match: PrereqMatch::Substring,
         }
     }
 }
 
+/// Inputs covered by the decision event's inputs hash when a randomized
+/// heuristic is enabled.
+#[derive(Serialize)]
+struct HashedInputs<'a> {
+    input: &'a DecomposerInput,
+    seed: u64,
+}
+
+/// Inputs covered by the inputs hash of a single objective's decision event.
+#[derive(Serialize)]
+struct HashedObjective<'a> {
+    plan_id: &'a str,
+    objective_index: usize,
+    objective: &'a str,
+    constraints: &'a [String],
+    context: &'a DecompositionContext,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+/// Deterministic pseudo-random generator (SplitMix64) for heuristics.
+///
+/// Each consumer derives its own generator from the configured seed and a
+/// stable key, so results do not depend on evaluation order.
+#[derive(Debug, Clone)]
+struct HeuristicRng {
+    state: u64,
+}
+
+impl HeuristicRng {
+    fn new(seed: u64, key: &str) -> Self {
+        // FNV-1a over the key, mixed with the seed
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in key.bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Self {
+            state: seed ^ hash,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform index in `0..bound`.
+    fn next_index(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
 /// Input for the Decomposer Agent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecomposerInput {
@@ -151,6 +282,162 @@ pub struct DecomposerOutput {
     pub analysis: DecompositionAnalysis,
 }
 
+impl DecomposerOutput {
+    /// Build the task dependency graph from the prerequisite relations.
+    ///
+    /// Fails if a relation names a task that is not part of the output, or
+    /// the relations form a cycle, e.g. through heuristically detected data
+    /// dependencies.
+    pub fn to_dag(&self) -> Result<TaskDag, DecomposerError> {
+        let tasks: Vec<String> = self.tasks.iter().map(|task| task.id.clone()).collect();
+        let mut dependents: BTreeMap<String, BTreeSet<String>> =
+            tasks.iter().map(|id| (id.clone(), BTreeSet::new())).collect();
+        let mut prerequisites = dependents.clone();
+
+        for relation in &self.prerequisites {
+            for id in [&relation.prerequisite_task_id, &relation.dependent_task_id] {
+                if !dependents.contains_key(id) {
+                    return Err(DecomposerError::UnknownTask(id.clone()));
+                }
+            }
+            if let Some(ids) = dependents.get_mut(&relation.prerequisite_task_id) {
+                ids.insert(relation.dependent_task_id.clone());
+            }
+            if let Some(ids) = prerequisites.get_mut(&relation.dependent_task_id) {
+                ids.insert(relation.prerequisite_task_id.clone());
+            }
+        }
+
+        let collect = |map: BTreeMap<String, BTreeSet<String>>| {
+            map.into_iter()
+                .map(|(id, ids)| (id, ids.into_iter().collect()))
+                .collect()
+        };
+        let dag = TaskDag {
+            tasks,
+            dependents: collect(dependents),
+            prerequisites: collect(prerequisites),
+        };
+
+        let order = dag.kahn_order();
+        if order.len() < dag.tasks.len() {
+            return Err(DecomposerError::CycleDetected(dag.describe_cycle(&order)));
+        }
+        Ok(dag)
+    }
+}
+
+/// Dependency graph of the atomic tasks of a decomposition.
+///
+/// Built by [`DecomposerOutput::to_dag`], which guarantees it is acyclic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskDag {
+    /// Task ids in decomposition order
+    tasks: Vec<String>,
+    /// Tasks waiting on each task, keyed by task id
+    dependents: BTreeMap<String, Vec<String>>,
+    /// Tasks each task waits on, keyed by task id
+    prerequisites: BTreeMap<String, Vec<String>>,
+}
+
+impl TaskDag {
+    /// Adjacency lists: the tasks that depend on each task.
+    pub fn adjacency(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.dependents
+    }
+
+    /// Tasks that depend on a task.
+    pub fn dependents(&self, task_id: &str) -> &[String] {
+        self.dependents.get(task_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Tasks a task depends on.
+    pub fn prerequisites(&self, task_id: &str) -> &[String] {
+        self.prerequisites.get(task_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Number of tasks.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether the graph has no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Task ids ordered so every task comes after its prerequisites.
+    ///
+    /// Tasks free to run at the same point keep their decomposition order.
+    pub fn topological_order(&self) -> Vec<String> {
+        self.kahn_order()
+    }
+
+    /// Kahn's algorithm; stops short of the tasks on or behind a cycle.
+    fn kahn_order(&self) -> Vec<String> {
+        let position: HashMap<&str, usize> = self
+            .tasks
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| (id.as_str(), idx))
+            .collect();
+        let mut pending: HashMap<&str, usize> = self
+            .tasks
+            .iter()
+            .map(|id| (id.as_str(), self.prerequisites(id).len()))
+            .collect();
+        let mut ready: BTreeSet<usize> = self
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| pending[id.as_str()] == 0)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.tasks.len());
+        while let Some(idx) = ready.pop_first() {
+            let id = &self.tasks[idx];
+            for dependent in self.dependents(id) {
+                let count = pending.get_mut(dependent.as_str()).expect("dependent is a task");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(position[dependent.as_str()]);
+                }
+            }
+            order.push(id.clone());
+        }
+        order
+    }
+
+    /// Describe a cycle among the tasks Kahn's algorithm could not order.
+    fn describe_cycle(&self, ordered: &[String]) -> String {
+        let ordered: BTreeSet<&str> = ordered.iter().map(String::as_str).collect();
+        let Some(start) = self.tasks.iter().find(|id| !ordered.contains(id.as_str())) else {
+            return String::new();
+        };
+
+        // Every unordered task waits on another unordered task, so walking
+        // prerequisites must revisit a task
+        let mut path: Vec<&str> = vec![start];
+        loop {
+            let current = path[path.len() - 1];
+            let next = self
+                .prerequisites(current)
+                .iter()
+                .map(String::as_str)
+                .find(|id| !ordered.contains(id))
+                .expect("unordered task has an unordered prerequisite");
+            if let Some(pos) = path.iter().position(|id| *id == next) {
+                let mut cycle = path[pos..].to_vec();
+                cycle.reverse();
+                cycle.push(cycle[0]);
+                return cycle.join(" -> ");
+            }
+            path.push(next);
+        }
+    }
+}
+
 /// An atomic, bounded task that cannot be further decomposed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AtomicTask {
@@ -281,6 +568,10 @@ pub enum DecomposerError {
     MaxDepthExceeded(u32),
     #[error("Maximum task limit ({0}) exceeded")]
     MaxTasksExceeded(usize),
+    #[error("Prerequisite cycle detected: {0}")]
+    CycleDetected(String),
+    #[error("Prerequisite references unknown task {0}")]
+    UnknownTask(String),
     #[error("Failed to serialize output: {0}")]
     SerializationError(String),
     #[error("Decision event error: {0}")]
@@ -295,7 +586,18 @@ impl DecomposerAgent {
 
     /// Create a new Decomposer Agent with custom configuration.
     pub fn with_config(config: DecomposerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: DecisionMetrics::default(),
+        }
+    }
+
+    /// Record every emitted DecisionEvent into a metrics sink.
+    ///
+    /// Recording is observational only and does not affect the decision.
+    pub fn with_metrics(mut self, sink: Arc<dyn DecisionMetricsSink>) -> Self {
+        self.metrics = DecisionMetrics::new(sink);
+        self
     }
 
     /// Decompose a plan into atomic tasks.
@@ -320,13 +622,20 @@ impl DecomposerAgent {
         self.validate_input(input)?;
 
         // Compute inputs hash for determinism verification
-        let inputs_hash = compute_inputs_hash(input);
+        let inputs_hash = match self.hashed_seed() {
+            Some(seed) => compute_inputs_hash(&HashedInputs { input, seed }),
+            None => compute_inputs_hash(input),
+        };
 
         // Perform decomposition analysis (pure function, no side effects)
-        let output = self.analyze_and_decompose(input, start_time)?;
-
-        // Calculate overall confidence
-        let confidence = self.calculate_confidence(&output);
+        let objectives: Vec<(usize, &str)> = input
+            .plan
+            .objectives
+            .iter()
+            .map(String::as_str)
+            .enumerate()
+            .collect();
+        let output = self.analyze_and_decompose(input, &objectives, start_time)?;
 
         // Create telemetry metadata
         let telemetry = TelemetryMetadata::new()
@@ -334,8 +643,103 @@ impl DecomposerAgent {
             .with_label("plan_id", &input.plan.id)
             .with_label("task_count", output.tasks.len().to_string());
 
+        let event = self.build_event(inputs_hash, &output, telemetry, input.execution_ref.as_deref())?;
+
+        self.metrics.record(&event);
+
+        Ok(event)
+    }
+
+    /// Decompose a plan into atomic tasks, one DecisionEvent per objective.
+    ///
+    /// Each event covers the tasks of a single objective, with an inputs
+    /// hash over that objective alone, so objectives can be audited
+    /// separately. All events share one execution reference: the input's,
+    /// or a generated one. Together they hold the same tasks as the single
+    /// event of [`decompose`](Self::decompose); boundaries and
+    /// prerequisites are only detected within an objective.
+    ///
+    /// # Agent Constitution Compliance
+    /// The invocation is still a single decision: the events are validated
+    /// together and returned (and recorded) only if every one of them is
+    /// valid.
+    pub fn decompose_per_objective(
+        &self,
+        input: &DecomposerInput,
+    ) -> Result<Vec<DecisionEvent>, DecomposerError> {
+        let start_time = Instant::now();
+
+        self.validate_input(input)?;
+
+        let execution_ref = input
+            .execution_ref
+            .clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let outputs = input
+            .plan
+            .objectives
+            .iter()
+            .enumerate()
+            .map(|(idx, objective)| self.analyze_and_decompose(input, &[(idx, objective.as_str())], start_time))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The task limit applies to the plan, not to each objective
+        let total_tasks: usize = outputs.iter().map(|output| output.tasks.len()).sum();
+        if total_tasks > self.config.max_tasks {
+            return Err(DecomposerError::MaxTasksExceeded(self.config.max_tasks));
+        }
+
+        let events = outputs
+            .iter()
+            .zip(&input.plan.objectives)
+            .enumerate()
+            .map(|(idx, (output, objective))| {
+                let inputs_hash = compute_inputs_hash(&HashedObjective {
+                    plan_id: &input.plan.id,
+                    objective_index: idx,
+                    objective,
+                    constraints: &input.plan.constraints,
+                    context: &input.context,
+                    seed: self.hashed_seed(),
+                });
+                let telemetry = TelemetryMetadata::new()
+                    .with_duration(output.analysis.processing_duration_ms)
+                    .with_label("plan_id", &input.plan.id)
+                    .with_label("objective_index", idx.to_string())
+                    .with_label("task_count", output.tasks.len().to_string());
+                self.build_event(inputs_hash, output, telemetry, Some(&execution_ref))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for event in &events {
+            self.metrics.record(event);
+        }
+
+        Ok(events)
+    }
+
+    /// The seed, if any heuristic depends on it.
+    ///
+    /// Only then is it part of the inputs hash, so hashes of events from
+    /// unseeded configurations stay comparable with earlier ones.
+    fn hashed_seed(&self) -> Option<u64> {
+        self.config.tag_sample_size.map(|_| self.config.seed)
+    }
+
+    /// Build and validate the DecisionEvent of a decomposition.
+    fn build_event(
+        &self,
+        inputs_hash: String,
+        output: &DecomposerOutput,
+        telemetry: TelemetryMetadata,
+        execution_ref: Option<&str>,
+    ) -> Result<DecisionEvent, DecomposerError> {
+        // Calculate overall confidence
+        let confidence = self.calculate_confidence(output);
+
         // Serialize output for the decision event
-        let outputs = serde_json::to_value(&output)
+        let outputs = serde_json::to_value(output)
             .map_err(|e| DecomposerError::SerializationError(e.to_string()))?;
 
         // Build constraints that were applied
@@ -354,8 +758,8 @@ impl DecomposerAgent {
         .with_telemetry(telemetry);
 
         // Set execution reference if provided
-        if let Some(ref exec_ref) = input.execution_ref {
-            event = event.with_execution_ref(exec_ref.clone());
+        if let Some(exec_ref) = execution_ref {
+            event = event.with_execution_ref(exec_ref);
         }
 
         // Validate the event before returning
@@ -380,12 +784,14 @@ impl DecomposerAgent {
         Ok(())
     }
 
-    /// Perform the actual decomposition analysis.
+    /// Perform the actual decomposition analysis of the given objectives,
+    /// as `(index, objective)` pairs.
     ///
     /// This is a pure function with no side effects.
     fn analyze_and_decompose(
         &self,
         input: &DecomposerInput,
+        objectives: &[(usize, &str)],
         start_time: Instant,
     ) -> Result<DecomposerOutput, DecomposerError> {
         let mut tasks = Vec::new();
@@ -394,7 +800,7 @@ impl DecomposerAgent {
         let mut complexity_distribution: HashMap<String, usize> = HashMap::new();
 
         // Decompose each objective into atomic tasks
-        for (idx, objective) in input.plan.objectives.iter().enumerate() {
+        for &(idx, objective) in objectives {
             let objective_tasks = self.decompose_objective(
                 objective,
                 &input.plan.id,
@@ -476,7 +882,7 @@ impl DecomposerAgent {
             name: format!("Objective {}: {}", objective_idx + 1, truncate(objective, 50)),
             description: objective.to_string(),
             complexity,
-            tags: self.extract_tags(objective),
+            tags: self.task_tags(objective, &main_task_id),
             inputs: self.extract_inputs(objective),
             outputs: self.extract_outputs(objective),
             acceptance_criteria: self.extract_acceptance_criteria(objective),
@@ -518,18 +924,20 @@ impl DecomposerAgent {
         // Heuristic-based complexity analysis
         let word_count = objective.split_whitespace().count();
         let has_multiple_parts = objective.contains(" and ") || objective.contains(", ");
-        let has_technical_terms = objective.contains("integrate")
-            || objective.contains("migrate")
-            || objective.contains("refactor")
-            || objective.contains("security")
-            || objective.contains("performance");
+        let has_technical_terms = self
+            .config
+            .complexity_keywords
+            .iter()
+            .any(|keyword| objective.contains(keyword.as_str()));
+        let (detailed, lengthy) = self.config.word_count_thresholds;
+        let midpoint = (detailed + lengthy) / 2;
 
         match (word_count, has_multiple_parts, has_technical_terms) {
             (_, _, true) if has_multiple_parts => Complexity::Critical,
-            (w, true, _) if w > 20 => Complexity::High,
-            (w, _, true) if w > 10 => Complexity::High,
-            (w, true, _) if w > 10 => Complexity::Medium,
-            (w, _, _) if w > 15 => Complexity::Medium,
+            (w, true, _) if w > lengthy => Complexity::High,
+            (w, _, true) if w > detailed => Complexity::High,
+            (w, true, _) if w > detailed => Complexity::Medium,
+            (w, _, _) if w > midpoint => Complexity::Medium,
             _ => Complexity::Low,
         }
     }
@@ -562,12 +970,13 @@ impl DecomposerAgent {
             let task_id = format!("{}-obj{}-sub{}", plan_id, objective_idx, sub_idx);
             let complexity = self.analyze_objective_complexity(part, context);
 
+            let tags = self.task_tags(part, &task_id);
             let subtask = AtomicTask {
                 id: task_id,
                 name: format!("Subtask {}.{}: {}", objective_idx + 1, sub_idx + 1, truncate(part, 40)),
                 description: part.to_string(),
                 complexity,
-                tags: self.extract_tags(part),
+                tags,
                 inputs: self.extract_inputs(part),
                 outputs: self.extract_outputs(part),
                 acceptance_criteria: vec![format!("Complete: {}", truncate(part, 100))],
@@ -581,6 +990,23 @@ impl DecomposerAgent {
         Ok(subtasks)
     }
 
+    /// Tags for a task, sampled down to `tag_sample_size` if configured.
+    fn task_tags(&self, text: &str, task_id: &str) -> Vec<String> {
+        let mut tags = self.extract_tags(text);
+
+        if let Some(sample_size) = self.config.tag_sample_size {
+            let mut rng = HeuristicRng::new(self.config.seed, task_id);
+            let mut sampled = Vec::with_capacity(sample_size.min(tags.len()));
+            while sampled.len() < sample_size && !tags.is_empty() {
+                let idx = rng.next_index(tags.len());
+                sampled.push(tags.remove(idx));
+            }
+            tags = sampled;
+        }
+
+        tags
+    }
+
     /// Extract tags from text content.
     fn extract_tags(&self, text: &str) -> Vec<String> {
         let mut tags = Vec::new();
@@ -756,9 +1182,7 @@ impl DecomposerAgent {
                 for other_task in tasks.iter().skip(i + 1) {
                     for input in &other_task.inputs {
                         // Simple heuristic: if names are similar, there might be a dependency
-                        if output.name.to_lowercase().contains(&input.name.to_lowercase())
-                            || input.name.to_lowercase().contains(&output.name.to_lowercase())
-                        {
+                        if self.config.prerequisite_match.matches(&output.name, &input.name) {
                             prerequisites.push(PrerequisiteRelation {
                                 prerequisite_task_id: task.id.clone(),
                                 dependent_task_id: other_task.id.clone(),
@@ -771,6 +1195,7 @@ impl DecomposerAgent {
             }
         }
 
+        prerequisites.retain(|relation| relation.confidence >= self.config.min_prerequisite_confidence);
         prerequisites
     }
 
@@ -821,6 +1246,11 @@ impl DecomposerAgent {
             format!("min_confidence:{}", self.config.min_confidence),
         ];
 
+        if let Some(sample_size) = self.config.tag_sample_size {
+            constraints.push(format!("seed:{}", self.config.seed));
+            constraints.push(format!("tag_sample_size:{}", sample_size));
+        }
+
         if self.config.detect_prerequisites {
             constraints.push("prerequisite_detection:enabled".to_string());
         }
@@ -833,6 +1263,7 @@ impl DecomposerAgent {
         constraints.push("read_only:true".to_string());
         constraints.push("non_executing:true".to_string());
 
+        constraints.sort();
         constraints
     }
 }
@@ -888,6 +1319,12 @@ mod tests {
             max_tasks: 50,
             detect_prerequisites: true,
             detect_boundaries: false,
+            seed: 0,
+            tag_sample_size: None,
+            complexity_keywords: vec!["database".to_string()],
+            word_count_thresholds: (5, 8),
+            min_prerequisite_confidence: 0.5,
+            prerequisite_match: PrereqMatch::Exact,
         };
         let agent = DecomposerAgent::with_config(config);
         assert_eq!(agent.config.max_depth, 3);
@@ -1009,6 +1446,36 @@ mod tests {
         assert!(matches!(complex_complexity, Complexity::High | Complexity::Critical));
     }
 
+    #[test]
+    fn test_custom_complexity_keywords() {
+        let context = DecompositionContext::default();
+        let objective = "Recalibrate the spectrometer in the north laboratory wing every single week";
+
+        let default_agent = DecomposerAgent::new();
+        assert_eq!(
+            default_agent.analyze_objective_complexity(objective, &context),
+            Complexity::Low
+        );
+
+        let lab_agent = DecomposerAgent::with_config(DecomposerConfig {
+            complexity_keywords: vec!["spectrometer".to_string(), "centrifuge".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            lab_agent.analyze_objective_complexity(objective, &context),
+            Complexity::High
+        );
+
+        // Missing fields deserialize to the defaults
+        let config: DecomposerConfig = serde_json::from_str(
+            r#"{"max_depth": 5, "min_confidence": 0.7, "max_tasks": 100,
+                "detect_prerequisites": true, "detect_boundaries": true}"#,
+        )
+        .unwrap();
+        assert_eq!(config.complexity_keywords, DecomposerConfig::default().complexity_keywords);
+        assert_eq!(config.word_count_thresholds, DEFAULT_WORD_COUNT_THRESHOLDS);
+    }
+
     #[test]
     fn test_tag_extraction() {
         let agent = DecomposerAgent::new();
@@ -1034,6 +1501,261 @@ mod tests {
         assert!(event.telemetry.labels.contains_key("task_count"));
     }
 
+    #[test]
+    fn test_seed_is_part_of_inputs_hash() {
+        let input = sample_input();
+
+        let seeded = |seed, tag_sample_size| {
+            DecomposerAgent::with_config(DecomposerConfig {
+                seed,
+                tag_sample_size,
+                ..Default::default()
+            })
+        };
+
+        let hash1 = seeded(7, Some(2)).decompose(&input).unwrap().inputs_hash;
+        let hash2 = seeded(7, Some(2)).decompose(&input).unwrap().inputs_hash;
+        let hash3 = seeded(8, Some(2)).decompose(&input).unwrap().inputs_hash;
+
+        assert_eq!(hash1, hash2);
+        assert_ne!(hash1, hash3);
+
+        // Without a seeded heuristic the hash covers the input alone, as
+        // it did before seeds existed
+        let unseeded = seeded(7, None).decompose(&input).unwrap().inputs_hash;
+        assert_eq!(unseeded, compute_inputs_hash(&input));
+        assert_eq!(unseeded, seeded(8, None).decompose(&input).unwrap().inputs_hash);
+    }
+
+    #[test]
+    fn test_same_seed_reproduces_sampled_tags() {
+        let mut input = sample_input();
+        input.plan.objectives = vec![
+            "Build backend API endpoints with auth, database storage, tests and deploy docs".to_string(),
+        ];
+
+        let tags_for = |seed| {
+            let agent = DecomposerAgent::with_config(DecomposerConfig {
+                seed,
+                tag_sample_size: Some(2),
+                ..Default::default()
+            });
+            let event = agent.decompose(&input).unwrap();
+            let output: DecomposerOutput = serde_json::from_value(event.outputs).unwrap();
+            output.tasks.into_iter().map(|t| t.tags).collect::<Vec<_>>()
+        };
+
+        // Identical inputs and seed produce identical outputs
+        let first = tags_for(42);
+        assert_eq!(first, tags_for(42));
+        assert!(first.iter().all(|tags| tags.len() <= 2));
+
+        // Different seeds sample different tags
+        let distinct: std::collections::HashSet<_> = (0..16).map(tags_for).collect();
+        assert!(distinct.len() > 1);
+    }
+
+    #[test]
+    fn test_per_objective_events_cover_the_plan() {
+        let agent = DecomposerAgent::new();
+        let input = sample_input();
+
+        let single: DecomposerOutput =
+            serde_json::from_value(agent.decompose(&input).unwrap().outputs).unwrap();
+        let events = agent.decompose_per_objective(&input).unwrap();
+        assert_eq!(events.len(), input.plan.objectives.len());
+
+        let outputs: Vec<DecomposerOutput> = events
+            .iter()
+            .map(|event| serde_json::from_value(event.outputs.clone()).unwrap())
+            .collect();
+        let total: usize = outputs.iter().map(|output| output.tasks.len()).sum();
+        assert_eq!(total, single.tasks.len());
+        for (idx, output) in outputs.iter().enumerate() {
+            let prefix = format!("plan-001-obj{}-", idx);
+            assert!(output.tasks.iter().all(|task| task.id.starts_with(&prefix)));
+        }
+
+        assert!(events.iter().all(|event| event.execution_ref == "test-execution-001"));
+        let hashes: std::collections::HashSet<_> = events.iter().map(|e| &e.inputs_hash).collect();
+        assert_eq!(hashes.len(), events.len());
+
+        // Without an execution reference the events still share one
+        let mut unreferenced = sample_input();
+        unreferenced.execution_ref = None;
+        let events = agent.decompose_per_objective(&unreferenced).unwrap();
+        assert!(events.iter().all(|event| event.execution_ref == events[0].execution_ref));
+    }
+
+    #[test]
+    fn test_per_objective_events_are_deterministic() {
+        let agent = DecomposerAgent::new();
+        let input = sample_input();
+        let summary = |events: Vec<DecisionEvent>| -> Vec<(String, Vec<String>)> {
+            events
+                .into_iter()
+                .map(|event| {
+                    let output: DecomposerOutput = serde_json::from_value(event.outputs).unwrap();
+                    (event.inputs_hash, output.tasks.into_iter().map(|task| task.id).collect())
+                })
+                .collect()
+        };
+
+        let first = summary(agent.decompose_per_objective(&input).unwrap());
+        let second = summary(agent.decompose_per_objective(&input).unwrap());
+        assert_eq!(first, second);
+
+        // An objective's hash covers only its own slice of the plan
+        let mut changed = sample_input();
+        changed.plan.objectives[2] = "Document the auth module".to_string();
+        let third = summary(agent.decompose_per_objective(&changed).unwrap());
+        assert_eq!(first[0].0, third[0].0);
+        assert_ne!(first[2].0, third[2].0);
+    }
+
+    fn relation(from: &str, to: &str, relation_type: PrerequisiteType) -> PrerequisiteRelation {
+        PrerequisiteRelation {
+            prerequisite_task_id: from.to_string(),
+            dependent_task_id: to.to_string(),
+            relation_type,
+            confidence: 0.7,
+        }
+    }
+
+    fn task_with_io(id: &str, inputs: &[&str], outputs: &[&str]) -> AtomicTask {
+        AtomicTask {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: id.to_string(),
+            complexity: Complexity::Low,
+            tags: Vec::new(),
+            inputs: inputs
+                .iter()
+                .map(|name| TaskInput {
+                    name: name.to_string(),
+                    description: String::new(),
+                    source: None,
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|name| TaskOutput {
+                    name: name.to_string(),
+                    description: String::new(),
+                })
+                .collect(),
+            acceptance_criteria: Vec::new(),
+            depth: 0,
+            parent_id: None,
+        }
+    }
+
+    #[test]
+    fn test_exact_prerequisite_matching_drops_spurious_relations() {
+        let input = sample_input();
+        let tasks = vec![
+            task_with_io("collect", &[], &["metadata", "Report"]),
+            task_with_io("publish", &["data", "report"], &[]),
+        ];
+        let relations = |prerequisite_match| {
+            DecomposerAgent::with_config(DecomposerConfig {
+                prerequisite_match,
+                ..Default::default()
+            })
+            .detect_prerequisites(&tasks, &input)
+        };
+
+        // "metadata" contains "data", so substring mode infers two relations
+        assert_eq!(relations(PrereqMatch::Substring).len(), 2);
+        assert_eq!(relations(PrereqMatch::Exact).len(), 1);
+    }
+
+    #[test]
+    fn test_low_confidence_prerequisites_are_dropped() {
+        let agent = |min_prerequisite_confidence| {
+            DecomposerAgent::with_config(DecomposerConfig {
+                min_prerequisite_confidence,
+                ..Default::default()
+            })
+        };
+
+        // Data dependencies (0.7) fall below 0.8, hard dependencies (0.95) do not
+        let input = sample_input();
+        let mut child = task_with_io("child", &[], &["report"]);
+        child.parent_id = Some("parent".to_string());
+        let tasks = vec![task_with_io("parent", &[], &[]), child, task_with_io("reader", &["report"], &[])];
+        assert_eq!(agent(0.0).detect_prerequisites(&tasks, &input).len(), 2);
+        let kept = agent(0.8).detect_prerequisites(&tasks, &input);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].relation_type, PrerequisiteType::HardDependency);
+
+        // The analysis counts only kept relations
+        let mut input = sample_input();
+        input.plan.objectives = vec!["Migrate the billing database and refactor the invoice service".to_string()];
+        let analysis = |min_prerequisite_confidence| {
+            let event = agent(min_prerequisite_confidence).decompose(&input).unwrap();
+            serde_json::from_value::<DecomposerOutput>(event.outputs).unwrap().analysis
+        };
+        assert!(analysis(0.0).prerequisite_count > 0);
+        assert_eq!(analysis(0.99).prerequisite_count, 0);
+    }
+
+    #[test]
+    fn test_dag_orders_subtasks_before_parents() {
+        let agent = DecomposerAgent::new();
+        let mut input = sample_input();
+        input.plan.objectives = vec![
+            "Migrate the billing database and refactor the invoice service".to_string(),
+            "Write release notes".to_string(),
+        ];
+        let output: DecomposerOutput =
+            serde_json::from_value(agent.decompose(&input).unwrap().outputs).unwrap();
+        assert!(output.tasks.iter().any(|task| task.parent_id.is_some()));
+
+        let dag = output.to_dag().unwrap();
+        let order = dag.topological_order();
+        assert_eq!(order.len(), output.tasks.len());
+        let position = |id: &str| order.iter().position(|task| task == id).unwrap();
+        for relation in &output.prerequisites {
+            assert!(position(&relation.prerequisite_task_id) < position(&relation.dependent_task_id));
+        }
+        for task in output.tasks.iter().filter(|task| task.parent_id.is_some()) {
+            let parent = task.parent_id.as_deref().unwrap();
+            assert!(position(&task.id) < position(parent));
+            assert!(dag.dependents(&task.id).iter().any(|id| id == parent));
+            assert!(dag.prerequisites(parent).contains(&task.id));
+        }
+        assert_eq!(order, dag.topological_order());
+    }
+
+    #[test]
+    fn test_dag_detects_data_dependency_cycle() {
+        let agent = DecomposerAgent::new();
+        let mut output: DecomposerOutput =
+            serde_json::from_value(agent.decompose(&sample_input()).unwrap().outputs).unwrap();
+        output.prerequisites = vec![
+            relation("plan-001-obj0-main", "plan-001-obj1-main", PrerequisiteType::DataDependency),
+            relation("plan-001-obj1-main", "plan-001-obj2-main", PrerequisiteType::DataDependency),
+            relation("plan-001-obj2-main", "plan-001-obj0-main", PrerequisiteType::DataDependency),
+        ];
+
+        match output.to_dag() {
+            Err(DecomposerError::CycleDetected(cycle)) => {
+                assert!(cycle.contains("plan-001-obj0-main"));
+                assert_eq!(cycle.matches(" -> ").count(), 3);
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+
+        output.prerequisites.pop();
+        let order = output.to_dag().unwrap().topological_order();
+        let position = |id: &str| order.iter().position(|task| task == id).unwrap();
+        assert!(position("plan-001-obj0-main") < position("plan-001-obj2-main"));
+
+        output.prerequisites.push(relation("plan-001-obj2-main", "missing", PrerequisiteType::DataDependency));
+        assert!(matches!(output.to_dag(), Err(DecomposerError::UnknownTask(id)) if id == "missing"));
+    }
+
     #[test]
     fn test_execution_ref_preserved() {
         let agent = DecomposerAgent::new();
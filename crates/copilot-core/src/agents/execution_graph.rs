@@ -9,6 +9,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// The name of this repository in the Agentics execution graph.
@@ -90,6 +91,78 @@ impl Artifact {
     }
 }
 
+/// Limits on the artifacts attached to agent-level spans.
+///
+/// Artifacts travel with the serialized graph, so unbounded payloads
+/// produce oversized messages downstream. Unset limits are not enforced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactPolicy {
+    /// Maximum number of artifacts per span
+    pub max_artifacts: Option<usize>,
+    /// Maximum serialized size of each artifact's `data`, in bytes
+    pub max_data_bytes: Option<usize>,
+    /// Accepted `artifact_type`s; any type when `None`
+    pub allowed_types: Option<Vec<String>>,
+}
+
+impl ArtifactPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_artifacts(mut self, max: usize) -> Self {
+        self.max_artifacts = Some(max);
+        self
+    }
+
+    pub fn with_max_data_bytes(mut self, max: usize) -> Self {
+        self.max_data_bytes = Some(max);
+        self
+    }
+
+    pub fn with_allowed_types<I, S>(mut self, types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_types = Some(types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check the artifacts of one span against the policy.
+    pub fn check(&self, artifacts: &[Artifact]) -> Result<(), ExecutionGraphError> {
+        if let Some(limit) = self.max_artifacts {
+            if artifacts.len() > limit {
+                return Err(ExecutionGraphError::TooManyArtifacts {
+                    count: artifacts.len(),
+                    limit,
+                });
+            }
+        }
+
+        for artifact in artifacts {
+            if let Some(allowed) = &self.allowed_types {
+                if !allowed.contains(&artifact.artifact_type) {
+                    return Err(ExecutionGraphError::ArtifactTypeNotAllowed(
+                        artifact.artifact_type.clone(),
+                    ));
+                }
+            }
+            if let Some(limit) = self.max_data_bytes {
+                let size = serde_json::to_vec(&artifact.data).map_or(usize::MAX, |bytes| bytes.len());
+                if size > limit {
+                    return Err(ExecutionGraphError::ArtifactTooLarge {
+                        name: artifact.name.clone(),
+                        size,
+                        limit,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A single span in the execution graph.
 ///
 /// Every span has a required parent_span_id (not optional) to enforce
@@ -129,6 +202,17 @@ pub struct ExecutionSpan {
     pub attributes: HashMap<String, String>,
 }
 
+impl ExecutionSpan {
+    /// Time from start to end of the span; `None` while it is running.
+    ///
+    /// An end time before the start time (clock skew between emitters)
+    /// counts as zero.
+    pub fn duration(&self) -> Option<Duration> {
+        self.end_time
+            .map(|end| (end - self.start_time).to_std().unwrap_or_default())
+    }
+}
+
 /// The complete execution graph for one invocation of this repository.
 ///
 /// The graph is append-only and causally ordered via parent_span_id.
@@ -140,8 +224,16 @@ pub struct ExecutionGraph {
     pub execution_id: String,
     /// The span_id of the repo-level span (root of this repo's subgraph)
     pub repo_span_id: String,
+    /// The span_id of the Core span the repo span hangs off, as provided
+    /// by the external caller. Empty in graphs recorded before it was
+    /// kept, in which case any non-empty repo parent is accepted.
+    #[serde(default)]
+    pub core_span_id: String,
     /// All spans, append-only, causally ordered
     pub spans: Vec<ExecutionSpan>,
+    /// Limits on artifacts attached when completing agent spans
+    #[serde(skip)]
+    artifact_policy: Option<ArtifactPolicy>,
 }
 
 /// Errors from ExecutionGraph operations.
@@ -157,6 +249,12 @@ pub enum ExecutionGraphError {
     SpanAlreadyCompleted(String),
     #[error("Invalid execution graph: {0}")]
     InvalidGraph(String),
+    #[error("Artifact {name} is {size} bytes, over the limit of {limit}")]
+    ArtifactTooLarge { name: String, size: usize, limit: usize },
+    #[error("Span has {count} artifacts, over the limit of {limit}")]
+    TooManyArtifacts { count: usize, limit: usize },
+    #[error("Artifact type not allowed: {0}")]
+    ArtifactTypeNotAllowed(String),
 }
 
 impl ExecutionGraph {
@@ -181,7 +279,7 @@ impl ExecutionGraph {
 
         let repo_span = ExecutionSpan {
             span_id: repo_span_id.clone(),
-            parent_span_id: parent,
+            parent_span_id: parent.clone(),
             trace_id: trace,
             span_type: SpanType::Repo,
             repo_name: Some(REPO_NAME.to_string()),
@@ -197,10 +295,18 @@ impl ExecutionGraph {
         Ok(Self {
             execution_id: execution_id.into(),
             repo_span_id,
+            core_span_id: parent,
             spans: vec![repo_span],
+            artifact_policy: None,
         })
     }
 
+    /// Enforce an artifact policy when completing agent spans.
+    pub fn with_artifact_policy(mut self, policy: ArtifactPolicy) -> Self {
+        self.artifact_policy = Some(policy);
+        self
+    }
+
     /// Start a new agent-level span as a child of the repo span.
     ///
     /// Returns the new span_id for later completion/failure.
@@ -228,17 +334,24 @@ impl ExecutionGraph {
     }
 
     /// Complete an agent-level span successfully, attaching any artifacts.
+    ///
+    /// Artifacts violating the graph's [`ArtifactPolicy`] are rejected and
+    /// leave the span running.
     pub fn complete_agent_span(
         &mut self,
         span_id: &str,
         artifacts: Vec<Artifact>,
     ) -> Result<(), ExecutionGraphError> {
+        let policy = self.artifact_policy.clone();
         let span = self
             .find_span_mut(span_id)?;
 
         if span.status != ExecutionStatus::Running {
             return Err(ExecutionGraphError::SpanAlreadyCompleted(span_id.to_string()));
         }
+        if let Some(policy) = policy {
+            policy.check(&artifacts)?;
+        }
 
         span.status = ExecutionStatus::Completed;
         span.end_time = Some(Utc::now());
@@ -294,8 +407,11 @@ impl ExecutionGraph {
     ///
     /// Checks:
     /// - At least one agent-level span exists
-    /// - All spans have valid parent_span_id references
+    /// - All spans have valid parent_span_id references: the single repo
+    ///   span hangs off the Core span, every agent span off the repo span
     pub fn validate(&self) -> Result<(), ExecutionGraphError> {
+        self.validate_tree()?;
+
         let agent_count = self
             .spans
             .iter()
@@ -309,6 +425,51 @@ impl ExecutionGraph {
         Ok(())
     }
 
+    /// Check that the spans form the Core → Repo → Agent tree.
+    fn validate_tree(&self) -> Result<(), ExecutionGraphError> {
+        let invalid = |reason: String| Err(ExecutionGraphError::InvalidGraph(reason));
+
+        for span in &self.spans {
+            match span.span_type {
+                SpanType::Repo if span.span_id != self.repo_span_id => {
+                    return invalid(format!(
+                        "repo span {} is not the graph's repo span {}",
+                        span.span_id, self.repo_span_id
+                    ));
+                }
+                SpanType::Repo => {
+                    if span.parent_span_id.is_empty() {
+                        return Err(ExecutionGraphError::MissingParentSpanId);
+                    }
+                    if !self.core_span_id.is_empty() && span.parent_span_id != self.core_span_id {
+                        return invalid(format!(
+                            "repo span {} has parent {}, expected core span {}",
+                            span.span_id, span.parent_span_id, self.core_span_id
+                        ));
+                    }
+                }
+                SpanType::Agent if span.parent_span_id != self.repo_span_id => {
+                    return invalid(format!(
+                        "agent span {} has parent {}, expected repo span {}",
+                        span.span_id, span.parent_span_id, self.repo_span_id
+                    ));
+                }
+                SpanType::Agent => {}
+                SpanType::Core => {
+                    return invalid(format!(
+                        "core span {} belongs to the caller's graph",
+                        span.span_id
+                    ));
+                }
+            }
+        }
+
+        if self.repo_span().is_none() {
+            return invalid(format!("repo span {} is missing", self.repo_span_id));
+        }
+        Ok(())
+    }
+
     /// Check if the graph has any agent-level spans.
     pub fn has_agent_spans(&self) -> bool {
         self.spans.iter().any(|s| s.span_type == SpanType::Agent)
@@ -327,6 +488,22 @@ impl ExecutionGraph {
             .collect()
     }
 
+    /// Duration of the whole invocation, i.e. of the repo-level span.
+    ///
+    /// `None` until the repo span has completed or failed.
+    pub fn total_duration(&self) -> Option<Duration> {
+        self.repo_span().and_then(ExecutionSpan::duration)
+    }
+
+    /// The agent-level span that took longest, among those that ended.
+    pub fn slowest_agent_span(&self) -> Option<&ExecutionSpan> {
+        self.agent_spans()
+            .into_iter()
+            .filter_map(|span| span.duration().map(|duration| (duration, span)))
+            .max_by_key(|(duration, _)| *duration)
+            .map(|(_, span)| span)
+    }
+
     /// Serialize the execution graph to a JSON value.
     pub fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
         serde_json::to_value(self)
@@ -347,6 +524,44 @@ impl ExecutionGraph {
     }
 }
 
+/// Run an agent invocation inside its own agent-level span.
+///
+/// Starts a span for `agent_name`, runs `f`, and completes the span with the
+/// returned artifacts on success or fails it with the error message
+/// otherwise, so an invocation that returns early with an error still
+/// leaves a span in the graph. If the artifacts violate the graph's
+/// [`ArtifactPolicy`], the span is failed with the violation while the
+/// agent's result is still returned.
+pub fn instrument_agent<T, E>(
+    graph: &mut ExecutionGraph,
+    agent_name: &str,
+    f: impl FnOnce() -> Result<(T, Vec<Artifact>), E>,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+{
+    let span_id = graph.start_agent_span(agent_name);
+    let result = f();
+
+    // The span was started above, so it exists and is still running
+    match result {
+        Ok((value, artifacts)) => {
+            if let Err(rejected) = graph.complete_agent_span(&span_id, artifacts) {
+                graph
+                    .fail_agent_span(&span_id, rejected.to_string())
+                    .expect("span with rejected artifacts is still running");
+            }
+            Ok(value)
+        }
+        Err(error) => {
+            graph
+                .fail_agent_span(&span_id, error.to_string())
+                .expect("freshly started span can be failed");
+            Err(error)
+        }
+    }
+}
+
 /// Generate a unique span ID (16 hex characters, matching CorrelationContext format).
 fn generate_span_id() -> String {
     Uuid::new_v4().to_string().replace('-', "")[..16].to_string()
@@ -418,6 +633,146 @@ mod tests {
         assert!(failed.end_time.is_some());
     }
 
+    #[test]
+    fn test_instrument_agent_records_success() {
+        let mut graph = ExecutionGraph::new("exec-1", "parent-abc", "trace-xyz").unwrap();
+
+        let result: Result<u32, String> = instrument_agent(&mut graph, "planner-agent", || {
+            let artifact = Artifact::new("plan", "report", "plan-1", serde_json::json!({"steps": 3}));
+            Ok((3, vec![artifact]))
+        });
+
+        assert_eq!(result, Ok(3));
+        let spans = graph.agent_spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].agent_name.as_deref(), Some("planner-agent"));
+        assert_eq!(spans[0].status, ExecutionStatus::Completed);
+        assert_eq!(spans[0].artifacts[0].reference, "plan-1");
+        assert!(graph.complete_repo().is_ok());
+    }
+
+    #[test]
+    fn test_instrument_agent_records_failure() {
+        let mut graph = ExecutionGraph::new("exec-1", "parent-abc", "trace-xyz").unwrap();
+
+        let result: Result<(), String> = instrument_agent(&mut graph, "planner-agent", || {
+            Err("Input validation failed".to_string())?;
+            Ok(((), Vec::new()))
+        });
+
+        assert_eq!(result, Err("Input validation failed".to_string()));
+        let spans = graph.agent_spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].status, ExecutionStatus::Failed);
+        assert_eq!(spans[0].failure_reason.as_deref(), Some("Input validation failed"));
+        assert!(spans[0].end_time.is_some());
+        assert!(spans[0].artifacts.is_empty());
+    }
+
+    #[test]
+    fn test_span_durations() {
+        let mut graph = ExecutionGraph::new("exec-1", "parent-abc", "trace-xyz").unwrap();
+        let fast = graph.start_agent_span("fast-agent");
+        let slow = graph.start_agent_span("slow-agent");
+        graph.start_agent_span("running-agent");
+        graph.complete_agent_span(&fast, Vec::new()).unwrap();
+        graph.fail_agent_span(&slow, "timed out").unwrap();
+
+        // Pin the timings of the finished spans
+        let start = Utc::now();
+        for (span, millis) in graph.spans.iter_mut().skip(1).zip([40, 250]) {
+            span.start_time = start;
+            span.end_time = Some(start + chrono::Duration::milliseconds(millis));
+        }
+
+        assert_eq!(graph.spans[1].duration(), Some(Duration::from_millis(40)));
+        assert_eq!(graph.spans[3].duration(), None);
+        let slowest = graph.slowest_agent_span().unwrap();
+        assert_eq!(slowest.agent_name.as_deref(), Some("slow-agent"));
+
+        // The repo span is still running
+        assert_eq!(graph.total_duration(), None);
+        graph.complete_repo().unwrap();
+        assert!(graph.total_duration().is_some());
+    }
+
+    #[test]
+    fn test_orphaned_agent_span_fails_validation() {
+        let mut graph = ExecutionGraph::new("exec-1", "core-span-123", "trace-abc").unwrap();
+        graph.start_agent_span("agent-a");
+        let orphan = graph.start_agent_span("agent-b");
+        assert!(graph.validate().is_ok());
+
+        // As if deserialized from a malformed graph
+        let mut json = graph.to_json().unwrap();
+        json["spans"][2]["parent_span_id"] = serde_json::json!("unknown-span");
+        let malformed: ExecutionGraph = serde_json::from_value(json).unwrap();
+        match malformed.validate() {
+            Err(ExecutionGraphError::InvalidGraph(reason)) => assert!(reason.contains(&orphan)),
+            other => panic!("expected InvalidGraph, got {:?}", other),
+        }
+
+        let mut detached = graph.clone();
+        detached.spans[0].parent_span_id = "other-core".to_string();
+        assert!(matches!(detached.validate(), Err(ExecutionGraphError::InvalidGraph(_))));
+
+        let mut rootless = graph.clone();
+        rootless.spans.remove(0);
+        assert!(matches!(rootless.validate(), Err(ExecutionGraphError::InvalidGraph(_))));
+    }
+
+    #[test]
+    fn test_artifact_policy_rejects_oversized_artifacts() {
+        const MB: usize = 1024 * 1024;
+        let mut graph = ExecutionGraph::new("exec-1", "parent-abc", "trace-xyz")
+            .unwrap()
+            .with_artifact_policy(ArtifactPolicy::new().with_max_data_bytes(MB));
+        let span_id = graph.start_agent_span("report-agent");
+
+        let large = Artifact::new("dump", "report", "dump-1", serde_json::json!("x".repeat(2 * MB)));
+        match graph.complete_agent_span(&span_id, vec![large]) {
+            Err(ExecutionGraphError::ArtifactTooLarge { name, size, limit }) => {
+                assert_eq!(name, "dump");
+                assert!(size > 2 * MB);
+                assert_eq!(limit, MB);
+            }
+            other => panic!("expected ArtifactTooLarge, got {:?}", other),
+        }
+        assert_eq!(graph.spans[1].status, ExecutionStatus::Running);
+
+        let small = Artifact::new("summary", "report", "summary-1", serde_json::json!({"lines": 3}));
+        graph.complete_agent_span(&span_id, vec![small]).unwrap();
+        assert_eq!(graph.spans[1].artifacts.len(), 1);
+    }
+
+    #[test]
+    fn test_artifact_policy_limits_count_and_types() {
+        let policy = ArtifactPolicy::new()
+            .with_max_artifacts(1)
+            .with_allowed_types(["decision_event"]);
+        let event = || Artifact::new("event", "decision_event", "evt-1", serde_json::json!({}));
+
+        assert!(policy.check(&[event()]).is_ok());
+        assert!(matches!(
+            policy.check(&[event(), event()]),
+            Err(ExecutionGraphError::TooManyArtifacts { count: 2, limit: 1 })
+        ));
+        let config = Artifact::new("config", "config", "cfg-1", serde_json::json!({}));
+        assert!(matches!(
+            policy.check(std::slice::from_ref(&config)),
+            Err(ExecutionGraphError::ArtifactTypeNotAllowed(kind)) if kind == "config"
+        ));
+
+        // Instrumented agents keep their result but record the violation
+        let mut graph = ExecutionGraph::new("exec-1", "parent-abc", "trace-xyz")
+            .unwrap()
+            .with_artifact_policy(policy);
+        let result: Result<u8, String> = instrument_agent(&mut graph, "config-agent", || Ok((7, vec![config])));
+        assert_eq!(result, Ok(7));
+        assert_eq!(graph.spans[1].status, ExecutionStatus::Failed);
+        assert!(graph.spans[1].failure_reason.as_deref().unwrap().contains("not allowed"));
+    }
+
     #[test]
     fn test_fails_without_agent_spans() {
         let mut graph = ExecutionGraph::new("exec-1", "parent-abc", "trace-xyz").unwrap();
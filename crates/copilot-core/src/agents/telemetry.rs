@@ -3,9 +3,10 @@
 //! Provides tracing and metrics emission compatible with the LLM-Observatory platform.
 //! All agents MUST emit telemetry for observability.
 
-use crate::agents::contracts::{DecisionEvent, TelemetryMetadata};
+use crate::agents::contracts::{DecisionEvent, DecisionType, TelemetryMetadata};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 /// Telemetry event types for LLM-Observatory.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -278,6 +279,93 @@ impl AgentMetrics {
     }
 }
 
+/// Metric values extracted from a DecisionEvent.
+///
+/// Only the agent ID and decision type are kept as labels so series stay
+/// low cardinality; per-invocation identifiers such as plan IDs are
+/// dropped. Counters come from telemetry labels named `*_count` with an
+/// integer value, plus the number of constraints applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionSample {
+    /// Agent identifier
+    pub agent_id: String,
+    /// Type of decision made
+    pub decision_type: DecisionType,
+    /// Processing duration in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Decision confidence
+    pub confidence: f32,
+    /// Counter increments by name
+    pub counters: BTreeMap<String, u64>,
+}
+
+impl DecisionSample {
+    /// Extract the metric values of a decision event.
+    pub fn from_decision_event(decision: &DecisionEvent) -> Self {
+        let mut counters: BTreeMap<String, u64> = decision
+            .telemetry
+            .labels
+            .iter()
+            .filter(|(key, _)| key.ends_with("_count"))
+            .filter_map(|(key, value)| Some((key.clone(), value.parse().ok()?)))
+            .collect();
+        counters.insert(
+            "constraints_applied".to_string(),
+            decision.constraints_applied.len() as u64,
+        );
+
+        Self {
+            agent_id: decision.agent_id.clone(),
+            decision_type: decision.decision_type,
+            duration_ms: decision.telemetry.duration_ms,
+            confidence: decision.confidence,
+            counters,
+        }
+    }
+}
+
+/// Destination for agent decision metrics, e.g. a Prometheus registry.
+pub trait DecisionMetricsSink: Send + Sync {
+    /// Record one decision.
+    fn record_decision(&self, sample: &DecisionSample);
+}
+
+/// Optional handle to a [`DecisionMetricsSink`].
+///
+/// Agents hold one and record every DecisionEvent they emit through it.
+/// Without a sink, recording is a no-op.
+#[derive(Clone, Default)]
+pub struct DecisionMetrics {
+    sink: Option<Arc<dyn DecisionMetricsSink>>,
+}
+
+impl DecisionMetrics {
+    /// Record decisions into a sink.
+    pub fn new(sink: Arc<dyn DecisionMetricsSink>) -> Self {
+        Self { sink: Some(sink) }
+    }
+
+    /// Whether a sink is attached.
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Record a decision event, if a sink is attached.
+    pub fn record(&self, decision: &DecisionEvent) {
+        if let Some(ref sink) = self.sink {
+            sink.record_decision(&DecisionSample::from_decision_event(decision));
+        }
+    }
+}
+
+impl std::fmt::Debug for DecisionMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecisionMetrics")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
 /// OpenTelemetry-compatible span data for export.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OTelSpan {
@@ -413,7 +501,7 @@ impl OTelSpan {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::agents::contracts::{DecisionEvent, DecisionType};
+    use crate::agents::contracts::{DecisionEvent, DecisionType, TelemetryMetadata};
 
     #[test]
     fn test_telemetry_event_creation() {
@@ -477,6 +565,38 @@ mod tests {
         assert_eq!(metrics.failure_rate(), 0.05);
     }
 
+    #[test]
+    fn test_decision_sample_drops_high_cardinality_labels() {
+        let decision = DecisionEvent::new(
+            "decomposer-agent",
+            "1.0.0",
+            DecisionType::TaskDecomposition,
+            "hash123",
+            serde_json::json!({}),
+            0.8,
+        )
+        .with_constraint("max_depth=5")
+        .with_telemetry(
+            TelemetryMetadata::new()
+                .with_duration(12)
+                .with_label("plan_id", "plan-42")
+                .with_label("task_count", "7"),
+        );
+
+        let sample = DecisionSample::from_decision_event(&decision);
+        assert_eq!(sample.duration_ms, Some(12));
+        assert_eq!(
+            sample.counters,
+            BTreeMap::from([
+                ("constraints_applied".to_string(), 1),
+                ("task_count".to_string(), 7),
+            ])
+        );
+
+        // No sink: recording is a no-op
+        DecisionMetrics::default().record(&decision);
+    }
+
     #[test]
     fn test_telemetry_event_json() {
         let event = TelemetryEvent::invocation_start("test-agent", "1.0.0")
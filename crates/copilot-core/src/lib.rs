@@ -29,14 +29,14 @@ pub use agents::{
     decomposer::{
         AtomicTask, BoundaryType, Complexity, DecomposerAgent, DecomposerConfig, DecomposerError,
         DecomposerInput, DecomposerOutput, Plan, PrerequisiteRelation, PrerequisiteType,
-        TaskBoundary, DECOMPOSER_AGENT_ID, DECOMPOSER_AGENT_VERSION,
+        PrereqMatch, TaskBoundary, TaskDag, DECOMPOSER_AGENT_ID, DECOMPOSER_AGENT_VERSION,
     },
     telemetry::{
-        AgentMetrics, OTelSpan, SpanKind, SpanStatus, StatusCode, TelemetryContext,
+        AgentMetrics, DecisionMetrics, DecisionMetricsSink, DecisionSample, OTelSpan, SpanKind, SpanStatus, StatusCode, TelemetryContext,
         TelemetryEvent, TelemetryEventType,
     },
     execution_graph::{
-        Artifact, ExecutionGraph, ExecutionGraphError, ExecutionSpan, ExecutionStatus,
-        SpanType, REPO_NAME,
+        instrument_agent, Artifact, ArtifactPolicy, ExecutionGraph, ExecutionGraphError, ExecutionSpan,
+        ExecutionStatus, SpanType, REPO_NAME,
     },
 };
@@ -5,5 +5,9 @@ pub mod migrations;
 pub use pool::{create_pool, PgPoolConfig};
 pub use repositories::{
     SessionRepository, ConversationRepository, MessageRepository, WorkflowRepository,
+    RepositoryQuota, WorkflowFilter, WorkflowPage, WorkflowSort, MAX_WORKFLOW_PAGE_SIZE,
+};
+pub use migrations::{
+    run_migrations, run_migration_batch, rollback_migrations, rollback_migration_batch,
+    get_migration_record, Migration, MigrationRecord, MigrationStatus,
 };
-pub use migrations::{run_migrations, rollback_migrations, Migration};
@@ -9,15 +9,20 @@ pub use database::{
     pool::{create_pool, PgPoolConfig},
     repositories::{
         SessionRepository, ConversationRepository, MessageRepository, WorkflowRepository,
+        RepositoryQuota, WorkflowFilter, WorkflowPage, WorkflowSort, MAX_WORKFLOW_PAGE_SIZE,
+    },
+    migrations::{
+        run_migrations, run_migration_batch, rollback_migrations, rollback_migration_batch,
+        get_migration_record, Migration,
+        MigrationRecord, MigrationStatus,
     },
-    migrations::{run_migrations, rollback_migrations, Migration},
 };
 
 pub use cache::redis::{RedisCache, RedisCacheConfig};
 pub use cache::memory::{MemoryCache, MemoryCacheConfig};
 pub use cache::response::{CachedResponse, ResponseCacheConfig, CacheKeyBuilder, CacheControl, ResponseCache};
 
-pub use messaging::nats::{NatsPublisher, NatsConfig, NatsSubscriber};
+pub use messaging::nats::{publish_execution_graph, NatsPublisher, NatsConfig, NatsSubscriber};
 
 pub use health::{
     DatabaseHealthCheck, RedisHealthCheck, NatsHealthCheck, CompositeHealthChecker, HealthStatus,
@@ -33,7 +38,7 @@ pub use resilience::{
 
 pub use metrics::{
     PrometheusMetrics, MetricsConfig, MetricsHandle, HttpMetrics, DatabaseMetrics,
-    CacheMetrics, CircuitBreakerMetrics, MetricsCollector, SystemMetrics,
+    CacheMetrics, CircuitBreakerMetrics, AgentDecisionMetrics, MetricsCollector, SystemMetrics,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -62,6 +67,16 @@ pub enum InfraError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("Quota exceeded for {quota}: requested {requested}, limit {limit}")]
+    QuotaExceeded {
+        quota: &'static str,
+        requested: i64,
+        limit: i64,
+    },
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
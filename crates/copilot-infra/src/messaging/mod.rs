@@ -1,3 +1,3 @@
 pub mod nats;
 
-pub use nats::{NatsPublisher, NatsConfig, NatsSubscriber};
+pub use nats::{publish_execution_graph, NatsPublisher, NatsConfig, NatsSubscriber};
@@ -6,6 +6,7 @@ use std::time::Duration;
 use tracing::{debug, info, warn, error};
 
 use copilot_core::events::{Event, EventPublisher};
+use copilot_core::ExecutionGraph;
 use crate::{InfraError, Result};
 
 #[derive(Debug, Clone)]
@@ -98,6 +99,11 @@ impl NatsPublisher {
         Ok(Self { client, config })
     }
 
+    /// Wrap an existing client, e.g. one shared with subscribers
+    pub fn with_client(client: Client, config: NatsConfig) -> Self {
+        Self { client, config }
+    }
+
     fn make_subject(&self, subject: &str) -> String {
         match &self.config.subject_prefix {
             Some(prefix) => format!("{}{}", prefix, subject),
@@ -188,6 +194,27 @@ impl EventPublisher for NatsPublisher {
     }
 }
 
+/// Publish a completed execution graph for downstream collectors
+///
+/// The graph is validated first; an invalid graph is never published.
+pub async fn publish_execution_graph(
+    publisher: &NatsPublisher,
+    subject: &str,
+    graph: &ExecutionGraph,
+) -> Result<()> {
+    graph.validate().map_err(|e| {
+        warn!("Not publishing execution graph {}: {}", graph.execution_id, e);
+        InfraError::Messaging(format!(
+            "Refusing to publish invalid execution graph {}: {}",
+            graph.execution_id, e
+        ))
+    })?;
+
+    debug!("Publishing execution graph {} with {} spans", graph.execution_id, graph.spans.len());
+    let payload = serde_json::to_vec(graph)?;
+    publisher.publish_raw(subject, payload).await
+}
+
 pub struct NatsSubscriber {
     subscriber: Subscriber,
     subject: String,
@@ -297,6 +324,27 @@ mod tests {
         assert_eq!(config.subject_prefix, Some("app.".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_invalid_execution_graph_is_not_published() {
+        // Nothing listens here; the client connects lazily and never does
+        let client = ConnectOptions::new()
+            .retry_on_initial_connect()
+            .connect("nats://127.0.0.1:1")
+            .await
+            .unwrap();
+        let publisher = NatsPublisher::with_client(client, NatsConfig::default());
+
+        let graph = ExecutionGraph::new("exec-1", "core-span", "trace-1").unwrap();
+        let result = publish_execution_graph(&publisher, "execution_graphs", &graph).await;
+        match result {
+            Err(InfraError::Messaging(message)) => {
+                assert!(message.contains("invalid execution graph exec-1"), "{}", message);
+                assert!(message.contains("agent spans"), "{}", message);
+            }
+            other => panic!("expected a validation error, got {:?}", other.err()),
+        }
+    }
+
     #[tokio::test]
     async fn test_make_subject_without_prefix() {
         let config = NatsConfig::new("nats://localhost")
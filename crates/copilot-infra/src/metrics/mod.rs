@@ -7,6 +7,6 @@ pub mod collector;
 
 pub use prometheus::{
     PrometheusMetrics, MetricsConfig, MetricsHandle, HttpMetrics, DatabaseMetrics,
-    CacheMetrics, CircuitBreakerMetrics,
+    CacheMetrics, CircuitBreakerMetrics, AgentDecisionMetrics, AgentDecisionSeries,
 };
 pub use collector::{MetricsCollector, SystemMetrics};
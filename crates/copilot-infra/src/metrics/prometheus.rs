@@ -2,9 +2,10 @@
 //!
 //! Provides metric types and registration for Prometheus monitoring.
 
-use std::collections::HashMap;
+use copilot_core::{DecisionMetricsSink, DecisionSample};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::debug;
@@ -228,6 +229,7 @@ pub struct MetricsHandle {
     database: DatabaseMetrics,
     cache: CacheMetrics,
     circuit_breaker: CircuitBreakerMetrics,
+    agents: Arc<AgentDecisionMetrics>,
 }
 
 impl MetricsHandle {
@@ -250,6 +252,14 @@ impl MetricsHandle {
     pub fn circuit_breaker(&self) -> &CircuitBreakerMetrics {
         &self.circuit_breaker
     }
+
+    /// Get agent decision metrics
+    ///
+    /// Clone the `Arc` into an agent's `with_metrics` to record its
+    /// decisions in this registry.
+    pub fn agents(&self) -> &Arc<AgentDecisionMetrics> {
+        &self.agents
+    }
 }
 
 /// HTTP request metrics
@@ -498,6 +508,203 @@ impl Default for CircuitBreakerMetrics {
     }
 }
 
+/// Metrics of one agent and decision type
+#[derive(Debug)]
+pub struct AgentDecisionSeries {
+    /// Decisions emitted
+    pub decisions: Counter,
+    /// Decision processing duration in seconds
+    pub duration: Histogram,
+    /// Decision confidence
+    pub confidence: Histogram,
+    /// Counters reported by the agent, by name
+    pub counters: BTreeMap<String, Counter>,
+}
+
+impl AgentDecisionSeries {
+    fn new(latency_buckets: Vec<f64>) -> Self {
+        Self {
+            decisions: Counter::new(),
+            duration: Histogram::new(latency_buckets),
+            confidence: Histogram::new(vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0]),
+            counters: BTreeMap::new(),
+        }
+    }
+}
+
+/// Agent decision metrics, labeled by agent ID and decision type
+///
+/// Records the DecisionEvents agents emit. Labels are limited to the agent
+/// ID and decision type to keep cardinality low.
+#[derive(Debug)]
+pub struct AgentDecisionMetrics {
+    latency_buckets: Vec<f64>,
+    series: std::sync::RwLock<BTreeMap<(String, String), AgentDecisionSeries>>,
+}
+
+impl AgentDecisionMetrics {
+    /// Create new agent decision metrics
+    pub fn new(config: &MetricsConfig) -> Self {
+        Self {
+            latency_buckets: config.latency_buckets.clone(),
+            series: std::sync::RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Number of decisions recorded for an agent and decision type
+    pub fn decisions(&self, agent_id: &str, decision_type: &str) -> u64 {
+        self.with_series(agent_id, decision_type, |series| series.decisions.get())
+            .unwrap_or(0)
+    }
+
+    /// Value of an agent-reported counter
+    pub fn counter(&self, agent_id: &str, decision_type: &str, name: &str) -> u64 {
+        self.with_series(agent_id, decision_type, |series| {
+            series.counters.get(name).map(Counter::get)
+        })
+        .flatten()
+        .unwrap_or(0)
+    }
+
+    /// Run a function on the series of an agent and decision type
+    pub fn with_series<T>(
+        &self,
+        agent_id: &str,
+        decision_type: &str,
+        f: impl FnOnce(&AgentDecisionSeries) -> T,
+    ) -> Option<T> {
+        let series = self.series.read().unwrap_or_else(PoisonError::into_inner);
+        series
+            .get(&(agent_id.to_string(), decision_type.to_string()))
+            .map(f)
+    }
+
+    fn render(&self, prefix: &str, output: &mut String) {
+        let series = self.series.read().unwrap_or_else(PoisonError::into_inner);
+        let labels = |(agent_id, decision_type): &(String, String)| {
+            format!(
+                "agent_id=\"{}\",decision_type=\"{}\"",
+                escape_label_value(agent_id),
+                escape_label_value(decision_type)
+            )
+        };
+
+        output.push_str(&format!(
+            "# HELP {}_agent_decisions_total Total agent decisions\n",
+            prefix
+        ));
+        output.push_str(&format!("# TYPE {}_agent_decisions_total counter\n", prefix));
+        for (key, s) in series.iter() {
+            output.push_str(&format!(
+                "{}_agent_decisions_total{{{}}} {}\n",
+                prefix,
+                labels(key),
+                s.decisions.get()
+            ));
+        }
+
+        for (name, help, histogram) in [
+            (
+                "agent_decision_duration_seconds",
+                "Agent decision duration in seconds",
+                (|s: &AgentDecisionSeries| &s.duration) as fn(&AgentDecisionSeries) -> &Histogram,
+            ),
+            (
+                "agent_decision_confidence",
+                "Agent decision confidence",
+                |s: &AgentDecisionSeries| &s.confidence,
+            ),
+        ] {
+            output.push_str(&format!("# HELP {}_{} {}\n", prefix, name, help));
+            output.push_str(&format!("# TYPE {}_{} histogram\n", prefix, name));
+            for (key, s) in series.iter() {
+                let histogram = histogram(s);
+                let labels = labels(key);
+                let mut cumulative = 0u64;
+                for (bucket, count) in histogram.get_buckets() {
+                    cumulative += count;
+                    output.push_str(&format!(
+                        "{}_{}_bucket{{{},le=\"{}\"}} {}\n",
+                        prefix, name, labels, bucket, cumulative
+                    ));
+                }
+                output.push_str(&format!(
+                    "{}_{}_bucket{{{},le=\"+Inf\"}} {}\n",
+                    prefix,
+                    name,
+                    labels,
+                    histogram.get_count()
+                ));
+                output.push_str(&format!(
+                    "{}_{}_sum{{{}}} {}\n",
+                    prefix,
+                    name,
+                    labels,
+                    histogram.get_sum()
+                ));
+                output.push_str(&format!(
+                    "{}_{}_count{{{}}} {}\n",
+                    prefix,
+                    name,
+                    labels,
+                    histogram.get_count()
+                ));
+            }
+        }
+
+        output.push_str(&format!(
+            "# HELP {}_agent_decision_counter_total Counters reported by agents\n",
+            prefix
+        ));
+        output.push_str(&format!(
+            "# TYPE {}_agent_decision_counter_total counter\n",
+            prefix
+        ));
+        for (key, s) in series.iter() {
+            for (name, counter) in &s.counters {
+                output.push_str(&format!(
+                    "{}_agent_decision_counter_total{{{},name=\"{}\"}} {}\n",
+                    prefix,
+                    labels(key),
+                    escape_label_value(name),
+                    counter.get()
+                ));
+            }
+        }
+    }
+}
+
+impl DecisionMetricsSink for AgentDecisionMetrics {
+    fn record_decision(&self, sample: &DecisionSample) {
+        let key = (sample.agent_id.clone(), sample.decision_type.to_string());
+        let mut series = self.series.write().unwrap_or_else(PoisonError::into_inner);
+        let series = series
+            .entry(key)
+            .or_insert_with(|| AgentDecisionSeries::new(self.latency_buckets.clone()));
+
+        series.decisions.inc();
+        if let Some(duration_ms) = sample.duration_ms {
+            series.duration.observe(duration_ms as f64 / 1000.0);
+        }
+        series.confidence.observe(sample.confidence as f64);
+        for (name, value) in &sample.counters {
+            series
+                .counters
+                .entry(name.clone())
+                .or_default()
+                .inc_by(*value);
+        }
+    }
+}
+
+/// Escape a label value for the Prometheus text format
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 /// Main Prometheus metrics registry
 pub struct PrometheusMetrics {
     config: MetricsConfig,
@@ -512,6 +719,7 @@ impl PrometheusMetrics {
             database: DatabaseMetrics::new(&config),
             cache: CacheMetrics::new(&config),
             circuit_breaker: CircuitBreakerMetrics::new(),
+            agents: Arc::new(AgentDecisionMetrics::new(&config)),
         };
 
         Self { config, handle }
@@ -690,6 +898,9 @@ impl PrometheusMetrics {
             self.handle.database.pool_connections.get()
         ));
 
+        // Agent decision metrics
+        self.handle.agents.render(prefix, &mut output);
+
         output
     }
 }
@@ -774,4 +985,66 @@ mod tests {
         assert!(output.contains("copilot_cache_hits_total 1"));
         assert!(output.contains("copilot_circuit_breaker_successes_total 1"));
     }
+
+    #[tokio::test]
+    async fn test_decomposer_records_agent_metrics() {
+        use copilot_core::{DecomposerAgent, DecomposerInput, Plan, DECOMPOSER_AGENT_ID};
+
+        let metrics = PrometheusMetrics::default_config();
+        let agent = DecomposerAgent::new().with_metrics(metrics.handle().agents().clone());
+        let input = DecomposerInput {
+            plan: Plan {
+                id: "plan-1".to_string(),
+                name: "Deploy".to_string(),
+                description: "Deploy the service".to_string(),
+                objectives: vec!["Build the image".to_string(), "Deploy to staging".to_string()],
+                constraints: Vec::new(),
+                metadata: HashMap::new(),
+            },
+            context: Default::default(),
+            execution_ref: None,
+        };
+
+        let event = agent.decompose(&input).unwrap();
+        agent.decompose(&input).unwrap();
+
+        let agents = metrics.handle().agents();
+        let task_count: u64 = event.telemetry.labels["task_count"].parse().unwrap();
+        assert_eq!(agents.decisions(DECOMPOSER_AGENT_ID, "task_decomposition"), 2);
+        assert_eq!(
+            agents.counter(DECOMPOSER_AGENT_ID, "task_decomposition", "task_count"),
+            2 * task_count
+        );
+        agents.with_series(DECOMPOSER_AGENT_ID, "task_decomposition", |series| {
+            assert_eq!(series.duration.get_count(), 2);
+            assert!((series.confidence.get_sum() - 2.0 * event.confidence as f64).abs() < 1e-6);
+        });
+
+        let output = metrics.render().await;
+        assert!(output.contains(
+            "copilot_agent_decisions_total{agent_id=\"decomposer-agent\",decision_type=\"task_decomposition\"} 2"
+        ));
+        assert!(!output.contains("plan-1"));
+    }
+
+    #[tokio::test]
+    async fn test_agent_label_values_are_escaped() {
+        use copilot_core::DecisionType;
+
+        let metrics = PrometheusMetrics::default_config();
+        metrics.handle().agents().record_decision(&DecisionSample {
+            agent_id: "team \"a\"\\ops\nx".to_string(),
+            decision_type: DecisionType::TaskDecomposition,
+            duration_ms: None,
+            confidence: 0.9,
+            counters: BTreeMap::from([("odd\"name".to_string(), 1)]),
+        });
+
+        let output = metrics.render().await;
+        assert!(output.contains(
+            r#"copilot_agent_decisions_total{agent_id="team \"a\"\\ops\nx",decision_type="task_decomposition"} 1"#
+        ));
+        assert!(output.contains(r#"name="odd\"name"} 1"#));
+        assert!(!output.contains("ops\nx"));
+    }
 }
@@ -0,0 +1,248 @@
+//! Action request extraction.
+//!
+//! Besides querying observability data, users ask the agent to act:
+//! "restart the auth-service pod", "roll back checkout to v41". This module
+//! recognizes such requests, classifies the kind of action, and extracts
+//! the verb and the target so the request can be routed to a workflow.
+//!
+//! Acting on a misread message is far more costly than answering the wrong
+//! question, so action requests carry their own confidence and must clear
+//! [`ACTION_CONFIDENCE_THRESHOLD`] to be routed. Only imperatives count:
+//! "why did auth-service restart?" is a question about a restart, not a
+//! request for one, and neither is "deploy frequency for checkout", where
+//! the verb qualifies a measurement. Hedged requests ("maybe restart it?") and requests
+//! without a clear target score below the threshold.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Confidence an action request needs to be routed without confirmation.
+pub const ACTION_CONFIDENCE_THRESHOLD: f64 = 0.9;
+
+/// Confidence of a bare imperative action verb.
+const BASE_CONFIDENCE: f64 = 0.7;
+/// Confidence added when the action has a target.
+const TARGET_BONUS: f64 = 0.25;
+/// Confidence removed when the request is hedged or phrased as a question.
+const HEDGE_PENALTY: f64 = 0.3;
+
+/// Kind of action a message requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    /// Restart a workload (e.g., "restart the auth-service pod")
+    Restart,
+    /// Change replica count or size (e.g., "scale checkout to 5")
+    Scale,
+    /// Revert to a previous version (e.g., "roll back payments")
+    Rollback,
+    /// Deploy a version (e.g., "deploy api-gateway v2")
+    Deploy,
+    /// Stop a workload (e.g., "stop the batch job")
+    Stop,
+    /// Start a workload (e.g., "start the worker")
+    Start,
+    /// Delete a resource (e.g., "delete pod web-1")
+    Delete,
+    /// Silence or acknowledge an alert (e.g., "silence HighLatency")
+    Silence,
+}
+
+impl ActionKind {
+    /// Returns a human-readable description of the action kind.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Restart => "Restart a workload",
+            Self::Scale => "Scale a workload",
+            Self::Rollback => "Roll back to a previous version",
+            Self::Deploy => "Deploy a version",
+            Self::Stop => "Stop a workload",
+            Self::Start => "Start a workload",
+            Self::Delete => "Delete a resource",
+            Self::Silence => "Silence or acknowledge an alert",
+        }
+    }
+
+    /// Whether the action can disrupt service or lose data.
+    pub fn is_disruptive(&self) -> bool {
+        !matches!(self, Self::Start | Self::Silence)
+    }
+
+    fn from_verb(verb: &str) -> Option<Self> {
+        let kind = match verb {
+            "restart" | "reboot" | "bounce" | "recycle" => Self::Restart,
+            "scale" | "resize" => Self::Scale,
+            "rollback" | "roll back" | "revert" => Self::Rollback,
+            "deploy" | "roll out" | "rollout" => Self::Deploy,
+            "stop" | "kill" | "terminate" | "shut down" | "shutdown" | "drain" => Self::Stop,
+            "start" | "launch" => Self::Start,
+            "delete" | "remove" | "purge" => Self::Delete,
+            "silence" | "mute" | "acknowledge" | "ack" | "snooze" => Self::Silence,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// An action extracted from a message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionRequest {
+    /// Kind of action
+    pub kind: ActionKind,
+    /// The verb as written, lowercased (e.g., "bounce")
+    pub verb: String,
+    /// What the action applies to (e.g., "auth-service")
+    pub target: Option<String>,
+    /// Kind of resource the target was called, if any (e.g., "pod")
+    pub resource: Option<String>,
+}
+
+lazy_static! {
+    /// An action verb at the start of the message, optionally after a
+    /// politeness or hedging prefix.
+    static ref ACTION_REGEX: Regex = Regex::new(
+        r"(?i)^\s*(?P<prefix>(?:(?:please|kindly|maybe|perhaps|can\s+you|could\s+you|would\s+you|should\s+(?:we|i|you)|let's|lets|go\s+ahead\s+and|just)\s+)*)(?P<verb>roll\s+back|roll\s+out|shut\s+down|rollback|rollout|shutdown|restart|reboot|bounce|recycle|scale|resize|revert|deploy|stop|kill|terminate|drain|start|launch|delete|remove|purge|silence|mute|acknowledge|ack|snooze)\b(?P<rest>.*)$"
+    ).unwrap();
+
+    /// Measurements of an action, which turn a leading action verb into
+    /// a noun ("deploy frequency", "restart count").
+    static ref MEASUREMENT_REGEX: Regex = Regex::new(
+        r"(?i)^\s*(?:frequency|frequencies|rates?|counts?|times?|durations?|history|success|failures?|errors?|events?|latency|stats|statistics|trends?|metrics?)\b"
+    ).unwrap();
+
+    /// Prefix words that make a request tentative.
+    static ref HEDGE_REGEX: Regex =
+        Regex::new(r"(?i)\b(maybe|perhaps|should|might|could)\b").unwrap();
+}
+
+/// Words skipped before a target.
+const DETERMINERS: &[&str] = &["the", "a", "an", "my", "our", "this", "that", "all", "every"];
+
+/// Words that end a target phrase.
+const TARGET_TERMINATORS: &[&str] = &[
+    "to", "in", "on", "for", "by", "from", "at", "with", "and", "then", "now", "please", "back",
+    "over", "again",
+];
+
+/// Resource kinds a target may be called.
+const RESOURCES: &[&str] = &[
+    "pod", "pods", "service", "services", "deployment", "deployments", "container",
+    "containers", "node", "nodes", "cluster", "job", "jobs", "worker", "workers", "alert",
+    "alerts", "instance", "instances", "replica", "replicas", "statefulset", "daemonset",
+];
+
+/// Extracts an action request from a message, with its confidence.
+///
+/// Returns `None` unless the message starts with an action verb used as
+/// an imperative, so questions that merely mention an action are not
+/// mistaken for requests.
+pub fn extract_action(query: &str) -> Option<(ActionRequest, f64)> {
+    let captures = ACTION_REGEX.captures(query)?;
+    if MEASUREMENT_REGEX.is_match(&captures["rest"]) {
+        return None;
+    }
+    let verb = captures["verb"]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let kind = ActionKind::from_verb(&verb)?;
+    let (target, resource) = extract_target(&captures["rest"]);
+
+    let hedged = HEDGE_REGEX.is_match(&captures["prefix"]) || query.trim_end().ends_with('?');
+    let mut confidence = BASE_CONFIDENCE;
+    if target.is_some() {
+        confidence += TARGET_BONUS;
+    }
+    if hedged {
+        confidence -= HEDGE_PENALTY;
+    }
+
+    let action = ActionRequest {
+        kind,
+        verb,
+        target,
+        resource,
+    };
+    Some((action, confidence.clamp(0.0, 1.0)))
+}
+
+/// Splits the words after the verb into a target and a resource kind.
+///
+/// "the auth-service pod in prod" gives target "auth-service" and resource
+/// "pod".
+fn extract_target(rest: &str) -> (Option<String>, Option<String>) {
+    let words: Vec<String> = rest
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| matches!(c, ',' | '.' | '!' | '?' | '"' | '\'' | '`')))
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let mut target = Vec::new();
+    let mut resource = None;
+    for word in words
+        .iter()
+        .skip_while(|w| DETERMINERS.contains(&w.to_lowercase().as_str()))
+    {
+        let lower = word.to_lowercase();
+        if TARGET_TERMINATORS.contains(&lower.as_str()) {
+            break;
+        }
+        if RESOURCES.contains(&lower.as_str()) {
+            resource.get_or_insert(lower);
+            continue;
+        }
+        // Pronouns leave the target to be resolved from the conversation
+        if matches!(lower.as_str(), "it" | "them" | "this" | "that") {
+            break;
+        }
+        target.push(word.as_str());
+    }
+
+    let target = (!target.is_empty()).then(|| target.join(" "));
+    (target, resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_verb_target_and_resource() {
+        let (action, confidence) = extract_action("Restart the auth-service pod").unwrap();
+        assert_eq!(action.kind, ActionKind::Restart);
+        assert_eq!(action.verb, "restart");
+        assert_eq!(action.target.as_deref(), Some("auth-service"));
+        assert_eq!(action.resource.as_deref(), Some("pod"));
+        assert!(confidence >= ACTION_CONFIDENCE_THRESHOLD);
+
+        let (action, _) = extract_action("please roll back checkout to v41").unwrap();
+        assert_eq!(action.kind, ActionKind::Rollback);
+        assert_eq!(action.verb, "roll back");
+        assert_eq!(action.target.as_deref(), Some("checkout"));
+    }
+
+    #[test]
+    fn test_questions_and_hedges_score_low() {
+        assert!(extract_action("Why did auth-service restart?").is_none());
+        assert!(extract_action("Deploy frequency for checkout last week").is_none());
+        assert!(extract_action("restart count of the auth-service pod").is_none());
+
+        let (_, hedged) = extract_action("maybe restart the auth-service?").unwrap();
+        assert!(hedged < ACTION_CONFIDENCE_THRESHOLD);
+
+        // No clear target: the pronoun must be resolved first
+        let (action, untargeted) = extract_action("restart it").unwrap();
+        assert_eq!(action.target, None);
+        assert!(untargeted < ACTION_CONFIDENCE_THRESHOLD);
+    }
+
+    #[test]
+    fn test_disruptive_kinds() {
+        assert!(ActionKind::Delete.is_disruptive());
+        assert!(ActionKind::Restart.is_disruptive());
+        assert!(!ActionKind::Silence.is_disruptive());
+    }
+}
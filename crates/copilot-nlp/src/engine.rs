@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use crate::error::{NlpError, Result};
 use tracing::{debug, info, instrument};
 
-use crate::entity::{Entity, EntityExtractor};
+use crate::entity::{sort_entities, Entity, EntityExtractor};
 use crate::intent::{Intent, IntentClassifier};
 use crate::query::{QueryLanguage, QueryTranslator};
 use crate::{NlpContext, NlpEngine};
@@ -25,6 +25,8 @@ pub struct NlpEngineImpl {
     query_translator: QueryTranslator,
     /// Optional context for improved accuracy
     context: Option<NlpContext>,
+    /// Whether output must be identical for identical input
+    deterministic: bool,
 }
 
 impl NlpEngineImpl {
@@ -36,6 +38,7 @@ impl NlpEngineImpl {
             entity_extractor: EntityExtractor::new(),
             query_translator: QueryTranslator::new(),
             context: None,
+            deterministic: false,
         }
     }
 
@@ -57,9 +60,33 @@ impl NlpEngineImpl {
             entity_extractor,
             query_translator: QueryTranslator::new(),
             context: Some(context),
+            deterministic: false,
         }
     }
 
+    /// Replaces the query translator, e.g. to restrict the available
+    /// query languages.
+    pub fn with_query_translator(mut self, query_translator: QueryTranslator) -> Self {
+        self.query_translator = query_translator;
+        self
+    }
+
+    /// Enables deterministic mode.
+    ///
+    /// In deterministic mode entities are returned ordered by span, then
+    /// type, and translation orders its input entities the same way, so
+    /// identical input always produces identical, byte-stable output.
+    /// Useful for caching results and for snapshot tests.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Whether the engine runs in deterministic mode.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
     /// Updates the context for the NLP engine.
     ///
     /// # Arguments
@@ -185,7 +212,10 @@ impl NlpEngine for NlpEngineImpl {
         let processed_query = self.preprocess_query(query);
 
         // Extract entities
-        let entities = self.entity_extractor.extract(&processed_query);
+        let mut entities = self.entity_extractor.extract(&processed_query);
+        if self.deterministic {
+            sort_entities(&mut entities);
+        }
 
         info!("Extracted {} entities", entities.len());
 
@@ -220,16 +250,16 @@ impl NlpEngine for NlpEngineImpl {
         // Validate input
         self.validate_query(query)?;
 
-        // Translate based on target language
-        let translated_query = match target_language {
-            QueryLanguage::PromQL => self.query_translator.to_promql(intent, entities),
-            QueryLanguage::LogQL => self.query_translator.to_logql(intent, entities),
-            QueryLanguage::SQL => self.query_translator.to_sql(intent, entities),
-            QueryLanguage::TraceQL => {
-                // TraceQL not yet implemented, return a placeholder
-                debug!("TraceQL translation not yet implemented");
-                return Err(NlpError::unsupported("TraceQL translation not yet implemented"));
-            }
+        // Translate based on target language, from a canonical entity order
+        // in deterministic mode
+        let translated_query = if self.deterministic {
+            let mut entities = entities.to_vec();
+            sort_entities(&mut entities);
+            self.query_translator
+                .translate_to(target_language, intent, &entities)?
+        } else {
+            self.query_translator
+                .translate_to(target_language, intent, entities)?
         };
 
         info!(
@@ -311,6 +341,20 @@ mod tests {
         assert!(!query.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_translate_query_disabled_language() {
+        let engine = NlpEngineImpl::new().with_query_translator(
+            QueryTranslator::new().with_available_languages([QueryLanguage::PromQL]),
+        );
+        let intent = engine.classify_intent("Find errors in auth-service").await.unwrap();
+
+        let result = engine
+            .translate_query("Find errors in auth-service", &intent, &[], QueryLanguage::LogQL)
+            .await;
+
+        assert!(matches!(result, Err(NlpError::FeatureNotEnabled(_))));
+    }
+
     #[tokio::test]
     async fn test_engine_with_context() {
         let context = NlpContext {
@@ -380,4 +424,42 @@ mod tests {
         engine.update_context(context);
         assert!(engine.context().is_some());
     }
+
+    #[tokio::test]
+    async fn test_deterministic_pipeline_is_reproducible() {
+        let query = "Show error rate and cpu usage for checkout-service and api-gateway in production over the last hour";
+
+        let run = |reverse: bool| async move {
+            let engine = NlpEngineImpl::new().with_deterministic(true);
+            let intent = engine.classify_intent(query).await.unwrap();
+            let mut entities = engine.extract_entities(query).await.unwrap();
+            let extracted = entities.clone();
+            if reverse {
+                entities.reverse();
+            }
+            let translated = engine
+                .translate_query(query, &intent, &entities, QueryLanguage::PromQL)
+                .await
+                .unwrap();
+            (
+                serde_json::to_string(&intent).unwrap(),
+                serde_json::to_string(&extracted).unwrap(),
+                translated,
+                extracted,
+            )
+        };
+
+        let first = run(false).await;
+        let second = run(true).await;
+        assert_eq!(first.0, second.0);
+        assert_eq!(first.1, second.1);
+        assert_eq!(first.2, second.2);
+
+        // Entities are ordered by span
+        let spans: Vec<_> = first.3.iter().map(|e| e.span.unwrap()).collect();
+        let mut sorted = spans.clone();
+        sorted.sort();
+        assert!(spans.len() > 2);
+        assert_eq!(spans, sorted);
+    }
 }
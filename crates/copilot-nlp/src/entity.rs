@@ -8,8 +8,35 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// An individual extractor for one family of entities.
+type ExtractorFn = fn(&EntityExtractor, &str) -> Vec<Entity>;
+
+/// Extractors run by [`EntityExtractor::extract`], in output order.
+const EXTRACTORS: [ExtractorFn; 10] = [
+    EntityExtractor::extract_time_ranges,
+    EntityExtractor::extract_metrics,
+    EntityExtractor::extract_severity,
+    EntityExtractor::extract_services,
+    EntityExtractor::extract_http_status,
+    EntityExtractor::extract_endpoints,
+    EntityExtractor::extract_thresholds,
+    EntityExtractor::extract_aggregations,
+    EntityExtractor::extract_environments,
+    EntityExtractor::extract_kubernetes,
+];
+
+/// Default query length, in bytes, from which extractors run in parallel.
+#[cfg(feature = "parallel")]
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 2048;
+
 /// Types of entities that can be extracted from queries.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Ordered by declaration, which breaks ties between entities at the same
+/// position in [`sort_entities`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum EntityType {
     /// Time range (e.g., "last 5 minutes", "past hour")
     TimeRange,
@@ -33,6 +60,12 @@ pub enum EntityType {
     Threshold,
     /// Aggregation function (e.g., "avg", "sum", "max")
     Aggregation,
+    /// Kubernetes pod (e.g., "auth-7d8f9-xk2")
+    Pod,
+    /// Kubernetes deployment
+    Deployment,
+    /// Container within a pod
+    Container,
 }
 
 impl EntityType {
@@ -50,12 +83,15 @@ impl EntityType {
             Self::Host => "Host or instance identifier",
             Self::Threshold => "Threshold or limit value",
             Self::Aggregation => "Aggregation function",
+            Self::Pod => "Kubernetes pod name",
+            Self::Deployment => "Kubernetes deployment name",
+            Self::Container => "Container name",
         }
     }
 }
 
 /// Represents an extracted entity with type, value, and position.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Entity {
     /// Type of the entity
     pub entity_type: EntityType,
@@ -67,6 +103,10 @@ pub struct Entity {
     pub original_text: String,
     /// Confidence score (0.0 to 1.0)
     pub confidence: f64,
+    /// Byte range (start, end) of the match in the query, if known, e.g.
+    /// for highlighting it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<(usize, usize)>,
 }
 
 impl Entity {
@@ -84,15 +124,102 @@ impl Entity {
             normalized_value,
             original_text,
             confidence,
+            span: None,
         }
     }
 
+    /// Sets the byte range of the match in the query.
+    pub fn with_span(mut self, start: usize, end: usize) -> Self {
+        self.span = Some((start, end));
+        self
+    }
+
     /// Returns true if the confidence is above the threshold (0.7).
     pub fn is_confident(&self) -> bool {
         self.confidence >= 0.7
     }
 }
 
+/// Sorts entities into a stable order: by position in the query, then by
+/// type, then by value.
+///
+/// Entities without a span sort last. The order depends only on the
+/// entities themselves, so the same query always yields the same list.
+pub fn sort_entities(entities: &mut [Entity]) {
+    entities.sort_by(|a, b| {
+        a.span
+            .is_none()
+            .cmp(&b.span.is_none())
+            .then_with(|| a.span.cmp(&b.span))
+            .then_with(|| a.entity_type.cmp(&b.entity_type))
+            .then_with(|| a.normalized_value.cmp(&b.normalized_value))
+            .then_with(|| a.value.cmp(&b.value))
+            .then_with(|| b.confidence.total_cmp(&a.confidence))
+    });
+}
+
+/// Drops entities whose match overlaps another match of the same type.
+///
+/// Several patterns can match the same text, or part of a longer match,
+/// e.g. a known metric and the built-in pattern for it. An entity is
+/// dropped when its span is inside the span of a same-typed entity with a
+/// higher confidence, or when it repeats another entity's span exactly.
+/// Of two same-typed matches that partially overlap, the wider one is
+/// kept, or the more confident one if both are as wide. Matches are
+/// visited by start offset, then by descending confidence and width, so
+/// the widest of equally confident matches is the one kept. Kept entities
+/// stay in their original order; entities without a span are always kept.
+fn drop_overlapping(entities: &mut Vec<Entity>) {
+    let mut order: Vec<(usize, (usize, usize))> = entities
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| e.span.map(|span| (i, span)))
+        .collect();
+    order.sort_by(|(a, (a_start, a_end)), (b, (b_start, b_end))| {
+        a_start
+            .cmp(b_start)
+            .then_with(|| entities[*b].confidence.total_cmp(&entities[*a].confidence))
+            .then_with(|| b_end.cmp(a_end))
+    });
+
+    let mut dropped = vec![false; entities.len()];
+    for (n, &(outer, (start, end))) in order.iter().enumerate() {
+        if dropped[outer] {
+            continue;
+        }
+        for &(inner, (inner_start, inner_end)) in &order[n + 1..] {
+            if inner_start >= end {
+                break;
+            }
+            let (kept, candidate) = (&entities[outer], &entities[inner]);
+            if dropped[inner] || candidate.entity_type != kept.entity_type {
+                continue;
+            }
+            if inner_end <= end {
+                let duplicate = (inner_start, inner_end) == (start, end);
+                if duplicate || kept.confidence > candidate.confidence {
+                    dropped[inner] = true;
+                }
+                continue;
+            }
+
+            // Partial overlap: the outer match starts first, the inner one
+            // ends last
+            let (outer_width, inner_width) = (end - start, inner_end - inner_start);
+            let inner_wins = inner_width > outer_width
+                || (inner_width == outer_width && candidate.confidence > kept.confidence);
+            if inner_wins {
+                dropped[outer] = true;
+                break;
+            }
+            dropped[inner] = true;
+        }
+    }
+
+    let mut dropped = dropped.into_iter();
+    entities.retain(|_| !dropped.next().unwrap_or(false));
+}
+
 lazy_static! {
     /// Time range patterns
     static ref TIME_PATTERNS: Vec<(Regex, fn(&str) -> Option<String>)> = vec![
@@ -209,6 +336,59 @@ lazy_static! {
         (Regex::new(r"(?i)\b(development|dev)\b").unwrap(), "development"),
         (Regex::new(r"(?i)\b(test|testing)\b").unwrap(), "test"),
     ];
+
+    /// Pods generated by a deployment: `<name>-<template hash>-<suffix>`
+    static ref POD_NAME_PATTERN: Regex =
+        Regex::new(r"(?i)\b([a-z][a-z0-9-]*?)-([a-z0-9]{5,10})-([a-z0-9]{3,5})\b").unwrap();
+
+    /// Kubernetes objects named next to their kind, e.g. "pod auth-0",
+    /// "the auth-api deployment" or "deploy/auth". Each form is a separate
+    /// pattern so that a rejected match of one cannot hide another. The
+    /// flag marks the explicit `kind/name` form.
+    static ref KUBERNETES_PATTERNS: Vec<(Regex, EntityType, bool)> = [
+        (r"pods?", EntityType::Pod),
+        (r"deployments?|deploy", EntityType::Deployment),
+        (r"containers?", EntityType::Container),
+    ]
+    .into_iter()
+    .flat_map(|(kind, entity_type)| {
+        let name = r"[a-z0-9](?:[a-z0-9-]*[a-z0-9])?";
+        [
+            (format!(r"(?i)\b(?:{})/({})\b", kind, name), true),
+            (format!(r"(?i)\b(?:{})\s+({})\b", kind, name), false),
+            (format!(r"(?i)\b({})\s+(?:{})(?:[^\w/-]|$)", name, kind), false),
+        ]
+        .map(|(pattern, explicit)| (Regex::new(&pattern).unwrap(), entity_type.clone(), explicit))
+    })
+    .collect();
+}
+
+/// Words that precede a Kubernetes kind without naming an object
+const KUBERNETES_STOPWORDS: &[&str] = &[
+    "a", "all", "an", "any", "each", "every", "its", "my", "new", "no", "of", "our", "the",
+    "that", "their", "this", "which", "restart", "restarted", "restarting", "delete", "kill",
+    "crashing", "failing", "scale", "describe", "show", "list", "logs", "for", "in", "on",
+];
+
+/// Whether a word next to a Kubernetes kind looks like an object name
+///
+/// Object names are lowercase, and a name written without `kind/` must
+/// also contain a hyphen or a digit, which sets it apart from ordinary
+/// words such as "pods are" or "deploy to".
+fn is_kubernetes_name(word: &str) -> bool {
+    !KUBERNETES_STOPWORDS.contains(&word)
+        && !word.chars().any(|c| c.is_ascii_uppercase())
+        && word.chars().any(|c| c == '-' || c.is_ascii_digit())
+}
+
+/// Confidence of entities matched by a registered pattern.
+const CUSTOM_PATTERN_CONFIDENCE: f64 = 0.8;
+
+/// A pattern registered with [`EntityExtractor::register_pattern`].
+struct CustomPattern {
+    entity_type: EntityType,
+    regex: Regex,
+    normalizer: Option<fn(&str) -> String>,
 }
 
 /// Entity extractor that identifies and extracts entities from text.
@@ -217,6 +397,11 @@ pub struct EntityExtractor {
     known_services: Vec<String>,
     /// Custom metric names
     known_metrics: Vec<String>,
+    /// Patterns registered at runtime, run after the built-in ones
+    custom_patterns: Vec<CustomPattern>,
+    /// Query length from which extractors run in parallel
+    #[cfg(feature = "parallel")]
+    parallel_threshold: usize,
 }
 
 impl EntityExtractor {
@@ -225,6 +410,9 @@ impl EntityExtractor {
         Self {
             known_services: Vec::new(),
             known_metrics: Vec::new(),
+            custom_patterns: Vec::new(),
+            #[cfg(feature = "parallel")]
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
         }
     }
 
@@ -233,6 +421,9 @@ impl EntityExtractor {
         Self {
             known_services,
             known_metrics,
+            custom_patterns: Vec::new(),
+            #[cfg(feature = "parallel")]
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
         }
     }
 
@@ -244,41 +435,102 @@ impl EntityExtractor {
     ///
     /// # Returns
     ///
-    /// A vector of extracted entities
+    /// A vector of extracted entities. A match within a more confident
+    /// match of the same type, or repeating one, is dropped, and of two
+    /// partially overlapping matches of the same type the wider is kept.
     pub fn extract(&self, query: &str) -> Vec<Entity> {
         trace!("Extracting entities from query: {}", query);
 
-        let mut entities = Vec::new();
-
-        // Extract time ranges
-        entities.extend(self.extract_time_ranges(query));
-
-        // Extract metrics
-        entities.extend(self.extract_metrics(query));
-
-        // Extract severity levels
-        entities.extend(self.extract_severity(query));
+        #[cfg(feature = "parallel")]
+        let mut entities = if query.len() >= self.parallel_threshold {
+            self.extract_parallel(query)
+        } else {
+            self.extract_sequential(query)
+        };
+        #[cfg(not(feature = "parallel"))]
+        let mut entities = self.extract_sequential(query);
 
-        // Extract services
-        entities.extend(self.extract_services(query));
+        self.extract_custom(query, &mut entities);
+        drop_overlapping(&mut entities);
 
-        // Extract HTTP status codes
-        entities.extend(self.extract_http_status(query));
+        debug!("Extracted {} entities", entities.len());
+        entities
+    }
 
-        // Extract endpoints
-        entities.extend(self.extract_endpoints(query));
+    /// Registers a pattern that recognizes entities of a type.
+    ///
+    /// The first capture group of each match is the entity's text, or the
+    /// whole match if the pattern has no groups. `normalizer` maps that
+    /// text to the normalized value; without one the text is used as is.
+    /// Matches get a confidence of 0.8, and are dropped when a built-in or
+    /// earlier pattern already extracted the same text as the same type.
+    pub fn register_pattern(
+        &mut self,
+        entity_type: EntityType,
+        regex: Regex,
+        normalizer: Option<fn(&str) -> String>,
+    ) {
+        self.custom_patterns.push(CustomPattern {
+            entity_type,
+            regex,
+            normalizer,
+        });
+    }
 
-        // Extract thresholds
-        entities.extend(self.extract_thresholds(query));
+    /// Sets the query length, in bytes, from which extractors run in parallel.
+    ///
+    /// Short queries are faster to extract sequentially than to fan out.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = threshold;
+        self
+    }
 
-        // Extract aggregations
-        entities.extend(self.extract_aggregations(query));
+    /// Runs each extractor in turn.
+    fn extract_sequential(&self, query: &str) -> Vec<Entity> {
+        EXTRACTORS.iter().flat_map(|extract| extract(self, query)).collect()
+    }
 
-        // Extract environments
-        entities.extend(self.extract_environments(query));
+    /// Runs the extractors concurrently, merging results in extractor order.
+    #[cfg(feature = "parallel")]
+    fn extract_parallel(&self, query: &str) -> Vec<Entity> {
+        let results: Vec<Vec<Entity>> = EXTRACTORS
+            .par_iter()
+            .map(|extract| extract(self, query))
+            .collect();
+        results.into_iter().flatten().collect()
+    }
 
-        debug!("Extracted {} entities", entities.len());
-        entities
+    /// Appends the matches of registered patterns not already extracted.
+    fn extract_custom(&self, query: &str, entities: &mut Vec<Entity>) {
+        for pattern in &self.custom_patterns {
+            for captures in pattern.regex.captures_iter(query) {
+                let Some(mat) = captures.get(1).or_else(|| captures.get(0)) else {
+                    continue;
+                };
+                let text = mat.as_str();
+                if text.is_empty()
+                    || entities
+                        .iter()
+                        .any(|e| e.entity_type == pattern.entity_type && e.original_text == text)
+                {
+                    continue;
+                }
+                let normalized = pattern
+                    .normalizer
+                    .map_or_else(|| text.to_string(), |normalize| normalize(text));
+                entities.push(
+                    Entity::new(
+                        pattern.entity_type.clone(),
+                        text.to_string(),
+                        normalized,
+                        text.to_string(),
+                        CUSTOM_PATTERN_CONFIDENCE,
+                    )
+                    .with_span(mat.start(), mat.end()),
+                );
+            }
+        }
     }
 
     /// Extracts time range entities.
@@ -294,7 +546,7 @@ impl EntityExtractor {
                         normalized,
                         mat.as_str().to_string(),
                         0.95,
-                    ));
+                    ).with_span(mat.start(), mat.end()));
                 }
             }
         }
@@ -307,15 +559,16 @@ impl EntityExtractor {
         let mut entities = Vec::new();
 
         // Check known metrics first
+        let lower = query.to_lowercase();
         for metric in &self.known_metrics {
-            if query.to_lowercase().contains(&metric.to_lowercase()) {
+            if let Some(start) = lower.find(&metric.to_lowercase()) {
                 entities.push(Entity::new(
                     EntityType::Metric,
                     metric.clone(),
                     metric.clone(),
                     metric.clone(),
                     0.9,
-                ));
+                ).with_span(start, start + metric.len()));
             }
         }
 
@@ -328,7 +581,7 @@ impl EntityExtractor {
                     normalized.to_string(),
                     mat.as_str().to_string(),
                     0.85,
-                ));
+                ).with_span(mat.start(), mat.end()));
             }
         }
 
@@ -347,7 +600,7 @@ impl EntityExtractor {
                     normalized.to_string(),
                     mat.as_str().to_string(),
                     0.9,
-                ));
+                ).with_span(mat.start(), mat.end()));
             }
         }
 
@@ -359,15 +612,16 @@ impl EntityExtractor {
         let mut entities = Vec::new();
 
         // Check known services first
+        let lower = query.to_lowercase();
         for service in &self.known_services {
-            if query.to_lowercase().contains(&service.to_lowercase()) {
+            if let Some(start) = lower.find(&service.to_lowercase()) {
                 entities.push(Entity::new(
                     EntityType::Service,
                     service.clone(),
                     service.clone(),
                     service.clone(),
                     0.95,
-                ));
+                ).with_span(start, start + service.len()));
             }
         }
 
@@ -379,7 +633,7 @@ impl EntityExtractor {
                 mat.as_str().to_lowercase(),
                 mat.as_str().to_string(),
                 0.8,
-            ));
+            ).with_span(mat.start(), mat.end()));
         }
 
         entities
@@ -396,7 +650,7 @@ impl EntityExtractor {
                 mat.as_str().to_string(),
                 mat.as_str().to_string(),
                 0.9,
-            ));
+            ).with_span(mat.start(), mat.end()));
         }
 
         entities
@@ -413,7 +667,7 @@ impl EntityExtractor {
                 mat.as_str().to_string(),
                 mat.as_str().to_string(),
                 0.85,
-            ));
+            ).with_span(mat.start(), mat.end()));
         }
 
         entities
@@ -431,7 +685,7 @@ impl EntityExtractor {
                     mat.as_str().trim().to_string(),
                     mat.as_str().to_string(),
                     0.85,
-                ));
+                ).with_span(mat.start(), mat.end()));
             }
         }
 
@@ -450,7 +704,7 @@ impl EntityExtractor {
                     normalized.to_string(),
                     mat.as_str().to_string(),
                     0.9,
-                ));
+                ).with_span(mat.start(), mat.end()));
             }
         }
 
@@ -469,12 +723,72 @@ impl EntityExtractor {
                     normalized.to_string(),
                     mat.as_str().to_string(),
                     0.9,
-                ));
+                ).with_span(mat.start(), mat.end()));
             }
         }
 
         entities
     }
+    /// Extracts Kubernetes pod, deployment and container entities.
+    ///
+    /// A name counts when it is written next to its kind ("pod auth-0",
+    /// "the auth deployment", "deploy/auth"). A pod is also recognized on
+    /// its own by the name a deployment gives it, `<name>-<hash>-<suffix>`,
+    /// where the hash contains a digit; plain hyphenated names such as
+    /// "auth-service" or "blue-green-rollout" are not pods.
+    fn extract_kubernetes(&self, query: &str) -> Vec<Entity> {
+        let mut found: Vec<(usize, Entity)> = Vec::new();
+        let mut push = |start: usize, entity: Entity| {
+            if !found.iter().any(|(_, e)| {
+                e.entity_type == entity.entity_type && e.normalized_value == entity.normalized_value
+            }) {
+                found.push((start, entity));
+            }
+        };
+
+        for (pattern, entity_type, explicit) in KUBERNETES_PATTERNS.iter() {
+            for caps in pattern.captures_iter(query) {
+                let name = caps.get(1).expect("pattern has one group");
+                if !explicit && !is_kubernetes_name(name.as_str()) {
+                    continue;
+                }
+                let normalized = name.as_str().to_lowercase();
+                push(
+                    name.start(),
+                    Entity::new(
+                        entity_type.clone(),
+                        name.as_str().to_string(),
+                        normalized,
+                        caps[0].trim_end_matches(|c: char| !c.is_alphanumeric()).to_string(),
+                        0.9,
+                    )
+                    .with_span(name.start(), name.end()),
+                );
+            }
+        }
+
+        for caps in POD_NAME_PATTERN.captures_iter(query) {
+            if !caps[2].chars().any(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let name = caps.get(0).expect("group 0 is the match");
+            push(
+                name.start(),
+                Entity::new(
+                    EntityType::Pod,
+                    name.as_str().to_string(),
+                    name.as_str().to_lowercase(),
+                    name.as_str().to_string(),
+                    0.8,
+                )
+                .with_span(name.start(), name.end()),
+            );
+        }
+
+        // In the order they appear in the query
+        found.sort_by_key(|(start, _)| *start);
+        found.into_iter().map(|(_, entity)| entity).collect()
+    }
 }
 
 impl Default for EntityExtractor {
@@ -579,4 +893,209 @@ mod tests {
         assert!(entities.iter().any(|e| e.entity_type == EntityType::Service));
         assert!(entities.iter().any(|e| e.entity_type == EntityType::Metric));
     }
+
+    fn values(entities: &[Entity], entity_type: EntityType) -> Vec<&str> {
+        entities
+            .iter()
+            .filter(|e| e.entity_type == entity_type)
+            .map(|e| e.normalized_value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_kubernetes_pod() {
+        let extractor = EntityExtractor::new();
+
+        let entities = extractor.extract("restart the auth-7d8f9-xk2 pod");
+        assert_eq!(values(&entities, EntityType::Pod), ["auth-7d8f9-xk2"]);
+
+        // Recognized by its generated name alone
+        let entities = extractor.extract("why is Checkout-5F6b7c8d9-ABCDE crashing?");
+        assert_eq!(values(&entities, EntityType::Pod), ["checkout-5f6b7c8d9-abcde"]);
+
+        let entities = extractor.extract("show logs for container/envoy in pod payments-0");
+        assert_eq!(values(&entities, EntityType::Pod), ["payments-0"]);
+        assert_eq!(values(&entities, EntityType::Container), ["envoy"]);
+
+        let entities = extractor.extract("scale the auth-api deployment, then check deploy/billing");
+        assert_eq!(values(&entities, EntityType::Deployment), ["auth-api", "billing"]);
+    }
+
+    #[test]
+    fn test_plain_names_are_not_pods() {
+        let extractor = EntityExtractor::new();
+        for query in [
+            "Show errors from auth-service",
+            "roll out the blue-green-canary strategy",
+            "latency in us-east-1 since 2024-01-15",
+            "restart the pod",
+            "how many pods are crashlooping",
+        ] {
+            let entities = extractor.extract(query);
+            assert!(values(&entities, EntityType::Pod).is_empty(), "{}", query);
+        }
+        let entities = extractor.extract("deploy to production");
+        assert!(values(&entities, EntityType::Deployment).is_empty());
+        let entities = extractor.extract("Show errors from auth-service");
+        assert_eq!(values(&entities, EntityType::Service), ["auth-service"]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_extraction_matches_sequential() {
+        let paragraph = "At 14:02 the payment-service in production started returning 503 errors \
+            on /api/v1/checkout. CPU usage went above 90% and p99 latency spiked over the last \
+            15 minutes. Critical alerts fired for auth-service in staging; average memory \
+            climbed past 2GB. ";
+        let query = paragraph.repeat(40);
+        let extractor = EntityExtractor::new();
+
+        let sequential = extractor.extract_sequential(&query);
+        let parallel = extractor.extract_parallel(&query);
+        assert!(!sequential.is_empty());
+        assert_eq!(sequential, parallel);
+
+        // Above the threshold `extract` goes parallel, below it stays sequential
+        assert!(query.len() >= DEFAULT_PARALLEL_THRESHOLD);
+        assert_eq!(extractor.extract(&query), sequential);
+        let eager = EntityExtractor::new().with_parallel_threshold(0);
+        assert_eq!(eager.extract(paragraph), extractor.extract_sequential(paragraph));
+    }
+
+    fn lowercase(value: &str) -> String {
+        value.to_lowercase()
+    }
+
+    #[test]
+    fn test_registered_patterns_extract_alongside_built_ins() {
+        let mut extractor = EntityExtractor::new();
+        extractor.register_pattern(
+            EntityType::Host,
+            Regex::new(r"(?i)\bnode-(\d+)\b").unwrap(),
+            None,
+        );
+        // No capture group: the whole match is the entity
+        extractor.register_pattern(
+            EntityType::Deployment,
+            Regex::new(r"(?i)\bREL-\d+\b").unwrap(),
+            Some(lowercase),
+        );
+
+        let entities = extractor.extract("Show CPU on node-7 and node-12 since REL-42");
+        let hosts: Vec<_> = entities
+            .iter()
+            .filter(|e| e.entity_type == EntityType::Host)
+            .map(|e| (e.normalized_value.as_str(), e.span.unwrap()))
+            .collect();
+        assert_eq!(hosts, [("7", (17, 18)), ("12", (28, 30))]);
+        assert!(entities.iter().any(|e| e.entity_type == EntityType::Metric));
+
+        let release = entities
+            .iter()
+            .find(|e| e.entity_type == EntityType::Deployment)
+            .unwrap();
+        assert_eq!(release.original_text, "REL-42");
+        assert_eq!(release.normalized_value, "rel-42");
+        assert_eq!(release.confidence, 0.8);
+    }
+
+    #[test]
+    fn test_registered_pattern_duplicates_are_dropped() {
+        let mut extractor = EntityExtractor::new();
+        extractor.register_pattern(
+            EntityType::Service,
+            Regex::new(r"\b[a-z]+-service\b").unwrap(),
+            None,
+        );
+        extractor.register_pattern(
+            EntityType::Service,
+            Regex::new(r"\b(auth)-service\b|\b(?:auth-service)\b").unwrap(),
+            None,
+        );
+
+        let entities = extractor.extract("Is auth-service up?");
+        let services: Vec<_> = entities
+            .iter()
+            .filter(|e| e.entity_type == EntityType::Service)
+            .collect();
+        // The built-in match and the identical registered one count once
+        assert_eq!(services.iter().filter(|e| e.original_text == "auth-service").count(), 1);
+        // A different text of the same type is kept
+        assert!(services.iter().any(|e| e.original_text == "auth"));
+    }
+
+    #[test]
+    fn test_repeated_matches_are_extracted_once() {
+        let extractor = EntityExtractor::new();
+        let entities = extractor.extract("show last 5 minutes and last 5 minutes of cpu");
+        assert_eq!(values(&entities, EntityType::TimeRange), ["5m"]);
+
+        // A known metric and the built-in pattern match the same text
+        let extractor = EntityExtractor::with_context(Vec::new(), vec!["cpu".to_string()]);
+        let entities = extractor.extract("show cpu for auth-service");
+        let metrics: Vec<_> = entities
+            .iter()
+            .filter(|e| e.entity_type == EntityType::Metric)
+            .collect();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].confidence, 0.9);
+        assert_eq!(metrics[0].span, Some((5, 8)));
+    }
+
+    #[test]
+    fn test_contained_lower_confidence_matches_are_dropped() {
+        let mut extractor = EntityExtractor::new();
+        // Matches "time" inside the built-in "response time" match
+        extractor.register_pattern(EntityType::Metric, Regex::new(r"\btime\b").unwrap(), None);
+        let entities = extractor.extract("show response time");
+        assert_eq!(values(&entities, EntityType::Metric), ["response_time"]);
+
+        // Other types overlapping the same text are kept
+        let mut entities = vec![
+            Entity::new(EntityType::Metric, "error rate".into(), "error_rate".into(), "error rate".into(), 0.85)
+                .with_span(5, 15),
+            Entity::new(EntityType::Severity, "error".into(), "error".into(), "error".into(), 0.8)
+                .with_span(5, 10),
+            Entity::new(EntityType::Metric, "rate".into(), "rate".into(), "rate".into(), 0.9)
+                .with_span(11, 15),
+        ];
+        drop_overlapping(&mut entities);
+        assert_eq!(entities.len(), 3);
+        entities[2].confidence = 0.5;
+        drop_overlapping(&mut entities);
+        assert_eq!(values(&entities, EntityType::Metric), ["error_rate"]);
+        assert_eq!(values(&entities, EntityType::Severity), ["error"]);
+    }
+
+    #[test]
+    fn test_partially_overlapping_matches_are_resolved() {
+        let metric = |value: &str, confidence, start, end| {
+            Entity::new(EntityType::Metric, value.into(), value.into(), value.into(), confidence)
+                .with_span(start, end)
+        };
+
+        // Neither span contains the other: the wider one is kept, and a
+        // match only touching it survives
+        let mut entities = vec![
+            metric("response time", 0.9, 5, 18),
+            metric("time to first byte", 0.8, 14, 32),
+            metric("p99", 0.8, 32, 35),
+        ];
+        drop_overlapping(&mut entities);
+        assert_eq!(values(&entities, EntityType::Metric), ["time to first byte", "p99"]);
+
+        // Equally wide matches are decided by confidence
+        let mut entities = vec![metric("cpu usage", 0.8, 0, 9), metric("usage spike", 0.85, 4, 13)];
+        drop_overlapping(&mut entities);
+        assert_eq!(values(&entities, EntityType::Metric), ["usage spike"]);
+
+        let mut extractor = EntityExtractor::new();
+        extractor.register_pattern(
+            EntityType::Metric,
+            Regex::new(r"\btime to first byte\b").unwrap(),
+            Some(|_: &str| "ttfb".to_string()),
+        );
+        let entities = extractor.extract("show response time to first byte");
+        assert_eq!(values(&entities, EntityType::Metric), ["ttfb"]);
+    }
 }
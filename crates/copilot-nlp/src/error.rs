@@ -20,6 +20,9 @@ pub enum NlpError {
     #[error("Unsupported operation: {0}")]
     Unsupported(String),
 
+    #[error("Feature not enabled: {0}")]
+    FeatureNotEnabled(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -45,6 +48,10 @@ impl NlpError {
         Self::Unsupported(msg.into())
     }
 
+    pub fn feature_not_enabled(msg: impl Into<String>) -> Self {
+        Self::FeatureNotEnabled(msg.into())
+    }
+
     pub fn internal(msg: impl Into<String>) -> Self {
         Self::Internal(msg.into())
     }
@@ -62,6 +69,7 @@ impl From<NlpError> for copilot_core::AppError {
             NlpError::EntityExtraction(msg) => copilot_core::AppError::internal(msg),
             NlpError::QueryTranslation(msg) => copilot_core::AppError::internal(msg),
             NlpError::Unsupported(msg) => copilot_core::AppError::validation(msg),
+            NlpError::FeatureNotEnabled(msg) => copilot_core::AppError::validation(msg),
             NlpError::Internal(msg) => copilot_core::AppError::internal(msg),
         }
     }
@@ -3,16 +3,20 @@
 //! This module provides pattern-based intent classification using pre-compiled
 //! regular expressions for fast matching and confidence scoring.
 
+use crate::action::{extract_action, ActionRequest, ACTION_CONFIDENCE_THRESHOLD};
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tracing::{debug, trace};
 
 /// Supported intent types for observability queries.
 ///
 /// These intents cover the primary use cases for observability and monitoring.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Ordered by declaration, which breaks ties between equally scored
+/// intents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum IntentType {
     /// Query metrics (e.g., "Show CPU usage")
     QueryMetrics,
@@ -42,6 +46,8 @@ pub enum IntentType {
     SloMonitoring,
     /// Trend analysis (e.g., "Show traffic trends")
     TrendAnalysis,
+    /// Request to perform an action (e.g., "Restart the auth-service pod")
+    ActionRequest,
     /// General query (fallback for unclear intents)
     GeneralQuery,
     /// Unknown intent
@@ -66,10 +72,22 @@ impl IntentType {
             Self::DependencyAnalysis => "Analyze service dependencies",
             Self::SloMonitoring => "Monitor SLOs and SLIs",
             Self::TrendAnalysis => "Analyze trends over time",
+            Self::ActionRequest => "Perform an operational action",
             Self::GeneralQuery => "General observability query",
             Self::Unknown => "Unknown or unclear intent",
         }
     }
+
+    /// Returns the confidence needed to act on this intent.
+    ///
+    /// Action requests change the system rather than read from it, so they
+    /// need [`ACTION_CONFIDENCE_THRESHOLD`] rather than the usual 0.7.
+    pub fn confidence_threshold(&self) -> f64 {
+        match self {
+            Self::ActionRequest => ACTION_CONFIDENCE_THRESHOLD,
+            _ => 0.7,
+        }
+    }
 }
 
 /// Represents a classified intent with confidence score.
@@ -83,6 +101,12 @@ pub struct Intent {
     pub matched_patterns: Vec<String>,
     /// Alternative intents that were considered
     pub alternatives: Vec<(IntentType, f64)>,
+    /// The requested action, for action requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ActionRequest>,
+    /// Calibrated confidence floor, overriding the intent type's default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
 }
 
 impl Intent {
@@ -93,12 +117,32 @@ impl Intent {
             confidence,
             matched_patterns: Vec::new(),
             alternatives: Vec::new(),
+            action: None,
+            threshold: None,
         }
     }
 
-    /// Returns true if the confidence is above the threshold (0.7).
+    /// Returns true if the confidence reaches the calibrated threshold, or
+    /// else the intent type's default (0.7, or
+    /// [`ACTION_CONFIDENCE_THRESHOLD`] for action requests).
     pub fn is_confident(&self) -> bool {
-        self.confidence >= 0.7
+        self.is_confident_for(
+            self.threshold
+                .unwrap_or_else(|| self.intent_type.confidence_threshold()),
+        )
+    }
+
+    /// Returns true if the confidence reaches the given threshold.
+    pub fn is_confident_for(&self, threshold: f64) -> bool {
+        self.confidence >= threshold
+    }
+
+    /// Returns the requested action if it is confident enough to be routed
+    /// to a workflow without asking the user to confirm.
+    pub fn routable_action(&self) -> Option<&ActionRequest> {
+        self.action.as_ref().filter(|_| {
+            self.intent_type == IntentType::ActionRequest && self.is_confident()
+        })
     }
 }
 
@@ -242,6 +286,11 @@ lazy_static! {
             weight: 0.8,
             intent: IntentType::ErrorAnalysis,
         },
+        IntentPattern {
+            regex: Regex::new(r"(?i)\b(recent|new|latest)\s+(errors?|failures?|exceptions?)").unwrap(),
+            weight: 0.8,
+            intent: IntentType::ErrorAnalysis,
+        },
 
         // CapacityPlanning patterns
         IntentPattern {
@@ -297,6 +346,8 @@ lazy_static! {
 pub struct IntentClassifier {
     /// Custom patterns added by the user
     custom_patterns: Vec<IntentPattern>,
+    /// Confidence floors per intent type, overriding the defaults
+    calibration: HashMap<IntentType, f64>,
 }
 
 impl IntentClassifier {
@@ -304,9 +355,28 @@ impl IntentClassifier {
     pub fn new() -> Self {
         Self {
             custom_patterns: Vec::new(),
+            calibration: HashMap::new(),
         }
     }
 
+    /// Sets the confidence floors of intent types.
+    ///
+    /// Intents of a calibrated type carry its floor, so
+    /// [`Intent::is_confident`] applies it instead of the type's default.
+    /// Types missing from the map keep their default.
+    pub fn with_calibration(mut self, calibration: HashMap<IntentType, f64>) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Returns the confidence an intent of the given type needs.
+    pub fn confidence_threshold(&self, intent_type: IntentType) -> f64 {
+        self.calibration
+            .get(&intent_type)
+            .copied()
+            .unwrap_or_else(|| intent_type.confidence_threshold())
+    }
+
     /// Adds a custom pattern for intent classification.
     ///
     /// # Arguments
@@ -341,10 +411,53 @@ impl IntentClassifier {
     ///
     /// An `Intent` object with the classified type, confidence, and matched patterns
     pub fn classify(&self, query: &str) -> Intent {
-        trace!("Classifying intent for query: {}", query);
+        let mut intent = self.score(query);
+        intent.threshold = self.calibration.get(&intent.intent_type).copied();
+        intent
+    }
+
+    /// Classifies every intent a compound query expresses.
+    ///
+    /// Returns the intents that reach their (calibrated) confidence
+    /// threshold, most confident first, so a query such as "show cpu and
+    /// recent errors" can be translated once per intent. An action request
+    /// is returned alone, as with [`classify`](Self::classify).
+    pub fn classify_multi(&self, query: &str) -> Vec<Intent> {
+        let primary = self.classify(query);
+        if primary.intent_type == IntentType::ActionRequest {
+            return if primary.is_confident() { vec![primary] } else { Vec::new() };
+        }
+
+        let (scores, mut matched_patterns) = self.match_patterns(query);
+        let max_score = scores.values().fold(0.0_f64, |a, &b| a.max(b));
+        let mut intents: Vec<Intent> = scores
+            .iter()
+            .map(|(&intent_type, &score)| Intent {
+                matched_patterns: matched_patterns.remove(&intent_type).unwrap_or_default(),
+                threshold: self.calibration.get(&intent_type).copied(),
+                ..Intent::new(intent_type, score / max_score)
+            })
+            .filter(Intent::is_confident)
+            .collect();
+        intents.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.intent_type.cmp(&b.intent_type))
+        });
 
-        let mut scores: HashMap<IntentType, f64> = HashMap::new();
-        let mut matched_patterns: HashMap<IntentType, Vec<String>> = HashMap::new();
+        debug!("Classified {} intents for compound query", intents.len());
+        intents
+    }
+
+    /// Sums the weights of the patterns matching the query per intent.
+    ///
+    /// Ordered maps, so equally scored intents rank the same on every run.
+    fn match_patterns(
+        &self,
+        query: &str,
+    ) -> (BTreeMap<IntentType, f64>, BTreeMap<IntentType, Vec<String>>) {
+        let mut scores: BTreeMap<IntentType, f64> = BTreeMap::new();
+        let mut matched_patterns: BTreeMap<IntentType, Vec<String>> = BTreeMap::new();
 
         // Check all patterns (built-in and custom)
         let all_patterns = INTENT_PATTERNS.iter().chain(self.custom_patterns.iter());
@@ -360,9 +473,42 @@ impl IntentClassifier {
             }
         }
 
+        (scores, matched_patterns)
+    }
+
+    /// Scores the query against all patterns, without calibration.
+    fn score(&self, query: &str) -> Intent {
+        trace!("Classifying intent for query: {}", query);
+        let (scores, matched_patterns) = self.match_patterns(query);
+
         // Normalize scores and find the best match
         let max_score = scores.values().fold(0.0_f64, |a, &b| a.max(b));
 
+        // An imperative action verb makes the message an action request;
+        // what it would otherwise have been asked about becomes an
+        // alternative
+        if let Some((action, confidence)) = extract_action(query) {
+            debug!(
+                "Classified action request: {:?} on {:?} with confidence: {}",
+                action.kind, action.target, confidence
+            );
+            let mut alternatives: Vec<(IntentType, f64)> = scores
+                .iter()
+                .map(|(&intent, &score)| (intent, score / max_score))
+                .filter(|(_, score)| *score > 0.3)
+                .collect();
+            alternatives.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+            return Intent {
+                intent_type: IntentType::ActionRequest,
+                confidence,
+                matched_patterns: vec![format!("action:{}", action.verb)],
+                alternatives,
+                action: Some(action),
+                threshold: None,
+            };
+        }
+
         if max_score == 0.0 {
             debug!("No patterns matched, returning Unknown intent");
             return Intent {
@@ -370,6 +516,8 @@ impl IntentClassifier {
                 confidence: 0.0,
                 matched_patterns: Vec::new(),
                 alternatives: Vec::new(),
+                action: None,
+                threshold: None,
             };
         }
 
@@ -380,7 +528,7 @@ impl IntentClassifier {
             .collect();
 
         // Sort by score descending
-        intent_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        intent_scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 
         let (best_intent, confidence) = intent_scores[0];
         let alternatives = intent_scores[1..]
@@ -404,6 +552,8 @@ impl IntentClassifier {
             confidence,
             matched_patterns: patterns,
             alternatives,
+            action: None,
+            threshold: None,
         }
     }
 }
@@ -470,6 +620,87 @@ mod tests {
         assert_eq!(intent.intent_type, IntentType::GeneralQuery);
     }
 
+    #[test]
+    fn test_classify_action_request() {
+        let classifier = IntentClassifier::new();
+        let intent = classifier.classify("Restart the auth-service pod");
+        assert_eq!(intent.intent_type, IntentType::ActionRequest);
+        assert!(intent.is_confident());
+
+        let action = intent.routable_action().unwrap();
+        assert_eq!(action.kind, crate::action::ActionKind::Restart);
+        assert_eq!(action.verb, "restart");
+        assert_eq!(action.target.as_deref(), Some("auth-service"));
+
+        // Asking about a restart is not requesting one
+        let question = classifier.classify("Why did auth-service restart?");
+        assert_eq!(question.intent_type, IntentType::RootCauseAnalysis);
+        assert!(question.action.is_none());
+
+        // Nor is asking how often deploys happen
+        let metric = classifier.classify("Deploy frequency for checkout last week");
+        assert_ne!(metric.intent_type, IntentType::ActionRequest);
+        assert!(metric.action.is_none());
+    }
+
+    #[test]
+    fn test_low_confidence_action_is_not_routed() {
+        let classifier = IntentClassifier::new();
+        let intent = classifier.classify("should we scale the checkout service?");
+        assert_eq!(intent.intent_type, IntentType::ActionRequest);
+        assert!(intent.action.is_some());
+        assert!(intent.confidence < ACTION_CONFIDENCE_THRESHOLD);
+        assert!(!intent.is_confident());
+        assert!(intent.routable_action().is_none());
+
+        // A query intent at the same confidence would count as confident
+        let query = Intent::new(IntentType::QueryMetrics, intent.confidence.max(0.7));
+        assert!(query.is_confident());
+    }
+
+    #[test]
+    fn test_calibration_sets_per_type_floors() {
+        let classifier = IntentClassifier::new().with_calibration(HashMap::from([
+            (IntentType::AlertInvestigation, 0.95),
+            (IntentType::SearchLogs, 0.5),
+        ]));
+        assert_eq!(classifier.confidence_threshold(IntentType::AlertInvestigation), 0.95);
+        assert_eq!(classifier.confidence_threshold(IntentType::QueryMetrics), 0.7);
+
+        let alert = classifier.classify("the disk alert triggered overnight");
+        assert_eq!(alert.intent_type, IntentType::AlertInvestigation);
+        assert_eq!(alert.threshold, Some(0.95));
+
+        // The same raw confidence passes one floor and not the other
+        let raw = 0.8;
+        let alert = Intent {
+            confidence: raw,
+            ..alert
+        };
+        let mut logs = Intent::new(IntentType::SearchLogs, raw);
+        logs.threshold = Some(classifier.confidence_threshold(IntentType::SearchLogs));
+        assert!(!alert.is_confident());
+        assert!(logs.is_confident());
+        assert!(alert.is_confident_for(0.7));
+    }
+
+    #[test]
+    fn test_classify_multi_splits_compound_query() {
+        let classifier = IntentClassifier::new();
+        let intents = classifier.classify_multi("show cpu and recent errors for auth-service");
+        let types: Vec<IntentType> = intents.iter().map(|i| i.intent_type).collect();
+        assert!(types.contains(&IntentType::QueryMetrics), "{:?}", types);
+        assert!(types.contains(&IntentType::ErrorAnalysis), "{:?}", types);
+        assert!(intents.windows(2).all(|w| w[0].confidence >= w[1].confidence));
+        assert!(intents.iter().all(|i| i.is_confident() && !i.matched_patterns.is_empty()));
+
+        // A single-intent query yields the same intent as classify
+        let query = "Show me CPU usage for the last hour";
+        let intents = classifier.classify_multi(query);
+        assert_eq!(intents[0].intent_type, classifier.classify(query).intent_type);
+        assert!(classifier.classify_multi("hello there").is_empty());
+    }
+
     #[test]
     fn test_intent_description() {
         assert!(!IntentType::QueryMetrics.description().is_empty());
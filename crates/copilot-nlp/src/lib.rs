@@ -9,7 +9,9 @@
 //!
 //! - **Intent Classification**: Identifies user intent from natural language with confidence scoring
 //! - **Entity Extraction**: Extracts entities like time ranges, services, metrics, and severity levels
+//! - **Action Requests**: Recognizes requests to act ("restart the auth-service pod") with their verb and target
 //! - **Query Translation**: Converts natural language to PromQL, LogQL, and SQL queries
+//! - **Query Planning**: Splits compound requests into ordered, dependent queries
 //!
 //! ## Example
 //!
@@ -27,10 +29,12 @@
 //! }
 //! ```
 
+pub mod action;
 pub mod engine;
 pub mod entity;
 pub mod error;
 pub mod intent;
+pub mod plan;
 pub mod query;
 
 use async_trait::async_trait;
@@ -39,10 +43,14 @@ use serde::{Deserialize, Serialize};
 pub use error::{NlpError, Result};
 use std::collections::HashMap;
 
+pub use action::{ActionKind, ActionRequest, ACTION_CONFIDENCE_THRESHOLD};
 pub use engine::NlpEngineImpl;
-pub use entity::{Entity, EntityExtractor, EntityType};
+pub use entity::{sort_entities, Entity, EntityExtractor, EntityType};
 pub use intent::{Intent, IntentClassifier, IntentType};
-pub use query::{QueryLanguage, QueryTranslator};
+pub use plan::{PlanStep, QueryPlan, QueryPlanner, StepKind, TranslatedQuery};
+pub use query::{
+    escape_regex, escape_string, ComparisonSpec, MetricSchema, QueryLanguage, QueryTranslator,
+};
 
 /// Main NLP engine trait for processing natural language queries.
 ///
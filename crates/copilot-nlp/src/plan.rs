@@ -0,0 +1,307 @@
+//! Multi-step query planning.
+//!
+//! Compound analytical requests such as "compare error rates between prod
+//! and staging over the last day and highlight anomalies" need more than
+//! one backend query. [`QueryPlanner`] splits such a request into clauses,
+//! classifies each one, and translates it with the regular
+//! [`QueryTranslator`], producing an ordered [`QueryPlan`] whose steps name
+//! the steps they depend on.
+//!
+//! Steps that analyze data ("highlight anomalies", "find the root cause")
+//! depend on every step before them, so they run on the data those steps
+//! fetched. Steps that fetch data are independent of each other. Steps are
+//! listed in dependency order: a step only depends on earlier steps.
+
+use crate::entity::{Entity, EntityType};
+use crate::error::Result as NlpResult;
+use crate::intent::{IntentClassifier, IntentType};
+use crate::query::{QueryLanguage, QueryTranslator};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::debug;
+
+/// Verbs that start a new clause after "and" or a comma.
+const CLAUSE_VERBS: &[&str] = &[
+    "analyze", "analyse", "break", "check", "compare", "correlate", "detect", "display",
+    "explain", "find", "flag", "graph", "highlight", "identify", "investigate", "list",
+    "plot", "show", "spot",
+];
+
+lazy_static! {
+    /// Clause separators. Semicolons and "then" always separate clauses;
+    /// "and" and commas only when a clause verb follows.
+    static ref CLAUSE_SEPARATOR: Regex =
+        Regex::new(r"(?i);\s*|,?\s+(?:and\s+)?then\s+|,?\s+and\s+|,\s+").unwrap();
+}
+
+/// A sub-query translated into a backend query language.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranslatedQuery {
+    /// Intent the sub-query was classified as
+    pub intent: IntentType,
+    /// Language the query was translated to
+    pub language: QueryLanguage,
+    /// The translated query
+    pub query: String,
+}
+
+/// Role of a step in a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepKind {
+    /// Fetches data from a backend
+    Fetch,
+    /// Analyzes the data fetched by earlier steps
+    Analysis,
+}
+
+/// One step of a query plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Position of the step in the plan
+    pub id: usize,
+    /// The clause of the request the step answers
+    pub description: String,
+    /// Role of the step
+    pub kind: StepKind,
+    /// The step's translated query
+    pub query: TranslatedQuery,
+    /// Ids of the steps whose results this step needs, all earlier
+    pub depends_on: Vec<usize>,
+}
+
+/// Ordered steps answering a compound request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryPlan {
+    /// Steps in dependency order
+    pub steps: Vec<PlanStep>,
+}
+
+impl QueryPlan {
+    /// Returns the step with the given id.
+    pub fn step(&self, id: usize) -> Option<&PlanStep> {
+        self.steps.get(id)
+    }
+
+    /// Returns the steps that depend on the given step.
+    pub fn dependents(&self, id: usize) -> impl Iterator<Item = &PlanStep> {
+        self.steps.iter().filter(move |s| s.depends_on.contains(&id))
+    }
+
+    /// Returns the number of steps.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns true if the plan has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// Plans compound requests as several translated queries.
+pub struct QueryPlanner {
+    classifier: IntentClassifier,
+    translator: QueryTranslator,
+}
+
+impl QueryPlanner {
+    /// Creates a planner with the default classifier and translator.
+    pub fn new() -> Self {
+        Self {
+            classifier: IntentClassifier::new(),
+            translator: QueryTranslator::new(),
+        }
+    }
+
+    /// Sets the classifier used for each clause.
+    pub fn with_classifier(mut self, classifier: IntentClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Sets the translator used for each clause.
+    pub fn with_translator(mut self, translator: QueryTranslator) -> Self {
+        self.translator = translator;
+        self
+    }
+
+    /// Plans a request.
+    ///
+    /// `entities` are those extracted from the whole request. Each step
+    /// uses the entities of its own clause, and inherits entities of other
+    /// types from the rest of the request, so "highlight anomalies" looks
+    /// at the metric and time range named earlier. Entities without a span
+    /// apply to every step.
+    ///
+    /// # Errors
+    ///
+    /// Returns the translator's error if a clause cannot be translated to
+    /// any available language.
+    pub fn plan(&self, query: &str, entities: &[Entity]) -> NlpResult<QueryPlan> {
+        let mut steps: Vec<PlanStep> = Vec::new();
+        for (start, end) in split_clauses(query) {
+            let intent = self.classifier.classify(&query[start..end]);
+            let clause_entities = scoped_entities(entities, start, end);
+            let (language, translated) = self.translator.translate(&intent, &clause_entities)?;
+
+            let kind = if is_analysis(intent.intent_type) && !steps.is_empty() {
+                StepKind::Analysis
+            } else {
+                StepKind::Fetch
+            };
+            let depends_on = match kind {
+                StepKind::Analysis => (0..steps.len()).collect(),
+                StepKind::Fetch => Vec::new(),
+            };
+
+            steps.push(PlanStep {
+                id: steps.len(),
+                description: query[start..end].to_string(),
+                kind,
+                query: TranslatedQuery {
+                    intent: intent.intent_type,
+                    language,
+                    query: translated,
+                },
+                depends_on,
+            });
+        }
+
+        debug!("Planned {} steps for compound request", steps.len());
+        Ok(QueryPlan { steps })
+    }
+}
+
+impl Default for QueryPlanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Intents that analyze data rather than fetch it.
+fn is_analysis(intent_type: IntentType) -> bool {
+    matches!(
+        intent_type,
+        IntentType::DetectAnomalies | IntentType::RootCauseAnalysis | IntentType::CapacityPlanning
+    )
+}
+
+/// Splits a request into the byte ranges of its clauses.
+fn split_clauses(query: &str) -> Vec<(usize, usize)> {
+    let mut clauses = Vec::new();
+    let mut start = 0;
+    for separator in CLAUSE_SEPARATOR.find_iter(query) {
+        let text = separator.as_str().to_ascii_lowercase();
+        let next_word: String = query[separator.end()..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let hard = text.contains(';') || text.contains("then");
+        if hard || CLAUSE_VERBS.contains(&next_word.as_str()) {
+            clauses.push((start, separator.start()));
+            start = separator.end();
+        }
+    }
+    clauses.push((start, query.len()));
+
+    clauses
+        .into_iter()
+        .map(|(start, end)| trim_range(query, start, end))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+/// Narrows a range to exclude surrounding whitespace and punctuation.
+fn trim_range(query: &str, start: usize, end: usize) -> (usize, usize) {
+    let clause = &query[start..end];
+    let trimmed = clause.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    let start = start + clause.len() - trimmed.len();
+    let trimmed = trimmed.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '.' | '?' | '!'));
+    (start, start + trimmed.len())
+}
+
+/// The entities a clause uses: its own, plus those of other types from the
+/// rest of the request.
+fn scoped_entities(entities: &[Entity], start: usize, end: usize) -> Vec<Entity> {
+    let in_clause = |e: &Entity| e.span.map_or(true, |(s, e)| s >= start && e <= end);
+    let own: Vec<Entity> = entities.iter().filter(|e| in_clause(e)).cloned().collect();
+    let own_types: HashSet<EntityType> = own
+        .iter()
+        .filter(|e| e.span.is_some())
+        .map(|e| e.entity_type.clone())
+        .collect();
+
+    let mut scoped = own;
+    scoped.extend(
+        entities
+            .iter()
+            .filter(|e| !in_clause(e) && !own_types.contains(&e.entity_type))
+            .cloned(),
+    );
+    scoped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::EntityExtractor;
+
+    fn plan(query: &str) -> QueryPlan {
+        let entities = EntityExtractor::new().extract(query);
+        QueryPlanner::new().plan(query, &entities).unwrap()
+    }
+
+    #[test]
+    fn test_compound_request_plans_dependent_steps() {
+        let plan = plan(
+            "Show error rate for checkout-service over the last 6 hours, then detect anomalies and find the root cause",
+        );
+        assert_eq!(plan.len(), 3);
+
+        let fetch = plan.step(0).unwrap();
+        assert_eq!(fetch.description, "Show error rate for checkout-service over the last 6 hours");
+        assert_eq!(fetch.kind, StepKind::Fetch);
+        assert!(fetch.depends_on.is_empty());
+
+        let anomalies = plan.step(1).unwrap();
+        assert_eq!(anomalies.description, "detect anomalies");
+        assert_eq!(anomalies.kind, StepKind::Analysis);
+        assert_eq!(anomalies.query.intent, IntentType::DetectAnomalies);
+        assert_eq!(anomalies.depends_on, vec![0]);
+        // The analysis inherits the service and time range named earlier
+        assert!(anomalies.query.query.contains("6h"));
+
+        let root_cause = plan.step(2).unwrap();
+        assert_eq!(root_cause.query.intent, IntentType::RootCauseAnalysis);
+        assert_eq!(root_cause.depends_on, vec![0, 1]);
+        assert_eq!(root_cause.query.language, QueryLanguage::LogQL);
+        assert!(root_cause.query.query.contains("checkout-service"));
+        assert_eq!(plan.dependents(0).count(), 2);
+    }
+
+    #[test]
+    fn test_and_only_splits_before_a_verb() {
+        let compared = plan(
+            "compare error rates between prod and staging over the last day and highlight anomalies",
+        );
+        assert_eq!(compared.len(), 2);
+        assert_eq!(
+            compared.steps[0].description,
+            "compare error rates between prod and staging over the last day"
+        );
+        assert_eq!(compared.steps[0].query.intent, IntentType::CompareMetrics);
+        assert_eq!(compared.steps[1].description, "highlight anomalies");
+        assert_eq!(compared.steps[1].depends_on, vec![0]);
+
+        // Independent fetches do not depend on each other
+        let fetches = plan("show latency for checkout-service and show error rate for payment-service");
+        assert_eq!(fetches.len(), 2);
+        assert!(fetches.steps.iter().all(|s| s.kind == StepKind::Fetch && s.depends_on.is_empty()));
+        assert!(fetches.steps[0].query.query.contains("checkout-service"));
+        assert!(!fetches.steps[0].query.query.contains("payment-service"));
+    }
+}
@@ -1,14 +1,25 @@
 //! Query translation module.
 //!
 //! This module provides translation from natural language queries and extracted
-//! entities into structured query languages like PromQL, LogQL, and SQL.
+//! entities into structured query languages like PromQL, LogQL, SQL and TraceQL.
 
 use crate::entity::{Entity, EntityType};
+use crate::error::{NlpError, Result as NlpResult};
 use crate::intent::{Intent, IntentType};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tracing::{debug, trace};
 
+lazy_static! {
+    /// A threshold: comparison, amount and optional unit
+    static ref THRESHOLD: Regex = Regex::new(
+        r"(?i)^\s*(>=|<=|>|<|above|over|more than|below|under|less than)\s*(\d+(?:\.\d+)?)\s*([a-zµ%]*)\s*$"
+    )
+    .unwrap();
+}
+
 /// Supported query languages for translation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum QueryLanguage {
@@ -23,6 +34,9 @@ pub enum QueryLanguage {
 }
 
 impl QueryLanguage {
+    /// All query languages.
+    pub const ALL: [QueryLanguage; 4] = [Self::PromQL, Self::LogQL, Self::SQL, Self::TraceQL];
+
     /// Returns a human-readable description of the query language.
     pub fn description(&self) -> &'static str {
         match self {
@@ -34,6 +48,71 @@ impl QueryLanguage {
     }
 }
 
+/// Known metric and label names, e.g. loaded from Prometheus metadata.
+///
+/// Used by [`QueryTranslator::validate_mappings`] to catch mappings that
+/// target names the backend does not have.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricSchema {
+    /// Known metric names
+    pub metrics: HashSet<String>,
+    /// Known label names; an empty set skips label validation
+    pub labels: HashSet<String>,
+}
+
+impl MetricSchema {
+    /// Creates a schema from known metric names.
+    pub fn from_metrics<I, S>(metrics: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            metrics: metrics.into_iter().map(Into::into).collect(),
+            labels: HashSet::new(),
+        }
+    }
+
+    /// Adds known label names to the schema.
+    pub fn with_labels<I, S>(mut self, labels: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.labels.extend(labels.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns true if the metric name is known.
+    pub fn has_metric(&self, name: &str) -> bool {
+        self.metrics.contains(name)
+    }
+
+    /// Returns true if the label name is known.
+    pub fn has_label(&self, name: &str) -> bool {
+        self.labels.contains(name)
+    }
+}
+
+/// Structure of a side-by-side comparison query, for rendering a
+/// comparison chart.
+///
+/// `group_by` is the query's `by` clause, so each series of the result is
+/// identified by its values of those labels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComparisonSpec {
+    /// Metric being compared, as queried
+    pub metric: String,
+    /// Label whose values are compared, e.g. `service`
+    pub dimension: String,
+    /// Compared values of the dimension, in the order they were named
+    pub series: Vec<String>,
+    /// Labels the query groups by
+    pub group_by: Vec<String>,
+    /// Range each series' rate is computed over
+    pub time_range: String,
+}
+
 /// Query translator that converts natural language to structured queries.
 pub struct QueryTranslator {
     /// Default time range if none specified
@@ -42,6 +121,12 @@ pub struct QueryTranslator {
     metric_mappings: HashMap<String, String>,
     /// Custom label mappings
     label_mappings: HashMap<String, String>,
+    /// Optional schema that mapping targets are validated against
+    schema: Option<MetricSchema>,
+    /// Whether entity values are escaped when interpolated into queries
+    escape_values: bool,
+    /// Query languages whose backends are available in this deployment
+    available_languages: HashSet<QueryLanguage>,
 }
 
 impl QueryTranslator {
@@ -51,6 +136,9 @@ impl QueryTranslator {
             default_time_range: "5m".to_string(),
             metric_mappings: Self::default_metric_mappings(),
             label_mappings: HashMap::new(),
+            schema: None,
+            escape_values: true,
+            available_languages: QueryLanguage::ALL.into_iter().collect(),
         }
     }
 
@@ -63,6 +151,178 @@ impl QueryTranslator {
             default_time_range: "5m".to_string(),
             metric_mappings,
             label_mappings,
+            schema: None,
+            escape_values: true,
+            available_languages: QueryLanguage::ALL.into_iter().collect(),
+        }
+    }
+
+    /// Sets the schema used by [`validate_mappings`](Self::validate_mappings).
+    pub fn with_schema(mut self, schema: MetricSchema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Enables or disables escaping of entity values in generated queries.
+    ///
+    /// Escaping is enabled by default. Disabling it interpolates values
+    /// verbatim, which is only safe for trusted, pre-sanitized values.
+    pub fn with_escaping(mut self, enabled: bool) -> Self {
+        self.escape_values = enabled;
+        self
+    }
+
+    /// Restricts translation to the given query languages.
+    ///
+    /// Deployments without a Prometheus, Loki or Tempo datasource should
+    /// disable the matching language so no queries are generated for it.
+    /// All languages are available by default.
+    pub fn with_available_languages<I>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = QueryLanguage>,
+    {
+        self.available_languages = languages.into_iter().collect();
+        self
+    }
+
+    /// Enables or disables a query language at runtime.
+    pub fn set_language_available(&mut self, language: QueryLanguage, available: bool) {
+        if available {
+            self.available_languages.insert(language);
+        } else {
+            self.available_languages.remove(&language);
+        }
+    }
+
+    /// Returns true if translation to the language is enabled.
+    pub fn is_available(&self, language: QueryLanguage) -> bool {
+        self.available_languages.contains(&language)
+    }
+
+    /// Returns the enabled query languages in a stable order.
+    pub fn available_languages(&self) -> Vec<QueryLanguage> {
+        QueryLanguage::ALL
+            .into_iter()
+            .filter(|l| self.is_available(*l))
+            .collect()
+    }
+
+    /// Translates to an explicitly chosen query language.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NlpError::FeatureNotEnabled`] if the language is not
+    /// available.
+    pub fn translate_to(
+        &self,
+        language: QueryLanguage,
+        intent: &Intent,
+        entities: &[Entity],
+    ) -> NlpResult<String> {
+        if !self.is_available(language) {
+            return Err(NlpError::feature_not_enabled(format!(
+                "{:?} backend is not available",
+                language
+            )));
+        }
+
+        match language {
+            QueryLanguage::PromQL => Ok(self.to_promql(intent, entities)),
+            QueryLanguage::LogQL => Ok(self.to_logql(intent, entities)),
+            QueryLanguage::SQL => Ok(self.to_sql(intent, entities)),
+            QueryLanguage::TraceQL => Ok(self.to_traceql(intent, entities)),
+        }
+    }
+
+    /// Translates to the best available query language for the intent.
+    ///
+    /// Trace-oriented intents prefer TraceQL, log-oriented intents prefer
+    /// LogQL and metric-oriented intents prefer PromQL; SQL is the fallback
+    /// for all of them. Disabled languages are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NlpError::FeatureNotEnabled`] if none of the languages
+    /// suitable for the intent is available.
+    pub fn translate(
+        &self,
+        intent: &Intent,
+        entities: &[Entity],
+    ) -> NlpResult<(QueryLanguage, String)> {
+        let language = Self::preferred_languages(intent.intent_type)
+            .iter()
+            .copied()
+            .find(|l| self.is_available(*l))
+            .ok_or_else(|| {
+                NlpError::feature_not_enabled(format!(
+                    "No available query language for {:?}",
+                    intent.intent_type
+                ))
+            })?;
+
+        debug!("Routing {:?} to {:?}", intent.intent_type, language);
+        let query = self.translate_to(language, intent, entities)?;
+        Ok((language, query))
+    }
+
+    /// Query languages suited to an intent, most preferred first.
+    fn preferred_languages(intent_type: IntentType) -> &'static [QueryLanguage] {
+        use QueryLanguage::*;
+
+        match intent_type {
+            IntentType::AnalyzeTraces => &[TraceQL, LogQL, SQL],
+            IntentType::SearchLogs
+            | IntentType::ErrorAnalysis
+            | IntentType::RootCauseAnalysis
+            | IntentType::AlertInvestigation
+            | IntentType::DependencyAnalysis => &[LogQL, SQL],
+            _ => &[PromQL, SQL],
+        }
+    }
+
+    /// Validates mapping targets against the configured schema.
+    ///
+    /// Intended to be called at startup to catch misconfigured mappings
+    /// that would otherwise silently produce queries against nonexistent
+    /// metrics. Without a schema there is nothing to check against and
+    /// validation succeeds. Label mappings are only checked when the schema
+    /// lists labels.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if every mapping is valid, otherwise one message per
+    /// offending mapping, sorted for stable output
+    pub fn validate_mappings(&self) -> Result<(), Vec<String>> {
+        let schema = match &self.schema {
+            Some(schema) => schema,
+            None => return Ok(()),
+        };
+
+        let mut errors: Vec<String> = self
+            .metric_mappings
+            .iter()
+            .filter(|(_, target)| !schema.has_metric(target))
+            .map(|(name, target)| {
+                format!("metric mapping '{}' targets unknown metric '{}'", name, target)
+            })
+            .collect();
+
+        if !schema.labels.is_empty() {
+            errors.extend(
+                self.label_mappings
+                    .iter()
+                    .filter(|(_, target)| !schema.has_label(target))
+                    .map(|(name, target)| {
+                        format!("label mapping '{}' targets unknown label '{}'", name, target)
+                    }),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            errors.sort();
+            Err(errors)
         }
     }
 
@@ -113,28 +373,31 @@ impl QueryTranslator {
     pub fn to_promql(&self, intent: &Intent, entities: &[Entity]) -> String {
         trace!("Translating to PromQL: intent={:?}", intent.intent_type);
 
-        let time_range = self.get_entity_value(entities, EntityType::TimeRange)
+        let time_range = self.get_best_entity_value(entities, EntityType::TimeRange)
             .unwrap_or(&self.default_time_range);
 
-        let metric = self.get_entity_value(entities, EntityType::Metric);
-        let service = self.get_entity_value(entities, EntityType::Service);
-        let aggregation = self.get_entity_value(entities, EntityType::Aggregation);
+        let metric = self.get_best_entity_value(entities, EntityType::Metric);
+        let services = self.get_all_entity_values(entities, EntityType::Service);
+        let aggregation = self.get_best_entity_value(entities, EntityType::Aggregation);
 
         match intent.intent_type {
             IntentType::QueryMetrics | IntentType::PerformanceAnalysis => {
-                self.build_promql_metrics_query(metric, service, aggregation, time_range)
+                self.build_promql_metrics_query(metric, &services, aggregation, time_range)
             }
             IntentType::ErrorAnalysis => {
-                self.build_promql_error_query(service, time_range)
-            }
-            IntentType::CompareMetrics => {
-                self.build_promql_compare_query(metric, time_range)
+                self.build_promql_error_query(&services, time_range)
             }
+            IntentType::CompareMetrics => match self.to_promql_comparison(entities) {
+                Some((query, _)) => query,
+                None => self.build_promql_compare_query(metric, time_range),
+            },
             IntentType::TrendAnalysis => {
-                self.build_promql_trend_query(metric, service, time_range)
+                let aggregation = self.get_best_entity(entities, EntityType::Aggregation);
+                let threshold = self.get_best_entity_value(entities, EntityType::Threshold);
+                self.build_promql_trend_query(metric, &services, aggregation, threshold, time_range)
             }
             IntentType::ServiceHealth => {
-                self.build_promql_health_query(service)
+                self.build_promql_health_query(&services)
             }
             _ => {
                 // Default: simple metric query
@@ -148,6 +411,43 @@ impl QueryTranslator {
         }
     }
 
+    /// Translates a comparison of a metric across named services to PromQL.
+    ///
+    /// The query returns one series per named service, and the spec
+    /// describes them. Returns `None` unless at least two services are
+    /// named.
+    pub fn to_promql_comparison(&self, entities: &[Entity]) -> Option<(String, ComparisonSpec)> {
+        let services = self.get_all_entity_values(entities, EntityType::Service);
+        if services.len() < 2 {
+            return None;
+        }
+
+        let time_range = self.get_best_entity_value(entities, EntityType::TimeRange)
+            .unwrap_or(&self.default_time_range);
+        let metric_name = self.get_best_entity_value(entities, EntityType::Metric)
+            .and_then(|m| self.metric_mappings.get(m))
+            .map(|s| s.as_str())
+            .unwrap_or("up");
+
+        let spec = ComparisonSpec {
+            metric: metric_name.to_string(),
+            dimension: "service".to_string(),
+            series: services.iter().map(|s| s.to_string()).collect(),
+            group_by: vec!["service".to_string()],
+            time_range: time_range.to_string(),
+        };
+        let query = format!(
+            "sum(rate({}{{{}}}[{}])) by ({})",
+            metric_name,
+            self.regex_label_matcher(&spec.dimension, &services),
+            time_range,
+            spec.group_by.join(", ")
+        );
+
+        debug!("Comparing {} across {} services", metric_name, services.len());
+        Some((query, spec))
+    }
+
     /// Translates a query to LogQL.
     ///
     /// # Arguments
@@ -161,33 +461,31 @@ impl QueryTranslator {
     pub fn to_logql(&self, intent: &Intent, entities: &[Entity]) -> String {
         trace!("Translating to LogQL: intent={:?}", intent.intent_type);
 
-        let time_range = self.get_entity_value(entities, EntityType::TimeRange)
+        let time_range = self.get_best_entity_value(entities, EntityType::TimeRange)
             .unwrap_or(&self.default_time_range);
 
-        let service = self.get_entity_value(entities, EntityType::Service);
-        let severity = self.get_entity_value(entities, EntityType::Severity);
-        let endpoint = self.get_entity_value(entities, EntityType::Endpoint);
+        let services = self.get_all_entity_values(entities, EntityType::Service);
+        let severity = self.get_best_entity_value(entities, EntityType::Severity);
+        let endpoint = self.get_best_entity_value(entities, EntityType::Endpoint);
 
         match intent.intent_type {
             IntentType::SearchLogs | IntentType::ErrorAnalysis => {
-                self.build_logql_search_query(service, severity, endpoint, time_range)
+                self.build_logql_search_query(&services, severity, endpoint, time_range)
             }
             IntentType::RootCauseAnalysis | IntentType::AlertInvestigation => {
-                self.build_logql_analysis_query(service, severity, time_range)
+                self.build_logql_analysis_query(&services, severity, time_range)
             }
             IntentType::TrendAnalysis => {
-                self.build_logql_trend_query(service, severity, time_range)
+                self.build_logql_trend_query(&services, severity, time_range)
             }
             _ => {
                 // Default: simple log stream
                 let mut labels = Vec::new();
 
-                if let Some(svc) = service {
-                    labels.push(format!("service=\"{}\"", svc));
-                }
+                labels.extend(self.service_matcher(&services));
 
                 if let Some(sev) = severity {
-                    labels.push(format!("level=\"{}\"", sev));
+                    labels.push(self.label_matcher("level", sev));
                 }
 
                 let label_selector = if labels.is_empty() {
@@ -214,10 +512,10 @@ impl QueryTranslator {
     pub fn to_sql(&self, intent: &Intent, entities: &[Entity]) -> String {
         trace!("Translating to SQL: intent={:?}", intent.intent_type);
 
-        let service = self.get_entity_value(entities, EntityType::Service);
-        let severity = self.get_entity_value(entities, EntityType::Severity);
-        let metric = self.get_entity_value(entities, EntityType::Metric);
-        let aggregation = self.get_entity_value(entities, EntityType::Aggregation)
+        let service = self.get_best_entity_value(entities, EntityType::Service);
+        let severity = self.get_best_entity_value(entities, EntityType::Severity);
+        let metric = self.get_best_entity_value(entities, EntityType::Metric);
+        let aggregation = self.get_best_entity_value(entities, EntityType::Aggregation)
             .unwrap_or("avg");
 
         match intent.intent_type {
@@ -235,7 +533,7 @@ impl QueryTranslator {
                 let mut conditions = Vec::new();
 
                 if let Some(svc) = service {
-                    conditions.push(format!("service = '{}'", svc));
+                    conditions.push(self.sql_condition("service", svc));
                 }
 
                 let where_clause = if conditions.is_empty() {
@@ -249,12 +547,140 @@ impl QueryTranslator {
         }
     }
 
-    /// Helper function to get entity value by type.
-    fn get_entity_value<'a>(&self, entities: &'a [Entity], entity_type: EntityType) -> Option<&'a str> {
+    /// Translates a query to TraceQL.
+    ///
+    /// Services, HTTP status codes and endpoints become span attribute
+    /// conditions, and duration thresholds ("> 100ms", "above 2 seconds")
+    /// become `duration` filters. Root cause and error analysis only look
+    /// at error spans. Without any conditions the query matches every span.
+    ///
+    /// # Arguments
+    ///
+    /// * `intent` - The classified intent
+    /// * `entities` - Extracted entities
+    ///
+    /// # Returns
+    ///
+    /// A TraceQL query string
+    pub fn to_traceql(&self, intent: &Intent, entities: &[Entity]) -> String {
+        trace!("Translating to TraceQL: intent={:?}", intent.intent_type);
+
+        let services = self.get_all_entity_values(entities, EntityType::Service);
+        let statuses = self.get_all_entity_values(entities, EntityType::HttpStatus);
+        let endpoints = self.get_all_entity_values(entities, EntityType::Endpoint);
+
+        let mut conditions = Vec::new();
+        conditions.extend(self.traceql_matcher("resource.service.name", &services));
+        conditions.extend(Self::traceql_status_condition(&statuses));
+        conditions.extend(self.traceql_matcher("span.http.target", &endpoints));
+        conditions.extend(
+            self.get_all_entity_values(entities, EntityType::Threshold)
+                .into_iter()
+                .filter_map(traceql_duration_filter),
+        );
+
+        if matches!(
+            intent.intent_type,
+            IntentType::RootCauseAnalysis | IntentType::ErrorAnalysis
+        ) {
+            conditions.push("status = error".to_string());
+        }
+
+        if conditions.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", conditions.join(" && "))
+        }
+    }
+
+    /// Returns the normalized value of the most confident entity of a type.
+    ///
+    /// When several entities of the type were extracted, e.g. two services,
+    /// the one extracted with the highest confidence wins; ties go to the
+    /// one listed first.
+    pub fn get_best_entity_value<'a>(&self, entities: &'a [Entity], entity_type: EntityType) -> Option<&'a str> {
+        self.get_best_entity(entities, entity_type)
+            .map(|e| e.normalized_value.as_str())
+    }
+
+    /// Returns the most confident entity of a type.
+    fn get_best_entity<'a>(&self, entities: &'a [Entity], entity_type: EntityType) -> Option<&'a Entity> {
         entities
             .iter()
-            .find(|e| e.entity_type == entity_type)
-            .map(|e| e.normalized_value.as_str())
+            .filter(|e| e.entity_type == entity_type)
+            .reduce(|best, e| if e.confidence > best.confidence { e } else { best })
+    }
+
+    /// Returns every distinct normalized value of a type, in the order
+    /// extracted.
+    ///
+    /// Used where a query can cover several values at once, such as a
+    /// comparison grouped `by (service)`.
+    pub fn get_all_entity_values<'a>(&self, entities: &'a [Entity], entity_type: EntityType) -> Vec<&'a str> {
+        let mut values: Vec<&str> = Vec::new();
+        for entity in entities.iter().filter(|e| e.entity_type == entity_type) {
+            if !values.contains(&entity.normalized_value.as_str()) {
+                values.push(&entity.normalized_value);
+            }
+        }
+        values
+    }
+
+    // Label and filter rendering
+
+    /// Renders an equality label matcher, e.g. `service="api"`.
+    fn label_matcher(&self, label: &str, value: &str) -> String {
+        if self.escape_values {
+            format!("{}=\"{}\"", label, escape_logql_label(value))
+        } else {
+            format!("{}=\"{}\"", label, value)
+        }
+    }
+
+    /// Renders a regex label matcher that matches any of the values literally.
+    fn regex_label_matcher(&self, label: &str, values: &[&str]) -> String {
+        if self.escape_values {
+            let alternatives: Vec<String> = values.iter().map(|v| escape_regex(v)).collect();
+            format!("{}=~\"{}\"", label, escape_logql_label(&alternatives.join("|")))
+        } else {
+            format!("{}=~\"{}\"", label, values.join("|"))
+        }
+    }
+
+    /// Renders an SQL equality condition, e.g. `service = 'api'`.
+    fn sql_condition(&self, column: &str, value: &str) -> String {
+        if self.escape_values {
+            format!("{} = '{}'", column, escape_sql_literal(value))
+        } else {
+            format!("{} = '{}'", column, value)
+        }
+    }
+
+    /// Renders a matcher for the requested services, if any.
+    ///
+    /// A single service uses an equality matcher; several use a regex
+    /// matcher over their literal values.
+    fn service_matcher(&self, services: &[&str]) -> Option<String> {
+        match services {
+            [] => None,
+            [service] => Some(self.label_matcher("service", service)),
+            _ => Some(self.regex_label_matcher("service", services)),
+        }
+    }
+
+    /// Renders a LogQL line filter matching the value literally.
+    fn line_filter(&self, value: &str) -> String {
+        if !self.escape_values {
+            return format!("|~ `{}`", value);
+        }
+
+        let pattern = escape_regex(value);
+        if pattern.contains('`') {
+            // Raw strings cannot contain a backtick; fall back to a quoted string
+            format!("|~ \"{}\"", escape_string(&pattern))
+        } else {
+            format!("|~ `{}`", pattern)
+        }
     }
 
     // PromQL query builders
@@ -262,7 +688,7 @@ impl QueryTranslator {
     fn build_promql_metrics_query(
         &self,
         metric: Option<&str>,
-        service: Option<&str>,
+        services: &[&str],
         aggregation: Option<&str>,
         time_range: &str,
     ) -> String {
@@ -272,9 +698,7 @@ impl QueryTranslator {
             .unwrap_or("up");
 
         let mut labels = Vec::new();
-        if let Some(svc) = service {
-            labels.push(format!("service=\"{}\"", svc));
-        }
+        labels.extend(self.service_matcher(services));
 
         let label_selector = if labels.is_empty() {
             String::new()
@@ -294,12 +718,10 @@ impl QueryTranslator {
         }
     }
 
-    fn build_promql_error_query(&self, service: Option<&str>, time_range: &str) -> String {
+    fn build_promql_error_query(&self, services: &[&str], time_range: &str) -> String {
         let mut labels = vec!["code=~\"5..\"".to_string()];
 
-        if let Some(svc) = service {
-            labels.push(format!("service=\"{}\"", svc));
-        }
+        labels.extend(self.service_matcher(services));
 
         format!(
             "sum(rate(http_requests_total{{{}}}[{}]))",
@@ -320,10 +742,15 @@ impl QueryTranslator {
         )
     }
 
+    /// Aggregates the metric over the window with the requested function,
+    /// `avg_over_time` by default, and keeps only the points past a
+    /// threshold if one was given.
     fn build_promql_trend_query(
         &self,
         metric: Option<&str>,
-        service: Option<&str>,
+        services: &[&str],
+        aggregation: Option<&Entity>,
+        threshold: Option<&str>,
         time_range: &str,
     ) -> String {
         let metric_name = metric
@@ -332,9 +759,7 @@ impl QueryTranslator {
             .unwrap_or("up");
 
         let mut labels = Vec::new();
-        if let Some(svc) = service {
-            labels.push(format!("service=\"{}\"", svc));
-        }
+        labels.extend(self.service_matcher(services));
 
         let label_selector = if labels.is_empty() {
             String::new()
@@ -342,17 +767,20 @@ impl QueryTranslator {
             format!("{{{}}}", labels.join(", "))
         };
 
-        format!(
-            "avg_over_time({}{}[{}])",
-            metric_name, label_selector, time_range
-        )
+        let query = promql_range_call(
+            aggregation,
+            &format!("{}{}[{}]", metric_name, label_selector, time_range),
+        );
+        match threshold.and_then(|threshold| promql_threshold_filter(threshold, metric_name)) {
+            Some(filter) => format!("{} {}", query, filter),
+            None => query,
+        }
     }
 
-    fn build_promql_health_query(&self, service: Option<&str>) -> String {
-        if let Some(svc) = service {
-            format!("up{{service=\"{}\"}}", svc)
-        } else {
-            "up".to_string()
+    fn build_promql_health_query(&self, services: &[&str]) -> String {
+        match self.service_matcher(services) {
+            Some(matcher) => format!("up{{{}}}", matcher),
+            None => "up".to_string(),
         }
     }
 
@@ -360,7 +788,7 @@ impl QueryTranslator {
 
     fn build_logql_search_query(
         &self,
-        service: Option<&str>,
+        services: &[&str],
         severity: Option<&str>,
         endpoint: Option<&str>,
         time_range: &str,
@@ -368,16 +796,14 @@ impl QueryTranslator {
         let mut labels = Vec::new();
         let mut filters = Vec::new();
 
-        if let Some(svc) = service {
-            labels.push(format!("service=\"{}\"", svc));
-        }
+        labels.extend(self.service_matcher(services));
 
         if let Some(sev) = severity {
-            labels.push(format!("level=\"{}\"", sev));
+            labels.push(self.label_matcher("level", sev));
         }
 
         if let Some(ep) = endpoint {
-            filters.push(format!("|~ `{}`", ep));
+            filters.push(self.line_filter(ep));
         }
 
         let label_selector = labels.join(", ");
@@ -391,18 +817,16 @@ impl QueryTranslator {
 
     fn build_logql_analysis_query(
         &self,
-        service: Option<&str>,
+        services: &[&str],
         severity: Option<&str>,
         time_range: &str,
     ) -> String {
         let mut labels = Vec::new();
 
-        if let Some(svc) = service {
-            labels.push(format!("service=\"{}\"", svc));
-        }
+        labels.extend(self.service_matcher(services));
 
         if let Some(sev) = severity {
-            labels.push(format!("level=\"{}\"", sev));
+            labels.push(self.label_matcher("level", sev));
         } else {
             labels.push("level=\"error\"".to_string());
         }
@@ -417,18 +841,16 @@ impl QueryTranslator {
 
     fn build_logql_trend_query(
         &self,
-        service: Option<&str>,
+        services: &[&str],
         severity: Option<&str>,
         time_range: &str,
     ) -> String {
         let mut labels = Vec::new();
 
-        if let Some(svc) = service {
-            labels.push(format!("service=\"{}\"", svc));
-        }
+        labels.extend(self.service_matcher(services));
 
         if let Some(sev) = severity {
-            labels.push(format!("level=\"{}\"", sev));
+            labels.push(self.label_matcher("level", sev));
         }
 
         let label_selector = labels.join(", ");
@@ -451,7 +873,7 @@ impl QueryTranslator {
         let mut conditions = Vec::new();
 
         if let Some(svc) = service {
-            conditions.push(format!("service = '{}'", svc));
+            conditions.push(self.sql_condition("service", svc));
         }
 
         let where_clause = if conditions.is_empty() {
@@ -472,11 +894,11 @@ impl QueryTranslator {
         let mut conditions = Vec::new();
 
         if let Some(svc) = service {
-            conditions.push(format!("service = '{}'", svc));
+            conditions.push(self.sql_condition("service", svc));
         }
 
         if let Some(sev) = severity {
-            conditions.push(format!("level = '{}'", sev));
+            conditions.push(self.sql_condition("level", sev));
         }
 
         let where_clause = if conditions.is_empty() {
@@ -501,7 +923,7 @@ impl QueryTranslator {
         let mut conditions = Vec::new();
 
         if let Some(svc) = service {
-            conditions.push(format!("service = '{}'", svc));
+            conditions.push(self.sql_condition("service", svc));
         }
 
         let where_clause = if conditions.is_empty() {
@@ -517,6 +939,200 @@ impl QueryTranslator {
             where_clause
         )
     }
+
+    // TraceQL query builders
+
+    /// Renders a condition matching an attribute against any of the values.
+    fn traceql_matcher(&self, attribute: &str, values: &[&str]) -> Option<String> {
+        let quote = |value: &str| {
+            if self.escape_values {
+                escape_string(value)
+            } else {
+                value.to_string()
+            }
+        };
+        match values {
+            [] => None,
+            [value] => Some(format!("{} = \"{}\"", attribute, quote(value))),
+            _ => {
+                let alternatives: Vec<String> = if self.escape_values {
+                    values.iter().map(|v| escape_regex(v)).collect()
+                } else {
+                    values.iter().map(|v| v.to_string()).collect()
+                };
+                Some(format!("{} =~ \"{}\"", attribute, quote(&alternatives.join("|"))))
+            }
+        }
+    }
+
+    /// Renders a condition on the HTTP status code.
+    ///
+    /// Codes are numeric in TraceQL, so values that are not a code or a
+    /// class such as `5xx` are skipped rather than quoted.
+    fn traceql_status_condition(statuses: &[&str]) -> Option<String> {
+        let conditions: Vec<String> = statuses
+            .iter()
+            .filter_map(|status| {
+                let status = status.trim().to_ascii_lowercase();
+                if let Ok(code) = status.parse::<u16>() {
+                    return Some(format!("span.http.status_code = {}", code));
+                }
+                let class = status.strip_suffix("xx")?.parse::<u16>().ok()?;
+                Some(format!(
+                    "span.http.status_code >= {} && span.http.status_code < {}",
+                    class * 100,
+                    (class + 1) * 100
+                ))
+            })
+            .collect();
+
+        match conditions.len() {
+            0 => None,
+            1 => conditions.into_iter().next(),
+            _ => Some(format!("({})", conditions.join(" || "))),
+        }
+    }
+}
+
+/// Converts a duration threshold such as "> 100ms" or "below 2 seconds"
+/// into a TraceQL `duration` filter.
+///
+/// Units are normalized to TraceQL's (`ns`, `us`, `ms`, `s`, `m`, `h`) and
+/// a bare number is taken as milliseconds. Thresholds in other units, such
+/// as percentages, are not durations and yield `None`.
+fn traceql_duration_filter(threshold: &str) -> Option<String> {
+    let (operator, amount, unit) = parse_threshold(threshold)?;
+    Some(format!("duration {} {}", operator, duration_literal(amount, unit)?))
+}
+
+/// Converts a threshold such as "above 90%" or "< 500ms" into a PromQL
+/// comparison against `metric`, e.g. `< 0.5`.
+///
+/// Prometheus records durations in seconds, so duration thresholds are
+/// converted to seconds. A percentage is a ratio (90% is `0.9`) when the
+/// metric is one, by the `_ratio` naming convention, and compared as
+/// written otherwise. A bare number is compared as written. Thresholds in
+/// other units, such as sizes, yield `None` rather than a comparison
+/// against the wrong scale.
+fn promql_threshold_filter(threshold: &str, metric: &str) -> Option<String> {
+    let (operator, amount, unit) = parse_threshold(threshold)?;
+    let amount: f64 = amount.parse().ok()?;
+    let amount = match unit.to_lowercase().as_str() {
+        "" => amount,
+        "%" | "percent" if metric.ends_with("_ratio") => amount / 100.0,
+        "%" | "percent" => amount,
+        unit => match duration_unit(unit)? {
+            "ns" => amount / 1e9,
+            "us" => amount / 1e6,
+            "ms" => amount / 1e3,
+            "m" => amount * 60.0,
+            "h" => amount * 3600.0,
+            _ => amount,
+        },
+    };
+    Some(format!("{} {}", operator, amount))
+}
+
+/// Splits a threshold into its comparison operator, amount and unit.
+fn parse_threshold(threshold: &str) -> Option<(&'static str, &str, &str)> {
+    let captures = THRESHOLD.captures(threshold)?;
+    let operator = match captures[1].to_ascii_lowercase().as_str() {
+        ">" | "above" | "over" | "more than" => ">",
+        "<" | "below" | "under" | "less than" => "<",
+        ">=" => ">=",
+        "<=" => "<=",
+        _ => return None,
+    };
+    let (amount, unit) = (captures.get(2)?.as_str(), captures.get(3)?.as_str());
+    Some((operator, amount, unit))
+}
+
+/// Applies the PromQL range function for an aggregation to a range
+/// selector.
+///
+/// Percentiles use `quantile_over_time` at the percentile written ("p99"
+/// is 0.99); a bare "percentile" means the 95th.
+fn promql_range_call(aggregation: Option<&Entity>, range: &str) -> String {
+    let function = match aggregation.map(|a| a.normalized_value.as_str()) {
+        Some("max") => "max_over_time",
+        Some("min") => "min_over_time",
+        Some("sum") => "sum_over_time",
+        Some("count") => "count_over_time",
+        Some("percentile") => {
+            let quantile = aggregation
+                .and_then(|a| a.value.to_lowercase().strip_prefix('p')?.parse::<f64>().ok())
+                .map_or(0.95, |percentile| percentile / 100.0);
+            return format!("quantile_over_time({}, {})", quantile, range);
+        }
+        _ => "avg_over_time",
+    };
+    format!("{}({})", function, range)
+}
+
+/// Renders an amount and unit as a TraceQL duration literal.
+fn duration_literal(amount: &str, unit: &str) -> Option<String> {
+    let unit = if unit.is_empty() { "ms" } else { duration_unit(unit)? };
+    Some(format!("{}{}", amount, unit))
+}
+
+/// The canonical abbreviation of a duration unit.
+fn duration_unit(unit: &str) -> Option<&'static str> {
+    let unit = match unit.to_lowercase().as_str() {
+        "ms" | "msec" | "msecs" | "millisecond" | "milliseconds" => "ms",
+        "ns" | "nanosecond" | "nanoseconds" => "ns",
+        "us" | "µs" | "microsecond" | "microseconds" => "us",
+        "s" | "sec" | "secs" | "second" | "seconds" => "s",
+        "m" | "min" | "mins" | "minute" | "minutes" => "m",
+        "h" | "hr" | "hrs" | "hour" | "hours" => "h",
+        _ => return None,
+    };
+    Some(unit)
+}
+
+/// Escapes a value for use inside a double-quoted PromQL or LogQL string.
+pub fn escape_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes a value for use inside a double-quoted label selector value.
+///
+/// Loki label selectors share PromQL's string syntax, so this is
+/// [`escape_string`]; it names what the value is used for.
+fn escape_logql_label(value: &str) -> String {
+    escape_string(value)
+}
+
+/// Escapes a value for use inside a single-quoted SQL string literal by
+/// doubling embedded quotes, as standard SQL does.
+fn escape_sql_literal(value: &str) -> String {
+    value.replace('\'', "''")
+}
+
+/// Escapes RE2 metacharacters so a value matches literally in `=~` and
+/// `|~` matchers. Characters such as `-` are left as is.
+pub fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(
+            c,
+            '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 impl Default for QueryTranslator {
@@ -606,10 +1222,414 @@ mod tests {
         assert!(query.contains("web-service"));
     }
 
+    #[test]
+    fn test_promql_comparison_of_named_services() {
+        let translator = QueryTranslator::new();
+        let entities = vec![
+            create_test_entity(EntityType::Metric, "latency"),
+            create_test_entity(EntityType::Service, "payment-service"),
+            create_test_entity(EntityType::Service, "checkout.v2"),
+            create_test_entity(EntityType::Service, "payment-service"),
+            create_test_entity(EntityType::TimeRange, "1h"),
+        ];
+
+        let (query, spec) = translator.to_promql_comparison(&entities).unwrap();
+        assert_eq!(
+            query,
+            "sum(rate(http_request_duration_seconds{service=~\"payment-service|checkout\\\\.v2\"}[1h])) by (service)"
+        );
+        assert_eq!(spec.metric, "http_request_duration_seconds");
+        assert_eq!(spec.dimension, "service");
+        assert_eq!(spec.series, ["payment-service", "checkout.v2"]);
+        assert_eq!(spec.time_range, "1h");
+        // The spec describes the query's grouping
+        assert!(query.ends_with(&format!("by ({})", spec.group_by.join(", "))));
+
+        // Compare intents translate to the same query
+        let intent = create_test_intent(IntentType::CompareMetrics);
+        assert_eq!(translator.to_promql(&intent, &entities), query);
+    }
+
+    #[test]
+    fn test_promql_comparison_needs_two_services() {
+        let translator = QueryTranslator::new();
+        let entities = vec![
+            create_test_entity(EntityType::Metric, "cpu"),
+            create_test_entity(EntityType::Service, "checkout"),
+        ];
+        assert!(translator.to_promql_comparison(&entities).is_none());
+
+        let intent = create_test_intent(IntentType::CompareMetrics);
+        assert_eq!(
+            translator.to_promql(&intent, &entities),
+            "sum(rate(node_cpu_seconds_total[5m])) by (service)"
+        );
+    }
+
     #[test]
     fn test_query_language_description() {
         assert!(!QueryLanguage::PromQL.description().is_empty());
         assert!(!QueryLanguage::LogQL.description().is_empty());
         assert!(!QueryLanguage::SQL.description().is_empty());
     }
+
+    fn stub_schema() -> MetricSchema {
+        MetricSchema::from_metrics(["node_cpu_seconds_total", "http_requests_total"])
+            .with_labels(["instance", "service"])
+    }
+
+    #[test]
+    fn test_validate_mappings_without_schema() {
+        let mut metrics = HashMap::new();
+        metrics.insert("cpu".to_string(), "node_cpu_secnds_total".to_string());
+        let translator = QueryTranslator::with_mappings(metrics, HashMap::new());
+
+        assert!(translator.validate_mappings().is_ok());
+    }
+
+    #[test]
+    fn test_validate_mappings_against_schema() {
+        let mut metrics = HashMap::new();
+        metrics.insert("cpu".to_string(), "node_cpu_seconds_total".to_string());
+        metrics.insert("rps".to_string(), "http_requests_total".to_string());
+        let mut labels = HashMap::new();
+        labels.insert("host".to_string(), "instance".to_string());
+        let translator =
+            QueryTranslator::with_mappings(metrics, labels).with_schema(stub_schema());
+
+        assert!(translator.validate_mappings().is_ok());
+    }
+
+    #[test]
+    fn test_validate_mappings_reports_every_typo() {
+        let mut metrics = HashMap::new();
+        metrics.insert("cpu".to_string(), "node_cpu_seconds_total".to_string());
+        metrics.insert("qps".to_string(), "http_request_total".to_string());
+        metrics.insert("errors".to_string(), "http_requests_totl".to_string());
+        let mut labels = HashMap::new();
+        labels.insert("host".to_string(), "instanse".to_string());
+        let translator =
+            QueryTranslator::with_mappings(metrics, labels).with_schema(stub_schema());
+
+        let errors = translator.validate_mappings().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "label mapping 'host' targets unknown label 'instanse'",
+                "metric mapping 'errors' targets unknown metric 'http_requests_totl'",
+                "metric mapping 'qps' targets unknown metric 'http_request_total'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape_string() {
+        assert_eq!(escape_string(r#"say "hi"\now"#), r#"say \"hi\"\\now"#);
+        assert_eq!(escape_string("plain-name"), "plain-name");
+    }
+
+    #[test]
+    fn test_label_value_with_quote_is_escaped() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::ServiceHealth);
+        let entities = vec![create_test_entity(EntityType::Service, r#"api"} or up{job="x"#)];
+
+        let query = translator.to_promql(&intent, &entities);
+        assert_eq!(query, r#"up{service="api\"} or up{job=\"x"}"#);
+    }
+
+    #[test]
+    fn test_regex_matcher_treats_values_literally() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::ServiceHealth);
+        let entities = vec![
+            create_test_entity(EntityType::Service, "auth-service.v2"),
+            create_test_entity(EntityType::Service, "billing(eu)"),
+        ];
+
+        let query = translator.to_promql(&intent, &entities);
+        assert_eq!(
+            query,
+            r#"up{service=~"auth-service\\.v2|billing\\(eu\\)"}"#
+        );
+    }
+
+    #[test]
+    fn test_logql_line_filter_escaping() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::SearchLogs);
+
+        let entities = vec![create_test_entity(EntityType::Endpoint, "/api/v1.0/users?id=1")];
+        let query = translator.to_logql(&intent, &entities);
+        assert!(query.contains(r"|~ `/api/v1\.0/users\?id=1`"));
+
+        let entities = vec![create_test_entity(EntityType::Endpoint, "/a`b")];
+        let query = translator.to_logql(&intent, &entities);
+        assert!(query.contains(r#"|~ "/a`b""#));
+    }
+
+    #[test]
+    fn test_escaping_can_be_disabled() {
+        let translator = QueryTranslator::new().with_escaping(false);
+        let intent = create_test_intent(IntentType::ServiceHealth);
+        let entities = vec![create_test_entity(EntityType::Service, "a.b")];
+
+        assert_eq!(translator.to_promql(&intent, &entities), r#"up{service="a.b"}"#);
+    }
+
+    #[test]
+    fn test_translate_to_disabled_language_errors() {
+        let translator = QueryTranslator::new()
+            .with_available_languages([QueryLanguage::LogQL, QueryLanguage::SQL]);
+        let intent = create_test_intent(IntentType::QueryMetrics);
+
+        let err = translator
+            .translate_to(QueryLanguage::PromQL, &intent, &[])
+            .unwrap_err();
+        assert!(matches!(err, NlpError::FeatureNotEnabled(_)));
+        assert!(translator.translate_to(QueryLanguage::SQL, &intent, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_translate_routes_to_available_language() {
+        let mut translator = QueryTranslator::new();
+        let metrics = create_test_intent(IntentType::QueryMetrics);
+        let logs = create_test_intent(IntentType::SearchLogs);
+
+        assert_eq!(translator.translate(&metrics, &[]).unwrap().0, QueryLanguage::PromQL);
+        assert_eq!(translator.translate(&logs, &[]).unwrap().0, QueryLanguage::LogQL);
+
+        translator.set_language_available(QueryLanguage::PromQL, false);
+        translator.set_language_available(QueryLanguage::LogQL, false);
+        let (language, query) = translator.translate(&metrics, &[]).unwrap();
+        assert_eq!(language, QueryLanguage::SQL);
+        assert!(query.contains("SELECT"));
+        assert_eq!(translator.translate(&logs, &[]).unwrap().0, QueryLanguage::SQL);
+
+        translator.set_language_available(QueryLanguage::SQL, false);
+        assert!(matches!(
+            translator.translate(&metrics, &[]),
+            Err(NlpError::FeatureNotEnabled(_))
+        ));
+    }
+
+    #[test]
+    fn test_traceql_selectors_from_entities() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::AnalyzeTraces);
+
+        assert_eq!(translator.to_traceql(&intent, &[]), "{}");
+
+        let entities = vec![
+            create_test_entity(EntityType::Service, "checkout-service"),
+            create_test_entity(EntityType::HttpStatus, "503"),
+            create_test_entity(EntityType::Endpoint, "/api/cart"),
+            create_test_entity(EntityType::Threshold, "> 100ms"),
+        ];
+        assert_eq!(
+            translator.to_traceql(&intent, &entities),
+            r#"{ resource.service.name = "checkout-service" && span.http.status_code = 503 && span.http.target = "/api/cart" && duration > 100ms }"#
+        );
+
+        // Root cause analysis looks at error spans only
+        let rca = create_test_intent(IntentType::RootCauseAnalysis);
+        let entities = vec![
+            create_test_entity(EntityType::Service, "a.b"),
+            create_test_entity(EntityType::Service, "c"),
+            create_test_entity(EntityType::HttpStatus, "5xx"),
+        ];
+        assert_eq!(
+            translator.to_traceql(&rca, &entities),
+            r#"{ resource.service.name =~ "a\\.b|c" && span.http.status_code >= 500 && span.http.status_code < 600 && status = error }"#
+        );
+        assert_eq!(translator.to_traceql(&rca, &[]), "{ status = error }");
+
+        let (language, _) = translator.translate(&intent, &[]).unwrap();
+        assert_eq!(language, QueryLanguage::TraceQL);
+    }
+
+    #[test]
+    fn test_traceql_threshold_units_are_normalized() {
+        let filter = |threshold: &str| {
+            let entities = vec![create_test_entity(EntityType::Threshold, threshold)];
+            QueryTranslator::new().to_traceql(&create_test_intent(IntentType::AnalyzeTraces), &entities)
+        };
+
+        assert_eq!(filter("> 100ms"), "{ duration > 100ms }");
+        assert_eq!(filter("above 2 seconds"), "{ duration > 2s }");
+        assert_eq!(filter("below 1.5 sec"), "{ duration < 1.5s }");
+        assert_eq!(filter("< 250"), "{ duration < 250ms }");
+        assert_eq!(filter(">= 3 Minutes"), "{ duration >= 3m }");
+        assert_eq!(filter("under 500 microseconds"), "{ duration < 500us }");
+        // Not a duration
+        assert_eq!(filter("> 90%"), "{}");
+        assert_eq!(filter("above 2 gb"), "{}");
+    }
+
+    #[test]
+    fn test_most_confident_entity_wins() {
+        let translator = QueryTranslator::new();
+        let entities = vec![
+            Entity::new(EntityType::Service, "auth".into(), "auth-service".into(), "auth".into(), 0.6),
+            Entity::new(EntityType::Service, "payment-service".into(), "payment-service".into(), "payment-service".into(), 0.9),
+            Entity::new(EntityType::Service, "cart-service".into(), "cart-service".into(), "cart-service".into(), 0.9),
+        ];
+
+        // Highest confidence first, the earlier of equals on a tie
+        assert_eq!(
+            translator.get_best_entity_value(&entities, EntityType::Service),
+            Some("payment-service")
+        );
+        assert_eq!(translator.get_best_entity_value(&entities, EntityType::Metric), None);
+        let query = translator.to_sql(&create_test_intent(IntentType::SearchLogs), &entities);
+        assert!(query.contains("service = 'payment-service'"));
+
+        // Comparisons cover every service
+        assert_eq!(
+            translator.get_all_entity_values(&entities, EntityType::Service),
+            ["auth-service", "payment-service", "cart-service"]
+        );
+        let query = translator.to_promql(&create_test_intent(IntentType::CompareMetrics), &entities);
+        assert!(query.contains("auth-service|payment-service|cart-service"));
+        assert!(query.ends_with("by (service)"));
+    }
+
+    #[test]
+    fn test_promql_trend_uses_requested_aggregation() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::TrendAnalysis);
+        let trend = |aggregation: Option<&str>| {
+            let mut entities = vec![
+                create_test_entity(EntityType::Metric, "cpu"),
+                create_test_entity(EntityType::TimeRange, "1h"),
+            ];
+            entities.extend(aggregation.map(|a| create_test_entity(EntityType::Aggregation, a)));
+            translator.to_promql(&intent, &entities)
+        };
+
+        for (aggregation, function) in [
+            (Some("max"), "max_over_time"),
+            (Some("min"), "min_over_time"),
+            (Some("sum"), "sum_over_time"),
+            (Some("count"), "count_over_time"),
+            (Some("avg"), "avg_over_time"),
+            (None, "avg_over_time"),
+        ] {
+            assert_eq!(
+                trend(aggregation),
+                format!("{}(node_cpu_seconds_total[1h])", function),
+                "{:?}",
+                aggregation
+            );
+        }
+    }
+
+    #[test]
+    fn test_promql_trend_applies_threshold() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::TrendAnalysis);
+        let with_threshold = |threshold: &str| {
+            let entities = vec![
+                create_test_entity(EntityType::Metric, "cpu"),
+                create_test_entity(EntityType::TimeRange, "1h"),
+                create_test_entity(EntityType::Threshold, threshold),
+            ];
+            translator.to_promql(&intent, &entities)
+        };
+
+        assert_eq!(with_threshold("above 90%"), "avg_over_time(node_cpu_seconds_total[1h]) > 90");
+        assert_eq!(with_threshold("< 0.5"), "avg_over_time(node_cpu_seconds_total[1h]) < 0.5");
+        // Not a comparison: ignored
+        assert_eq!(with_threshold("90%"), "avg_over_time(node_cpu_seconds_total[1h])");
+        // Not in a unit the series can be in: ignored
+        assert_eq!(with_threshold("above 2 gb"), "avg_over_time(node_cpu_seconds_total[1h])");
+
+        // End to end from the extracted entities
+        let query = "max cpu above 90% in the last 1 hour";
+        let entities = crate::entity::EntityExtractor::new().extract(query);
+        assert_eq!(
+            translator.to_promql(&intent, &entities),
+            "max_over_time(node_cpu_seconds_total[1h]) > 90"
+        );
+    }
+
+    #[test]
+    fn test_promql_trend_thresholds_in_base_units() {
+        let mut metrics = HashMap::new();
+        metrics.insert("latency".to_string(), "http_request_duration_seconds".to_string());
+        metrics.insert("cpu".to_string(), "container_cpu_usage_ratio".to_string());
+        let translator = QueryTranslator::with_mappings(metrics, HashMap::new());
+        let intent = create_test_intent(IntentType::TrendAnalysis);
+        let trend = |metric: &str, threshold: &str| {
+            let entities = vec![
+                create_test_entity(EntityType::Metric, metric),
+                create_test_entity(EntityType::TimeRange, "1h"),
+                create_test_entity(EntityType::Threshold, threshold),
+            ];
+            translator.to_promql(&intent, &entities)
+        };
+
+        assert_eq!(
+            trend("latency", "above 500ms"),
+            "avg_over_time(http_request_duration_seconds[1h]) > 0.5"
+        );
+        assert_eq!(
+            trend("latency", "> 2 minutes"),
+            "avg_over_time(http_request_duration_seconds[1h]) > 120"
+        );
+        assert_eq!(trend("cpu", "above 90%"), "avg_over_time(container_cpu_usage_ratio[1h]) > 0.9");
+    }
+
+    #[test]
+    fn test_promql_trend_percentile() {
+        let translator = QueryTranslator::new();
+        let intent = create_test_intent(IntentType::TrendAnalysis);
+        let trend = |written: &str| {
+            let entities = vec![
+                create_test_entity(EntityType::Metric, "latency"),
+                create_test_entity(EntityType::TimeRange, "1h"),
+                Entity::new(
+                    EntityType::Aggregation,
+                    written.to_string(),
+                    "percentile".to_string(),
+                    written.to_string(),
+                    0.9,
+                ),
+            ];
+            translator.to_promql(&intent, &entities)
+        };
+
+        assert_eq!(
+            trend("p99"),
+            "quantile_over_time(0.99, http_request_duration_seconds[1h])"
+        );
+        assert_eq!(
+            trend("percentile"),
+            "quantile_over_time(0.95, http_request_duration_seconds[1h])"
+        );
+    }
+
+    #[test]
+    fn test_sql_values_are_quoted() {
+        let translator = QueryTranslator::new();
+        let entities = vec![
+            create_test_entity(EntityType::Service, "o'brien-svc"),
+            create_test_entity(EntityType::Severity, "error' OR '1'='1"),
+        ];
+
+        let query = translator.to_sql(&create_test_intent(IntentType::SearchLogs), &entities);
+        assert!(query.contains("service = 'o''brien-svc'"));
+        assert!(query.contains("level = 'error'' OR ''1''=''1'"));
+
+        let query = translator.to_sql(&create_test_intent(IntentType::QueryMetrics), &entities);
+        assert!(query.contains("WHERE service = 'o''brien-svc'"));
+
+        let query = translator.to_sql(&create_test_intent(IntentType::GeneralQuery), &entities);
+        assert!(query.contains("WHERE service = 'o''brien-svc'"));
+
+        let query = translator.to_logql(&create_test_intent(IntentType::SearchLogs), &[
+            create_test_entity(EntityType::Service, "say \"hi\""),
+        ]);
+        assert!(query.contains(r#"service="say \"hi\"""#));
+    }
 }
@@ -0,0 +1,243 @@
+//! Adaptive concurrency control
+//!
+//! A fixed concurrency limit is either too low for a healthy downstream or
+//! too high for a struggling one. [`AdaptiveConcurrency`] adjusts the limit
+//! AIMD-style from observed step latencies and outcomes: while the average
+//! latency over a window stays at or under the target, the limit grows by
+//! one; when it exceeds the target by the configured tolerance, or too many
+//! steps fail, the limit is cut multiplicatively.
+//!
+//! To avoid oscillation, decisions are made once per full window rather
+//! than per sample, latencies between the target and the tolerance hold
+//! the limit steady, and after a cut the next window is discarded since it
+//! was measured under the old limit.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Configuration for adaptive concurrency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveConfig {
+    /// Lowest limit the controller will set
+    pub min_limit: usize,
+    /// Highest limit the controller will set
+    pub max_limit: usize,
+    /// Limit to start from
+    pub initial_limit: usize,
+    /// Average latency considered healthy
+    pub target_latency_ms: u64,
+    /// Factor over the target at which the limit is cut
+    pub latency_tolerance: f64,
+    /// Fraction of failed steps in a window at which the limit is cut
+    pub max_error_rate: f64,
+    /// Factor the limit is multiplied by when cut
+    pub backoff_ratio: f64,
+    /// Samples per adjustment
+    pub window: usize,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            min_limit: 1,
+            max_limit: 64,
+            initial_limit: 4,
+            target_latency_ms: 1000,
+            latency_tolerance: 1.5,
+            max_error_rate: 0.2,
+            backoff_ratio: 0.5,
+            window: 10,
+        }
+    }
+}
+
+impl AdaptiveConfig {
+    /// Create a configuration with limit bounds and a latency target
+    pub fn new(min_limit: usize, max_limit: usize, target_latency: Duration) -> Self {
+        let min_limit = min_limit.max(1);
+        let max_limit = max_limit.max(min_limit);
+        Self {
+            min_limit,
+            max_limit,
+            initial_limit: min_limit,
+            target_latency_ms: target_latency.as_millis() as u64,
+            ..Default::default()
+        }
+    }
+
+    /// Set the starting limit
+    pub fn with_initial_limit(mut self, limit: usize) -> Self {
+        self.initial_limit = limit;
+        self
+    }
+
+    /// Set the number of samples per adjustment
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Set the factor over the target at which the limit is cut
+    pub fn with_latency_tolerance(mut self, tolerance: f64) -> Self {
+        self.latency_tolerance = tolerance.max(1.0);
+        self
+    }
+
+    /// Set the error rate at which the limit is cut
+    pub fn with_max_error_rate(mut self, rate: f64) -> Self {
+        self.max_error_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    fn clamp(&self, limit: usize) -> usize {
+        limit.clamp(self.min_limit, self.max_limit)
+    }
+}
+
+/// AIMD concurrency limit driven by latency and error samples
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrency {
+    config: AdaptiveConfig,
+    limit: usize,
+    samples: usize,
+    errors: usize,
+    total_latency_ms: u64,
+    /// Discard the current window, measured under a limit since cut
+    cooling_down: bool,
+}
+
+impl AdaptiveConcurrency {
+    /// Create a controller at the configured initial limit
+    pub fn new(config: AdaptiveConfig) -> Self {
+        Self {
+            limit: config.clamp(config.initial_limit),
+            config,
+            samples: 0,
+            errors: 0,
+            total_latency_ms: 0,
+            cooling_down: false,
+        }
+    }
+
+    /// Current limit
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &AdaptiveConfig {
+        &self.config
+    }
+
+    /// Record a completed step
+    ///
+    /// Returns the new limit if this sample completed a window that
+    /// changed it.
+    pub fn record(&mut self, latency: Duration, success: bool) -> Option<usize> {
+        self.samples += 1;
+        self.total_latency_ms += latency.as_millis() as u64;
+        if !success {
+            self.errors += 1;
+        }
+        if self.samples < self.config.window.max(1) {
+            return None;
+        }
+
+        let average_ms = self.total_latency_ms as f64 / self.samples as f64;
+        let error_rate = self.errors as f64 / self.samples as f64;
+        let cooling_down = self.cooling_down;
+        self.samples = 0;
+        self.errors = 0;
+        self.total_latency_ms = 0;
+        self.cooling_down = false;
+        if cooling_down {
+            return None;
+        }
+
+        let target = self.config.target_latency_ms as f64;
+        let next = if error_rate > self.config.max_error_rate
+            || average_ms > target * self.config.latency_tolerance
+        {
+            self.cooling_down = true;
+            self.config
+                .clamp((self.limit as f64 * self.config.backoff_ratio).floor() as usize)
+        } else if average_ms <= target {
+            self.config.clamp(self.limit + 1)
+        } else {
+            self.limit
+        };
+
+        if next == self.limit {
+            return None;
+        }
+
+        tracing::debug!(
+            from = self.limit,
+            to = next,
+            average_latency_ms = average_ms,
+            error_rate,
+            "Adjusted concurrency limit"
+        );
+        self.limit = next;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller() -> AdaptiveConcurrency {
+        AdaptiveConcurrency::new(
+            AdaptiveConfig::new(2, 8, Duration::from_millis(100)).with_window(5),
+        )
+    }
+
+    /// Feed `windows` full windows of one latency and return the limit
+    /// after each
+    fn feed(controller: &mut AdaptiveConcurrency, latency_ms: u64, windows: usize) -> Vec<usize> {
+        (0..windows)
+            .map(|_| {
+                for _ in 0..controller.config().window {
+                    controller.record(Duration::from_millis(latency_ms), true);
+                }
+                controller.limit()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_limit_rises_then_falls_with_latency() {
+        let mut controller = controller();
+        assert_eq!(controller.limit(), 2);
+
+        // Healthy latency grows the limit one step per window up to the max
+        assert_eq!(feed(&mut controller, 50, 8), [3, 4, 5, 6, 7, 8, 8, 8]);
+
+        // Degraded latency halves it, skipping the window after each cut,
+        // down to the min
+        assert_eq!(feed(&mut controller, 400, 5), [4, 4, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_latency_within_tolerance_holds_steady() {
+        let mut controller = controller();
+        feed(&mut controller, 50, 3);
+        assert_eq!(controller.limit(), 5);
+
+        // Between the target and the tolerance nothing changes
+        assert_eq!(feed(&mut controller, 130, 4), [5, 5, 5, 5]);
+    }
+
+    #[test]
+    fn test_error_spike_cuts_limit() {
+        let mut controller = controller();
+        feed(&mut controller, 50, 4);
+        assert_eq!(controller.limit(), 6);
+
+        for i in 0..5 {
+            controller.record(Duration::from_millis(50), i % 2 == 0);
+        }
+        assert_eq!(controller.limit(), 3);
+    }
+}
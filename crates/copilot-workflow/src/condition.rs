@@ -0,0 +1,193 @@
+//! Condition expressions
+//!
+//! A condition step picks a branch by evaluating its expression against
+//! the execution's variables. An expression tests a variable, `approved`
+//! or `!approved`, or compares one with a literal, `attempts >= 3` or
+//! `status == "healthy"`. Tests combine with `&&` and `||`, `&&` binding
+//! tighter; there are no parentheses.
+//!
+//! Variables are dotted paths: shared state by key, `deploy.region`, and
+//! outputs of finished steps under `steps`, `steps.check.status`. A
+//! variable that does not exist is `null`. Literals are JSON, and a bare
+//! word that is not JSON is a string.
+
+use crate::{Result, WorkflowError};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Comparison operators, two-character operators first so `>=` is not
+/// read as `>`
+const OPERATORS: [&str; 6] = ["==", "!=", ">=", "<=", ">", "<"];
+
+/// Evaluate a condition expression against variables
+///
+/// Fails only if the expression cannot be parsed; missing variables are
+/// `null` and compare unequal to anything else.
+pub fn evaluate(expression: &str, variables: &Value) -> Result<bool> {
+    let mut any = false;
+    for clause in split_outside_quotes(expression, "||") {
+        let mut all = true;
+        for test in split_outside_quotes(clause, "&&") {
+            // Evaluate every test so a malformed one is always reported
+            all &= evaluate_test(expression, test.trim(), variables)?;
+        }
+        any |= all;
+    }
+    Ok(any)
+}
+
+/// Outputs of a condition step: its result and the branch taken
+pub fn branch_outputs(
+    result: bool,
+    true_steps: &[String],
+    false_steps: &[String],
+) -> HashMap<String, Value> {
+    let next_steps = if result { true_steps } else { false_steps };
+    HashMap::from([
+        ("condition_result".to_string(), Value::Bool(result)),
+        ("next_steps".to_string(), serde_json::json!(next_steps)),
+    ])
+}
+
+fn evaluate_test(expression: &str, test: &str, variables: &Value) -> Result<bool> {
+    if test.is_empty() {
+        return Err(invalid(expression, "empty test"));
+    }
+
+    if let Some((position, operator)) = find_operator(test) {
+        let variable = test[..position].trim();
+        let literal = test[position + operator.len()..].trim();
+        if variable.is_empty() || literal.is_empty() {
+            return Err(invalid(expression, &format!("incomplete comparison \"{}\"", test)));
+        }
+
+        let left = lookup(variables, variable);
+        let right = parse_literal(literal);
+        return Ok(match operator {
+            "==" => left == right,
+            "!=" => left != right,
+            ">=" => compare(&left, &right).is_some_and(Ordering::is_ge),
+            "<=" => compare(&left, &right).is_some_and(Ordering::is_le),
+            ">" => compare(&left, &right).is_some_and(Ordering::is_gt),
+            _ => compare(&left, &right).is_some_and(Ordering::is_lt),
+        });
+    }
+
+    match test.strip_prefix('!') {
+        Some(negated) => Ok(!is_truthy(&operand(negated.trim(), variables))),
+        None => Ok(is_truthy(&operand(test, variables))),
+    }
+}
+
+/// A boolean literal, or else a variable
+fn operand(token: &str, variables: &Value) -> Value {
+    match token {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => lookup(variables, token),
+    }
+}
+
+/// Value at a dotted path, `null` if there is none
+fn lookup(variables: &Value, path: &str) -> Value {
+    let pointer: String = path
+        .split('.')
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect();
+    variables.pointer(&pointer).cloned().unwrap_or(Value::Null)
+}
+
+fn parse_literal(literal: &str) -> Value {
+    serde_json::from_str(literal).unwrap_or_else(|_| Value::String(literal.to_string()))
+}
+
+/// Order numbers by value and strings lexically; other values are unordered
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64()?.partial_cmp(&r.as_f64()?),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    }
+}
+
+/// First comparison operator outside a string literal
+fn find_operator(test: &str) -> Option<(usize, &'static str)> {
+    let mut quoted = false;
+    for (position, c) in test.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if quoted {
+            continue;
+        }
+        let rest = &test[position..];
+        if let Some(operator) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            return Some((position, operator));
+        }
+    }
+    None
+}
+
+fn split_outside_quotes<'a>(expression: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (position, c) in expression.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if !quoted && position >= start && expression[position..].starts_with(separator) {
+            parts.push(&expression[start..position]);
+            start = position + separator.len();
+        }
+    }
+    parts.push(&expression[start..]);
+    parts
+}
+
+fn invalid(expression: &str, reason: &str) -> WorkflowError {
+    WorkflowError::InvalidDefinition(format!("Invalid condition \"{}\": {}", expression, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_evaluate_expressions() {
+        let variables = json!({
+            "approved": true,
+            "attempts": 3,
+            "region": "eu-west",
+            "steps": { "check": { "status": "healthy", "errors": [] } },
+        });
+
+        assert!(evaluate("approved", &variables).unwrap());
+        assert!(!evaluate("!approved", &variables).unwrap());
+        assert!(evaluate("attempts >= 3", &variables).unwrap());
+        assert!(!evaluate("attempts > 3", &variables).unwrap());
+        assert!(evaluate("region == eu-west", &variables).unwrap());
+        assert!(evaluate("steps.check.status == \"healthy\"", &variables).unwrap());
+        assert!(!evaluate("steps.check.errors", &variables).unwrap());
+        assert!(evaluate("steps.check.errors || attempts < 5 && approved", &variables).unwrap());
+        assert!(!evaluate("approved && missing", &variables).unwrap());
+        assert!(evaluate("missing == null", &variables).unwrap());
+        assert!(evaluate("region != \"a && b\"", &variables).unwrap());
+
+        assert!(evaluate("approved &&", &variables).is_err());
+        assert!(evaluate("attempts >", &variables).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 //! Directed Acyclic Graph (DAG) for workflow execution order
 
-use crate::step::WorkflowStep;
+use crate::step::{StepAction, WorkflowStep};
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::DfsPostOrder;
 use petgraph::Direction;
@@ -19,6 +19,12 @@ pub enum DagValidationError {
         dependency: String,
     },
 
+    #[error("Unknown branch target: condition {step} branches to {target} which does not exist")]
+    UnknownBranchTarget {
+        step: String,
+        target: String,
+    },
+
     #[error("Empty workflow: no steps defined")]
     EmptyWorkflow,
 
@@ -44,11 +50,16 @@ pub struct WorkflowDag {
 
 impl WorkflowDag {
     /// Create a new workflow DAG from steps
-    pub fn new(steps: Vec<WorkflowStep>) -> Result<Self, DagValidationError> {
+    ///
+    /// Steps on a branch of a condition step depend on the condition, so
+    /// they are not ready until it has picked a branch.
+    pub fn new(mut steps: Vec<WorkflowStep>) -> Result<Self, DagValidationError> {
         if steps.is_empty() {
             return Err(DagValidationError::EmptyWorkflow);
         }
 
+        Self::add_branch_dependencies(&mut steps)?;
+
         let mut graph = DiGraph::new();
         let mut step_to_node = HashMap::new();
         let mut node_to_step = HashMap::new();
@@ -99,6 +110,32 @@ impl WorkflowDag {
         Ok(dag)
     }
 
+    /// Make every branch target of a condition step depend on it
+    fn add_branch_dependencies(steps: &mut [WorkflowStep]) -> Result<(), DagValidationError> {
+        let mut branches = Vec::new();
+        for step in steps.iter() {
+            if let StepAction::Condition { true_steps, false_steps, .. } = &step.action {
+                for target in true_steps.iter().chain(false_steps) {
+                    branches.push((step.id.clone(), target.clone()));
+                }
+            }
+        }
+
+        for (condition, target) in branches {
+            let step = steps.iter_mut().find(|s| s.id == target).ok_or_else(|| {
+                DagValidationError::UnknownBranchTarget {
+                    step: condition.clone(),
+                    target: target.clone(),
+                }
+            })?;
+            if !step.dependencies.contains(&condition) {
+                step.dependencies.push(condition);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Validate the DAG for cycles and other issues
     pub fn validate(&self) -> Result<(), DagValidationError> {
         // Check for cycles using DFS
@@ -306,6 +343,108 @@ impl WorkflowDag {
             .collect()
     }
 
+    /// Add a step to the DAG
+    ///
+    /// The DAG is re-validated and left unchanged if the new step has a
+    /// duplicate ID, a missing dependency, or introduces a cycle.
+    pub fn add_step(&mut self, step: WorkflowStep) -> Result<(), DagValidationError> {
+        if self.steps.contains_key(&step.id) {
+            return Err(DagValidationError::DuplicateStepId(step.id));
+        }
+
+        let mut steps = self.cloned_steps();
+        steps.push(step);
+        self.rebuild(steps)
+    }
+
+    /// Remove a step from the DAG, returning the IDs of all removed steps
+    ///
+    /// A step that other steps depend on is rejected with
+    /// `MissingDependency` unless `cascade` is set, in which case all
+    /// transitive dependents are removed as well.
+    pub fn remove_step(
+        &mut self,
+        step_id: &str,
+        cascade: bool,
+    ) -> Result<Vec<String>, DagValidationError> {
+        if !self.steps.contains_key(step_id) {
+            return Err(DagValidationError::InvalidStep(format!(
+                "step {} does not exist",
+                step_id
+            )));
+        }
+
+        let mut removed = vec![step_id.to_string()];
+        if cascade {
+            let mut stack = vec![step_id.to_string()];
+            while let Some(current) = stack.pop() {
+                for dependent in self.get_dependents(&current) {
+                    if !removed.contains(&dependent) {
+                        removed.push(dependent.clone());
+                        stack.push(dependent);
+                    }
+                }
+            }
+        } else if let Some(dependent) = self.get_dependents(step_id).into_iter().min() {
+            return Err(DagValidationError::MissingDependency {
+                step: dependent,
+                dependency: step_id.to_string(),
+            });
+        }
+
+        let steps = self
+            .cloned_steps()
+            .into_iter()
+            .filter(|step| !removed.contains(&step.id))
+            .collect();
+        self.rebuild(steps)?;
+
+        Ok(removed)
+    }
+
+    /// Replace the dependencies of a step
+    ///
+    /// The DAG is re-validated and left unchanged if a dependency is missing
+    /// or the change introduces a cycle.
+    pub fn update_dependencies(
+        &mut self,
+        step_id: &str,
+        dependencies: Vec<String>,
+    ) -> Result<(), DagValidationError> {
+        if !self.steps.contains_key(step_id) {
+            return Err(DagValidationError::InvalidStep(format!(
+                "step {} does not exist",
+                step_id
+            )));
+        }
+
+        let steps = self
+            .cloned_steps()
+            .into_iter()
+            .map(|mut step| {
+                if step.id == step_id {
+                    step.dependencies = dependencies.clone();
+                }
+                step
+            })
+            .collect();
+        self.rebuild(steps)
+    }
+
+    /// Steps in graph insertion order, so rebuilds keep a stable layout
+    fn cloned_steps(&self) -> Vec<WorkflowStep> {
+        self.graph
+            .node_indices()
+            .map(|node| self.steps[&self.node_to_step[&node]].clone())
+            .collect()
+    }
+
+    /// Replace this DAG with one built from `steps`, only if it validates
+    fn rebuild(&mut self, steps: Vec<WorkflowStep>) -> Result<(), DagValidationError> {
+        *self = Self::new(steps)?;
+        Ok(())
+    }
+
     /// Get number of steps
     pub fn len(&self) -> usize {
         self.steps.len()
@@ -355,6 +494,41 @@ mod tests {
         assert!(matches!(result, Err(DagValidationError::CycleDetected(_))));
     }
 
+    #[test]
+    fn test_condition_branches_depend_on_condition() {
+        let condition = |true_steps: &[&str], false_steps: &[&str]| {
+            WorkflowStep::new(
+                "Check",
+                StepType::Condition,
+                StepAction::Condition {
+                    expression: "healthy".to_string(),
+                    true_steps: true_steps.iter().map(|s| s.to_string()).collect(),
+                    false_steps: false_steps.iter().map(|s| s.to_string()).collect(),
+                },
+            )
+            .with_id("check")
+        };
+
+        let dag = WorkflowDag::new(vec![
+            condition(&["deploy"], &["rollback"]),
+            create_test_step("deploy", "Deploy", vec![]),
+            create_test_step("rollback", "Rollback", vec![]),
+        ])
+        .unwrap();
+        assert_eq!(dag.get_dependencies("deploy"), vec!["check".to_string()]);
+        assert_eq!(dag.get_dependencies("rollback"), vec!["check".to_string()]);
+        assert_eq!(dag.get_root_steps(), vec!["check".to_string()]);
+
+        let result = WorkflowDag::new(vec![
+            condition(&["deploy"], &["missing"]),
+            create_test_step("deploy", "Deploy", vec![]),
+        ]);
+        assert!(matches!(
+            result,
+            Err(DagValidationError::UnknownBranchTarget { target, .. }) if target == "missing"
+        ));
+    }
+
     #[test]
     fn test_missing_dependency() {
         let steps = vec![
@@ -413,4 +587,70 @@ mod tests {
         assert!(ready.contains(&"step2".to_string()));
         assert!(ready.contains(&"step3".to_string()));
     }
+
+    #[test]
+    fn test_add_step() {
+        let mut dag = WorkflowDag::new(vec![create_test_step("step1", "Step 1", vec![])]).unwrap();
+
+        dag.add_step(create_test_step("step2", "Step 2", vec!["step1".to_string()]))
+            .unwrap();
+
+        assert_eq!(dag.len(), 2);
+        assert_eq!(dag.get_dependents("step1"), vec!["step2".to_string()]);
+
+        let result = dag.add_step(create_test_step("step2", "Step 2", vec![]));
+        assert!(matches!(result, Err(DagValidationError::DuplicateStepId(_))));
+
+        let result = dag.add_step(create_test_step("step3", "Step 3", vec!["missing".to_string()]));
+        assert!(matches!(result, Err(DagValidationError::MissingDependency { .. })));
+        assert_eq!(dag.len(), 2);
+    }
+
+    #[test]
+    fn test_cycle_introducing_edits_are_rejected() {
+        let mut dag = WorkflowDag::new(vec![
+            create_test_step("step1", "Step 1", vec![]),
+            create_test_step("step2", "Step 2", vec!["step1".to_string()]),
+        ])
+        .unwrap();
+
+        let result = dag.add_step(create_test_step("step3", "Step 3", vec!["step3".to_string()]));
+        assert!(matches!(result, Err(DagValidationError::CycleDetected(_))));
+
+        let result = dag.update_dependencies("step1", vec!["step2".to_string()]);
+        assert!(matches!(result, Err(DagValidationError::CycleDetected(_))));
+
+        // The DAG is unchanged after the rejected edits
+        assert_eq!(dag.len(), 2);
+        assert!(dag.get_dependencies("step1").is_empty());
+        assert_eq!(dag.topological_sort(), vec!["step1".to_string(), "step2".to_string()]);
+    }
+
+    #[test]
+    fn test_remove_depended_on_step() {
+        let mut dag = WorkflowDag::new(vec![
+            create_test_step("step1", "Step 1", vec![]),
+            create_test_step("step2", "Step 2", vec!["step1".to_string()]),
+            create_test_step("step3", "Step 3", vec!["step2".to_string()]),
+            create_test_step("step4", "Step 4", vec!["step1".to_string()]),
+        ])
+        .unwrap();
+
+        let result = dag.remove_step("step2", false);
+        assert!(matches!(
+            result,
+            Err(DagValidationError::MissingDependency { ref step, ref dependency })
+                if step == "step3" && dependency == "step2"
+        ));
+        assert_eq!(dag.len(), 4);
+
+        let mut removed = dag.remove_step("step2", true).unwrap();
+        removed.sort();
+        assert_eq!(removed, vec!["step2".to_string(), "step3".to_string()]);
+        assert_eq!(dag.len(), 2);
+
+        dag.remove_step("step4", false).unwrap();
+        assert_eq!(dag.len(), 1);
+        assert!(dag.get_dependents("step1").is_empty());
+    }
 }
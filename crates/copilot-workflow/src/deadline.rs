@@ -0,0 +1,120 @@
+//! Workflow deadlines
+//!
+//! A workflow's `timeout_secs` is an end-to-end budget. Each step may run
+//! for at most `min(step timeout, time left before the deadline)`, so a
+//! slow early step shrinks the budget of the steps after it. Time spent
+//! paused does not count against the budget.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// What limited a step that timed out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutCause {
+    /// The step's own timeout
+    Step,
+    /// The workflow deadline
+    Deadline,
+    /// The step's wait for an external event
+    Event,
+}
+
+impl std::fmt::Display for TimeoutCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeoutCause::Step => write!(f, "step timeout"),
+            TimeoutCause::Deadline => write!(f, "workflow deadline"),
+            TimeoutCause::Event => write!(f, "event timeout"),
+        }
+    }
+}
+
+/// End-to-end time budget of an execution, excluding paused time
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    budget: Duration,
+    started: Instant,
+    paused_total: Duration,
+    paused_at: Option<Instant>,
+}
+
+impl Deadline {
+    /// Start a deadline `budget` from now
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            started: Instant::now(),
+            paused_total: Duration::ZERO,
+            paused_at: None,
+        }
+    }
+
+    /// Stop the clock
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    /// Restart the clock
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += paused_at.elapsed();
+        }
+    }
+
+    /// Time counted against the budget so far
+    pub fn elapsed(&self) -> Duration {
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        now.saturating_duration_since(self.started)
+            .saturating_sub(self.paused_total)
+    }
+
+    /// Time left before the deadline
+    pub fn remaining(&self) -> Duration {
+        self.budget.saturating_sub(self.elapsed())
+    }
+
+    /// Effective time limit of a step and what imposes it
+    ///
+    /// The step's own timeout wins ties.
+    pub fn step_limit(&self, step_timeout: Option<Duration>) -> (Duration, TimeoutCause) {
+        let remaining = self.remaining();
+        match step_timeout {
+            Some(timeout) if timeout <= remaining => (timeout, TimeoutCause::Step),
+            _ => (remaining, TimeoutCause::Deadline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paused_time_is_not_counted() {
+        let mut deadline = Deadline::new(Duration::from_secs(10));
+        tokio::time::sleep(Duration::from_secs(3)).await;
+
+        deadline.pause();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(deadline.remaining(), Duration::from_secs(7));
+        deadline.resume();
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(deadline.elapsed(), Duration::from_secs(5));
+        assert_eq!(
+            deadline.step_limit(Some(Duration::from_secs(8))),
+            (Duration::from_secs(5), TimeoutCause::Deadline)
+        );
+        assert_eq!(
+            deadline.step_limit(Some(Duration::from_secs(4))),
+            (Duration::from_secs(4), TimeoutCause::Step)
+        );
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+    }
+}
@@ -1,14 +1,22 @@
 //! Workflow engine with state machine and execution control
 
 use crate::approval::{ApprovalGate, ApprovalRequest, ApprovalStatus};
+use crate::condition;
 use crate::dag::WorkflowDag;
+use crate::deadline::{Deadline, TimeoutCause};
 use crate::execution::{DefaultStepExecutor, ExecutionContext, StepExecutor};
-use crate::step::{StepResult, StepState, WorkflowStep};
+use crate::export::{ExecutionBundle, RedactionPolicy};
+use crate::priority::{Priority, SchedulerConfig, StepScheduler};
+use crate::schema;
+use crate::step::{HeartbeatConfig, StepAction, StepResult, StepState, WorkflowStep};
 use crate::{Result, WorkflowError};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
+use tokio::time::Duration;
 use uuid::Uuid;
 
 /// Status of a workflow execution
@@ -46,6 +54,10 @@ pub struct WorkflowState {
     pub failed_steps: HashSet<String>,
     /// Skipped step IDs
     pub skipped_steps: HashSet<String>,
+    /// Skipped steps that were on a branch a condition did not take, or
+    /// only reachable through one; a subset of `skipped_steps`
+    #[serde(default)]
+    pub branch_skipped_steps: HashSet<String>,
     /// Step results
     pub step_results: HashMap<String, StepResult>,
     /// Pending approval IDs
@@ -56,6 +68,13 @@ pub struct WorkflowState {
     pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
     /// Error message if failed
     pub error: Option<String>,
+    /// Execution that started this one as a subworkflow
+    #[serde(default)]
+    pub parent_execution_id: Option<String>,
+    /// Results of the compensating actions run by a rollback, in the
+    /// order they ran
+    #[serde(default)]
+    pub compensations: Vec<StepResult>,
 }
 
 impl WorkflowState {
@@ -69,11 +88,14 @@ impl WorkflowState {
             running_steps: HashSet::new(),
             failed_steps: HashSet::new(),
             skipped_steps: HashSet::new(),
+            branch_skipped_steps: HashSet::new(),
             step_results: HashMap::new(),
             pending_approvals: Vec::new(),
             started_at: None,
             completed_at: None,
             error: None,
+            parent_execution_id: None,
+            compensations: Vec::new(),
         }
     }
 
@@ -111,7 +133,14 @@ pub struct WorkflowDefinition {
     #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
     /// Maximum execution time in seconds
+    ///
+    /// Propagated to steps as a deadline: no step runs past it, and time
+    /// spent paused does not count.
     pub timeout_secs: Option<u64>,
+    /// Run the compensating actions of completed steps if the workflow
+    /// fails
+    #[serde(default)]
+    pub rollback_on_failure: bool,
 }
 
 impl WorkflowDefinition {
@@ -124,6 +153,7 @@ impl WorkflowDefinition {
             steps: Vec::new(),
             metadata: HashMap::new(),
             timeout_secs: None,
+            rollback_on_failure: false,
         }
     }
 
@@ -145,6 +175,14 @@ impl WorkflowDefinition {
         self
     }
 
+    /// Roll back completed steps if the workflow fails
+    ///
+    /// See [`WorkflowStep::with_compensating_action`].
+    pub fn with_rollback_on_failure(mut self) -> Self {
+        self.rollback_on_failure = true;
+        self
+    }
+
     /// Validate the workflow definition
     pub fn validate(&self) -> Result<()> {
         if self.steps.is_empty() {
@@ -156,10 +194,27 @@ impl WorkflowDefinition {
         // Create DAG to validate structure
         WorkflowDag::new(self.steps.clone())?;
 
+        for step in &self.steps {
+            if let Some(output_schema) = &step.output_schema {
+                schema::check_schema(output_schema).map_err(|violation| {
+                    WorkflowError::InvalidDefinition(format!(
+                        "Output schema of step {} is not supported at {}",
+                        step.id, violation
+                    ))
+                })?;
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Default limit on how deeply subworkflows may nest
+pub const DEFAULT_MAX_SUBWORKFLOW_DEPTH: usize = 8;
+
+/// Default time a rollback waits for running steps before compensating
+pub const DEFAULT_ROLLBACK_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Workflow engine
 #[derive(Clone)]
 pub struct WorkflowEngine {
@@ -169,6 +224,16 @@ pub struct WorkflowEngine {
     approval_gate: Arc<ApprovalGate>,
     /// Step executor
     executor: Arc<dyn StepExecutor>,
+    /// Workflow definitions registered with `create_workflow`
+    definitions: Arc<RwLock<HashMap<String, WorkflowDefinition>>>,
+    /// Admits steps by priority under the concurrency limit
+    scheduler: Arc<StepScheduler>,
+    /// Redacts step outputs in exported executions
+    redaction: Arc<RedactionPolicy>,
+    /// Deepest allowed nesting of subworkflow executions
+    max_subworkflow_depth: usize,
+    /// Longest a rollback waits for running steps
+    rollback_timeout: Duration,
 }
 
 /// Internal workflow execution state
@@ -178,6 +243,16 @@ struct WorkflowExecution {
     state: WorkflowState,
     context: ExecutionContext,
     cancel_flag: Arc<RwLock<bool>>,
+    priority: Priority,
+    ticket: u64,
+    /// End-to-end deadline from the definition's timeout
+    deadline: Option<Deadline>,
+    /// Set once the execution loop has finished
+    finished: Arc<watch::Sender<bool>>,
+    /// Payloads of the external events signalled so far, by event key
+    events: Arc<watch::Sender<HashMap<String, serde_json::Value>>>,
+    /// Number of subworkflow executions this one is nested in
+    depth: usize,
 }
 
 impl Default for WorkflowEngine {
@@ -193,6 +268,11 @@ impl WorkflowEngine {
             executions: Arc::new(RwLock::new(HashMap::new())),
             approval_gate: Arc::new(ApprovalGate::new()),
             executor: Arc::new(DefaultStepExecutor::new()),
+            definitions: Arc::new(RwLock::new(HashMap::new())),
+            scheduler: StepScheduler::new(SchedulerConfig::default()),
+            redaction: Arc::new(RedactionPolicy::default()),
+            max_subworkflow_depth: DEFAULT_MAX_SUBWORKFLOW_DEPTH,
+            rollback_timeout: DEFAULT_ROLLBACK_TIMEOUT,
         }
     }
 
@@ -202,9 +282,46 @@ impl WorkflowEngine {
             executions: Arc::new(RwLock::new(HashMap::new())),
             approval_gate: Arc::new(ApprovalGate::new()),
             executor,
+            definitions: Arc::new(RwLock::new(HashMap::new())),
+            scheduler: StepScheduler::new(SchedulerConfig::default()),
+            redaction: Arc::new(RedactionPolicy::default()),
+            max_subworkflow_depth: DEFAULT_MAX_SUBWORKFLOW_DEPTH,
+            rollback_timeout: DEFAULT_ROLLBACK_TIMEOUT,
         }
     }
 
+    /// Limit concurrent steps across all executions
+    ///
+    /// Steps beyond the limit wait and are admitted by execution priority.
+    /// With an adaptive configuration the limit follows step latencies.
+    pub fn with_scheduler(mut self, config: SchedulerConfig) -> Self {
+        self.scheduler = StepScheduler::new(config);
+        self
+    }
+
+    /// Set the policy used to redact exported executions
+    pub fn with_redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.redaction = Arc::new(policy);
+        self
+    }
+
+    /// Limit how deeply subworkflows may nest
+    ///
+    /// A subworkflow step that would exceed the limit fails, which also
+    /// stops workflows that embed themselves.
+    pub fn with_max_subworkflow_depth(mut self, depth: usize) -> Self {
+        self.max_subworkflow_depth = depth;
+        self
+    }
+
+    /// Limit how long a rollback waits for running steps
+    ///
+    /// The wait also ends at the workflow deadline, if that comes first.
+    pub fn with_rollback_timeout(mut self, timeout: Duration) -> Self {
+        self.rollback_timeout = timeout;
+        self
+    }
+
     /// Create and validate a workflow
     pub async fn create_workflow(&self, definition: WorkflowDefinition) -> Result<String> {
         // Validate the definition
@@ -217,11 +334,47 @@ impl WorkflowEngine {
             "Workflow created"
         );
 
-        Ok(definition.id.clone())
+        let workflow_id = definition.id.clone();
+        self.definitions.write().await.insert(workflow_id.clone(), definition);
+        Ok(workflow_id)
     }
 
     /// Execute a workflow
     pub async fn execute_workflow(&self, definition: WorkflowDefinition) -> Result<String> {
+        self.start_execution(definition, Priority::default(), None).await
+    }
+
+    /// Execute a workflow registered with `create_workflow` at a priority
+    ///
+    /// When the engine's concurrency limit is reached, ready steps of
+    /// higher-priority executions are admitted first.
+    pub async fn execute_with_priority(
+        &self,
+        workflow_id: &str,
+        priority: impl Into<Priority>,
+    ) -> Result<String> {
+        let definition = self
+            .definitions
+            .read()
+            .await
+            .get(workflow_id)
+            .cloned()
+            .ok_or_else(|| WorkflowError::NotFound(workflow_id.to_string()))?;
+
+        self.start_execution(definition, priority.into(), None).await
+    }
+
+    /// Start an execution of a workflow definition
+    ///
+    /// A subworkflow execution is started with its parent's ID and the
+    /// state it starts from. It runs within whatever is left of its
+    /// parent's deadline, if that is tighter than its own timeout.
+    async fn start_execution(
+        &self,
+        definition: WorkflowDefinition,
+        priority: Priority,
+        parent: Option<(&str, HashMap<String, serde_json::Value>)>,
+    ) -> Result<String> {
         let workflow_id = definition.id.clone();
 
         // Validate
@@ -229,6 +382,7 @@ impl WorkflowEngine {
 
         // Create DAG
         let dag = WorkflowDag::new(definition.steps.clone())?;
+        let timeout_secs = definition.timeout_secs;
 
         // Create execution
         let execution_id = Uuid::new_v4().to_string();
@@ -238,6 +392,27 @@ impl WorkflowEngine {
 
         let context = ExecutionContext::new(&workflow_id, &execution_id);
         let cancel_flag = Arc::new(RwLock::new(false));
+        let mut deadline = timeout_secs.map(|secs| Deadline::new(Duration::from_secs(secs)));
+
+        let mut depth = 0;
+        if let Some((parent_id, inputs)) = parent {
+            let parent_deadline;
+            (depth, parent_deadline) = {
+                let executions = self.executions.read().await;
+                let parent = executions.get(parent_id)
+                    .ok_or_else(|| WorkflowError::NotFound(parent_id.to_string()))?;
+                (parent.depth + 1, parent.deadline)
+            };
+            if let Some(remaining) = parent_deadline.map(|deadline| deadline.remaining()) {
+                if deadline.map_or(true, |own| remaining < own.remaining()) {
+                    deadline = Some(Deadline::new(remaining));
+                }
+            }
+            state.parent_execution_id = Some(parent_id.to_string());
+            for (key, value) in inputs {
+                context.set_state(key, value).await;
+            }
+        }
 
         let execution = WorkflowExecution {
             definition,
@@ -245,7 +420,14 @@ impl WorkflowEngine {
             state,
             context,
             cancel_flag: cancel_flag.clone(),
+            priority,
+            ticket: self.scheduler.ticket(),
+            deadline,
+            finished: Arc::new(watch::channel(false).0),
+            events: Arc::new(watch::channel(HashMap::new()).0),
+            depth,
         };
+        let finished = Arc::clone(&execution.finished);
 
         // Store execution
         {
@@ -256,6 +438,8 @@ impl WorkflowEngine {
         tracing::info!(
             workflow_id = %workflow_id,
             execution_id = %execution_id,
+            priority = priority.0,
+            depth,
             "Workflow execution started"
         );
 
@@ -270,6 +454,7 @@ impl WorkflowEngine {
                     "Workflow execution failed"
                 );
             }
+            finished.send_replace(true);
         });
 
         Ok(execution_id)
@@ -292,13 +477,46 @@ impl WorkflowEngine {
                 break;
             }
 
+            // A failed step that fails the workflow ends the execution
+            let failed = {
+                let executions = self.executions.read().await;
+                let execution = executions.get(execution_id)
+                    .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+                execution.state.status == WorkflowStatus::Failed
+            };
+
+            if failed {
+                self.roll_back(execution_id).await?;
+                break;
+            }
+
+            // A paused execution lets running steps finish but starts no new ones
+            let paused = {
+                let executions = self.executions.read().await;
+                let execution = executions.get(execution_id)
+                    .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+                execution.state.status == WorkflowStatus::Paused
+            };
+
+            if paused {
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                continue;
+            }
+
             // Get ready steps
             let ready_steps = {
                 let executions = self.executions.read().await;
                 let execution = executions.get(execution_id)
                     .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
 
-                execution.dag.get_ready_steps(&execution.state.completed_steps)
+                // A join after a branch waits only for the branch taken
+                let satisfied: HashSet<String> = execution
+                    .state
+                    .completed_steps
+                    .union(&execution.state.branch_skipped_steps)
+                    .cloned()
+                    .collect();
+                execution.dag.get_ready_steps(&satisfied)
             };
 
             // Filter out already running or completed steps
@@ -321,12 +539,17 @@ impl WorkflowEngine {
             if steps_to_run.is_empty() {
                 // Check if workflow is complete
                 let is_complete = {
-                    let executions = self.executions.read().await;
-                    let execution = executions.get(execution_id)
+                    let mut executions = self.executions.write().await;
+                    let execution = executions.get_mut(execution_id)
                         .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
 
+                    Self::skip_blocked_steps(execution);
                     execution.state.running_steps.is_empty()
-                        && execution.dag.get_ready_steps(&execution.state.completed_steps).is_empty()
+                        && execution.definition.steps.iter().all(|step| {
+                            execution.state.completed_steps.contains(&step.id)
+                                || execution.state.failed_steps.contains(&step.id)
+                                || execution.state.skipped_steps.contains(&step.id)
+                        })
                 };
 
                 if is_complete {
@@ -363,6 +586,193 @@ impl WorkflowEngine {
         Ok(())
     }
 
+    /// Undo the completed steps of a failed execution that rolls back
+    ///
+    /// Waits for steps still running, up to the rollback timeout or the
+    /// workflow deadline, then runs the compensating actions of the steps
+    /// that had completed when the execution failed, in reverse
+    /// topological order through the step executor. Steps still running
+    /// are told to stop through the cancel flag. A failed compensation is
+    /// recorded and the rollback goes on with the remaining steps.
+    async fn roll_back(&self, execution_id: &str) -> Result<()> {
+        let (completed, limit) = {
+            let executions = self.executions.read().await;
+            let execution = executions.get(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+            if !execution.definition.rollback_on_failure {
+                return Ok(());
+            }
+            let limit = execution.deadline.map_or(self.rollback_timeout, |deadline| {
+                deadline.remaining().min(self.rollback_timeout)
+            });
+            (execution.state.completed_steps.clone(), limit)
+        };
+
+        let waiting_since = tokio::time::Instant::now();
+        let (compensations, context) = loop {
+            let executions = self.executions.read().await;
+            let execution = executions.get(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+
+            let running = &execution.state.running_steps;
+            if running.is_empty() || waiting_since.elapsed() >= limit {
+                if !running.is_empty() {
+                    tracing::warn!(
+                        execution_id = %execution_id,
+                        running = ?running,
+                        "Rolling back without waiting further for running steps"
+                    );
+                    *execution.cancel_flag.write().await = true;
+                }
+
+                let compensations: Vec<WorkflowStep> = execution
+                    .dag
+                    .topological_sort()
+                    .iter()
+                    .rev()
+                    .filter(|id| completed.contains(*id))
+                    .filter_map(|id| execution.dag.get_step(id))
+                    .filter_map(|step| {
+                        let action = step.compensating_action.clone()?;
+                        let mut compensation = step.clone();
+                        compensation.id = format!("{}:compensate", step.id);
+                        compensation.action = action;
+                        compensation.compensating_action = None;
+                        Some(compensation)
+                    })
+                    .collect();
+                break (compensations, execution.context.clone());
+            }
+            drop(executions);
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        };
+
+        tracing::info!(
+            execution_id = %execution_id,
+            compensations = compensations.len(),
+            "Rolling back workflow"
+        );
+
+        for compensation in compensations {
+            let result = match self.run_step(&compensation, &context).await {
+                Ok(result) => result,
+                Err(e) => StepResult::pending(compensation.id.clone()).fail(e.to_string()),
+            };
+            if !result.is_success() {
+                tracing::warn!(
+                    execution_id = %execution_id,
+                    step_id = %compensation.id,
+                    error = ?result.error,
+                    "Compensation failed"
+                );
+            }
+
+            let mut executions = self.executions.write().await;
+            let execution = executions.get_mut(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+            execution.state.compensations.push(result);
+        }
+
+        Ok(())
+    }
+
+    /// Whether an execution has already failed or been cancelled, so a
+    /// step that ends later does not replace its status or error
+    fn has_ended(state: &WorkflowState) -> bool {
+        matches!(state.status, WorkflowStatus::Failed | WorkflowStatus::Cancelled)
+    }
+
+    /// Skip pending steps that depend on a failed or skipped step
+    ///
+    /// Such steps can never become ready, so without this a workflow with a
+    /// non-fatal step failure would never finish. Skipping propagates to
+    /// their dependents on later passes.
+    fn skip_blocked_steps(execution: &mut WorkflowExecution) {
+        let state = &mut execution.state;
+        for step in &execution.definition.steps {
+            let pending = !state.completed_steps.contains(&step.id)
+                && !state.running_steps.contains(&step.id)
+                && !state.failed_steps.contains(&step.id)
+                && !state.skipped_steps.contains(&step.id);
+            // The DAG's dependencies include those on condition steps.
+            // Steps skipped by branching do not block their joins.
+            let blocked = execution.dag.get_dependencies(&step.id).iter().any(|dep| {
+                state.failed_steps.contains(dep)
+                    || (state.skipped_steps.contains(dep) && !state.branch_skipped_steps.contains(dep))
+            });
+
+            if pending && blocked {
+                tracing::info!(
+                    execution_id = %state.execution_id,
+                    step_id = %step.id,
+                    "Skipping step with failed dependency"
+                );
+                state.skipped_steps.insert(step.id.clone());
+                state
+                    .step_results
+                    .insert(step.id.clone(), StepResult::pending(step.id.clone()).skip());
+            }
+        }
+    }
+
+    /// Skip the steps on the branch a completed condition did not take
+    ///
+    /// Steps on both branches still run. Steps that depend only on skipped
+    /// branch steps are skipped with them, while a join that also depends
+    /// on a step that can still run treats the skipped branch as satisfied.
+    fn skip_untaken_branch(execution: &mut WorkflowExecution, step: &WorkflowStep, result: &StepResult) {
+        let StepAction::Condition { true_steps, false_steps, .. } = &step.action else {
+            return;
+        };
+        let Some(taken) = result.outputs.get("condition_result").and_then(|v| v.as_bool()) else {
+            return;
+        };
+        let (taken_steps, untaken_steps) = if taken {
+            (true_steps, false_steps)
+        } else {
+            (false_steps, true_steps)
+        };
+
+        let mut untaken: Vec<String> = untaken_steps
+            .iter()
+            .filter(|id| !taken_steps.contains(id))
+            .cloned()
+            .collect();
+        let state = &mut execution.state;
+        while !untaken.is_empty() {
+            for step_id in &untaken {
+                tracing::info!(
+                    execution_id = %state.execution_id,
+                    condition = %step.id,
+                    step_id = %step_id,
+                    "Skipping step on untaken branch"
+                );
+                state.skipped_steps.insert(step_id.clone());
+                state.branch_skipped_steps.insert(step_id.clone());
+                state
+                    .step_results
+                    .insert(step_id.clone(), StepResult::pending(step_id.clone()).skip());
+            }
+
+            // Pending steps reachable only through the untaken branch
+            untaken = execution
+                .definition
+                .steps
+                .iter()
+                .filter(|s| {
+                    let deps = execution.dag.get_dependencies(&s.id);
+                    !deps.is_empty()
+                        && deps.iter().all(|dep| state.branch_skipped_steps.contains(dep))
+                        && !state.completed_steps.contains(&s.id)
+                        && !state.running_steps.contains(&s.id)
+                        && !state.failed_steps.contains(&s.id)
+                        && !state.skipped_steps.contains(&s.id)
+                })
+                .map(|s| s.id.clone())
+                .collect();
+        }
+    }
+
     /// Execute a single step
     async fn execute_step(&self, execution_id: &str, step_id: &str) -> Result<()> {
         // Mark step as running
@@ -373,8 +783,8 @@ impl WorkflowEngine {
             execution.state.running_steps.insert(step_id.to_string());
         }
 
-        // Get step and context
-        let (step, context) = {
+        // Get step, context and admission priority
+        let (step, context, priority, ticket) = {
             let executions = self.executions.read().await;
             let execution = executions.get(execution_id)
                 .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
@@ -385,11 +795,87 @@ impl WorkflowEngine {
                 ))?
                 .clone();
 
-            (step, execution.context.clone())
+            (step, execution.context.clone(), execution.priority, execution.ticket)
         };
 
-        // Execute step
-        let result = self.executor.execute_step(&step, &context).await?;
+        if let StepAction::WaitForEvent { event_key, timeout_secs } = &step.action {
+            let result = self
+                .wait_for_event(execution_id, &step, event_key, *timeout_secs, &context)
+                .await?;
+            return self.finish_step(execution_id, &step, result).await;
+        }
+
+        if let StepAction::Subworkflow { workflow_id, inputs } = &step.action {
+            let result = self
+                .run_subworkflow(execution_id, &step, workflow_id, inputs, priority, &context)
+                .await?;
+            return self.finish_step(execution_id, &step, result).await;
+        }
+
+        // Branching is the engine's job, so conditions never reach the executor
+        if let StepAction::Condition { expression, true_steps, false_steps } = &step.action {
+            let pending = StepResult::pending(step.id.clone());
+            let result = match condition::evaluate(expression, &context.variables().await) {
+                Ok(taken) => {
+                    let outputs = condition::branch_outputs(taken, true_steps, false_steps);
+                    context.set_step_outputs(&step.id, outputs.clone()).await;
+                    pending.complete(outputs)
+                }
+                Err(e) => pending.fail(e.to_string()),
+            };
+            return self.finish_step(execution_id, &step, result).await;
+        }
+
+        // Wait for admission, then execute step within its time limit
+        let permit = self.scheduler.acquire(priority, ticket).await;
+        let started = tokio::time::Instant::now();
+        let result = match self.step_limit(execution_id, &step).await? {
+            Some((limit, cause)) => {
+                match tokio::time::timeout(limit, self.run_step(&step, &context)).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        tracing::warn!(
+                            execution_id = %execution_id,
+                            step_id = %step_id,
+                            limit_ms = limit.as_millis() as u64,
+                            %cause,
+                            "Step timed out"
+                        );
+                        Ok(StepResult::pending(step.id.clone()).time_out(cause, limit))
+                    }
+                }
+            }
+            None => self.run_step(&step, &context).await,
+        };
+        drop(permit);
+        let success = result
+            .as_ref()
+            .is_ok_and(|r| !matches!(r.state, StepState::Failed | StepState::Stalled));
+        self.scheduler.record(started.elapsed(), success);
+        self.finish_step(execution_id, &step, result?).await
+    }
+
+    /// Validate a finished step's outputs and record its result
+    async fn finish_step(
+        &self,
+        execution_id: &str,
+        step: &WorkflowStep,
+        mut result: StepResult,
+    ) -> Result<()> {
+        let step_id = step.id.as_str();
+
+        // Hold completed steps to their output contract
+        if let (StepState::Completed, Some(schema)) = (&result.state, &step.output_schema) {
+            if let Err(violation) = schema::validate_outputs(schema, &result.outputs) {
+                tracing::warn!(
+                    execution_id = %execution_id,
+                    step_id = %step_id,
+                    %violation,
+                    "Step output does not match its schema"
+                );
+                result = result.fail(format!("Output does not match schema at {}", violation));
+            }
+        }
 
         // Update state
         {
@@ -402,11 +888,21 @@ impl WorkflowEngine {
             match result.state {
                 StepState::Completed => {
                     execution.state.completed_steps.insert(step_id.to_string());
+                    Self::skip_untaken_branch(execution, step, &result);
                 }
                 StepState::Failed => {
                     execution.state.failed_steps.insert(step_id.to_string());
 
-                    if step.fail_on_error {
+                    if step.fail_on_error && !Self::has_ended(&execution.state) {
+                        execution.state.status = WorkflowStatus::Failed;
+                        execution.state.error = result.error.clone();
+                        execution.state.completed_at = Some(chrono::Utc::now());
+                    }
+                }
+                StepState::Stalled => {
+                    execution.state.failed_steps.insert(step_id.to_string());
+
+                    if step.fail_on_error && !Self::has_ended(&execution.state) {
                         execution.state.status = WorkflowStatus::Failed;
                         execution.state.error = result.error.clone();
                         execution.state.completed_at = Some(chrono::Utc::now());
@@ -424,6 +920,272 @@ impl WorkflowEngine {
         Ok(())
     }
 
+    /// Suspend a `WaitForEvent` step until its event is signalled
+    ///
+    /// The step holds no scheduler slot while it waits. It fails at its
+    /// own timeout or the workflow deadline, whichever comes first, or
+    /// once the execution is cancelled.
+    async fn wait_for_event(
+        &self,
+        execution_id: &str,
+        step: &WorkflowStep,
+        event_key: &str,
+        timeout_secs: Option<u64>,
+        context: &ExecutionContext,
+    ) -> Result<StepResult> {
+        let mut waiting = StepResult::pending(step.id.clone());
+        waiting.state = StepState::WaitingEvent;
+
+        let (mut events, deadline, cancel_flag) = {
+            let mut executions = self.executions.write().await;
+            let execution = executions.get_mut(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+            execution.state.step_results.insert(step.id.clone(), waiting.clone());
+            (execution.events.subscribe(), execution.deadline, execution.cancel_flag.clone())
+        };
+
+        tracing::info!(
+            execution_id = %execution_id,
+            step_id = %step.id,
+            event_key,
+            "Waiting for event"
+        );
+
+        let timeout = timeout_secs.map(Duration::from_secs);
+        let limit = match deadline {
+            Some(deadline) => Some(deadline.step_limit(timeout)),
+            None => timeout.map(|timeout| (timeout, TimeoutCause::Step)),
+        };
+        let received = events.wait_for(|events| events.contains_key(event_key));
+        let limited = async {
+            match limit {
+                Some((limit, cause)) => tokio::time::timeout(limit, received)
+                    .await
+                    .map_err(|_| (limit, cause)),
+                None => Ok(received.await),
+            }
+        };
+        let received = tokio::select! {
+            received = limited => match received {
+                Ok(received) => received,
+                Err((limit, cause)) => {
+                    let cause = match cause {
+                        TimeoutCause::Step => TimeoutCause::Event,
+                        other => other,
+                    };
+                    tracing::warn!(
+                        execution_id = %execution_id,
+                        step_id = %step.id,
+                        event_key,
+                        %cause,
+                        "Timed out waiting for event"
+                    );
+                    let mut result = waiting.fail(format!(
+                        "Timed out after {}ms waiting for event \"{}\" ({})",
+                        limit.as_millis(),
+                        event_key,
+                        cause
+                    ));
+                    result.timed_out = Some(cause);
+                    return Ok(result);
+                }
+            },
+            _ = Self::cancel_requested(&cancel_flag) => {
+                tracing::info!(
+                    execution_id = %execution_id,
+                    step_id = %step.id,
+                    event_key,
+                    "Stopped waiting for event"
+                );
+                return Ok(waiting.fail(format!(
+                    "Cancelled while waiting for event \"{}\"",
+                    event_key
+                )));
+            }
+        };
+        let payload = received
+            .map_err(|_| WorkflowError::NotRunning(execution_id.to_string()))?[event_key]
+            .clone();
+
+        let outputs = match payload {
+            serde_json::Value::Object(fields) => fields.into_iter().collect(),
+            other => HashMap::from([("payload".to_string(), other)]),
+        };
+        context.set_step_outputs(&step.id, outputs.clone()).await;
+        Ok(waiting.complete(outputs))
+    }
+
+    /// Resolve once cancellation of the execution has been requested
+    async fn cancel_requested(cancel_flag: &RwLock<bool>) {
+        while !*cancel_flag.read().await {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Run a registered workflow as a child execution of a step
+    ///
+    /// The step holds no scheduler slot while the child runs, since the
+    /// child's own steps need slots. It fails if the workflow is not
+    /// registered, nesting would exceed the depth limit, or the child does
+    /// not complete.
+    ///
+    /// The future is boxed because the child's steps run through here in
+    /// turn, which would otherwise give it an infinitely recursive type.
+    fn run_subworkflow<'a>(
+        &'a self,
+        execution_id: &'a str,
+        step: &'a WorkflowStep,
+        workflow_id: &'a str,
+        inputs: &'a HashMap<String, serde_json::Value>,
+        priority: Priority,
+        context: &'a ExecutionContext,
+    ) -> Pin<Box<dyn Future<Output = Result<StepResult>> + Send + 'a>> {
+        Box::pin(async move {
+            let pending = StepResult::pending(step.id.clone());
+
+            let depth = {
+                let executions = self.executions.read().await;
+                executions.get(execution_id)
+                    .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?
+                    .depth
+            };
+            if depth >= self.max_subworkflow_depth {
+                return Ok(pending.fail(format!(
+                    "Subworkflow {} would exceed the maximum nesting depth of {}",
+                    workflow_id, self.max_subworkflow_depth
+                )));
+            }
+
+            let definition = self.definitions.read().await.get(workflow_id).cloned();
+            let Some(definition) = definition else {
+                return Ok(pending.fail(WorkflowError::NotFound(workflow_id.to_string()).to_string()));
+            };
+
+            let child_id = match self
+                .start_execution(definition, priority, Some((execution_id, inputs.clone())))
+                .await
+            {
+                Ok(child_id) => child_id,
+                Err(e) => {
+                    return Ok(pending.fail(format!("Subworkflow {} could not start: {}", workflow_id, e)));
+                }
+            };
+            tracing::info!(
+                execution_id = %execution_id,
+                step_id = %step.id,
+                child_execution_id = %child_id,
+                workflow_id,
+                "Subworkflow started"
+            );
+
+            let child = self.wait_for_completion(&child_id).await?;
+            if child.status != WorkflowStatus::Completed {
+                return Ok(pending.fail(format!(
+                    "Subworkflow {} execution {} ended {:?}: {}",
+                    workflow_id,
+                    child_id,
+                    child.status,
+                    child.error.as_deref().unwrap_or("no error reported")
+                )));
+            }
+
+            let steps: serde_json::Map<String, serde_json::Value> = child
+                .step_results
+                .into_iter()
+                .filter(|(_, result)| result.state == StepState::Completed)
+                .map(|(step_id, result)| (step_id, serde_json::json!(result.outputs)))
+                .collect();
+            let outputs = HashMap::from([
+                ("execution_id".to_string(), serde_json::json!(child_id)),
+                ("steps".to_string(), serde_json::Value::Object(steps)),
+            ]);
+            context.set_step_outputs(&step.id, outputs.clone()).await;
+            Ok(pending.complete(outputs))
+        })
+    }
+
+    /// Time limit of a step of an execution with a deadline
+    ///
+    /// The smaller of the step's timeout and the time left before the
+    /// deadline. Without a deadline the executor enforces the step's own
+    /// timeout, so there is no limit here.
+    async fn step_limit(
+        &self,
+        execution_id: &str,
+        step: &WorkflowStep,
+    ) -> Result<Option<(Duration, TimeoutCause)>> {
+        let executions = self.executions.read().await;
+        let execution = executions.get(execution_id)
+            .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+
+        Ok(execution.deadline.map(|deadline| {
+            deadline.step_limit(step.timeout_secs.map(Duration::from_secs))
+        }))
+    }
+
+    /// Run a step through the executor, enforcing its heartbeat if any
+    ///
+    /// A stalled attempt is abandoned and restarted up to the configured
+    /// number of times; the step's own timeout still bounds each attempt.
+    async fn run_step(&self, step: &WorkflowStep, context: &ExecutionContext) -> Result<StepResult> {
+        let heartbeat = match &step.heartbeat {
+            Some(heartbeat) => heartbeat,
+            None => return self.executor.execute_step(step, context).await,
+        };
+
+        let mut restarts = 0;
+        loop {
+            if let Some(result) = self.watch_heartbeat(step, context, heartbeat).await {
+                return result;
+            }
+
+            if restarts < heartbeat.max_restarts {
+                restarts += 1;
+                tracing::warn!(
+                    step_id = %step.id,
+                    restarts,
+                    "Step stalled, restarting"
+                );
+                continue;
+            }
+
+            tracing::warn!(step_id = %step.id, "Step stalled");
+            return Ok(StepResult::pending(step.id.clone()).stall(format!(
+                "No heartbeat within {}ms",
+                heartbeat.interval_ms
+            )));
+        }
+    }
+
+    /// Run one attempt, returning `None` if the step missed a heartbeat
+    async fn watch_heartbeat(
+        &self,
+        step: &WorkflowStep,
+        context: &ExecutionContext,
+        heartbeat: &HeartbeatConfig,
+    ) -> Option<Result<StepResult>> {
+        let interval = heartbeat.interval();
+
+        // The start of an attempt counts as its first heartbeat
+        context.heartbeat(&step.id).await;
+
+        let execution = self.executor.execute_step(step, context);
+        tokio::pin!(execution);
+        let mut check = tokio::time::interval((interval / 4).max(Duration::from_millis(1)));
+
+        loop {
+            tokio::select! {
+                result = &mut execution => return Some(result),
+                _ = check.tick() => {
+                    let silent = context.since_heartbeat(&step.id).await.unwrap_or_default();
+                    if silent > interval {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
     /// Mark workflow as complete
     async fn mark_workflow_complete(&self, execution_id: &str) -> Result<()> {
         let mut executions = self.executions.write().await;
@@ -458,7 +1220,11 @@ impl WorkflowEngine {
         Ok(())
     }
 
-    /// Pause a workflow (typically for approval)
+    /// Pause a running workflow
+    ///
+    /// Steps already running finish, but no new steps start until the
+    /// workflow is resumed with [`resume_workflow`](Self::resume_workflow).
+    /// Running subworkflow executions are paused with it.
     pub async fn pause_workflow(&self, execution_id: &str) -> Result<()> {
         let mut executions = self.executions.write().await;
         let execution = executions.get_mut(execution_id)
@@ -468,7 +1234,17 @@ impl WorkflowEngine {
             return Err(WorkflowError::NotRunning(execution_id.to_string()));
         }
 
-        execution.state.status = WorkflowStatus::Paused;
+        let mut ids = Self::descendants(&executions, execution_id);
+        ids.push(execution_id.to_string());
+        for id in ids {
+            let execution = executions.get_mut(&id).filter(|e| e.state.status == WorkflowStatus::Running);
+            if let Some(execution) = execution {
+                execution.state.status = WorkflowStatus::Paused;
+                if let Some(deadline) = &mut execution.deadline {
+                    deadline.pause();
+                }
+            }
+        }
 
         tracing::info!(
             execution_id = %execution_id,
@@ -479,6 +1255,8 @@ impl WorkflowEngine {
     }
 
     /// Resume a paused workflow
+    ///
+    /// Paused subworkflow executions are resumed with it.
     pub async fn resume_workflow(&self, execution_id: &str) -> Result<()> {
         let mut executions = self.executions.write().await;
         let execution = executions.get_mut(execution_id)
@@ -490,7 +1268,17 @@ impl WorkflowEngine {
             ));
         }
 
-        execution.state.status = WorkflowStatus::Running;
+        let mut ids = Self::descendants(&executions, execution_id);
+        ids.push(execution_id.to_string());
+        for id in ids {
+            let execution = executions.get_mut(&id).filter(|e| e.state.status == WorkflowStatus::Paused);
+            if let Some(execution) = execution {
+                execution.state.status = WorkflowStatus::Running;
+                if let Some(deadline) = &mut execution.deadline {
+                    deadline.resume();
+                }
+            }
+        }
 
         tracing::info!(
             execution_id = %execution_id,
@@ -501,12 +1289,17 @@ impl WorkflowEngine {
     }
 
     /// Cancel a workflow
+    ///
+    /// Its subworkflow executions are cancelled with it.
     pub async fn cancel_workflow(&self, execution_id: &str) -> Result<()> {
-        let mut executions = self.executions.write().await;
-        let execution = executions.get_mut(execution_id)
+        let executions = self.executions.read().await;
+        let execution = executions.get(execution_id)
             .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
 
         *execution.cancel_flag.write().await = true;
+        for id in Self::descendants(&executions, execution_id) {
+            *executions[&id].cancel_flag.write().await = true;
+        }
 
         tracing::info!(
             execution_id = %execution_id,
@@ -516,6 +1309,71 @@ impl WorkflowEngine {
         Ok(())
     }
 
+    /// Signal an external event to an execution
+    ///
+    /// Resumes the step waiting for `event_key`, with `payload` as its
+    /// outputs. An event signalled before its step starts waiting is kept
+    /// until the step gets there; signalling a key again replaces the
+    /// payload.
+    pub async fn signal(
+        &self,
+        execution_id: &str,
+        event_key: &str,
+        payload: serde_json::Value,
+    ) -> Result<()> {
+        let executions = self.executions.read().await;
+        let execution = executions.get(execution_id)
+            .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+
+        if execution.state.is_terminal() {
+            return Err(WorkflowError::NotRunning(execution_id.to_string()));
+        }
+        let awaited = execution.definition.steps.iter().any(|step| {
+            matches!(&step.action, StepAction::WaitForEvent { event_key: key, .. } if key == event_key)
+        });
+        if !awaited {
+            return Err(WorkflowError::UnexpectedEvent(event_key.to_string()));
+        }
+
+        execution.events.send_modify(|events| {
+            events.insert(event_key.to_string(), payload);
+        });
+
+        tracing::info!(
+            execution_id = %execution_id,
+            event_key,
+            "Event signalled"
+        );
+
+        Ok(())
+    }
+
+    /// IDs of the subworkflow executions an execution started
+    pub async fn child_executions(&self, execution_id: &str) -> Vec<String> {
+        self.executions
+            .read()
+            .await
+            .values()
+            .filter(|execution| execution.state.parent_execution_id.as_deref() == Some(execution_id))
+            .map(|execution| execution.state.execution_id.clone())
+            .collect()
+    }
+
+    /// IDs of the executions nested below an execution, at any depth
+    fn descendants(executions: &HashMap<String, WorkflowExecution>, execution_id: &str) -> Vec<String> {
+        let mut found = Vec::new();
+        let mut parents = vec![execution_id.to_string()];
+        while let Some(parent) = parents.pop() {
+            for execution in executions.values() {
+                if execution.state.parent_execution_id.as_deref() == Some(parent.as_str()) {
+                    found.push(execution.state.execution_id.clone());
+                    parents.push(execution.state.execution_id.clone());
+                }
+            }
+        }
+        found
+    }
+
     /// Get workflow execution status
     pub async fn get_status(&self, execution_id: &str) -> Result<WorkflowState> {
         let executions = self.executions.read().await;
@@ -525,6 +1383,58 @@ impl WorkflowEngine {
         Ok(execution.state.clone())
     }
 
+    /// Wait until an execution has finished and return its final state
+    ///
+    /// Returns once the execution loop has ended, whether the workflow
+    /// completed, failed or was cancelled.
+    pub async fn wait_for_completion(&self, execution_id: &str) -> Result<WorkflowState> {
+        let mut finished = {
+            let executions = self.executions.read().await;
+            let execution = executions.get(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+            execution.finished.subscribe()
+        };
+
+        // The sender lives as long as the execution, so this cannot fail
+        let _ = finished.wait_for(|done| *done).await;
+        self.get_status(execution_id).await
+    }
+
+    /// Export an execution as a self-describing bundle for audit
+    ///
+    /// The bundle holds the definition, execution record, per-step results
+    /// and the execution graph, with values redacted by the engine's
+    /// redaction policy. Running executions can be exported too; steps
+    /// that have not run yet are reported as pending.
+    pub async fn export_execution(&self, execution_id: &str) -> Result<ExecutionBundle> {
+        let (definition, order, state, context) = {
+            let executions = self.executions.read().await;
+            let execution = executions.get(execution_id)
+                .ok_or_else(|| WorkflowError::NotFound(execution_id.to_string()))?;
+            (
+                execution.definition.clone(),
+                execution.dag.topological_sort(),
+                execution.state.clone(),
+                execution.context.clone(),
+            )
+        };
+
+        let variables = context.get_all_state().await;
+        let execution_graph = match &context.execution_graph {
+            Some(graph) => Some(graph.lock().await.clone()),
+            None => None,
+        };
+
+        Ok(ExecutionBundle::new(
+            &definition,
+            &order,
+            &state,
+            &variables,
+            execution_graph,
+            &self.redaction,
+        ))
+    }
+
     /// Get approval gate
     pub fn approval_gate(&self) -> &ApprovalGate {
         &self.approval_gate
@@ -534,7 +1444,78 @@ impl WorkflowEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{MockStepExecutor, ScriptedOutcome};
     use crate::step::{StepAction, StepType};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Heartbeats `beats` times every `every`, then either finishes or hangs
+    struct HeartbeatingExecutor {
+        beats: u32,
+        every: Duration,
+        hang_attempts: u32,
+        attempts: AtomicU32,
+    }
+
+    impl HeartbeatingExecutor {
+        fn new(beats: u32, every_ms: u64, hang_attempts: u32) -> Self {
+            Self {
+                beats,
+                every: Duration::from_millis(every_ms),
+                hang_attempts,
+                attempts: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl StepExecutor for HeartbeatingExecutor {
+        async fn execute_step(
+            &self,
+            step: &WorkflowStep,
+            context: &ExecutionContext,
+        ) -> Result<StepResult> {
+            let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+            for _ in 0..self.beats {
+                tokio::time::sleep(self.every).await;
+                context.heartbeat(&step.id).await;
+            }
+            if attempt < self.hang_attempts {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Ok(StepResult::pending(step.id.clone()).complete(HashMap::new()))
+        }
+    }
+
+    fn parallel_workflow(id: &str, steps: usize) -> WorkflowDefinition {
+        (0..steps).fold(WorkflowDefinition::new(id, id).with_id(id), |workflow, i| {
+            let step_id = format!("{}-{}", id, i);
+            workflow.add_step(
+                WorkflowStep::new(&step_id, StepType::Action, StepAction::Wait { duration_secs: 0 })
+                    .with_id(&step_id),
+            )
+        })
+    }
+
+    fn heartbeat_workflow(heartbeat: HeartbeatConfig) -> WorkflowDefinition {
+        WorkflowDefinition::new("Heartbeat", "Heartbeat workflow").add_step(
+            WorkflowStep::new("step1", StepType::Action, StepAction::Wait { duration_secs: 0 })
+                .with_id("step1")
+                .with_timeout(30)
+                .with_heartbeat(heartbeat),
+        )
+    }
+
+    async fn wait_for_terminal(engine: &WorkflowEngine, execution_id: &str) -> WorkflowState {
+        for _ in 0..50 {
+            let state = engine.get_status(execution_id).await.unwrap();
+            if state.is_terminal() {
+                return state;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("workflow did not finish");
+    }
 
     #[tokio::test]
     async fn test_workflow_definition() {
@@ -573,4 +1554,726 @@ mod tests {
             WorkflowStatus::Running | WorkflowStatus::Completed
         ));
     }
+
+    #[tokio::test]
+    async fn test_silent_step_flagged_stalled_before_timeout() {
+        let executor = Arc::new(HeartbeatingExecutor::new(2, 20, u32::MAX));
+        let engine = WorkflowEngine::with_executor(executor);
+        let started = std::time::Instant::now();
+
+        let execution_id = engine
+            .execute_workflow(heartbeat_workflow(HeartbeatConfig::new(100)))
+            .await
+            .unwrap();
+        let state = wait_for_terminal(&engine, &execution_id).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        assert!(state.failed_steps.contains("step1"));
+        assert_eq!(state.step_results["step1"].state, StepState::Stalled);
+    }
+
+    #[tokio::test]
+    async fn test_slow_step_with_heartbeats_completes() {
+        // Runs for 4x the heartbeat interval but keeps reporting
+        let executor = Arc::new(HeartbeatingExecutor::new(10, 40, 0));
+        let engine = WorkflowEngine::with_executor(executor);
+
+        let execution_id = engine
+            .execute_workflow(heartbeat_workflow(HeartbeatConfig::new(100)))
+            .await
+            .unwrap();
+        let state = wait_for_terminal(&engine, &execution_id).await;
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(state.step_results["step1"].state, StepState::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_step_restarted() {
+        let executor = Arc::new(HeartbeatingExecutor::new(1, 20, 1));
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine
+            .execute_workflow(heartbeat_workflow(HeartbeatConfig::new(100).with_max_restarts(1)))
+            .await
+            .unwrap();
+        let state = wait_for_terminal(&engine, &execution_id).await;
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(executor.attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_high_priority_steps_admitted_first() {
+        let executor = Arc::new(
+            MockStepExecutor::new().with_default(ScriptedOutcome::success().after(Duration::from_millis(150))),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone())
+            .with_scheduler(SchedulerConfig::new(1).with_aging_interval(Duration::from_secs(60)));
+
+        engine.create_workflow(parallel_workflow("routine", 3)).await.unwrap();
+        engine.create_workflow(parallel_workflow("incident", 3)).await.unwrap();
+
+        let routine = engine.execute_with_priority("routine", Priority::LOW).await.unwrap();
+        // Let the routine workflow take the only slot and queue its other steps
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let incident = engine.execute_with_priority("incident", Priority::CRITICAL).await.unwrap();
+
+        wait_for_terminal(&engine, &routine).await;
+        wait_for_terminal(&engine, &incident).await;
+
+        let started = executor.calls();
+        let workflows: Vec<_> = started.iter().map(|id| id.split('-').next().unwrap()).collect();
+        assert_eq!(
+            workflows,
+            ["routine", "incident", "incident", "incident", "routine", "routine"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_priority_requires_registered_workflow() {
+        let engine = WorkflowEngine::new();
+        let result = engine.execute_with_priority("unknown", Priority::HIGH).await;
+        assert!(matches!(result, Err(WorkflowError::NotFound(_))));
+    }
+
+    /// A -> (B, C) -> D
+    fn diamond_workflow(fail_on_error: bool) -> WorkflowDefinition {
+        let step = |id: &str, deps: &[&str]| {
+            WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 })
+                .with_id(id)
+                .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+                .with_fail_on_error(fail_on_error)
+        };
+        WorkflowDefinition::new("Diamond", "Diamond workflow")
+            .add_step(step("a", &[]))
+            .add_step(step("b", &["a"]))
+            .add_step(step("c", &["a"]))
+            .add_step(step("d", &["b", "c"]))
+    }
+
+    fn ids(steps: &HashSet<String>) -> Vec<&str> {
+        let mut ids: Vec<_> = steps.iter().map(String::as_str).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_diamond_with_scripted_outcomes() {
+        let executor = Arc::new(
+            MockStepExecutor::new()
+                .succeed("a", HashMap::from([("rows".to_string(), serde_json::json!(3))]))
+                .script("b", ScriptedOutcome::success().after(Duration::from_secs(5)))
+                .script("c", ScriptedOutcome::success().after(Duration::from_secs(1))),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(diamond_workflow(true)).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["a", "b", "c", "d"]);
+        assert!(state.failed_steps.is_empty() && state.skipped_steps.is_empty());
+        assert_eq!(state.step_results["a"].outputs["rows"], serde_json::json!(3));
+
+        let calls = executor.calls();
+        assert_eq!(calls.first().map(String::as_str), Some("a"));
+        assert_eq!(calls.last().map(String::as_str), Some("d"));
+        assert_eq!(calls.len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_diamond_fatal_failure_stops_workflow() {
+        let executor = Arc::new(MockStepExecutor::new().fail("b", "disk full"));
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(diamond_workflow(true)).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        assert_eq!(state.error.as_deref(), Some("disk full"));
+        assert_eq!(ids(&state.failed_steps), ["b"]);
+        assert_eq!(executor.call_count("d"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paused_diamond_starts_no_branches_until_resumed() {
+        let executor = Arc::new(
+            MockStepExecutor::new().script("a", ScriptedOutcome::success().after(Duration::from_secs(1))),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(diamond_workflow(true)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        engine.pause_workflow(&execution_id).await.unwrap();
+
+        // The in-flight step finishes, but its dependents wait
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let state = engine.get_status(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Paused);
+        assert_eq!(ids(&state.completed_steps), ["a"]);
+        assert!(state.running_steps.is_empty());
+        assert_eq!(executor.call_count("b") + executor.call_count("c"), 0);
+
+        engine.resume_workflow(&execution_id).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["a", "b", "c", "d"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_diamond_non_fatal_failure_skips_dependents() {
+        let executor = Arc::new(MockStepExecutor::new().fail("c", "flaky"));
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(diamond_workflow(false)).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["a", "b"]);
+        assert_eq!(ids(&state.failed_steps), ["c"]);
+        assert_eq!(ids(&state.skipped_steps), ["d"]);
+        assert_eq!(state.step_results["d"].state, StepState::Skipped);
+        assert_eq!(executor.call_count("d"), 0);
+    }
+
+    /// `probe` -> `check` branching to `deploy` if the probe is healthy,
+    /// else to `rollback` -> `page`
+    fn branching_workflow() -> WorkflowDefinition {
+        let step = |id: &str, deps: &[&str]| {
+            WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 })
+                .with_id(id)
+                .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+        };
+        let check = WorkflowStep::new(
+            "check",
+            StepType::Condition,
+            StepAction::Condition {
+                expression: "steps.probe.healthy == true".to_string(),
+                true_steps: vec!["deploy".to_string()],
+                false_steps: vec!["rollback".to_string()],
+            },
+        )
+        .with_id("check")
+        .with_dependencies(vec!["probe".to_string()]);
+        WorkflowDefinition::new("Branching", "Deploy or roll back")
+            .add_step(step("probe", &[]))
+            .add_step(check)
+            .add_step(step("deploy", &[]))
+            .add_step(step("rollback", &[]))
+            .add_step(step("page", &["rollback"]))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_condition_skips_untaken_branch() {
+        let executor = Arc::new(
+            MockStepExecutor::new()
+                .succeed("probe", HashMap::from([("healthy".to_string(), serde_json::json!(true))])),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(branching_workflow()).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["check", "deploy", "probe"]);
+        assert_eq!(ids(&state.skipped_steps), ["page", "rollback"]);
+        assert_eq!(state.step_results["check"].outputs["condition_result"], serde_json::json!(true));
+        assert_eq!(executor.call_count("check"), 0);
+        assert_eq!(executor.call_count("rollback"), 0);
+        assert_eq!(executor.call_count("page"), 0);
+
+        let executor = Arc::new(
+            MockStepExecutor::new()
+                .succeed("probe", HashMap::from([("healthy".to_string(), serde_json::json!(false))])),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(branching_workflow()).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(ids(&state.completed_steps), ["check", "page", "probe", "rollback"]);
+        assert_eq!(ids(&state.skipped_steps), ["deploy"]);
+        assert_eq!(executor.call_count("deploy"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_join_after_branches_runs_after_the_taken_one() {
+        let step = |id: &str, deps: &[&str]| {
+            WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 })
+                .with_id(id)
+                .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+        };
+        // `deploy` or `rollback` -> `page`, then both merge into `report`
+        let workflow = branching_workflow()
+            .add_step(step("report", &["deploy", "page"]))
+            .add_step(step("archive", &["report"]));
+
+        for (healthy, ran, skipped) in [
+            (true, ["deploy", "report"], vec!["page", "rollback"]),
+            (false, ["page", "report"], vec!["deploy"]),
+        ] {
+            let executor = Arc::new(
+                MockStepExecutor::new()
+                    .succeed("probe", HashMap::from([("healthy".to_string(), serde_json::json!(healthy))])),
+            );
+            let engine = WorkflowEngine::with_executor(executor.clone());
+
+            let execution_id = engine.execute_workflow(workflow.clone()).await.unwrap();
+            let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+            assert_eq!(state.status, WorkflowStatus::Completed);
+            assert_eq!(ids(&state.skipped_steps), skipped);
+            for id in ran.into_iter().chain(["archive"]) {
+                assert!(state.completed_steps.contains(id), "{} did not run", id);
+            }
+            assert_eq!(executor.call_count("report"), 1);
+        }
+    }
+
+    fn subworkflow_step(id: &str, workflow_id: &str, deps: &[&str]) -> WorkflowStep {
+        WorkflowStep::new(
+            id,
+            StepType::Subworkflow,
+            StepAction::Subworkflow {
+                workflow_id: workflow_id.to_string(),
+                inputs: HashMap::from([("region".to_string(), serde_json::json!("eu-west"))]),
+            },
+        )
+        .with_id(id)
+        .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_nested_subworkflows_propagate_outputs() {
+        let executor = Arc::new(
+            MockStepExecutor::new()
+                .succeed("build", HashMap::from([("artifact".to_string(), serde_json::json!("app-1.2"))])),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let leaf = |id: &str| {
+            WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 }).with_id(id)
+        };
+        engine
+            .create_workflow(WorkflowDefinition::new("Build", "Leaf").with_id("build-wf").add_step(leaf("build")))
+            .await
+            .unwrap();
+        engine
+            .create_workflow(
+                WorkflowDefinition::new("Release", "Middle")
+                    .with_id("release-wf")
+                    .add_step(subworkflow_step("compile", "build-wf", &[])),
+            )
+            .await
+            .unwrap();
+        let parent = WorkflowDefinition::new("Deploy", "Top")
+            .add_step(subworkflow_step("release", "release-wf", &[]))
+            .add_step(leaf("announce").with_dependencies(vec!["release".to_string()]));
+
+        let execution_id = engine.execute_workflow(parent).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["announce", "release"]);
+        assert_eq!(
+            state.step_results["release"].outputs["steps"]["compile"]["steps"]["build"]["artifact"],
+            serde_json::json!("app-1.2")
+        );
+
+        let children = engine.child_executions(&execution_id).await;
+        assert_eq!(children.len(), 1);
+        let child = engine.get_status(&children[0]).await.unwrap();
+        assert_eq!(child.parent_execution_id.as_deref(), Some(execution_id.as_str()));
+        assert_eq!(state.step_results["release"].outputs["execution_id"], serde_json::json!(children[0]));
+
+        let grandchildren = engine.child_executions(&children[0]).await;
+        assert_eq!(grandchildren.len(), 1);
+        assert_eq!(engine.get_status(&grandchildren[0]).await.unwrap().workflow_id, "build-wf");
+        assert_eq!(executor.call_count("build"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_missing_subworkflow_fails_step() {
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+
+        let definition = WorkflowDefinition::new("Deploy", "Top")
+            .add_step(subworkflow_step("release", "unregistered", &[]));
+        let execution_id = engine.execute_workflow(definition).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        assert_eq!(state.error.as_deref(), Some("Workflow not found: unregistered"));
+        assert!(engine.child_executions(&execution_id).await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_recursive_subworkflow_stops_at_depth_limit() {
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()))
+            .with_max_subworkflow_depth(2);
+        engine
+            .create_workflow(
+                WorkflowDefinition::new("Loop", "Embeds itself")
+                    .with_id("loop")
+                    .add_step(subworkflow_step("again", "loop", &[])),
+            )
+            .await
+            .unwrap();
+
+        let execution_id = engine.execute_with_priority("loop", Priority::default()).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        // Depths 0 and 1 start children; the execution at depth 2 may not
+        let child = engine.child_executions(&execution_id).await.remove(0);
+        let grandchild = engine.child_executions(&child).await.remove(0);
+        assert!(engine.child_executions(&grandchild).await.is_empty());
+        let innermost = engine.get_status(&grandchild).await.unwrap();
+        assert!(innermost.error.unwrap().contains("maximum nesting depth of 2"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_and_pause_cascade_to_subworkflows() {
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+        engine.create_workflow(event_workflow(None).with_id("deploy-wf")).await.unwrap();
+        let parent = WorkflowDefinition::new("Release", "Top")
+            .add_step(subworkflow_step("deploy", "deploy-wf", &[]));
+
+        let execution_id = engine.execute_workflow(parent).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let child = engine.child_executions(&execution_id).await.remove(0);
+
+        engine.pause_workflow(&execution_id).await.unwrap();
+        assert_eq!(engine.get_status(&child).await.unwrap().status, WorkflowStatus::Paused);
+        engine.resume_workflow(&execution_id).await.unwrap();
+        assert_eq!(engine.get_status(&child).await.unwrap().status, WorkflowStatus::Running);
+
+        engine.cancel_workflow(&execution_id).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Cancelled);
+        let child = engine.wait_for_completion(&child).await.unwrap();
+        assert_eq!(child.status, WorkflowStatus::Cancelled);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_subworkflow_runs_within_parent_deadline() {
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+        engine.create_workflow(event_workflow(None).with_id("deploy-wf")).await.unwrap();
+        let parent = WorkflowDefinition::new("Release", "Top")
+            .with_timeout(60)
+            .add_step(subworkflow_step("deploy", "deploy-wf", &[]));
+
+        let started = tokio::time::Instant::now();
+        let execution_id = engine.execute_workflow(parent).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        assert!(started.elapsed() < Duration::from_secs(61));
+        let child = engine.child_executions(&execution_id).await.remove(0);
+        let child = engine.get_status(&child).await.unwrap();
+        assert!(child.error.unwrap().contains("workflow deadline"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failure_rolls_back_completed_steps_in_reverse() {
+        let executor = Arc::new(MockStepExecutor::new().fail("c", "migration failed"));
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let step = |id: &str, deps: &[&str]| {
+            WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 })
+                .with_id(id)
+                .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+                .with_compensating_action(StepAction::Custom {
+                    handler: format!("undo_{}", id),
+                    parameters: HashMap::new(),
+                })
+        };
+        let definition = WorkflowDefinition::new("Migrate", "Three steps")
+            .add_step(step("a", &[]))
+            .add_step(step("b", &["a"]))
+            .add_step(step("c", &["b"]))
+            .with_rollback_on_failure();
+
+        let execution_id = engine.execute_workflow(definition).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        assert_eq!(executor.calls(), ["a", "b", "c", "b:compensate", "a:compensate"]);
+        let compensated: Vec<_> = state.compensations.iter().map(|r| r.step_id.as_str()).collect();
+        assert_eq!(compensated, ["b:compensate", "a:compensate"]);
+        assert!(state.compensations.iter().all(|r| r.is_success()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rollback_does_not_wait_forever_for_running_steps() {
+        let executor = Arc::new(
+            MockStepExecutor::new()
+                .script("slow", ScriptedOutcome::success().after(Duration::from_secs(5)))
+                .script("c", ScriptedOutcome::failure("migration failed").after(Duration::from_secs(1))),
+        );
+        let engine = WorkflowEngine::with_executor(executor.clone())
+            .with_rollback_timeout(Duration::from_secs(30));
+
+        let step = |id: &str, action: StepAction, deps: &[&str]| {
+            WorkflowStep::new(id, StepType::Action, action)
+                .with_id(id)
+                .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+                .with_compensating_action(StepAction::Custom {
+                    handler: format!("undo_{}", id),
+                    parameters: HashMap::new(),
+                })
+        };
+        let wait = || StepAction::Wait { duration_secs: 0 };
+        let never_signalled = StepAction::WaitForEvent {
+            event_key: "approved".to_string(),
+            timeout_secs: None,
+        };
+        let definition = WorkflowDefinition::new("Migrate", "Rollback with stragglers")
+            .add_step(step("a", wait(), &[]))
+            .add_step(step("c", wait(), &["a"]))
+            .add_step(step("slow", wait(), &[]))
+            .add_step(step("approval", never_signalled, &[]))
+            .with_rollback_on_failure();
+
+        let execution_id = engine.execute_workflow(definition).await.unwrap();
+        let state = tokio::time::timeout(
+            Duration::from_secs(120),
+            engine.wait_for_completion(&execution_id),
+        )
+        .await
+        .expect("rollback waited forever")
+        .unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        // `slow` finished while the rollback waited, after the failure
+        assert!(state.completed_steps.contains("slow"));
+        let compensated: Vec<_> = state.compensations.iter().map(|r| r.step_id.as_str()).collect();
+        assert_eq!(compensated, ["a:compensate"]);
+    }
+
+    /// `query` must output a non-negative integer `count` and a `host`;
+    /// `notify` depends on it
+    fn schema_workflow() -> WorkflowDefinition {
+        let step = |id: &str, deps: &[&str]| {
+            WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 })
+                .with_id(id)
+                .with_dependencies(deps.iter().map(|d| d.to_string()).collect())
+        };
+        let query = step("query", &[]).with_output_schema(serde_json::json!({
+            "type": "object",
+            "required": ["count", "host"],
+            "properties": {
+                "count": {"type": "integer", "minimum": 0},
+                "host": {"type": "string"}
+            }
+        }));
+        WorkflowDefinition::new("schema", "Output contract")
+            .with_id("schema")
+            .add_step(query)
+            .add_step(step("notify", &["query"]))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_step_output_matching_schema_completes() {
+        let outputs = HashMap::from([
+            ("count".to_string(), serde_json::json!(12)),
+            ("host".to_string(), serde_json::json!("db-1")),
+        ]);
+        let executor = Arc::new(MockStepExecutor::new().succeed("query", outputs));
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(schema_workflow()).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["notify", "query"]);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_output_schema_is_rejected() {
+        let mut workflow = schema_workflow();
+        workflow.steps[0].output_schema = Some(serde_json::json!({
+            "type": "object",
+            "properties": {"host": {"anyOf": [{"type": "string"}, {"type": "null"}]}}
+        }));
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+
+        let err = engine.execute_workflow(workflow).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            WorkflowError::InvalidDefinition(
+                "Output schema of step query is not supported at /properties/host: unsupported keyword \"anyOf\"".to_string()
+            )
+            .to_string()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_step_output_violating_schema_fails_step() {
+        let outputs = HashMap::from([
+            ("count".to_string(), serde_json::json!("twelve")),
+            ("host".to_string(), serde_json::json!("db-1")),
+        ]);
+        let executor = Arc::new(MockStepExecutor::new().succeed("query", outputs));
+        let engine = WorkflowEngine::with_executor(executor.clone());
+
+        let execution_id = engine.execute_workflow(schema_workflow()).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        assert_eq!(ids(&state.failed_steps), ["query"]);
+        assert_eq!(
+            state.error.as_deref(),
+            Some("Output does not match schema at /count: expected integer, found string")
+        );
+        assert_eq!(executor.call_count("notify"), 0);
+    }
+
+    /// `deploy` waits for "deploy.finished", then `verify` runs
+    fn event_workflow(timeout_secs: Option<u64>) -> WorkflowDefinition {
+        let deploy = StepAction::WaitForEvent {
+            event_key: "deploy.finished".to_string(),
+            timeout_secs,
+        };
+        WorkflowDefinition::new("Event", "Event-driven workflow")
+            .add_step(WorkflowStep::new("deploy", StepType::Wait, deploy).with_id("deploy"))
+            .add_step(
+                WorkflowStep::new("verify", StepType::Action, StepAction::Wait { duration_secs: 0 })
+                    .with_id("verify")
+                    .with_dependency("deploy"),
+            )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_signal_resumes_waiting_step() {
+        let executor = Arc::new(MockStepExecutor::new());
+        let engine = WorkflowEngine::with_executor(executor.clone());
+        let execution_id = engine.execute_workflow(event_workflow(Some(600))).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        let state = engine.get_status(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Running);
+        assert_eq!(state.step_results["deploy"].state, StepState::WaitingEvent);
+        assert_eq!(executor.call_count("verify"), 0);
+
+        let result = engine.signal(&execution_id, "deploy.started", serde_json::json!({})).await;
+        assert!(matches!(result, Err(WorkflowError::UnexpectedEvent(_))));
+        engine
+            .signal(&execution_id, "deploy.finished", serde_json::json!({"version": "1.4.2"}))
+            .await
+            .unwrap();
+
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Completed);
+        assert_eq!(ids(&state.completed_steps), ["deploy", "verify"]);
+        assert_eq!(state.step_results["deploy"].outputs["version"], serde_json::json!("1.4.2"));
+        // The executor never ran the waiting step itself
+        assert_eq!(executor.call_count("deploy"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unsignalled_event_times_out() {
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+        let started = tokio::time::Instant::now();
+        let execution_id = engine.execute_workflow(event_workflow(Some(5))).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        let deploy = &state.step_results["deploy"];
+        assert_eq!(deploy.state, StepState::Failed);
+        assert_eq!(deploy.timed_out, Some(TimeoutCause::Event));
+        assert_eq!(
+            deploy.error.as_deref(),
+            Some("Timed out after 5000ms waiting for event \"deploy.finished\" (event timeout)")
+        );
+        assert!(started.elapsed() < Duration::from_secs(6));
+
+        // Finished executions take no more signals
+        let result = engine.signal(&execution_id, "deploy.finished", serde_json::json!(null)).await;
+        assert!(matches!(result, Err(WorkflowError::NotRunning(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_stops_waiting_for_event() {
+        let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+        let execution_id = engine.execute_workflow(event_workflow(None)).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        engine.cancel_workflow(&execution_id).await.unwrap();
+        engine.wait_for_completion(&execution_id).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        let state = engine.get_status(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Cancelled);
+        assert!(state.running_steps.is_empty());
+        assert!(state.error.is_none());
+        let deploy = &state.step_results["deploy"];
+        assert_eq!(deploy.state, StepState::Failed);
+        assert_eq!(
+            deploy.error.as_deref(),
+            Some("Cancelled while waiting for event \"deploy.finished\"")
+        );
+    }
+
+    /// `fetch` runs for `fetch_secs`, then `report` for `report_secs`
+    /// under a step timeout of `report_timeout`
+    fn deadline_workflow(deadline: u64, report_timeout: u64) -> WorkflowDefinition {
+        WorkflowDefinition::new("Deadline", "Deadline workflow")
+            .with_timeout(deadline)
+            .add_step(
+                WorkflowStep::new("fetch", StepType::Action, StepAction::Wait { duration_secs: 0 })
+                    .with_id("fetch"),
+            )
+            .add_step(
+                WorkflowStep::new("report", StepType::Action, StepAction::Wait { duration_secs: 0 })
+                    .with_id("report")
+                    .with_dependency("fetch")
+                    .with_timeout(report_timeout),
+            )
+    }
+
+    fn slow_steps(fetch_secs: u64, report_secs: u64) -> Arc<MockStepExecutor> {
+        Arc::new(
+            MockStepExecutor::new()
+                .script("fetch", ScriptedOutcome::success().after(Duration::from_secs(fetch_secs)))
+                .script("report", ScriptedOutcome::success().after(Duration::from_secs(report_secs))),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_step_shrinks_later_step_budget() {
+        // Alone, the report fits its own 5s timeout
+        let engine = WorkflowEngine::with_executor(slow_steps(0, 4));
+        let execution_id = engine.execute_workflow(deadline_workflow(10, 5)).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+        assert_eq!(state.status, WorkflowStatus::Completed);
+
+        // After a 7s fetch only ~3s of the 10s deadline are left
+        let engine = WorkflowEngine::with_executor(slow_steps(7, 4));
+        let started = tokio::time::Instant::now();
+        let execution_id = engine.execute_workflow(deadline_workflow(10, 5)).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        assert_eq!(state.status, WorkflowStatus::Failed);
+        let report = &state.step_results["report"];
+        assert_eq!(report.timed_out, Some(TimeoutCause::Deadline));
+        assert!(report.error.as_deref().unwrap().contains("workflow deadline"));
+
+        // The report was cut off at the deadline rather than its own timeout
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(10));
+        assert!(elapsed < Duration::from_secs(11));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_step_timeout_reported_when_tighter_than_deadline() {
+        let engine = WorkflowEngine::with_executor(slow_steps(7, 4));
+        let execution_id = engine.execute_workflow(deadline_workflow(60, 2)).await.unwrap();
+        let state = engine.wait_for_completion(&execution_id).await.unwrap();
+
+        let report = &state.step_results["report"];
+        assert_eq!(report.timed_out, Some(TimeoutCause::Step));
+        assert!(report.error.as_deref().unwrap().contains("step timeout"));
+    }
 }
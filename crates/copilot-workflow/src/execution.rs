@@ -1,5 +1,6 @@
 //! Workflow execution engine with retry logic and timeout handling
 
+use crate::condition;
 use crate::step::{StepAction, StepResult, StepState, WorkflowStep};
 use crate::{Result, WorkflowError};
 use async_trait::async_trait;
@@ -8,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
-use tokio::time::{timeout, Duration};
+use tokio::time::{timeout, Duration, Instant};
 
 /// Configuration for retry behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +57,8 @@ pub struct ExecutionContext {
     state: Arc<RwLock<HashMap<String, serde_json::Value>>>,
     /// Step outputs
     outputs: Arc<RwLock<HashMap<String, HashMap<String, serde_json::Value>>>>,
+    /// Last heartbeat per step
+    heartbeats: Arc<RwLock<HashMap<String, Instant>>>,
     /// Execution graph for Agentics span tracking (optional)
     pub execution_graph: Option<Arc<Mutex<ExecutionGraph>>>,
 }
@@ -68,6 +71,7 @@ impl ExecutionContext {
             execution_id: execution_id.into(),
             state: Arc::new(RwLock::new(HashMap::new())),
             outputs: Arc::new(RwLock::new(HashMap::new())),
+            heartbeats: Arc::new(RwLock::new(HashMap::new())),
             execution_graph: None,
         }
     }
@@ -121,6 +125,34 @@ impl ExecutionContext {
         outputs.clone()
     }
 
+    /// Variables condition expressions are evaluated against
+    ///
+    /// The shared state, with the outputs of finished steps under `steps`.
+    pub async fn variables(&self) -> serde_json::Value {
+        let mut variables: serde_json::Map<String, serde_json::Value> =
+            self.get_all_state().await.into_iter().collect();
+        let steps = self
+            .get_all_outputs()
+            .await
+            .into_iter()
+            .map(|(step_id, outputs)| (step_id, serde_json::json!(outputs)))
+            .collect();
+        variables.insert("steps".to_string(), serde_json::Value::Object(steps));
+        serde_json::Value::Object(variables)
+    }
+
+    /// Report that a step is still making progress
+    pub async fn heartbeat(&self, step_id: &str) {
+        let mut heartbeats = self.heartbeats.write().await;
+        heartbeats.insert(step_id.to_string(), Instant::now());
+    }
+
+    /// Time since a step last reported a heartbeat
+    pub async fn since_heartbeat(&self, step_id: &str) -> Option<Duration> {
+        let heartbeats = self.heartbeats.read().await;
+        heartbeats.get(step_id).map(|at| at.elapsed())
+    }
+
     /// Clear all state and outputs
     pub async fn clear(&self) {
         let mut state = self.state.write().await;
@@ -263,11 +295,25 @@ impl DefaultStepExecutor {
                     self.execute_condition(expression, true_steps, false_steps, context).await
                 }
                 StepAction::Wait { duration_secs } => {
-                    self.execute_wait(*duration_secs).await
+                    self.execute_wait(step, *duration_secs, context).await
                 }
                 StepAction::Custom { handler, parameters } => {
                     self.execute_custom(handler, parameters, context).await
                 }
+                StepAction::WaitForEvent { event_key, .. } => {
+                    // Signals are delivered by the engine, which runs these
+                    // steps itself
+                    Err(WorkflowError::StepExecutionFailed {
+                        step_id: step.id.clone(),
+                        reason: format!("waiting for event {:?} requires the workflow engine", event_key),
+                    })
+                }
+                StepAction::Subworkflow { workflow_id, .. } => {
+                    Err(WorkflowError::StepExecutionFailed {
+                        step_id: step.id.clone(),
+                        reason: format!("subworkflow {:?} requires the workflow engine", workflow_id),
+                    })
+                }
             }
         };
 
@@ -401,22 +447,35 @@ impl DefaultStepExecutor {
         expression: &str,
         true_steps: &[String],
         false_steps: &[String],
-        _context: &ExecutionContext,
+        context: &ExecutionContext,
     ) -> Result<HashMap<String, serde_json::Value>> {
         tracing::info!(expression, "Evaluating condition");
 
-        // Mock implementation - always returns true
-        let mut outputs = HashMap::new();
-        outputs.insert("condition_result".to_string(), serde_json::json!(true));
-        outputs.insert("next_steps".to_string(), serde_json::json!(true_steps));
-
-        Ok(outputs)
+        let result = condition::evaluate(expression, &context.variables().await)?;
+        Ok(condition::branch_outputs(result, true_steps, false_steps))
     }
 
-    async fn execute_wait(&self, duration_secs: u64) -> Result<HashMap<String, serde_json::Value>> {
+    async fn execute_wait(
+        &self,
+        step: &WorkflowStep,
+        duration_secs: u64,
+        context: &ExecutionContext,
+    ) -> Result<HashMap<String, serde_json::Value>> {
         tracing::info!(duration_secs, "Waiting");
 
-        tokio::time::sleep(Duration::from_secs(duration_secs)).await;
+        // Waiting is progress: keep heartbeating so long waits are not stalls
+        let total = Duration::from_secs(duration_secs);
+        match &step.heartbeat {
+            Some(heartbeat) => {
+                let slice = heartbeat.interval() / 2;
+                let deadline = Instant::now() + total;
+                while Instant::now() < deadline {
+                    context.heartbeat(&step.id).await;
+                    tokio::time::sleep(slice.min(deadline.saturating_duration_since(Instant::now()))).await;
+                }
+            }
+            None => tokio::time::sleep(total).await,
+        }
 
         let mut outputs = HashMap::new();
         outputs.insert("waited_secs".to_string(), serde_json::json!(duration_secs));
@@ -0,0 +1,609 @@
+//! Execution export for audit and reproduction
+//!
+//! An [`ExecutionBundle`] captures everything about one finished (or
+//! in-flight) execution in a single self-describing document: the workflow
+//! definition it ran, the execution record, every step's result and timing
+//! in topological order, the dependency graph and, when the execution was
+//! tracked with one, the Agentics execution graph. Auditors can review the
+//! bundle as-is, and the embedded definition is enough to run the workflow
+//! again once its redacted secrets are supplied.
+//!
+//! Step outputs, workflow variables and the definition's actions (HTTP
+//! headers, command environments, agent and handler parameters) may hold
+//! credentials or customer data, so values are passed through a
+//! [`RedactionPolicy`] before they leave the engine. The bundle lists
+//! every path it redacted.
+
+use crate::engine::{WorkflowDefinition, WorkflowStatus, WorkflowState};
+use crate::step::{StepAction, StepResult, StepState, WorkflowStep};
+use crate::{Result, WorkflowError};
+use chrono::{DateTime, Utc};
+use copilot_core::agents::execution_graph::ExecutionGraph;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Format identifier of execution bundles
+pub const EXECUTION_BUNDLE_FORMAT: &str = "copilot-workflow/execution-bundle";
+
+/// Version of the execution bundle format
+///
+/// Bumped whenever a field is removed or changes meaning; readers reject
+/// bundles with a newer version.
+pub const EXECUTION_BUNDLE_VERSION: u32 = 1;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Step metadata key marking all of a step's outputs as sensitive
+pub const SENSITIVE_METADATA_KEY: &str = "sensitive";
+
+/// Output keys redacted by the default policy
+const DEFAULT_SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "credentials",
+    "private_key",
+];
+
+/// Which exported values are replaced by [`REDACTED`]
+///
+/// A key is redacted when it ends with one of the policy's keys, ignoring
+/// case and treating `-` as `_`, so `api_key` covers `X-Api-Key` and
+/// `password` covers `db_password`. Keys are matched at any depth of an
+/// output value. Whole steps can be redacted by ID or by setting the
+/// [`SENSITIVE_METADATA_KEY`] metadata on the step to `true`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionPolicy {
+    /// Output and variable keys whose values are redacted
+    pub keys: BTreeSet<String>,
+    /// Steps whose outputs are redacted entirely
+    pub steps: BTreeSet<String>,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            keys: DEFAULT_SENSITIVE_KEYS.iter().map(|k| k.to_string()).collect(),
+            steps: BTreeSet::new(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// A policy that redacts nothing
+    pub fn none() -> Self {
+        Self {
+            keys: BTreeSet::new(),
+            steps: BTreeSet::new(),
+        }
+    }
+
+    /// Redact values stored under a key
+    pub fn redact_key(mut self, key: impl Into<String>) -> Self {
+        self.keys.insert(key.into().to_lowercase());
+        self
+    }
+
+    /// Redact all outputs of a step
+    pub fn redact_step(mut self, step_id: impl Into<String>) -> Self {
+        self.steps.insert(step_id.into());
+        self
+    }
+
+    /// Whether all outputs of a step are redacted
+    pub fn redacts_step(&self, step: &WorkflowStep) -> bool {
+        self.steps.contains(&step.id)
+            || step
+                .metadata
+                .get(SENSITIVE_METADATA_KEY)
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(false)
+    }
+
+    fn redacts_key(&self, key: &str) -> bool {
+        let key = normalize_key(key);
+        self.keys.iter().any(|k| key.ends_with(&normalize_key(k)))
+    }
+
+    /// Redact the values an action carries, recording paths under `path`
+    fn redact_action(
+        &self,
+        action: &mut StepAction,
+        path: &str,
+        redact_all: bool,
+        redacted: &mut Vec<String>,
+    ) {
+        match action {
+            StepAction::Command { env, .. } => {
+                self.redact_strings(env, &format!("{}.env", path), redact_all, redacted)
+            }
+            StepAction::HttpRequest { headers, .. } => {
+                self.redact_strings(headers, &format!("{}.headers", path), redact_all, redacted)
+            }
+            StepAction::AgentInvoke { parameters, .. } | StepAction::Custom { parameters, .. } => {
+                self.redact_values(parameters, &format!("{}.parameters", path), redact_all, redacted)
+            }
+            StepAction::Subworkflow { inputs, .. } => {
+                self.redact_values(inputs, &format!("{}.inputs", path), redact_all, redacted)
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_values(
+        &self,
+        values: &mut HashMap<String, serde_json::Value>,
+        path: &str,
+        redact_all: bool,
+        redacted: &mut Vec<String>,
+    ) {
+        for (key, value) in values.iter_mut() {
+            let path = format!("{}.{}", path, key);
+            *value = if redact_all || self.redacts_key(key) {
+                redacted.push(path);
+                serde_json::Value::String(REDACTED.to_string())
+            } else {
+                self.redact_value(value, &path, redacted)
+            };
+        }
+    }
+
+    fn redact_strings(
+        &self,
+        values: &mut HashMap<String, String>,
+        path: &str,
+        redact_all: bool,
+        redacted: &mut Vec<String>,
+    ) {
+        for (key, value) in values.iter_mut() {
+            if redact_all || self.redacts_key(key) {
+                redacted.push(format!("{}.{}", path, key));
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+
+    /// Redact a map of values, returning the redacted paths
+    fn apply(
+        &self,
+        values: &HashMap<String, serde_json::Value>,
+        redact_all: bool,
+    ) -> (BTreeMap<String, serde_json::Value>, Vec<String>) {
+        let mut redacted = Vec::new();
+        let values = values
+            .iter()
+            .map(|(key, value)| {
+                let value = if redact_all || self.redacts_key(key) {
+                    redacted.push(key.clone());
+                    serde_json::Value::String(REDACTED.to_string())
+                } else {
+                    self.redact_value(value, key, &mut redacted)
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        redacted.sort();
+        (values, redacted)
+    }
+
+    fn redact_value(
+        &self,
+        value: &serde_json::Value,
+        path: &str,
+        redacted: &mut Vec<String>,
+    ) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        let path = format!("{}.{}", path, key);
+                        let value = if self.redacts_key(key) {
+                            redacted.push(path);
+                            serde_json::Value::String(REDACTED.to_string())
+                        } else {
+                            self.redact_value(value, &path, redacted)
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            serde_json::Value::Array(items) => serde_json::Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.redact_value(item, &format!("{}[{}]", path, i), redacted))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    key.to_lowercase().replace('-', "_")
+}
+
+/// Summary of an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    /// Workflow ID
+    pub workflow_id: String,
+    /// Execution ID
+    pub execution_id: String,
+    /// Status at export time
+    pub status: WorkflowStatus,
+    /// Execution start time
+    pub started_at: Option<DateTime<Utc>>,
+    /// Execution end time
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Wall-clock duration, if the execution has ended
+    pub duration_ms: Option<i64>,
+    /// Error message if failed
+    pub error: Option<String>,
+    /// Workflow variables, redacted
+    pub variables: BTreeMap<String, serde_json::Value>,
+    /// Variable paths replaced by [`REDACTED`]
+    #[serde(default)]
+    pub redacted: Vec<String>,
+}
+
+/// Result and timing of one step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    /// Step ID
+    pub step_id: String,
+    /// Step name
+    pub name: String,
+    /// State at export time; `Pending` for steps that never ran
+    pub state: StepState,
+    /// Steps this step depended on
+    pub dependencies: Vec<String>,
+    /// Execution start time
+    pub started_at: Option<DateTime<Utc>>,
+    /// Execution end time
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Execution duration, if the step has ended
+    pub duration_ms: Option<i64>,
+    /// Number of retry attempts
+    pub retry_count: u32,
+    /// Error message if failed
+    pub error: Option<String>,
+    /// Step outputs, redacted
+    pub outputs: BTreeMap<String, serde_json::Value>,
+    /// Paths replaced by [`REDACTED`]: output keys, and values of the
+    /// step's actions in the definition under `action` and
+    /// `compensating_action`
+    pub redacted: Vec<String>,
+}
+
+impl StepRecord {
+    fn new(
+        step: &WorkflowStep,
+        state: &WorkflowState,
+        result: Option<&StepResult>,
+        policy: &RedactionPolicy,
+        action_paths: &[String],
+    ) -> Self {
+        let status = if state.completed_steps.contains(&step.id) {
+            StepState::Completed
+        } else if state.failed_steps.contains(&step.id) {
+            result.map_or(StepState::Failed, |r| r.state.clone())
+        } else if state.skipped_steps.contains(&step.id) {
+            StepState::Skipped
+        } else if state.running_steps.contains(&step.id) {
+            // A running step has a result only while it waits for an event
+            result.map_or(StepState::Running, |r| r.state.clone())
+        } else {
+            StepState::Pending
+        };
+
+        let (outputs, mut redacted) = result
+            .map(|r| policy.apply(&r.outputs, policy.redacts_step(step)))
+            .unwrap_or_default();
+        redacted.extend(action_paths.iter().cloned());
+        redacted.sort();
+        let completed_at = result.and_then(|r| r.completed_at);
+
+        Self {
+            step_id: step.id.clone(),
+            name: step.name.clone(),
+            state: status,
+            dependencies: step.dependencies.clone(),
+            started_at: result.map(|r| r.started_at),
+            completed_at,
+            duration_ms: result
+                .zip(completed_at)
+                .map(|(r, end)| (end - r.started_at).num_milliseconds()),
+            retry_count: result.map_or(0, |r| r.retry_count),
+            error: result.and_then(|r| r.error.clone()),
+            outputs,
+            redacted,
+        }
+    }
+}
+
+/// Self-describing export of one workflow execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionBundle {
+    /// Always [`EXECUTION_BUNDLE_FORMAT`]
+    pub format: String,
+    /// Format version, see [`EXECUTION_BUNDLE_VERSION`]
+    pub version: u32,
+    /// Version of the engine that produced the bundle
+    pub engine_version: String,
+    /// When the bundle was exported
+    pub exported_at: DateTime<Utc>,
+    /// Definition the execution ran, with its actions redacted
+    pub definition: WorkflowDefinition,
+    /// Execution summary
+    pub execution: ExecutionRecord,
+    /// Per-step records in topological order
+    pub steps: Vec<StepRecord>,
+    /// Agentics execution graph, if the execution was tracked with one
+    pub execution_graph: Option<ExecutionGraph>,
+    /// Policy the values were redacted with
+    pub redaction: RedactionPolicy,
+}
+
+impl ExecutionBundle {
+    /// Assemble a bundle from an execution's definition and state
+    pub(crate) fn new(
+        definition: &WorkflowDefinition,
+        order: &[String],
+        state: &WorkflowState,
+        variables: &HashMap<String, serde_json::Value>,
+        execution_graph: Option<ExecutionGraph>,
+        policy: &RedactionPolicy,
+    ) -> Self {
+        let mut action_paths: HashMap<String, Vec<String>> = HashMap::new();
+        let mut definition = definition.clone();
+        for step in &mut definition.steps {
+            let redact_all = policy.redacts_step(step);
+            let paths = action_paths.entry(step.id.clone()).or_default();
+            policy.redact_action(&mut step.action, "action", redact_all, paths);
+            if let Some(action) = &mut step.compensating_action {
+                policy.redact_action(action, "compensating_action", redact_all, paths);
+            }
+        }
+
+        let steps_by_id: HashMap<&str, &WorkflowStep> =
+            definition.steps.iter().map(|s| (s.id.as_str(), s)).collect();
+        let steps = order
+            .iter()
+            .filter_map(|id| steps_by_id.get(id.as_str()))
+            .map(|step| {
+                StepRecord::new(
+                    step,
+                    state,
+                    state.step_results.get(&step.id),
+                    policy,
+                    &action_paths[&step.id],
+                )
+            })
+            .collect();
+
+        let (variables, redacted) = policy.apply(variables, false);
+        let execution = ExecutionRecord {
+            workflow_id: state.workflow_id.clone(),
+            execution_id: state.execution_id.clone(),
+            status: state.status.clone(),
+            started_at: state.started_at,
+            completed_at: state.completed_at,
+            duration_ms: state
+                .started_at
+                .zip(state.completed_at)
+                .map(|(start, end)| (end - start).num_milliseconds()),
+            error: state.error.clone(),
+            variables,
+            redacted,
+        };
+
+        Self {
+            format: EXECUTION_BUNDLE_FORMAT.to_string(),
+            version: EXECUTION_BUNDLE_VERSION,
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: Utc::now(),
+            definition,
+            execution,
+            steps,
+            execution_graph,
+            redaction: policy.clone(),
+        }
+    }
+
+    /// Record of a step
+    pub fn step(&self, step_id: &str) -> Option<&StepRecord> {
+        self.steps.iter().find(|s| s.step_id == step_id)
+    }
+
+    /// Serialize the bundle as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse a bundle, rejecting other formats and newer versions
+    pub fn from_json(json: &str) -> Result<Self> {
+        let bundle: Self = serde_json::from_str(json)?;
+        if bundle.format != EXECUTION_BUNDLE_FORMAT {
+            return Err(WorkflowError::InvalidDefinition(format!(
+                "Not an execution bundle: {}",
+                bundle.format
+            )));
+        }
+        if bundle.version > EXECUTION_BUNDLE_VERSION {
+            return Err(WorkflowError::InvalidDefinition(format!(
+                "Unsupported execution bundle version {} (newest supported is {})",
+                bundle.version, EXECUTION_BUNDLE_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redaction_is_recursive_and_recorded() {
+        let policy = RedactionPolicy::default().redact_key("Customer");
+        let outputs = HashMap::from([
+            ("rows".to_string(), json!(3)),
+            ("customer".to_string(), json!("acme")),
+            (
+                "connection".to_string(),
+                json!({"host": "db", "auth": [{"password": "hunter2"}]}),
+            ),
+        ]);
+
+        let (values, redacted) = policy.apply(&outputs, false);
+
+        assert_eq!(values["rows"], json!(3));
+        assert_eq!(values["customer"], json!(REDACTED));
+        assert_eq!(values["connection"]["host"], json!("db"));
+        assert_eq!(values["connection"]["auth"][0]["password"], json!(REDACTED));
+        assert_eq!(redacted, ["connection.auth[0].password", "customer"]);
+
+        let (values, _) = RedactionPolicy::none().apply(&outputs, false);
+        assert_eq!(values["connection"]["auth"][0]["password"], json!("hunter2"));
+    }
+
+    #[test]
+    fn test_keys_match_by_suffix_ignoring_case() {
+        let policy = RedactionPolicy::default();
+        for key in ["db_password", "X-Api-Key", "Authorization", "GITHUB_TOKEN"] {
+            assert!(policy.redacts_key(key), "{} should be redacted", key);
+        }
+        for key in ["token_count", "Content-Type", "password_policy"] {
+            assert!(!policy.redacts_key(key), "{} should be kept", key);
+        }
+    }
+
+    #[test]
+    fn test_definition_actions_are_redacted() {
+        use crate::step::StepType;
+
+        let request = StepAction::HttpRequest {
+            method: "POST".to_string(),
+            url: "https://example.com/deploy".to_string(),
+            headers: HashMap::from([
+                ("Authorization".to_string(), "Bearer abc".to_string()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]),
+            body: None,
+        };
+        let command = StepAction::Command {
+            command: "migrate".to_string(),
+            args: vec![],
+            env: HashMap::from([("DB_PASSWORD".to_string(), "hunter2".to_string())]),
+        };
+        let undo = StepAction::Custom {
+            handler: "rollback".to_string(),
+            parameters: HashMap::from([("client_secret".to_string(), json!("s3cret"))]),
+        };
+        let definition = WorkflowDefinition::new("wf", "test")
+            .add_step(WorkflowStep::new("deploy", StepType::Action, request).with_id("deploy"))
+            .add_step(
+                WorkflowStep::new("migrate", StepType::Action, command)
+                    .with_id("migrate")
+                    .with_compensating_action(undo),
+            );
+        let state = WorkflowState::new(&definition.id, "exec");
+        let order = ["deploy".to_string(), "migrate".to_string()];
+        let bundle = ExecutionBundle::new(
+            &definition,
+            &order,
+            &state,
+            &HashMap::new(),
+            None,
+            &RedactionPolicy::default(),
+        );
+
+        let json = bundle.to_json().unwrap();
+        for secret in ["Bearer abc", "hunter2", "s3cret"] {
+            assert!(!json.contains(secret), "{} leaked", secret);
+        }
+        assert!(json.contains("application/json"));
+        assert_eq!(bundle.step("deploy").unwrap().redacted, ["action.headers.Authorization"]);
+        assert_eq!(
+            bundle.step("migrate").unwrap().redacted,
+            ["action.env.DB_PASSWORD", "compensating_action.parameters.client_secret"]
+        );
+    }
+
+    #[test]
+    fn test_subworkflow_inputs_are_redacted() {
+        use crate::step::StepType;
+
+        let child = StepAction::Subworkflow {
+            workflow_id: "rotate-keys".to_string(),
+            inputs: HashMap::from([
+                ("api_key".to_string(), json!("sk-live-123")),
+                ("region".to_string(), json!("eu-west-1")),
+            ]),
+        };
+        let definition = WorkflowDefinition::new("wf", "test")
+            .add_step(WorkflowStep::new("rotate", StepType::Subworkflow, child).with_id("rotate"));
+        let state = WorkflowState::new(&definition.id, "exec");
+        let bundle = ExecutionBundle::new(
+            &definition,
+            &["rotate".to_string()],
+            &state,
+            &HashMap::new(),
+            None,
+            &RedactionPolicy::default(),
+        );
+
+        let json = bundle.to_json().unwrap();
+        assert!(!json.contains("sk-live-123"));
+        assert!(json.contains("eu-west-1"));
+        assert_eq!(bundle.step("rotate").unwrap().redacted, ["action.inputs.api_key"]);
+    }
+
+    #[test]
+    fn test_variable_redactions_are_recorded() {
+        let definition = WorkflowDefinition::new("wf", "test");
+        let state = WorkflowState::new(&definition.id, "exec");
+        let variables = HashMap::from([
+            ("region".to_string(), json!("eu-west-1")),
+            ("deploy".to_string(), json!({"github_token": "ghp_123"})),
+        ]);
+        let bundle = ExecutionBundle::new(
+            &definition,
+            &[],
+            &state,
+            &variables,
+            None,
+            &RedactionPolicy::default(),
+        );
+
+        assert_eq!(bundle.execution.variables["region"], json!("eu-west-1"));
+        assert_eq!(bundle.execution.redacted, ["deploy.github_token"]);
+        assert!(!bundle.to_json().unwrap().contains("ghp_123"));
+    }
+
+    #[test]
+    fn test_newer_versions_are_rejected() {
+        let definition = WorkflowDefinition::new("wf", "test");
+        let state = WorkflowState::new(&definition.id, "exec");
+        let mut bundle = ExecutionBundle::new(
+            &definition,
+            &[],
+            &state,
+            &HashMap::new(),
+            None,
+            &RedactionPolicy::default(),
+        );
+
+        let parsed = ExecutionBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.version, EXECUTION_BUNDLE_VERSION);
+
+        bundle.version = EXECUTION_BUNDLE_VERSION + 1;
+        assert!(ExecutionBundle::from_json(&bundle.to_json().unwrap()).is_err());
+    }
+}
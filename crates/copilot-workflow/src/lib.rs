@@ -3,30 +3,51 @@
 //! This crate provides a comprehensive workflow execution engine with:
 //! - DAG-based workflow definition and validation
 //! - Parallel and sequential step execution
+//! - Priority-based step admission under a concurrency limit
+//! - Adaptive concurrency driven by downstream latency
 //! - Approval gates with timeout handling
+//! - Condition steps branching on execution variables
+//! - Registered workflows embedded as subworkflow steps
+//! - Steps suspended until an external event is signalled
 //! - State management and persistence
 //! - Retry logic with exponential backoff
+//! - Workflow deadlines propagated to step timeouts
 //! - Real-time workflow status tracking
+//! - Execution export for audit and reproduction
+//! - Opt-in JSON Schema validation of step outputs
 //! - Workflow versioning and rollback
 //! - Scheduled workflow execution
 //! - Event-driven workflow triggers
 //! - Workflow templates library
 
+pub mod adaptive;
 pub mod approval;
+pub mod condition;
 pub mod dag;
+pub mod deadline;
 pub mod engine;
 pub mod execution;
+pub mod export;
+pub mod mock;
+pub mod priority;
+pub mod schema;
 pub mod step;
 pub mod versioning;
 pub mod scheduling;
 pub mod triggers;
 pub mod templates;
 
+pub use adaptive::{AdaptiveConcurrency, AdaptiveConfig};
 pub use approval::{ApprovalGate, ApprovalRequest, ApprovalStatus};
 pub use dag::{WorkflowDag, DagValidationError};
+pub use deadline::{Deadline, TimeoutCause};
 pub use engine::{WorkflowEngine, WorkflowDefinition, WorkflowStatus, WorkflowState};
 pub use execution::{ExecutionContext, StepExecutor, RetryConfig};
-pub use step::{WorkflowStep, StepType, StepState, StepResult, StepAction};
+pub use export::{ExecutionBundle, ExecutionRecord, RedactionPolicy, StepRecord};
+pub use mock::{MockStepExecutor, ScriptedOutcome};
+pub use priority::{Priority, SchedulerConfig, StepPermit, StepScheduler};
+pub use schema::SchemaViolation;
+pub use step::{WorkflowStep, StepType, StepState, StepResult, StepAction, HeartbeatConfig};
 pub use versioning::{WorkflowVersion, VersionManager, VersionBump, VersionRepository};
 pub use scheduling::{Schedule, ScheduledWorkflow, WorkflowScheduler, ScheduleRepository};
 pub use triggers::{TriggerEvent, TriggerCondition, WorkflowTrigger, TriggerManager, EventBus, EventSource};
@@ -57,6 +78,9 @@ pub enum WorkflowError {
     #[error("Workflow not running: {0}")]
     NotRunning(String),
 
+    #[error("No step waits for event: {0}")]
+    UnexpectedEvent(String),
+
     #[error("Approval timeout: {0}")]
     ApprovalTimeout(String),
 
@@ -0,0 +1,205 @@
+//! Scripted step executor for tests
+//!
+//! [`MockStepExecutor`] implements [`StepExecutor`] with outcomes scripted
+//! per step ID, so workflow tests can drive the engine through success,
+//! failure and delay paths deterministically. Production code keeps using
+//! [`DefaultStepExecutor`](crate::execution::DefaultStepExecutor); tests
+//! swap the mock in with [`WorkflowEngine::with_executor`].
+//!
+//! [`WorkflowEngine::with_executor`]: crate::engine::WorkflowEngine::with_executor
+
+use crate::execution::{ExecutionContext, StepExecutor};
+use crate::step::{StepResult, WorkflowStep};
+use crate::{Result, WorkflowError};
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Scripted behavior of one step execution
+#[derive(Debug, Clone)]
+pub enum ScriptedOutcome {
+    /// Complete with the given outputs
+    Succeed(HashMap<String, serde_json::Value>),
+    /// Fail with the given error message
+    Fail(String),
+    /// Skip the step
+    Skip,
+    /// Return an executor error rather than a failed result
+    Error(String),
+    /// Wait, then behave as the inner outcome
+    Delayed(Duration, Box<ScriptedOutcome>),
+}
+
+impl ScriptedOutcome {
+    /// Complete without outputs
+    pub fn success() -> Self {
+        Self::Succeed(HashMap::new())
+    }
+
+    /// Fail with a message
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self::Fail(message.into())
+    }
+
+    /// Delay this outcome
+    pub fn after(self, delay: Duration) -> Self {
+        Self::Delayed(delay, Box::new(self))
+    }
+}
+
+/// Step executor whose outcomes are scripted per step ID
+///
+/// Each execution of a step consumes the next scripted outcome; the last
+/// one is repeated for further executions, e.g. retries or restarts. Steps
+/// without a script use the default outcome, which succeeds without
+/// outputs unless changed. Every execution is recorded in order.
+#[derive(Debug)]
+pub struct MockStepExecutor {
+    scripts: Mutex<HashMap<String, VecDeque<ScriptedOutcome>>>,
+    default_outcome: ScriptedOutcome,
+    calls: Mutex<Vec<String>>,
+}
+
+impl Default for MockStepExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockStepExecutor {
+    /// Create a mock where every step succeeds
+    pub fn new() -> Self {
+        Self {
+            scripts: Mutex::new(HashMap::new()),
+            default_outcome: ScriptedOutcome::success(),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the outcome of steps without a script
+    pub fn with_default(mut self, outcome: ScriptedOutcome) -> Self {
+        self.default_outcome = outcome;
+        self
+    }
+
+    /// Append an outcome to a step's script
+    pub fn script(self, step_id: impl Into<String>, outcome: ScriptedOutcome) -> Self {
+        self.scripts
+            .lock()
+            .unwrap()
+            .entry(step_id.into())
+            .or_default()
+            .push_back(outcome);
+        self
+    }
+
+    /// Script a step to succeed with outputs
+    pub fn succeed(
+        self,
+        step_id: impl Into<String>,
+        outputs: HashMap<String, serde_json::Value>,
+    ) -> Self {
+        self.script(step_id, ScriptedOutcome::Succeed(outputs))
+    }
+
+    /// Script a step to fail
+    pub fn fail(self, step_id: impl Into<String>, message: impl Into<String>) -> Self {
+        self.script(step_id, ScriptedOutcome::failure(message))
+    }
+
+    /// Step IDs in the order they were executed
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Number of times a step was executed
+    pub fn call_count(&self, step_id: &str) -> usize {
+        self.calls.lock().unwrap().iter().filter(|id| *id == step_id).count()
+    }
+
+    fn next_outcome(&self, step_id: &str) -> ScriptedOutcome {
+        let mut scripts = self.scripts.lock().unwrap();
+        match scripts.get_mut(step_id) {
+            Some(script) if script.len() > 1 => script.pop_front().unwrap(),
+            Some(script) if !script.is_empty() => script[0].clone(),
+            _ => self.default_outcome.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl StepExecutor for MockStepExecutor {
+    async fn execute_step(
+        &self,
+        step: &WorkflowStep,
+        context: &ExecutionContext,
+    ) -> Result<StepResult> {
+        self.calls.lock().unwrap().push(step.id.clone());
+
+        let mut outcome = self.next_outcome(&step.id);
+        while let ScriptedOutcome::Delayed(delay, inner) = outcome {
+            tokio::time::sleep(delay).await;
+            outcome = *inner;
+        }
+
+        let result = StepResult::pending(step.id.clone());
+        match outcome {
+            ScriptedOutcome::Succeed(outputs) => {
+                context.set_step_outputs(&step.id, outputs.clone()).await;
+                Ok(result.complete(outputs))
+            }
+            ScriptedOutcome::Fail(message) => Ok(result.fail(message)),
+            ScriptedOutcome::Skip => Ok(result.skip()),
+            ScriptedOutcome::Error(reason) => Err(WorkflowError::StepExecutionFailed {
+                step_id: step.id.clone(),
+                reason,
+            }),
+            ScriptedOutcome::Delayed(..) => unreachable!("delays are unwrapped above"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step::{StepAction, StepState, StepType};
+
+    fn step(id: &str) -> WorkflowStep {
+        WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 }).with_id(id)
+    }
+
+    #[tokio::test]
+    async fn test_script_is_consumed_and_last_outcome_repeats() {
+        let mock = MockStepExecutor::new()
+            .fail("a", "first attempt")
+            .script("a", ScriptedOutcome::success());
+        let context = ExecutionContext::new("wf", "exec");
+
+        let first = mock.execute_step(&step("a"), &context).await.unwrap();
+        let second = mock.execute_step(&step("a"), &context).await.unwrap();
+        let third = mock.execute_step(&step("a"), &context).await.unwrap();
+
+        assert_eq!(first.state, StepState::Failed);
+        assert_eq!(second.state, StepState::Completed);
+        assert_eq!(third.state, StepState::Completed);
+        assert_eq!(mock.call_count("a"), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delayed_outcome_and_outputs() {
+        let outputs = HashMap::from([("rows".to_string(), serde_json::json!(3))]);
+        let mock = MockStepExecutor::new().script(
+            "a",
+            ScriptedOutcome::Succeed(outputs.clone()).after(Duration::from_secs(30)),
+        );
+        let context = ExecutionContext::new("wf", "exec");
+
+        let started = tokio::time::Instant::now();
+        let result = mock.execute_step(&step("a"), &context).await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_secs(30));
+        assert_eq!(result.outputs, outputs);
+        assert_eq!(context.get_step_outputs("a").await, Some(outputs));
+    }
+}
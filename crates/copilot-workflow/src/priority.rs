@@ -0,0 +1,408 @@
+//! Priority-based step admission
+//!
+//! Bounds how many workflow steps run at once across all executions and
+//! admits waiting steps by priority. Waiting steps age: their effective
+//! priority rises the longer they wait, so routine work is not starved by
+//! a steady stream of urgent workflows. Ties are admitted in submission
+//! order.
+//!
+//! The limit is either fixed or, with [`AdaptiveConfig`], adjusted from the
+//! latencies and outcomes of completed steps.
+
+use crate::adaptive::{AdaptiveConcurrency, AdaptiveConfig};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+/// Priority of a workflow execution; higher values are admitted first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Priority(pub u32);
+
+impl Priority {
+    /// Background work
+    pub const LOW: Priority = Priority(0);
+    /// Routine work
+    pub const NORMAL: Priority = Priority(50);
+    /// Work that should run ahead of routine work
+    pub const HIGH: Priority = Priority(100);
+    /// Incident response
+    pub const CRITICAL: Priority = Priority(200);
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::NORMAL
+    }
+}
+
+impl From<u32> for Priority {
+    fn from(value: u32) -> Self {
+        Priority(value)
+    }
+}
+
+/// Configuration for the step scheduler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// Maximum number of steps running at once across all executions
+    pub max_concurrent_steps: usize,
+    /// Waiting time after which a step's priority is raised by one
+    pub aging_interval_ms: u64,
+    /// Adjust the limit from step latencies; overrides `max_concurrent_steps`
+    #[serde(default)]
+    pub adaptive: Option<AdaptiveConfig>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_steps: usize::MAX,
+            aging_interval_ms: 1000,
+            adaptive: None,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Create a configuration with a concurrency limit
+    pub fn new(max_concurrent_steps: usize) -> Self {
+        Self {
+            max_concurrent_steps,
+            ..Default::default()
+        }
+    }
+
+    /// Set the aging interval
+    pub fn with_aging_interval(mut self, interval: Duration) -> Self {
+        self.aging_interval_ms = interval.as_millis() as u64;
+        self
+    }
+
+    /// Create a configuration whose limit adapts to downstream latency
+    pub fn adaptive(config: AdaptiveConfig) -> Self {
+        Self {
+            adaptive: Some(config),
+            ..Default::default()
+        }
+    }
+}
+
+struct Waiter {
+    priority: Priority,
+    ticket: u64,
+    enqueued_at: Instant,
+    sender: oneshot::Sender<StepPermit>,
+}
+
+struct SchedulerState {
+    limit: usize,
+    running: usize,
+    waiters: Vec<Waiter>,
+    next_ticket: u64,
+}
+
+/// Admits workflow steps by priority under a concurrency limit
+pub struct StepScheduler {
+    config: SchedulerConfig,
+    state: Mutex<SchedulerState>,
+    adaptive: Option<Mutex<AdaptiveConcurrency>>,
+}
+
+impl StepScheduler {
+    /// Create a scheduler
+    pub fn new(config: SchedulerConfig) -> Arc<Self> {
+        let adaptive = config.adaptive.clone().map(AdaptiveConcurrency::new);
+        let limit = adaptive
+            .as_ref()
+            .map_or(config.max_concurrent_steps, AdaptiveConcurrency::limit);
+        Arc::new(Self {
+            state: Mutex::new(SchedulerState {
+                limit,
+                running: 0,
+                waiters: Vec::new(),
+                next_ticket: 0,
+            }),
+            adaptive: adaptive.map(Mutex::new),
+            config,
+        })
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// Issue a submission ticket; lower tickets win priority ties
+    pub fn ticket(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        ticket
+    }
+
+    /// Number of steps waiting for admission
+    pub fn waiting(&self) -> usize {
+        self.state.lock().unwrap().waiters.len()
+    }
+
+    /// Current concurrency limit
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Change the concurrency limit
+    ///
+    /// Raising it admits waiting steps right away. Lowering it never
+    /// interrupts running steps; freed slots are withheld until the
+    /// running count is under the new limit.
+    pub fn set_limit(self: &Arc<Self>, limit: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.limit = limit;
+        self.admit_waiters(&mut state);
+    }
+
+    /// Record a finished step's latency and outcome
+    ///
+    /// With adaptive concurrency enabled this may adjust the limit;
+    /// otherwise it does nothing.
+    pub fn record(self: &Arc<Self>, latency: Duration, success: bool) {
+        let Some(adaptive) = &self.adaptive else {
+            return;
+        };
+        let changed = adaptive.lock().unwrap().record(latency, success);
+        if let Some(limit) = changed {
+            self.set_limit(limit);
+        }
+    }
+
+    /// Wait for admission
+    ///
+    /// The step holds its slot until the returned permit is dropped.
+    pub async fn acquire(self: &Arc<Self>, priority: Priority, ticket: u64) -> StepPermit {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.running < state.limit && state.waiters.is_empty() {
+                state.running += 1;
+                return StepPermit::new(self);
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            state.waiters.push(Waiter {
+                priority,
+                ticket,
+                enqueued_at: Instant::now(),
+                sender,
+            });
+            receiver
+        };
+
+        // The scheduler outlives its waiters, so the sender is never dropped
+        // without sending
+        receiver.await.expect("step scheduler dropped a waiter")
+    }
+
+    /// Effective priority of a waiter, including aging
+    fn effective_priority(&self, waiter: &Waiter, now: Instant) -> u64 {
+        let waited = now.saturating_duration_since(waiter.enqueued_at).as_millis() as u64;
+        let boost = waited.checked_div(self.config.aging_interval_ms).unwrap_or(0);
+        waiter.priority.0 as u64 + boost
+    }
+
+    /// Return a finished step's slot and admit waiters into free slots
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        state.running -= 1;
+        self.admit_waiters(&mut state);
+    }
+
+    /// Hand free slots to the best waiters
+    fn admit_waiters(self: &Arc<Self>, state: &mut SchedulerState) {
+        let now = Instant::now();
+
+        while state.running < state.limit && !state.waiters.is_empty() {
+            let best = state
+                .waiters
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| {
+                    self.effective_priority(a, now)
+                        .cmp(&self.effective_priority(b, now))
+                        .then_with(|| b.ticket.cmp(&a.ticket))
+                        .then_with(|| b.enqueued_at.cmp(&a.enqueued_at))
+                })
+                .map(|(idx, _)| idx)
+                .unwrap_or_default();
+
+            let waiter = state.waiters.remove(best);
+            match waiter.sender.send(StepPermit::new(self)) {
+                Ok(()) => state.running += 1,
+                // The waiting step was cancelled; try the next one
+                Err(mut permit) => permit.disarm(),
+            }
+        }
+    }
+}
+
+/// A step's slot in the scheduler, released on drop
+pub struct StepPermit {
+    scheduler: Option<Arc<StepScheduler>>,
+}
+
+impl StepPermit {
+    fn new(scheduler: &Arc<StepScheduler>) -> Self {
+        Self {
+            scheduler: Some(Arc::clone(scheduler)),
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.scheduler = None;
+    }
+}
+
+impl Drop for StepPermit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Queue one waiter per (priority, ticket) behind a held slot and
+    /// return the order in which they are admitted
+    async fn admission_order(
+        scheduler: &Arc<StepScheduler>,
+        waiters: &[(Priority, u64)],
+        stagger: Duration,
+    ) -> Vec<u64> {
+        let held = scheduler.acquire(Priority::NORMAL, u64::MAX).await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for &(priority, ticket) in waiters {
+            let waiter = Arc::clone(scheduler);
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _permit = waiter.acquire(priority, ticket).await;
+                tx.send(ticket).unwrap();
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            });
+            tokio::time::sleep(stagger).await;
+        }
+        while scheduler.waiting() < waiters.len() {
+            tokio::task::yield_now().await;
+        }
+
+        drop(held);
+        let mut order = Vec::new();
+        for _ in waiters {
+            order.push(rx.recv().await.unwrap());
+        }
+        order
+    }
+
+    #[tokio::test]
+    async fn test_higher_priority_admitted_first() {
+        let scheduler = StepScheduler::new(SchedulerConfig::new(1));
+        let order = admission_order(
+            &scheduler,
+            &[(Priority::LOW, 0), (Priority::CRITICAL, 1), (Priority::NORMAL, 2)],
+            Duration::ZERO,
+        )
+        .await;
+        assert_eq!(order, vec![1, 2, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_ties_admitted_in_submission_order() {
+        let scheduler = StepScheduler::new(SchedulerConfig::new(1));
+        let order = admission_order(
+            &scheduler,
+            &[(Priority::HIGH, 7), (Priority::HIGH, 3), (Priority::HIGH, 5)],
+            Duration::ZERO,
+        )
+        .await;
+        assert_eq!(order, vec![3, 5, 7]);
+    }
+
+    #[tokio::test]
+    async fn test_aging_prevents_starvation() {
+        let scheduler = StepScheduler::new(
+            SchedulerConfig::new(1).with_aging_interval(Duration::from_millis(1)),
+        );
+        // The low priority waiter has waited long enough to outrank the
+        // newer, slightly higher priority one
+        let order = admission_order(
+            &scheduler,
+            &[(Priority(0), 0), (Priority(5), 1)],
+            Duration::from_millis(50),
+        )
+        .await;
+        assert_eq!(order, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_set_limit_admits_and_withholds() {
+        let scheduler = StepScheduler::new(SchedulerConfig::new(1));
+        let first = scheduler.acquire(Priority::NORMAL, 0).await;
+
+        let waiter = {
+            let scheduler = Arc::clone(&scheduler);
+            tokio::spawn(async move { scheduler.acquire(Priority::NORMAL, 1).await })
+        };
+        while scheduler.waiting() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Raising the limit admits the waiter while the first step runs
+        scheduler.set_limit(2);
+        let second = waiter.await.unwrap();
+
+        // Lowering it keeps both running but withholds the next free slot
+        scheduler.set_limit(1);
+        drop(first);
+        let third = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire(Priority::NORMAL, 2),
+        )
+        .await;
+        assert!(third.is_err());
+
+        drop(second);
+        let third = tokio::time::timeout(
+            Duration::from_secs(1),
+            scheduler.acquire(Priority::NORMAL, 3),
+        )
+        .await;
+        assert!(third.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_does_not_leak_slot() {
+        let scheduler = StepScheduler::new(SchedulerConfig::new(1));
+        let held = scheduler.acquire(Priority::NORMAL, 0).await;
+
+        let waiter = {
+            let scheduler = Arc::clone(&scheduler);
+            tokio::spawn(async move { scheduler.acquire(Priority::HIGH, 1).await })
+        };
+        while scheduler.waiting() == 0 {
+            tokio::task::yield_now().await;
+        }
+        waiter.abort();
+        let _ = waiter.await;
+        drop(held);
+
+        let permit = tokio::time::timeout(
+            Duration::from_secs(1),
+            scheduler.acquire(Priority::LOW, 2),
+        )
+        .await;
+        assert!(permit.is_ok());
+    }
+}
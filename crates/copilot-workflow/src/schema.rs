@@ -0,0 +1,372 @@
+//! Step output schemas
+//!
+//! Step outputs feed the templates of downstream steps, so a step that
+//! returns the wrong shape breaks a later step at dispatch time, far from
+//! the cause. A step may declare a JSON Schema for its outputs with
+//! [`WorkflowStep::with_output_schema`]; the engine validates the outputs
+//! of a completed step against it and fails the step on a mismatch,
+//! naming the offending field.
+//!
+//! The validator supports the subset of JSON Schema that describes step
+//! outputs: `type`, `enum`, `const`, `properties`, `required`,
+//! `additionalProperties`, `items`, `minItems`, `maxItems`, `minLength`,
+//! `maxLength`, `pattern`, `minimum` and `maximum`, plus the annotations
+//! `title`, `description`, `default`, `examples`, `$schema`, `$id` and
+//! `$comment`. A schema using any other keyword (`oneOf`, `format`,
+//! `$ref`, ...) is rejected by [`check_schema`] when the workflow is
+//! validated, rather than having the keyword silently ignored and invalid
+//! outputs pass.
+//!
+//! [`WorkflowStep::with_output_schema`]: crate::step::WorkflowStep::with_output_schema
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where and how a value violates a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value, e.g. `/rows/0/count`; empty
+    /// for the root
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+/// Keywords the validator enforces
+const SUPPORTED_KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minimum",
+    "maximum",
+];
+
+/// Keywords that only annotate a schema and constrain nothing
+const ANNOTATION_KEYWORDS: &[&str] = &[
+    "title",
+    "description",
+    "default",
+    "examples",
+    "$schema",
+    "$id",
+    "$comment",
+];
+
+/// Check that a schema only uses keywords the validator enforces
+///
+/// Also rejects `pattern`s that are not valid regular expressions and the
+/// array form of `items`, so every constraint a schema states is one
+/// [`validate`] checks.
+pub fn check_schema(schema: &Value) -> Result<(), SchemaViolation> {
+    check_schema_at(schema, &mut String::new())
+}
+
+fn check_schema_at(schema: &Value, path: &mut String) -> Result<(), SchemaViolation> {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        Value::Bool(_) => return Ok(()),
+        other => {
+            let found = type_name(other);
+            return Err(violation(path, format!("schema must be an object or boolean, found {}", found)));
+        }
+    };
+
+    for (keyword, value) in schema {
+        if ANNOTATION_KEYWORDS.contains(&keyword.as_str()) {
+            continue;
+        }
+        if !SUPPORTED_KEYWORDS.contains(&keyword.as_str()) {
+            return Err(violation(path, format!("unsupported keyword \"{}\"", keyword)));
+        }
+
+        let len = path.len();
+        push_segment(path, keyword);
+        match (keyword.as_str(), value) {
+            ("properties", Value::Object(properties)) => {
+                for (name, property) in properties {
+                    let len = path.len();
+                    push_segment(path, name);
+                    check_schema_at(property, path)?;
+                    path.truncate(len);
+                }
+            }
+            ("additionalProperties", additional) => check_schema_at(additional, path)?,
+            ("items", Value::Array(_)) => {
+                return Err(violation(path, "tuple \"items\" are not supported"));
+            }
+            ("items", items) => check_schema_at(items, path)?,
+            ("pattern", Value::String(pattern)) => {
+                if let Err(e) = Regex::new(pattern) {
+                    return Err(violation(path, format!("invalid pattern {:?}: {}", pattern, e)));
+                }
+            }
+            _ => {}
+        }
+        path.truncate(len);
+    }
+    Ok(())
+}
+
+/// Validate step outputs against a schema
+pub fn validate_outputs(
+    schema: &Value,
+    outputs: &HashMap<String, Value>,
+) -> Result<(), SchemaViolation> {
+    let outputs = Value::Object(outputs.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+    validate(schema, &outputs)
+}
+
+/// Validate a value against a schema
+pub fn validate(schema: &Value, value: &Value) -> Result<(), SchemaViolation> {
+    validate_at(schema, value, &mut String::new())
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &mut String) -> Result<(), SchemaViolation> {
+    let Some(schema) = schema.as_object() else {
+        // `true` and `{}`-like schemas accept anything; `false` nothing
+        return match schema {
+            Value::Bool(false) => Err(violation(path, "no value is allowed here")),
+            _ => Ok(()),
+        };
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+            return Err(violation(
+                path,
+                format!("expected {}, found {}", allowed.join(" or "), type_name(value)),
+            ));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(violation(path, format!("{} is not one of {}", value, Value::from(options.clone()))));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(violation(path, format!("expected {}, found {}", expected, value)));
+        }
+    }
+
+    match value {
+        Value::Object(fields) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                if let Some(missing) = required
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|name| !fields.contains_key(*name))
+                {
+                    return Err(violation(path, format!("missing required field \"{}\"", missing)));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in fields {
+                let field_schema = match properties.and_then(|p| p.get(name)) {
+                    Some(field_schema) => field_schema,
+                    None => match schema.get("additionalProperties") {
+                        Some(additional) => additional,
+                        None => continue,
+                    },
+                };
+                let len = path.len();
+                push_segment(path, name);
+                validate_at(field_schema, field, path)?;
+                path.truncate(len);
+            }
+        }
+        Value::Array(items) => {
+            check_bound(schema, "minItems", items.len(), path, |n, min| n >= min, "at least")?;
+            check_bound(schema, "maxItems", items.len(), path, |n, max| n <= max, "at most")?;
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    let len = path.len();
+                    push_segment(path, &index.to_string());
+                    validate_at(item_schema, item, path)?;
+                    path.truncate(len);
+                }
+            }
+        }
+        Value::String(s) => {
+            let chars = s.chars().count();
+            check_bound(schema, "minLength", chars, path, |n, min| n >= min, "at least")?;
+            check_bound(schema, "maxLength", chars, path, |n, max| n <= max, "at most")?;
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                let regex = Regex::new(pattern)
+                    .map_err(|e| violation(path, format!("invalid pattern {:?}: {}", pattern, e)))?;
+                if !regex.is_match(s) {
+                    return Err(violation(path, format!("{:?} does not match {:?}", s, pattern)));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or(f64::NAN);
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    return Err(violation(path, format!("{} is less than the minimum {}", n, min)));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    return Err(violation(path, format!("{} is greater than the maximum {}", n, max)));
+                }
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+
+    Ok(())
+}
+
+/// Check a `min*`/`max*` keyword against a length or count
+fn check_bound(
+    schema: &serde_json::Map<String, Value>,
+    keyword: &str,
+    actual: usize,
+    path: &str,
+    holds: impl Fn(usize, usize) -> bool,
+    relation: &str,
+) -> Result<(), SchemaViolation> {
+    match schema.get(keyword).and_then(Value::as_u64) {
+        Some(bound) if !holds(actual, bound as usize) => Err(violation(
+            path,
+            format!("length {} violates {} ({} {})", actual, keyword, relation, bound),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Append a JSON pointer segment, escaping `~` and `/`
+fn push_segment(path: &mut String, segment: &str) {
+    path.push('/');
+    path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+}
+
+fn violation(path: &str, message: impl Into<String>) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["status", "rows"],
+            "properties": {
+                "status": {"enum": ["ok", "partial"]},
+                "rows": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["host"],
+                        "properties": {
+                            "host": {"type": "string", "minLength": 1},
+                            "count": {"type": "integer", "minimum": 0}
+                        }
+                    }
+                }
+            },
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_unsupported_keywords_are_rejected() {
+        assert_eq!(check_schema(&schema()), Ok(()));
+        assert_eq!(
+            check_schema(&json!({"title": "Rows", "type": "array", "items": true})),
+            Ok(())
+        );
+
+        let check = |schema: Value| check_schema(&schema).unwrap_err().to_string();
+        assert_eq!(
+            check(json!({"oneOf": [{"type": "string"}, {"type": "integer"}]})),
+            "/: unsupported keyword \"oneOf\""
+        );
+        assert_eq!(
+            check(json!({"properties": {"host": {"type": "string", "format": "hostname"}}})),
+            "/properties/host: unsupported keyword \"format\""
+        );
+        assert_eq!(
+            check(json!({"items": {"additionalProperties": {"$ref": "#/defs/row"}}})),
+            "/items/additionalProperties: unsupported keyword \"$ref\""
+        );
+        assert_eq!(
+            check(json!({"items": [{"type": "string"}]})),
+            "/items: tuple \"items\" are not supported"
+        );
+        assert!(check(json!({"pattern": "("})).starts_with("/pattern: invalid pattern"));
+    }
+
+    #[test]
+    fn test_valid_value_passes() {
+        let value = json!({"status": "ok", "rows": [{"host": "db-1", "count": 3}]});
+        assert_eq!(validate(&schema(), &value), Ok(()));
+    }
+
+    #[test]
+    fn test_violations_name_the_offending_field() {
+        let check = |value: Value| validate(&schema(), &value).unwrap_err();
+
+        let err = check(json!({"status": "ok", "rows": [{"host": "db-1", "count": "3"}]}));
+        assert_eq!(err.path, "/rows/0/count");
+        assert_eq!(err.to_string(), "/rows/0/count: expected integer, found string");
+
+        let err = check(json!({"status": "ok"}));
+        assert_eq!(err.to_string(), "/: missing required field \"rows\"");
+
+        let err = check(json!({"status": "done", "rows": []}));
+        assert_eq!(err.path, "/status");
+
+        let err = check(json!({"status": "ok", "rows": [], "extra": 1}));
+        assert_eq!(err.path, "/extra");
+    }
+}
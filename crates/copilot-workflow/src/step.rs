@@ -1,5 +1,6 @@
 //! Workflow step definitions and state management
 
+use crate::deadline::TimeoutCause;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -18,6 +19,8 @@ pub enum StepType {
     Parallel,
     /// Wait/delay step
     Wait,
+    /// Run another workflow as a step
+    Subworkflow,
 }
 
 /// State of a workflow step
@@ -36,8 +39,12 @@ pub enum StepState {
     Skipped,
     /// Step is waiting for approval
     WaitingApproval,
+    /// Step is waiting for an external event
+    WaitingEvent,
     /// Step is paused
     Paused,
+    /// Step stopped reporting heartbeats and was abandoned
+    Stalled,
 }
 
 /// Action to be performed in a step
@@ -72,17 +79,47 @@ pub enum StepAction {
         parameters: HashMap<String, serde_json::Value>,
     },
     /// Conditional evaluation
+    ///
+    /// Exactly one branch runs: when the
+    /// [expression](crate::condition) is true the `false_steps` are
+    /// skipped, and otherwise the `true_steps`. Branch steps implicitly
+    /// depend on the condition. Steps that depend only on the skipped
+    /// branch are skipped too, and a step joining both branches runs once
+    /// the taken one completes.
     Condition {
         expression: String,
-        #[serde(default)]
+        #[serde(default, alias = "on_true")]
         true_steps: Vec<String>,
-        #[serde(default)]
+        #[serde(default, alias = "on_false")]
         false_steps: Vec<String>,
     },
     /// Wait for a duration
     Wait {
         duration_secs: u64,
     },
+    /// Wait for an external event signalled with
+    /// [`WorkflowEngine::signal`](crate::engine::WorkflowEngine::signal)
+    ///
+    /// The event's payload becomes the step's outputs: the fields of an
+    /// object payload, or any other payload under `payload`.
+    WaitForEvent {
+        event_key: String,
+        /// Seconds to wait before the step fails; unbounded if unset
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+    /// Run a workflow registered with
+    /// [`WorkflowEngine::create_workflow`](crate::engine::WorkflowEngine::create_workflow)
+    /// as a child execution
+    ///
+    /// The inputs seed the child's shared state. The step's outputs are
+    /// the child's `execution_id` and, under `steps`, the outputs of each
+    /// of its steps by step ID.
+    Subworkflow {
+        workflow_id: String,
+        #[serde(default)]
+        inputs: HashMap<String, serde_json::Value>,
+    },
     /// Custom action
     Custom {
         handler: String,
@@ -111,6 +148,9 @@ pub struct StepResult {
     /// Number of retry attempts
     #[serde(default)]
     pub retry_count: u32,
+    /// What limited the step, if it timed out
+    #[serde(default)]
+    pub timed_out: Option<TimeoutCause>,
 }
 
 impl StepResult {
@@ -124,6 +164,7 @@ impl StepResult {
             started_at: chrono::Utc::now(),
             completed_at: None,
             retry_count: 0,
+            timed_out: None,
         }
     }
 
@@ -143,6 +184,21 @@ impl StepResult {
         self
     }
 
+    /// Mark as failed by a timeout
+    pub fn time_out(mut self, cause: TimeoutCause, after: std::time::Duration) -> Self {
+        self = self.fail(format!("Step timed out after {}ms ({})", after.as_millis(), cause));
+        self.timed_out = Some(cause);
+        self
+    }
+
+    /// Mark as stalled
+    pub fn stall(mut self, reason: String) -> Self {
+        self.state = StepState::Stalled;
+        self.error = Some(reason);
+        self.completed_at = Some(chrono::Utc::now());
+        self
+    }
+
     /// Mark as skipped
     pub fn skip(mut self) -> Self {
         self.state = StepState::Skipped;
@@ -154,7 +210,7 @@ impl StepResult {
     pub fn is_terminal(&self) -> bool {
         matches!(
             self.state,
-            StepState::Completed | StepState::Failed | StepState::Skipped
+            StepState::Completed | StepState::Failed | StepState::Skipped | StepState::Stalled
         )
     }
 
@@ -164,6 +220,43 @@ impl StepResult {
     }
 }
 
+/// Liveness requirements for a long-running step
+///
+/// A step with a heartbeat must call [`ExecutionContext::heartbeat`] at least
+/// once per interval. A step that misses an interval is marked
+/// [`StepState::Stalled`] regardless of how much of its timeout remains.
+///
+/// [`ExecutionContext::heartbeat`]: crate::execution::ExecutionContext::heartbeat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatConfig {
+    /// Maximum time between heartbeats in milliseconds
+    pub interval_ms: u64,
+    /// Number of times a stalled step is restarted before it is marked stalled
+    #[serde(default)]
+    pub max_restarts: u32,
+}
+
+impl HeartbeatConfig {
+    /// Create a heartbeat requirement with the given interval
+    pub fn new(interval_ms: u64) -> Self {
+        Self {
+            interval_ms,
+            max_restarts: 0,
+        }
+    }
+
+    /// Restart a stalled step up to `max_restarts` times
+    pub fn with_max_restarts(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Heartbeat interval as a duration
+    pub fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.interval_ms.max(1))
+    }
+}
+
 /// Configuration for a workflow step
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowStep {
@@ -193,6 +286,15 @@ pub struct WorkflowStep {
     /// Metadata for the step
     #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Heartbeat requirement for stall detection
+    #[serde(default)]
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// JSON Schema the step's outputs must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    /// Action undoing the step, run if the workflow fails and rolls back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensating_action: Option<StepAction>,
 }
 
 fn default_max_retries() -> u32 {
@@ -217,6 +319,9 @@ impl WorkflowStep {
             max_retries: 3,
             fail_on_error: true,
             metadata: HashMap::new(),
+            heartbeat: None,
+            output_schema: None,
+            compensating_action: None,
         }
     }
 
@@ -257,6 +362,27 @@ impl WorkflowStep {
         self
     }
 
+    /// Require heartbeats from this step
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Validate the step's outputs against a JSON Schema on completion
+    ///
+    /// See [`schema`](crate::schema) for the supported keywords; a
+    /// workflow whose schemas use others fails validation.
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Undo the step with this action if the workflow rolls back
+    pub fn with_compensating_action(mut self, action: StepAction) -> Self {
+        self.compensating_action = Some(action);
+        self
+    }
+
     /// Add metadata
     pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
         self.metadata.insert(key.into(), value);
@@ -618,6 +618,7 @@ impl TemplateBuilders {
             steps: workflow_steps,
             metadata: HashMap::new(),
             timeout_secs: None,
+            rollback_on_failure: false,
         };
 
         let mut template = WorkflowTemplate::new(name, description, definition)
@@ -711,6 +712,7 @@ impl TemplateBuilders {
             steps,
             metadata: HashMap::new(),
             timeout_secs: None,
+            rollback_on_failure: false,
         };
 
         WorkflowTemplate::new(name, description, definition)
@@ -762,6 +764,7 @@ mod tests {
             .with_id("step-1")],
             metadata: HashMap::new(),
             timeout_secs: None,
+            rollback_on_failure: false,
         };
 
         WorkflowTemplate::new("Test Template", "A test template", definition)
@@ -499,6 +499,7 @@ mod tests {
             ).with_id("step-1")],
             metadata: Default::default(),
             timeout_secs: None,
+            rollback_on_failure: false,
         }
     }
 
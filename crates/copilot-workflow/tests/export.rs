@@ -0,0 +1,91 @@
+//! Exporting executed workflows as audit bundles
+
+use copilot_workflow::export::{EXECUTION_BUNDLE_VERSION, REDACTED};
+use copilot_workflow::{
+    ExecutionBundle, MockStepExecutor, RedactionPolicy, StepAction, StepState, StepType,
+    WorkflowDefinition, WorkflowEngine, WorkflowStatus, WorkflowStep,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn step(id: &str) -> WorkflowStep {
+    WorkflowStep::new(id, StepType::Action, StepAction::Wait { duration_secs: 0 }).with_id(id)
+}
+
+#[tokio::test]
+async fn test_export_contains_all_steps_with_statuses() {
+    let mock = MockStepExecutor::new()
+        .succeed(
+            "fetch",
+            HashMap::from([
+                ("rows".to_string(), json!(42)),
+                ("api_key".to_string(), json!("sk-live-123")),
+            ]),
+        )
+        .succeed(
+            "enrich",
+            HashMap::from([("customer".to_string(), json!("acme"))]),
+        )
+        .fail("notify", "smtp unavailable");
+    let engine = WorkflowEngine::with_executor(Arc::new(mock))
+        .with_redaction_policy(RedactionPolicy::default().redact_step("enrich"));
+
+    let definition = WorkflowDefinition::new("nightly-report", "Build and send the report")
+        .add_step(step("fetch"))
+        .add_step(step("enrich").with_dependency("fetch"))
+        .add_step(step("notify").with_dependency("enrich").with_fail_on_error(false))
+        .add_step(step("archive").with_dependency("notify"));
+
+    let execution_id = engine.execute_workflow(definition).await.unwrap();
+    let state = engine.wait_for_completion(&execution_id).await.unwrap();
+    assert_eq!(state.status, WorkflowStatus::Completed);
+
+    let bundle = engine.export_execution(&execution_id).await.unwrap();
+
+    assert_eq!(bundle.version, EXECUTION_BUNDLE_VERSION);
+    assert_eq!(bundle.execution.execution_id, execution_id);
+    assert_eq!(bundle.execution.status, WorkflowStatus::Completed);
+    assert!(bundle.execution.duration_ms.is_some());
+    assert_eq!(bundle.definition.steps.len(), 4);
+
+    let statuses: Vec<_> = bundle
+        .steps
+        .iter()
+        .map(|s| (s.step_id.as_str(), s.state.clone()))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("fetch", StepState::Completed),
+            ("enrich", StepState::Completed),
+            ("notify", StepState::Failed),
+            ("archive", StepState::Skipped),
+        ]
+    );
+
+    let fetch = bundle.step("fetch").unwrap();
+    assert_eq!(fetch.outputs["rows"], json!(42));
+    assert_eq!(fetch.outputs["api_key"], json!(REDACTED));
+    assert_eq!(fetch.redacted, ["api_key"]);
+    assert!(fetch.duration_ms.is_some());
+
+    let enrich = bundle.step("enrich").unwrap();
+    assert_eq!(enrich.outputs["customer"], json!(REDACTED));
+    assert_eq!(enrich.dependencies, ["fetch"]);
+
+    let notify = bundle.step("notify").unwrap();
+    assert_eq!(notify.error.as_deref(), Some("smtp unavailable"));
+
+    // The bundle round-trips and carries no secrets
+    let json = bundle.to_json().unwrap();
+    assert!(!json.contains("sk-live-123"));
+    let parsed = ExecutionBundle::from_json(&json).unwrap();
+    assert_eq!(parsed.steps.len(), 4);
+}
+
+#[tokio::test]
+async fn test_export_unknown_execution() {
+    let engine = WorkflowEngine::with_executor(Arc::new(MockStepExecutor::new()));
+    assert!(engine.export_execution("missing").await.is_err());
+}